@@ -0,0 +1,1271 @@
+//! The compiler is the last step of the compilation process which translates an AST to Lua code.
+//!
+//! The compiler module handles the compilation of a list of [`ComplexToken`] ([`Expression`]) into a Lua code.
+//! It exposes the [`Compiler`] struct which is used to compile to Lua.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::iter::{Iterator, Peekable};
+
+use crate::emitter::CodeEmitter;
+use crate::helpers::Helper;
+use crate::target::{codegen_target, CodegenTarget, Declaration};
+use crate::traceback::TRACEBACK_MARKER;
+use clue_frontend::env::LuaVersion;
+use clue_frontend::{
+	env::{BitwiseMode, ContinueMode, Options},
+	format_clue,
+	parser::{
+		CodeBlock, ComplexToken, ComplexToken::*, Expression, FunctionArgs, FunctionAttributes,
+	},
+	scanner::TokenType::*,
+};
+
+/// The Compiler struct is used to compile a list of [`ComplexToken`] ([`Expression`]) into a lua code.
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::compiler::*;
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let filename = String::from("file.clue");
+///     let compiler = Compiler::new(&options, &filename);
+///     let code = "local fn a() {return 1;}".to_owned();
+///
+///     let (ctokens, _) = parse_source(code, &filename, &options)?;
+///     let output = compiler.compile_tokens(0, ctokens)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Compiler<'a> {
+	options: &'a Options,
+	filename: &'a String,
+	helpers: RefCell<BTreeSet<Helper>>,
+}
+
+impl<'a> Compiler<'a> {
+	/// Creates a new [`Compiler`] instance.
+	/// # Example
+	/// ```rust
+	/// use clue_backend_lua::compiler::Compiler;
+	/// use clue_frontend::env::Options;
+	///
+	/// let options = Options::default();
+	/// let compiler = Compiler::new(&options, &String::from("file.clue"));
+	/// ```
+	pub const fn new(options: &'a Options, filename: &'a String) -> Self {
+		Self {
+			options,
+			filename,
+			helpers: RefCell::new(BTreeSet::new()),
+		}
+	}
+
+	/// Returns the [`Helper`]s called by the code compiled so far,
+	/// which must be defined with [`compile_helpers`](crate::helpers::compile_helpers)
+	pub fn helpers(&self) -> BTreeSet<Helper> {
+		self.helpers.borrow().clone()
+	}
+
+	// the name a global is declared with, through the `_ENV` local with the global_table option
+	fn global_name(&self, name: &str) -> String {
+		match self.options.env_global_table {
+			Some(_) => format_clue!(self.helper(Helper::GlobalTable), ".", name),
+			None => name.to_owned(),
+		}
+	}
+
+	// what is written differently for the language chosen with the language option
+	fn target(&self) -> &'static dyn CodegenTarget {
+		codegen_target(self.options.env_language)
+	}
+
+	// remembers that the code calls the helper and returns its name
+	fn helper(&self, helper: Helper) -> String {
+		self.helpers.borrow_mut().insert(helper);
+		helper.name(self.options)
+	}
+
+	fn indentate(&self, scope: usize) -> String {
+		let mut result = String::with_capacity(128);
+		for _ in 0..scope {
+			result += "\t";
+		}
+		result
+	}
+
+	fn indentate_if<T: Iterator>(&self, ctokens: &mut Peekable<T>, scope: usize) -> String {
+		match ctokens.peek() {
+			Some(_) => {
+				format!("\n{}", self.indentate(scope))
+			}
+			None => String::with_capacity(4),
+		}
+	}
+
+	fn compile_list<T>(
+		&self,
+		list: Vec<T>,
+		separator: &str,
+		tostring: &mut impl FnMut(T) -> Result<String, String>,
+	) -> Result<String, String> {
+		let mut result = String::new();
+		let end = list.len();
+		let mut start = 0usize;
+		for element in list {
+			result += &(tostring(element)?);
+			start += 1;
+			if start < end {
+				result += separator
+			}
+		}
+		Ok(result)
+	}
+
+	fn compile_identifiers(&self, names: Vec<String>) -> Result<String, String> {
+		self.compile_list(names, ", ", &mut Ok)
+	}
+
+	fn compile_expressions(&self, scope: usize, values: Vec<Expression>) -> Result<String, String> {
+		self.compile_list(values, ", ", &mut |expr| {
+			self.compile_expression(scope, expr)
+		})
+	}
+
+	// wraps the function with its attributes, the closest one to the function being applied first
+	fn compile_attributes(
+		&self,
+		scope: usize,
+		name: &str,
+		attributes: FunctionAttributes,
+	) -> Result<String, String> {
+		if attributes.is_empty() {
+			return Ok(String::new());
+		}
+		let name = match name.rsplit_once(':') {
+			Some((table, method)) => format_clue!(table, ".", method),
+			None => name.to_owned(),
+		};
+		let mut value = name.clone();
+		for (attribute, args) in attributes.into_iter().rev() {
+			value = if args.is_empty() {
+				format_clue!(attribute, "(", value, ")")
+			} else {
+				let args = self.compile_expressions(scope, args)?;
+				format_clue!(attribute, "(", value, ", ", args, ")")
+			};
+		}
+		let pre = self.indentate(scope);
+		Ok(format_clue!("\n", pre, name, " = ", value, ";"))
+	}
+
+	fn compile_function(
+		&self,
+		scope: usize,
+		name: Option<&str>,
+		args: FunctionArgs,
+		code: CodeBlock,
+	) -> Result<(String, String), String> {
+		let (start, end) = (code.start, code.end);
+		let profile = self.options.env_profile as usize;
+		let wrappers = self.options.env_debug as usize + profile;
+		let mut code = self.compile_code_block(scope + wrappers, "", code)?;
+		let args = self.compile_list(args, ", ", &mut |(arg, default)| {
+			if let Some((default, line)) = default {
+				let default = self.compile_expression(scope + wrappers + 2, default)?;
+				let pre = self.indentate(scope + wrappers + 1);
+				let debug = self.compile_debug_line(line, scope + wrappers + 2, true);
+				let line = self.compile_debug_comment(line);
+				code = format_clue!(
+					"\n",
+					pre,
+					"if ",
+					arg,
+					" == nil then\n",
+					pre,
+					"\t",
+					debug,
+					arg,
+					" = ",
+					default,
+					line,
+					"\n",
+					pre,
+					"end",
+					code
+				);
+			}
+			Ok(arg)
+		})?;
+		if self.options.env_debug {
+			let pre = self.indentate(scope + profile);
+			code = format_clue!(
+				"\n",
+				pre,
+				"\treturn ",
+				self.helper(Helper::DebugCall),
+				"(",
+				quote_lua_string(&match name {
+					Some(name) => format!("function '{name}' (lines {start}-{end})"),
+					None => format!("anonymous function (lines {start}-{end})"),
+				}),
+				", ",
+				quote_lua_string(self.filename),
+				", function(",
+				args,
+				")",
+				code,
+				"end",
+				if args.is_empty() {
+					String::new()
+				} else {
+					format_clue!(", ", args)
+				},
+				")\n",
+				pre
+			)
+		}
+		if self.options.env_profile {
+			let pre = self.indentate(scope);
+			let hook = match &self.options.env_profile_hook {
+				Some(hook) => hook.clone(),
+				None => self.helper(Helper::Profiler),
+			};
+			let name = quote_lua_string(&match name {
+				Some(name) => format!("{name} ({}:{start})", self.filename),
+				None => format!("anonymous function ({}:{start})", self.filename),
+			});
+			code = format!(
+				"\n{pre}\t{hook}.enter({name})\n{pre}\treturn {hook}.exit({name}, (function({args}){code}end)({args}))\n{pre}"
+			)
+		}
+		Ok((code, args))
+	}
+
+	// adds the types of the parameters and of the returned value to the parameters of a function,
+	// from the annotations before it, returning them with the closing bracket
+	fn compile_signature(&self, args: String, declaration: &Declaration) -> String {
+		let target = self.target();
+		let params = &declaration.params;
+		let args: Vec<String> = (args.split(", ").filter(|arg| !arg.is_empty()))
+			.map(|arg| match params.iter().find(|(name, ..)| name == arg) {
+				Some((_, optional, luatype)) => {
+					format_clue!(arg, target.compile_type(*optional, luatype))
+				}
+				None => arg.to_owned(),
+			})
+			.collect();
+		let returns = match &declaration.returns {
+			Some(luatype) => target.compile_type(false, luatype),
+			None => String::new(),
+		};
+		format_clue!(args.join(", "), ")", returns)
+	}
+
+	fn compile_code_block(
+		&self,
+		scope: usize,
+		start: &str,
+		block: CodeBlock,
+	) -> Result<String, String> {
+		let pre = self.indentate(scope);
+		let code = self.compile_tokens(scope + 1, block.code)?;
+		let debug = self.compile_debug_line(block.start, scope + 1, true);
+		let coverage = self.compile_coverage_hook(block.start);
+		Ok(if self.options.env_debug {
+			format!(
+				"{}\n{}\t{}--{}->{}\n{}\n{}",
+				start, pre, debug, block.start, block.end, code, pre
+			)
+		} else if !coverage.is_empty() {
+			format_clue!(start, "\n", pre, "\t", coverage, "\n", code, "\n", pre)
+		} else {
+			format_clue!(start, "\n", code, "\n", pre)
+		})
+	}
+
+	// closes the resource of a `with` block with its `__close` metamethod or else its `close` method
+	fn compile_close(&self, name: &str, pre: &str) -> String {
+		format_clue!(
+			pre,
+			"if ",
+			name,
+			" ~= nil then\n",
+			pre,
+			"\tlocal _meta = getmetatable(",
+			name,
+			");\n",
+			pre,
+			"\tif type(_meta) == \"table\" and _meta.__close then\n",
+			pre,
+			"\t\t_meta.__close(",
+			name,
+			", _err);\n",
+			pre,
+			"\telse\n",
+			pre,
+			"\t\t",
+			name,
+			":close();\n",
+			pre,
+			"\tend\n",
+			pre,
+			"end\n"
+		)
+	}
+
+	// the call to the coverage hook, also made at the start of the blocks to know if they ran
+	fn compile_coverage_hook(&self, line: usize) -> String {
+		match &self.options.env_coverage {
+			Some(hook) => {
+				let file = quote_lua_string(self.filename);
+				format_clue!(hook, "(", file, ", ", line.to_string(), ");")
+			}
+			None => String::new(),
+		}
+	}
+
+	fn compile_debug_comment(&self, line: usize) -> String {
+		if self.options.env_debug {
+			format!(" --{line}")
+		} else {
+			String::new()
+		}
+	}
+
+	fn compile_debug_line(&self, line: usize, scope: usize, indentate_last: bool) -> String {
+		let mut debug = if self.options.env_debug {
+			format_clue!("_clueline = ", line.to_string(), ";")
+		} else {
+			String::new()
+		};
+		debug += &self.compile_coverage_hook(line);
+		if self.options.env_traceback || self.options.env_source_map || self.options.env_debug_info
+		{
+			debug += &format_clue!(TRACEBACK_MARKER, self.filename, ":", line.to_string());
+		}
+		if !debug.is_empty() {
+			if indentate_last {
+				format_clue!(debug, "\n", self.indentate(scope))
+			} else {
+				format_clue!("\n", self.indentate(scope), debug)
+			}
+		} else {
+			String::new()
+		}
+	}
+
+	// whether the symbol is a function of the bit library the bitwise operators were turned into
+	fn is_pure_bit_call(&self, lexeme: &str) -> bool {
+		self.options.env_bitwise == BitwiseMode::Pure
+			&& self.options.env_jitbit.as_ref().is_some_and(|bit| {
+				lexeme
+					.strip_prefix(bit.as_str())
+					.is_some_and(|function| function.starts_with('.'))
+			})
+	}
+
+	// wraps the value with a check that fails with the message if it's nil, for the strict option
+	fn compile_strict_check(&self, value: String, message: String, line: usize) -> String {
+		let message = format!("{}:{line}: {message}", self.filename);
+		format_clue!(
+			self.helper(Helper::StrictCheck),
+			"(",
+			value,
+			", ",
+			quote_lua_string(&message),
+			")"
+		)
+	}
+
+	// `arithmetic` is whether the value is used in arithmetic, which with the strict option
+	// checks that the call it ends with didn't return nil
+	fn compile_identifier(
+		&self,
+		scope: usize,
+		expr: Expression,
+		line: usize,
+		arithmetic: bool,
+	) -> Result<String, String> {
+		// only names and the indexing of them, not the code generated for other operators
+		let strict = self.options.env_strict
+			&& expr.iter().all(|t| match t {
+				SYMBOL(lexeme, _) => {
+					is_name(lexeme)
+						|| matches!(lexeme.as_str(), "." | ":" | "[" | "]" | "[(" | ")]")
+				}
+				EXPR(_, _) | CALL(_, _) => true,
+				_ => false,
+			});
+		let call = strict && arithmetic && matches!(expr.back(), Some(CALL(_, _)));
+		let mut result = String::with_capacity(32);
+		// the code without the checks, to show in their messages
+		let mut plain = String::with_capacity(32);
+		for t in expr {
+			if strict
+				&& !result.is_empty()
+				&& matches!(&t, SYMBOL(index, _) if matches!(index.as_str(), "." | ":" | "[" | "[("))
+			{
+				let message = format!("attempt to index '{plain}' (a nil value)");
+				result = self.compile_strict_check(result, message, line);
+			}
+			let code = match t {
+				SYMBOL(lexeme, _) => lexeme,
+				EXPR(expr, _) => self.compile_expression(scope, expr)?,
+				CALL(args, _) if call => {
+					let message =
+						format!("attempt to perform arithmetic on the nil returned by '{plain}'");
+					let args = self.compile_expressions(scope, args)?;
+					let value = format_clue!(result, "(", args, ")");
+					return Ok(self.compile_strict_check(value, message, line));
+				}
+				CALL(args, _) => {
+					format_clue!("(", self.compile_expressions(scope, args.clone())?, ")")
+				}
+				_ => return Err(String::from("Unexpected ComplexToken found")),
+			};
+			result += &code;
+			if strict {
+				plain += &code;
+			}
+		}
+		Ok(result)
+	}
+
+	fn compile_expression(&self, mut scope: usize, expr: Expression) -> Result<String, String> {
+		let mut result = String::with_capacity(64);
+		// which tokens are arithmetic operators, for the strict option
+		let arithmetic: Vec<bool> = if self.options.env_strict {
+			expr.iter()
+				.map(|t| matches!(t, SYMBOL(op, _) if is_arithmetic(op)))
+				.collect()
+		} else {
+			Vec::new()
+		};
+		for (i, t) in expr.into_iter().enumerate() {
+			result += &match t {
+				SYMBOL(lexeme, _) if self.is_pure_bit_call(&lexeme) => {
+					self.helper(Helper::PureBit);
+					lexeme
+				}
+				SYMBOL(lexeme, _) => lexeme,
+				TABLE {
+					values,
+					metas,
+					metatable,
+					..
+				} => {
+					scope += 1;
+					let mut prevline = 0;
+					let pre1 = self.indentate(scope);
+					let values = if values.is_empty() {
+						String::new()
+					} else {
+						self.compile_list(values, ", ", &mut |(name, value, span)| {
+							let value = self.compile_expression(scope, value)?;
+							let l = if prevline != 0 {
+								self.compile_debug_comment(prevline)
+							} else {
+								String::new()
+							};
+							prevline = span.start.line;
+							if let Some(name) = name {
+								let name = self.compile_expression(scope, name)?;
+								Ok(format_clue!(l, "\n", pre1, name, " = ", value))
+							} else {
+								Ok(format_clue!(l, "\n", pre1, value))
+							}
+						})? + &self.compile_debug_comment(prevline)
+							+ "\n"
+					};
+					prevline = 0;
+					let pre2 = self.indentate(scope - 1);
+					if metas.is_empty() {
+						scope -= 1;
+						if let Some(metatable) = metatable {
+							format!("setmetatable({{{values}{pre2}}}, {metatable})")
+						} else {
+							format!("{{{values}{pre2}}}")
+						}
+					} else {
+						let metas =
+							self.compile_list(metas, ", ", &mut |(name, value, span)| {
+								let value = self.compile_expression(scope, value)?;
+								let l = if prevline != 0 {
+									self.compile_debug_comment(prevline)
+								} else {
+									String::new()
+								};
+								prevline = span.start.line;
+								Ok(format_clue!(l, "\n", pre1, name, " = ", value))
+							})?;
+						scope -= 1;
+						let line = self.compile_debug_comment(prevline);
+						format!("setmetatable({{{values}{pre2}}}, {{{metas}{line}\n{pre2}}})",)
+					}
+				}
+				LAMBDA { args, code, .. } => {
+					let (code, args) = self.compile_function(scope, None, args, code)?;
+					format_clue!("function(", args, ")", code, "end")
+				}
+				CONST_BLOCK { code, .. } => {
+					let (code, _) = self.compile_function(scope, None, FunctionArgs::new(), code)?;
+					format_clue!("(function()", code, "end)()")
+				}
+				IDENT { expr, span } => {
+					let arithmetic = [i.wrapping_sub(1), i + 1]
+						.iter()
+						.any(|&i| arithmetic.get(i).is_some_and(|&op| op));
+					self.compile_identifier(scope, expr, span.start.line, arithmetic)?
+				}
+				CALL(args, _) => format!("({})", self.compile_expressions(scope, args)?),
+				EXPR(expr, _) => format!("({})", self.compile_expression(scope, expr)?),
+				_ => return Err(String::from("Unexpected ComplexToken found")),
+			}
+		}
+		Ok(result)
+	}
+
+	fn compile_elseif_chain(
+		&self,
+		scope: usize,
+		condition: Expression,
+		code: CodeBlock,
+		next: Option<Box<ComplexToken>>,
+	) -> Result<String, String> {
+		let condition = self.compile_expression(scope, condition)?;
+		let code = self.compile_code_block(scope, "then", code)?;
+		let next = if let Some(next) = next {
+			String::from("else")
+				+ &match *next {
+					IF_STATEMENT {
+						condition,
+						code,
+						next,
+						..
+					} => self.compile_elseif_chain(scope, condition, code, next)?,
+					DO_BLOCK(code, _) => self.compile_code_block(scope, "", code)?,
+					_ => return Err(String::from("Unexpected ComplexToken found")),
+				}
+		} else {
+			String::new()
+		};
+		Ok(format_clue!("if ", condition, " ", code, next))
+	}
+
+	/// Compiles the static variables of a file, as returned by
+	/// [`parse_tokens`](clue_frontend::parser::parse_tokens), into a [`String`] of Lua
+	/// which must be put before the rest of the code.
+	///
+	/// # Errors
+	/// Returns an error if an unexpected [`ComplexToken`] is found.
+	pub fn compile_statics(&self, statics: Expression) -> Result<String, String> {
+		if statics.is_empty() {
+			return Ok(String::new());
+		}
+		let code = self.compile_tokens(0, statics)?;
+		Ok(if self.options.env_debug {
+			format!("--statics defined in \"{}\":\n{code}\n\n", self.filename)
+		} else {
+			code + "\n"
+		})
+	}
+
+	/// Compiles an [`Expression`] into a [`String`] of Lua.
+	///
+	/// # Errors
+	/// Returns an error if an unexpected [`ComplexToken`] is found.
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_backend_lua::compiler::*;
+	/// use clue_frontend::{env::Options, parse_source};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let options = Options::default();
+	///     let filename = String::from("file.clue");
+	///     let compiler = Compiler::new(&options, &filename);
+	///     let code = "local fn a() {return 1;}".to_owned();
+	///
+	///     let (ctokens, _) = parse_source(code, &filename, &options)?;
+	///     let output = compiler.compile_tokens(0, ctokens)?;
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn compile_tokens(&self, scope: usize, ctokens: Expression) -> Result<String, String> {
+		let mut result = String::new();
+		self.emit_tokens(scope, ctokens, &mut result)?;
+		Ok(result)
+	}
+
+	/// Compiles an [`Expression`] like [`Compiler::compile_tokens`],
+	/// giving each statement to `emitter` as soon as it's compiled (see [`CodeEmitter`]).
+	///
+	/// # Errors
+	/// Returns an error if an unexpected [`ComplexToken`] is found or if `emitter` fails.
+	pub fn emit_tokens(
+		&self,
+		scope: usize,
+		ctokens: Expression,
+		emitter: &mut dyn CodeEmitter,
+	) -> Result<(), String> {
+		if self.options.env_global_table.is_some() {
+			use LuaVersion::*;
+			if !matches!(self.options.env_target, Some(Lua52 | Lua53 | Lua54)) {
+				return Err(String::from(
+					"The global table can only be changed when targeting Lua 5.2 or later",
+				));
+			}
+			self.helper(Helper::GlobalTable);
+		}
+		emitter.emit(&self.indentate(scope))?;
+		let ctokens = &mut ctokens.into_iter().peekable();
+		// the annotations read so far, which describe the next statement
+		let mut declared: Option<Declaration> = None;
+		while let Some(t) = ctokens.next() {
+			if let SYMBOL(lexeme, _) = &t {
+				if let Some(annotation) = lexeme.strip_prefix("---@") {
+					let pending = declared.get_or_insert_with(Declaration::default);
+					pending.add(annotation);
+					if let Some(annotation) = self.target().compile_annotation(annotation) {
+						emitter.emit(&(annotation + &self.indentate_if(ctokens, scope)))?;
+					}
+					continue;
+				}
+			}
+			let declaration = declared.take().unwrap_or_default();
+			let code = match t {
+				SYMBOL(lexeme, _) => lexeme + &self.indentate_if(ctokens, scope),
+				VARIABLE {
+					local,
+					r#const,
+					names,
+					values,
+					span,
+				} => {
+					let line = span.start.line;
+					let debug = self.compile_debug_line(line, scope, true);
+					let line = self.compile_debug_comment(line);
+					let record = match &declaration.class {
+						Some((class, _)) if names.len() == 1 && names[0] == *class => {
+							let pre = self.indentate(scope);
+							self.target().compile_class(local, &declaration, &pre)
+						}
+						_ => None,
+					};
+					if let Some(record) = record {
+						format_clue!(debug, record, line, self.indentate_if(ctokens, scope))
+					} else if !local && self.options.env_rawsetglobals {
+						let mut result = debug;
+						let mut valuesit = values.iter();
+						let namesit = &mut names.iter().peekable();
+						while let Some(name) = namesit.next() {
+							let value = if let Some(value) = valuesit.next() {
+								self.compile_expression(scope, value.clone())?
+							} else {
+								String::from("nil")
+							};
+							let end = {
+								let pend = self.indentate_if(namesit, scope);
+								if !pend.is_empty() {
+									pend
+								} else {
+									self.indentate_if(ctokens, scope)
+								}
+							};
+							let globals = match self.options.env_global_table {
+								Some(_) => "_ENV",
+								None => "_G",
+							};
+							write!(result, "rawset({globals}, \"{name}\", {value});{line}{end}")
+								.map_err(|e| e.to_string())?
+						}
+						result
+					} else {
+						let end = self.indentate_if(ctokens, scope);
+						let post = if r#const
+							&& self
+								.options
+								.env_target
+								.is_some_and(|lua| lua == LuaVersion::Lua54)
+						{
+							" <const> "
+						} else {
+							""
+						};
+						let global = !local && post.is_empty();
+						let pre = if global {
+							self.target().global_keyword()
+						} else {
+							"local "
+						};
+						let mut names = if global {
+							names.iter().map(|name| self.global_name(name)).collect()
+						} else {
+							names
+						};
+						if let (Some(luatype), [name]) = (&declaration.r#type, &mut names[..]) {
+							*name += &self.target().compile_type(false, luatype);
+						}
+						if values.is_empty() {
+							let ident = self.compile_identifiers(names)?;
+							format_clue!(debug, pre, ident, ";", line, end)
+						} else {
+							let values = self.compile_expressions(scope, values)?;
+							let names = self.compile_identifiers(names)?;
+							format_clue!(debug, pre, names, post, " = ", values, ";", line, end)
+						}
+					}
+				}
+				ALTER {
+					kind,
+					names,
+					values,
+					span,
+				} => {
+					let line = span.start.line;
+					let iter = names.into_iter();
+					let mut names: Vec<String> = Vec::new();
+					for name in iter {
+						names.push(self.compile_expression(scope, name)?)
+					}
+					// Luau has the compound assignments natively, but only for one variable
+					let native = match kind {
+						INCREASE => Some(" += "),
+						DECREASE => Some(" -= "),
+						MULTIPLY => Some(" *= "),
+						DIVIDE => Some(" /= "),
+						EXPONENTIATE => Some(" ^= "),
+						CONCATENATE => Some(" ..= "),
+						MODULATE => Some(" %= "),
+						_ => None,
+					}
+					.filter(|_| {
+						self.options.env_target == Some(LuaVersion::Luau)
+							&& names.len() == 1 && values.len() == 1
+					});
+					let mut i = 0usize;
+					let values = self.compile_list(values, ", ", &mut |expr| {
+						if native.is_some() {
+							return self.compile_expression(scope, expr);
+						}
+						let name = if let Some(name) = names.get(i) {
+							name.clone()
+						} else {
+							String::from("nil")
+						};
+						i += 1;
+						Ok((if kind == DEFINE {
+							String::new()
+						} else {
+							name + match kind {
+								DEFINE_AND => " and ",
+								DEFINE_OR => " or ",
+								INCREASE => " + ",
+								DECREASE => " - ",
+								MULTIPLY => " * ",
+								DIVIDE => " / ",
+								EXPONENTIATE => " ^ ",
+								CONCATENATE => " .. ",
+								MODULATE => " % ",
+								_ => return Err(String::from("Unexpected alter type found")),
+							}
+						}) + &self.compile_expression(scope, expr)?)
+					})?;
+					let names = self.compile_identifiers(names)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let line = self.compile_debug_comment(line);
+					format_clue!(
+						debug,
+						names,
+						native.unwrap_or(" = "),
+						values,
+						";",
+						line,
+						self.indentate_if(ctokens, scope)
+					)
+				}
+				FUNCTION {
+					local,
+					name,
+					args,
+					code,
+					attributes,
+					..
+				} => {
+					let end = self.indentate_if(ctokens, scope);
+					let global =
+						!local && matches!((name.len(), name.front()), (1, Some(SYMBOL(_, _))));
+					let pre = if local {
+						"local "
+					} else if global {
+						self.target().global_keyword()
+					} else {
+						""
+					};
+					let name = self.compile_expression(scope, name)?;
+					let (code, args) = self.compile_function(scope, Some(&name), args, code)?;
+					let args = self.compile_signature(args, &declaration);
+					let attributes = self.compile_attributes(scope, &name, attributes)?;
+					let declared = if global {
+						self.global_name(&name)
+					} else {
+						name
+					};
+					let code = format_clue!("function ", declared, "(", args, code, "end");
+					format_clue!(pre, code, attributes, end)
+				}
+				IF_STATEMENT {
+					condition,
+					code,
+					next,
+					..
+				} => {
+					let code = self.compile_elseif_chain(scope, condition, code, next)?;
+					format_clue!(code, "end", self.indentate_if(ctokens, scope))
+				}
+				MATCH_BLOCK {
+					name,
+					value,
+					branches,
+					span,
+				} => {
+					let line = span.start.line;
+					let value = self.compile_expression(scope, value)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let line = self.compile_debug_comment(line);
+					// the internal code of the first branch is compiled before the first if,
+					// so it needs its own block to not leak its locals (like destructured fields)
+					let wrap = branches.first().is_some_and(|branch| !branch.1.is_empty());
+					let branches = {
+						let outer = scope;
+						let scope = scope + wrap as usize;
+						let mut result = self.indentate(scope);
+						let last = branches.len() - 1;
+						let branches = branches.into_iter().enumerate();
+						for (i, (conditions, internal_expr, extraif, code)) in branches {
+							let empty = conditions.is_empty();
+							let default = empty && extraif.is_none();
+							let condition = {
+								let mut condition =
+									self.compile_list(conditions, "or ", &mut |expr| {
+										let expr = self.compile_expression(scope, expr)?;
+										Ok(format_clue!("(", name, " == ", expr, ") "))
+									})?;
+								format_clue!(
+									"if ",
+									if let Some(extraif) = extraif {
+										condition.pop();
+										let extraif = self.compile_expression(scope, extraif)?;
+										if empty {
+											extraif + " "
+										} else {
+											format_clue!("(", condition, ") and ", extraif, " ")
+										}
+									} else {
+										condition
+									},
+									"then"
+								)
+							};
+							let end = if i >= last { "end" } else { "else" };
+							let pre = self.indentate(scope + i);
+							let internal_code = if internal_expr.is_empty() {
+								None
+							} else {
+								Some(self.compile_tokens(scope + i, internal_expr)?)
+							};
+							let code = if i == 0 {
+								let code = self.compile_code_block(scope, &condition, code)? + end;
+								if let Some(internal_code) = internal_code {
+									// result is already indented
+									format_clue!(internal_code.trim_start(), '\n', pre, code)
+								} else {
+									code
+								}
+							} else if default {
+								self.compile_code_block(scope + i - 1, "", code)? + end
+							} else {
+								let code = self.compile_code_block(scope + i, &condition, code)?;
+								let mut code = format_clue!('\n', pre, code, end);
+								if let Some(internal_code) = internal_code {
+									code = format_clue!('\n', internal_code, code)
+								}
+								if i >= last {
+									code +=
+										&format_clue!('\n', self.indentate(scope + i - 1), "end");
+								}
+								code
+							};
+							result += &code;
+						}
+						let result = if last > 1 {
+							result.push('\n');
+							for i in (1..last - 1).rev() {
+								result += &(self.indentate(scope + i) + "end\n");
+							}
+							format_clue!(result, self.indentate(scope), "end")
+						} else {
+							result
+						};
+						if wrap {
+							let pre = self.indentate(outer);
+							format_clue!(pre, "do\n", result, '\n', pre, "end")
+						} else {
+							result
+						}
+					};
+					let end = self.indentate_if(ctokens, scope);
+					format_clue!(
+						debug, "local ", name, " = ", value, ';', line, '\n', branches, end
+					)
+				}
+				WHILE_LOOP {
+					condition,
+					code,
+					span,
+					..
+				} => {
+					let line = span.start.line;
+					let condition = self.compile_expression(scope, condition)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let code = self.compile_code_block(scope, "do", code)?;
+					format_clue!(
+						debug,
+						"while ",
+						condition,
+						" ",
+						code,
+						debug,
+						"end",
+						self.indentate_if(ctokens, scope)
+					)
+				}
+				LOOP_UNTIL {
+					condition,
+					code,
+					span,
+					..
+				} => {
+					let line = span.end.line;
+					let condition = self.compile_expression(scope, condition)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let code = self.compile_code_block(scope, "", code)?;
+					format_clue!(
+						"repeat ",
+						code,
+						debug,
+						"until ",
+						condition,
+						self.indentate_if(ctokens, scope)
+					)
+				}
+				FOR_LOOP {
+					iterator,
+					start,
+					end,
+					alter,
+					code,
+					span,
+					..
+				} => {
+					let line = span.start.line;
+					let start = self.compile_expression(scope, start)?;
+					let endexpr = self.compile_expression(scope, end)?;
+					let alter = self.compile_expression(scope, alter)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let code = self.compile_code_block(scope, "do", code)?;
+					let end = self.indentate_if(ctokens, scope);
+					format_clue!(
+						debug, "for ", iterator, " = ", start, ", ", endexpr, ", ", alter, " ",
+						code, debug, "end", end
+					)
+				}
+				FOR_FUNC_LOOP {
+					iterators,
+					expr,
+					code,
+					span,
+					..
+				} => {
+					let line = span.start.line;
+					let expr = self.compile_expression(scope, expr)?;
+					let iterators = self.compile_identifiers(iterators)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let code = self.compile_code_block(scope, "do", code)?;
+					format_clue!(
+						debug,
+						"for ",
+						iterators,
+						" in ",
+						expr,
+						" ",
+						code,
+						debug,
+						"end",
+						self.indentate_if(ctokens, scope)
+					)
+				}
+				TRY_CATCH {
+					totry,
+					error,
+					catch,
+					finally,
+					..
+				} => {
+					let i = self.indentate_if(ctokens, scope);
+					if catch.is_none() && finally.is_none() {
+						let totry = self.compile_code_block(scope, "function()", totry)?;
+						format_clue!("pcall(", totry, "end)", i)
+					} else if let Some(finally) = finally {
+						// the catch block runs protected too, so that finally runs even if it throws
+						let pre = self.indentate(scope + 1);
+						let totry = self.compile_code_block(scope + 1, "function()", totry)?;
+						let catch = match catch {
+							Some(catch) => {
+								let start =
+									format_clue!("function(", error.unwrap_or_default(), ")");
+								let catch = self.compile_code_block(scope + 2, &start, catch)?;
+								format_clue!(
+									pre,
+									"if not _check then\n",
+									pre,
+									"\t_check, _err = pcall(",
+									catch,
+									"end, _err)\n",
+									pre,
+									"end\n"
+								)
+							}
+							None => String::new(),
+						};
+						let finally = self.compile_code_block(scope + 1, "do", finally)?;
+						format_clue!(
+							"do\n",
+							pre,
+							"local _check, _err = pcall(",
+							totry,
+							"end)\n",
+							catch,
+							pre,
+							finally,
+							"end\n",
+							pre,
+							"if not _check then error(_err, 0) end\n",
+							self.indentate(scope),
+							"end",
+							i
+						)
+					} else {
+						let pre = self.indentate(scope + 1);
+						let totry = self.compile_code_block(scope + 1, "function()", totry)?;
+						let catch = self.compile_code_block(
+							scope + 1,
+							"if not _check then",
+							catch.unwrap(),
+						)?;
+						let error = match error {
+							Some(error) => format_clue!(", ", error),
+							None => String::new(),
+						};
+						format_clue!(
+							"do\n",
+							pre,
+							"local _check",
+							error,
+							" = pcall(",
+							totry,
+							"end)\n",
+							pre,
+							catch,
+							"end\n",
+							self.indentate(scope),
+							"end",
+							i
+						)
+					}
+				}
+				WITH_BLOCK {
+					name,
+					value,
+					code,
+					span,
+				} => {
+					let line = span.start.line;
+					let end = self.indentate_if(ctokens, scope);
+					let pre = self.indentate(scope + 1);
+					let value = self.compile_expression(scope + 1, value)?;
+					let debug = self.compile_debug_line(line, scope + 1, true);
+					if self
+						.options
+						.env_target
+						.is_some_and(|lua| lua == LuaVersion::Lua54)
+					{
+						let code = self.compile_code_block(scope, "", code)?;
+						let close = self.compile_close(&name, &(pre.clone() + "\t"));
+						format_clue!(
+							"do\n",
+							pre,
+							debug,
+							"local ",
+							name,
+							" = ",
+							value,
+							";\n",
+							pre,
+							"local _close <close> = setmetatable({}, {__close = function(_, _err)\n",
+							close,
+							pre,
+							"end});",
+							code,
+							"end",
+							end
+						)
+					} else {
+						let code = self.compile_code_block(scope + 1, "function()", code)?;
+						format_clue!(
+							"do\n",
+							pre,
+							debug,
+							"local ",
+							name,
+							" = ",
+							value,
+							";\n",
+							pre,
+							"local _check, _err = pcall(",
+							code,
+							"end);\n",
+							self.compile_close(&name, &pre),
+							pre,
+							"if not _check then error(_err, 0) end\n",
+							self.indentate(scope),
+							"end",
+							end
+						)
+					}
+				}
+				IDENT { expr, span } => {
+					let line = span.start.line;
+					let expr = self.compile_identifier(scope, expr, line, false)?;
+					let debug = self.compile_debug_line(line, scope, true);
+					let line = self.compile_debug_comment(line);
+					format_clue!(debug, expr, ";", line, self.indentate_if(ctokens, scope))
+				}
+				EXPR(expr, _) => {
+					format!("({})", self.compile_expression(scope, expr)?)
+				}
+				DO_BLOCK(code, _) => {
+					format!(
+						"{}end{}",
+						self.compile_code_block(scope, "do", code)?,
+						self.indentate_if(ctokens, scope)
+					)
+				}
+				RETURN_EXPR(exprs, _) => {
+					if let Some(exprs) = exprs {
+						format!("return {};", self.compile_expressions(scope, exprs)?)
+					} else {
+						String::from("return;")
+					}
+				}
+				CONTINUE_LOOP(Some(label), _) | BREAK_LOOP(Some(label), _) => {
+					return Err(format!(
+						"No enclosing loop in the same function is labeled '{label}'"
+					))
+				}
+				CONTINUE_LOOP(None, _) => {
+					let end = self.indentate_if(ctokens, scope);
+					format!(
+						"{};{}",
+						if matches!(
+							self.options.env_continue,
+							ContinueMode::LuaJIT | ContinueMode::Goto
+						) {
+							"goto continue"
+						} else {
+							"continue"
+						},
+						end
+					)
+				}
+				BREAK_LOOP(None, _) => String::from("break;") + &self.indentate_if(ctokens, scope),
+				GOTO_LABEL(label, _) => {
+					format_clue!("goto ", label, ";", self.indentate_if(ctokens, scope))
+				}
+				LABEL(label, _) => {
+					format_clue!("::", label, "::", self.indentate_if(ctokens, scope))
+				}
+				UNSUPPORTED {
+					keyword,
+					version,
+					span,
+				} => {
+					return Err(format!(
+						"Cannot compile code which needs Clue {version} ('{keyword}' at {}:{})",
+						span.start.line, span.start.column
+					))
+				}
+				ERROR {
+					message,
+					line,
+					column,
+				} => {
+					return Err(format!(
+						"Cannot compile code with syntax errors ({line}:{column}: {message})"
+					))
+				}
+				_ => return Err(String::from("Unexpected ComplexToken found")),
+			};
+			emitter.emit(&code)?;
+		}
+		Ok(())
+	}
+}
+
+fn is_name(lexeme: &str) -> bool {
+	lexeme.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+		&& lexeme
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_arithmetic(op: &str) -> bool {
+	matches!(op, "+" | "-" | "*" | "/" | "%" | "^" | "//")
+}
+
+/// Turns the given string into a Lua string literal (quotes included) which evaluates back to it.
+/// This is the only safe way to put arbitrary user strings (like file names) in generated code,
+/// long brackets like `[[...]]` break as soon as the string contains `]]`.
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::compiler::quote_lua_string;
+///
+/// assert_eq!(quote_lua_string("main.clue"), "\"main.clue\"");
+/// assert_eq!(quote_lua_string("a]]\"b\\\n"), "\"a]]\\\"b\\\\\\n\"");
+/// assert_eq!(quote_lua_string("\01"), "\"\\0001\"");
+/// ```
+pub fn quote_lua_string(string: &str) -> String {
+	let mut result = String::with_capacity(string.len() + 2);
+	result.push('"');
+	for c in string.chars() {
+		match c {
+			'"' => result += "\\\"",
+			'\\' => result += "\\\\",
+			'\n' => result += "\\n",
+			'\r' => result += "\\r",
+			'\t' => result += "\\t",
+			// always use 3 digits so a digit after it is not read as part of the escape
+			c if c.is_ascii_control() => result += &format!("\\{:03}", c as u8),
+			c => result.push(c),
+		}
+	}
+	result.push('"');
+	result
+}
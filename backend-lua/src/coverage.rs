@@ -0,0 +1,104 @@
+//! The coverage module finds the lines the compiler instrumented when
+//! [`Options::env_coverage`](clue_frontend::env::Options::env_coverage) is set
+//! and turns them into an [lcov](https://github.com/linux-test-project/lcov) tracefile where none of them are hit yet.
+//!
+//! The hook (`__clue_cov(file, line)` by default) is called before each instrumented statement
+//! and is defined by the user to count the hits at runtime, which can then be added to the
+//! tracefile to know which lines of the Clue files never ran.
+
+use std::collections::BTreeSet;
+
+// reads a string quoted by `quote_lua_string` at the start of `code`, returning it and the rest of the code
+fn unquote_lua_string(code: &str) -> Option<(String, &str)> {
+	let mut result = String::new();
+	let mut chars = code.strip_prefix('"')?.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Some((result, &code[i + 2..])),
+			'\\' => match chars.next()?.1 {
+				'n' => result.push('\n'),
+				'r' => result.push('\r'),
+				't' => result.push('\t'),
+				c if c.is_ascii_digit() => {
+					let digits = [c, chars.next()?.1, chars.next()?.1];
+					let code: String = digits.iter().collect();
+					result.push(char::from(code.parse::<u8>().ok()?));
+				}
+				c => result.push(c),
+			},
+			c => result.push(c),
+		}
+	}
+	None
+}
+
+/// Returns the lines of every file instrumented in the given code by calling `hook`,
+/// in the order the files first appear
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compile_ast, coverage::covered_lines};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("main.clue");
+///     let options = Options {
+///         env_coverage: Some(String::from("__clue_cov")),
+///         ..Options::default()
+///     };
+///     let parsed = parse_source("local a = 1\n\nprint(a)".to_owned(), &filename, &options)?;
+///     let code = compile_ast(parsed, &filename, &options)?;
+///     assert_eq!(
+///         code,
+///         "__clue_cov(\"main.clue\", 1);\nlocal a = 1;\n__clue_cov(\"main.clue\", 3);\nprint(a);"
+///     );
+///     let lines = covered_lines(&code, "__clue_cov");
+///     assert_eq!(lines, [(filename, [1, 3].into())]);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn covered_lines(code: &str, hook: &str) -> Vec<(String, BTreeSet<usize>)> {
+	let mut files: Vec<(String, BTreeSet<usize>)> = Vec::new();
+	let call = format!("{hook}(");
+	for (i, _) in code.match_indices(&call) {
+		let Some((file, rest)) = unquote_lua_string(&code[i + call.len()..]) else {
+			continue;
+		};
+		let Some(line) = rest
+			.strip_prefix(", ")
+			.and_then(|rest| rest.split_once(')'))
+			.and_then(|(line, _)| line.parse().ok())
+		else {
+			continue;
+		};
+		match files.iter_mut().find(|(name, _)| *name == file) {
+			Some((_, lines)) => {
+				lines.insert(line);
+			}
+			None => files.push((file, BTreeSet::from([line]))),
+		}
+	}
+	files
+}
+
+/// Makes an lcov tracefile from the output of [`covered_lines`], with every line hit 0 times
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::coverage::lcov;
+///
+/// let files = [(String::from("main.clue"), [1, 3].into())];
+/// assert_eq!(lcov(&files), "SF:main.clue\nDA:1,0\nDA:3,0\nLF:2\nLH:0\nend_of_record\n");
+/// ```
+pub fn lcov(files: &[(String, BTreeSet<usize>)]) -> String {
+	let mut result = String::new();
+	for (file, lines) in files {
+		result += &format!("SF:{file}\n");
+		for line in lines {
+			result += &format!("DA:{line},0\n");
+		}
+		result += &format!("LF:{}\nLH:0\nend_of_record\n", lines.len());
+	}
+	result
+}
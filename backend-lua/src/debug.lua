@@ -0,0 +1,22 @@
+local _clueline, _errored, _errored_file = 0
+
+local function _clue_error(err, source)
+	if _errored then return end
+	err = tostring(err)
+	err = err:match(".+: (.-)$") or err
+	if source then
+		_errored = ("%s:%d: in %s: %s"):format(_errored_file, _clueline, source, err)
+	else
+		_errored = ("%s:%d: %s"):format(_errored_file, _clueline, err)
+	end
+end
+
+local ok, err = pcall(function()
+{}
+end)
+
+if not ok then
+	_errored_file = {}
+	_clue_error(err)
+	error(_errored, 0)
+end
\ No newline at end of file
@@ -0,0 +1,141 @@
+//! The debuginfo module turns the line markers the compiler leaves when
+//! [`Options::env_debug_info`](clue_frontend::env::Options::env_debug_info) is enabled
+//! into a JSON file for debuggers and crash report symbolizers.
+//!
+//! It tells the Clue file and line of every line of the output (like a source map, but without
+//! having to decode it) and where the temporary variables the compiler adds (`_internal0`, ...) come from,
+//! so that they can be hidden or explained when they show up in a stack trace.
+
+use crate::{sourcemap::quote_json_string, traceback::marked_lines};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The debug information of a compiled file
+pub struct DebugInfo {
+	/// The Clue files the output comes from
+	pub sources: Vec<String>,
+
+	/// The index in `sources` and the line of every line of the output, if it comes from one
+	pub lines: Vec<Option<(usize, usize)>>,
+
+	/// The temporary variables added by the compiler, with the index in `sources`
+	/// and the first line of the code they were added for
+	pub temporaries: Vec<(String, usize, usize)>,
+}
+
+impl DebugInfo {
+	/// Creates a new empty [`DebugInfo`]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the debug information as JSON, `file` is the name of the output file if it has one
+	pub fn build(&self, file: Option<&str>) -> String {
+		let sources: Vec<String> = self.sources.iter().map(|s| quote_json_string(s)).collect();
+		let file = match file {
+			Some(file) => format!("\"file\":{},", quote_json_string(file)),
+			None => String::new(),
+		};
+		let lines: Vec<String> = self
+			.lines
+			.iter()
+			.map(|line| match line {
+				Some((source, line)) => format!("[{source},{line}]"),
+				None => String::from("null"),
+			})
+			.collect();
+		let temporaries: Vec<String> = self
+			.temporaries
+			.iter()
+			.map(|(name, source, line)| {
+				format!(
+					"{{\"name\":{},\"source\":{source},\"line\":{line}}}",
+					quote_json_string(name)
+				)
+			})
+			.collect();
+		format!(
+			"{{\"version\":1,{file}\"sources\":[{}],\"lines\":[{}],\"temporaries\":[{}]}}",
+			sources.join(","),
+			lines.join(","),
+			temporaries.join(",")
+		)
+	}
+}
+
+// the temporary variables used in a line of the output
+fn temporaries(line: &str) -> impl Iterator<Item = &str> {
+	line.match_indices("_internal").filter_map(|(start, _)| {
+		let before = line[..start].chars().next_back();
+		if before.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+			return None;
+		}
+		let digits = line[start + 9..]
+			.find(|c: char| !c.is_ascii_digit())
+			.unwrap_or(line.len() - start - 9);
+		(digits > 0).then(|| &line[start..start + 9 + digits])
+	})
+}
+
+/// Removes the line markers left by the compiler from the final output (after the statics and
+/// the base for directories have been added), returning the code without them and its debug information.
+/// The lines without a marker come from the same line as the closest marked line before them.
+///
+/// `skipped` is the number of lines that will be put before the code, like in
+/// [`extract_source_map`](crate::sourcemap::extract_source_map).
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compile_ast, debuginfo::extract_debug_info};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("main.clue");
+///     let options = Options {
+///         env_debug_info: true,
+///         ..Options::default()
+///     };
+///     let parsed = parse_source("local a = 1\n\nlocal b = a?.c".to_owned(), &filename, &options)?;
+///     let (code, info) = extract_debug_info(&compile_ast(parsed, &filename, &options)?, 0);
+///     assert!(code.contains("_internal0"));
+///     assert_eq!(info.lines.first(), Some(&Some((0, 1))));
+///     assert_eq!(info.temporaries, [(String::from("_internal0"), 0, 3)]);
+///     assert!(info
+///         .build(Some("main.lua"))
+///         .ends_with(r#""temporaries":[{"name":"_internal0","source":0,"line":3}]}"#));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn extract_debug_info(code: &str, skipped: usize) -> (String, DebugInfo) {
+	let mut info = DebugInfo::new();
+	info.lines.resize(skipped, None);
+	let mut result = String::with_capacity(code.len());
+	let mut last = None;
+	for (line, location) in marked_lines(code) {
+		if let Some((file, number)) = location.and_then(|location| location.rsplit_once(':')) {
+			let source = match info.sources.iter().position(|s| s == file) {
+				Some(index) => index,
+				None => {
+					info.sources.push(file.to_owned());
+					info.sources.len() - 1
+				}
+			};
+			last = number.parse().ok().map(|number| (source, number));
+		}
+		info.lines.push(last);
+		if let Some((source, clue_line)) = last {
+			for name in temporaries(line) {
+				// every file numbers its own temporaries
+				let known = (info.temporaries.iter())
+					.any(|(other, other_source, _)| other == name && *other_source == source);
+				if !known {
+					info.temporaries.push((name.to_owned(), source, clue_line));
+				}
+			}
+		}
+		result += line;
+		result.push('\n');
+	}
+	result.pop();
+	(result, info)
+}
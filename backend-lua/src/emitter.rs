@@ -0,0 +1,106 @@
+//! The emitter module is where the compiled code goes, piece by piece, instead of one big [`String`].
+//!
+//! [`Compiler::emit_tokens`](crate::compiler::Compiler::emit_tokens) hands every top level statement
+//! to a [`CodeEmitter`] as soon as it's compiled, and so does [`compile_ast_to`](crate::compile_ast_to)
+//! after the [`helpers`](crate::helpers), the static variables and the preamble,
+//! so the output can be written to a file (see [`WriteEmitter`]) without ever being whole in memory.
+
+use std::io::Write;
+
+/// Something the compiled code can be written to, in order
+///
+/// It's implemented for [`String`] (an in-memory buffer), for [`Vec<String>`] (which keeps every
+/// piece separate), for [`WriteEmitter`] and for any `FnMut(&str) -> Result<(), String>`
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compiler::Compiler, emitter::CodeEmitter};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let filename = String::from("file.clue");
+///     let (ctokens, _) = parse_source("print(1)\nprint(2)".to_owned(), &filename, &options)?;
+///     let mut pieces = 0;
+///     let mut count = |_: &str| -> Result<(), String> {
+///         pieces += 1;
+///         Ok(())
+///     };
+///     Compiler::new(&options, &filename).emit_tokens(0, ctokens, &mut count)?;
+///     assert!(pieces >= 2);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait CodeEmitter {
+	/// Writes the next piece of the code
+	///
+	/// # Errors
+	/// If the code can't be written, an [`Err`] containing a [`String`] with the error message will be returned
+	fn emit(&mut self, code: &str) -> Result<(), String>;
+}
+
+impl CodeEmitter for String {
+	fn emit(&mut self, code: &str) -> Result<(), String> {
+		self.push_str(code);
+		Ok(())
+	}
+}
+
+impl CodeEmitter for Vec<String> {
+	fn emit(&mut self, code: &str) -> Result<(), String> {
+		if !code.is_empty() {
+			self.push(code.to_owned());
+		}
+		Ok(())
+	}
+}
+
+impl<F: FnMut(&str) -> Result<(), String>> CodeEmitter for F {
+	fn emit(&mut self, code: &str) -> Result<(), String> {
+		self(code)
+	}
+}
+
+/// A [`CodeEmitter`] writing the code to anything that implements [`Write`],
+/// like a [`File`](std::fs::File) (wrapped in a [`BufWriter`](std::io::BufWriter)) or [`Stdout`](std::io::Stdout)
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compile_ast_to, emitter::WriteEmitter};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let parsed = parse_source("print(1)".to_owned(), &filename, &options)?;
+///     let mut emitter = WriteEmitter::new(Vec::new());
+///     compile_ast_to(parsed, &filename, &options, &mut emitter)?;
+///     assert_eq!(emitter.into_inner(), b"print(1);");
+///
+///     Ok(())
+/// }
+/// ```
+pub struct WriteEmitter<W: Write> {
+	writer: W,
+}
+
+impl<W: Write> WriteEmitter<W> {
+	/// Creates a new [`WriteEmitter`] writing to `writer`
+	pub const fn new(writer: W) -> Self {
+		Self { writer }
+	}
+
+	/// Returns the writer, which is not flushed
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+impl<W: Write> CodeEmitter for WriteEmitter<W> {
+	fn emit(&mut self, code: &str) -> Result<(), String> {
+		self.writer
+			.write_all(code.as_bytes())
+			.map_err(|e| e.to_string())
+	}
+}
@@ -0,0 +1,71 @@
+//! The entry module wraps the whole output in the boilerplate a framework expects from the file it loads,
+//! see [`Options::env_wrap`](clue_frontend::env::Options::env_wrap).
+//!
+//! The code is put inside a function, so that its `return` gives the value the framework needs.
+
+use clue_frontend::{env::WrapMode, format_clue};
+
+// the code takes the place of `§`, its varargs are the ones the file was loaded with
+const LOVE2D: &str = "local _callbacks = (function(...)
+§
+end)(...)
+if type(_callbacks) == \"table\" then
+	for name, callback in pairs(_callbacks) do
+		if type(callback) == \"function\" then
+			love[name] = callback
+		end
+	end
+end";
+
+const AMD: &str = "define(function(require, exports, module)
+§
+end)";
+
+/// Wraps `code` in the boilerplate of the given [`WrapMode`]
+///
+/// The lines of the code are moved down by one, which is why it must be called before
+/// the source maps and the tracebacks are made from the output
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::entry::wrap_entry;
+/// use clue_frontend::{env::WrapMode, format_clue};
+///
+/// let code = String::from("return 1");
+/// assert_eq!(wrap_entry(code.clone(), WrapMode::None), code);
+/// assert_eq!(
+///     wrap_entry(code, WrapMode::AMD),
+///     "define(function(require, exports, module)\nreturn 1\nend)"
+/// );
+/// ```
+pub fn wrap_entry(code: String, mode: WrapMode) -> String {
+	match mode {
+		WrapMode::None => code,
+		mode => {
+			let (before, after) = entry_parts(mode);
+			format_clue!(before, code, after)
+		}
+	}
+}
+
+/// Returns the boilerplate of the given [`WrapMode`] which goes before and after the code
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::entry::{entry_parts, wrap_entry};
+/// use clue_frontend::{env::WrapMode, format_clue};
+///
+/// let (before, after) = entry_parts(WrapMode::Love2D);
+/// let code = String::from("return {}");
+/// assert_eq!(before.to_owned() + &code + after, wrap_entry(code, WrapMode::Love2D));
+/// assert_eq!(entry_parts(WrapMode::None), ("", ""));
+/// ```
+pub fn entry_parts(mode: WrapMode) -> (&'static str, &'static str) {
+	let template = match mode {
+		WrapMode::None => return ("", ""),
+		WrapMode::Love2D => LOVE2D,
+		WrapMode::AMD => AMD,
+	};
+	let (before, after) = template.split_once('§').unwrap();
+	(before, after)
+}
@@ -0,0 +1,213 @@
+//! The helpers module is the small runtime some of the compiled code needs.
+//!
+//! Instead of writing the same logic again everywhere it's used, the [`Compiler`](crate::compiler::Compiler)
+//! calls a [`Helper`] and remembers it (see [`Compiler::helpers`](crate::compiler::Compiler::helpers)),
+//! then [`compile_helpers`] defines each needed helper once before the static variables.
+//! When many files are bundled together, their helpers are merged so that they are still defined once.
+//!
+//! The code compiled with [`Options::env_debug`] is also run inside the error handler of
+//! [`add_debug_wrapper`], which reports the Clue file and line its errors come from.
+
+use crate::compiler::quote_lua_string;
+use clue_frontend::{
+	env::{BitwiseMode, Options},
+	format_clue,
+};
+use std::collections::BTreeSet;
+
+/// A function (or library) of the runtime added to the compiled code when it's needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Helper {
+	/// The `_ENV` local set to [`Options::env_global_table`], which comes before the other helpers
+	/// so that they use it too
+	GlobalTable,
+
+	/// `_clue_call`, which runs the functions with [`Options::env_debug`] to report their errors
+	DebugCall,
+
+	/// `_clue_check`, which fails with the given message when a value is nil, see [`Options::env_strict`]
+	StrictCheck,
+
+	/// `clue_profile`, the profiler used by [`Options::env_profile`] when there's no
+	/// [`Options::env_profile_hook`], which adds up the time spent in each function
+	Profiler,
+
+	/// The bit library written in Lua used with [`BitwiseMode::Pure`](clue_frontend::env::BitwiseMode::Pure),
+	/// named after [`Options::env_jitbit`]
+	PureBit,
+}
+
+impl Helper {
+	/// The helpers the compiler can call with the given options, a superset of the ones
+	/// [`Compiler::helpers`](crate::compiler::Compiler::helpers) finds once the code is compiled
+	pub fn available(options: &Options) -> BTreeSet<Self> {
+		[
+			(Self::GlobalTable, options.env_global_table.is_some()),
+			(Self::DebugCall, options.env_debug),
+			(Self::StrictCheck, options.env_strict),
+			(
+				Self::Profiler,
+				options.env_profile && options.env_profile_hook.is_none(),
+			),
+			(
+				Self::PureBit,
+				options.env_bitwise == BitwiseMode::Pure && options.env_jitbit.is_some(),
+			),
+		]
+		.into_iter()
+		.filter_map(|(helper, available)| available.then_some(helper))
+		.collect()
+	}
+
+	/// The name the compiled code uses to call the helper
+	pub fn name(self, options: &Options) -> String {
+		match self {
+			Self::GlobalTable => String::from("_ENV"),
+			Self::DebugCall => String::from("_clue_call"),
+			Self::StrictCheck => String::from("_clue_check"),
+			Self::Profiler => String::from("clue_profile"),
+			Self::PureBit => options
+				.env_jitbit
+				.clone()
+				.unwrap_or_else(|| String::from("bit")),
+		}
+	}
+
+	/// The Lua code defining the helper
+	pub fn code(self, options: &Options) -> String {
+		let name = self.name(options);
+		match self {
+			Self::GlobalTable => match &options.env_global_table {
+				Some(table) if table != "_ENV" => format!("local {name} = {table}\n"),
+				_ => String::new(),
+			},
+			// it's called as a tail call, so the caller of the function is at level 2
+			Self::DebugCall => format!(
+				"local function {name}(source, file, f, ...)
+	local result = {{xpcall(f, function(err)
+		_errored_file = file
+		_clue_error(err, source)
+	end, ...)}}
+	table.remove(result, 1)
+	if _errored then
+		local err, caller = _errored, debug.getinfo(2, \"f\").func
+		if caller == pcall or caller == xpcall then _errored = nil end
+		error(err, 0)
+	end
+	return (unpack or table.unpack)(result)
+end
+"
+			),
+			Self::StrictCheck => format!(
+				"local function {name}(value, message)
+	if value == nil then error(message, 0) end
+	return value
+end
+"
+			),
+			// it's global so that `clue_profile.report()` can be called from anywhere after running the code
+			Self::Profiler => format!(
+				"{name} = {{times = {{}}, calls = {{}}, started = {{}}}}
+function {name}.enter(name)
+	local started = {name}.started
+	started[#started + 1] = os.clock()
+end
+function {name}.exit(name, ...)
+	local time = os.clock() - table.remove({name}.started)
+	{name}.times[name] = ({name}.times[name] or 0) + time
+	{name}.calls[name] = ({name}.calls[name] or 0) + 1
+	return ...
+end
+function {name}.report()
+	local names = {{}}
+	for name in pairs({name}.times) do names[#names + 1] = name end
+	table.sort(names, function(a, b) return {name}.times[a] > {name}.times[b] end)
+	for _, name in ipairs(names) do
+		print(string.format(\"%.6fs\\t%d calls\\t%s\", {name}.times[name], {name}.calls[name], name))
+	end
+end
+"
+			),
+			// the numbers are treated as unsigned 32 bit integers like bit32 does
+			Self::PureBit => format!(
+				"local {name} = {{}}
+function {name}.op(a, b, f)
+	a, b = math.floor(a) % 4294967296, math.floor(b) % 4294967296
+	local r, p = 0, 1
+	for _ = 1, 32 do
+		local x, y = a % 2, b % 2
+		if f(x, y) then r = r + p end
+		a, b, p = (a - x) / 2, (b - y) / 2, p * 2
+	end
+	return r
+end
+function {name}.band(a, b) return {name}.op(a, b, function(x, y) return x + y == 2 end) end
+function {name}.bor(a, b) return {name}.op(a, b, function(x, y) return x + y > 0 end) end
+function {name}.bxor(a, b) return {name}.op(a, b, function(x, y) return x ~= y end) end
+function {name}.bnot(a) return 4294967295 - math.floor(a) % 4294967296 end
+function {name}.lshift(a, n) return math.floor(a) * 2 ^ n % 4294967296 end
+function {name}.rshift(a, n) return math.floor(math.floor(a) % 4294967296 / 2 ^ n) end
+"
+			),
+		}
+	}
+}
+
+/// Defines the given helpers once each, in a stable order, to be put before the static variables
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compiler::Compiler, helpers::compile_helpers};
+/// use clue_frontend::{
+///     env::{LuaVersion, Options},
+///     parse_source,
+/// };
+///
+/// fn main() -> Result<(), String> {
+///     let mut options = Options {
+///         env_target: Some(LuaVersion::Lua51Pure),
+///         ..Options::default()
+///     };
+///     options.preset();
+///     let filename = String::from("file.clue");
+///     let compiler = Compiler::new(&options, &filename);
+///     let (ctokens, _) = parse_source("a = b & c\nd = e | f".to_owned(), &filename, &options)?;
+///     let code = compiler.compile_tokens(0, ctokens)?;
+///     let helpers = compile_helpers(&compiler.helpers(), &options);
+///
+///     assert_eq!(code, "a = bit.band(b, c);\nd = bit.bor(e, f);");
+///     assert_eq!(helpers.matches("local bit = {}").count(), 1);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn compile_helpers(helpers: &BTreeSet<Helper>, options: &Options) -> String {
+	helpers.iter().map(|helper| helper.code(options)).collect()
+}
+
+/// Wraps the code compiled with [`Options::env_debug`] in the function that catches its errors
+/// and raises them again with the line they come from in the file called `name`
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::helpers::add_debug_wrapper;
+///
+/// let code = add_debug_wrapper("_clueline = 1\nerror(\"oops\")", "main.clue");
+/// assert!(code.contains("\t_clueline = 1\n\terror(\"oops\")"));
+/// assert!(code.contains("_errored_file = \"main.clue\""));
+/// ```
+pub fn add_debug_wrapper(code: &str, name: &str) -> String {
+	format!(
+		include_str!("debug.lua"),
+		format_clue!("\t", code.replace('\n', "\n\t")),
+		quote_lua_string(name)
+	)
+}
+
+/// The number of lines [`add_debug_wrapper`] puts before the code
+pub fn debug_wrapper_offset() -> usize {
+	include_str!("debug.lua")
+		.lines()
+		.take_while(|line| *line != "{}")
+		.count()
+}
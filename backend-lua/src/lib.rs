@@ -0,0 +1,124 @@
+//! # The Clue Lua backend
+//! This crate turns the AST produced by the `clue_frontend` crate into Lua code.
+//!
+//! Most users will want to use the `Clue` struct of the `clue_core` crate instead,
+//! which runs the whole compilation process.
+//!
+//! # Stable API
+//! [`compile_ast`], [`compile_ast_to`], the [`compiler::Compiler`] struct and the [`coverage`], [`debuginfo`], [`emitter`], [`entry`], [`helpers`], [`preamble`], [`sourcemap`], [`stats`], [`style`], [`target`], [`traceback`] and [`wrap`] modules are the stable API of the backend,
+//! and it follows the same semver policy as the frontend (see the `clue_frontend` crate).
+//!
+//! # Writing other backends
+//! A backend only needs the frontend: it takes the `(Expression, Expression)` returned by
+//! [`parse_tokens`](clue_frontend::parser::parse_tokens) (the code and its static variables)
+//! and can use the [`visitor`](clue_frontend::parser::visitor) module to walk it.
+//! The options that only make sense for Lua (like [`Options::env_target`]) can be ignored.
+//!
+//! Languages that are close enough to Lua (like Teal) don't need their own backend,
+//! they are a [`target::CodegenTarget`] chosen with [`Options::env_language`].
+
+use clue_frontend::{env::Options, format_clue, parser::Expression};
+use compiler::Compiler;
+use emitter::CodeEmitter;
+use entry::entry_parts;
+use helpers::{compile_helpers, Helper};
+use preamble::compile_preamble;
+use style::OutputStyle;
+
+pub mod compiler;
+pub mod coverage;
+pub mod debuginfo;
+pub mod emitter;
+pub mod entry;
+pub mod helpers;
+pub mod preamble;
+pub mod sourcemap;
+pub mod stats;
+pub mod style;
+pub mod target;
+pub mod traceback;
+pub mod wrap;
+
+/// Compiles the output of [`parse_tokens`](clue_frontend::parser::parse_tokens) into Lua code,
+/// with the [`helpers`] it needs, the static variables and the [`preamble`] at the top,
+/// wrapped in the boilerplate of [`Options::env_wrap`] (see [`entry`])
+///
+/// # Errors
+/// If an error occurs while compiling the code, an [`Err`] containing a [`String`] with the error message will be returned
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::compile_ast;
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let parsed = parse_source("static a = 1\nprint(a)".to_owned(), &filename, &options)?;
+///     assert_eq!(compile_ast(parsed, &filename, &options)?, "local a = 1;\nprint(a);");
+///
+///     Ok(())
+/// }
+/// ```
+pub fn compile_ast(
+	(ctokens, statics): (Expression, Expression),
+	filename: &String,
+	options: &Options,
+) -> Result<String, String> {
+	let compiler = Compiler::new(options, filename);
+	let code = compiler.compile_tokens(0, ctokens)?;
+	let statics = compiler.compile_statics(statics)?;
+	let helpers = compile_helpers(&compiler.helpers(), options);
+	let preamble = compile_preamble(options)?;
+	let (before, after) = entry_parts(options.env_wrap);
+	let code = format_clue!(before, helpers, statics, preamble, code, after);
+	Ok(OutputStyle::new(options).apply(code))
+}
+
+/// Compiles the output of [`parse_tokens`](clue_frontend::parser::parse_tokens) like [`compile_ast`],
+/// writing the result to `emitter` instead of returning one [`String`]
+///
+/// Every statement is given to `emitter` as soon as it's compiled, so the output is never
+/// whole in memory. Since the [`helpers`] come first, every helper the options could need
+/// (see [`Helper::available`](helpers::Helper::available)) is defined, even the ones the code doesn't use.
+/// When the options change the [`style`] of the output, it needs to be laid out whole
+/// and it's given to `emitter` at once.
+///
+/// # Errors
+/// If an error occurs while compiling the code or `emitter` fails, an [`Err`] containing a [`String`] with the error message will be returned
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::compile_ast_to;
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let parsed = parse_source("static a = 1\nprint(a)".to_owned(), &filename, &options)?;
+///     let mut pieces = Vec::new();
+///     compile_ast_to(parsed, &filename, &options, &mut pieces)?;
+///     assert_eq!(pieces.concat(), "local a = 1;\nprint(a);");
+///
+///     Ok(())
+/// }
+/// ```
+pub fn compile_ast_to(
+	(ctokens, statics): (Expression, Expression),
+	filename: &String,
+	options: &Options,
+	emitter: &mut dyn CodeEmitter,
+) -> Result<(), String> {
+	if OutputStyle::new(options) != OutputStyle::default() {
+		return emitter.emit(&compile_ast((ctokens, statics), filename, options)?);
+	}
+	let compiler = Compiler::new(options, filename);
+	let statics = compiler.compile_statics(statics)?;
+	let (before, after) = entry_parts(options.env_wrap);
+	emitter.emit(before)?;
+	emitter.emit(&compile_helpers(&Helper::available(options), options))?;
+	emitter.emit(&statics)?;
+	emitter.emit(&compile_preamble(options)?)?;
+	compiler.emit_tokens(0, ctokens, emitter)?;
+	emitter.emit(after)
+}
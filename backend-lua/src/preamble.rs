@@ -0,0 +1,197 @@
+//! The preamble module checks the Lua code given with
+//! [`Options::env_preamble`](clue_frontend::env::Options::env_preamble),
+//! which is put after the static variables of every output (like `local unpack = table.unpack`).
+//!
+//! The check doesn't run the code: it makes sure the strings, comments and brackets are closed
+//! and every block (`function`, `if`, `do`, `repeat`, ...) ends where it should,
+//! which catches most mistakes before they break the whole output.
+
+use clue_frontend::env::Options;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Block {
+	Bracket(u8),
+	Function,
+	If,
+	/// A `while` or `for` loop before its `do`
+	Loop,
+	Do,
+	Repeat,
+}
+
+impl Block {
+	fn name(self) -> String {
+		match self {
+			Self::Bracket(c) => format!("'{}'", c as char),
+			Self::Function => String::from("'function'"),
+			Self::If => String::from("'if'"),
+			Self::Loop => String::from("the loop"),
+			Self::Do => String::from("'do'"),
+			Self::Repeat => String::from("'repeat'"),
+		}
+	}
+}
+
+// returns the level of the long bracket (`[[`, `[=[`, ...) at the start of `bytes`, if there is one
+fn long_bracket(bytes: &[u8]) -> Option<usize> {
+	let level = bytes.iter().skip(1).take_while(|&&c| c == b'=').count();
+	(bytes.first() == Some(&b'[') && bytes.get(level + 1) == Some(&b'[')).then_some(level)
+}
+
+// skips the long string or comment of the given level starting at `i`, returning where it ends
+fn skip_long(bytes: &[u8], mut i: usize, level: usize, line: &mut usize) -> Result<usize, String> {
+	let close: Vec<u8> = [b']']
+		.into_iter()
+		.chain(std::iter::repeat(b'=').take(level))
+		.chain([b']'])
+		.collect();
+	let start = *line;
+	while i < bytes.len() {
+		if bytes[i..].starts_with(&close) {
+			return Ok(i + close.len());
+		}
+		if bytes[i] == b'\n' {
+			*line += 1;
+		}
+		i += 1;
+	}
+	Err(format!("line {start}: unfinished long string or comment"))
+}
+
+// closes the last block, which must be one of the valid ones
+fn close(
+	blocks: &mut Vec<(Block, usize)>,
+	what: &str,
+	line: usize,
+	valid: &[Block],
+) -> Result<(), String> {
+	match blocks.pop() {
+		Some((block, _)) if valid.contains(&block) => Ok(()),
+		Some((block, start)) => Err(format!(
+			"line {line}: unexpected {what}, {} at line {start} is not closed",
+			block.name()
+		)),
+		None => Err(format!("line {line}: unexpected {what}")),
+	}
+}
+
+// checks that a Lua chunk can be parsed, without checking the expressions
+fn check_lua_syntax(code: &str) -> Result<(), String> {
+	let bytes = code.as_bytes();
+	let mut blocks: Vec<(Block, usize)> = Vec::new();
+	let mut line = 1;
+	let mut i = 0;
+	while i < bytes.len() {
+		let c = bytes[i];
+		match c {
+			b'\n' => line += 1,
+			b'-' if bytes.get(i + 1) == Some(&b'-') => {
+				i = match long_bracket(&bytes[i + 2..]) {
+					Some(level) => skip_long(bytes, i + level + 4, level, &mut line)?,
+					None => i + bytes[i..].iter().take_while(|&&c| c != b'\n').count(),
+				};
+				continue;
+			}
+			b'[' if long_bracket(&bytes[i..]).is_some() => {
+				let level = long_bracket(&bytes[i..]).unwrap_or_default();
+				i = skip_long(bytes, i + level + 2, level, &mut line)?;
+				continue;
+			}
+			b'"' | b'\'' => {
+				i += 1;
+				loop {
+					match bytes.get(i) {
+						Some(b'\\') => {
+							if bytes.get(i + 1) == Some(&b'\n') {
+								line += 1;
+							}
+							i += 1;
+						}
+						Some(&quote) if quote == c => break,
+						None | Some(b'\n') => {
+							return Err(format!("line {line}: unfinished string"))
+						}
+						_ => {}
+					}
+					i += 1;
+				}
+			}
+			b'(' | b'[' | b'{' => blocks.push((Block::Bracket(c), line)),
+			b')' => close(&mut blocks, "')'", line, &[Block::Bracket(b'(')])?,
+			b']' => close(&mut blocks, "']'", line, &[Block::Bracket(b'[')])?,
+			b'}' => close(&mut blocks, "'}'", line, &[Block::Bracket(b'{')])?,
+			c if c.is_ascii_alphanumeric() || c == b'_' => {
+				let len = bytes[i..]
+					.iter()
+					.take_while(|&&c| c.is_ascii_alphanumeric() || c == b'_')
+					.count();
+				let word = &code[i..i + len];
+				match word {
+					"function" => blocks.push((Block::Function, line)),
+					"if" => blocks.push((Block::If, line)),
+					"while" | "for" => blocks.push((Block::Loop, line)),
+					"repeat" => blocks.push((Block::Repeat, line)),
+					"do" => match blocks.last_mut() {
+						Some((block @ Block::Loop, _)) => *block = Block::Do,
+						_ => blocks.push((Block::Do, line)),
+					},
+					"then" | "elseif" | "else"
+						if !matches!(blocks.last(), Some((Block::If, _))) =>
+					{
+						return Err(format!("line {line}: unexpected '{word}'"))
+					}
+					"end" => {
+						let valid = [Block::Function, Block::If, Block::Do];
+						close(&mut blocks, "'end'", line, &valid)?
+					}
+					"until" => close(&mut blocks, "'until'", line, &[Block::Repeat])?,
+					_ => {}
+				}
+				i += len;
+				continue;
+			}
+			_ => {}
+		}
+		i += 1;
+	}
+	match blocks.pop() {
+		Some((block, start)) => Err(format!("{} at line {start} is not closed", block.name())),
+		None => Ok(()),
+	}
+}
+
+/// Returns [`Options::env_preamble`] ending with a new line, to be put after the static variables
+///
+/// # Errors
+/// If the preamble is not valid Lua, an [`Err`] with the problem will be returned
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::preamble::compile_preamble;
+/// use clue_frontend::env::Options;
+///
+/// let mut options = Options {
+///     env_preamble: Some(String::from("local unpack = unpack or table.unpack")),
+///     ..Options::default()
+/// };
+/// assert_eq!(
+///     compile_preamble(&options),
+///     Ok(String::from("local unpack = unpack or table.unpack\n"))
+/// );
+///
+/// options.env_preamble = Some(String::from("local function f()\n\treturn {1, 2}\n"));
+/// assert_eq!(
+///     compile_preamble(&options),
+///     Err(String::from("The preamble is not valid Lua: 'function' at line 1 is not closed"))
+/// );
+/// ```
+pub fn compile_preamble(options: &Options) -> Result<String, String> {
+	let Some(preamble) = &options.env_preamble else {
+		return Ok(String::new());
+	};
+	check_lua_syntax(preamble).map_err(|e| format!("The preamble is not valid Lua: {e}"))?;
+	Ok(match preamble.ends_with('\n') {
+		true => preamble.clone(),
+		false => format!("{preamble}\n"),
+	})
+}
@@ -0,0 +1,157 @@
+//! The sourcemap module turns the line markers the compiler leaves when
+//! [`Options::env_source_map`](clue_frontend::env::Options::env_source_map) is enabled
+//! into a [source map](https://sourcemaps.info/spec.html) (version 3),
+//! so tools like debuggers can find the Clue line of every line of the output.
+//!
+//! The maps only tell the line: every mapped line starts at column 0 of the Clue line.
+
+use crate::traceback::marked_lines;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// appends `value` to the mappings as a base 64 VLQ
+fn push_vlq(mappings: &mut String, value: isize) {
+	let mut vlq = if value < 0 {
+		(value.unsigned_abs() << 1) | 1
+	} else {
+		value.unsigned_abs() << 1
+	};
+	loop {
+		let digit = vlq & 31;
+		vlq >>= 5;
+		let continuation = if vlq > 0 { 32 } else { 0 };
+		mappings.push(BASE64[digit | continuation] as char);
+		if vlq == 0 {
+			break;
+		}
+	}
+}
+
+/// Quotes the string as a JSON string, escaping the characters JSON doesn't allow in it
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::sourcemap::quote_json_string;
+///
+/// assert_eq!(quote_json_string("a \"b\"\n"), "\"a \\\"b\\\"\\n\"");
+/// ```
+pub fn quote_json_string(string: &str) -> String {
+	let mut result = String::with_capacity(string.len() + 2);
+	result.push('"');
+	for c in string.chars() {
+		match c {
+			'"' => result += "\\\"",
+			'\\' => result += "\\\\",
+			'\n' => result += "\\n",
+			c if c.is_control() => result += &format!("\\u{:04x}", c as u32),
+			c => result.push(c),
+		}
+	}
+	result.push('"');
+	result
+}
+
+#[derive(Debug, Clone, Default)]
+/// Builds a source map one line of the output at a time
+pub struct SourceMapBuilder {
+	sources: Vec<String>,
+	mappings: String,
+	lines: usize,
+	// the source index and the (0 based) line of the last mapping, which the next one is relative to
+	last: (usize, usize),
+}
+
+impl SourceMapBuilder {
+	/// Creates a new empty [`SourceMapBuilder`]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds the next line of the output, coming from the given line of the given file if any
+	pub fn add_line(&mut self, location: Option<(&str, usize)>) {
+		if self.lines > 0 {
+			self.mappings.push(';');
+		}
+		self.lines += 1;
+		let Some((source, line)) = location else {
+			return;
+		};
+		let index = match self.sources.iter().position(|s| s == source) {
+			Some(index) => index,
+			None => {
+				self.sources.push(source.to_owned());
+				self.sources.len() - 1
+			}
+		};
+		let line = line.saturating_sub(1);
+		let (last_index, last_line) = self.last;
+		push_vlq(&mut self.mappings, 0);
+		push_vlq(&mut self.mappings, index as isize - last_index as isize);
+		push_vlq(&mut self.mappings, line as isize - last_line as isize);
+		push_vlq(&mut self.mappings, 0);
+		self.last = (index, line);
+	}
+
+	/// Returns the source map as JSON, `file` is the name of the output file if it has one
+	pub fn build(&self, file: Option<&str>) -> String {
+		let sources: Vec<String> = self.sources.iter().map(|s| quote_json_string(s)).collect();
+		let file = match file {
+			Some(file) => format!("\"file\":{},", quote_json_string(file)),
+			None => String::new(),
+		};
+		format!(
+			"{{\"version\":3,{file}\"sources\":[{}],\"names\":[],\"mappings\":{}}}",
+			sources.join(","),
+			quote_json_string(&self.mappings)
+		)
+	}
+}
+
+/// Removes the line markers left by the compiler from the final output (after the statics and
+/// the base for directories have been added), returning the code without them and its source map.
+/// The lines without a marker are mapped to the same line as the closest marked line before them.
+///
+/// `skipped` is the number of lines that will be put before the code, which are left unmapped:
+/// to also add the traceback helper, give 1 and call
+/// [`add_traceback_helper`](crate::traceback::add_traceback_helper) on the code with the markers.
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compile_ast, sourcemap::extract_source_map};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("main.clue");
+///     let options = Options {
+///         env_source_map: true,
+///         ..Options::default()
+///     };
+///     let parsed = parse_source("local a = 1\n\nprint(a)".to_owned(), &filename, &options)?;
+///     let (code, map) = extract_source_map(&compile_ast(parsed, &filename, &options)?, 0);
+///     assert_eq!(code, "local a = 1;\nprint(a);");
+///     assert_eq!(
+///         map.build(Some("main.lua")),
+///         r#"{"version":3,"file":"main.lua","sources":["main.clue"],"names":[],"mappings":"AAAA;AAEA"}"#
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+pub fn extract_source_map(code: &str, skipped: usize) -> (String, SourceMapBuilder) {
+	let mut map = SourceMapBuilder::new();
+	for _ in 0..skipped {
+		map.add_line(None);
+	}
+	let mut result = String::with_capacity(code.len());
+	let mut last = None;
+	for (line, location) in marked_lines(code) {
+		if let Some((file, line)) = location.and_then(|location| location.rsplit_once(':')) {
+			last = line.parse().ok().map(|line| (file, line));
+		}
+		map.add_line(last);
+		result += line;
+		result.push('\n');
+	}
+	result.pop();
+	(result, map)
+}
@@ -0,0 +1,143 @@
+//! The stats module contains [`CompileStats`], what is measured while compiling a file,
+//! and [`stats_report`], which turns the stats of many files into a table
+//! to find which files make the output bigger or take the longest to compile.
+
+use clue_frontend::env::Stage;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// What was measured while compiling a file
+pub struct CompileStats {
+	/// The name of the file
+	pub file: String,
+
+	/// The number of lines of the file, after the preprocessor ran
+	pub input_lines: usize,
+
+	/// The number of tokens found by the scanner, without the one at the end of the file
+	pub tokens: usize,
+
+	/// The number of lines of the compiled code
+	pub output_lines: usize,
+
+	/// The size of the compiled code in bytes
+	pub output_bytes: usize,
+
+	/// How long every stage took, in the order they ran
+	pub times: Vec<(Stage, Duration)>,
+}
+
+impl CompileStats {
+	/// Creates the stats of the file, with nothing measured yet
+	pub fn new(file: impl Into<String>) -> Self {
+		Self {
+			file: file.into(),
+			..Self::default()
+		}
+	}
+
+	/// Runs `f` and adds how long it took to the time of the stage
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_backend_lua::stats::CompileStats;
+	/// use clue_frontend::env::Stage;
+	///
+	/// let mut stats = CompileStats::new("main.clue");
+	/// let sum = stats.time(Stage::Compile, || 1 + 2);
+	/// assert_eq!(sum, 3);
+	/// assert_eq!(stats.times.len(), 1);
+	/// ```
+	pub fn time<T>(&mut self, stage: Stage, f: impl FnOnce() -> T) -> T {
+		let start = Instant::now();
+		let result = f();
+		self.add_time(stage, start.elapsed());
+		result
+	}
+
+	/// Adds `elapsed` to the time of the stage
+	pub fn add_time(&mut self, stage: Stage, elapsed: Duration) {
+		match self.times.iter_mut().find(|(other, _)| *other == stage) {
+			Some((_, time)) => *time += elapsed,
+			None => self.times.push((stage, elapsed)),
+		}
+	}
+
+	/// Adds the compiled code to the size of the output
+	pub fn add_output(&mut self, code: &str) {
+		self.output_lines += code.lines().count();
+		self.output_bytes += code.len();
+	}
+
+	/// Returns how long the stage took, zero if it didn't run
+	pub fn stage_time(&self, stage: Stage) -> Duration {
+		self.times
+			.iter()
+			.filter(|(other, _)| *other == stage)
+			.map(|(_, time)| *time)
+			.sum()
+	}
+}
+
+// the stages shown by the report, the mangling of the names is part of the parsing
+const REPORTED_STAGES: [Stage; 4] = [Stage::Preprocess, Stage::Scan, Stage::Parse, Stage::Compile];
+
+/// Formats the stats of the files as a table, from the file with the biggest output,
+/// with the total of every column in the last row
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::stats::{stats_report, CompileStats};
+///
+/// let mut small = CompileStats::new("small.clue");
+/// small.add_output("print(1);");
+/// let mut big = CompileStats::new("big.clue");
+/// big.add_output("print(1);\nprint(2);");
+/// let report = stats_report(&[small, big]);
+/// let files: Vec<&str> = report
+///     .lines()
+///     .skip(1)
+///     .map(|line| line.split_whitespace().next().unwrap())
+///     .collect();
+/// assert_eq!(files, ["big.clue", "small.clue", "Total"]);
+/// ```
+pub fn stats_report(stats: &[CompileStats]) -> String {
+	let mut sorted: Vec<&CompileStats> = stats.iter().collect();
+	sorted.sort_by(|a, b| {
+		b.output_bytes
+			.cmp(&a.output_bytes)
+			.then(a.file.cmp(&b.file))
+	});
+	let mut total = CompileStats::new("Total");
+	for file in &sorted {
+		total.input_lines += file.input_lines;
+		total.tokens += file.tokens;
+		total.output_lines += file.output_lines;
+		total.output_bytes += file.output_bytes;
+		total.times.extend(file.times.iter().cloned());
+	}
+	let width = sorted
+		.iter()
+		.map(|file| file.file.len())
+		.max()
+		.unwrap_or_default()
+		.max(5);
+	let mut report = format!(
+		"{:width$}  {:>8}  {:>8}  {:>12}  {:>12}",
+		"File", "Lines", "Tokens", "Output lines", "Output bytes"
+	);
+	for stage in REPORTED_STAGES {
+		report += &format!("  {:>15}", format!("{} (ms)", stage.name()));
+	}
+	for file in sorted.into_iter().chain([&total]) {
+		report += &format!(
+			"\n{:width$}  {:>8}  {:>8}  {:>12}  {:>12}",
+			file.file, file.input_lines, file.tokens, file.output_lines, file.output_bytes
+		);
+		for stage in REPORTED_STAGES {
+			let time = file.stage_time(stage).as_secs_f64() * 1000.0;
+			report += &format!("  {time:>15.3}");
+		}
+	}
+	report
+}
@@ -0,0 +1,237 @@
+//! The style module lays out the compiled code the way the options ask for,
+//! for projects which commit the generated Lua and need it to follow their style guide.
+//!
+//! The compiler always indents with tabs and ends the statements with semicolons,
+//! [`OutputStyle::apply`] then changes the final output (after the statics and
+//! the base for directories have been added), leaving strings and comments untouched.
+//! [`minify`] makes the output of `--emit minified` as small as it can be instead.
+
+use crate::wrap::{find_breaks, long_bracket, wrap_lines, State};
+use clue_frontend::env::Options;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How the compiled code is laid out, the default is how the compiler writes it
+pub struct OutputStyle {
+	/// The number of spaces to indent with instead of tabs, see [`Options::env_indent_spaces`]
+	pub indent_spaces: Option<usize>,
+
+	/// Whether to remove the semicolons at the end of the lines, see [`Options::env_omit_semicolons`]
+	pub omit_semicolons: bool,
+
+	/// The most empty lines in a row to keep, see [`Options::env_max_blank_lines`]
+	pub max_blank_lines: Option<usize>,
+
+	/// The column after which the lines are split, see [`Options::env_max_line_length`]
+	pub max_line_length: Option<usize>,
+}
+
+impl OutputStyle {
+	/// Creates the [`OutputStyle`] chosen by the given options
+	pub const fn new(options: &Options) -> Self {
+		Self {
+			indent_spaces: options.env_indent_spaces,
+			omit_semicolons: options.env_omit_semicolons,
+			max_blank_lines: options.env_max_blank_lines,
+			max_line_length: options.env_max_line_length,
+		}
+	}
+
+	/// The style of `--emit minified` before [`minify`]: no indentation, no empty lines and no semicolons,
+	/// except the ones [`minify`] needs to keep the statements apart
+	pub const fn minified() -> Self {
+		Self {
+			indent_spaces: Some(0),
+			omit_semicolons: true,
+			max_blank_lines: Some(0),
+			max_line_length: None,
+		}
+	}
+
+	/// Lays out the code with this style.
+	/// A semicolon is kept when the next line starts with `(`, which would otherwise continue the statement.
+	/// The line markers of [`Options::env_traceback`] stay where they are,
+	/// so it must be called before [`add_traceback_helper`](crate::traceback::add_traceback_helper)
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_backend_lua::style::OutputStyle;
+	///
+	/// let style = OutputStyle {
+	///     indent_spaces: Some(2),
+	///     omit_semicolons: true,
+	///     max_blank_lines: Some(1),
+	///     ..OutputStyle::default()
+	/// };
+	/// let code = "if a then\n\tb = \"x;\";\n\n\n\tf();\n\t(g or h)();\nend";
+	/// assert_eq!(
+	///     style.apply(code.to_owned()),
+	///     "if a then\n  b = \"x;\"\n\n  f();\n  (g or h)()\nend"
+	/// );
+	/// ```
+	pub fn apply(&self, code: String) -> String {
+		if *self == Self::default() {
+			return code;
+		}
+		let indent = match self.indent_spaces {
+			Some(spaces) => " ".repeat(spaces),
+			None => String::from("\t"),
+		};
+		// every line, whether it starts and ends outside strings and comments, and where its code ends
+		let mut state = State::Code;
+		let lines: Vec<(&str, bool, usize, bool)> = code
+			.split('\n')
+			.map(|line| {
+				let starts_in_code = matches!(state, State::Code);
+				let (_, code_end) = find_breaks(line, &mut state);
+				(line, starts_in_code, code_end, matches!(state, State::Code))
+			})
+			.collect();
+		let mut result = String::with_capacity(code.len());
+		let mut blank_lines = 0;
+		for (i, &(line, starts_in_code, code_end, ends_in_code)) in lines.iter().enumerate() {
+			if starts_in_code && line.trim().is_empty() {
+				blank_lines += 1;
+				if self.max_blank_lines.is_some_and(|max| blank_lines > max) {
+					continue;
+				}
+			} else {
+				blank_lines = 0;
+			}
+			let tabs = if starts_in_code && self.indent_spaces.is_some() {
+				line.len() - line.trim_start_matches('\t').len()
+			} else {
+				0
+			};
+			let mut semicolon = None;
+			if self.omit_semicolons && ends_in_code {
+				let code = line[..code_end].trim_end();
+				let next = lines[i + 1..]
+					.iter()
+					.map(|(line, ..)| line.trim_start())
+					.find(|line| !line.is_empty());
+				if code.ends_with(';') && !next.is_some_and(|next| next.starts_with('(')) {
+					semicolon = Some(code.len() - 1);
+				}
+			}
+			for _ in 0..tabs {
+				result += &indent;
+			}
+			match semicolon {
+				Some(semicolon) => {
+					result += &line[tabs..semicolon];
+					result += &line[semicolon + 1..];
+				}
+				None => result += &line[tabs..],
+			}
+			result.push('\n');
+		}
+		result.pop();
+		match self.max_line_length {
+			Some(max_length) => wrap_lines(&result, max_length, &indent),
+			None => result,
+		}
+	}
+}
+
+// whether a space is needed between the end of `before` and the character after it
+// for the tokens around it to stay the same
+fn needs_space(before: &[u8], after: u8) -> bool {
+	let word = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80;
+	let Some(&last) = before.last() else {
+		return false;
+	};
+	// a number goes on while it's followed by letters, digits or dots,
+	// it's the last word when it starts with a digit or follows one and a dot (like `1.e5`)
+	let number = || {
+		let start = before.iter().rposition(|&c| !word(c)).map_or(0, |i| i + 1);
+		before[start].is_ascii_digit()
+			|| (start >= 2 && before[start - 1] == b'.' && before[start - 2].is_ascii_digit())
+	};
+	(word(last) && word(after))
+		|| (after == b'.' && (last == b'.' || (word(last) && number())))
+		|| (last == b'-' && after == b'-')
+		|| (last == b'[' && matches!(after, b'[' | b'='))
+}
+
+// the position after the end of the long string or comment with brackets of the given level
+// which starts at `start`, or the end of the code if it's never closed
+fn long_end(bytes: &[u8], start: usize, level: usize) -> usize {
+	let close = format!("]{}]", "=".repeat(level));
+	bytes[start..]
+		.windows(close.len())
+		.position(|window| window == close.as_bytes())
+		.map_or(bytes.len(), |end| start + end + close.len())
+}
+
+/// Removes the comments and the whitespace that the code doesn't need, putting it all on one line.
+/// A space is only kept between two words or numbers, and where removing it would join
+/// two tokens into another one (like `- -` into a comment), while strings are copied as they are
+/// and a `#!` line at the start stays on its own line.
+/// It should be used on code laid out with [`OutputStyle::minified`], which leaves a semicolon
+/// before the statements starting with `(`, and after the line markers have been removed,
+/// since they are comments
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::style::minify;
+///
+/// let code = "local function add(a, b)\n\t-- adds\n\treturn a - -b .. \" x  y\" .. 0xf .. 1\nend\n--[[ done ]]\nprint(add(1, 2))";
+/// assert_eq!(
+///     minify(code),
+///     "local function add(a,b)return a- -b..\" x  y\"..0xf ..1 end print(add(1,2))"
+/// );
+/// ```
+pub fn minify(code: &str) -> String {
+	let bytes = code.as_bytes();
+	let mut result = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	if bytes.starts_with(b"#!") {
+		i = bytes
+			.iter()
+			.position(|&c| c == b'\n')
+			.map_or(bytes.len(), |end| end + 1);
+		result.extend_from_slice(&bytes[..i]);
+	}
+	let mut spaced = false;
+	while i < bytes.len() {
+		let c = bytes[i];
+		if c.is_ascii_whitespace() {
+			spaced = true;
+			i += 1;
+			continue;
+		}
+		if c == b'-' && bytes.get(i + 1) == Some(&b'-') {
+			i = match long_bracket(&bytes[i + 2..]) {
+				Some(level) => long_end(bytes, i + level + 4, level),
+				None => bytes[i..]
+					.iter()
+					.position(|&c| c == b'\n')
+					.map_or(bytes.len(), |end| i + end),
+			};
+			spaced = true;
+			continue;
+		}
+		if spaced && needs_space(&result, c) {
+			result.push(b' ');
+		}
+		spaced = false;
+		let end = match c {
+			b'"' | b'\'' => {
+				let mut end = i + 1;
+				while end < bytes.len() && bytes[end] != c {
+					end += if bytes[end] == b'\\' { 2 } else { 1 };
+				}
+				(end + 1).min(bytes.len())
+			}
+			b'[' => match long_bracket(&bytes[i..]) {
+				Some(level) => long_end(bytes, i + level + 2, level),
+				None => i + 1,
+			},
+			_ => i + 1,
+		};
+		result.extend_from_slice(&bytes[i..end]);
+		i = end;
+	}
+	// only whole characters are skipped or copied
+	String::from_utf8(result).unwrap()
+}
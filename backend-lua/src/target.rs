@@ -0,0 +1,177 @@
+//! The target module contains the [`CodegenTarget`] trait, which is what the [`Compiler`](crate::compiler::Compiler)
+//! writes differently for each [`Language`] the code can be compiled to.
+//!
+//! Both languages share the whole AST: the types Teal needs come from the annotations
+//! the parser puts before the structs, their functions and the named enums (`---@class`, `---@param`, ...),
+//! which are read into a [`Declaration`] describing the statement after them.
+
+use clue_frontend::env::Language;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// What the annotations before a statement say about it
+pub struct Declaration {
+	/// The name of the struct declared by the statement (`---@class`), with its mixins
+	pub class: Option<(String, Vec<String>)>,
+
+	/// The fields of the struct and their types (`---@field`)
+	pub fields: Vec<(String, String)>,
+
+	/// The parameters of the function, whether they are optional and their types (`---@param`)
+	pub params: Vec<(String, bool, String)>,
+
+	/// The type returned by the function (`---@return`)
+	pub returns: Option<String>,
+
+	/// The type of the variable (`---@type`)
+	pub r#type: Option<String>,
+
+	/// The name of the enum declared by the statement (`---@enum`)
+	pub r#enum: Option<String>,
+}
+
+impl Declaration {
+	/// Adds an annotation (without `---@`) to the declaration, the unknown ones are ignored
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_backend_lua::target::Declaration;
+	///
+	/// let mut declaration = Declaration::default();
+	/// declaration.add("param y? number");
+	/// declaration.add("return Point");
+	/// assert_eq!(
+	///     declaration.params,
+	///     [(String::from("y"), true, String::from("number"))]
+	/// );
+	/// assert_eq!(declaration.returns, Some(String::from("Point")));
+	/// ```
+	pub fn add(&mut self, annotation: &str) {
+		let (kind, rest) = annotation.split_once(' ').unwrap_or((annotation, ""));
+		let (name, luatype) = rest.split_once(' ').unwrap_or((rest, "any"));
+		match kind {
+			"class" => {
+				let (name, parents) = rest.split_once(" : ").unwrap_or((rest, ""));
+				let parents = parents.split(", ").filter(|p| !p.is_empty());
+				self.class = Some((name.to_owned(), parents.map(str::to_owned).collect()));
+			}
+			"field" => self.fields.push((name.to_owned(), luatype.to_owned())),
+			"param" => {
+				let optional = name.ends_with('?');
+				let name = name.trim_end_matches('?').to_owned();
+				self.params.push((name, optional, luatype.to_owned()))
+			}
+			"return" => self.returns = Some(rest.to_owned()),
+			"type" => self.r#type = Some(rest.to_owned()),
+			"enum" => self.r#enum = Some(rest.to_owned()),
+			_ => {}
+		}
+	}
+}
+
+/// The parts of the output that depend on the language the code is compiled to
+pub trait CodegenTarget {
+	/// The extension of the files written in this language
+	fn extension(&self) -> &'static str;
+
+	/// Compiles an annotation put by the parser (without `---@`), if it's kept in the output
+	fn compile_annotation(&self, annotation: &str) -> Option<String>;
+
+	/// Compiles the declaration of the table of a struct, which replaces `local Name = {}`,
+	/// or returns [`None`] to compile it like any other variable.
+	/// `pre` is the indentation of the declaration.
+	fn compile_class(&self, local: bool, declaration: &Declaration, pre: &str) -> Option<String>;
+
+	/// Compiles the type of a variable, a parameter or what a function returns,
+	/// which is put after its name (or after the parameters)
+	fn compile_type(&self, optional: bool, luatype: &str) -> String;
+
+	/// The keyword put before the declaration of a global variable or function
+	fn global_keyword(&self) -> &'static str;
+}
+
+/// Plain Lua, where the annotations stay as EmmyLua comments
+pub struct Lua;
+
+impl CodegenTarget for Lua {
+	fn extension(&self) -> &'static str {
+		"lua"
+	}
+
+	fn compile_annotation(&self, annotation: &str) -> Option<String> {
+		Some(format!("---@{annotation}"))
+	}
+
+	fn compile_class(&self, _: bool, _: &Declaration, _: &str) -> Option<String> {
+		None
+	}
+
+	fn compile_type(&self, _: bool, _: &str) -> String {
+		String::new()
+	}
+
+	fn global_keyword(&self) -> &'static str {
+		""
+	}
+}
+
+/// [Teal](https://github.com/teal-language/tl), where structs are records.
+/// Named enums stay tables, since the type of their table is inferred from its values
+/// and the enums of Teal can only hold strings.
+pub struct Teal;
+
+impl Teal {
+	// the Teal name of a type of the EmmyLua annotations
+	fn type_name(luatype: &str) -> &str {
+		match luatype {
+			"table" => "{any:any}",
+			luatype => luatype,
+		}
+	}
+}
+
+impl CodegenTarget for Teal {
+	fn extension(&self) -> &'static str {
+		"tl"
+	}
+
+	fn compile_annotation(&self, _: &str) -> Option<String> {
+		None
+	}
+
+	fn compile_class(&self, local: bool, declaration: &Declaration, pre: &str) -> Option<String> {
+		let (name, _) = declaration.class.as_ref()?;
+		let mut record = format!("{} record {name}", if local { "local" } else { "global" });
+		for (field, luatype) in &declaration.fields {
+			record += &format!("\n{pre}\t{field}: {}", Self::type_name(luatype));
+		}
+		// every struct is the `__index` of its instances
+		record += &format!("\n{pre}\t__index: {name}");
+		Some(format!("{record}\n{pre}end"))
+	}
+
+	fn compile_type(&self, optional: bool, luatype: &str) -> String {
+		let optional = if optional { "?" } else { "" };
+		format!("{optional}: {}", Self::type_name(luatype))
+	}
+
+	fn global_keyword(&self) -> &'static str {
+		"global "
+	}
+}
+
+/// Returns the [`CodegenTarget`] of the given [`Language`]
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::target::codegen_target;
+/// use clue_frontend::env::Language;
+///
+/// assert_eq!(codegen_target(Language::Lua).extension(), "lua");
+/// assert_eq!(codegen_target(Language::Teal).extension(), "tl");
+/// ```
+pub fn codegen_target(language: Language) -> &'static dyn CodegenTarget {
+	match language {
+		Language::Lua => &Lua,
+		Language::Teal => &Teal,
+	}
+}
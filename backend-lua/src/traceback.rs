@@ -0,0 +1,95 @@
+//! The traceback module adds a `clue_traceback` function to the compiled code,
+//! which works like `debug.traceback` but shows the Clue files and lines instead of the Lua ones.
+//!
+//! When [`Options::env_traceback`](clue_frontend::env::Options::env_traceback) is enabled the compiler
+//! leaves a marker comment before the statements it compiles,
+//! [`add_traceback_helper`] then removes them and turns them into the line map used by the helper.
+
+use crate::compiler::quote_lua_string;
+use clue_frontend::format_clue;
+
+/// The comment the compiler leaves before a statement, followed by `file:line`.
+pub(crate) const TRACEBACK_MARKER: &str = " --@clue:";
+
+/// Splits the code in lines without their markers, each with the `file:line` of its marker if it has one.
+/// A marker on its own line belongs to the next one and doesn't make a line.
+pub(crate) fn marked_lines(code: &str) -> Vec<(&str, Option<&str>)> {
+	let mut lines = Vec::new();
+	let mut pending = None;
+	for line in code.lines() {
+		let (line, location) = match line.rfind(TRACEBACK_MARKER) {
+			Some(marker) => (
+				&line[..marker],
+				Some(&line[marker + TRACEBACK_MARKER.len()..]),
+			),
+			None => (line, None),
+		};
+		if line.trim().is_empty() && location.is_some() {
+			// the marker was on its own line, it belongs to the next one
+			pending = location;
+			continue;
+		}
+		lines.push((line, location.or(pending.take())));
+	}
+	lines
+}
+
+/// Removes the line markers left by the compiler from the final output (after the statics and
+/// the base for directories have been added) and puts the `clue_traceback` helper on its first line.
+///
+/// The helper finds the line it was loaded at when it runs,
+/// so the code can still be wrapped in something else afterwards (like the `--debug` wrapper of the cli).
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::{compile_ast, traceback::add_traceback_helper};
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options {
+///         env_traceback: true,
+///         ..Options::default()
+///     };
+///     let parsed = parse_source("local a = 1".to_owned(), &filename, &options)?;
+///     let code = add_traceback_helper(&compile_ast(parsed, &filename, &options)?);
+///     assert!(code.starts_with("local clue_traceback = "));
+///     assert!(code.contains("[2] = \"(library):1\""));
+///     assert!(code.ends_with("\nlocal a = 1;"));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn add_traceback_helper(code: &str) -> String {
+	let mut map = String::new();
+	let mut result = String::with_capacity(code.len());
+	// the helper itself is the first line
+	for (current, (line, location)) in (2..).zip(marked_lines(code)) {
+		if let Some(location) = location {
+			map += &format_clue!(
+				"[",
+				current.to_string(),
+				"] = ",
+				quote_lua_string(location),
+				", "
+			);
+		}
+		result += line;
+		result.push('\n');
+	}
+	result.pop();
+	format_clue!(
+		"local clue_traceback = debug and (function() ",
+		"local map, source, base = {",
+		map,
+		"}, debug.getinfo(1, \"S\").short_src, debug.getinfo(1, \"l\").currentline - 1 ",
+		"local pattern = source:gsub(\"%p\", \"%%%0\") .. \":(%d+)\" ",
+		"local function remap(line) for i = tonumber(line) - base, 1, -1 do ",
+		"if map[i] then return map[i] end end end ",
+		"return function(msg, level) local traceback = debug.traceback(msg, (level or 1) + 1) ",
+		"if type(traceback) ~= \"string\" then return traceback end ",
+		"return (traceback:gsub(pattern, remap)) end ",
+		"end)() or function(msg) return msg end\n",
+		result
+	)
+}
@@ -0,0 +1,159 @@
+//! The wrap module splits the long lines of the compiled code,
+//! see [`Options::env_max_line_length`](clue_frontend::env::Options::env_max_line_length).
+
+use crate::traceback::TRACEBACK_MARKER;
+use std::iter::once;
+
+/// What the end of the previous line was inside of
+#[derive(Clone, Copy)]
+pub(crate) enum State {
+	Code,
+	/// A quoted string continued with `\` on the next line
+	Quoted(u8),
+	/// A long string or comment, with the level of its brackets
+	Long(usize),
+}
+
+// returns the level of the long bracket (`[[`, `[=[`, ...) at the start of `bytes`, if there is one
+pub(crate) fn long_bracket(bytes: &[u8]) -> Option<usize> {
+	let level = bytes.iter().skip(1).take_while(|&&c| c == b'=').count();
+	(bytes.first() == Some(&b'[') && bytes.get(level + 1) == Some(&b'[')).then_some(level)
+}
+
+fn width(code: &str) -> usize {
+	code.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+// finds where the line can be split, as the position and the number of spaces replaced by the split,
+// and where its code ends (the start of a comment which ends the line)
+pub(crate) fn find_breaks(line: &str, state: &mut State) -> (Vec<(usize, usize)>, usize) {
+	let bytes = line.as_bytes();
+	let mut breaks = Vec::new();
+	let mut indented = false;
+	let mut i = 0;
+	while i < bytes.len() {
+		let c = bytes[i];
+		match *state {
+			State::Long(level) if c == b']' => {
+				let close = bytes[i + 1..].iter().take_while(|&&c| c == b'=').count();
+				if close == level && bytes.get(i + level + 1) == Some(&b']') {
+					*state = State::Code;
+					i += level + 1;
+				}
+			}
+			State::Long(_) => {}
+			State::Quoted(_) if c == b'\\' => i += 1,
+			State::Quoted(quote) if c == quote => *state = State::Code,
+			State::Quoted(_) => {}
+			State::Code => match c {
+				b'"' | b'\'' => *state = State::Quoted(c),
+				b'[' => {
+					if let Some(level) = long_bracket(&bytes[i..]) {
+						*state = State::Long(level);
+						i += level + 1;
+					}
+				}
+				b'-' if bytes.get(i + 1) == Some(&b'-') => match long_bracket(&bytes[i + 2..]) {
+					Some(level) => {
+						*state = State::Long(level);
+						i += level + 3;
+					}
+					None => return (breaks, i),
+				},
+				b' ' | b'\t' if !indented => {}
+				b' ' => {
+					let spaces = bytes[i..].iter().take_while(|&&c| c == b' ').count();
+					// a line starting with ( would be ambiguous in Lua 5.1
+					if !matches!(bytes.get(i + spaces), None | Some(b'(')) {
+						breaks.push((i, spaces));
+					}
+					i += spaces;
+					continue;
+				}
+				b',' | b';' => {
+					indented = true;
+					if !matches!(bytes.get(i + 1), None | Some(b' ' | b'(')) {
+						breaks.push((i + 1, 0));
+					}
+				}
+				_ => indented = true,
+			},
+		}
+		i += 1;
+	}
+	(breaks, bytes.len())
+}
+
+/// Splits the lines of `code` longer than `max_length` columns (with tabs counting as 4)
+/// on the spaces and after the commas and semicolons which are not inside strings or comments,
+/// indenting the continuation lines by one more `indent` than the line they come from.
+/// Lines which can't be split safely are left as they are.
+///
+/// The line markers of [`Options::env_traceback`](clue_frontend::env::Options::env_traceback)
+/// are kept on the first part of their line, so it must be called before
+/// [`add_traceback_helper`](crate::traceback::add_traceback_helper).
+///
+/// # Example
+/// ```rust
+/// use clue_backend_lua::wrap::wrap_lines;
+///
+/// let code = "\tlocal a, b = \"a long string\", {1,2,3}";
+/// assert_eq!(
+///     wrap_lines(code, 20, "\t"),
+///     "\tlocal a, b =\n\t\t\"a long string\",\n\t\t{1,2,3}"
+/// );
+/// ```
+pub fn wrap_lines(code: &str, max_length: usize, indent: &str) -> String {
+	let mut result = String::with_capacity(code.len() + code.len() / max_length.max(1));
+	let mut state = State::Code;
+	for line in code.split('\n') {
+		let (breaks, code_end) = find_breaks(line, &mut state);
+		if width(line) <= max_length || breaks.is_empty() {
+			result += line;
+			result.push('\n');
+			continue;
+		}
+		// the line's traceback marker goes with its first part, other comments with the last one
+		let marked = code_end > 0
+			&& line.as_bytes()[code_end - 1..].starts_with(TRACEBACK_MARKER.as_bytes());
+		let code_end = if marked { code_end - 1 } else { code_end };
+		let line_indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+		let mut fragments = Vec::new();
+		let mut start = 0;
+		let mut last = None;
+		for (at, spaces) in breaks
+			.into_iter()
+			.filter(|&(at, spaces)| at + spaces < code_end)
+			.chain(once((code_end, 0)))
+		{
+			if let Some((last_at, last_spaces)) = last {
+				let prefix = if fragments.is_empty() {
+					0
+				} else {
+					width(line_indent) + width(indent)
+				};
+				if prefix + width(&line[start..at]) > max_length {
+					fragments.push(&line[start..last_at]);
+					start = last_at + last_spaces;
+				}
+			}
+			last = Some((at, spaces));
+		}
+		fragments.push(&line[start..code_end]);
+		let last = fragments.len() - 1;
+		for (i, fragment) in fragments.into_iter().enumerate() {
+			if i > 0 {
+				result.push('\n');
+				result += line_indent;
+				result += indent;
+			}
+			result += fragment;
+			if i == if marked { 0 } else { last } {
+				result += &line[code_end..];
+			}
+		}
+		result.push('\n');
+	}
+	result.pop();
+	result
+}
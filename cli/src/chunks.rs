@@ -0,0 +1,70 @@
+use clue_core::format_clue;
+
+// returns where the top level statements and the modules of a bundle start
+fn boundaries(code: &str) -> impl Iterator<Item = usize> + '_ {
+	code.match_indices('\n').map(|(i, _)| i + 1).filter(|&i| {
+		let line = &code[i..];
+		line.starts_with("\t[\"") || line.starts_with(|c: char| !c.is_whitespace())
+	})
+}
+
+/// Splits the compiled code into chunks of at most `max_bytes` bytes each,
+/// only between top level statements or between the modules of a bundle.
+/// Returns the loader, which loads the chunks (called `<name>_1`, `<name>_2`, ...) in order
+/// with `require` and runs the code they make up, and the chunks themselves
+pub fn split_chunks(
+	code: &str,
+	name: &str,
+	max_bytes: usize,
+) -> Result<(String, Vec<String>), String> {
+	let level = (0..)
+		.map(|n| "=".repeat(n))
+		.find(|level| !code.contains(&format_clue!("]", level, "]")))
+		.unwrap();
+	// the first newline of a long string is skipped, so chunks that start with one keep it
+	let open = format_clue!("return [", level, "[\n");
+	let close = format_clue!("]", level, "]\n");
+	let wrapper = open.len() + close.len();
+	if max_bytes <= wrapper {
+		return Err(format!(
+			"The maximum chunk size must be bigger than {wrapper} bytes"
+		));
+	}
+	let budget = max_bytes - wrapper;
+	let too_big = |start: usize| {
+		format!(
+			"The statement at line {} of the output is bigger than the maximum chunk size",
+			code[..start].matches('\n').count() + 1
+		)
+	};
+	let mut chunks = Vec::new();
+	let mut start = 0;
+	let mut end = 0;
+	for boundary in boundaries(code).chain([code.len()]) {
+		if boundary - start > budget {
+			if end == start {
+				return Err(too_big(start));
+			}
+			chunks.push(format_clue!(open, &code[start..end], close));
+			start = end;
+			if boundary - start > budget {
+				return Err(too_big(start));
+			}
+		}
+		end = boundary;
+	}
+	if end > start {
+		chunks.push(format_clue!(open, &code[start..end], close));
+	}
+	let loader = format!(
+		"local chunks = {{}}\nfor i = 1, {} do\n\tchunks[i] = require(\"{name}_\" .. i)\nend\nreturn assert((loadstring or load)(table.concat(chunks), \"={name}\"))(...)\n",
+		chunks.len()
+	);
+	if loader.len() > max_bytes {
+		return Err(format!(
+			"The loader of the chunks is bigger than the maximum chunk size ({} bytes)",
+			loader.len()
+		));
+	}
+	Ok((loader, chunks))
+}
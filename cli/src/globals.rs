@@ -0,0 +1,121 @@
+use clue_core::env::TargetEnv;
+use clue_core::scanner::{Token, TokenType::*};
+
+/// A global function or variable defined by a file of a bundle
+#[derive(Debug, Clone)]
+pub struct GlobalDefinition {
+	pub name: String,
+	pub file: String,
+	pub line: usize,
+	pub column: usize,
+	/// Whether the definition is marked with `@override`, allowing it to replace another file's
+	pub overrides: bool,
+}
+
+/// Finds the global functions, enums, structs and variables declared with `global` in the tokens
+pub fn find_global_definitions(tokens: &[Token], file: &str) -> Vec<GlobalDefinition> {
+	let mut definitions = Vec::new();
+	let mut overrides = false;
+	let mut i = 0;
+	while i < tokens.len() {
+		let token = &tokens[i];
+		i += 1;
+		match token.kind {
+			AT if tokens
+				.get(i)
+				.is_some_and(|t| t.kind == IDENTIFIER && t.lexeme == "override") =>
+			{
+				overrides = true;
+				i += 1;
+			}
+			GLOBAL => {
+				if tokens.get(i).is_some_and(|t| t.kind == CONST) {
+					i += 1;
+				}
+				let names = match tokens.get(i).map(|t| t.kind) {
+					Some(FN | ENUM | STRUCT) => {
+						i += 1;
+						tokens
+							.get(i)
+							.filter(|t| t.kind == IDENTIFIER)
+							.into_iter()
+							.collect()
+					}
+					_ => {
+						let mut names = Vec::new();
+						while let Some(t) = tokens.get(i).filter(|t| t.kind == IDENTIFIER) {
+							names.push(t);
+							i += 1;
+							if tokens.get(i).map_or(true, |t| t.kind != COMMA) {
+								break;
+							}
+							i += 1;
+						}
+						names
+					}
+				};
+				definitions.extend(names.into_iter().map(|t| GlobalDefinition {
+					name: t.lexeme.clone(),
+					file: file.to_owned(),
+					line: t.line,
+					column: t.column,
+					overrides,
+				}));
+				overrides = false;
+			}
+			LOCAL | STATIC | METHOD => overrides = false,
+			_ => {}
+		}
+	}
+	definitions
+}
+
+/// Warns about the globals defined by the code which replace the ones of the environment it runs in
+pub fn check_environment_globals(definitions: &[GlobalDefinition], environment: TargetEnv) {
+	for definition in definitions {
+		if environment.defines_global(&definition.name) && !definition.overrides {
+			eprintln!(
+				"Warning: global '{}' defined in \"{}\" at {}:{} replaces the one of {:?}",
+				definition.name, definition.file, definition.line, definition.column, environment
+			);
+		}
+	}
+}
+
+/// Checks that no two files define the same global, since the file which runs last would silently
+/// replace the other's definition. A definition marked with `@override` only gives a warning
+pub fn check_global_definitions(mut definitions: Vec<GlobalDefinition>) -> Result<(), String> {
+	definitions.sort_by(|a, b| (&a.name, &a.file).cmp(&(&b.name, &b.file)));
+	let mut duplicates = 0;
+	for (i, definition) in definitions.iter().enumerate() {
+		let Some(first) = definitions[..i]
+			.iter()
+			.rev()
+			.take_while(|first| first.name == definition.name)
+			.find(|first| first.file != definition.file)
+		else {
+			continue;
+		};
+		let message = format!(
+			"global '{}' is defined both in \"{}\" at {}:{} and in \"{}\" at {}:{}",
+			definition.name,
+			first.file,
+			first.line,
+			first.column,
+			definition.file,
+			definition.line,
+			definition.column
+		);
+		if definition.overrides || first.overrides {
+			eprintln!("Warning: {message}");
+		} else {
+			eprintln!("Error: {message}");
+			duplicates += 1;
+		}
+	}
+	match duplicates {
+		0 => Ok(()),
+		1 => Err(String::from("1 global is defined by more than one file!")),
+		n => Err(format!("{n} globals are defined by more than one file!")),
+	}
+}
@@ -1,36 +1,172 @@
 #![allow(clippy::blocks_in_conditions)]
 
-use clap::{crate_version, Parser};
+use chunks::split_chunks;
+use clap::{crate_version, Parser, Subcommand, ValueEnum};
 use clue_core::{
-	check,
+	check, compile_ast_to,
 	compiler::*,
-	env::{BitwiseMode, ContinueMode, LuaVersion, Options},
+	coverage::{covered_lines, lcov},
+	debuginfo::{extract_debug_info, DebugInfo},
+	emitter::{CodeEmitter, WriteEmitter},
+	entry::wrap_entry,
+	env::{
+		BitwiseMode, ContinueMode, EnumMode, Language, LuaVersion, NamedArgsMode, Options, Stage,
+		TargetEnv, WrapMode,
+	},
+	features::{analyze_tokens, FeatureSet},
 	format_clue,
+	helpers::{add_debug_wrapper, compile_helpers, debug_wrapper_offset, Helper},
+	optimize::mangle_names,
 	parser::*,
+	passes::PassManager,
+	preamble::compile_preamble,
 	preprocessor::*,
 	scanner::*,
+	sourcemap::{extract_source_map, SourceMapBuilder},
+	stats::{stats_report, CompileStats},
+	style::{minify, OutputStyle},
+	target::codegen_target,
+	traceback::add_traceback_helper,
 };
-use std::{fs, path::PathBuf, time::Instant};
-use threads::compile_folder;
+use globals::{check_environment_globals, find_global_definitions, GlobalDefinition};
+use precompile::precompile;
+use runner::write_self_runner;
+use scaffold::{init_project, new_project, project_arguments, Template};
+use std::{
+	collections::BTreeSet,
+	ffi::OsString,
+	fs,
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+	sync::Mutex,
+	thread,
+	time::{Duration, Instant},
+};
+use symbols::symbols_to_json;
+use threads::{check_for_files, compile_folder, compile_folder_files};
+use trace::view_trace;
 
 #[cfg(feature = "rpmalloc")]
 #[global_allocator]
 /// The best memory allocator available for Clue
 static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
 
+// the stats of the compiled files, which are compiled by many threads when compiling a folder
+static STATS: Mutex<Vec<CompileStats>> = Mutex::new(Vec::new());
+
+mod chunks;
+mod globals;
+mod precompile;
+mod runner;
+mod scaffold;
+mod symbols;
 mod threads;
+mod trace;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[clap(rename_all = "verbatim")]
+/// What the compiler should write in the output file
+enum Emit {
+	#[clap(name = "lua")]
+	/// The compiled Lua code
+	Lua,
+
+	#[clap(name = "ast")]
+	/// The parsed syntax tree as JSON (single files only)
+	Ast,
+
+	#[clap(name = "self-runner")]
+	/// An executable which runs the compiled Lua code, a copy of Clue with the code inside it
+	/// when it can run it with its interpreter or a shell script running Lua otherwise
+	SelfRunner,
+
+	#[clap(name = "minified")]
+	/// The compiled Lua code without indentation, empty lines and semicolons, saved as <OUTPUT>.min.lua
+	Minified,
+
+	#[clap(name = "map")]
+	/// The source map of the compiled Lua code, saved next to it as <OUTPUT>.lua.map (like --source-map)
+	Map,
+
+	#[clap(name = "symbols")]
+	/// The globals and the locals of the code as JSON, saved as <OUTPUT>.symbols.json (single files only)
+	Symbols,
+}
+
+#[derive(Subcommand)]
+/// Commands which do something else than compiling
+enum Command {
+	/// Create a new project in a new directory
+	New {
+		/// The path of the directory to create, its name is used as the name of the project
+		path: PathBuf,
+
+		/// The template to create the project from
+		#[clap(
+			long,
+			value_enum,
+			ignore_case(true),
+			default_value = "lua",
+			value_name = "TEMPLATE"
+		)]
+		template: Template,
+	},
+
+	/// Create a new project in an existing directory
+	Init {
+		/// The path of the directory, its name is used as the name of the project
+		#[clap(default_value = ".")]
+		path: PathBuf,
+
+		/// The template to create the project from
+		#[clap(
+			long,
+			value_enum,
+			ignore_case(true),
+			default_value = "lua",
+			value_name = "TEMPLATE"
+		)]
+		template: Template,
+	},
+
+	/// Compile a project created by "clue new" or "clue init" as its clue.toml says
+	Build {
+		/// The directory of the project, which contains clue.toml
+		#[clap(default_value = ".")]
+		path: PathBuf,
+	},
+
+	/// Show a parse trace written by --trace-parse, indented by the depth of each event
+	Trace {
+		/// The trace file (<FILE NAME>.trace.txt)
+		path: PathBuf,
+
+		/// Only show the events of this line of code
+		#[clap(long)]
+		line: Option<usize>,
+
+		/// Hide the events nested deeper than this
+		#[clap(long, value_name = "DEPTH")]
+		max_depth: Option<usize>,
+	},
+}
 
 #[derive(Parser)]
 #[clap(
 	version,
+	args_conflicts_with_subcommands = true,
+	subcommand_negates_reqs = true,
 	about = "C/Rust like programming language that compiles into Lua code\nMade by Maiori\nhttps://github.com/ClueLang/Clue",
 	long_about = None
 )]
 struct Cli {
+	#[clap(subcommand)]
+	command: Option<Command>,
+
 	/// The path to the directory where the *.clue files are located.
 	/// Every directory inside the given directory will be checked too.
 	/// If the path points to a single *.clue file, only that file will be compiled.
-	#[clap(required_unless_present = "license")]
+	#[clap(required_unless_present_any = ["license", "grammar", "emit-token-spec"])]
 	path: Option<PathBuf>,
 
 	/// The name the output file will have
@@ -43,6 +179,14 @@ struct Cli {
 	#[clap(short = 'L', long, display_order = 1000)]
 	license: bool,
 
+	/// Print the catalog of the tokens and of the AST nodes of Clue as JSON
+	#[clap(long, display_order = 1000)]
+	grammar: bool,
+
+	/// Print every keyword, symbol and literal form of Clue with its highlighting category as JSON
+	#[clap(long, display_order = 1000)]
+	emit_token_spec: bool,
+
 	/// Print list of detected tokens in compiled files
 	#[clap(long)]
 	tokens: bool,
@@ -59,6 +203,10 @@ struct Cli {
 	#[clap(short = 'E', long)]
 	expand: bool,
 
+	/// Print the size, the number of tokens and the compile time of every file, biggest output first
+	#[clap(long)]
+	stats: bool,
+
 	/// Use LuaJIT's bit library for bitwise operations
 	#[clap(
 		short,
@@ -91,6 +239,41 @@ struct Cli {
 	)]
 	r#continue: ContinueMode,
 
+	/// Change the way named arguments in function calls (like f(x = 1)) are passed
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		default_value = "table",
+		value_name = "MODE"
+	)]
+	named_args: NamedArgsMode,
+
+	/// Change the way named enums are compiled, a single enum can choose another one
+	/// with the @table, @reverse or @inline attribute
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		default_value = "table",
+		value_name = "MODE"
+	)]
+	enums: EnumMode,
+
+	/// Put EmmyLua annotations (---@class, ---@field, ---@param, ...) before the compiled structs and enums
+	#[clap(long)]
+	annotations: bool,
+
+	/// Change the language of the output, teal writes .tl files where structs are records
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		default_value = "lua",
+		value_name = "LANGUAGE"
+	)]
+	language: Language,
+
 	/// Don't save compiled code
 	#[clap(short = 'D', long)]
 	dontsave: bool,
@@ -103,14 +286,109 @@ struct Cli {
 	#[clap(short, long)]
 	rawsetglobals: bool,
 
-	/// Add debug information in output (might slow down runtime)
+	/// Read and declare the globals in this table through an _ENV local (Lua 5.2 and later),
+	/// which can sandbox the output
+	#[clap(long, value_name = "TABLE")]
+	global_table: Option<String>,
+
+	/// Turn the globals declared at the top level of a file into locals returned at its end,
+	/// so that the output can be required as a module
+	#[clap(long)]
+	exports: bool,
+
+	/// Wrap the output in the boilerplate of a framework: love2d gives the functions in the table
+	/// returned by the code to LÖVE as its callbacks, amd makes the code the factory of a define
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		default_value = "none",
+		value_name = "FRAMEWORK"
+	)]
+	wrap: WrapMode,
+
+	/// Add debug information in output (might slow down runtime) and write it to <OUTPUT>.debug.json
 	#[clap(short, long)]
 	debug: bool,
 
+	/// Check at runtime that the values being indexed and the results of the calls used in
+	/// arithmetic are not nil, reporting the Clue file and line (slows down runtime)
+	#[clap(long)]
+	strict: bool,
+
+	/// Add a clue_traceback function to the output which works like debug.traceback but shows Clue files and lines
+	#[clap(long)]
+	traceback: bool,
+
+	/// Write a source map (<OUTPUT>.map) which maps the lines of the output to the Clue lines they come from
+	#[clap(long)]
+	source_map: bool,
+
+	/// Call HOOK (__clue_cov by default) with the Clue file and line before the statements and blocks
+	/// and write the lines it's called for as an lcov tracefile (<OUTPUT>.lcov)
+	#[clap(
+		long,
+		min_values = 0,
+		require_equals = true,
+		default_missing_value = "__clue_cov",
+		value_name = "HOOK"
+	)]
+	coverage: Option<String>,
+
+	/// Call clue_profile.enter and clue_profile.exit around every function, adding up the time spent
+	/// in each one (call clue_profile.report() to print it)
+	#[clap(long)]
+	profile: bool,
+
+	/// Use this table's enter(name) and exit(name, ...) functions to profile instead of clue_profile
+	#[clap(long, requires = "profile", value_name = "TABLE")]
+	profile_hook: Option<String>,
+
+	/// Define a preprocessor variable (like LOG_LEVEL=info), can be used multiple times
+	#[clap(long, value_name = "NAME=VALUE")]
+	define: Vec<String>,
+
+	/// Don't compile, report the language features used and the minimum Lua version they need
+	#[clap(long)]
+	infer_target: bool,
+
+	/// Compile the file again whenever it changes, unless only its comments and whitespace changed
+	#[clap(long)]
+	watch: bool,
+
+	/// Put the Lua code of this file after the static variables of the output
+	#[clap(long, value_name = "FILE NAME")]
+	preamble: Option<PathBuf>,
+
 	/// Use a custom Lua file as base for compiling the directory
 	#[clap(short = 'B', long, value_name = "FILE NAME")]
 	base: Option<String>,
 
+	/// Compile the directory to a single file which doesn't need the Lua files around it:
+	/// require loads the modules of the bundle from it and import of other modules uses require
+	#[clap(long, conflicts_with = "base")]
+	bundle: bool,
+
+	/// When compiling a directory, name its files by their path relative to it in the output,
+	/// so that the output is the same wherever the directory is
+	#[clap(long)]
+	reproducible: bool,
+
+	/// When compiling a directory, write every file to its own Lua file with the same path under DIR
+	/// instead of putting them all in one file (give the directory itself to write them next to the Clue files)
+	#[clap(
+		long,
+		value_name = "DIR",
+		conflicts_with("bundle"),
+		conflicts_with("base"),
+		conflicts_with("outputname"),
+		conflicts_with("precompile"),
+		conflicts_with("dontsave"),
+		conflicts_with("debug"),
+		conflicts_with("wrap")
+	)]
+	outdir: Option<PathBuf>,
+
 	/// Uses preset configuration based on the targeted Lua version
 	#[clap(
 		short,
@@ -124,9 +402,27 @@ struct Cli {
 	)]
 	target: Option<LuaVersion>,
 
+	/// Uses the preset configuration of the program the code runs in,
+	/// and warns when the code defines a global it already has
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		conflicts_with("target"),
+		conflicts_with("bitwise"),
+		conflicts_with("jitbit"),
+		conflicts_with("continue"),
+		value_name = "ENVIRONMENT"
+	)]
+	target_env: Option<TargetEnv>,
+
 	/// Change OS checked by @ifos
 	#[clap(long, default_value = std::env::consts::OS, value_name = "TARGET OS")]
 	targetos: String,
+
+	/// Change architecture checked by @ifarch
+	#[clap(long, default_value = std::env::consts::ARCH, value_name = "TARGET ARCH")]
+	targetarch: String,
 	/*/// This is not yet supported (Coming out in 4.0)
 	#[clap(short, long, value_name = "MODE")]
 	types: Option<String>,*/
@@ -151,6 +447,112 @@ struct Cli {
 		)]
 		std: LuaSTD,
 	*/
+	/// Choose what to output: the compiled Lua code, the syntax tree as JSON and more,
+	/// more than one can be given separated by commas (like lua,minified,map,symbols) to compile only once
+	#[clap(
+		long,
+		value_enum,
+		ignore_case(true),
+		value_delimiter = ',',
+		default_value = "lua",
+		value_name = "KIND"
+	)]
+	emit: Vec<Emit>,
+
+	/// Write the result of the given compilation stage to <FILE NAME>.<STAGE>.txt, can be used multiple times
+	#[clap(long, value_enum, ignore_case(true), value_name = "STAGE")]
+	dump_after: Vec<Stage>,
+
+	/// Write every decision of the parser to <FILE NAME>.trace.txt, even if parsing fails,
+	/// which can be read with "clue trace"
+	#[clap(long)]
+	trace_parse: bool,
+
+	/// Fold constant expressions (like 1 + 2) and remove the branches that can never run
+	#[clap(long)]
+	fold_constants: bool,
+
+	/// Run the const { ... } blocks while compiling, in a sandbox with a limit on the instructions
+	/// and the memory they can use, and write the value they return in the output
+	#[clap(long)]
+	const_eval: bool,
+
+	/// Stop the const { ... } blocks of a file run by --const-eval after N instructions
+	#[clap(long, value_name = "N", requires = "const-eval")]
+	const_max_instructions: Option<u64>,
+
+	/// Stop the const { ... } blocks of a file run by --const-eval once they use more than BYTES bytes of memory
+	#[clap(long, value_name = "BYTES", requires = "const-eval")]
+	const_max_memory: Option<usize>,
+
+	/// Only let the const { ... } blocks run by --const-eval use these global functions and values
+	/// besides the string, math, table and bit libraries, separated by commas
+	#[clap(
+		long,
+		value_delimiter = ',',
+		value_name = "NAMES",
+		requires = "const-eval"
+	)]
+	const_globals: Option<Vec<String>>,
+
+	/// Replace the calls to local functions which only return a simple expression with that expression
+	#[clap(long)]
+	inline_functions: bool,
+
+	/// Rename the local variables to short names, "--dump-after mangle" writes what they were called
+	#[clap(long)]
+	mangle_names: bool,
+
+	/// When the code uses syntax from a newer version of Clue (like extern),
+	/// fail with a single error telling which version it needs
+	#[clap(long)]
+	forward_compat: bool,
+
+	/// Make chained comparisons like 0 <= x < 10 mean 0 <= x and x < 10,
+	/// evaluating the operand in the middle only once
+	#[clap(long)]
+	chained_comparisons: bool,
+
+	/// Compile match blocks comparing the value with at least CASES different literals
+	/// to a table of functions indexed by them instead of an if chain
+	#[clap(long, value_name = "CASES")]
+	match_dispatch: Option<usize>,
+
+	/// When compiling a folder, run the static variables of each file when the file is first imported
+	/// instead of when the program starts, they are then only visible in their own file
+	#[clap(long)]
+	lazy_modules: bool,
+
+	/// Split the output into files of at most BYTES bytes each (<OUTPUT>_1.lua, <OUTPUT>_2.lua, ...),
+	/// which the output file loads in order with require
+	#[clap(long, value_name = "BYTES")]
+	max_chunk_bytes: Option<usize>,
+
+	/// Split the lines of the output longer than COLUMNS where it doesn't change the meaning of the code,
+	/// for tools which don't handle long lines well
+	#[clap(long, value_name = "COLUMNS")]
+	max_line_length: Option<usize>,
+
+	/// Indent the output with N spaces instead of tabs
+	#[clap(long, value_name = "N")]
+	indent_spaces: Option<usize>,
+
+	/// Remove the semicolons at the end of the statements of the output when they are not needed
+	#[clap(long)]
+	omit_semicolons: bool,
+
+	/// Never put more than N empty lines in a row in the output
+	#[clap(long, value_name = "N")]
+	max_blank_lines: Option<usize>,
+
+	/// Also compile the output to bytecode (<OUTPUT>.luac), which fails if the target can't load it
+	#[clap(long, conflicts_with = "dontsave")]
+	precompile: bool,
+
+	/// The command used by --precompile [default: luac, luajit when targeting LuaJIT]
+	#[clap(long, requires = "precompile", value_name = "COMMAND")]
+	luac: Option<String>,
+
 	#[cfg(feature = "mlua")]
 	/// Execute the output Lua code once it's compiled
 	#[clap(short, long)]
@@ -162,37 +564,119 @@ struct Cli {
 	symbols: bool,
 }
 
-pub fn compile_code(
+fn dump(
+	options: &Options,
+	stage: Stage,
+	name: &str,
+	result: impl FnOnce() -> String,
+) -> Result<(), String> {
+	if options.env_dump.contains(&stage) {
+		check!(fs::write(
+			format_clue!(name, ".", stage.name(), ".txt"),
+			result()
+		));
+	}
+	Ok(())
+}
+
+fn format_ast((ctokens, statics): &(Expression, Expression)) -> String {
+	format!("{ctokens:#?}\n\nStatic variables:\n{statics:#?}")
+}
+
+// preprocesses, scans and parses the code, which is the work shared by every kind of output,
+// and finds the globals it defines
+fn parse_code(
 	codes: PPCode,
 	variables: &PPVars,
+	mut remapping: Remapping,
 	name: &String,
-	scope: usize,
 	options: &Options,
-) -> Result<(String, String), String> {
-	let time = Instant::now();
-	let code = preprocess_codes(0, codes, variables, name)?;
+	stats: &mut CompileStats,
+) -> Result<((Expression, Expression), Vec<GlobalDefinition>), String> {
+	let code = stats.time(Stage::Preprocess, || {
+		preprocess_codes(0, codes, variables, &mut remapping, name)
+	})?;
 	if options.env_expand {
-		println!("Preprocessed file \"{name}\":\n{}", code.to_string());
+		println!("Preprocessed file \"{name}\":\n{code}");
 	}
-	let tokens: Vec<Token> = scan_code(code, name)?;
+	dump(options, Stage::Preprocess, name, || code.to_string())?;
+	let tokens: Vec<Token> = stats.time(Stage::Scan, || {
+		scan_code_with_options(code, name, &remapping, options)
+	})?;
+	stats.input_lines = tokens.last().map_or(0, |token| token.line);
+	stats.tokens = tokens.len() - 1;
 	if options.env_tokens {
 		println!("Scanned tokens of file \"{name}\":\n{tokens:#?}");
 	}
-	let (ctokens, statics) = parse_tokens(
-		tokens,
-		/*if flag!(env_types) != TypesMode::NONE {
-			Some(AHashMap::default())
-		} else {
-			None
-		},*/
-		name, options,
-	)?;
+	dump(options, Stage::Scan, name, || format!("{tokens:#?}"))?;
+	let globals = find_global_definitions(&tokens, name);
+	if let Some(environment) = options.env_target_env {
+		check_environment_globals(&globals, environment);
+	}
+	let parse_start = Instant::now();
+	let mut parsed = if options.env_trace_parse {
+		let (result, trace) = parse_tokens_traced(tokens, name, &remapping, options);
+		check!(fs::write(format_clue!(name, ".trace.txt"), trace));
+		result?
+	} else {
+		parse_tokens(
+			tokens,
+			/*if flag!(env_types) != TypesMode::NONE {
+				Some(AHashMap::default())
+			} else {
+				None
+			},*/
+			name, &remapping, options,
+		)?
+	};
+	dump(options, Stage::Parse, name, || format_ast(&parsed))?;
+	PassManager::from_options(options).run(&mut parsed, options)?;
+	dump(options, Stage::Optimize, name, || format_ast(&parsed))?;
+	if options.env_mangle_names {
+		let renames = mangle_names(&mut parsed.0, &mut parsed.1);
+		dump(options, Stage::Mangle, name, || {
+			renames
+				.iter()
+				.map(|(name, new_name)| format!("{new_name} = {name}\n"))
+				.collect()
+		})?;
+	}
+	// the passes and the mangling are part of the parsing
+	stats.add_time(Stage::Parse, parse_start.elapsed());
+	let (ctokens, statics) = parsed;
 
 	if options.env_struct {
 		println!("Parsed structure of file \"{name}\":\n{ctokens:#?}");
 	}
+	Ok(((ctokens, statics), globals))
+}
 
-	let code = Compiler::new(options, name).compile_tokens(scope, ctokens)?;
+fn compile_parsed(
+	(ctokens, statics): (Expression, Expression),
+	name: &String,
+	scope: usize,
+	options: &Options,
+	time: Instant,
+	stats: &mut CompileStats,
+) -> Result<(String, String, BTreeSet<Helper>), String> {
+	let compiler = Compiler::new(options, name);
+	// the modules of a bundle are compiled with a scope bigger than 0
+	let (ctokens, statics) = if options.env_lazy_modules && scope > 0 {
+		let mut code = statics;
+		code.extend(ctokens);
+		(code, Expression::new())
+	} else {
+		(ctokens, statics)
+	};
+	let (code, statics) = stats.time(Stage::Compile, || {
+		Ok::<_, String>((
+			compiler.compile_tokens(scope, ctokens)?,
+			compiler.compile_statics(statics)?,
+		))
+	})?;
+	stats.add_output(&statics);
+	stats.add_output(&code);
+	dump(options, Stage::Compile, name, || statics.clone() + &code)?;
 
 	if options.env_output {
 		println!("Compiled Lua code of file \"{name}\":\n{code}");
@@ -202,9 +686,261 @@ pub fn compile_code(
 		name,
 		time.elapsed().as_secs_f32()
 	);
+	Ok((code, statics, compiler.helpers()))
+}
+
+pub fn compile_code(
+	codes: PPCode,
+	variables: &PPVars,
+	remapping: Remapping,
+	name: &String,
+	scope: usize,
+	options: &Options,
+) -> Result<(String, String, BTreeSet<Helper>, Vec<GlobalDefinition>), String> {
+	let time = Instant::now();
+	let mut stats = CompileStats::new(name.as_str());
+	let (parsed, globals) = parse_code(codes, variables, remapping, name, options, &mut stats)?;
+
+	#[cfg(feature = "json")]
+	if options.env_ast {
+		let json = ast_to_json(name.as_str(), parsed, true)?;
+		if options.env_output {
+			println!("Syntax tree of file \"{name}\":\n{json}");
+		}
+		return Ok((json, String::new(), BTreeSet::new(), globals));
+	}
+
+	let (code, statics, helpers) = compile_parsed(parsed, name, scope, options, time, &mut stats)?;
+	add_stats(stats, options);
+	Ok((code, statics, helpers, globals))
+}
+
+// compiles the code straight into the output file, a statement at a time,
+// when nothing else needs the whole output
+fn compile_code_to_file(
+	codes: PPCode,
+	variables: &PPVars,
+	remapping: Remapping,
+	name: &String,
+	options: &Options,
+	output_path: &Path,
+) -> Result<(), String> {
+	let time = Instant::now();
+	let mut stats = CompileStats::new(name.as_str());
+	let (parsed, _) = parse_code(codes, variables, remapping, name, options, &mut stats)?;
+	let mut file = WriteEmitter::new(BufWriter::new(check!(fs::File::create(output_path))));
+	let mut emitter = |code: &str| {
+		stats.add_output(code);
+		file.emit(code)
+	};
+	let start = Instant::now();
+	compile_ast_to(parsed, name, options, &mut emitter)?;
+	stats.add_time(Stage::Compile, start.elapsed());
+	check!(file.into_inner().flush());
+	println!(
+		"Compiled file \"{}\" in {} seconds!",
+		name,
+		time.elapsed().as_secs_f32()
+	);
+	add_stats(stats, options);
+	Ok(())
+}
+
+// keeps the stats of the file for the report printed by `print_stats`
+fn add_stats(stats: CompileStats, options: &Options) {
+	if options.env_stats {
+		STATS.lock().unwrap().push(stats);
+	}
+}
+
+// prints the report of every file compiled until now, when `--stats` was given
+fn print_stats(stats: bool) {
+	if stats {
+		println!("{}", stats_report(&STATS.lock().unwrap()));
+	}
+}
+
+// compiles the code and gives its syntax tree and the globals it defines to `save_parsed`,
+// parsing it only once
+fn compile_code_with_ast(
+	codes: PPCode,
+	variables: &PPVars,
+	remapping: Remapping,
+	name: &String,
+	options: &Options,
+	save_parsed: impl FnOnce(&(Expression, Expression), &[GlobalDefinition]) -> Result<(), String>,
+) -> Result<(String, String), String> {
+	let time = Instant::now();
+	let mut stats = CompileStats::new(name.as_str());
+	let (parsed, globals) = parse_code(codes, variables, remapping, name, options, &mut stats)?;
+	save_parsed(&parsed, &globals)?;
+	let (code, statics, helpers) = compile_parsed(parsed, name, 0, options, time, &mut stats)?;
+	add_stats(stats, options);
+	let statics = compile_helpers(&helpers, options) + &statics + &compile_preamble(options)?;
 	Ok((code, statics))
 }
 
+fn analyze_file(path: PathBuf, name: &String, options: &Options) -> Result<FeatureSet, String> {
+	let (codes, variables, mut remapping) = read_file(path, name, options)?;
+	let code = preprocess_codes(0, codes, &variables, &mut remapping, name)?;
+	let tokens = scan_code_with_options(code, name, &remapping, options)?;
+	Ok(analyze_tokens(&tokens))
+}
+
+fn infer_target(path: PathBuf, pathiscode: bool, options: &Options) -> Result<(), String> {
+	let features = if pathiscode {
+		let filename = String::from("(command line)");
+		let mut code = path.to_string_lossy().into_owned();
+		let code = unsafe { code.as_bytes_mut() };
+		let (codes, variables, .., mut remapping) =
+			preprocess_code(code, 1, false, &filename, options)?;
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+		analyze_tokens(&scan_code_with_options(
+			code, &filename, &remapping, options,
+		)?)
+	} else if path.is_dir() {
+		let mut features = FeatureSet::EMPTY;
+		for (filepath, name) in check!(check_for_files(path, String::new())) {
+			features |= analyze_file(filepath, &name, options)?;
+		}
+		features
+	} else if path.is_file() {
+		let name = path.file_name().unwrap().to_string_lossy().into_owned();
+		analyze_file(path, &name, options)?
+	} else {
+		return Err(format!("{} was not found!", path.to_string_lossy()));
+	};
+	if features.is_empty() {
+		println!("No version specific features are used");
+	}
+	for (name, requirement) in features.requirements() {
+		println!("Uses {name}: {requirement}");
+	}
+	match features.compatible_targets()[..] {
+		[] => println!("No Lua version can run all the features used"),
+		[minimum, ref others @ ..] => {
+			println!("Minimum target: {minimum:?}");
+			if !others.is_empty() {
+				println!("Also compatible with: {others:?}");
+			}
+		}
+	}
+	Ok(())
+}
+
+// compiles the file and then compiles it again whenever it changes,
+// the changes that leave the same tokens (only in comments and whitespace) are skipped
+// unless the output has the lines of the code
+fn watch(path: PathBuf, options: &Options, args: &[OsString]) -> Result<(), String> {
+	let name = path.file_name().unwrap().to_string_lossy().into_owned();
+	let lines_matter = options.env_debug
+		|| options.env_traceback
+		|| options.env_source_map
+		|| options.env_debug_info
+		|| options.env_coverage.is_some();
+	let scan = || {
+		let (codes, variables, mut remapping) = read_file(&path, &name, options)?;
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &name)?;
+		scan_code_with_options(code, &name, &remapping, options)
+	};
+	let mut tokens: Option<Vec<Token>> = None;
+	let mut modified = None;
+	loop {
+		let time = check!(check!(fs::metadata(&path)).modified());
+		if modified != Some(time) {
+			modified = Some(time);
+			match scan() {
+				Ok(new)
+					if !lines_matter
+						&& tokens.as_ref().is_some_and(|old| same_tokens(old, &new)) =>
+				{
+					println!("No semantic change in \"{name}\"");
+				}
+				Ok(new) => {
+					tokens = Some(new);
+					let mut cli = Cli::parse_from(args);
+					cli.watch = false;
+					if let Err(error) = compile(cli, args) {
+						eprintln!("Error: {error}");
+					}
+				}
+				Err(error) => {
+					tokens = None;
+					eprintln!("Error: {error}");
+				}
+			}
+		}
+		thread::sleep(Duration::from_millis(250));
+	}
+}
+
+// a ModuleScript of Roblox must return exactly one value,
+// so a file which doesn't return at the top level returns nil
+fn add_module_return(code: String, options: &Options) -> String {
+	let last = code.lines().rev().find(|line| !line.trim().is_empty());
+	let returns = last.is_some_and(|line| line == "return" || line.starts_with("return "));
+	if options.env_target_env == Some(TargetEnv::Roblox) && !options.env_ast && !returns {
+		code + "\nreturn nil"
+	} else {
+		code
+	}
+}
+
+fn finish_output(
+	traceback: bool,
+	source_map: bool,
+	debug_info: bool,
+	style: &OutputStyle,
+	code: String,
+) -> (String, Option<SourceMapBuilder>, Option<DebugInfo>) {
+	let code = style.apply(code);
+	// the debug information is only made with `--debug`, which wraps the code in add_debug_wrapper
+	let info = debug_info
+		.then(|| extract_debug_info(&code, debug_wrapper_offset() + traceback as usize).1);
+	match (traceback, source_map) {
+		(true, true) => {
+			let (_, map) = extract_source_map(&code, 1);
+			(add_traceback_helper(&code), Some(map), info)
+		}
+		(true, false) => (add_traceback_helper(&code), None, info),
+		(false, true) => {
+			let (code, map) = extract_source_map(&code, 0);
+			(code, Some(map), info)
+		}
+		(false, false) if info.is_some() => (extract_debug_info(&code, 0).0, None, info),
+		(false, false) => (code, None, None),
+	}
+}
+
+// writes the source map of the output next to it, as <output>.map
+fn write_source_map(output_path: &Path, map: &SourceMapBuilder) -> Result<(), String> {
+	let mut map_path = output_path.as_os_str().to_owned();
+	map_path.push(".map");
+	let file = output_path
+		.file_name()
+		.map(|file| file.to_string_lossy().into_owned());
+	check!(fs::write(map_path, map.build(file.as_deref())));
+	Ok(())
+}
+
+// writes the debug information of the output next to it, as <output>.debug.json
+fn write_debug_info(output_path: &Path, info: &DebugInfo) -> Result<(), String> {
+	let mut info_path = output_path.as_os_str().to_owned();
+	info_path.push(".debug.json");
+	let file = output_path
+		.file_name()
+		.map(|file| file.to_string_lossy().into_owned());
+	check!(fs::write(info_path, info.build(file.as_deref())));
+	Ok(())
+}
+
+fn write_coverage(output_path: &Path, code: &str, hook: &str) -> Result<(), String> {
+	let mut lcov_path = output_path.as_os_str().to_owned();
+	lcov_path.push(".lcov");
+	check!(fs::write(lcov_path, lcov(&covered_lines(code, hook))));
+	Ok(())
+}
+
 #[cfg(feature = "mlua")]
 fn execute_lua_code(code: &str) {
 	println!("Running compiled code...");
@@ -220,21 +956,30 @@ fn finish(
 	debug: bool,
 	#[cfg(feature = "mlua")] execute: bool,
 	output_path: Option<PathBuf>,
+	runner: Option<(PathBuf, Option<LuaVersion>)>,
+	bytecode: Option<(Option<String>, Option<LuaVersion>)>,
+	name: &str,
 	code: String,
 ) -> Result<(), String> {
-	if debug {
-		let new_output = format!(
-			include_str!("debug.lua"),
-			format_clue!("\t", code.replace('\n', "\n\t"))
-		);
-		if let Some(output_path) = output_path {
+	let code = if debug {
+		let new_output = add_debug_wrapper(&code, name);
+		if let Some(output_path) = &output_path {
 			check!(fs::write(output_path, &new_output));
 		}
-		#[cfg(feature = "mlua")]
-		if execute {
-			execute_lua_code(&new_output)
-		}
-		return Ok(());
+		new_output
+	} else {
+		code
+	};
+	if let (Some((compiler, target)), Some(output_path)) = (bytecode, output_path) {
+		precompile(
+			&output_path.with_extension("luac"),
+			&code,
+			compiler.as_deref(),
+			target,
+		)?;
+	}
+	if let Some((runner_path, target)) = runner {
+		write_self_runner(&runner_path, &code, target)?;
 	}
 	#[cfg(feature = "mlua")]
 	if execute {
@@ -243,30 +988,80 @@ fn finish(
 	Ok(())
 }
 
+// the file the output is saved to, `output_name` with the extension added if it doesn't have it
+fn output_path(output_name: Option<PathBuf>, output_extension: &str) -> PathBuf {
+	match output_name {
+		Some(mut output_path) => {
+			match output_path.extension() {
+				Some(extension) if extension != output_extension => {
+					output_path.set_extension(format_clue!(
+						extension.to_string_lossy(),
+						".",
+						output_extension
+					));
+				}
+				None => {
+					output_path.set_extension(output_extension);
+				}
+				_ => {}
+			}
+			output_path
+		}
+		None => PathBuf::from("main").with_extension(output_extension),
+	}
+}
+
+// writes the output without its comments and with as little whitespace as possible next to it,
+// as <output>.min.lua
+fn write_minified(
+	output_name: Option<PathBuf>,
+	output_extension: &str,
+	code: &str,
+) -> Result<(), String> {
+	// the traceback and the source map need the lines of the code, so they are left out
+	// and `minify` removes the markers they leave in the code along with the other comments
+	let style = OutputStyle::minified();
+	let (code, ..) = finish_output(false, false, false, &style, code.to_owned());
+	let code = minify(&code);
+	let minified_path = output_path(output_name, output_extension)
+		.with_extension(format_clue!("min.", output_extension));
+	check!(fs::write(minified_path, code));
+	Ok(())
+}
+
 fn save_result(
 	dont_save: bool,
 	output_name: Option<PathBuf>,
-	code: String,
+	output_extension: &str,
+	max_chunk_bytes: Option<usize>,
+	coverage: Option<&str>,
+	(code, source_map, debug_info): (String, Option<SourceMapBuilder>, Option<DebugInfo>),
 ) -> Result<(Option<PathBuf>, String), String> {
 	Ok((
 		if !dont_save {
-			let output_path = match output_name {
-				Some(mut output_path) => {
-					match output_path.extension() {
-						Some(extension) if extension != "lua" => {
-							output_path
-								.set_extension(format_clue!(extension.to_string_lossy(), ".lua"));
-						}
-						None => {
-							output_path.set_extension("lua");
-						}
-						_ => {}
+			let output_path = output_path(output_name, output_extension);
+			match max_chunk_bytes {
+				Some(max_bytes) => {
+					let name = output_path.file_stem().unwrap().to_string_lossy();
+					let (loader, chunks) = split_chunks(&code, &name, max_bytes)?;
+					for (i, chunk) in chunks.iter().enumerate() {
+						let chunk_path =
+							output_path.with_file_name(format!("{name}_{}.lua", i + 1));
+						check!(fs::write(chunk_path, chunk));
 					}
-					output_path
+					check!(fs::write(&output_path, loader));
 				}
-				None => PathBuf::from("main.lua"),
-			};
-			check!(fs::write(&output_path, &code));
+				None => check!(fs::write(&output_path, &code)),
+			}
+			if let Some(map) = source_map {
+				write_source_map(&output_path, &map)?;
+			}
+			if let Some(info) = debug_info {
+				write_debug_info(&output_path, &info)?;
+			}
+			if let Some(hook) = coverage {
+				write_coverage(&output_path, &code, hook)?;
+			}
 			Some(output_path)
 		} else {
 			None
@@ -277,29 +1072,77 @@ fn save_result(
 
 fn main() -> Result<(), String> {
 	std::env::set_var("CLUE_VERSION", crate_version!());
-	let cli = Cli::parse();
+	#[cfg(feature = "mlua")]
+	if let Some(code) = runner::embedded_code() {
+		if let Err(error) = runner::run_embedded_code(code) {
+			eprintln!("{error}");
+			std::process::exit(1);
+		}
+		return Ok(());
+	}
+	let mut args: Vec<OsString> = std::env::args_os().collect();
+	let mut cli = Cli::parse_from(&args);
+	if let Some(Command::Build { path }) = &cli.command {
+		check!(std::env::set_current_dir(path));
+		args = project_arguments()?;
+		cli = Cli::parse_from(&args);
+	}
+	match cli.command {
+		Some(Command::New { path, template }) => return new_project(&path, template),
+		Some(Command::Init { path, template }) => return init_project(&path, template),
+		Some(Command::Trace {
+			path,
+			line,
+			max_depth,
+		}) => return view_trace(&path, line, max_depth),
+		Some(Command::Build { .. }) | None => {}
+	}
 	if cli.license {
 		print!(include_str!("../LICENSE"));
 		return Ok(());
+	} else if cli.grammar {
+		print!("{}", clue_core::grammar::GRAMMAR);
+		return Ok(());
+	} else if cli.emit_token_spec {
+		println!("{}", clue_core::grammar::token_spec_json());
+		return Ok(());
 	} /*else if cli.types.is_some() {
 	  //TEMPORARY PLACEHOLDER UNTIL 4.0
 	  return Err(String::from("Type checking is not supported yet!"));
   }*/
+	compile(cli, &args)
+}
+
+// compiles what the arguments given to the cli (`args`, parsed as `cli`) say
+fn compile(cli: Cli, args: &[OsString]) -> Result<(), String> {
+	for define in &cli.define {
+		let (name, value) = define.split_once('=').unwrap_or((define, ""));
+		std::env::set_var(name, value);
+	}
 
 	if cli.r#continue == ContinueMode::LuaJIT {
 		println!("Warning: \"LuaJIT continue mode was deprecated and replaced by goto mode\"")
 	}
 
+	let preamble = match &cli.preamble {
+		Some(path) => match fs::read_to_string(path) {
+			Ok(preamble) => Some(preamble),
+			Err(_) => return Err(String::from("The given preamble was not found!")),
+		},
+		None => None,
+	};
+
 	let mut options = Options {
 		env_outputname: cli.outputname.clone(),
 		env_tokens: cli.tokens,
 		env_struct: cli.r#struct,
 		env_expand: cli.expand,
+		env_stats: cli.stats,
 		env_jitbit: {
 			if cli.jitbit.is_some() {
 				println!("Warning: \"--jitbit was deprecated and replaced by --bitwise\"");
 				cli.jitbit
-			} else if cli.bitwise == BitwiseMode::Library {
+			} else if matches!(cli.bitwise, BitwiseMode::Library | BitwiseMode::Pure) {
 				Some(String::from("bit"))
 			} else {
 				None
@@ -307,22 +1150,154 @@ fn main() -> Result<(), String> {
 		},
 		env_bitwise: cli.bitwise,
 		env_continue: cli.r#continue,
+		env_named_args: cli.named_args,
+		env_enums: cli.enums,
+		env_annotations: cli.annotations,
+		env_language: cli.language,
 		env_rawsetglobals: cli.rawsetglobals,
+		env_global_table: cli.global_table,
+		env_exports: cli.exports,
+		env_wrap: cli.wrap,
+		env_preamble: preamble,
 		env_debug: cli.debug,
+		env_strict: cli.strict,
+		env_traceback: cli.traceback,
+		env_source_map: cli.source_map || cli.emit.contains(&Emit::Map),
+		env_debug_info: cli.debug && !cli.dontsave,
+		env_coverage: cli.coverage,
+		env_profile: cli.profile,
+		env_profile_hook: cli.profile_hook,
 		env_output: if cli.pathiscode {
 			cli.outputname.is_none()
 		} else {
 			cli.output
 		},
 		env_target: cli.target,
+		env_target_env: cli.target_env,
 		env_targetos: cli.targetos,
+		env_targetarch: cli.targetarch,
 		#[cfg(feature = "lsp")]
 		env_symbols: cli.symbols,
 		#[cfg(not(feature = "lsp"))]
 		env_symbols: false,
+		env_ast: cli.emit == [Emit::Ast],
+		env_dump: cli.dump_after,
+		env_trace_parse: cli.trace_parse,
+		env_fold_constants: cli.fold_constants,
+		env_inline_functions: cli.inline_functions,
+		env_const_eval: cli.const_eval,
+		env_const_max_instructions: cli.const_max_instructions,
+		env_const_max_memory: cli.const_max_memory,
+		env_const_globals: cli.const_globals,
+		env_mangle_names: cli.mangle_names,
+		env_forward_compat: cli.forward_compat,
+		env_chained_comparisons: cli.chained_comparisons,
+		env_match_dispatch: cli.match_dispatch,
+		env_lazy_modules: cli.lazy_modules,
+		env_reproducible: cli.reproducible,
+		env_max_line_length: cli.max_line_length,
+		env_indent_spaces: cli.indent_spaces,
+		env_omit_semicolons: cli.omit_semicolons,
+		env_max_blank_lines: cli.max_blank_lines,
 	};
 	options.preset();
 
+	let emit_ast = cli.emit.contains(&Emit::Ast);
+	#[cfg(not(feature = "json"))]
+	if emit_ast {
+		return Err(String::from(
+			"This build of Clue does not support '--emit ast' (enable the 'json' feature)",
+		));
+	}
+	#[cfg(not(feature = "mlua"))]
+	if cli.const_eval {
+		return Err(String::from(
+			"This build of Clue does not support '--const-eval' (enable the 'interpreter' feature)",
+		));
+	}
+	let emit_lua = cli.emit.contains(&Emit::Lua);
+	let runner = if cli.emit.contains(&Emit::SelfRunner) && !cli.dontsave {
+		let runner_path = cli
+			.outputname
+			.clone()
+			.unwrap_or_else(|| PathBuf::from("main"))
+			.with_extension(std::env::consts::EXE_EXTENSION);
+		Some((runner_path, options.env_target))
+	} else {
+		None
+	};
+	if options.env_source_map && cli.max_chunk_bytes.is_some() {
+		return Err(String::from(
+			"'--source-map' cannot be used with '--max-chunk-bytes'",
+		));
+	}
+	if cli.emit.contains(&Emit::Map) && !emit_lua {
+		return Err(String::from(
+			"'--emit map' needs '--emit lua', the map is saved next to the Lua code",
+		));
+	}
+	let minified = cli.emit.contains(&Emit::Minified) && !cli.dontsave;
+	let emit_symbols = cli.emit.contains(&Emit::Symbols);
+	if cli.precompile && (cli.max_chunk_bytes.is_some() || !emit_lua || options.env_ast) {
+		return Err(String::from(
+			"'--precompile' can only be used when the output is a single Lua file",
+		));
+	}
+	#[cfg(feature = "mlua")]
+	let execute = cli.execute;
+	#[cfg(not(feature = "mlua"))]
+	let execute = false;
+	if options.env_language == Language::Teal && (cli.precompile || runner.is_some() || execute) {
+		return Err(String::from(
+			"Teal code cannot be executed, precompiled or turned into a self runner",
+		));
+	}
+	let bytecode = cli
+		.precompile
+		.then(|| (cli.luac.clone(), options.env_target));
+	if emit_ast && cli.max_chunk_bytes.is_some() {
+		return Err(String::from(
+			"'--max-chunk-bytes' cannot be used with '--emit ast'",
+		));
+	}
+	let output_extension = if options.env_ast {
+		"json"
+	} else {
+		codegen_target(options.env_language).extension()
+	};
+	let traceback = options.env_traceback && !options.env_ast;
+	let source_map = options.env_source_map && !options.env_ast;
+	let debug_info = options.env_debug_info && !options.env_ast;
+	let coverage = options.env_coverage.clone().filter(|_| !options.env_ast);
+	let wrap = if options.env_ast {
+		WrapMode::None
+	} else {
+		options.env_wrap
+	};
+	let style = if options.env_ast {
+		OutputStyle::default()
+	} else {
+		OutputStyle::new(&options)
+	};
+
+	// when only the Lua file is made from the output of a single file and it's saved as it is,
+	// it's written to the file while it's compiled
+	let streamed = emit_lua
+		&& !cli.dontsave
+		&& cli.emit.len() == 1
+		&& !cli.debug
+		&& !execute
+		&& bytecode.is_none()
+		&& !traceback
+		&& !source_map
+		&& !debug_info
+		&& coverage.is_none()
+		&& cli.max_chunk_bytes.is_none()
+		&& !options.env_ast
+		&& !options.env_output
+		&& !options.env_dump.contains(&Stage::Compile)
+		&& options.env_target_env != Some(TargetEnv::Roblox);
+
 	//let mut code = String::with_capacity(512);
 
 	/*if let Some(bit) = &options.env_jitbit {
@@ -335,35 +1310,135 @@ fn main() -> Result<(), String> {
 		};
 	}*/
 	let mut path = cli.path.unwrap();
+	if cli.infer_target {
+		return infer_target(path, cli.pathiscode, &options);
+	}
+	if cli.watch {
+		if cli.pathiscode || !path.is_file() {
+			return Err(String::from(
+				"'--watch' can only be used when compiling a single file",
+			));
+		}
+		return watch(path, &options, args);
+	}
+	if cli.pathiscode && runner.is_some() {
+		return Err(String::from(
+			"'--emit self-runner' cannot be used with '--pathiscode'",
+		));
+	}
+	if cli.pathiscode && bytecode.is_some() && cli.outputname.is_none() {
+		return Err(String::from(
+			"'--precompile' needs an output file name when used with '--pathiscode'",
+		));
+	}
+	if cli.outdir.is_some() && (cli.pathiscode || !path.is_dir()) {
+		return Err(String::from(
+			"'--outdir' can only be used when compiling a directory",
+		));
+	}
+	if minified && (cli.pathiscode || cli.outdir.is_some()) {
+		return Err(String::from(
+			"'--emit minified' cannot be used with '--pathiscode' or '--outdir'",
+		));
+	}
+	if emit_symbols && (cli.pathiscode || cli.outdir.is_some() || path.is_dir()) {
+		return Err(String::from(
+			"'--emit symbols' can only be used when compiling a single file",
+		));
+	}
+	if cli.outdir.is_some() && (emit_ast || runner.is_some() || execute) {
+		return Err(String::from(
+			"'--outdir' only writes Lua files, which cannot be executed or turned into a self runner",
+		));
+	}
+	if let Some(outdir) = cli.outdir {
+		let files = compile_folder_files(path, options)?;
+		print_stats(cli.stats);
+		for (file, code) in files {
+			let output_path = outdir.join(file).with_extension(output_extension);
+			if let Some(parent) = output_path.parent() {
+				check!(fs::create_dir_all(parent));
+			}
+			let code = finish_output(traceback, source_map, false, &style, code);
+			save_result(
+				false,
+				Some(output_path),
+				output_extension,
+				cli.max_chunk_bytes,
+				coverage.as_deref(),
+				code,
+			)?;
+		}
+		return Ok(());
+	}
+	if cli.bundle && (cli.pathiscode || !path.is_dir()) {
+		return Err(String::from(
+			"'--bundle' can only be used when compiling a directory",
+		));
+	}
+	if cli.pathiscode && emit_ast && !options.env_ast {
+		return Err(String::from(
+			"Multiple '--emit' kinds can only be used when compiling a single file",
+		));
+	}
 	if cli.pathiscode {
 		let filename = String::from("(command line)");
 		let mut code = path.to_string_lossy().into_owned();
 		let code = unsafe { code.as_bytes_mut() };
-		let preprocessed_code = preprocess_code(code, 1, false, &filename, &options)?;
-		let (code, statics) = compile_code(
-			preprocessed_code.0,
-			&preprocessed_code.1,
-			&filename,
-			0,
-			&options,
-		)?;
-		let code = code + &statics;
+		let (codes, variables, .., remapping) =
+			preprocess_code(code, 1, false, &filename, &options)?;
+		let (code, statics, helpers, _) =
+			compile_code(codes, &variables, remapping, &filename, 0, &options)?;
+		print_stats(cli.stats);
+		let preamble = compile_preamble(&options)?;
+		let code = compile_helpers(&helpers, &options) + &statics + &preamble + &code;
+		let code = wrap_entry(add_module_return(code, &options), wrap);
+		let (code, map, info) = finish_output(traceback, source_map, debug_info, &style, code);
+		if options.env_ast {
+			if let Some(outputname) = cli.outputname {
+				check!(fs::write(outputname, &code));
+			}
+			return Ok(());
+		}
 		#[cfg(feature = "mlua")]
 		if cli.execute {
 			execute_lua_code(&code)
 		}
 		return if let Some(outputname) = cli.outputname.clone() {
 			check!(fs::write(&outputname, &code));
+			if let Some(map) = map {
+				write_source_map(&outputname, &map)?;
+			}
+			if let Some(info) = info {
+				write_debug_info(&outputname, &info)?;
+			}
+			if let Some(hook) = &coverage {
+				write_coverage(&outputname, &code, hook)?;
+			}
 			#[cfg(feature = "mlua")]
-			return finish(cli.debug, cli.execute, Some(outputname), code);
+			return finish(
+				cli.debug,
+				cli.execute,
+				Some(outputname),
+				None,
+				bytecode,
+				&filename,
+				code,
+			);
 			#[cfg(not(feature = "mlua"))]
-			finish(cli.debug, Some(outputname), code)
+			finish(cli.debug, Some(outputname), None, bytecode, &filename, code)
 		} else {
 			Ok(())
 		};
 	}
-	let (output_path, code) = if path.is_dir() {
+	let (output_path, name, code) = if path.is_dir() {
+		if emit_ast {
+			return Err(String::from(
+				"'--emit ast' can only be used when compiling a single file",
+			));
+		}
 		let (output, statics) = compile_folder(path, String::new(), options)?;
+		print_stats(cli.stats);
 
 		let code = match cli.base {
 			Some(filename) => {
@@ -376,11 +1451,27 @@ fn main() -> Result<(), String> {
 					.replace("--STATICS\n", &statics)
 					.replace('§', &output)
 			}
+			None if cli.bundle => include_str!("bundle.lua")
+				.replace("--STATICS\n", &statics)
+				.replace('§', &output),
 			None => include_str!("base.lua")
 				.replace("--STATICS\n", &statics)
 				.replace('§', &output),
 		};
-		save_result(cli.dontsave, cli.outputname, code)?
+		let code = wrap_entry(code, wrap);
+		if minified {
+			write_minified(cli.outputname.clone(), output_extension, &code)?;
+		}
+		let code = finish_output(traceback, source_map, debug_info, &style, code);
+		let (output_path, code) = save_result(
+			cli.dontsave || !emit_lua,
+			cli.outputname,
+			output_extension,
+			cli.max_chunk_bytes,
+			coverage.as_deref(),
+			code,
+		)?;
+		(output_path, String::from("main.clue"), code)
 	} else if {
 		match path.extension() {
 			Some(extension) if extension != "clue" => {
@@ -394,10 +1485,79 @@ fn main() -> Result<(), String> {
 		path.is_file()
 	} {
 		let name = path.file_name().unwrap().to_string_lossy().into_owned();
-		let (rawcode, variables) = read_file(path, &name, &options)?;
-		let (output, statics) = compile_code(rawcode, &variables, &name, 0, &options)?;
-		let code = statics + &output;
-		save_result(cli.dontsave, cli.outputname, code)?
+		let (rawcode, variables, remapping) = read_file(path, &name, &options)?;
+		if streamed {
+			let output_path = output_path(cli.outputname, output_extension);
+			compile_code_to_file(
+				rawcode,
+				&variables,
+				remapping,
+				&name,
+				&options,
+				&output_path,
+			)?;
+			print_stats(cli.stats);
+			return Ok(());
+		}
+		let (output, statics) = if (emit_ast && !options.env_ast) || emit_symbols {
+			compile_code_with_ast(
+				rawcode,
+				&variables,
+				remapping,
+				&name,
+				&options,
+				|parsed, globals| {
+					#[cfg(feature = "json")]
+					if emit_ast {
+						let json = ast_to_json(name.as_str(), parsed.clone(), true)?;
+						save_result(
+							cli.dontsave,
+							cli.outputname.clone(),
+							"json",
+							None,
+							None,
+							(json, None, None),
+						)?;
+					}
+					if emit_symbols {
+						let json = symbols_to_json(&name, parsed, globals);
+						save_result(
+							cli.dontsave,
+							cli.outputname.clone(),
+							"symbols.json",
+							None,
+							None,
+							(json, None, None),
+						)?;
+					}
+					Ok(())
+				},
+			)?
+		} else {
+			let (code, statics, helpers, _) =
+				compile_code(rawcode, &variables, remapping, &name, 0, &options)?;
+			let preamble = compile_preamble(&options)?;
+			let statics = compile_helpers(&helpers, &options) + &statics + &preamble;
+			(code, statics)
+		};
+		print_stats(cli.stats);
+		let code = wrap_entry(add_module_return(statics + &output, &options), wrap);
+		if minified {
+			write_minified(cli.outputname.clone(), output_extension, &code)?;
+		}
+		let code = finish_output(traceback, source_map, debug_info, &style, code);
+		let result = save_result(
+			cli.dontsave || !emit_lua,
+			cli.outputname,
+			output_extension,
+			cli.max_chunk_bytes,
+			coverage.as_deref(),
+			code,
+		)?;
+		if options.env_ast {
+			return Ok(());
+		}
+		(result.0, name, result.1)
 	} else {
 		return Err(format!(
 			"{} was not found!",
@@ -406,18 +1566,442 @@ fn main() -> Result<(), String> {
 	};
 
 	#[cfg(feature = "mlua")]
-	return finish(cli.debug, cli.execute, output_path, code);
+	return finish(
+		cli.debug,
+		cli.execute,
+		output_path,
+		runner,
+		bytecode,
+		&name,
+		code,
+	);
 	#[cfg(not(feature = "mlua"))]
-	finish(cli.debug, output_path, code)
+	finish(cli.debug, output_path, runner, bytecode, &name, code)
 }
 
 #[cfg(test)]
 mod tests {
-	use crate::compile_folder;
-	use clue_core::env::Options;
+	use crate::scaffold::Template;
+	use crate::{
+		add_module_return, compile_folder, compile_folder_files, finish_output, new_project, STATS,
+	};
+	use clap::ValueEnum;
+	use clue_core::{
+		coverage::{covered_lines, lcov},
+		env::{Language, LuaVersion, Options, TargetEnv},
+		preamble::compile_preamble,
+		stats::stats_report,
+		style::OutputStyle,
+	};
+	use std::path::Path;
 
 	#[test]
 	fn compilation_success() {
 		compile_folder("../examples/", String::new(), Options::default()).unwrap();
 	}
+
+	#[test]
+	fn lazy_modules() {
+		let options = Options {
+			env_lazy_modules: true,
+			..Options::default()
+		};
+		let (output, statics) = compile_folder("../examples/", String::new(), options).unwrap();
+		assert!(statics.is_empty());
+		assert!(output.contains("local test3 = \"test3\""));
+	}
+
+	#[test]
+	fn stats() {
+		let options = Options {
+			env_stats: true,
+			..Options::default()
+		};
+		compile_folder("../examples/", String::new(), options).unwrap();
+		let stats = STATS.lock().unwrap();
+		let fizzbuzz = stats
+			.iter()
+			.find(|stats| stats.file.ends_with("fizzbuzz.clue"))
+			.unwrap();
+		assert!(fizzbuzz.tokens > 0 && fizzbuzz.output_lines > 0);
+		assert_eq!(fizzbuzz.times.len(), 4);
+		let report = stats_report(&stats);
+		assert!(report.lines().last().unwrap().starts_with("Total"));
+	}
+
+	#[test]
+	fn mangle_names() {
+		let mut options = Options {
+			env_target: Some(LuaVersion::LuaJIT),
+			..Options::default()
+		};
+		options.preset();
+		let (output, _) = compile_folder("../examples/", String::new(), options.clone()).unwrap();
+		options.env_mangle_names = true;
+		let (mangled, statics) = compile_folder("../examples/", String::new(), options).unwrap();
+		assert!(mangled.len() < output.len());
+		#[cfg(feature = "mlua")]
+		{
+			let code = include_str!("base.lua")
+				.replace("--STATICS\n", &statics)
+				.replace('§', &mangled);
+			mlua::Lua::new().load(&code).into_function().unwrap();
+		}
+		#[cfg(not(feature = "mlua"))]
+		drop(statics);
+	}
+
+	#[test]
+	fn output_style() {
+		let mut options = Options {
+			env_traceback: true,
+			env_target: Some(LuaVersion::LuaJIT),
+			..Options::default()
+		};
+		options.preset();
+		let (output, statics) = compile_folder("../examples/", String::new(), options).unwrap();
+		let code = include_str!("base.lua")
+			.replace("--STATICS\n", &statics)
+			.replace('§', &output);
+		let style = OutputStyle {
+			indent_spaces: Some(2),
+			omit_semicolons: true,
+			max_blank_lines: Some(0),
+			max_line_length: Some(40),
+		};
+		let (styled, ..) = finish_output(true, false, false, &style, code.clone());
+		let (code, ..) = finish_output(true, false, false, &OutputStyle::default(), code);
+		assert!(styled.lines().count() > code.lines().count());
+		assert!(!styled.contains("\n\t") && !styled.contains("\n\n"));
+		#[cfg(feature = "mlua")]
+		mlua::Lua::new().load(&styled).into_function().unwrap();
+	}
+
+	#[test]
+	fn pure_bit_library() {
+		let mut clue = clue_core::Clue::new();
+		clue.target(Some(LuaVersion::Lua51Pure));
+		let code = clue
+			.compile_code(String::from(
+				"local a = 12\nreturn a & 10, a | 3, a ^^ 5, ~a, a << 2, -1 >> 28",
+			))
+			.unwrap();
+		assert!(!code.contains("require"));
+		#[cfg(feature = "mlua")]
+		{
+			let results: (u32, u32, u32, u32, u32, u32) =
+				mlua::Lua::new().load(&code).call(()).unwrap();
+			assert_eq!(results, (8, 15, 9, 4294967283, 48, 15));
+		}
+	}
+
+	#[test]
+	fn strict() {
+		let mut clue = clue_core::Clue::new();
+		clue.strict(true);
+		let code = clue
+			.compile_code(String::from(
+				"local t = {}\nlocal fn f() {}\nlocal _, index = pcall(fn() { return t.a.b })\nlocal _, call = pcall(fn() { return f() + 1 })\nreturn index, call",
+			))
+			.unwrap();
+		assert!(code.contains("(library):3: attempt to index 't.a' (a nil value)"));
+		#[cfg(feature = "mlua")]
+		{
+			let (index, call): (String, String) = mlua::Lua::new().load(&code).call(()).unwrap();
+			assert_eq!(index, "(library):3: attempt to index 't.a' (a nil value)");
+			assert_eq!(
+				call,
+				"(library):4: attempt to perform arithmetic on the nil returned by 'f'"
+			);
+		}
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn precompile() {
+		use std::os::unix::fs::PermissionsExt;
+		let dir = std::env::temp_dir().join(format!("clue_precompile_{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		// a compiler which writes the code as it is
+		let luac = dir.join("fake-luac");
+		std::fs::write(&luac, "#!/bin/sh\ncat > \"$2\"\n").unwrap();
+		std::fs::set_permissions(&luac, std::fs::Permissions::from_mode(0o755)).unwrap();
+		let output = dir.join("main.luac");
+		let luac = luac.to_string_lossy();
+		crate::precompile(&output, "print(1)", Some(&luac), Some(LuaVersion::Lua54)).unwrap();
+		assert_eq!(std::fs::read_to_string(&output).unwrap(), "print(1)");
+		assert!(crate::precompile(&output, "print(1)", Some("false"), None).is_err());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn source_map() {
+		let options = Options {
+			env_source_map: true,
+			..Options::default()
+		};
+		let (output, statics) = compile_folder("../examples/", String::new(), options).unwrap();
+		let style = OutputStyle::default();
+		let (code, map, _) = finish_output(true, true, false, &style, statics + &output);
+		let map = map.unwrap().build(None);
+		let mappings = map.rsplit_once("\"mappings\":").unwrap().1;
+		assert_eq!(mappings.matches(';').count(), code.lines().count() - 1);
+		assert!(map.contains("\"../examples/goto.clue\""));
+	}
+
+	#[test]
+	fn debug_info() {
+		let options = Options {
+			env_debug: true,
+			env_debug_info: true,
+			..Options::default()
+		};
+		let (output, statics) = compile_folder("../examples/", String::new(), options).unwrap();
+		let style = OutputStyle::default();
+		let (code, _, info) = finish_output(false, false, true, &style, statics + &output);
+		let info = info.unwrap();
+		// the lines of the debug wrapper come first
+		assert_eq!(info.lines.len(), code.lines().count() + 14);
+		assert!(!code.contains("--@clue:"));
+		let goto = String::from("../examples/goto.clue");
+		assert!(info.sources.contains(&goto));
+		assert!(!info.temporaries.is_empty());
+	}
+
+	#[test]
+	fn preamble() {
+		let (output, statics) =
+			compile_folder("../examples/", String::new(), Options::default()).unwrap();
+		let base = include_str!("base.lua")
+			.replace("--STATICS\n", &statics)
+			.replace('§', &output);
+		// all the compiled examples are a valid preamble
+		let options = Options {
+			env_preamble: Some(base),
+			..Options::default()
+		};
+		assert!(compile_preamble(&options).is_ok());
+		let options = Options {
+			env_preamble: Some(String::from("local s = \"end\" -- end\nend")),
+			..Options::default()
+		};
+		let error = "The preamble is not valid Lua: line 2: unexpected 'end'";
+		assert_eq!(compile_preamble(&options), Err(String::from(error)));
+	}
+
+	#[test]
+	fn coverage() {
+		let mut clue = clue_core::Clue::new();
+		clue.coverage(Some(String::from("hit")));
+		let code = clue
+			.compile_code(String::from(
+				"local fn f(x) {\n\tif x > 1 {\n\t\treturn 1\n\t}\n\treturn 2\n}\nresult = f(0)",
+			))
+			.unwrap();
+		let lines = covered_lines(&code, "hit");
+		assert_eq!(lines, [(String::from("(library)"), [1, 2, 7].into())]);
+		assert!(lcov(&lines).starts_with("SF:(library)\nDA:1,0\nDA:2,0\nDA:7,0\n"));
+		#[cfg(feature = "mlua")]
+		{
+			let lua = mlua::Lua::new();
+			lua.load("hits = {}\nfunction hit(file, line) hits[#hits + 1] = line end")
+				.exec()
+				.unwrap();
+			lua.load(&code).exec().unwrap();
+			let hits: Vec<usize> = lua.globals().get("hits").unwrap();
+			assert_eq!(hits, [7, 1]);
+		}
+	}
+
+	#[test]
+	fn profile() {
+		let mut clue = clue_core::Clue::new();
+		clue.profile(true);
+		let source = "local fn fib(n) {\n\tif n < 2 { return n }\n\treturn fib(n - 1) + fib(n - 2)\n}\nreturn fib(10)";
+		let code = clue.compile_code(String::from(source)).unwrap();
+		assert_eq!(code.matches("clue_profile = ").count(), 1);
+		assert!(code.contains("clue_profile.enter(\"fib ((library):1)\")"));
+		clue.profile_hook(Some(String::from("hooks")));
+		let hooked = clue.compile_code(String::from(source)).unwrap();
+		assert!(!hooked.contains("clue_profile"));
+		assert!(hooked.contains("hooks.exit(\"fib ((library):1)\", "));
+		#[cfg(feature = "mlua")]
+		{
+			let lua = mlua::Lua::new();
+			let result: usize = lua.load(&code).call(()).unwrap();
+			assert_eq!(result, 55);
+			let calls: usize = lua
+				.load("clue_profile.calls[\"fib ((library):1)\"]")
+				.eval()
+				.unwrap();
+			assert_eq!(calls, 177);
+		}
+	}
+
+	#[test]
+	fn bundle() {
+		let path = std::env::temp_dir().join(format!("clue_bundle_{}", std::process::id()));
+		std::fs::create_dir_all(path.join("lib")).unwrap();
+		let main = "local util = require(\"lib.util\")\nreturn util.double(21)";
+		std::fs::write(path.join("main.clue"), main).unwrap();
+		std::fs::write(
+			path.join("lib/util.clue"),
+			"return {double = fn(x) => x * 2}",
+		)
+		.unwrap();
+		let (output, statics) = compile_folder(&path, String::new(), Options::default()).unwrap();
+		let code = include_str!("bundle.lua")
+			.replace("--STATICS\n", &statics)
+			.replace('§', &output);
+		#[cfg(feature = "mlua")]
+		{
+			let result: i32 = mlua::Lua::new().load(&code).call(()).unwrap();
+			assert_eq!(result, 42);
+		}
+		#[cfg(not(feature = "mlua"))]
+		drop(code);
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn outdir() {
+		let path = std::env::temp_dir().join(format!("clue_outdir_{}", std::process::id()));
+		std::fs::create_dir_all(path.join("lib")).unwrap();
+		let main = "local util = import(\"lib.util\")\nreturn util.double(21)";
+		std::fs::write(path.join("main.clue"), main).unwrap();
+		std::fs::write(
+			path.join("lib/util.clue"),
+			"static TWO = 2\nreturn {double = fn(x) => x * TWO}",
+		)
+		.unwrap();
+		let files = compile_folder_files(&path, Options::default()).unwrap();
+		let paths: Vec<&Path> = files.iter().map(|(path, _)| path.as_path()).collect();
+		assert_eq!(paths, [Path::new("lib/util.clue"), Path::new("main.clue")]);
+		assert!(files[0].1.starts_with("local TWO = 2;"));
+		assert!(files[1].1.starts_with("local import = require;"));
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn reproducible() {
+		let path = std::env::temp_dir().join(format!("clue_reproducible_{}", std::process::id()));
+		std::fs::create_dir_all(path.join("lib")).unwrap();
+		for name in ["b", "a", "lib/c", "main"] {
+			let code = format!("static {} = 1\nlocal fn f() {{}}", name.replace('/', "_"));
+			std::fs::write(path.join(name).with_extension("clue"), code).unwrap();
+		}
+		let options = Options {
+			env_debug: true,
+			env_reproducible: true,
+			..Options::default()
+		};
+		let first = compile_folder(&path, String::new(), options.clone()).unwrap();
+		for _ in 0..4 {
+			let again = compile_folder(&path, String::new(), options.clone()).unwrap();
+			assert_eq!(again, first);
+		}
+		let (output, statics) = first;
+		let modules: Vec<&str> = (output.lines())
+			.filter_map(|line| line.strip_prefix("\t[\""))
+			.collect();
+		assert_eq!(
+			modules,
+			[
+				"a\"] = function(...)",
+				"b\"] = function(...)",
+				"lib.c\"] = function(...)",
+				"main\"] = function(...)"
+			]
+		);
+		assert!(statics.find("local a = 1") < statics.find("local lib_c = 1"));
+		assert!(output.contains("\"lib/c.clue\""));
+		assert!(!output.contains(&*path.to_string_lossy()));
+		std::fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn templates_compile() {
+		let projects = std::env::temp_dir().join(format!("clue_templates_{}", std::process::id()));
+		for &template in Template::value_variants() {
+			let path = projects.join(format!("{template:?}"));
+			new_project(&path, template).unwrap();
+			assert!(new_project(&path, template).is_err());
+			let options = Options {
+				env_target: Some(template.target()),
+				..Options::default()
+			};
+			compile_folder(path.join("src"), String::new(), options).unwrap();
+		}
+		std::fs::remove_dir_all(projects).unwrap();
+	}
+
+	#[test]
+	fn duplicate_globals() {
+		let path = std::env::temp_dir().join(format!("clue_globals_{}", std::process::id()));
+		std::fs::create_dir_all(&path).unwrap();
+		std::fs::write(path.join("a.clue"), "global fn greet() {}\nglobal n = 1").unwrap();
+		std::fs::write(path.join("b.clue"), "global n = 2").unwrap();
+		assert!(compile_folder(&path, String::new(), Options::default()).is_err());
+		std::fs::write(path.join("b.clue"), "@override global n = 2").unwrap();
+		compile_folder(&path, String::new(), Options::default()).unwrap();
+		std::fs::remove_dir_all(path).unwrap();
+	}
+	#[test]
+	fn helpers_defined_once() {
+		let path = std::env::temp_dir().join(format!("clue_helpers_{}", std::process::id()));
+		std::fs::create_dir_all(&path).unwrap();
+		std::fs::write(path.join("a.clue"), "global fn a(x) { return x & 1 }").unwrap();
+		std::fs::write(path.join("b.clue"), "global fn b(x) { return x | 1 }").unwrap();
+		let mut options = Options {
+			env_target: Some(LuaVersion::Lua51Pure),
+			env_debug: true,
+			..Options::default()
+		};
+		options.preset();
+		let (output, statics) = compile_folder(&path, String::new(), options).unwrap();
+		assert_eq!(statics.matches("local bit = {}").count(), 1);
+		assert_eq!(statics.matches("local function _clue_call").count(), 1);
+		assert_eq!(output.matches("_clue_call(").count(), 2);
+		std::fs::remove_dir_all(path).unwrap();
+	}
+	#[test]
+	fn teal() {
+		let mut clue = clue_core::Clue::new();
+		clue.language(Language::Teal);
+		let code = clue
+			.compile_code(String::from(
+				"global struct Point {\n\tx = 0\n\tconstructor(x, y = 1) {}\n\tmethod move(dx) {}\n}",
+			))
+			.unwrap();
+		assert!(code.starts_with("global record Point\n\tx: number\n\t__index: Point\nend\n"));
+		assert!(code.contains("\nfunction Point.new(x: any, y?: number): Point\n"));
+		assert!(code.contains("\n\tlocal self: Point = setmetatable("));
+		assert!(code.contains("\nfunction Point:move(dx: any)\n"));
+		assert!(!code.contains("---@"));
+	}
+
+	#[test]
+	fn target_env() {
+		let mut options = Options {
+			env_target_env: Some(TargetEnv::Roblox),
+			..Options::default()
+		};
+		options.preset();
+		assert_eq!(options.env_target, Some(LuaVersion::Luau));
+		let code = add_module_return(String::from("local x = 1\n"), &options);
+		assert!(code.ends_with("\nreturn nil"));
+		let code = String::from("local x = 1\nreturn x\n");
+		assert_eq!(add_module_return(code.clone(), &options), code);
+		let code = String::from("function f()\n\treturn 1\nend");
+		assert!(add_module_return(code, &options).ends_with("end\nreturn nil"));
+		let mut options = Options {
+			env_target_env: Some(TargetEnv::CraftOS),
+			..Options::default()
+		};
+		options.preset();
+		assert_eq!(options.env_target, Some(LuaVersion::Lua51));
+		assert_eq!(options.env_jitbit.as_deref(), Some("bit32"));
+		let code = String::from("local x = 1");
+		assert_eq!(add_module_return(code.clone(), &options), code);
+	}
 }
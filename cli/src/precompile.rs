@@ -0,0 +1,54 @@
+use clue_core::{check, env::LuaVersion};
+use std::{
+	io::Write,
+	path::Path,
+	process::{Command, Stdio},
+};
+
+/// Compiles the Lua code to bytecode and writes it to `path`, which also checks
+/// that the target can load the code Clue generated.
+/// `compiler` is the command to run, `luac` (or `luajit` when targeting LuaJIT) if not given:
+/// commands whose name contains `luajit` are run like `luajit -b`, the others like `luac -o`
+///
+/// # Errors
+/// If the compiler can't be run or it refuses the code,
+/// an [`Err`] containing a [`String`] with its error message will be returned
+pub fn precompile(
+	path: &Path,
+	code: &str,
+	compiler: Option<&str>,
+	target: Option<LuaVersion>,
+) -> Result<(), String> {
+	let compiler = compiler.unwrap_or(match target {
+		None | Some(LuaVersion::LuaJIT) => "luajit",
+		_ => "luac",
+	});
+	let is_luajit = Path::new(compiler)
+		.file_name()
+		.is_some_and(|name| name.to_string_lossy().contains("luajit"));
+	let mut command = Command::new(compiler);
+	// both read the code from the standard input when given - as the input file
+	if is_luajit {
+		command.arg("-b").arg("-").arg(path);
+	} else {
+		command.arg("-o").arg(path).arg("-");
+	}
+	let mut child = match command
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+	{
+		Ok(child) => child,
+		Err(error) => return Err(format!("Could not run '{compiler}': {error}")),
+	};
+	check!(child.stdin.take().unwrap().write_all(code.as_bytes()));
+	let output = check!(child.wait_with_output());
+	if !output.status.success() {
+		return Err(format!(
+			"'{compiler}' could not compile the output:\n{}",
+			String::from_utf8_lossy(&output.stderr).trim_end()
+		));
+	}
+	Ok(())
+}
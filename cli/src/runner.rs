@@ -0,0 +1,96 @@
+use clue_core::{check, env::LuaVersion, format_clue};
+use std::{fs, path::Path};
+
+#[cfg(feature = "mlua")]
+use std::{
+	env,
+	fs::File,
+	io::{Read, Seek, SeekFrom},
+};
+
+/// Marks the end of an executable which has Lua code appended to it
+#[cfg(feature = "mlua")]
+const MAGIC: &[u8; 8] = b"CLUERUN\0";
+
+// a shell script which is also a Lua file, the shell runs the Lua interpreter on the file
+// while Lua skips the shebang and sees the shell commands as a comment
+fn shell_runner(code: &str, target: Option<LuaVersion>) -> String {
+	let interpreter = match target {
+		Some(LuaVersion::LuaJIT) => "luajit",
+		Some(LuaVersion::Luau) => "luau",
+		_ => "lua",
+	};
+	format_clue!(
+		"#!/bin/sh\n--[[ 2>/dev/null\nexec \"${LUA:-",
+		interpreter,
+		"}\" \"$0\" \"$@\"\n]]\n",
+		code
+	)
+}
+
+/// Writes an executable file which runs the given Lua code with the arguments it was launched with.
+/// When Clue is built with its interpreter and the code targets LuaJIT, the file is a copy of Clue
+/// with the code appended to it, so it doesn't need Lua to be installed.
+/// Otherwise it's a shell script which runs the code with the Lua interpreter in $LUA
+/// (or `lua`, `luajit` when targeting LuaJIT)
+pub fn write_self_runner(
+	path: &Path,
+	code: &str,
+	target: Option<LuaVersion>,
+) -> Result<(), String> {
+	#[cfg(feature = "mlua")]
+	let runner = if matches!(target, None | Some(LuaVersion::LuaJIT)) {
+		let mut runner = check!(fs::read(check!(env::current_exe())));
+		runner.extend_from_slice(code.as_bytes());
+		runner.extend_from_slice(&(code.len() as u64).to_le_bytes());
+		runner.extend_from_slice(MAGIC);
+		runner
+	} else {
+		shell_runner(code, target).into_bytes()
+	};
+	#[cfg(not(feature = "mlua"))]
+	let runner = shell_runner(code, target).into_bytes();
+	check!(fs::write(path, runner));
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		check!(fs::set_permissions(path, fs::Permissions::from_mode(0o755)));
+	}
+	Ok(())
+}
+
+/// Returns the Lua code appended to this executable, if it is a runner made by `--emit self-runner`
+#[cfg(feature = "mlua")]
+pub fn embedded_code() -> Option<String> {
+	let mut exe = File::open(env::current_exe().ok()?).ok()?;
+	let mut trailer = [0; 16];
+	exe.seek(SeekFrom::End(-16)).ok()?;
+	exe.read_exact(&mut trailer).ok()?;
+	if &trailer[8..] != MAGIC {
+		return None;
+	}
+	let len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+	let mut code = String::new();
+	exe.seek(SeekFrom::End(-16 - i64::try_from(len).ok()?))
+		.ok()?;
+	exe.take(len).read_to_string(&mut code).ok()?;
+	Some(code)
+}
+
+/// Runs the code of a runner, giving it the arguments of the executable like `lua` does
+#[cfg(feature = "mlua")]
+pub fn run_embedded_code(code: String) -> mlua::Result<()> {
+	let lua = mlua::Lua::new();
+	let mut args = env::args();
+	let name = args.next().unwrap_or_default();
+	let arg = lua.create_table()?;
+	arg.set(0, name.as_str())?;
+	let args: Vec<String> = args.collect();
+	for (i, argument) in args.iter().enumerate() {
+		arg.set(i + 1, argument.as_str())?;
+	}
+	lua.globals().set("arg", arg)?;
+	lua.load(code)
+		.set_name(format_clue!("=", name))
+		.call(mlua::Variadic::from_iter(args))
+}
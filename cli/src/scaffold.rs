@@ -0,0 +1,125 @@
+use clap::ValueEnum;
+use clue_core::{check, env::LuaVersion};
+use std::{ffi::OsString, fs, path::Path};
+use toml::{Table, Value};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[clap(rename_all = "verbatim")]
+/// What kind of project `clue new` and `clue init` create
+pub enum Template {
+	#[clap(name = "lua")]
+	/// A plain Lua program
+	Lua,
+
+	#[clap(name = "love2d")]
+	/// A LÖVE game
+	Love2D,
+
+	#[clap(name = "roblox")]
+	/// A Roblox script
+	Roblox,
+
+	#[clap(name = "openresty")]
+	/// An OpenResty request handler
+	OpenResty,
+}
+
+impl Template {
+	/// The Lua version the project is compiled for
+	pub const fn target(self) -> LuaVersion {
+		match self {
+			Template::Lua | Template::Love2D | Template::OpenResty => LuaVersion::LuaJIT,
+			Template::Roblox => LuaVersion::Luau,
+		}
+	}
+
+	/// The code of the project's src/main.clue
+	pub const fn main_code(self) -> &'static str {
+		match self {
+			Template::Lua => include_str!("templates/lua.clue"),
+			Template::Love2D => include_str!("templates/love2d.clue"),
+			Template::Roblox => include_str!("templates/roblox.clue"),
+			Template::OpenResty => include_str!("templates/openresty.clue"),
+		}
+	}
+}
+
+/// Creates the directory `path` and a new project inside it
+pub fn new_project(path: &Path, template: Template) -> Result<(), String> {
+	if path.exists() {
+		return Err(format!("{} already exists!", path.to_string_lossy()));
+	}
+	check!(fs::create_dir_all(path));
+	init_project(path, template)
+}
+
+/// Creates a new project inside the existing directory `path`, named after it:
+/// a clue.toml, a .gitignore and a src/main.clue which depends on the template.
+/// None of these files are overwritten if they already exist
+pub fn init_project(path: &Path, template: Template) -> Result<(), String> {
+	if !path.is_dir() {
+		return Err(format!("{} was not found!", path.to_string_lossy()));
+	}
+	let name = match check!(path.canonicalize()).file_name() {
+		Some(name) => name.to_string_lossy().into_owned(),
+		None => String::from("main"),
+	};
+	let src = path.join("src");
+	let files = [
+		(
+			path.join("clue.toml"),
+			format!(
+				include_str!("templates/clue.toml"),
+				Value::String(name.clone()),
+				template.target()
+			),
+		),
+		(
+			path.join(".gitignore"),
+			String::from(include_str!("templates/gitignore")),
+		),
+		(src.join("main.clue"), String::from(template.main_code())),
+	];
+	if let Some((existing, _)) = files.iter().find(|(file, _)| file.exists()) {
+		return Err(format!("{} already exists!", existing.to_string_lossy()));
+	}
+	check!(fs::create_dir_all(src));
+	for (file, code) in files {
+		check!(fs::write(file, code));
+	}
+	println!("Created project \"{name}\", compile it with \"clue build\" from its directory");
+	Ok(())
+}
+
+/// Reads the clue.toml of the project in the current directory
+/// and returns the arguments which compile it as it says
+pub fn project_arguments() -> Result<Vec<OsString>, String> {
+	let project = match fs::read_to_string("clue.toml") {
+		Ok(project) => project,
+		Err(_) => return Err(String::from("clue.toml was not found!")),
+	};
+	let project = match project.parse::<Table>() {
+		Ok(project) => project,
+		Err(error) => return Err(format!("clue.toml is not valid: {error}")),
+	};
+	let build = match project.get("build") {
+		Some(Value::Table(build)) => Some(build),
+		None => None,
+		Some(_) => return Err(String::from("[build] of clue.toml must be a table")),
+	};
+	let setting = |key: &str| match build.and_then(|build| build.get(key)) {
+		Some(Value::String(value)) => Ok(Some(value.clone())),
+		None => Ok(None),
+		Some(_) => Err(format!("'{key}' of clue.toml must be a string")),
+	};
+	let mut arguments = vec![
+		OsString::from("clue"),
+		OsString::from(setting("source")?.unwrap_or_else(|| String::from("src"))),
+		OsString::from(setting("output")?.unwrap_or_else(|| String::from("main.lua"))),
+	];
+	if let Some(target) = setting("target")? {
+		arguments.push(OsString::from("--target"));
+		arguments.push(OsString::from(target));
+	}
+	Ok(arguments)
+}
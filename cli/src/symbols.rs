@@ -0,0 +1,49 @@
+use crate::globals::GlobalDefinition;
+use clue_core::{
+	lint::{find_locals, LocalKind},
+	parser::Expression,
+	sourcemap::quote_json_string,
+};
+
+/// Builds the JSON saved by `--emit symbols`: the globals the file defines with their position
+/// and the locals it declares with the line they are declared on and how many times they are read
+pub fn symbols_to_json(
+	file: &str,
+	(ast, statics): &(Expression, Expression),
+	globals: &[GlobalDefinition],
+) -> String {
+	let globals: Vec<String> = globals
+		.iter()
+		.map(|global| {
+			format!(
+				"{{\"name\":{},\"line\":{},\"column\":{},\"overrides\":{}}}",
+				quote_json_string(&global.name),
+				global.line,
+				global.column,
+				global.overrides
+			)
+		})
+		.collect();
+	let locals: Vec<String> = find_locals(ast, statics)
+		.iter()
+		.map(|local| {
+			let kind = match local.kind {
+				LocalKind::Variable => "variable",
+				LocalKind::Argument => "argument",
+				LocalKind::Function => "function",
+			};
+			format!(
+				"{{\"name\":{},\"kind\":\"{kind}\",\"line\":{},\"reads\":{}}}",
+				quote_json_string(&local.name),
+				local.line,
+				local.reads
+			)
+		})
+		.collect();
+	format!(
+		"{{\"file\":{},\"globals\":[{}],\"locals\":[{}]}}",
+		quote_json_string(file),
+		globals.join(","),
+		locals.join(",")
+	)
+}
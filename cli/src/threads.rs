@@ -1,33 +1,39 @@
-use ahash::AHashMap;
-use clue_core::code::Code;
 use clue_core::env::Options;
-use clue_core::preprocessor::{read_file, PPCode, PPVar, PPVars};
+use clue_core::helpers::{compile_helpers, Helper};
+use clue_core::preamble::compile_preamble;
+use clue_core::preprocessor::{read_file, PPCode, PPVars, Remapping};
 use clue_core::{check, format_clue};
 use crossbeam_queue::SegQueue;
 use flume::Sender;
 use std::cmp;
+use std::collections::BTreeSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 
 use crate::compile_code;
+use crate::globals::{check_global_definitions, GlobalDefinition};
 
-type CodeQueue = SegQueue<(PPCode, String, String)>;
+type CodeQueue = SegQueue<(PPCode, Remapping, String, String)>;
 
 struct PreprocessorAnalyzerData {
 	errored: bool,
-	codes: (PPCode, String, String),
+	codes: (PPCode, Remapping, String, String),
 	pub variables: PPVars,
 }
 
 struct ThreadData {
 	errored: bool,
+	filename: String,
+	realname: String,
 	output: String,
 	static_vars: String,
+	helpers: BTreeSet<Helper>,
+	globals: Vec<GlobalDefinition>,
 }
 
-fn check_for_files(
+pub fn check_for_files(
 	path: PathBuf,
 	rpath: String,
 ) -> Result<SegQueue<(PathBuf, String)>, std::io::Error> {
@@ -46,34 +52,45 @@ fn check_for_files(
 			for file in check_for_files(filepath, realname + ".")? {
 				files.push(file)
 			}
-		} else if filepath.extension().map_or(false, |extension| extension == "clue") {
+		} else if filepath.extension().is_some_and(|extension| extension == "clue") {
 			files.push((filepath, realname));
 		}
 	}
 	Ok(files)
 }
 
+// the name of a file in the output: the path it was found at,
+// or its path relative to the directory with the reproducible option
+fn output_name(root: &Path, filepath: &Path, options: &Options) -> String {
+	match filepath.strip_prefix(root) {
+		Ok(relative) if options.env_reproducible => (relative.components())
+			.map(|component| component.as_os_str().to_string_lossy())
+			.collect::<Vec<_>>()
+			.join("/"),
+		_ => filepath.to_string_lossy().into_owned(),
+	}
+}
+
 fn wait_threads(threads: Vec<JoinHandle<()>>) {
 	for thread in threads {
 		thread.join().expect("Join shouldn't panic");
 	}
 }
 
-pub fn compile_folder(
-	file_path: impl Into<PathBuf>,
+// preprocesses and compiles every file of the folder with the given scope, sorted by their name
+fn compile_files(
+	root: &Path,
 	rpath: String,
-	options: Options,
-) -> Result<(String, String), String> {
-	let files = check!(check_for_files(file_path.into(), rpath));
+	options: &Arc<Options>,
+	scope: usize,
+) -> Result<Vec<ThreadData>, String> {
+	let files = check!(check_for_files(root.to_path_buf(), rpath));
 	let files_len = files.len();
 	let threads_count = cmp::min(files_len, num_cpus::get() * 2);
 	let codes = SegQueue::new();
 	let files = Arc::new(files);
-	let options = Arc::new(options);
 	let mut errored = 0;
 	let mut variables = vec![];
-	let mut output = String::with_capacity(files_len * 512) + "\n";
-	let mut statics = String::with_capacity(512);
 
 	let (tx, rx) = flume::unbounded();
 
@@ -85,15 +102,19 @@ pub fn compile_folder(
 		let files = files.clone();
 		let tx = tx.clone();
 		let options = options.clone();
+		let root = root.to_path_buf();
 
-		let thread = thread::spawn(move || preprocess_file_dir(files, tx, &options));
+		let thread = thread::spawn(move || preprocess_file_dir(files, tx, &root, &options));
 
 		threads.push(thread);
 	}
 
 	wait_threads(threads);
 
-	while let Ok(data) = rx.try_recv() {
+	// the threads finish in any order, so the files are sorted to always give the same output
+	let mut results: Vec<PreprocessorAnalyzerData> = rx.try_iter().collect();
+	results.sort_by(|a, b| a.codes.3.cmp(&b.codes.3));
+	for data in results {
 		if data.errored {
 			errored += 1;
 			continue;
@@ -109,12 +130,7 @@ pub fn compile_folder(
 		n => return Err(format!("{n} files failed to compile!")),
 	}
 
-	let variables = Arc::new(
-		variables
-			.into_iter()
-			.flatten()
-			.collect::<AHashMap<Code, PPVar>>(),
-	);
+	let variables = Arc::new(variables.into_iter().flatten().collect::<PPVars>());
 
 	let mut threads = Vec::with_capacity(threads_count);
 	let (tx, rx) = flume::unbounded();
@@ -126,46 +142,95 @@ pub fn compile_folder(
 		let codes = codes.clone();
 		let variables = variables.clone();
 
-		let thread = thread::spawn(move || compile_file_dir(tx, &options, codes, variables));
+		let thread = thread::spawn(move || compile_file_dir(tx, &options, codes, variables, scope));
 
 		threads.push(thread);
 	}
 
 	wait_threads(threads);
 
-	while let Ok(data) = rx.try_recv() {
-		if data.errored {
-			errored += 1;
-			continue;
+	let mut results: Vec<ThreadData> = rx.try_iter().collect();
+	results.sort_by(|a, b| a.realname.cmp(&b.realname));
+	match results.iter().filter(|data| data.errored).count() {
+		0 => {
+			let globals = results.iter_mut().flat_map(|data| data.globals.drain(..));
+			check_global_definitions(globals.collect())?;
+			Ok(results)
 		}
+		1 => Err(String::from("1 file failed to compile!")),
+		n => Err(format!("{n} files failed to compile!")),
+	}
+}
 
-		output += &data.output;
+pub fn compile_folder(
+	file_path: impl Into<PathBuf>,
+	rpath: String,
+	options: Options,
+) -> Result<(String, String), String> {
+	let root = file_path.into();
+	let options = Arc::new(options);
+	let results = compile_files(&root, rpath, &options, 2)?;
+	let mut output = String::with_capacity(results.len() * 512) + "\n";
+	let mut statics = String::with_capacity(512);
+	let mut helpers = BTreeSet::new();
+	for data in results {
+		output += &format_clue!(
+			"\t[\"",
+			data.realname.strip_suffix(".clue").unwrap(),
+			"\"] = function(...)\n",
+			data.output,
+			"\n\tend,\n"
+		);
 		statics += &data.static_vars;
+		helpers.extend(data.helpers);
 	}
+	// the helpers are defined once for all the files
+	let preamble = compile_preamble(&options)?;
+	let statics = compile_helpers(&helpers, &options) + &statics + &preamble;
+	Ok((output.chars().collect(), statics.chars().collect()))
+}
 
-	match errored {
-		0 => Ok((output.chars().collect(), statics.chars().collect())),
-		1 => Err(String::from("1 file failed to compile!")),
-		n => Err(format!("{n} files failed to compile!")),
-	}
+// compiles every file of the folder to its own code, with the helpers and the static variables it needs,
+// and returns it with the path of the file relative to the folder.
+// Every file is its own module, so `import` is `require` in the files which use it
+pub fn compile_folder_files(
+	file_path: impl Into<PathBuf>,
+	options: Options,
+) -> Result<Vec<(PathBuf, String)>, String> {
+	let root = file_path.into();
+	let options = Arc::new(options);
+	let preamble = compile_preamble(&options)?;
+	let results = compile_files(&root, String::new(), &options, 0)?;
+	let files = results.into_iter().map(|data| {
+		let path = Path::new(&data.filename);
+		let path = path.strip_prefix(&root).unwrap_or(path).to_path_buf();
+		let import = if data.output.contains("import") {
+			"local import = require;\n"
+		} else {
+			""
+		};
+		let statics = compile_helpers(&data.helpers, &options) + import + &data.static_vars;
+		(path, statics + &preamble + &data.output)
+	});
+	Ok(files.collect())
 }
 
 fn preprocess_file_dir(
 	files: Arc<SegQueue<(PathBuf, String)>>,
 	tx: Sender<PreprocessorAnalyzerData>,
+	root: &Path,
 	options: &Options,
 ) {
 	loop {
 		let (filename, filepath, realname) = match files.pop() {
 			None => break,
-			Some((filepath, realname)) => (
-				filepath.to_string_lossy().into_owned(),
-				filepath,
-				realname
-			),
+			Some((filepath, realname)) => {
+				(output_name(root, &filepath, options), filepath, realname)
+			}
 		};
 
-		let (file_codes, file_variables) = match read_file(filepath, &filename, options) {
+		let (file_codes, file_variables, remapping) = match read_file(filepath, &filename, options)
+		{
 			Ok(t) => t,
 			Err(e) => {
 				tx.send(PreprocessorAnalyzerData {
@@ -181,7 +246,7 @@ fn preprocess_file_dir(
 
 		tx.send(PreprocessorAnalyzerData {
 			errored: false,
-			codes: (file_codes, filename, realname),
+			codes: (file_codes, remapping, filename, realname),
 			variables: file_variables,
 		})
 		.unwrap();
@@ -192,40 +257,42 @@ fn compile_file_dir(
 	tx: Sender<ThreadData>,
 	options: &Options,
 	codes: Arc<CodeQueue>,
-	variables: Arc<AHashMap<Code, PPVar>>,
+	variables: Arc<PPVars>,
+	scope: usize,
 ) {
 	loop {
-		let (codes, filename, realname) = match codes.pop() {
+		let (codes, remapping, filename, realname) = match codes.pop() {
 			None => break,
 			Some(codes) => codes,
 		};
 
-		let (code, static_vars) = match compile_code(codes, &variables, &filename, 2, options) {
-			Ok(t) => t,
-			Err(e) => {
-				tx.send(ThreadData {
-					errored: true,
-					output: "".to_owned(),
-					static_vars: "".to_owned(),
-				})
-				.unwrap();
-				eprintln!("Error: {e}");
-				continue;
-			}
-		};
-
-		let string = format_clue!(
-			"\t[\"",
-			realname.strip_suffix(".clue").unwrap(),
-			"\"] = function(...)\n",
-			code,
-			"\n\tend,\n"
-		);
+		let (code, static_vars, helpers, globals) =
+			match compile_code(codes, &variables, remapping, &filename, scope, options) {
+				Ok(t) => t,
+				Err(e) => {
+					tx.send(ThreadData {
+						errored: true,
+						filename,
+						realname,
+						output: "".to_owned(),
+						static_vars: "".to_owned(),
+						helpers: BTreeSet::new(),
+						globals: Vec::new(),
+					})
+					.unwrap();
+					eprintln!("Error: {e}");
+					continue;
+				}
+			};
 
 		tx.send(ThreadData {
 			errored: false,
-			output: string,
+			filename,
+			realname,
+			output: code,
 			static_vars,
+			helpers,
+			globals,
 		})
 		.unwrap();
 	}
@@ -0,0 +1,37 @@
+use clue_core::check;
+use std::{fs, path::Path};
+
+// reads an event of a parse trace (`<depth> <kind> <line>:<column> <detail>`),
+// returning its depth, its line and the event without the depth
+fn parse_event(event: &str) -> Option<(usize, usize, &str)> {
+	let (depth, event) = event.split_once(' ')?;
+	let (_, position) = event.split_once(' ')?;
+	let (line, _) = position.split_once(':')?;
+	Some((depth.parse().ok()?, line.parse().ok()?, event))
+}
+
+/// Prints the parse trace written by `--trace-parse` in `path`, numbering the events
+/// so they can be referred to in bug reports and indenting them by how nested they are.
+/// If `line` is given only the events of that line are shown,
+/// and if `max_depth` is given the events nested deeper than it are hidden
+pub fn view_trace(
+	path: &Path,
+	line: Option<usize>,
+	max_depth: Option<usize>,
+) -> Result<(), String> {
+	let trace = check!(fs::read_to_string(path));
+	for (step, event) in trace.lines().enumerate() {
+		let Some((depth, event_line, event)) = parse_event(event) else {
+			return Err(format!(
+				"{} is not a parse trace, line {} is invalid",
+				path.to_string_lossy(),
+				step + 1
+			));
+		};
+		if line.is_some_and(|line| line != event_line) || max_depth.is_some_and(|max| depth > max) {
+			continue;
+		}
+		println!("{:>6} {}{event}", step + 1, "  ".repeat(depth));
+	}
+	Ok(())
+}
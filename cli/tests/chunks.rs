@@ -0,0 +1,91 @@
+//! Tests for `--max-chunk-bytes`, which splits the output into files loaded by a small loader.
+
+mod common;
+
+use common::{clue, project};
+use std::{fs, path::Path};
+
+const MAIN: &str = "local fn greet(name) {\n\treturn \"hello \" .. name\n}\n\n\
+	local fn add(a, b) {\n\treturn a + b\n}\n\n\
+	local names = {\"a\", \"b\", \"c\"}\n\
+	for _, name in names {\n\tprint(greet(name))\n}\n\
+	print(add(1, 2))\n";
+
+// the chunks written next to main.lua, in order
+fn chunks(dir: &Path) -> Vec<String> {
+	(1..)
+		.map(|i| dir.join(format!("main_{i}.lua")))
+		.take_while(|path| path.is_file())
+		.map(|path| fs::read_to_string(path).unwrap())
+		.collect()
+}
+
+#[test]
+fn chunks_split_between_statements() {
+	let dir = project("chunks_split", MAIN);
+	clue(&dir, &["main.clue", "--max-chunk-bytes", "160"]).unwrap();
+	let chunks = chunks(&dir);
+	assert!(chunks.len() > 1, "{chunks:?}");
+	let mut joined = String::new();
+	for chunk in &chunks {
+		assert!(chunk.len() <= 160, "{chunk}");
+		let code = chunk
+			.strip_prefix("return [[\n")
+			.and_then(|code| code.strip_suffix("]]\n"))
+			.unwrap_or_else(|| panic!("not a long string: {chunk}"));
+		assert!(code.starts_with(|c: char| !c.is_whitespace()), "{chunk}");
+		joined += code;
+	}
+	clue(&dir, &["main.clue", "--output", "whole"]).unwrap();
+	assert_eq!(joined, fs::read_to_string(dir.join("whole.lua")).unwrap());
+	let loader = fs::read_to_string(dir.join("main.lua")).unwrap();
+	assert!(loader.len() <= 160, "{loader}");
+	assert!(
+		loader.contains(&format!("for i = 1, {} do", chunks.len())),
+		"{loader}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn size_is_checked() {
+	let dir = project("chunks_size", MAIN);
+	let error = clue(&dir, &["main.clue", "--max-chunk-bytes", "10"]).unwrap_err();
+	assert!(error.contains("must be bigger than"), "{error}");
+	let error = clue(&dir, &["main.clue", "--max-chunk-bytes", "40"]).unwrap_err();
+	assert!(
+		error.contains("bigger than the maximum chunk size"),
+		"{error}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "interpreter")]
+#[test]
+fn chunks_run_in_order() {
+	use mlua::{Lua, Variadic};
+	use std::sync::{Arc, Mutex};
+
+	let dir = project("chunks_run", MAIN);
+	clue(&dir, &["main.clue", "--max-chunk-bytes", "160"]).unwrap();
+	let lua = Lua::new();
+	let printed = Arc::new(Mutex::new(Vec::new()));
+	let output = printed.clone();
+	let print = lua
+		.create_function(move |_, values: Variadic<String>| {
+			output.lock().unwrap().push(values.join("\t"));
+			Ok(())
+		})
+		.unwrap();
+	lua.globals().set("print", print).unwrap();
+	let package: mlua::Table = lua.globals().get("package").unwrap();
+	package
+		.set("path", dir.join("?.lua").to_string_lossy().into_owned())
+		.unwrap();
+	lua.load(dir.join("main.lua")).exec().unwrap();
+	assert_eq!(
+		*printed.lock().unwrap(),
+		["hello a", "hello b", "hello c", "3"]
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
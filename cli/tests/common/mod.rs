@@ -0,0 +1,58 @@
+//! Helpers shared by the tests which run the `clue` binary in a directory of their own.
+
+#![allow(dead_code)]
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+/// Makes a new empty directory, unique to the test and to this run of the tests
+pub fn directory(test: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("clue_{test}_{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/// Makes a new directory with a main.clue containing the code inside it, unique to the test
+pub fn project(test: &str, code: &str) -> PathBuf {
+	let dir = directory(test);
+	fs::write(dir.join("main.clue"), code).unwrap();
+	dir
+}
+
+/// Runs `clue` with the arguments inside the directory, returning its stderr when it fails
+pub fn clue(dir: &Path, args: &[&str]) -> Result<(), String> {
+	clue_output(dir, args).map(|_| ())
+}
+
+/// Runs `clue` with the arguments inside the directory, returning its stdout when it succeeds
+/// and its stderr when it fails
+pub fn clue_output(dir: &Path, args: &[&str]) -> Result<String, String> {
+	let output = Command::new(env!("CARGO_BIN_EXE_clue"))
+		.current_dir(dir)
+		.args(args)
+		.output()
+		.unwrap();
+	match output.status.success() {
+		true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+		false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+	}
+}
+
+/// Runs `clue` with the arguments inside the directory, returning its stderr when it succeeds,
+/// which has the warnings it printed
+pub fn clue_warnings(dir: &Path, args: &[&str]) -> String {
+	let output = Command::new(env!("CARGO_BIN_EXE_clue"))
+		.current_dir(dir)
+		.args(args)
+		.output()
+		.unwrap();
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,78 @@
+//! Tests for the options of `--const-eval`, which limit what the `const { ... }` blocks can do while compiling.
+
+mod common;
+
+use common::{clue, project};
+use std::fs;
+
+#[test]
+fn limits_need_const_eval() {
+	let dir = project("const_needs", "local x = const { 1 }\n");
+	for option in [
+		"--const-max-instructions=10",
+		"--const-max-memory=10",
+		"--const-globals=type",
+	] {
+		let error = clue(&dir, &["main.clue", option]).unwrap_err();
+		assert!(error.contains("--const-eval"), "{option}: {error}");
+	}
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "interpreter")]
+#[test]
+fn limits_are_used() {
+	let dir = project(
+		"const_limits",
+		"local x = const { local n = 0\nfor i = 1, 100000 { n += i }\nn }\n",
+	);
+	clue(&dir, &["main.clue", "--const-eval"]).unwrap();
+	let code = fs::read_to_string(dir.join("main.lua")).unwrap();
+	assert_eq!(code, "local x = 5000050000;");
+	let error = clue(
+		&dir,
+		&[
+			"main.clue",
+			"--const-eval",
+			"--const-max-instructions",
+			"10000",
+		],
+	)
+	.unwrap_err();
+	assert!(
+		error.contains("ran more than 10000 instructions"),
+		"{error}"
+	);
+	let error = clue(
+		&dir,
+		&["main.clue", "--const-eval", "--const-max-memory", "1024"],
+	)
+	.unwrap_err();
+	assert!(error.contains("memory"), "{error}");
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "interpreter")]
+#[test]
+fn globals_are_used() {
+	let dir = project("const_globals", "local x = const { type(tostring(1)) }\n");
+	let error = clue(
+		&dir,
+		&["main.clue", "--const-eval", "--const-globals", "tostring"],
+	)
+	.unwrap_err();
+	assert!(error.contains("nil value"), "{error}");
+	clue(
+		&dir,
+		&[
+			"main.clue",
+			"--const-eval",
+			"--const-globals",
+			"tostring,type",
+		],
+	)
+	.unwrap();
+	let code = fs::read_to_string(dir.join("main.lua")).unwrap();
+	assert_eq!(code, "local x = \"string\";");
+	fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,38 @@
+//! Tests for `--dump-after`, which writes what each stage of the compiler produced.
+
+mod common;
+
+use common::{clue, project};
+use std::fs;
+
+#[test]
+fn parse_is_before_the_passes() {
+	let dir = project("dump_passes", "print(1 + 2)\n");
+	clue(
+		&dir,
+		&[
+			"main.clue",
+			"--fold-constants",
+			"--dump-after",
+			"parse",
+			"--dump-after",
+			"optimize",
+		],
+	)
+	.unwrap();
+	let parsed = fs::read_to_string(dir.join("main.clue.parse.txt")).unwrap();
+	let optimized = fs::read_to_string(dir.join("main.clue.optimize.txt")).unwrap();
+	assert!(parsed.contains("\"+\""), "{parsed}");
+	assert!(!optimized.contains("\"+\""), "{optimized}");
+	assert!(optimized.contains("\"3\""), "{optimized}");
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_the_given_stages() {
+	let dir = project("dump_stages", "print(1 + 2)\n");
+	clue(&dir, &["main.clue", "--dump-after", "optimize"]).unwrap();
+	assert!(dir.join("main.clue.optimize.txt").is_file());
+	assert!(!dir.join("main.clue.parse.txt").exists());
+	fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,90 @@
+//! Tests for `--emit`, which runs the compiler to write several outputs in one build.
+
+mod common;
+
+use common::{clue, project};
+use std::fs;
+
+const MAIN: &str = "local fn add(a, b) {\n\treturn a + b\n}\n\nprint(add(1, 2))\n";
+
+#[test]
+fn several_outputs() {
+	let dir = project("emit_several", MAIN);
+	clue(
+		&dir,
+		&[
+			"main.clue",
+			"--emit",
+			"lua,minified,map,symbols",
+			"--output",
+			"out",
+		],
+	)
+	.unwrap();
+	let lua = fs::read_to_string(dir.join("out.lua")).unwrap();
+	let minified = fs::read_to_string(dir.join("out.min.lua")).unwrap();
+	assert!(lua.contains("\treturn a+b;"), "{lua}");
+	assert_eq!(
+		minified,
+		"local function add(a,b)return a+b end print(add(1,2))"
+	);
+	assert!(fs::read_to_string(dir.join("out.lua.map"))
+		.unwrap()
+		.contains("\"mappings\""));
+	assert!(dir.join("out.symbols.json").is_file());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_minified() {
+	let dir = project("emit_minified", MAIN);
+	clue(&dir, &["main.clue", "--emit", "minified"]).unwrap();
+	assert!(dir.join("main.min.lua").is_file());
+	assert!(!dir.join("main.lua").exists());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn map_needs_lua() {
+	let dir = project("emit_map", MAIN);
+	let error = clue(&dir, &["main.clue", "--emit", "minified,map"]).unwrap_err();
+	assert!(error.contains("'--emit map' needs '--emit lua'"), "{error}");
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn symbols() {
+	let code = "global fn greet(name) {\n\tlocal message = \"hi \" .. name\n\tprint(message)\n}\n";
+	let dir = project("emit_symbols", code);
+	clue(&dir, &["main.clue", "--emit", "symbols"]).unwrap();
+	assert!(!dir.join("main.lua").exists());
+	assert_eq!(
+		fs::read_to_string(dir.join("main.symbols.json")).unwrap(),
+		"{\"file\":\"main.clue\",\
+		\"globals\":[{\"name\":\"greet\",\"line\":1,\"column\":11,\"overrides\":false}],\
+		\"locals\":[{\"name\":\"name\",\"kind\":\"argument\",\"line\":1,\"reads\":1},\
+		{\"name\":\"message\",\"kind\":\"variable\",\"line\":2,\"reads\":1}]}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn symbols_of_a_directory() {
+	let dir = project("emit_symbols_dir", MAIN);
+	let error = clue(&dir, &[".", "--emit", "lua,symbols"]).unwrap_err();
+	assert!(
+		error.contains("'--emit symbols' can only be used when compiling a single file"),
+		"{error}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_lua_with_helpers() {
+	let dir = project("emit_lua_helpers", "print(1 & 3)\n");
+	clue(&dir, &["main.clue", "--target", "Lua51Pure"]).unwrap();
+	let lua = fs::read_to_string(dir.join("main.lua")).unwrap();
+	assert!(lua.starts_with("local bit = {}\n"), "{lua}");
+	assert!(lua.ends_with("print(bit.band(1, 3));"), "{lua}");
+	fs::remove_dir_all(dir).unwrap();
+}
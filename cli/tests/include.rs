@@ -0,0 +1,21 @@
+//! Tests for the errors of code that `@include` brings into the files of a directory.
+
+mod common;
+
+use common::{clue, project};
+use std::fs;
+
+#[test]
+fn directory_errors_point_into_included_file() {
+	let dir = project(
+		"include_directory",
+		"local x = 1\n@include \"inc.clue\"\nprint(x)\n",
+	);
+	fs::write(dir.join("inc.clue"), "local a = 1\nlocal b = ) 2\n").unwrap();
+	let error = clue(&dir, &["."]).unwrap_err();
+	assert!(
+		error.contains("Error in inc.clue:2:11!\nNote: included at ./main.clue:2:1"),
+		"{error}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,28 @@
+//! Tests for `--infer-target`, which reports the language features a file uses and the targets that run them.
+
+mod common;
+
+use common::{clue_output, project};
+use std::fs;
+
+#[test]
+fn ordered_tables_need_lua52() {
+	let dir = project("infer_target_ordered", "local t = ordered { a = 1 }\n");
+	let output = clue_output(&dir, &["main.clue", "--infer-target"]).unwrap();
+	assert!(
+		output.contains("Uses ordered tables: needs Lua 5.2+\nMinimum target: Lua52\nAlso compatible with: [Lua53, Lua54]"),
+		"{output}"
+	);
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ordered_name_before_a_block() {
+	let dir = project(
+		"infer_target_ordered_name",
+		"local ordered = true\nif ordered { print(1) }\n",
+	);
+	let output = clue_output(&dir, &["main.clue", "--infer-target"]).unwrap();
+	assert!(output.contains("Minimum target: Lua51\n"), "{output}");
+	fs::remove_dir_all(dir).unwrap();
+}
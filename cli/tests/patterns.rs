@@ -0,0 +1,30 @@
+//! Tests for the warnings about invalid Lua patterns passed to the pattern functions of the string library.
+
+mod common;
+
+use common::{clue_warnings, project};
+use std::fs;
+
+#[test]
+fn warns_about_string_library_calls() {
+	let dir = project(
+		"patterns_string",
+		"local a = string.match(x, \"(\")\nlocal b = (\"(\")::rep(2)\nlocal c = (\"x\")::gsub(\"%\", \"\")\n",
+	);
+	let warnings = clue_warnings(&dir, &["main.clue"]);
+	assert!(warnings.contains("main.clue:1:28!"), "{warnings}");
+	assert!(!warnings.contains("main.clue:2:"), "{warnings}");
+	assert!(warnings.contains("main.clue:3:24!"), "{warnings}");
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ignores_other_functions_and_plain_finds() {
+	let dir = project(
+		"patterns_ignored",
+		"local a = x::match(\"(\")\nlocal b = t.string.find(x, \"(\")\nlocal c = string.find(x, \"(\", 1, true)\nlocal d = (\"x\")::find(\"(\", 1, true)\n",
+	);
+	let warnings = clue_warnings(&dir, &["main.clue"]);
+	assert!(!warnings.contains("Invalid pattern"), "{warnings}");
+	fs::remove_dir_all(dir).unwrap();
+}
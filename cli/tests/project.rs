@@ -0,0 +1,46 @@
+//! Tests for the projects made by `clue new`, which `clue build` compiles as their clue.toml says.
+
+mod common;
+
+use common::{clue, directory};
+use std::fs;
+
+#[test]
+fn build_reads_clue_toml() {
+	let dir = directory("project");
+	clue(&dir, &["new", "game", "--template", "roblox"]).unwrap();
+	let project = dir.join("game");
+	let toml = fs::read_to_string(project.join("clue.toml")).unwrap();
+	fs::write(
+		project.join("clue.toml"),
+		toml.replace("\"main.lua\"", "\"out.lua\""),
+	)
+	.unwrap();
+	fs::write(project.join("src/main.clue"), "print(1 & 3)\n").unwrap();
+	clue(&dir, &["build", "game"]).unwrap();
+	let code = fs::read_to_string(project.join("out.lua")).unwrap();
+	// only the Luau target of clue.toml uses bit32
+	assert!(code.contains("bit32.band(1, 3)"), "{code}");
+	assert!(!project.join("main.lua").exists());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn name_is_a_toml_string() {
+	let dir = directory("project_name");
+	let name = "a \"quoted\" \\ name";
+	clue(&dir, &["new", name]).unwrap();
+	let toml = fs::read_to_string(dir.join(name).join("clue.toml")).unwrap();
+	assert!(toml.contains("name = 'a \"quoted\" \\ name'"), "{toml}");
+	clue(&dir.join(name), &["build"]).unwrap();
+	assert!(dir.join(name).join("main.lua").is_file());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn build_needs_clue_toml() {
+	let dir = directory("project_missing");
+	let error = clue(&dir, &["build"]).unwrap_err();
+	assert!(error.contains("clue.toml was not found!"), "{error}");
+	fs::remove_dir_all(dir).unwrap();
+}
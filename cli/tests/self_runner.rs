@@ -0,0 +1,64 @@
+//! Tests for `--emit self-runner`, which writes an executable running the compiled code.
+
+mod common;
+
+use common::{clue, project};
+use std::fs;
+
+const MAIN: &str = "local args = {...}\nprint(#args, table.concat(args, \",\"))\n";
+
+#[test]
+fn shell_runner() {
+	let dir = project("runner_shell", MAIN);
+	clue(
+		&dir,
+		&[
+			"main.clue",
+			"--emit",
+			"self-runner",
+			"--output",
+			"tool",
+			"--target",
+			"Lua54",
+		],
+	)
+	.unwrap();
+	let runner = fs::read_to_string(
+		dir.join("tool")
+			.with_extension(std::env::consts::EXE_EXTENSION),
+	)
+	.unwrap();
+	assert!(
+		runner.starts_with("#!/bin/sh\n--[[ 2>/dev/null\nexec \"${LUA:-lua}\" \"$0\" \"$@\"\n]]\n"),
+		"{runner}"
+	);
+	assert!(
+		runner.contains("print(#args, table.concat(args, \",\"));"),
+		"{runner}"
+	);
+	assert!(!dir.join("tool.lua").exists());
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "interpreter")]
+#[test]
+fn runner_runs_the_code() {
+	use std::process::Command;
+
+	let dir = project("runner_run", MAIN);
+	clue(
+		&dir,
+		&["main.clue", "--emit", "self-runner", "--output", "tool"],
+	)
+	.unwrap();
+	let output = Command::new(
+		dir.join("tool")
+			.with_extension(std::env::consts::EXE_EXTENSION),
+	)
+	.args(["a", "b"])
+	.output()
+	.unwrap();
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "2\ta,b\n");
+	fs::remove_dir_all(dir).unwrap();
+}
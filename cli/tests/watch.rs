@@ -0,0 +1,60 @@
+//! Tests for `--watch`, which compiles the file again when it changes.
+
+mod common;
+
+use common::project;
+use std::{
+	fs,
+	io::{BufRead, BufReader},
+	process::{Command, Stdio},
+	sync::mpsc,
+	thread,
+	time::Duration,
+};
+
+#[test]
+fn skips_changes_to_comments() {
+	let dir = project("watch", "print(1)\n");
+	let mut child = Command::new(env!("CARGO_BIN_EXE_clue"))
+		.current_dir(&dir)
+		.args(["main.clue", "--watch"])
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	let (tx, rx) = mpsc::channel();
+	let stdout = BufReader::new(child.stdout.take().unwrap());
+	thread::spawn(move || {
+		for line in stdout.lines() {
+			if tx.send(line.unwrap()).is_err() {
+				break;
+			}
+		}
+	});
+	let next_line = |expected: &str| loop {
+		let line = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+		if line.contains(expected) {
+			break;
+		}
+	};
+
+	next_line("Compiled file \"main.clue\"");
+	assert_eq!(
+		fs::read_to_string(dir.join("main.lua")).unwrap(),
+		"print(1);"
+	);
+	// the modification time must change
+	thread::sleep(Duration::from_millis(50));
+	fs::write(dir.join("main.clue"), "// prints one\nprint(1)\n").unwrap();
+	next_line("No semantic change in \"main.clue\"");
+	thread::sleep(Duration::from_millis(50));
+	fs::write(dir.join("main.clue"), "print(2)\n").unwrap();
+	next_line("Compiled file \"main.clue\"");
+	assert_eq!(
+		fs::read_to_string(dir.join("main.lua")).unwrap(),
+		"print(2);"
+	);
+
+	child.kill().unwrap();
+	child.wait().unwrap();
+	fs::remove_dir_all(dir).unwrap();
+}
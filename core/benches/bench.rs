@@ -1,4 +1,3 @@
-use ahash::AHashMap;
 use clue::{code::*, compiler::*, env::Options, parser::*, preprocessor::*, scanner::*};
 use clue_core as clue;
 use criterion::{criterion_group, criterion_main, Criterion};
@@ -8,10 +7,10 @@ use std::path::PathBuf;
 use std::thread::JoinHandle;
 use std::{cmp::min, fs, sync::Arc, thread};
 
-pub type CodeQueue = SegQueue<(PPCode, String)>;
+pub type CodeQueue = SegQueue<(PPCode, Remapping, String)>;
 
 struct PreprocessorAnalyzerData {
-	pub codes: (PPCode, String),
+	pub codes: (PPCode, Remapping, String),
 	pub variables: PPVars,
 }
 
@@ -28,6 +27,7 @@ fn wait_threads(threads: Vec<JoinHandle<()>>) {
 fn compile_code(
 	codes: PPCode,
 	variables: &PPVars,
+	mut remapping: Remapping,
 	name: &String,
 	scope: usize,
 	options: &Options,
@@ -39,16 +39,25 @@ fn compile_code(
 		let mut code = Code::with_capacity(size);
 		for (codepart, uses_vars) in codes {
 			code.append(if uses_vars {
-				preprocess_variables(0, &codepart, codepart.len(), variables, name)?
+				preprocess_variables(
+					0,
+					&codepart,
+					codepart.len(),
+					variables,
+					&mut remapping,
+					name,
+				)?
 			} else {
 				codepart
 			})
 		}
 		code
 	};
-	let tokens: Vec<Token> = scan_code(code, name)?;
-	let (ctokens, statics) = parse_tokens(tokens, name, options)?;
-	let code = Compiler::new(options, name).compile_tokens(scope, ctokens)?;
+	let tokens: Vec<Token> = scan_code_with_options(code, name, &remapping, options)?;
+	let (ctokens, statics) = parse_tokens(tokens, name, &remapping, options)?;
+	let compiler = Compiler::new(options, name);
+	let statics = compiler.compile_statics(statics)?;
+	let code = compiler.compile_tokens(scope, ctokens)?;
 	Ok((code, statics))
 }
 
@@ -56,15 +65,15 @@ fn compile_file_dir(
 	tx: Sender<ThreadData>,
 	options: &Options,
 	codes: Arc<CodeQueue>,
-	variables: Arc<AHashMap<Code, PPVar>>,
+	variables: Arc<PPVars>,
 ) {
 	loop {
-		let (codes, realname) = match codes.pop() {
+		let (codes, remapping, realname) = match codes.pop() {
 			None => break,
-			Some((codes, realname)) => (codes, realname),
+			Some((codes, remapping, realname)) => (codes, remapping, realname),
 		};
 
-		let (code, _static_vars) = match compile_code(codes, &variables, &realname, 2, options) {
+		let (code, _static_vars) = match compile_code(codes, &variables, remapping, &realname, 2, options) {
 			Ok(t) => t,
 			Err(e) => {
 				tx.send(ThreadData {
@@ -95,7 +104,7 @@ fn preprocess_file_dir(
 			),
 		};
 
-		let (file_codes, file_variables) = match read_file(filepath, &filename, options) {
+		let (file_codes, file_variables, remapping) = match read_file(filepath, &filename, options) {
 			Ok(t) => t,
 			Err(e) => {
 				tx.send(PreprocessorAnalyzerData {
@@ -109,7 +118,7 @@ fn preprocess_file_dir(
 		};
 
 		tx.send(PreprocessorAnalyzerData {
-			codes: (file_codes, realname),
+			codes: (file_codes, remapping, realname),
 			variables: file_variables,
 		})
 		.unwrap();
@@ -135,7 +144,7 @@ fn check_for_files(
 			for file in check_for_files(filepath, realname + ".")? {
 				files.push(file)
 			}
-		} else if filepath.extension().map_or(false, |extension| extension == "clue") {
+		} else if filepath.extension().is_some_and(|extension| extension == "clue") {
 			files.push((filepath, realname));
 		}
 	}
@@ -175,7 +184,7 @@ fn compile_folder(files: Arc<SegQueue<(PathBuf, String)>>) {
 		variables
 			.into_iter()
 			.flatten()
-			.collect::<AHashMap<Code, PPVar>>(),
+			.collect::<PPVars>(),
 	);
 
 	let mut threads = Vec::with_capacity(threads_count);
@@ -0,0 +1,58 @@
+//! Embeds Clue scripts in a game loop, with hot reloading.
+//!
+//! Run it with `cargo run -p clue_core --example embed --features mlua`,
+//! then edit `scripts/enemy.clue` in the temporary directory it prints while it runs.
+
+use clue_core::{env::LuaVersion, runtime::ClueRuntime, Clue};
+use std::{fs, thread, time::Duration};
+
+const ENEMY: &str = "\
+global enemy = {x = 0, speed = 1}
+log(\"the enemy is ready\")
+
+global fn update(dt) {
+	enemy.x += enemy.speed * dt
+	return enemy.x
+}
+";
+
+fn main() -> Result<(), String> {
+	let scripts = std::env::temp_dir().join("clue_embed").join("scripts");
+	fs::create_dir_all(&scripts).map_err(|e| e.to_string())?;
+	fs::write(scripts.join("enemy.clue"), ENEMY).map_err(|e| e.to_string())?;
+	println!("Scripts are in {}", scripts.display());
+
+	let mut clue = Clue::new();
+	clue.target(Some(LuaVersion::LuaJIT));
+	let mut runtime = ClueRuntime::new(clue, &scripts);
+
+	// Rust functions can be given to the scripts through the Lua state
+	let log = runtime
+		.lua()
+		.create_function(|_, message: String| {
+			println!("[script] {message}");
+			Ok(())
+		})
+		.map_err(|e| e.to_string())?;
+	runtime
+		.lua()
+		.globals()
+		.set("log", log)
+		.map_err(|e| e.to_string())?;
+
+	for frame in 0..100 {
+		// a script with an error doesn't stop the game, it runs again once it's fixed
+		match runtime.reload() {
+			Ok(reloaded) if !reloaded.is_empty() => println!("Reloaded {reloaded:?}"),
+			Ok(_) => {}
+			Err(error) => eprintln!("{error}"),
+		}
+		if frame == 50 {
+			runtime.set("enemy.speed", 3)?;
+		}
+		let x: f64 = runtime.call("update", 0.1)?;
+		println!("Frame {frame}: the enemy is at {x:.1}");
+		thread::sleep(Duration::from_millis(100));
+	}
+	Ok(())
+}
@@ -2,6 +2,21 @@
 //! This is the core of the Clue compiler
 //! This is used by the cli but can also be used by other projects
 //! It is recommended to use [`Clue`] instead of the lower level APIs unless you need to
+//!
+//! The lower level APIs are re-exported from the `clue_frontend` crate (preprocessor, scanner and parser)
+//! and the `clue_backend_lua` crate (Lua code generation), projects that only need one of them
+//! can depend on that crate directly, see their documentation for the stable API and the semver policy
+//!
+//! # Cargo features
+//! - `clap` (default): derives `clap::ValueEnum` for the enums of [`env`](mod@env), used by the cli
+//! - `phf` (default): looks keywords up with a perfect hash map instead of a `match`
+//! - `ahash` (default): uses `ahash` instead of the standard hasher for [`preprocessor::PPVars`]
+//! - `serde`, `json` and `lsp`: serialization of the AST and of the tokens, and the output used by the language server
+//! - `mlua`: the `runtime` module, which runs Clue scripts inside a Rust program (see `examples/embed.rs`)
+//! - `lua54`: only used by the tests, to run the code they compile for Lua 5.4 instead of LuaJIT (it can't be enabled with `mlua`)
+//!
+//! Disabling the default features gives the smallest build with the fewest dependencies,
+//! which compiles faster and is better suited for WASM and embedded uses.
 
 use std::{
 	ffi::OsStr,
@@ -11,60 +26,43 @@ use std::{
 };
 
 use code::Code;
-use compiler::Compiler;
-use env::{BitwiseMode, ContinueMode, LuaVersion, Options};
-use parser::{parse_tokens, Expression};
-use preprocessor::{preprocess_code, preprocess_codes, read_file};
-use scanner::{scan_code, Token};
-
-pub mod code;
-pub mod compiler;
-pub mod env;
-pub mod parser;
-pub mod preprocessor;
-pub mod scanner;
-
-#[macro_export]
-/// Check whether `tocheck` is `Ok` or `Err`
-/// If it's `Ok` it returns it
-/// If it's `Err` it converts the error to a `String` and propagates it to the caller function
-macro_rules! check {
-	($tocheck:expr) => {
-		match $tocheck {
-			Ok(t) => t,
-			Err(e) => return Err(e.to_string()),
-		}
-	};
-}
+use emitter::CodeEmitter;
+use env::{
+	BitwiseMode, ContinueMode, EnumMode, Language, LuaVersion, NamedArgsMode, Options, Stage,
+	TargetEnv, WrapMode,
+};
+use features::{analyze_tokens, FeatureSet};
+use optimize::mangle_names;
+use parser::{parse_tokens, parse_tokens_recovering, Expression, ParseError};
+use passes::{Pass, PassManager};
+use preprocessor::{preprocess_code, preprocess_codes, read_file, Remapping};
+use scanner::{scan_code_with_options, Token};
+use stats::CompileStats;
+use unit::CompilationUnit;
 
-#[macro_export]
-/// Format strings, used mainly inside the Clue code base
-/// This is used to format strings in a way that is more efficient than using `format!`
-///
-/// # Example
-/// ```rust
-/// use clue_core::format_clue;
-///
-/// let a = "Hello";
-/// let b = "World";
-/// let c = format_clue!(a, ", ", b, "!");
-/// assert_eq!(c, "Hello, World!");
-/// ```
-macro_rules! format_clue {
-    ($($strings:expr),+) => {{
-        let vc = [
-          $($strings.to_string(),)+
-        ];
-
-        vc.join("")
-    }};
-}
+pub use clue_backend_lua::{
+	compile_ast, compile_ast_to,
+	compiler, coverage, debuginfo, emitter, entry, helpers, preamble, sourcemap, stats, style,
+	target, traceback, wrap,
+};
+#[cfg(feature = "serde")]
+pub use clue_frontend::cache;
+pub use clue_frontend::{check, format_clue};
+pub use clue_frontend::{
+	code, env, features, grammar, lint, optimize, parser, pattern, preprocessor, scanner,
+};
+
+pub mod passes;
+#[cfg(feature = "mlua")]
+pub mod runtime;
+pub mod unit;
 
 /// The main Clue library API
 /// This is the API that you will use to interact with Clue for most use cases
 /// It's recommended to use this API instead of the lower level APIs unless you need to
 pub struct Clue {
 	options: Options,
+	passes: PassManager,
 }
 
 impl Clue {
@@ -72,7 +70,28 @@ impl Clue {
 	pub fn new() -> Self {
 		Clue {
 			options: Options::default(),
+			passes: PassManager::new(),
+		}
+	}
+
+	/// Adds a [`Pass`] which rewrites the AST of every file after it's parsed,
+	/// the passes run in the order they were added and before the optimizations enabled by the options
+	pub fn add_pass(&mut self, pass: impl Pass + Send + Sync + 'static) {
+		self.passes.add(pass);
+	}
+
+	// runs the added passes and then the built-in ones on the result of the parser
+	fn run_passes(
+		&self,
+		mut parsed: (Expression, Expression),
+		options: &Options,
+	) -> Result<(Expression, Expression), String> {
+		self.passes.run(&mut parsed, options)?;
+		PassManager::from_options(options).run(&mut parsed, options)?;
+		if options.env_mangle_names {
+			mangle_names(&mut parsed.0, &mut parsed.1);
 		}
+		Ok(parsed)
 	}
 
 	/// Sets the `tokens` option
@@ -103,24 +122,363 @@ impl Clue {
 		self.options.env_continue = mode;
 	}
 
+	/// Sets the `named_args_mode` option
+	/// The `named_args_mode` option is used to set how named arguments are passed to functions
+	/// See [`NamedArgsMode`] for the available named arguments modes
+	pub fn named_args_mode(&mut self, mode: NamedArgsMode) {
+		self.options.env_named_args = mode;
+	}
+
+	/// Sets the `enums` option
+	/// The `enums` option is used to set how named enums are compiled,
+	/// which a single enum can change with the `@table`, `@reverse` or `@inline` attribute
+	/// See [`EnumMode`] for the available enum modes
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::EnumMode, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.enums(EnumMode::Inline);
+	///     let code = clue.compile_code("local enum Side { LEFT, RIGHT }\nprint(Side.RIGHT)".to_owned())?;
+	///     assert_eq!(code, "print(2);");
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn enums(&mut self, mode: EnumMode) {
+		self.options.env_enums = mode;
+	}
+
+	/// Sets the `annotations` option
+	/// When the `annotations` option is enabled, the compiled structs and named enums
+	/// will have EmmyLua annotations (like `---@class`) before them
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.annotations(true);
+	///     let code = clue.compile_code("local enum Side { LEFT, RIGHT }".to_owned())?;
+	///     assert!(code.starts_with("---@enum Side\nlocal Side = {"));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn annotations(&mut self, env_annotations: bool) {
+		self.options.env_annotations = env_annotations;
+	}
+
+	/// Sets the `language` option
+	/// The `language` option is used to set the language the code is compiled to
+	/// See [`Language`] for the available languages
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::Language, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.language(Language::Teal);
+	///     let code = clue.compile_code("global fn double(n) { return n * 2 }".to_owned())?;
+	///     assert!(code.starts_with("global function double(n)"));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn language(&mut self, language: Language) {
+		self.options.env_language = language;
+	}
+
 	/// Sets the `rawsetglobals` option
 	/// When the `rawsetglobals` option is enabled, Clue will rawset(_G, ...) instead of simply x = ... for globals
 	pub fn rawsetglobals(&mut self, env_rawsetglobal: bool) {
 		self.options.env_rawsetglobals = env_rawsetglobal;
 	}
 
+	/// Sets the `global_table` option
+	/// When the `global_table` option is set (only for Lua 5.2 and later), the globals are read from
+	/// and declared in that table through an `_ENV` local
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::LuaVersion, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.target(Some(LuaVersion::Lua54));
+	///     clue.global_table(Some(String::from("sandbox")));
+	///     let code = clue.compile_code("global x = 1\nprint(x)".to_owned())?;
+	///     assert_eq!(code, "local _ENV = sandbox\n_ENV.x = 1;\nprint(x);");
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn global_table(&mut self, env_global_table: Option<String>) {
+		self.options.env_global_table = env_global_table;
+	}
+
+	/// Sets the `exports` option
+	/// When the `exports` option is enabled, the globals declared at the top level of the code
+	/// become locals, which are returned in a table at its end
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.exports(true);
+	///     let code = clue.compile_code("global x = 1\nglobal fn f() { return x }".to_owned())?;
+	///     assert!(code.starts_with("local x, f;\nx = 1;\n"));
+	///     assert!(code.ends_with("return {\n\tx = x, \n\tf = f\n};"));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn exports(&mut self, env_exports: bool) {
+		self.options.env_exports = env_exports;
+	}
+
+	/// Sets the `wrap` option
+	/// The `wrap` option is used to wrap the output in the boilerplate a framework expects
+	/// See [`WrapMode`] for the available frameworks
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::WrapMode, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.wrap(WrapMode::Love2D);
+	///     let code = clue.compile_code("return {draw = fn() {}}".to_owned())?;
+	///     assert!(code.starts_with("local _callbacks = (function(...)\nreturn {"));
+	///     assert!(code.contains("love[name] = callback"));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn wrap(&mut self, mode: WrapMode) {
+		self.options.env_wrap = mode;
+	}
+
+	/// Sets the `preamble` option
+	/// When the `preamble` option is set, that Lua code is put after the static variables,
+	/// compiling fails if it's not valid Lua
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.preamble(Some(String::from("local unpack = unpack or table.unpack")));
+	///     let code = clue.compile_code("static n = 2\nprint(unpack(t, n))".to_owned())?;
+	///     assert_eq!(code, "local n = 2;\nlocal unpack = unpack or table.unpack\nprint(unpack(t, n));");
+	///
+	///     clue.preamble(Some(String::from("if x then")));
+	///     assert!(clue.compile_code("print(1)".to_owned()).is_err());
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn preamble(&mut self, env_preamble: Option<String>) {
+		self.options.env_preamble = env_preamble;
+	}
+
 	/// Sets the `debug` option
 	/// When the `debug` option is enabled, debug mode will be enabled
 	pub fn debug(&mut self, env_debug: bool) {
 		self.options.env_debug = env_debug;
 	}
 
+	/// Sets the `strict` option
+	/// When the `strict` option is enabled, indexing nil values and using the nil returned by
+	/// a call in arithmetic will fail with an error pointing to the Clue file and line
+	pub fn strict(&mut self, env_strict: bool) {
+		self.options.env_strict = env_strict;
+	}
+
+	/// Sets the `traceback` option
+	/// When the `traceback` option is enabled, the compiled code will contain the line markers
+	/// needed by [`traceback::add_traceback_helper`]
+	pub fn traceback(&mut self, env_traceback: bool) {
+		self.options.env_traceback = env_traceback;
+	}
+
+	/// Sets the `source_map` option
+	/// When the `source_map` option is enabled, the compiled code will contain the line markers
+	/// needed by [`sourcemap::extract_source_map`]
+	pub fn source_map(&mut self, env_source_map: bool) {
+		self.options.env_source_map = env_source_map;
+	}
+
+	/// Sets the `debug_info` option
+	/// When the `debug_info` option is enabled, the compiled code will contain the line markers
+	/// needed by [`debuginfo::extract_debug_info`]
+	pub fn debug_info(&mut self, env_debug_info: bool) {
+		self.options.env_debug_info = env_debug_info;
+	}
+
+	/// Sets the `coverage` option
+	/// When the `coverage` option is set, the given function is called with the file and the line
+	/// before the statements and at the start of the blocks, and the lines can be found with
+	/// [`coverage::covered_lines`]
+	pub fn coverage(&mut self, env_coverage: Option<String>) {
+		self.options.env_coverage = env_coverage;
+	}
+
+	/// Sets the `profile` option
+	/// When the `profile` option is enabled, the body of every function is wrapped with
+	/// calls to the `enter` and `exit` functions of the profiler
+	pub fn profile(&mut self, env_profile: bool) {
+		self.options.env_profile = env_profile;
+	}
+
+	/// Sets the `profile_hook` option
+	/// When the `profile_hook` option is set, the `profile` option uses that table
+	/// instead of adding `clue_profile` to the output
+	pub fn profile_hook(&mut self, env_profile_hook: Option<String>) {
+		self.options.env_profile_hook = env_profile_hook;
+	}
+
 	/// Sets the `output` option
 	/// When the `output` option is enabled, the output will be printed to the console
 	pub fn output(&mut self, output: bool) {
 		self.options.env_output = output;
 	}
 
+	/// Sets the `fold_constants` option
+	/// When the `fold_constants` option is enabled, expressions made only of literals will be computed at compile time
+	pub fn fold_constants(&mut self, env_fold_constants: bool) {
+		self.options.env_fold_constants = env_fold_constants;
+	}
+
+	/// Sets the `inline_functions` option
+	/// When the `inline_functions` option is enabled, calls to small local functions will be replaced with what they return
+	pub fn inline_functions(&mut self, env_inline_functions: bool) {
+		self.options.env_inline_functions = env_inline_functions;
+	}
+
+	/// Sets the `const_eval` option
+	/// When the `const_eval` option is enabled (and the `mlua` feature too), the `const { ... }` blocks will be run at compile time
+	/// in a sandbox, see `ConstBlocks` in [`passes`]
+	pub fn const_eval(&mut self, env_const_eval: bool) {
+		self.options.env_const_eval = env_const_eval;
+	}
+
+	/// Sets the `const_max_instructions` option
+	/// When the `const_max_instructions` option is set, the `const { ... }` blocks run by the `const_eval` option
+	/// fail once they run more than that many instructions, instead of the default limit of `ConstBlocks` in [`passes`]
+	pub fn const_max_instructions(&mut self, env_const_max_instructions: Option<u64>) {
+		self.options.env_const_max_instructions = env_const_max_instructions;
+	}
+
+	/// Sets the `const_max_memory` option
+	/// When the `const_max_memory` option is set, the `const { ... }` blocks run by the `const_eval` option
+	/// fail once they use more than that many bytes, instead of the default limit of `ConstBlocks` in [`passes`]
+	pub fn const_max_memory(&mut self, env_const_max_memory: Option<usize>) {
+		self.options.env_const_max_memory = env_const_max_memory;
+	}
+
+	/// Sets the `const_globals` option
+	/// When the `const_globals` option is set, the `const { ... }` blocks run by the `const_eval` option
+	/// can only use those global functions and values besides the libraries, instead of `CONST_GLOBALS` in [`passes`]
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.const_eval(true);
+	///     clue.const_globals(Some(vec![String::from("tostring")]));
+	///     # #[cfg(feature = "mlua")]
+	///     assert!(clue.compile_code("local x = const { type(1) }".to_owned()).is_err());
+	///     let code = clue.compile_code("local x = const { tostring(1) }".to_owned())?;
+	///     # #[cfg(feature = "mlua")]
+	///     assert_eq!(code, "local x = \"1\";");
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn const_globals(&mut self, env_const_globals: Option<Vec<String>>) {
+		self.options.env_const_globals = env_const_globals;
+	}
+
+	/// Sets the `mangle_names` option
+	/// When the `mangle_names` option is enabled, local variables will be renamed to short names
+	pub fn mangle_names(&mut self, env_mangle_names: bool) {
+		self.options.env_mangle_names = env_mangle_names;
+	}
+
+	/// Sets the `forward_compat` option
+	/// When the `forward_compat` option is enabled, code using syntax from a newer version of Clue
+	/// fails with a single error telling which version it needs
+	pub fn forward_compat(&mut self, env_forward_compat: bool) {
+		self.options.env_forward_compat = env_forward_compat;
+	}
+
+	/// Sets the `chained_comparisons` option
+	/// When the `chained_comparisons` option is enabled, `a < b < c` means `a < b and b < c`
+	/// and `b` is evaluated only once
+	pub fn chained_comparisons(&mut self, env_chained_comparisons: bool) {
+		self.options.env_chained_comparisons = env_chained_comparisons;
+	}
+
+	/// Sets the `match_dispatch` option
+	/// When the `match_dispatch` option is set, the match blocks comparing the value with
+	/// at least that many different literals are compiled to a table of functions indexed by them
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.match_dispatch(Some(2));
+	///     let code = clue.compile_code(
+	///         "match x { 1 => { f(1) } 2 || 3 => { f(2) } default => { f(0) } }".to_owned(),
+	///     )?;
+	///     assert!(code.contains("[2] = _internal1,"));
+	///     assert!(!code.contains("if "));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn match_dispatch(&mut self, env_match_dispatch: Option<usize>) {
+		self.options.env_match_dispatch = env_match_dispatch;
+	}
+
+	/// Sets the `max_line_length` option
+	/// When the `max_line_length` option is set, the lines of the output longer than it are split
+	/// where it doesn't change the meaning of the code
+	pub fn max_line_length(&mut self, env_max_line_length: Option<usize>) {
+		self.options.env_max_line_length = env_max_line_length;
+	}
+
+	/// Sets the `indent_spaces` option
+	/// When the `indent_spaces` option is set, the output is indented with that many spaces instead of tabs
+	pub fn indent_spaces(&mut self, env_indent_spaces: Option<usize>) {
+		self.options.env_indent_spaces = env_indent_spaces;
+	}
+
+	/// Sets the `omit_semicolons` option
+	/// When the `omit_semicolons` option is enabled, the semicolons at the end of the statements are removed
+	/// unless they are needed
+	pub fn omit_semicolons(&mut self, env_omit_semicolons: bool) {
+		self.options.env_omit_semicolons = env_omit_semicolons;
+	}
+
+	/// Sets the `max_blank_lines` option
+	/// When the `max_blank_lines` option is set, the output never has more empty lines in a row than it
+	pub fn max_blank_lines(&mut self, env_max_blank_lines: Option<usize>) {
+		self.options.env_max_blank_lines = env_max_blank_lines;
+	}
+
 	/// Sets the `expand` option
 	/// When the `expand` option is enabled, the preprocessed file will be printed to the console
 	pub fn expand(&mut self, expand: bool) {
@@ -139,12 +497,43 @@ impl Clue {
 		self.options.preset();
 	}
 
+	/// Sets the `target_env` option
+	/// The `target_env` option is used to set the program the code runs in,
+	/// which also sets the target Lua version and the options that work in it
+	///
+	/// See [`TargetEnv`] for the available environments
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::TargetEnv, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let mut clue = Clue::new();
+	///     clue.target_env(Some(TargetEnv::CraftOS));
+	///     let code = clue.compile_code("local x = a & b".to_owned())?;
+	///     assert!(code.contains("bit32.band(a, b)"));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn target_env(&mut self, environment: Option<TargetEnv>) {
+		self.options.env_target_env = environment;
+		self.options.preset();
+	}
+
 	/// Sets the `target_os` option
 	/// The `target_os` option is used to set the target operating system
 	/// See [`std::env::const::OS`] for specifying the operating system
 	pub fn target_os(&mut self, os: String) {
 		self.options.env_targetos = os;
 	}
+
+	/// Sets the `target_arch` option
+	/// The `target_arch` option is used to set the target architecture
+	/// See [`std::env::consts::ARCH`] for specifying the architecture
+	pub fn target_arch(&mut self, arch: String) {
+		self.options.env_targetarch = arch;
+	}
 }
 
 impl Clue {
@@ -170,7 +559,7 @@ impl Clue {
 	pub fn preprocess_code(&self, code: String) -> Result<Code, String> {
 		let mut code = code;
 		let filename = String::from("(library)");
-		let (codes, variables, ..) = preprocess_code(
+		let (codes, variables, .., mut remapping) = preprocess_code(
 			// SAFETY: This is safe because the preprocessor will never output anything other than UTF-8
 			unsafe { code.as_bytes_mut() },
 			1,
@@ -178,7 +567,7 @@ impl Clue {
 			&filename,
 			&self.options,
 		)?;
-		preprocess_codes(0, codes, &variables, &filename)
+		preprocess_codes(0, codes, &variables, &mut remapping, &filename)
 	}
 
 	/// Preprocesses the given file
@@ -206,14 +595,24 @@ impl Clue {
 		&self,
 		path: P,
 	) -> Result<Code, String> {
+		Ok(self.preprocess_remapped_file(path)?.0)
+	}
+
+	// preprocesses the file like preprocess_file, also returning its name and the remapping
+	// which the scanner and the parser need to report the errors in the code it includes
+	fn preprocess_remapped_file<P: AsRef<Path> + AsRef<OsStr> + Display>(
+		&self,
+		path: P,
+	) -> Result<(Code, String, Remapping), String> {
 		let filepath = PathBuf::from(path.to_string());
 		let filename = filepath
 			.file_name()
 			.ok_or_else(|| format!("Invalid path: {}", path))?
 			.to_string_lossy()
 			.into_owned();
-		let (codes, variables) = read_file(filepath, &filename, &self.options)?;
-		preprocess_codes(0, codes, &variables, &filename)
+		let (codes, variables, mut remapping) = read_file(filepath, &filename, &self.options)?;
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+		Ok((code, filename, remapping))
 	}
 }
 
@@ -241,7 +640,7 @@ impl Clue {
 			.ok_or_else(|| format!("Invalid path: {}", path))?
 			.to_string_lossy()
 			.into_owned();
-		scan_code(code, &filename)
+		scan_code_with_options(code, &filename, &Remapping::default(), &self.options)
 	}
 
 	/// Scans the given preprocessed code for tokens
@@ -266,7 +665,8 @@ impl Clue {
 	///   Ok(())
 	/// }
 	pub fn scan_preprocessed(&self, code: Code) -> Result<Vec<Token>, String> {
-		scan_code(code, &String::from("(library)"))
+		let filename = String::from("(library)");
+		scan_code_with_options(code, &filename, &Remapping::default(), &self.options)
 	}
 
 	/// Scans the given code for tokens
@@ -316,8 +716,35 @@ impl Clue {
 		&self,
 		filename: P,
 	) -> Result<Vec<Token>, String> {
-		let code = self.preprocess_file(&filename)?;
-		self.scan_preprocessed_file(code, &filename)
+		let (code, filename, remapping) = self.preprocess_remapped_file(filename)?;
+		scan_code_with_options(code, &filename, &remapping, &self.options)
+	}
+
+	/// Finds the language features used by the given code without compiling it
+	/// Takes a [`String`] containing the code to analyze
+	///
+	/// Returns a [`Result`] containing the [`FeatureSet`] of the code,
+	/// which can be checked against a Lua version with [`FeatureSet::unsupported_by`]
+	///
+	/// # Errors
+	/// If an error occurs while preprocessing or scanning the code, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::LuaVersion, features::FeatureSet, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let clue = Clue::new();
+	///     let features = clue.analyze_features("print(a?.b & 1)".to_owned())?;
+	///     assert_eq!(features, FeatureSet::BITWISE | FeatureSet::SAFE_NAVIGATION);
+	///     assert_eq!(features.unsupported_by(LuaVersion::Lua51).to_string(), "bitwise operators");
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn analyze_features(&self, code: String) -> Result<FeatureSet, String> {
+		let tokens = self.scan_code(code)?;
+		Ok(analyze_tokens(&tokens))
 	}
 }
 
@@ -326,7 +753,7 @@ impl Clue {
 	/// Takes a [`Code`] containing the preprocessed code to parse
 	/// Returns a [`Result`] containing the parsed expression
 	///
-	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, String)` containing the parsed expression and the static variables
+	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, Expression)` containing the parsed expression and the static variables
 	///
 	/// # Errors
 	/// If an error occurs while parsing the code, an [`Err`] containing a [`String`] with the error message will be returned
@@ -342,7 +769,7 @@ impl Clue {
 	///
 	///  Ok(())
 	/// }
-	pub fn parse_preprocessed(&self, code: Code) -> Result<(Expression, String), String> {
+	pub fn parse_preprocessed(&self, code: Code) -> Result<(Expression, Expression), String> {
 		let tokens = self.scan_preprocessed(code)?;
 		self.parse_tokens(tokens)
 	}
@@ -351,7 +778,7 @@ impl Clue {
 	/// Takes a [`Vec<Token>`] containing the tokens to parse
 	/// Returns a [`Result`] containing the parsed expression
 	///
-	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, String)` containing the parsed expression and the static variables
+	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, Expression)` containing the parsed expression and the static variables
 	///
 	/// # Errors
 	/// If an error occurs while parsing the code, an [`Err`] containing a [`String`] with the error message will be returned
@@ -367,15 +794,17 @@ impl Clue {
 	///
 	///    Ok(())
 	/// }
-	pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<(Expression, String), String> {
-		parse_tokens(tokens, &String::from("(library)"), &self.options)
+	pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<(Expression, Expression), String> {
+		let filename = String::from("(library)");
+		let parsed = parse_tokens(tokens, &filename, &Remapping::default(), &self.options)?;
+		self.run_passes(parsed, &self.options)
 	}
 
 	/// Parses the given code
 	/// Takes a [`String`] containing the code to parse
 	/// Returns a [`Result`] containing the parsed expression
 	///
-	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, String)` containing the parsed expression and the static variables
+	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, Expression)` containing the parsed expression and the static variables
 	///
 	/// # Errors
 	/// If an error occurs while parsing the code, an [`Err`] containing a [`String`] with the error message will be returned
@@ -390,16 +819,70 @@ impl Clue {
 	///
 	///   Ok(())
 	/// }
-	pub fn parse_code(&self, code: String) -> Result<(Expression, String), String> {
+	pub fn parse_code(&self, code: String) -> Result<(Expression, Expression), String> {
 		let tokens = self.scan_code(code)?;
 		self.parse_tokens(tokens)
 	}
 
+	/// Parses the given code without stopping at the first syntax error
+	/// Takes a [`String`] containing the code to parse
+	/// Returns a [`Result`] containing the partial AST, the static variables and every error found
+	///
+	/// See [`parse_tokens_recovering`] for how errors are recovered from
+	///
+	/// # Errors
+	/// If an error occurs while preprocessing or scanning the code, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///   let clue = Clue::new();
+	///   let (expression, statics, errors) = clue.parse_code_recovering("print(".to_owned())?;
+	///   assert_eq!(errors.len(), 1);
+	///
+	///   Ok(())
+	/// }
+	pub fn parse_code_recovering(
+		&self,
+		code: String,
+	) -> Result<(Expression, Expression, Vec<ParseError>), String> {
+		let tokens = self.scan_code(code)?;
+		Ok(parse_tokens_recovering(
+			tokens,
+			&String::from("(library)"),
+			&self.options,
+		))
+	}
+
+	#[cfg(feature = "json")]
+	/// Parses the given code and serializes the result as a JSON [`AstDocument`](parser::AstDocument)
+	/// Takes a [`String`] containing the code to parse
+	///
+	/// # Errors
+	/// If an error occurs while parsing or serializing the code, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::Clue;
+	///
+	/// fn main() -> Result<(), String> {
+	///   let clue = Clue::new();
+	///   let json = clue.parse_code_to_json("print(\"Hello World!\")".to_owned())?;
+	///
+	///   Ok(())
+	/// }
+	pub fn parse_code_to_json(&self, code: String) -> Result<String, String> {
+		let parsed = self.parse_code(code)?;
+		parser::ast_to_json("(library)", parsed, false)
+	}
+
 	/// Parses the given file
 	/// Takes any type that implements [`AsRef<Path>`] and [`AsRef<OsStr>`] and [`Display`] containing the path to the file to parse
 	/// Returns a [`Result`] containing the parsed expression
 	///
-	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, String)` containing the parsed expression and the static variables
+	/// If the code was successfully parsed, the [`Result`] will return a `(Expression, Expression)` containing the parsed expression and the static variables
 	///
 	/// # Errors
 	/// If an error occurs while parsing the file, an [`Err`] containing a [`String`] with the error message will be returned
@@ -417,16 +900,11 @@ impl Clue {
 	pub fn parse_file<P: AsRef<Path> + AsRef<OsStr> + Display>(
 		&self,
 		path: P,
-	) -> Result<(Expression, String), String> {
-		let filepath: &Path = path.as_ref();
-		let filename = filepath
-			.file_name()
-			.ok_or_else(|| format!("Invalid path: {}", path))?
-			.to_string_lossy()
-			.into_owned();
-		let tokens = self.scan_file(&path)?;
-
-		parse_tokens(tokens, &filename, &self.options)
+	) -> Result<(Expression, Expression), String> {
+		let (code, filename, remapping) = self.preprocess_remapped_file(path)?;
+		let tokens = scan_code_with_options(code, &filename, &remapping, &self.options)?;
+		let parsed = parse_tokens(tokens, &filename, &remapping, &self.options)?;
+		self.run_passes(parsed, &self.options)
 	}
 }
 
@@ -452,10 +930,72 @@ impl Clue {
 	///    Ok(())
 	/// }
 	pub fn compile_tokens(&self, tokens: Vec<Token>) -> Result<String, String> {
-		let (ctokens, statics) = self.parse_tokens(tokens)?;
-		let filename = String::from("(library)");
-		let compiler = Compiler::new(&self.options, &filename);
-		Ok(statics + &compiler.compile_tokens(0, ctokens)?)
+		let parsed = self.parse_tokens(tokens)?;
+		self.compile_ast(parsed)
+	}
+
+	/// Compiles the given [`Vec`] of [`Token`] like [`Clue::compile_tokens`],
+	/// adding the number of tokens, the size of the output and the time taken
+	/// by the parsing and the compilation to `stats`
+	/// Returns a [`Result`] containing the compiled code
+	///
+	/// # Errors
+	/// If an error occurs while compiling the code, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{stats::CompileStats, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///    let clue = Clue::new();
+	///    let tokens = clue.scan_code("print(\"Hello World!\")".to_owned())?;
+	///    let mut stats = CompileStats::new("main.clue");
+	///    let code = clue.compile_tokens_with_stats(tokens, &mut stats)?;
+	///    assert_eq!(stats.tokens, 4);
+	///    assert_eq!(stats.output_bytes, code.len());
+	///
+	///    Ok(())
+	/// }
+	/// ```
+	pub fn compile_tokens_with_stats(
+		&self,
+		tokens: Vec<Token>,
+		stats: &mut CompileStats,
+	) -> Result<String, String> {
+		stats.input_lines += tokens.last().map_or(0, |token| token.line);
+		stats.tokens += tokens.len() - 1;
+		let parsed = stats.time(Stage::Parse, || self.parse_tokens(tokens))?;
+		let code = stats.time(Stage::Compile, || self.compile_ast(parsed))?;
+		stats.add_output(&code);
+		Ok(code)
+	}
+
+	/// Compiles the given code like [`Clue::compile_code`], measuring it (see [`CompileStats`])
+	/// Returns a [`Result`] containing the compiled code and its stats
+	///
+	/// # Errors
+	/// If an error occurs while compiling the code, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{stats::stats_report, Clue};
+	///
+	/// fn main() -> Result<(), String> {
+	///    let clue = Clue::new();
+	///    let (code, stats) = clue.compile_code_with_stats("print(1)\nprint(2)".to_owned())?;
+	///    assert_eq!(stats.input_lines, 2);
+	///    assert_eq!(stats.output_lines, code.lines().count());
+	///    println!("{}", stats_report(&[stats]));
+	///
+	///    Ok(())
+	/// }
+	/// ```
+	pub fn compile_code_with_stats(&self, code: String) -> Result<(String, CompileStats), String> {
+		let mut stats = CompileStats::new("(library)");
+		let code = stats.time(Stage::Preprocess, || self.preprocess_code(code))?;
+		let tokens = stats.time(Stage::Scan, || self.scan_preprocessed(code))?;
+		let code = self.compile_tokens_with_stats(tokens, &mut stats)?;
+		Ok((code, stats))
 	}
 
 	/// Compiles the given preprocessed code
@@ -484,7 +1024,7 @@ impl Clue {
 	}
 
 	/// Compiles the given AST
-	/// Takes a [`(Expression, String)`] containing the AST to compile and the statics
+	/// Takes a `(Expression, Expression)` containing the AST to compile and the statics
 	/// Returns a [`Result`] containing the compiled code
 	///
 	/// If the code was successfully compiled, the [`Result`] will return a [`String`] containing the compiled code
@@ -503,10 +1043,8 @@ impl Clue {
 	///
 	///    Ok(())
 	/// }
-	pub fn compile_ast(&self, (ctokens, statics): (Expression, String)) -> Result<String, String> {
-		let filename = String::from("(library)");
-		let compiler = Compiler::new(&self.options, &filename);
-		Ok(statics + &compiler.compile_tokens(0, ctokens)?)
+	pub fn compile_ast(&self, parsed: (Expression, Expression)) -> Result<String, String> {
+		compile_ast(parsed, &String::from("(library)"), &self.options)
 	}
 
 	/// Compiles the given code
@@ -533,6 +1071,50 @@ impl Clue {
 		self.compile_tokens(tokens)
 	}
 
+	/// Compiles the given AST like [`Clue::compile_ast`], writing the compiled code to `emitter`
+	/// piece by piece (see [`compile_ast_to`](clue_backend_lua::compile_ast_to))
+	///
+	/// # Errors
+	/// If an error occurs while compiling the code or writing it, an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn compile_ast_to(
+		&self,
+		parsed: (Expression, Expression),
+		emitter: &mut dyn CodeEmitter,
+	) -> Result<(), String> {
+		compile_ast_to(parsed, &String::from("(library)"), &self.options, emitter)
+	}
+
+	/// Compiles the given code like [`Clue::compile_code`], writing the compiled code to `emitter`
+	/// piece by piece instead of returning it, so that big outputs can go straight to a file
+	///
+	/// # Errors
+	/// If an error occurs while compiling the code or writing it, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{emitter::WriteEmitter, Clue};
+	/// use std::io::BufWriter;
+	///
+	/// fn main() -> Result<(), String> {
+	///    let clue = Clue::new();
+	///    let mut emitter = WriteEmitter::new(BufWriter::new(Vec::new()));
+	///    clue.compile_code_to("print(\"Hello World!\")".to_owned(), &mut emitter)?;
+	///    let output = emitter.into_inner().into_inner().map_err(|e| e.to_string())?;
+	///    assert_eq!(output, b"print(\"Hello World!\");");
+	///
+	///    Ok(())
+	/// }
+	/// ```
+	pub fn compile_code_to(
+		&self,
+		code: String,
+		emitter: &mut dyn CodeEmitter,
+	) -> Result<(), String> {
+		let tokens = self.scan_code(code)?;
+		let parsed = self.parse_tokens(tokens)?;
+		self.compile_ast_to(parsed, emitter)
+	}
+
 	/// Compiles the given file
 	/// Takes any type that implements [`AsRef<Path>`] and [`AsRef<OsStr>`] and [`Display`] containing the path to the file to compile
 	/// Returns a [`Result`] containing the compiled code
@@ -563,6 +1145,73 @@ impl Clue {
 		}
 		Ok(result)
 	}
+
+	/// Compiles the given [`CompilationUnit`] with its own options
+	/// Returns a [`Result`] containing the compiled code
+	///
+	/// # Errors
+	/// If an error occurs while reading or compiling the code of the unit, an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn compile_unit(&self, unit: &CompilationUnit) -> Result<String, String> {
+		let options = unit.options(&self.options);
+		let filename = unit.reader.filename();
+		let mut code = unit.reader.read()?;
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			// SAFETY: This is safe because the preprocessor will never output anything other than UTF-8
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&options,
+		)?;
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+		let parsed = parse_tokens(
+			scan_code_with_options(code, &filename, &remapping, &options)?,
+			&filename,
+			&remapping,
+			&options,
+		)?;
+		compile_ast(self.run_passes(parsed, &options)?, &filename, &options)
+	}
+
+	/// Compiles the given [`CompilationUnit`]s, which can each come from a different [`Reader`](unit::Reader)
+	/// Returns a [`Result`] containing the module name and the compiled code of every unit, in the same order
+	///
+	/// # Errors
+	/// If an error occurs while reading or compiling any unit, an [`Err`] containing a [`String`] with the error message will be returned
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{
+	///     env::ContinueMode,
+	///     unit::{CompilationUnit, Reader},
+	///     Clue,
+	/// };
+	/// use std::{collections::HashMap, path::PathBuf, sync::Arc};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let clue = Clue::new();
+	///     let vfs = HashMap::from([(PathBuf::from("legacy.clue"), String::from("return 1"))]);
+	///     let modules = clue.compile_units(&[
+	///         CompilationUnit::file("../examples/fizzbuzz.clue"),
+	///         CompilationUnit::code("main", "print(\"Hello World!\")"),
+	///         CompilationUnit::new(Reader::Virtual(Arc::new(vfs), PathBuf::from("legacy.clue")), "legacy")
+	///             .override_options(|options| options.env_continue = ContinueMode::Goto),
+	///     ])?;
+	///     assert_eq!(modules[1], (String::from("main"), String::from("print(\"Hello World!\");")));
+	///     assert_eq!(modules[2].0, "legacy");
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn compile_units(
+		&self,
+		units: &[CompilationUnit],
+	) -> Result<Vec<(String, String)>, String> {
+		units
+			.iter()
+			.map(|unit| Ok((unit.module_name.clone(), self.compile_unit(unit)?)))
+			.collect()
+	}
 }
 
 /// Creates a new [`Clue`] instance with the default options
@@ -0,0 +1,527 @@
+//! The passes module rewrites the AST between parsing and compiling.
+//!
+//! A [`Pass`] changes the AST of a file (and its static variables) in place and
+//! a [`PassManager`] runs a list of them in order.
+//! The optimizations of Clue are passes too, [`PassManager::from_options`] adds the ones
+//! enabled by the [`Options`], while projects can add their own (like removing the calls
+//! to functions only used while debugging) with [`Clue::add_pass`](crate::Clue::add_pass).
+//! With the `mlua` feature and [`Options::env_const_eval`], the `const { ... }` blocks are run by the `ConstBlocks` pass.
+
+#[cfg(feature = "mlua")]
+use crate::{
+	compile_ast,
+	env::LuaVersion,
+	parser::{
+		visitor::{walk_ctoken_mut, Transformer},
+		CodeBlock,
+		ComplexToken::{self, *},
+	},
+};
+use crate::{
+	env::Options,
+	optimize::{fold_constants, inline_functions},
+	parser::Expression,
+};
+#[cfg(feature = "mlua")]
+use mlua::{HookTriggers, Lua, LuaOptions, StdLib, Table, Value, VmState};
+#[cfg(feature = "mlua")]
+use std::{
+	cmp::Ordering,
+	ffi::c_void,
+	sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+
+/// A rewrite of the AST run between parsing and compiling, see the [module documentation](self)
+///
+/// # Example
+/// ```rust
+/// use clue_core::{
+///     env::Options,
+///     parser::{ComplexToken, Expression},
+///     passes::Pass,
+///     Clue,
+/// };
+///
+/// // removes the statements calling `debug_log`
+/// struct StripDebugLogs;
+///
+/// impl Pass for StripDebugLogs {
+///     fn name(&self) -> &str {
+///         "strip debug logs"
+///     }
+///
+///     fn run(&self, ast: &mut Expression, _: &mut Expression, _: &Options) -> Result<(), String> {
+///         ast.retain(|statement| !matches!(
+///             statement,
+///             ComplexToken::IDENT { expr, .. }
+///                 if matches!(expr.front(), Some(ComplexToken::SYMBOL(name, _)) if name == "debug_log")
+///         ));
+///         Ok(())
+///     }
+/// }
+///
+/// fn main() -> Result<(), String> {
+///     let mut clue = Clue::new();
+///     clue.add_pass(StripDebugLogs);
+///     let code = clue.compile_code("debug_log(\"start\")\nprint(1)".to_owned())?;
+///     assert_eq!(code, "print(1);");
+///
+///     Ok(())
+/// }
+/// ```
+pub trait Pass {
+	/// The name of the pass, shown in the errors it returns
+	fn name(&self) -> &str;
+
+	/// Rewrites the AST of a file and its static variables
+	///
+	/// # Errors
+	/// If the pass can't rewrite the AST, an [`Err`] containing a [`String`] with the error message should be returned
+	fn run(
+		&self,
+		ast: &mut Expression,
+		statics: &mut Expression,
+		options: &Options,
+	) -> Result<(), String>;
+}
+
+/// Folds the constant expressions and removes the branches that never run,
+/// see [`fold_constants`] and [`Options::env_fold_constants`]
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+	fn name(&self) -> &str {
+		"constant folding"
+	}
+
+	fn run(
+		&self,
+		ast: &mut Expression,
+		statics: &mut Expression,
+		_: &Options,
+	) -> Result<(), String> {
+		fold_constants(ast);
+		fold_constants(statics);
+		Ok(())
+	}
+}
+
+/// Replaces the calls to small local functions with what they return,
+/// see [`inline_functions`] and [`Options::env_inline_functions`]
+pub struct FunctionInlining;
+
+impl Pass for FunctionInlining {
+	fn name(&self) -> &str {
+		"function inlining"
+	}
+
+	fn run(
+		&self,
+		ast: &mut Expression,
+		statics: &mut Expression,
+		_: &Options,
+	) -> Result<(), String> {
+		inline_functions(ast);
+		inline_functions(statics);
+		Ok(())
+	}
+}
+
+// the libraries the `const { ... }` blocks can use
+#[cfg(feature = "mlua")]
+const LIBRARIES: [&str; 4] = ["string", "math", "table", "bit"];
+
+#[cfg(feature = "mlua")]
+/// The functions of the base library the `const { ... }` blocks can use by default, see [`ConstBlocks::globals`]
+pub const CONST_GLOBALS: [&str; 19] = [
+	"_G",
+	"_VERSION",
+	"assert",
+	"error",
+	"getmetatable",
+	"ipairs",
+	"next",
+	"pairs",
+	"pcall",
+	"rawequal",
+	"rawget",
+	"rawset",
+	"select",
+	"setmetatable",
+	"tonumber",
+	"tostring",
+	"type",
+	"unpack",
+	"xpcall",
+];
+
+#[cfg(feature = "mlua")]
+/// Runs the `const { ... }` blocks while compiling and replaces them with the value they return,
+/// so that lookup tables and hashes are computed once instead of every time the code is loaded.
+/// It's only added by [`PassManager::from_options`] when [`Options::env_const_eval`] is enabled,
+/// otherwise the blocks run when the code is loaded. It then uses the limits and the globals set by
+/// [`Options::env_const_max_instructions`], [`Options::env_const_max_memory`] and [`Options::env_const_globals`]
+///
+/// This is the only part of Clue which runs the code it compiles, so the blocks run in a sandbox:
+/// a LuaJIT state with only the `string`, `math`, `table` and `bit` libraries and the functions in [`globals`](Self::globals)
+/// (so no `io`, `os`, `require`, `load` or `print`), which fails once they run more than
+/// [`max_instructions`](Self::max_instructions) instructions or use more than [`max_memory`](Self::max_memory) bytes.
+/// They can't see the locals of the file.
+/// Their value must be `nil`, a boolean, a number, a string or a table of them (its metatable is ignored),
+/// which is written in the output as a literal
+///
+/// # Example
+/// ```rust
+/// use clue_core::Clue;
+///
+/// fn main() -> Result<(), String> {
+///     let mut clue = Clue::new();
+///     clue.const_eval(true);
+///     let code = clue.compile_code(
+///         "local squares = const { local t = {}\nfor i = 1, 4 { t[i] = i * i }\nt }".to_owned(),
+///     )?;
+///     assert_eq!(code, "local squares = {1, 4, 9, 16};");
+///     assert!(clue.compile_code("local x = const { while true {}\n1 }".to_owned()).is_err());
+///
+///     Ok(())
+/// }
+/// ```
+pub struct ConstBlocks {
+	/// The most instructions the blocks of a file can run, counted in steps of 1000
+	pub max_instructions: u64,
+
+	/// The most bytes of memory the blocks of a file can use
+	pub max_memory: usize,
+
+	/// The global functions and values the blocks can use besides the libraries, [`CONST_GLOBALS`] by default
+	pub globals: Vec<String>,
+}
+
+#[cfg(feature = "mlua")]
+impl Default for ConstBlocks {
+	fn default() -> Self {
+		Self {
+			max_instructions: 100_000_000,
+			max_memory: 64 * 1024 * 1024,
+			globals: CONST_GLOBALS
+				.iter()
+				.map(|global| global.to_string())
+				.collect(),
+		}
+	}
+}
+
+#[cfg(feature = "mlua")]
+impl ConstBlocks {
+	// the state the blocks of a file run in
+	fn sandbox(&self) -> Result<Lua, String> {
+		let libraries = StdLib::STRING | StdLib::MATH | StdLib::TABLE | StdLib::BIT;
+		let lua = Lua::new_with(libraries, LuaOptions::default()).map_err(|e| e.to_string())?;
+		lua.set_memory_limit(self.max_memory)
+			.map_err(|e| e.to_string())?;
+		let globals = lua.globals();
+		let names = globals
+			.pairs::<String, Value>()
+			.map(|pair| pair.map(|(name, _)| name))
+			.collect::<mlua::Result<Vec<String>>>()
+			.map_err(|e| e.to_string())?;
+		for name in names {
+			if !LIBRARIES.contains(&name.as_str()) && !self.globals.contains(&name) {
+				globals
+					.raw_set(name, Value::Nil)
+					.map_err(|e| e.to_string())?;
+			}
+		}
+		let max_instructions = self.max_instructions;
+		let instructions = AtomicU64::new(0);
+		lua.set_hook(
+			HookTriggers::new().every_nth_instruction(1000),
+			move |_, _| {
+				if instructions.fetch_add(1000, Relaxed) >= max_instructions {
+					Err(mlua::Error::runtime(format!(
+						"ran more than {max_instructions} instructions"
+					)))
+				} else {
+					Ok(VmState::Continue)
+				}
+			},
+		);
+		Ok(lua)
+	}
+}
+
+#[cfg(feature = "mlua")]
+impl Pass for ConstBlocks {
+	fn name(&self) -> &str {
+		"const blocks"
+	}
+
+	fn run(
+		&self,
+		ast: &mut Expression,
+		statics: &mut Expression,
+		_: &Options,
+	) -> Result<(), String> {
+		let mut evaluator = ConstEvaluator {
+			lua: self.sandbox()?,
+			error: None,
+		};
+		evaluator.transform_expression(statics);
+		evaluator.transform_expression(ast);
+		match evaluator.error {
+			Some(error) => Err(error),
+			None => Ok(()),
+		}
+	}
+}
+
+// runs the blocks from the innermost one and replaces them with the literal of their value
+#[cfg(feature = "mlua")]
+struct ConstEvaluator {
+	lua: Lua,
+	error: Option<String>,
+}
+
+#[cfg(feature = "mlua")]
+impl ConstEvaluator {
+	fn evaluate(&self, code: &CodeBlock, line: usize) -> Result<String, String> {
+		let mut options = Options {
+			env_target: Some(LuaVersion::LuaJIT),
+			..Options::default()
+		};
+		options.preset();
+		let ast = (code.code.clone(), Expression::new());
+		let code = compile_ast(ast, &String::from("(const)"), &options)?;
+		let value = self
+			.lua
+			.load(code)
+			.eval()
+			.map_err(|error| format!("The 'const' block at line {line} failed: {error}"))?;
+		lua_literal(&value, &mut Vec::new())
+			.map_err(|error| format!("The 'const' block at line {line} {error}"))
+	}
+}
+
+#[cfg(feature = "mlua")]
+impl Transformer for ConstEvaluator {
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		walk_ctoken_mut(self, ctoken);
+		if self.error.is_some() {
+			return;
+		}
+		if let CONST_BLOCK { code, span } = ctoken {
+			let span = span.clone();
+			match self.evaluate(code, span.start.line) {
+				Ok(literal) if literal.starts_with('-') => {
+					*ctoken = EXPR(Expression::from([SYMBOL(literal, span.clone())]), span)
+				}
+				Ok(literal) => *ctoken = SYMBOL(literal, span),
+				Err(error) => self.error = Some(error),
+			}
+		}
+	}
+}
+
+// writes the value as a Lua literal, `tables` are the tables it's inside of
+#[cfg(feature = "mlua")]
+fn lua_literal(value: &Value, tables: &mut Vec<*const c_void>) -> Result<String, String> {
+	Ok(match value {
+		Value::Nil => String::from("nil"),
+		Value::Boolean(boolean) => boolean.to_string(),
+		Value::Integer(n) => n.to_string(),
+		Value::Number(n) if n.is_nan() => String::from("(0/0)"),
+		Value::Number(n) if n.is_infinite() => {
+			String::from(if *n > 0.0 { "math.huge" } else { "-math.huge" })
+		}
+		// integers bigger than this cannot be represented exactly by the numbers of LuaJIT
+		Value::Number(n) if n.fract() == 0.0 && n.abs() <= (1u64 << 53) as f64 => {
+			(*n as i64).to_string()
+		}
+		Value::Number(n) => format!("{n:?}"),
+		Value::String(string) => {
+			let mut literal = String::from("\"");
+			for byte in string.as_bytes().iter() {
+				match byte {
+					b'\\' => literal += "\\\\",
+					b'"' => literal += "\\\"",
+					b'\n' => literal += "\\n",
+					b'\r' => literal += "\\r",
+					b'\t' => literal += "\\t",
+					b' '..=b'~' => literal.push(*byte as char),
+					// three digits, so that the digits after it are not part of the escape
+					_ => literal += &format!("\\{byte:03}"),
+				}
+			}
+			literal + "\""
+		}
+		Value::Table(table) => table_literal(table, tables)?,
+		value => {
+			return Err(format!(
+				"returned a {}, which can't be written as a literal",
+				value.type_name()
+			))
+		}
+	})
+}
+
+#[cfg(feature = "mlua")]
+fn table_literal(table: &Table, tables: &mut Vec<*const c_void>) -> Result<String, String> {
+	if tables.contains(&table.to_pointer()) {
+		return Err(String::from("returned a table which contains itself"));
+	}
+	tables.push(table.to_pointer());
+	let len = table.raw_len();
+	let mut items = Vec::new();
+	let mut fields = Vec::new();
+	for pair in table.pairs::<Value, Value>() {
+		let (key, value) = pair.map_err(|error| error.to_string())?;
+		match number(&key) {
+			Some(i) if i >= 1.0 && i <= len as f64 && i.fract() == 0.0 => items.push((i, value)),
+			_ => fields.push((key, value)),
+		}
+	}
+	items.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+	// the numbers first, then the strings and the booleans
+	fields.sort_by(|(a, _), (b, _)| match (a, b) {
+		(Value::String(a), Value::String(b)) => a.as_bytes().cmp(&b.as_bytes()),
+		(a, b) => match (number(a), number(b)) {
+			(Some(a), Some(b)) => a.total_cmp(&b),
+			(Some(_), _) => Ordering::Less,
+			(_, Some(_)) => Ordering::Greater,
+			_ => match (a, b) {
+				(Value::String(_), _) => Ordering::Less,
+				(_, Value::String(_)) => Ordering::Greater,
+				(a, b) => a.as_boolean().cmp(&b.as_boolean()),
+			},
+		},
+	});
+	let mut values = Vec::with_capacity(items.len() + fields.len());
+	for (_, value) in items {
+		values.push(lua_literal(&value, tables)?);
+	}
+	for (key, value) in fields {
+		let key = match &key {
+			Value::String(name) if is_lua_name(&name.as_bytes()) => name.to_string_lossy(),
+			key => format!("[{}]", lua_literal(key, tables)?),
+		};
+		values.push(format!("{key} = {}", lua_literal(&value, tables)?));
+	}
+	tables.pop();
+	Ok(format!("{{{}}}", values.join(", ")))
+}
+
+#[cfg(feature = "mlua")]
+fn number(value: &Value) -> Option<f64> {
+	match value {
+		Value::Integer(n) => Some(*n as f64),
+		Value::Number(n) => Some(*n),
+		_ => None,
+	}
+}
+
+// whether the string can be used as the key of a table without brackets
+#[cfg(feature = "mlua")]
+fn is_lua_name(name: &[u8]) -> bool {
+	name.first()
+		.is_some_and(|c| c.is_ascii_alphabetic() || *c == b'_')
+		&& name.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'_')
+		&& !matches!(
+			name,
+			b"and"
+				| b"break" | b"do"
+				| b"else" | b"elseif"
+				| b"end" | b"false"
+				| b"for" | b"function"
+				| b"goto" | b"if"
+				| b"in" | b"local"
+				| b"nil" | b"not"
+				| b"or" | b"repeat"
+				| b"return" | b"then"
+				| b"true" | b"until"
+				| b"while"
+		)
+}
+
+#[derive(Default)]
+/// A list of [`Pass`]es run in the order they were added
+pub struct PassManager {
+	passes: Vec<Box<dyn Pass + Send + Sync>>,
+}
+
+impl PassManager {
+	/// Creates a new [`PassManager`] without any pass
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a new [`PassManager`] with the built-in passes enabled by the options
+	/// ([`FunctionInlining`] and then [`ConstantFolding`]),
+	/// after `ConstBlocks` when [`Options::env_const_eval`] and the `mlua` feature are enabled
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_core::{env::Options, passes::PassManager};
+	///
+	/// let options = Options {
+	///     env_fold_constants: true,
+	///     env_inline_functions: true,
+	///     ..Options::default()
+	/// };
+	/// let passes = PassManager::from_options(&options);
+	/// assert!(passes
+	///     .names()
+	///     .ends_with(&["function inlining", "constant folding"]));
+	/// ```
+	pub fn from_options(options: &Options) -> Self {
+		let mut passes = Self::new();
+		#[cfg(feature = "mlua")]
+		if options.env_const_eval {
+			let mut blocks = ConstBlocks::default();
+			if let Some(max_instructions) = options.env_const_max_instructions {
+				blocks.max_instructions = max_instructions;
+			}
+			if let Some(max_memory) = options.env_const_max_memory {
+				blocks.max_memory = max_memory;
+			}
+			if let Some(globals) = &options.env_const_globals {
+				blocks.globals = globals.clone();
+			}
+			passes.add(blocks);
+		}
+		if options.env_inline_functions {
+			passes.add(FunctionInlining);
+		}
+		if options.env_fold_constants {
+			passes.add(ConstantFolding);
+		}
+		passes
+	}
+
+	/// Adds a pass, which runs after the ones added before it
+	pub fn add(&mut self, pass: impl Pass + Send + Sync + 'static) {
+		self.passes.push(Box::new(pass));
+	}
+
+	/// Returns the names of the passes, in the order they run
+	pub fn names(&self) -> Vec<&str> {
+		self.passes.iter().map(|pass| pass.name()).collect()
+	}
+
+	/// Runs all the passes on the AST and the static variables returned by the parser
+	///
+	/// # Errors
+	/// If a pass fails, an [`Err`] containing a [`String`] with its name and its error message will be returned
+	/// and the passes after it are not run
+	pub fn run(
+		&self,
+		(ast, statics): &mut (Expression, Expression),
+		options: &Options,
+	) -> Result<(), String> {
+		for pass in &self.passes {
+			if let Err(error) = pass.run(ast, statics, options) {
+				return Err(format!("Error in the {} pass: {error}", pass.name()));
+			}
+		}
+		Ok(())
+	}
+}
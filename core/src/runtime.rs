@@ -0,0 +1,165 @@
+//! A ready to use runtime for programs which embed Clue scripts, like games
+//!
+//! [`ClueRuntime`] owns a Lua state and a directory of Clue scripts:
+//! [`ClueRuntime::reload`] compiles and runs the scripts which changed since it was last called,
+//! so calling it once per frame (or whenever the program wants) gives hot reloading,
+//! while [`ClueRuntime::call`], [`ClueRuntime::get`] and [`ClueRuntime::set`] move values
+//! between Rust and the scripts with the types of [`mlua`].
+//!
+//! The Lua state is LuaJIT, so the [`Clue`] given to the runtime should target it
+//! (see [`Clue::target`]).
+
+use crate::{check, Clue};
+use mlua::{FromLua, FromLuaMulti, Function, IntoLua, IntoLuaMulti, Lua, Table, Value};
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+/// A Lua state running the Clue scripts of a directory, see the [module documentation](self)
+///
+/// # Example
+/// ```rust
+/// use clue_core::{env::LuaVersion, runtime::ClueRuntime, Clue};
+///
+/// fn main() -> Result<(), String> {
+///     let scripts = std::env::temp_dir().join("clue_runtime_doc");
+///     std::fs::create_dir_all(&scripts).unwrap();
+///     std::fs::write(scripts.join("player.clue"), "global speed = 2\nglobal fn step(x) { return x + speed }").unwrap();
+///
+///     let mut clue = Clue::new();
+///     clue.target(Some(LuaVersion::LuaJIT));
+///     let mut runtime = ClueRuntime::new(clue, &scripts);
+///     runtime.reload()?;
+///     runtime.set("speed", 5)?;
+///     let x: f64 = runtime.call("step", 1)?;
+///     assert_eq!(x, 6.0);
+///
+///     // nothing changed since the last reload
+///     assert!(runtime.reload()?.is_empty());
+///     std::fs::remove_dir_all(scripts).unwrap();
+///     Ok(())
+/// }
+/// ```
+pub struct ClueRuntime {
+	lua: Lua,
+	clue: Clue,
+	scripts: PathBuf,
+	modified: HashMap<PathBuf, SystemTime>,
+}
+
+// finds the .clue files inside `dir` and its subdirectories
+fn find_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) -> Result<(), String> {
+	for entry in check!(fs::read_dir(dir)) {
+		let path = check!(entry).path();
+		if path.is_dir() {
+			find_scripts(&path, scripts)?;
+		} else if path
+			.extension()
+			.is_some_and(|extension| extension == "clue")
+		{
+			scripts.push(path);
+		}
+	}
+	Ok(())
+}
+
+impl ClueRuntime {
+	/// Creates a new runtime for the scripts in `scripts` (and its subdirectories),
+	/// compiled with the options of `clue`.
+	/// No script runs until [`ClueRuntime::reload`] is called
+	pub fn new(clue: Clue, scripts: impl Into<PathBuf>) -> Self {
+		Self {
+			lua: Lua::new(),
+			clue,
+			scripts: scripts.into(),
+			modified: HashMap::new(),
+		}
+	}
+
+	/// The Lua state the scripts run in, to register Rust functions or use the rest of [`mlua`]
+	pub const fn lua(&self) -> &Lua {
+		&self.lua
+	}
+
+	/// Compiles and runs the scripts which were added or changed since the last reload,
+	/// in alphabetical order, and returns their paths.
+	/// The globals defined by the scripts which ran before are kept
+	///
+	/// # Errors
+	/// If the directory can't be read or a script fails to compile or to run,
+	/// an [`Err`] containing a [`String`] with the error message will be returned
+	/// and the following scripts are not run. The failed script runs again once it changes
+	pub fn reload(&mut self) -> Result<Vec<PathBuf>, String> {
+		let mut scripts = Vec::new();
+		find_scripts(&self.scripts, &mut scripts)?;
+		scripts.sort();
+		let mut reloaded = Vec::new();
+		for script in scripts {
+			let modified = check!(check!(fs::metadata(&script)).modified());
+			if self.modified.insert(script.clone(), modified) == Some(modified) {
+				continue;
+			}
+			let name = script.to_string_lossy().into_owned();
+			let code = self.clue.compile_tokens(self.clue.scan_file(&name)?)?;
+			if let Err(error) = self.lua.load(code).set_name(format!("@{name}")).exec() {
+				return Err(format!("Error while running {name}: {error}"));
+			}
+			reloaded.push(script);
+		}
+		Ok(reloaded)
+	}
+
+	// finds the value at a path of table keys like `player.inventory.size`
+	fn lookup(&self, path: &str) -> Result<(Table, String), String> {
+		let mut keys = path.split('.');
+		let mut table = self.lua.globals();
+		let mut key = keys.next().unwrap_or_default();
+		for next in keys {
+			table = match check!(table.get::<Value>(key)) {
+				Value::Table(inner) => inner,
+				_ => return Err(format!("'{key}' is not a table in '{path}'")),
+			};
+			key = next;
+		}
+		Ok((table, key.to_owned()))
+	}
+
+	/// Gets the value at `path`, which is the name of a global optionally followed
+	/// by the keys of the tables inside it (like `player.inventory.size`)
+	///
+	/// # Errors
+	/// If the path doesn't exist or the value can't be converted to `T`,
+	/// an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn get<T: FromLua>(&self, path: &str) -> Result<T, String> {
+		let (table, key) = self.lookup(path)?;
+		Ok(check!(table.get(key)))
+	}
+
+	/// Sets the value at `path` (see [`ClueRuntime::get`])
+	///
+	/// # Errors
+	/// If the path doesn't exist or the value can't be converted to Lua,
+	/// an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn set<T: IntoLua>(&self, path: &str, value: T) -> Result<(), String> {
+		let (table, key) = self.lookup(path)?;
+		check!(table.set(key, value));
+		Ok(())
+	}
+
+	/// Calls the function at `path` (see [`ClueRuntime::get`]) with the given arguments,
+	/// converting what it returns to `R` (which can be a tuple for many values)
+	///
+	/// # Errors
+	/// If the function doesn't exist, it fails or its result can't be converted to `R`,
+	/// an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn call<R: FromLuaMulti>(&self, path: &str, args: impl IntoLuaMulti) -> Result<R, String> {
+		let function: Function = self.get(path)?;
+		match function.call(args) {
+			Ok(result) => Ok(result),
+			Err(error) => Err(format!("Error while calling {path}: {error}")),
+		}
+	}
+}
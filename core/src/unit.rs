@@ -0,0 +1,163 @@
+//! Compilation units, the pieces of code a build made with [`Clue::compile_units`](crate::Clue::compile_units) is made of
+//!
+//! Each unit has its own [`Reader`], so a single build can mix files, strings and virtual files,
+//! and can override some of the [`Options`] of the build only for itself
+
+use std::{
+	collections::HashMap,
+	fmt, fs,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use crate::env::Options;
+
+/// A virtual file system, used to read the code of [`Reader::Virtual`] units
+pub trait VirtualFs {
+	/// Reads the file at the given path
+	///
+	/// # Errors
+	/// If the file cannot be read, an [`Err`] containing a [`String`] with the error message will be returned
+	fn read(&self, path: &Path) -> Result<String, String>;
+}
+
+impl VirtualFs for HashMap<PathBuf, String> {
+	fn read(&self, path: &Path) -> Result<String, String> {
+		self.get(path)
+			.cloned()
+			.ok_or_else(|| format!("{} was not found!", path.display()))
+	}
+}
+
+/// Where the code of a [`CompilationUnit`] comes from
+#[derive(Clone)]
+pub enum Reader {
+	/// A file on disk
+	File(PathBuf),
+
+	/// Code given as a string
+	Code(String),
+
+	/// A file of a [`VirtualFs`]
+	Virtual(Arc<dyn VirtualFs + Send + Sync>, PathBuf),
+}
+
+impl Reader {
+	/// Reads the code of the unit
+	///
+	/// # Errors
+	/// If the code cannot be read, an [`Err`] containing a [`String`] with the error message will be returned
+	pub fn read(&self) -> Result<String, String> {
+		match self {
+			Reader::File(path) => {
+				fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))
+			}
+			Reader::Code(code) => Ok(code.clone()),
+			Reader::Virtual(vfs, path) => vfs.read(path),
+		}
+	}
+
+	/// Returns the name used for the unit in error messages
+	pub fn filename(&self) -> String {
+		match self {
+			Reader::File(path) | Reader::Virtual(_, path) => path
+				.file_name()
+				.map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy())
+				.into_owned(),
+			Reader::Code(_) => String::from("(library)"),
+		}
+	}
+}
+
+impl fmt::Debug for Reader {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Reader::File(path) => f.debug_tuple("File").field(path).finish(),
+			Reader::Code(code) => f.debug_tuple("Code").field(code).finish(),
+			Reader::Virtual(_, path) => f.debug_tuple("Virtual").field(path).finish(),
+		}
+	}
+}
+
+/// Changes made to the [`Options`] of the build for a single [`CompilationUnit`],
+/// they should call [`Options::preset`] if they change `env_target`
+pub type OptionsOverrides = Arc<dyn Fn(&mut Options) + Send + Sync>;
+
+/// A piece of code compiled as a module of a build
+///
+/// # Example
+/// ```rust
+/// use clue_core::{env::ContinueMode, unit::CompilationUnit};
+///
+/// let unit = CompilationUnit::code("main", "print(\"Hello World!\")")
+///     .override_options(|options| options.env_continue = ContinueMode::Goto);
+/// assert_eq!(unit.module_name, "main");
+/// ```
+#[derive(Clone)]
+pub struct CompilationUnit {
+	/// Where the code of the unit comes from
+	pub reader: Reader,
+
+	/// The changes made to the options of the build for this unit, if any
+	pub overrides: Option<OptionsOverrides>,
+
+	/// The name the unit can be imported with
+	pub module_name: String,
+}
+
+impl CompilationUnit {
+	/// Creates a new [`CompilationUnit`] given its [`Reader`] and its module name
+	pub fn new(reader: Reader, module_name: impl Into<String>) -> Self {
+		Self {
+			reader,
+			overrides: None,
+			module_name: module_name.into(),
+		}
+	}
+
+	/// Creates a [`CompilationUnit`] that reads the file at the given path,
+	/// its module name is the path without the `.clue` extension and with dots instead of slashes
+	pub fn file(path: impl Into<PathBuf>) -> Self {
+		let path = path.into();
+		let module_name = path
+			.with_extension("")
+			.components()
+			.map(|component| component.as_os_str().to_string_lossy())
+			.collect::<Vec<_>>()
+			.join(".");
+		Self::new(Reader::File(path), module_name)
+	}
+
+	/// Creates a [`CompilationUnit`] with the given module name and code
+	pub fn code(module_name: impl Into<String>, code: impl Into<String>) -> Self {
+		Self::new(Reader::Code(code.into()), module_name)
+	}
+
+	/// Sets the changes made to the options of the build for this unit
+	pub fn override_options(
+		mut self,
+		overrides: impl Fn(&mut Options) + Send + Sync + 'static,
+	) -> Self {
+		self.overrides = Some(Arc::new(overrides));
+		self
+	}
+
+	/// Returns the options this unit is compiled with, given the ones of the build
+	pub fn options(&self, options: &Options) -> Options {
+		let mut options = options.clone();
+		if let Some(overrides) = &self.overrides {
+			overrides(&mut options);
+		}
+		options
+	}
+}
+
+impl fmt::Debug for CompilationUnit {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CompilationUnit")
+			.field("reader", &self.reader)
+			.field("overrides", &self.overrides.is_some())
+			.field("module_name", &self.module_name)
+			.finish()
+	}
+}
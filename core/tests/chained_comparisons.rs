@@ -0,0 +1,45 @@
+//! Tests for the chained comparisons, which must evaluate each operand at most once
+//! and keep the short-circuiting of `and` and `or`.
+
+mod common;
+
+use clue_core::Clue;
+
+fn compile(code: &str) -> String {
+	let mut clue = Clue::new();
+	clue.chained_comparisons(true);
+	clue.compile_code(code.to_owned())
+		.unwrap_or_else(|e| panic!("could not compile {code}: {e}"))
+}
+
+#[test]
+fn middle_evaluated_in_the_expression() {
+	assert_eq!(
+		compile("if t != nil && 0 < t.n < 10 { print(t) }"),
+		"local _internal0;\n\
+		if t~=nil and 0<(function()\n\
+		\t_internal0 = t.n;\n\
+		\treturn _internal0;\n\
+		end)() and _internal0<10 then\n\
+		\tprint(t);\n\
+		end"
+	);
+	assert_eq!(compile("local x = 0 <= a < 10"), "local x = 0<=a and a<10;");
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn short_circuits() {
+	let code = compile(
+		"local calls = 0
+		local fn n(t) { calls += 1; return t.n }
+		local fn check(t) { return t != nil && 0 < n(t) < 10 }
+		local fn count(...) { return 0 < select(\"#\", ...) <= 2 }
+		print(check(nil), check({n = 5}), check({n = 50}), calls)
+		print(count(1, 2), count(1, 2, 3))
+		while 0 < n({n = calls}) < 5 { }
+		print(calls)",
+	);
+	let output = common::run(&code).unwrap_or_else(|e| panic!("could not run {code}: {e}"));
+	assert_eq!(output, ["false\ttrue\tfalse\t2", "true\tfalse", "6"]);
+}
@@ -0,0 +1,57 @@
+//! Helpers shared by the tests which compile Clue code and run the output.
+
+#![allow(dead_code)]
+
+use clue_core::{env::LuaVersion, Clue};
+#[cfg(feature = "lua54")]
+use mlua54 as mlua;
+
+/// The version of Lua [`run`] uses: LuaJIT with the `mlua` feature and Lua 5.4 with the `lua54` one
+#[cfg(feature = "lua54")]
+pub const RUNTIME: LuaVersion = LuaVersion::Lua54;
+/// The version of Lua [`run`] uses: LuaJIT with the `mlua` feature and Lua 5.4 with the `lua54` one
+#[cfg(not(feature = "lua54"))]
+pub const RUNTIME: LuaVersion = LuaVersion::LuaJIT;
+
+/// Compiles the code for the target, or for any version of Lua when it's [`None`]
+pub fn compile(target: Option<LuaVersion>, code: &str) -> Result<String, String> {
+	let mut clue = Clue::new();
+	clue.target(target);
+	clue.compile_code(code.to_owned())
+}
+
+/// Compiles the code for any version of Lua, panicking with the error when it fails
+pub fn compile_ok(code: &str) -> String {
+	compile(None, code).unwrap_or_else(|e| panic!("could not compile {code}: {e}"))
+}
+
+/// Runs the compiled code with the [`RUNTIME`] and returns the lines it printed
+#[cfg(any(feature = "mlua", feature = "lua54"))]
+pub fn run(code: &str) -> Result<Vec<String>, String> {
+	use mlua::{Lua, LuaOptions, StdLib};
+	// the code compiled with `--debug` needs the debug library, which `Lua::new` leaves out
+	// SAFETY: the tests only run code they compiled themselves
+	let lua = unsafe { Lua::unsafe_new_with(StdLib::ALL_SAFE | StdLib::DEBUG, LuaOptions::new()) };
+	lua.load(
+		"_output = {}
+		function print(...)
+			local values = {}
+			for i = 1, select('#', ...) do values[i] = tostring((select(i, ...))) end
+			_output[#_output + 1] = table.concat(values, '\t')
+		end",
+	)
+	.exec()
+	.map_err(|e| e.to_string())?;
+	lua.load(code).exec().map_err(|e| e.to_string())?;
+	lua.globals()
+		.get::<Vec<String>>("_output")
+		.map_err(|e| e.to_string())
+}
+
+/// Compiles the code for LuaJIT, runs it and returns the lines it printed
+#[cfg(feature = "mlua")]
+pub fn compile_and_run(code: &str) -> Vec<String> {
+	let compiled = compile(Some(LuaVersion::LuaJIT), code)
+		.unwrap_or_else(|e| panic!("could not compile {code}: {e}"));
+	run(&compiled).unwrap_or_else(|e| panic!("could not run {compiled}: {e}"))
+}
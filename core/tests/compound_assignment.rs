@@ -0,0 +1,51 @@
+//! Tests for compound assignments like `t[k] += 1`, whose receiver and key
+//! are only evaluated once when they can have side effects.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn pure_targets_are_repeated() {
+	assert_eq!(compile_ok("t.count += 1"), "t.count = t.count + 1;");
+	assert_eq!(
+		compile_ok("t[k] ..= \"x\""),
+		"t[((k))] = t[((k))] .. \"x\";"
+	);
+}
+
+#[test]
+fn impure_parts_are_saved() {
+	assert_eq!(
+		compile_ok("get().n += 1"),
+		"local _internal0 = get();\n_internal0.n = _internal0.n + 1;"
+	);
+	assert_eq!(
+		compile_ok("t[next()] *= 2"),
+		"local _internal0 = next();\nt[((_internal0))] = t[((_internal0))] * 2;"
+	);
+	assert_eq!(
+		compile_ok("a.b[f()].c -= g()"),
+		"local _internal0 = a.b[((f()))];\n_internal0.c = _internal0.c - g();"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn side_effects_happen_once() {
+	let output = common::compile_and_run(
+		"local calls = {}
+		local fn count(name, value) {
+			calls[name] = (calls[name] || 0) + 1
+			return value
+		}
+		local t = {n = 1, list = {10, 20}, s = \"a\"}
+		count(\"t\", t).n += 1
+		count(\"t\", t).list[count(\"k\", 2)] *= 3
+		t[count(\"s\", \"s\")] ..= \"b\"
+		for _ = 1, 2 { count(\"t\", t).n += 1 }
+		print(t.n, t.list[1], t.list[2], t.s)
+		print(calls.t, calls.k, calls.s)",
+	);
+	assert_eq!(output, ["4\t10\t60\tab", "4\t1\t1"]);
+}
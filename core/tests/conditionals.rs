@@ -0,0 +1,83 @@
+//! Tests for `if` expressions and `? :`, which are compiled to `and`/`or`
+//! only when the value they pick can't be `false` or `nil`.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn truthy_values_use_and_or() {
+	assert_eq!(
+		compile_ok("local x = c ? 1 : 2"),
+		"local x = ((c) and 1 or (2));"
+	);
+	assert_eq!(
+		compile_ok("local x = if a { \"a\" } elseif b { \"b\" } else { \"c\" }"),
+		"local x = ((a) and \"a\" or (((b) and \"b\" or (\"c\"))));"
+	);
+}
+
+#[test]
+fn falsy_values_use_if() {
+	let expected = "local _internal0;\n\
+		if c then\n\
+		\t_internal0 = false;\n\
+		else\n\
+		\t_internal0 = 2;\n\
+		end\n\
+		local x = _internal0;";
+	assert_eq!(compile_ok("local x = c ? false : 2"), expected);
+	assert_eq!(compile_ok("local x = if c { false } else { 2 }"), expected);
+}
+
+#[test]
+fn lazy_operands_are_functions() {
+	assert_eq!(
+		compile_ok("local x = a || if c { f() } else { nil }"),
+		"local x = a or (function()\n\
+		\tlocal _internal0;\n\
+		\tif c then\n\
+		\t\t_internal0 = f();\n\
+		\telse\n\
+		\t\t_internal0 = nil;\n\
+		\tend\n\
+		\treturn _internal0;\n\
+		end)();"
+	);
+}
+
+#[test]
+fn else_if_is_elseif() {
+	assert_eq!(
+		compile_ok("local x = if a { 1 } else if b { 2 } else { 3 }"),
+		compile_ok("local x = if a { 1 } elseif b { 2 } else { 3 }")
+	);
+	assert_eq!(
+		compile_ok("if a { f() } else if b { g() } else { h() }"),
+		"if a then\n\tf();\nelseif b then\n\tg();\nelse\n\th();\nend"
+	);
+}
+
+#[test]
+fn if_needs_else() {
+	assert!(common::compile(None, "local x = if c { 1 }").is_err());
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn picks_the_right_value() {
+	let output = common::compile_and_run(
+		"local calls = 0
+		local fn call(value) { calls += 1; return value }
+		local fn pick(c) { return c ? call(false) : call(nil) }
+		local fn size(n) { return if n > 10 { \"big\" } else if n > 5 { \"medium\" } else { \"small\" } }
+		print(pick(true), pick(false), calls)
+		print(size(20), size(7), size(1))
+		print(true ? nil : 1, false ? 1 : nil)
+		print(1 || (true ? call(false) : 1), calls)",
+	);
+	assert_eq!(
+		output,
+		["false\tnil\t2", "big\tmedium\tsmall", "nil\tnil", "1\t2"]
+	);
+}
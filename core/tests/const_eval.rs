@@ -0,0 +1,108 @@
+//! Tests for the `const { ... }` blocks, which only run while compiling when `const_eval` is enabled.
+
+use clue_core::Clue;
+
+fn compile(code: &str, const_eval: bool) -> Result<String, String> {
+	let mut clue = Clue::new();
+	clue.const_eval(const_eval);
+	clue.compile_code(code.to_owned())
+}
+
+#[test]
+fn runs_when_loaded_by_default() {
+	let code = compile("local x = const { 1 + 2 }", false).unwrap();
+	assert_eq!(code, "local x = (function()\n\treturn 1+2;\nend)();");
+}
+
+#[test]
+fn locals_named_const_are_not_run() {
+	let code = "local _constants = (fn() { return os.exit(1) })()";
+	for const_eval in [false, true] {
+		let output = compile(code, const_eval).unwrap();
+		assert!(output.contains("os.exit(1)"), "{output}");
+	}
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn replaced_by_literals() {
+	assert_eq!(
+		compile("local x = const { 1 + 2 }", true).unwrap(),
+		"local x = 3;"
+	);
+	assert_eq!(
+		compile("local x = const { string.rep(\"ab\", 2) }", true).unwrap(),
+		"local x = \"abab\";"
+	);
+	assert_eq!(
+		compile("local x = const { {a = -1, [2] = true} }", true).unwrap(),
+		"local x = {[2] = true, a = -1};"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn sandboxed() {
+	for code in [
+		"local x = const { io.open(\"file\") }",
+		"local x = const { os.exit(1) }",
+		"local x = const { require(\"os\") }",
+		"local x = const { loadstring(\"return 1\") }",
+		"local x = const { dofile(\"main.lua\") }",
+		"local x = const { print(1) }",
+	] {
+		let error = compile(code, true).expect_err(code);
+		assert!(error.contains("nil value"), "{code}: {error}");
+	}
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn limited() {
+	let error = compile("local x = const { while true {}\n1 }", true).unwrap_err();
+	assert!(error.contains("instructions"), "{error}");
+	let error = compile(
+		"local x = const { local t = {}\nfor i = 1, 100000000 { t[i] = string.rep(\"x\", 100) .. i }\nt }",
+		true,
+	)
+	.unwrap_err();
+	assert!(error.contains("memory"), "{error}");
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn limits_and_globals_can_be_set() {
+	let loop_code = "local x = const { local n = 0\nfor i = 1, 100000 { n += i }\nn }";
+	let table_code =
+		"local x = const { local t = {}\nfor i = 1, 10000 { t[i] = string.rep(\"x\", 100) .. i }\n#t }";
+	let compile_with = |code: &str, set: &dyn Fn(&mut Clue)| {
+		let mut clue = Clue::new();
+		clue.const_eval(true);
+		set(&mut clue);
+		clue.compile_code(code.to_owned())
+	};
+	assert_eq!(compile(loop_code, true).unwrap(), "local x = 5000050000;");
+	let error =
+		compile_with(loop_code, &|clue| clue.const_max_instructions(Some(10_000))).unwrap_err();
+	assert!(
+		error.contains("ran more than 10000 instructions"),
+		"{error}"
+	);
+	assert_eq!(compile(table_code, true).unwrap(), "local x = 10000;");
+	let error =
+		compile_with(table_code, &|clue| clue.const_max_memory(Some(512 * 1024))).unwrap_err();
+	assert!(error.contains("memory"), "{error}");
+	let globals = Some(vec![String::from("tostring")]);
+	let error = compile_with("local x = const { type(1) }", &|clue| {
+		clue.const_globals(globals.clone())
+	})
+	.unwrap_err();
+	assert!(error.contains("nil value"), "{error}");
+	assert_eq!(
+		compile_with("local x = const { tostring(1) }", &|clue| {
+			clue.const_globals(globals.clone())
+		})
+		.unwrap(),
+		"local x = \"1\";"
+	);
+}
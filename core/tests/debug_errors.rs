@@ -0,0 +1,62 @@
+//! Tests for the errors of the code compiled with `--debug`, which tell the function
+//! they were raised in and its lines in the Clue code.
+
+mod common;
+
+#[cfg(feature = "mlua")]
+use clue_core::helpers::add_debug_wrapper;
+use clue_core::Clue;
+
+fn compile(code: &str) -> String {
+	let mut clue = Clue::new();
+	clue.debug(true);
+	clue.compile_code(code.to_owned())
+		.unwrap_or_else(|e| panic!("could not compile {code}: {e}"))
+}
+
+#[test]
+fn functions_are_called_through_clue_call() {
+	let code = compile("local fn add(a, b) {\n\treturn a + b\n}");
+	assert_eq!(code.matches("local function _clue_call(").count(), 1);
+	assert!(
+		code.contains(
+			"return _clue_call(\"function 'add' (lines 1-3)\", \"(library)\", function(a, b)"
+		),
+		"{code}"
+	);
+	assert!(code.contains("end, a, b)"), "{code}");
+}
+
+// runs the code inside the wrapper the CLI writes around the code compiled with `--debug`
+#[cfg(feature = "mlua")]
+fn run(code: &str) -> Result<Vec<String>, String> {
+	common::run(&add_debug_wrapper(&compile(code), "(library)"))
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn errors_tell_the_function() {
+	let error = run("local fn fail(x) {\n\terror(\"bad \" .. x)\n}\n\nfail(1)").unwrap_err();
+	assert!(
+		error.contains("(library):2: in function 'fail' (lines 1-3): bad 1"),
+		"{error}"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn caught_errors_and_results_are_kept() {
+	let output = run("local fn pair(a) {
+			if a == nil { error(\"no value\") }
+			return a, a * 2
+		}
+		print(pair(2))
+		local ok, err = pcall(pair)
+		print(ok, err)
+		print(pair(3))")
+	.unwrap();
+	assert_eq!(output[0], "2\t4");
+	assert!(output[1].starts_with("false\t"), "{output:?}");
+	assert!(output[1].contains("no value"), "{output:?}");
+	assert_eq!(output[2], "3\t6");
+}
@@ -0,0 +1,59 @@
+//! Tests for table destructuring, where `key = name` renames a key and `= value` after it is its default.
+
+mod common;
+
+use common::compile;
+
+#[test]
+fn renames() {
+	assert_eq!(
+		compile(None, "local {x, y, name = n} = point").unwrap(),
+		"local _internal0 = point;\n\
+		local x, y, n = _internal0.x, _internal0.y, _internal0.name;"
+	);
+	assert_eq!(
+		compile(None, "local a, b\n{x = a, y => b} = point").unwrap(),
+		"local a, b;\n\
+		local _internal0 = point;\n\
+		a, b = _internal0.x, _internal0.y;"
+	);
+}
+
+#[test]
+fn defaults() {
+	assert_eq!(
+		compile(
+			None,
+			"local {x = 0, y = (DEFAULT), name = n = \"none\"} = point"
+		)
+		.unwrap(),
+		"local _internal0 = point;\n\
+		local _internal1 = _internal0.x;\n\
+		if _internal1 == nil then\n\
+		\t_internal1 = 0;\n\
+		end\n\
+		local _internal2 = _internal0.y;\n\
+		if _internal2 == nil then\n\
+		\t_internal2 = (DEFAULT);\n\
+		end\n\
+		local _internal3 = _internal0.name;\n\
+		if _internal3 == nil then\n\
+		\t_internal3 = \"none\";\n\
+		end\n\
+		local x, y, n = _internal1, _internal2, _internal3;"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn runs() {
+	let output = common::compile_and_run(
+		"local DEFAULT = 3
+		local point = {x = 1, name = \"p\", inner = {z = 2}}
+		local {x, y = (DEFAULT), name = n, missing = m = \"m\", inner => {z = depth}} = point
+		print(x, y, n, m, depth)
+		{x = y, name => n} = {x = 10, name = \"q\"}
+		print(y, n)",
+	);
+	assert_eq!(output, ["1\t3\tp\tm\t2", "10\tq"]);
+}
@@ -0,0 +1,62 @@
+//! Tests for `do { ... }` expressions, whose statements are moved before the expression
+//! unless it's only evaluated when needed (like after `&&`), where they are run by a function.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn statements_come_first() {
+	assert_eq!(
+		compile_ok("local x = do {\n\tlocal t = f()\n\tt.value * 2\n}"),
+		"local _internal0;\n\
+		do\n\
+		\tlocal t = f();\n\
+		\t_internal0 = t.value*2;\n\
+		end\n\
+		local x = _internal0;"
+	);
+	assert_eq!(compile_ok("local x = 1 + do { 2 }"), "local x = 1+(2);");
+}
+
+#[test]
+fn lazy_operands_are_functions() {
+	assert_eq!(
+		compile_ok("local x = a && do {\n\tprint(1)\n\t2\n}"),
+		"local x = a and (function()\n\
+		\tlocal _internal0;\n\
+		\tdo\n\
+		\t\tprint(1);\n\
+		\t\t_internal0 = 2;\n\
+		\tend\n\
+		\treturn _internal0;\n\
+		end)();"
+	);
+	assert!(common::compile(
+		None,
+		"local fn f() { return a || do { if b { return 1 }\n 2 } }"
+	)
+	.is_err());
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn runs_only_when_needed() {
+	let output = common::compile_and_run(
+		"local runs = 0
+		local fn load() { return {value = 21} }
+		local doubled = do {
+			local t = load()
+			t.value * 2
+		}
+		local fn pick(a, ...) {
+			return a || do {
+				runs += 1
+				select(\"#\", ...)
+			}
+		}
+		print(doubled, 1 + do { 2 })
+		print(pick(5, 1, 2), pick(nil, 1, 2), pick(false), runs)",
+	);
+	assert_eq!(output, ["42\t3", "5\t2\t0\t2"]);
+}
@@ -0,0 +1,51 @@
+//! Golden tests for the enums, whose values are computed while compiling.
+
+mod common;
+
+use common::compile;
+
+#[test]
+fn values() {
+	assert_eq!(
+		compile(None, "local enum { LOW, MEDIUM, HIGH = 10, HIGHEST, NEGATIVE = -(2 + 3) }").unwrap(),
+		"local LOW = 1;\nlocal MEDIUM = 2;\nlocal HIGH = 10;\nlocal HIGHEST = 11;\nlocal NEGATIVE = -5;"
+	);
+	assert_eq!(
+		compile(
+			None,
+			"local enum Color { RED = 0xFF0000, BLUE = 0x0000FF, PURPLE = RED + BLUE }"
+		)
+		.unwrap(),
+		"local Color = {\n\tRED = 16711680, \n\tBLUE = 255, \n\tPURPLE = 16711935\n};"
+	);
+	assert_eq!(
+		compile(
+			None,
+			"global enum Mode { READ = \"r\", WRITE = \"w\" .. \"+\" }"
+		)
+		.unwrap(),
+		"Mode = {\n\tREAD = \"r\", \n\tWRITE = \"w+\"\n};"
+	);
+}
+
+#[test]
+fn invalid_values() {
+	for code in [
+		"local enum { A = \"a\", B }",
+		"local enum { A = f() }",
+		"local enum { A = 1.5 }",
+		"local enum { A = UNKNOWN + 1 }",
+	] {
+		assert!(compile(None, code).is_err(), "{code}");
+	}
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn runs() {
+	let output = common::compile_and_run(
+		"local enum Flags { READ = 1, WRITE = READ * 2, ALL = READ + WRITE, NAME = \"flags\" }
+		print(Flags.READ, Flags.WRITE, Flags.ALL, Flags.NAME)",
+	);
+	assert_eq!(output, ["1\t2\t3\tflags"]);
+}
@@ -0,0 +1,71 @@
+//! Tests for the `$log_*!` macros, which are removed with their arguments
+//! unless `LOG_LEVEL` enables their level.
+//!
+//! `LOG_LEVEL` and `LOG_FUNCTION` are environment variables shared by the tests,
+//! so each one holds [`ENV`] while it sets them and compiles.
+
+mod common;
+
+use std::{env, sync::Mutex};
+
+static ENV: Mutex<()> = Mutex::new(());
+
+const CODE: &str = "local fn f(n) {
+	$log_debug!(\"f\", n)
+	$log_info!(\"info\")
+	$log_error!(\"error\", n * 2)
+	return n
+}
+print(f(1))";
+
+fn compile(level: Option<&str>, function: Option<&str>) -> Result<String, String> {
+	match level {
+		Some(level) => env::set_var("LOG_LEVEL", level),
+		None => env::remove_var("LOG_LEVEL"),
+	}
+	match function {
+		Some(function) => env::set_var("LOG_FUNCTION", function),
+		None => env::remove_var("LOG_FUNCTION"),
+	}
+	common::compile(None, CODE)
+}
+
+#[test]
+fn stripped_by_default() {
+	let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+	let code = compile(None, None).unwrap();
+	assert_eq!(code, "local function f(n)\n\treturn n;\nend\nprint(f(1));");
+	assert_eq!(compile(Some("none"), None).unwrap(), code);
+}
+
+#[test]
+fn levels_from_the_given_one() {
+	let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+	assert_eq!(
+		compile(Some("INFO"), None).unwrap(),
+		"local function f(n)\n\
+		\tprint(\"[INFO]\", \"info\");\n\
+		\tprint(\"[ERROR]\", \"error\", n*2);\n\
+		\treturn n;\n\
+		end\n\
+		print(f(1));"
+	);
+	assert!(compile(Some("verbose"), None)
+		.unwrap_err()
+		.contains("Unknown log level 'verbose'"));
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn calls_the_log_function() {
+	let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+	let code = compile(Some("debug"), Some("log")).unwrap();
+	let output = common::run(&format!(
+		"function log(level, ...) print(level, ...) end\n{code}"
+	))
+	.unwrap();
+	assert_eq!(
+		output,
+		["debug\tf\t1", "info\tinfo", "error\terror\t2", "1"]
+	);
+}
@@ -0,0 +1,54 @@
+//! Tests for the guards of match arms, where `x if ...` binds the matched value to `x`
+//! and `(x) if ...` compares it with the variable `x`.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn name_binds_the_value() {
+	assert_eq!(
+		compile_ok("local s = match f() { n if n > 1 => n * 2, default => 0 }"),
+		"local _internal0 = f();\n\
+		do\n\
+		\tlocal n = _internal0;\n\
+		\tif n>1 then\n\
+		\t\t_internal0 = n*2;\n\
+		\telse\n\
+		\t\t_internal0 = 0;\n\
+		\tend\n\
+		end\n\
+		local s = _internal0;"
+	);
+}
+
+#[test]
+fn brackets_compare_with_the_variable() {
+	assert_eq!(
+		compile_ok("match x { (limit) if y => { print(\"limit\") } }"),
+		"local _internal0 = x;\n\
+		if ((_internal0 == (limit))) and y then\n\
+		\tprint(\"limit\");\n\
+		end"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn first_matching_arm_runs() {
+	let output = common::compile_and_run(
+		"local limit = 4
+		local fn describe(n) {
+			return match n {
+				0 => \"none\",
+				x if x < 0 => \"negative\",
+				(limit) if n % 2 == 0 => \"limit\",
+				x if x > 10 => \"many (\" .. x .. \")\",
+				default => \"some\"
+			}
+		}
+		local values = {0, -3, 4, 25, 5}
+		for _, n in values { print(describe(n)) }",
+	);
+	assert_eq!(output, ["none", "negative", "limit", "many (25)", "some"]);
+}
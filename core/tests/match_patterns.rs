@@ -0,0 +1,49 @@
+//! Tests for the table patterns of `match`, where `key` and `key = name` bind a field
+//! and `key == value` compares it.
+
+mod common;
+
+use common::compile;
+
+#[test]
+fn binds_and_compares() {
+	assert_eq!(
+		compile(None, "match msg { {text = t, kind == KIND, size = 1} => { print(t) } }").unwrap(),
+		"local _internal0 = msg;\n\
+		do\n\
+		\tlocal _internal1 = type(_internal0) == \"table\" and _internal0.kind == (KIND) and _internal0.size == (1);\n\
+		\tlocal t;\n\
+		\tif _internal1 then\n\
+		\t\tt = _internal0.text;\n\
+		\tend\n\
+		\tif _internal1 then\n\
+		\t\tprint(t);\n\
+		\tend\n\
+		end"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn runs() {
+	let output = common::compile_and_run(
+		"local SAY = \"say\"
+		local fn handle(msg) {
+			match msg {
+				{type == SAY, text = t} => { print(\"say\", t) }
+				{type = \"move\", x, y = vertical} if x > 0 => { print(\"move\", x, vertical) }
+				{type = \"nested\", pos = {x = left}} => { print(\"nested\", left) }
+				default => { print(\"other\") }
+			}
+		}
+		handle({type = \"say\", text = \"hi\"})
+		handle({type = \"move\", x = 1, y = 2})
+		handle({type = \"move\", x = 0, y = 2})
+		handle({type = \"nested\", pos = {x = 5}})
+		handle({type = \"SAY\", text = \"hi\"})",
+	);
+	assert_eq!(
+		output,
+		["say\thi", "move\t1\t2", "other", "nested\t5", "other"]
+	);
+}
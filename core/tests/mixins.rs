@@ -0,0 +1,100 @@
+//! Tests for `struct Name with Mixin1, Mixin2`, which copies the methods of other structs
+//! and the fields of other tables.
+
+mod common;
+
+use clue_core::{env::Language, Clue};
+use common::compile_ok;
+
+const MIXINS: &str = "local struct A {\n\tmethod f() { return 1 }\n}\n\
+	local struct B {\n\tmethod g() { return 2 }\n}\n";
+
+#[test]
+fn methods_are_copied() {
+	let code = compile_ok(&format!("{MIXINS}local struct C with A, B {{}}\nreturn C"));
+	assert!(
+		code.ends_with("\nC.f = A.f;\nC.g = B.g;\nreturn C;"),
+		"{code}"
+	);
+}
+
+#[test]
+fn own_methods_are_kept() {
+	let code = compile_ok(&format!(
+		"{MIXINS}local struct C with A, B {{\n\tmethod f() {{ return 3 }}\n}}"
+	));
+	assert!(code.contains("C.g = B.g;"), "{code}");
+	assert!(!code.contains("C.f = A.f;"), "{code}");
+}
+
+#[test]
+fn conflicts_are_errors() {
+	let error = common::compile(
+		None,
+		"local struct A {\n\tmethod f() {}\n}\nlocal struct B {\n\tmethod f() {}\n}\n\
+		local struct C with A, B {}",
+	)
+	.unwrap_err();
+	assert!(
+		error.contains("'f' is defined by both 'A' and 'B', struct 'C' must define it itself"),
+		"{error}"
+	);
+	compile_ok(
+		"local struct A {\n\tmethod f() {}\n}\nlocal struct B {\n\tmethod f() {}\n}\n\
+		local struct C with A, B {\n\tmethod f() {}\n}",
+	);
+}
+
+#[test]
+fn tables_are_copied_at_runtime() {
+	let code = compile_ok(&format!(
+		"{MIXINS}local T = {{}}\nlocal struct C with A, T {{}}\nreturn C"
+	));
+	assert!(
+		code.ends_with(
+			"\nC.f = A.f;\n\
+			for _internal0, _internal1 in pairs(T) do\n\
+			\tif C[_internal0] == nil then\n\
+			\t\tC[_internal0] = _internal1;\n\
+			\tend\n\
+			end\n\
+			return C;"
+		),
+		"{code}"
+	);
+}
+
+#[test]
+fn teal_mixins_must_be_structs() {
+	let mut clue = Clue::new();
+	clue.language(Language::Teal);
+	let error = clue
+		.compile_code(String::from("local t = {}\nlocal struct C with t {}"))
+		.unwrap_err();
+	assert!(
+		error.contains("'t' is not a struct declared before, Teal structs can only mix in structs"),
+		"{error}"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn tables_never_replace_methods() {
+	let output = common::compile_and_run(&format!(
+		"{MIXINS}local T = {{f = fn(self) {{ return 4 }}, h = fn(self) {{ return 5 }}, new = 6}}
+		local U = {{h = fn(self) {{ return 7 }}, i = fn(self) {{ return 8 }}}}
+		local struct C with A, T, U {{
+			method g() {{ return 3 }}
+		}}
+		local c = C.new()
+		print(c::f(), c::g(), c::h(), c::i())"
+	));
+	assert_eq!(output, ["1\t3\t5\t8"]);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn mixed_in_methods_use_self() {
+	let output = common::compile_and_run(include_str!("../../examples/structs.clue"));
+	assert_eq!(output, ["5", "1\t0", "I AM A DOG"]);
+}
@@ -0,0 +1,56 @@
+//! Tests for the named arguments moved to the position of their parameters,
+//! which are still evaluated in the order they were written.
+
+mod common;
+
+use clue_core::{
+	env::{LuaVersion, NamedArgsMode},
+	Clue,
+};
+
+const POS: &str = "local fn pos(a, b, c) { return a, b, c }\n";
+
+// compiles the code after the declaration of `pos`
+fn compile_with_pos(code: &str) -> String {
+	let mut clue = Clue::new();
+	clue.target(Some(LuaVersion::LuaJIT));
+	clue.named_args_mode(NamedArgsMode::Positional);
+	clue.compile_code(format!("{POS}{code}"))
+		.unwrap_or_else(|e| panic!("could not compile {code}: {e}"))
+}
+
+// the code compiled after the declaration of `pos`
+fn compile(code: &str) -> String {
+	let compiled = compile_with_pos(code);
+	compiled.split_once("end\n").unwrap().1.to_owned()
+}
+
+#[test]
+fn ordered_arguments_stay_in_the_call() {
+	assert_eq!(compile("pos(f(), c = g())"), "pos(f(), nil, g());");
+	assert_eq!(compile("pos(c = 3, a = 1)"), "pos(1, nil, 3);");
+}
+
+#[test]
+fn reordered_arguments_are_evaluated_first() {
+	assert_eq!(
+		compile("pos(f(), c = g(), b = 2)"),
+		"local _internal0 = f();\n\
+		local _internal1 = g();\n\
+		pos(_internal0, 2, _internal1);"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn evaluated_in_source_order() {
+	let output = common::run(&compile_with_pos(
+		"local n = 0
+		local fn k() { n += 1; return n }
+		print(pos(c = k(), a = k()))
+		print(pos(k(), c = k(), b = k()))
+		print(n)",
+	))
+	.unwrap();
+	assert_eq!(output, ["2\tnil\t1", "3\t5\t4", "5"]);
+}
@@ -0,0 +1,38 @@
+//! Tests for the ordered tables, which `pairs` iterates in the order their keys were added.
+
+mod common;
+
+use clue_core::env::LuaVersion::*;
+use common::compile;
+
+#[test]
+fn rejected_before_lua52() {
+	for target in [LuaJIT, Lua51, Lua51Pure, BLUA, Luau] {
+		let error = compile(Some(target), "local t = ordered { a = 1 }").unwrap_err();
+		assert!(error.contains("Lua 5.2 or later"), "{target:?}: {error}");
+	}
+	for target in [None, Some(Lua52), Some(Lua53), Some(Lua54)] {
+		compile(target, "local t = ordered { a = 1 }")
+			.unwrap_or_else(|e| panic!("{target:?}: {e}"));
+	}
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn keeps_the_order() {
+	// LuaJIT ignores `__pairs`, so the test calls it itself
+	let code = compile(
+		Some(Lua52),
+		"local t = ordered { z = 1, y = 2, \"first\", [\"x\"] = 3 }
+		t.w = 4
+		t.y = nil
+		t.v = 5
+		t.y = 6
+		t.z = 7
+		local mt = getmetatable(t)
+		for k, v with mt.__pairs(t) { print(k, v) }",
+	)
+	.unwrap();
+	let output = common::run(&code).unwrap_or_else(|e| panic!("could not run {code}: {e}"));
+	assert_eq!(output, ["z\t7", "y\t6", "1\tfirst", "x\t3", "w\t4", "v\t5"]);
+}
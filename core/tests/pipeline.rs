@@ -0,0 +1,43 @@
+//! Tests for `|>`, which passes the value on its left as the first argument of the call on its right.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn calls_are_nested() {
+	assert_eq!(
+		compile_ok("local y = x |> f(a) |> g"),
+		"local y = g(f(x, a));"
+	);
+	assert_eq!(
+		compile_ok("local y = x |> obj::m(1)"),
+		"local y = obj:m(x, 1);"
+	);
+	assert_eq!(compile_ok("local y = a + b |> f"), "local y = f(a+b);");
+}
+
+#[test]
+fn safe_calls_check_the_function() {
+	assert_eq!(
+		compile_ok("local y = x |> t?.f()"),
+		"local _internal0 = t;\nlocal y = (_internal0 and _internal0.f(x));"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn results_flow_through_the_calls() {
+	let output = common::compile_and_run(
+		"local fn double(x) { return x * 2 }
+		local fn add(x, y) { return x + y }
+		local counter = {total = 0, add = fn(self, n) { self.total += n; return self.total }}
+		local callbacks = {}
+		print(5 |> add(1) |> double)
+		print(10 |> counter::add(), 5 |> counter::add())
+		print(1 |> callbacks.missing?())
+		local greeting = \"hello world\"
+		greeting |> string.gsub(\"o\", \"0\") |> string.upper |> print",
+	);
+	assert_eq!(output, ["12", "10\t15", "nil", "HELL0 W0RLD"]);
+}
@@ -0,0 +1,161 @@
+//! Golden tests for the precedence of the operators, which the parser reads with a Pratt parser.
+//!
+//! The expected code was written by hand following the precedence of Lua's own operators:
+//! `or` < `and` < comparisons < `|` < `~` < `&` < shifts < `..` < `+ -` < `* / // %` < unary < `^`
+
+mod common;
+
+use clue_core::{env::LuaVersion, Clue};
+
+fn compile(target: LuaVersion, expression: &str) -> String {
+	let mut clue = Clue::new();
+	clue.target(Some(target));
+	let code = clue
+		.compile_code(format!("local x = {expression}"))
+		.unwrap_or_else(|e| panic!("could not compile {expression}: {e}"));
+	let line = code
+		.lines()
+		.find_map(|line| line.strip_prefix("local x = "))
+		.unwrap_or_else(|| panic!("no assignment in the output of {expression}: {code}"));
+	line.trim_end_matches(';').to_owned()
+}
+
+#[test]
+fn bit_library() {
+	for (expression, expected) in [
+		("a || b && c == d | e", "a or b and c==bit.bor(d, e)"),
+		("a | b ^^ c & d", "bit.bor(a, bit.bxor(b, bit.band(c, d)))"),
+		("a & b | c & d", "bit.bor(bit.band(a, b), bit.band(c, d))"),
+		("a & b << c", "bit.band(a, bit.lshift(b, c))"),
+		("a << 1 >> 2", "bit.rshift(bit.lshift(a, 1), 2)"),
+		("a << b .. c", "bit.lshift(a, b..c)"),
+		("a & b + c * d", "bit.band(a, b+c*d)"),
+		("a == b & c", "a==bit.band(b, c)"),
+		("~a + b", "bit.bnot(a)+b"),
+		("-~a ^ b", "-bit.bnot(a^b)"),
+		("!(a & b) && c", "not (bit.band(a, b)) and c"),
+		("(a | b) & c", "bit.band((bit.bor(a, b)), c)"),
+		(
+			"f(a & b) | t[c & d]",
+			"bit.bor(f(bit.band(a, b)), t[(bit.band(c, d))])",
+		),
+	] {
+		assert_eq!(
+			compile(LuaVersion::LuaJIT, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn floor_division() {
+	for (expression, expected) in [
+		("a /_ b", "math.floor(a/b)"),
+		("a + b /_ c", "a+math.floor(b/c)"),
+		("a /_ b * c", "math.floor(a/b)*c"),
+		("a * b /_ c", "math.floor(a*b/c)"),
+		("a /_ b /_ c", "math.floor(math.floor(a/b)/c)"),
+		("a /_ b ^ c", "math.floor(a/b^c)"),
+		("a /_ -b", "math.floor(a/-b)"),
+		("a .. b /_ c", "a..math.floor(b/c)"),
+	] {
+		assert_eq!(
+			compile(LuaVersion::Lua54, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn native_operators_stay_flat() {
+	for (expression, expected) in [
+		("a || b && c == d | e", "a or b and c==d|e"),
+		("a & b << c", "a&b<<c"),
+		("~a + b", "~a+b"),
+	] {
+		assert_eq!(
+			compile(LuaVersion::Lua54, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn luau_bit32() {
+	for (expression, expected) in [
+		("a | b & c", "bit32.bor(a, bit32.band(b, c))"),
+		("a << b .. c", "bit32.lshift(a, b..c)"),
+	] {
+		assert_eq!(
+			compile(LuaVersion::Luau, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn unary_operators() {
+	for (expression, expected) in [
+		("-a ^ b", "-a^b"),
+		("a ^ -b ^ c", "a^-b^c"),
+		("!a == b", "not a==b"),
+		("#t + 1", "#t+1"),
+		("a - -b", "a- -b"),
+		("- -a", "- -a"),
+	] {
+		assert_eq!(
+			compile(LuaVersion::Lua54, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn operators_taking_the_whole_expression() {
+	for (expression, expected) in [
+		("a + b |> f() * 2", "f(a+b)*2"),
+		("a || b |> f()", "f(a or b)"),
+	] {
+		assert_eq!(
+			compile(LuaVersion::Lua54, expression),
+			expected,
+			"{expression}"
+		);
+	}
+}
+
+#[test]
+fn chained_comparisons_in_brackets() {
+	let mut clue = Clue::new();
+	clue.chained_comparisons(true);
+	let code = clue
+		.compile_code(String::from("local x = a < b < c == d"))
+		.unwrap();
+	assert_eq!(code, "local x = (a<b and b<c)==d;");
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn evaluated_with_lua_precedence() {
+	// the expected values are computed by Rust with every operation in brackets
+	for (expression, expected) in [
+		("1 | 6 & 3 << 1", 1 | (6 & (3 << 1))),
+		("12 ^^ 10 | 1", (12 ^ 10) | 1),
+		("12 | 10 ^^ 6", 12 | (10 ^ 6)),
+		("~5 & 7 + 1", !5 & (7 + 1)),
+		("1 + 2 << 1", (1 + 2) << 1),
+		("7 /_ 2 * 2 + 1", ((7 / 2) * 2) + 1),
+		("2 ^ 3 ^ 2 /_ 10", 2i64.pow(3u32.pow(2)) / 10),
+		("-2 ^ 2", -(2i64.pow(2))),
+		("20 - 5 - 3", (20 - 5) - 3),
+		("100 / 10 / 5", (100 / 10) / 5),
+	] {
+		let output = common::compile_and_run(&format!("print({expression})"));
+		assert_eq!(output, [expected.to_string()], "{expression}");
+	}
+}
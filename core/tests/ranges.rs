@@ -0,0 +1,106 @@
+//! Tests for the ranges of `for i in start..end`, whose end is excluded
+//! even when the bounds or the step are not integers.
+
+mod common;
+
+use clue_core::env::LuaVersion;
+
+fn compile(code: &str) -> String {
+	common::compile(Some(LuaVersion::Lua54), code)
+		.unwrap_or_else(|e| panic!("could not compile {code}: {e}"))
+}
+
+#[test]
+fn integer_bounds_end_before() {
+	assert_eq!(
+		compile("for i in 0..10 by 4 { f(i) }"),
+		"for i = 0, 10-1, 4 do\n\tf(i);\nend"
+	);
+	assert_eq!(
+		compile("for i in 3..0 { f(i) }"),
+		"for i = 3, 0+1, -1 do\n\tf(i);\nend"
+	);
+}
+
+#[test]
+fn other_bounds_are_compared() {
+	assert_eq!(
+		compile("for i in 1..2.5 { f(i) }"),
+		"for i = 1, 2.5, 1 do\n\tif i>=2.5 then\n\t\tbreak;\n\tend\n\tf(i);\nend"
+	);
+	assert_eq!(
+		compile("for i in a()..b() by 1 { f(i) }"),
+		"local _internal0, _internal1 = a(), b();\n\
+		for i = _internal0, _internal1, 1 do\n\
+		\tif i>=_internal1 then\n\t\tbreak;\n\tend\n\
+		\tf(i);\n\
+		end"
+	);
+}
+
+#[test]
+fn direction_is_checked_at_runtime() {
+	assert_eq!(
+		compile("for i in a..=b { f(i) }"),
+		"local _internal0, _internal1 = a, b;\n\
+		for i = _internal0, _internal1, _internal0 > _internal1 and -1 or 1 do\n\
+		\tf(i);\n\
+		end"
+	);
+	assert_eq!(
+		compile("for i in a..3 { f(i) }"),
+		"local _internal0 = a;\n\
+		local _internal1 = _internal0 > 3 and -1 or 1;\n\
+		for i = _internal0, 3, _internal1 do\n\
+		\tif (i-3)*_internal1>=0 then\n\t\tbreak;\n\tend\n\
+		\tf(i);\n\
+		end"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn counts_down_from_a_bigger_start() {
+	let output = common::compile_and_run(
+		"local b, a = 5, 1
+		local calls = 0
+		local fn bound(value) { calls += 1; return value }
+		for i in 3..1 { print(\"a\", i) }
+		for i in b..a { print(\"b\", i) }
+		for i in a..b { print(\"c\", i) }
+		for i in bound(3)..=bound(2) { print(\"d\", i) }
+		for i in b..a + 3.5 { print(\"e\", i) }
+		print(calls)",
+	);
+	assert_eq!(
+		output,
+		[
+			"a\t3", "a\t2", "b\t5", "b\t4", "b\t3", "b\t2", "c\t1", "c\t2", "c\t3", "c\t4",
+			"d\t3", "d\t2", "e\t5", "2"
+		]
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn values_below_the_end() {
+	let output = common::compile_and_run(
+		"local n = 3.5
+		local calls = 0
+		local fn bound(value) { calls += 1; return value }
+		for i in 1..2.5 { print(\"a\", i) }
+		for i in 0.5..3 { print(\"b\", i) }
+		for i in 3..0.5 { print(\"c\", i) }
+		for i in 0..n by 1.5 { print(\"d\", i) }
+		for i in bound(1)..bound(3) { print(\"e\", i) }
+		for i in 1..=2 { print(\"f\", i) }
+		print(calls)",
+	);
+	assert_eq!(
+		output,
+		[
+			"a\t1", "a\t2", "b\t0.5", "b\t1.5", "b\t2.5", "c\t3", "c\t2", "c\t1", "d\t0", "d\t1.5",
+			"d\t3", "e\t1", "e\t2", "f\t1", "f\t2", "2"
+		]
+	);
+}
@@ -0,0 +1,42 @@
+//! Tests for `...name` parameters, which put the extra arguments and their count in a table.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn table_is_made_when_used() {
+	assert_eq!(
+		compile_ok("local fn f(a, ...rest) {\n\treturn g(a, rest)\n}"),
+		"local function f(a, ...)\n\
+		\tlocal rest = {\n\
+		\t\tn = select(\"#\", ...), \n\
+		\t\t...\n\
+		\t};\n\
+		\treturn g(a, rest);\n\
+		end"
+	);
+	assert!(compile_ok("local g = fn(...rest) => rest.n").contains("local rest = {"));
+}
+
+#[test]
+fn table_is_not_made_when_unused() {
+	assert_eq!(
+		compile_ok("local fn f(...rest) {\n\treturn 1\n}"),
+		"local function f(...)\n\treturn 1;\nend"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn count_includes_nils() {
+	let output = common::compile_and_run(
+		"local fn count(label, ...rest) {
+			return label .. \" \" .. rest.n .. \" \" .. tostring(rest[rest.n])
+		}
+		print(count(\"none\"))
+		print(count(\"two\", 1, 2))
+		print(count(\"nils\", 1, nil, nil))",
+	);
+	assert_eq!(output, ["none 0 nil", "two 2 2", "nils 3 nil"]);
+}
@@ -0,0 +1,53 @@
+//! Tests for assignments through safe navigation like `a?.b.c = v`,
+//! which only happen (and compute `v`) when the safe part of the chain is not nil.
+
+mod common;
+
+fn compile(code: &str) -> Result<String, String> {
+	common::compile(None, code)
+}
+
+#[test]
+fn guarded_by_an_if() {
+	assert_eq!(
+		compile("a?.b.c = v").unwrap(),
+		"local _internal0 = a;\nif _internal0 then\n\t_internal0.b.c = v;\nend"
+	);
+	assert_eq!(
+		compile("a?.b?[k] = f()").unwrap(),
+		"local _internal0 = a;\n\
+		local _internal1 = _internal0 and _internal0.b;\n\
+		if _internal1 then\n\
+		\t_internal1[((k))] = f();\n\
+		end"
+	);
+}
+
+#[test]
+fn only_one_variable() {
+	for code in ["a?.b, x = 1, 2", "x, a?.b = 1, 2", "x, a?[1] += 1, 2"] {
+		let error = compile(code).unwrap_err();
+		assert!(
+			error.contains("Safe indexing can only be used when altering a single variable"),
+			"{code}: {error}"
+		);
+	}
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn assigns_when_not_nil() {
+	let output = common::compile_and_run(
+		"local settings = {window = {width = 1}}
+		local missing
+		local calls = 0
+		local fn value(v) { calls += 1; return v }
+		settings?.window.width = value(800)
+		settings?.window?[\"height\"] = value(600)
+		settings?.other?.size = value(1)
+		missing?.window.width = value(1024)
+		settings?.window.width += 10
+		print(settings.window.width, settings.window.height, settings.other, missing, calls)",
+	);
+	assert_eq!(output, ["810\t600\tnil\tnil\t2"]);
+}
@@ -0,0 +1,48 @@
+//! Tests for `#?` and `-?`, which give nil instead of an error when their operand is nil.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn operand_is_checked() {
+	assert_eq!(
+		compile_ok("local n = #?x"),
+		"local _internal0 = x;\nlocal n = _internal0 and #_internal0;"
+	);
+	assert_eq!(
+		compile_ok("local n = -?a.b + 1"),
+		"local _internal0 = a.b;\nlocal n = (_internal0 and -_internal0)+1;"
+	);
+}
+
+#[test]
+fn operand_is_evaluated_once() {
+	assert_eq!(
+		compile_ok("local n = #?f()"),
+		"local _internal0 = f();\nlocal n = _internal0 and #_internal0;"
+	);
+}
+
+#[test]
+fn power_binds_tighter() {
+	assert_eq!(
+		compile_ok("local n = -?x ^ 2 * 3"),
+		"local _internal0 = x;\nlocal n = (_internal0 and -_internal0^2)*3;"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn nil_operands_give_nil() {
+	let output = common::compile_and_run(
+		"local rows = {
+			{ name = \"a\", tags = { \"x\", \"y\" }, offset = 2 },
+			{ name = \"b\" },
+		}
+		for _, row in rows {
+			print(row.name, #?row.tags ?? 0, -?row.offset, -?row.offset ^ 2)
+		}",
+	);
+	assert_eq!(output, ["a\t2\t-2\t-4", "b\t0\tnil\tnil"]);
+}
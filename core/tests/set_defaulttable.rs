@@ -0,0 +1,45 @@
+//! Tests for `set { ... }`, a table whose keys are the given values,
+//! and `defaulttable(factory)`, a table which creates its missing values with `factory`.
+
+mod common;
+
+use common::compile_ok;
+
+#[test]
+fn set_keys_are_true() {
+	assert_eq!(
+		compile_ok("local s = set {a, \"c\", 1}"),
+		"local s = {\n\t[(a)] = true, \n\t[(\"c\")] = true, \n\t[(1)] = true\n};"
+	);
+	assert_eq!(compile_ok("local s = set {}"), "local s = {};");
+}
+
+#[test]
+fn factory_is_evaluated_once() {
+	assert_eq!(
+		compile_ok("local d = defaulttable(make())"),
+		"local _internal0 = make();\n\
+		local d = setmetatable({}, {\n\
+		\t__index = function(t, k) local v = _internal0(k) t[k] = v return v end\n\
+		});"
+	);
+	assert!(common::compile(None, "local d = defaulttable()").is_err());
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn values_are_created_once() {
+	let output = common::compile_and_run(
+		"local keywords = set { \"if\", \"else\", \"while\" }
+		local created = 0
+		local words = defaulttable(fn() { created += 1; return {} })
+		local source = { \"if\", \"x\", \"while\", \"y\", \"x\" }
+		for _, word in source {
+			table.insert(words[keywords[word] ? \"keyword\" : \"name\"], word)
+		}
+		local lengths = defaulttable(fn(word) => #word)
+		print(#words.keyword, #words.name, created, keywords.x)
+		print(lengths.hello, rawget(lengths, \"hello\"), rawget(lengths, \"hi\"))",
+	);
+	assert_eq!(output, ["2\t3\t2\tnil", "5\t5\tnil"]);
+}
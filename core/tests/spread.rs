@@ -0,0 +1,62 @@
+//! Tests for `...t` in tables and in the arguments of calls, which unpacks `t` in place
+//! with the `unpack` function of the target when it's the last value.
+
+mod common;
+
+use clue_core::env::LuaVersion;
+
+#[test]
+fn last_argument_is_unpacked() {
+	for (target, unpack) in [
+		(LuaVersion::Lua54, "table.unpack"),
+		(LuaVersion::LuaJIT, "unpack"),
+		(LuaVersion::Lua51, "unpack"),
+	] {
+		assert_eq!(
+			common::compile(Some(target), "f(1, ...args)").unwrap(),
+			format!("f(1, {unpack}(args));")
+		);
+	}
+}
+
+#[test]
+fn spread_is_evaluated_once() {
+	let code = common::compile(Some(LuaVersion::Lua54), "f(...g(), 2)").unwrap();
+	assert_eq!(code.matches("g()").count(), 1, "{code}");
+	assert!(
+		code.ends_with("f(table.unpack(_internal0, 1, _internal1));"),
+		"{code}"
+	);
+}
+
+#[cfg(feature = "mlua")]
+#[test]
+fn values_are_in_order() {
+	let output = common::compile_and_run(
+		"local fn show(...) {
+			local values = {}
+			for i = 1, select(\"#\", ...) { values[i] = tostring((select(i, ...))) }
+			print(table.concat(values, \",\"))
+		}
+		local numbers = {1, 2, 3}
+		local calls = 0
+		local fn get() { calls += 1; return numbers }
+		show(...numbers)
+		show(0, ...get(), 4, ...{5, 6})
+		show(...{}, 1)
+		local both = {0, ...numbers, ...numbers, 7}
+		show(...both)
+		local config = {...{host = \"localhost\", port = 80, \"a\"}, port = 8080, ...{\"b\"}}
+		print(config.host, config.port, config[1], config[2], calls)",
+	);
+	assert_eq!(
+		output,
+		[
+			"1,2,3",
+			"0,1,2,3,4,5,6",
+			"1",
+			"0,1,2,3,1,2,3,7",
+			"localhost\t8080\ta\tb\t1"
+		]
+	);
+}
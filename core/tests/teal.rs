@@ -0,0 +1,65 @@
+//! Golden tests for the Teal output of structs, whose records declare every key
+//! the generated code writes to them, as `tl check` requires
+
+use clue_core::{env::Language, Clue};
+
+fn compile(code: &str) -> String {
+	let mut clue = Clue::new();
+	clue.language(Language::Teal);
+	clue.compile_code(code.to_owned()).unwrap()
+}
+
+#[test]
+fn records_declare_index() {
+	assert_eq!(
+		compile("local struct Point {\n\tx = 0\n\tmethod length() {\n\t\treturn self.x\n\t}\n}"),
+		"local record Point
+	x: number
+	__index: Point
+end
+Point.__index = Point;
+function Point.new(): Point
+	local self: Point = setmetatable({
+		x = 0
+	}, Point as metatable<Point>);
+	return self;
+end
+function Point:length()
+	return self.x;
+end"
+	);
+}
+
+#[test]
+fn records_declare_mixin_methods() {
+	let code = compile(
+		"local struct Named {\n\tmethod describe() {\n\t\treturn self.name\n\t}\n}\nlocal struct Dog with Named {\n\tname = \"a dog\"\n}",
+	);
+	assert!(
+		code.contains(
+			"local record Dog
+	name: string
+	describe: function(Dog, ...: any): any...
+	__index: Dog
+end"
+		),
+		"{code}"
+	);
+	assert!(
+		code.contains("Dog.describe = Named.describe as function(Dog, ...: any): any...;"),
+		"{code}"
+	);
+}
+
+#[test]
+fn lua_has_no_casts() {
+	let mut clue = Clue::new();
+	clue.annotations(true);
+	let code = clue
+		.compile_code(String::from(
+			"local struct A {\n\tmethod f() {}\n}\nlocal struct B with A {}",
+		))
+		.unwrap();
+	assert!(!code.contains(" as "), "{code}");
+	assert!(!code.contains("---@field f"), "{code}");
+}
@@ -0,0 +1,101 @@
+//! Tests for the `with` blocks on every target, below Lua 5.4 their body is run by `pcall`.
+
+mod common;
+
+use clue_core::env::LuaVersion::{self, *};
+use common::compile;
+
+const TARGETS: [Option<LuaVersion>; 9] = [
+	None,
+	Some(LuaJIT),
+	Some(Lua54),
+	Some(Lua53),
+	Some(Lua52),
+	Some(Lua51),
+	Some(BLUA),
+	Some(Luau),
+	Some(Lua51Pure),
+];
+
+#[test]
+fn lua54_uses_close() {
+	let code = compile(Some(Lua54), "with r = open() { print(r) }").unwrap();
+	assert!(
+		code.starts_with("do\n\tlocal r = open();\n\tlocal _close <close> = setmetatable({}, {__close = function(_, _err)\n"),
+		"{code}"
+	);
+	assert!(code.contains("\t\t\t\tr:close();\n"), "{code}");
+}
+
+#[test]
+fn jumps_out_of_the_block() {
+	for jump in [
+		"return r",
+		"break",
+		"continue",
+		"loop { break }\nreturn",
+		"print(...)",
+	] {
+		let code = format!("for i = 1, 2 {{\n\twith r = open() {{\n\t\t{jump}\n\t}}\n}}");
+		for target in TARGETS {
+			let result = compile(target, &code);
+			if target == Some(Lua54) {
+				assert!(result.is_ok(), "{target:?}: {jump}: {result:?}");
+			} else {
+				let error = result.expect_err(&format!("{target:?}: {jump}"));
+				assert!(
+					error.contains("cannot be used inside 'with'"),
+					"{target:?}: {jump}: {error}"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn jumps_inside_the_block() {
+	let code = "with r = open() {
+		for i = 1, 2 {
+			if i == 1 { continue }
+			break
+		}
+		local f = fn() { return r }
+	}";
+	for target in TARGETS {
+		compile(target, code).unwrap_or_else(|e| panic!("{target:?}: {e}"));
+	}
+}
+
+#[cfg(any(feature = "mlua", feature = "lua54"))]
+#[test]
+fn closes_the_resource() {
+	use common::{run, RUNTIME};
+
+	const OPEN: &str = "local fn open(name) {
+		return {name = name, close = fn(self) { print(\"closed \" .. self.name) }}
+	}
+	";
+
+	let code = compile(
+		Some(RUNTIME),
+		&format!("{OPEN}with r = open(\"a\") {{ print(\"using \" .. r.name) }}"),
+	)
+	.unwrap();
+	assert_eq!(run(&code).unwrap(), ["using a", "closed a"]);
+
+	let code = compile(
+		Some(RUNTIME),
+		&format!("{OPEN}with r = open(\"b\") {{ error(\"failed\") }}"),
+	)
+	.unwrap();
+	let error = run(&code).unwrap_err();
+	assert!(error.contains("failed"), "{error}");
+
+	let code = compile(
+		Some(RUNTIME),
+		"local r = setmetatable({}, {__close = fn(self, err) { print(\"__close\", err) }})
+		with r = r { print(\"using\") }",
+	)
+	.unwrap();
+	assert_eq!(run(&code).unwrap(), ["using", "__close\tnil"]);
+}
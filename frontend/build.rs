@@ -0,0 +1,417 @@
+//! Generates the grammar catalog exposed by the `grammar` module,
+//! by reading the definitions of the tokens and of the AST from the source code of the scanner and the parser.
+
+use std::{env, fs, path::Path};
+
+fn main() {
+	println!("cargo:rerun-if-changed=src/scanner.rs");
+	println!("cargo:rerun-if-changed=src/parser.rs");
+	let scanner = fs::read_to_string("src/scanner.rs").expect("could not read the scanner");
+	let parser = fs::read_to_string("src/parser.rs").expect("could not read the parser");
+
+	let (symbols, delimiters) = symbols(&scanner);
+	let (keywords, reserved) = keywords(&scanner);
+	let spec = token_spec(&symbols, &delimiters, &keywords, &reserved);
+	let structs = nodes(&parser, "pub struct CodeBlock {", Some("CodeBlock"));
+	let nodes = nodes(&parser, "pub enum ComplexToken {", None);
+	let aliases = aliases(&parser, &(structs.join("") + &nodes.join("")));
+	let json = object(
+		vec![
+			(
+				"version",
+				find_between(&parser, "pub const AST_FORMAT_VERSION: u32 = ", ";").to_owned(),
+			),
+			("tokens", list(tokens(&scanner), 1)),
+			(
+				"symbols",
+				list(
+					symbols
+						.iter()
+						.map(|(lexeme, token)| object_line(&[("lexeme", lexeme), ("token", token)]))
+						.collect(),
+					1,
+				),
+			),
+			(
+				"string_delimiters",
+				list(delimiters.iter().map(|(c, _)| quote(c)).collect(), 1),
+			),
+			(
+				"keywords",
+				list(
+					keywords
+						.iter()
+						.map(|(keyword, token, lua)| {
+							format!(
+								"{{\"keyword\": {}, \"token\": {}, \"lua\": {lua}}}",
+								quote(keyword),
+								quote(token)
+							)
+						})
+						.collect(),
+					1,
+				),
+			),
+			(
+				"reserved",
+				list(reserved.iter().map(|word| quote(word)).collect(), 1),
+			),
+			("aliases", object(aliases, 1)),
+			("structs", list(structs, 1)),
+			("nodes", list(nodes, 1)),
+		],
+		0,
+	);
+
+	let out = env::var("OUT_DIR").expect("OUT_DIR is not set");
+	fs::write(Path::new(&out).join("grammar.json"), json + "\n")
+		.expect("could not write the grammar");
+	fs::write(Path::new(&out).join("token_spec.rs"), spec).expect("could not write the token spec");
+}
+
+fn quote(text: &str) -> String {
+	format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn list(items: Vec<String>, indent: usize) -> String {
+	if items.is_empty() {
+		return String::from("[]");
+	}
+	let inner = "\t".repeat(indent + 1);
+	let items: Vec<String> = items.iter().map(|item| inner.clone() + item).collect();
+	format!("[\n{}\n{}]", items.join(",\n"), "\t".repeat(indent))
+}
+
+fn object(entries: Vec<(&str, String)>, indent: usize) -> String {
+	let entries = entries
+		.into_iter()
+		.map(|(key, value)| format!("{}: {value}", quote(key)))
+		.collect();
+	let list = list(entries, indent);
+	format!("{{{}}}", &list[1..list.len() - 1])
+}
+
+fn find_between<'a>(code: &'a str, start: &str, end: &str) -> &'a str {
+	let code = &code[code
+		.find(start)
+		.unwrap_or_else(|| panic!("missing {start}"))
+		+ start.len()..];
+	&code[..code.find(end).unwrap_or_else(|| panic!("missing {end}"))]
+}
+
+// returns the position after the bracket matching the one at `open`, skipping char and string literals
+fn matching(code: &[u8], open: usize) -> usize {
+	let mut depth = 0;
+	let mut i = open;
+	while i < code.len() {
+		match code[i] {
+			b'(' | b'[' | b'{' => depth += 1,
+			b')' | b']' | b'}' => {
+				depth -= 1;
+				if depth == 0 {
+					return i + 1;
+				}
+			}
+			b'\'' if code[i + 1] == b'\\' => i += 3,
+			b'\'' if code[i + 2] == b'\'' => i += 2,
+			b'"' => {
+				i += 1;
+				while code[i] != b'"' {
+					i += if code[i] == b'\\' { 2 } else { 1 };
+				}
+			}
+			_ => {}
+		}
+		i += 1;
+	}
+	panic!("unclosed bracket")
+}
+
+// returns the inside of the block opened by the last character of `start`
+fn block<'a>(code: &'a str, start: &str) -> &'a str {
+	let open = code
+		.find(start)
+		.unwrap_or_else(|| panic!("missing {start}"))
+		+ start.len()
+		- 1;
+	&code[open + 1..matching(code.as_bytes(), open) - 1]
+}
+
+fn tokens(scanner: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut category = "other";
+	for line in block(scanner, "pub enum TokenType {")
+		.lines()
+		.map(str::trim)
+	{
+		if let Some(comment) = line.strip_prefix("//") {
+			category = comment.split_whitespace().next().unwrap_or("other");
+			continue;
+		} else if line.is_empty() {
+			category = "other";
+		}
+		for name in line
+			.split(',')
+			.map(str::trim)
+			.filter(|name| !name.is_empty())
+		{
+			tokens.push(object_line(&[("name", name), ("category", category)]));
+		}
+	}
+	tokens
+}
+
+fn object_line(entries: &[(&str, &str)]) -> String {
+	let entries: Vec<String> = entries
+		.iter()
+		.map(|(key, value)| format!("{}: {}", quote(key), quote(value)))
+		.collect();
+	format!("{{{}}}", entries.join(", "))
+}
+
+// a symbol and the token it is scanned as
+type Symbol = (String, String);
+
+// a string delimiter and whether it starts a raw string
+type Delimiter = (String, bool);
+
+// a keyword, the token it is scanned as and whether it's also a Lua keyword
+type Keyword = (String, String, bool);
+
+// returns the symbols with their tokens and the string delimiters, with whether they start raw strings
+fn symbols(scanner: &str) -> (Vec<Symbol>, Vec<Delimiter>) {
+	let mut symbols = Vec::new();
+	let mut delimiters = Vec::new();
+	read_symbols(
+		block(scanner, "const SYMBOLS: SymbolsMap = generate_map(&["),
+		String::new(),
+		&mut symbols,
+		&mut delimiters,
+	);
+	(symbols, delimiters)
+}
+
+// reads the entries of a `generate_map` call, `prefix` being the symbol that leads to it
+fn read_symbols(
+	entries: &str,
+	prefix: String,
+	symbols: &mut Vec<Symbol>,
+	delimiters: &mut Vec<Delimiter>,
+) {
+	let mut i = 0;
+	while let Some(start) = entries[i..].find('(').map(|start| start + i) {
+		i = matching(entries.as_bytes(), start);
+		let entry = entries[start + 1..i - 1].trim_start();
+		let (c, entry) = match entry.strip_prefix("'\\") {
+			Some(entry) => (&entry[..1], &entry[3..]),
+			None => (&entry[1..2], &entry[4..]),
+		};
+		let lexeme = prefix.clone() + c;
+		let entry = entry.trim();
+		if let Some(token) = entry.strip_prefix("SymbolType::Just(") {
+			symbols.push((lexeme, token.trim_end_matches(')').to_owned()));
+		} else if entry.starts_with("SymbolType::Symbols(") {
+			let open = entry.find("&[").unwrap() + 1;
+			let close = matching(entry.as_bytes(), open);
+			let token =
+				entry[close..].trim_matches(|c: char| c == ')' || c == ',' || c.is_whitespace());
+			symbols.push((lexeme.clone(), token.to_owned()));
+			read_symbols(&entry[open + 1..close - 1], lexeme, symbols, delimiters);
+		} else if entry.contains("read_string") || entry.contains("read_raw_string") {
+			delimiters.push((lexeme, entry.contains("read_raw_string")));
+		} else {
+			// functions that check one more character, like the one for `?::`
+			let next = find_between(entry, "compare('", "')");
+			let token = find_between(entry, "add_token(", ")");
+			symbols.push((lexeme + next, token.to_owned()));
+		}
+	}
+}
+
+// returns the keywords with their tokens and whether they are also Lua keywords, and the reserved words
+fn keywords(scanner: &str) -> (Vec<Keyword>, Vec<String>) {
+	let mut keywords = Vec::new();
+	let mut reserved = Vec::new();
+	let map = find_between(scanner, "\nkeywords! {", "\n}");
+	for line in map
+		.lines()
+		.filter_map(|line| line.trim().strip_prefix("b\""))
+	{
+		let (keyword, kind) = line.split_once("\" =>").unwrap();
+		let kind = kind.trim().trim_start_matches("KeywordType::");
+		let (token, lua) = match kind.split_once('(') {
+			Some(("Just", token)) => (token, false),
+			Some(("Lua", token)) => (token, true),
+			_ => {
+				reserved.push(keyword.to_owned());
+				continue;
+			}
+		};
+		keywords.push((
+			keyword.to_owned(),
+			token.trim_end_matches("),").to_owned(),
+			lua,
+		));
+	}
+	(keywords, reserved)
+}
+
+// generates the entries of `grammar::TOKEN_SPEC` as a Rust array
+fn token_spec(
+	symbols: &[Symbol],
+	delimiters: &[Delimiter],
+	keywords: &[Keyword],
+	reserved: &[String],
+) -> String {
+	let entry = |text: &str, form: &str, category: &str, token: Option<&str>| {
+		format!(
+			"\tTokenSpec {{ text: {text:?}, form: TokenForm::{form}, category: {category:?}, token: {} }},\n",
+			token.map_or_else(|| String::from("None"), |token| format!("Some(TokenType::{token})"))
+		)
+	};
+	let mut spec = String::from("[\n");
+	for (keyword, token, _) in keywords {
+		let category = match token.as_str() {
+			"IF" | "ELSEIF" | "ELSE" | "FOR" | "OF" | "IN" | "WITH" | "WHILE" | "UNTIL"
+			| "LOOP" | "RETURN" | "CONTINUE" | "BREAK" | "GOTO" | "TRY" | "CATCH" | "MATCH"
+			| "DEFAULT" | "DO" => "keyword.control",
+			"TRUE" | "FALSE" | "NIL" => "constant.language",
+			_ => "keyword.declaration",
+		};
+		spec += &entry(keyword, "Keyword", category, Some(token));
+	}
+	for word in reserved {
+		spec += &entry(word, "Reserved", "invalid.reserved", None);
+	}
+	for (lexeme, token) in symbols {
+		let category = match token.as_str() {
+			"ROUND_BRACKET_OPEN"
+			| "ROUND_BRACKET_CLOSED"
+			| "SQUARE_BRACKET_OPEN"
+			| "SQUARE_BRACKET_CLOSED"
+			| "CURLY_BRACKET_OPEN"
+			| "CURLY_BRACKET_CLOSED"
+			| "SAFE_CALL"
+			| "SAFE_SQUARE_BRACKET"
+			| "COMMA"
+			| "SEMICOLON"
+			| "DOT"
+			| "SAFE_DOT"
+			| "DOUBLE_COLON"
+			| "SAFE_DOUBLE_COLON"
+			| "COLON"
+			| "ARROW"
+			| "AT" => "punctuation",
+			"DEFINE" | "DEFINE_AND" | "DEFINE_OR" | "DEFINE_COALESCE" | "INCREASE" | "DECREASE"
+			| "MULTIPLY" | "DIVIDE" | "EXPONENTIATE" | "CONCATENATE" | "MODULATE" => {
+				"keyword.operator.assignment"
+			}
+			_ => "keyword.operator",
+		};
+		spec += &entry(lexeme, "Symbol", category, Some(token));
+	}
+	for (delimiter, raw) in delimiters {
+		let pattern = if *raw {
+			format!("{delimiter}[^{delimiter}]*{delimiter}")
+		} else {
+			format!("{delimiter}(\\\\.|[^{delimiter}\\\\])*{delimiter}")
+		};
+		spec += &entry(&pattern, "Literal", "string", Some("STRING"));
+	}
+	for (pattern, category, token) in [
+		("[A-Za-z_][A-Za-z0-9_]*", "variable", Some("IDENTIFIER")),
+		(
+			"[0-9]+(\\.[0-9]+)?([eE]-?[0-9]+)?(LL|ULL)?",
+			"constant.numeric",
+			Some("NUMBER"),
+		),
+		(
+			"0[xX][0-9A-Fa-f]+(\\.[0-9A-Fa-f]+)?(LL|ULL)?",
+			"constant.numeric",
+			Some("NUMBER"),
+		),
+		(
+			"0[bB][01]+(\\.[01]+)?(LL|ULL)?",
+			"constant.numeric",
+			Some("NUMBER"),
+		),
+		("//.*", "comment", None),
+		("/\\*[\\s\\S]*?\\*/", "comment", None),
+	] {
+		spec += &entry(pattern, "Literal", category, token);
+	}
+	spec + "]\n"
+}
+
+// returns the type aliases used by the AST
+fn aliases<'a>(parser: &'a str, nodes: &str) -> Vec<(&'a str, String)> {
+	let aliases: Vec<(&str, &str)> = parser
+		.lines()
+		.filter_map(|line| {
+			line.strip_prefix("pub type ")
+				.or_else(|| line.strip_prefix("type "))
+		})
+		.map(|alias| {
+			let (name, definition) = alias.split_once(" = ").unwrap();
+			(name, definition.trim_end_matches(';'))
+		})
+		.collect();
+	let mut used = String::from(nodes);
+	let mut found = Vec::new();
+	while let Some((name, definition)) = aliases
+		.iter()
+		.find(|(name, _)| used.contains(name) && !found.iter().any(|(found, _)| found == name))
+	{
+		used += definition;
+		found.push((*name, quote(definition)));
+	}
+	found.sort();
+	found
+}
+
+// reads the variants of an enum, or the fields of the struct called `name`
+fn nodes(parser: &str, start: &str, name: Option<&str>) -> Vec<String> {
+	let mut nodes = Vec::new();
+	let mut current: Option<(String, String, Vec<String>)> =
+		name.map(|name| (name.to_owned(), String::new(), Vec::new()));
+	let mut docs = Vec::new();
+	let finish = |(name, doc, fields): (String, String, Vec<String>), nodes: &mut Vec<String>| {
+		nodes.push(format!(
+			"{{\"name\": {}, \"doc\": {}, \"fields\": {}}}",
+			quote(&name),
+			quote(&doc),
+			list(fields, 2)
+		));
+	};
+	for line in block(parser, start).lines().map(str::trim) {
+		if let Some(doc) = line.strip_prefix("///") {
+			docs.push(doc.trim());
+			continue;
+		}
+		let doc = docs.join(" ");
+		if let Some(variant) = line.strip_suffix(" {") {
+			current = Some((variant.to_owned(), doc, Vec::new()));
+		} else if line == "}," {
+			finish(current.take().unwrap(), &mut nodes);
+		} else if let Some((field, kind)) = line.split_once(": ") {
+			let field = field.trim_start_matches("pub ").trim_start_matches("r#");
+			let kind = kind.trim_end_matches(',');
+			let field = object_line(&[("name", field), ("type", kind), ("doc", &doc)]);
+			current.as_mut().unwrap().2.push(field);
+		} else if let Some((variant, kind)) = line.split_once('(') {
+			let field = object_line(&[
+				("name", "0"),
+				("type", kind.trim_end_matches("),")),
+				("doc", ""),
+			]);
+			finish((variant.to_owned(), doc, vec![field]), &mut nodes);
+		} else {
+			continue;
+		}
+		docs.clear();
+	}
+	if let Some(node) = current {
+		finish(node, &mut nodes);
+	}
+	nodes
+}
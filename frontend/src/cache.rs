@@ -0,0 +1,149 @@
+//! The cache module stores token streams and ASTs in a compact binary format,
+//! so tools can save the result of scanning or parsing a file and load it back later
+//! instead of doing the work again.
+//!
+//! Every cache starts with a header made of:
+//! - the 4 bytes `CLUE`, followed by a byte telling if it contains tokens (`0`) or an AST (`1`)
+//! - the [`CACHE_FORMAT_VERSION`] as a little endian `u32`
+//! - the version of Clue that made it, as a byte with its length followed by the version itself
+//! - a checksum of the rest of the cache as a little endian `u64`
+//!
+//! A cache made by another version of Clue or whose checksum doesn't match is refused,
+//! so a cache from before an upgrade or a partially written one is never used:
+//! the caller should scan or parse the file again and replace it.
+
+use crate::{
+	check,
+	parser::{AstDocument, Expression, AST_FORMAT_VERSION},
+	scanner::Token,
+};
+
+/// The version of the layout of the caches made by this module,
+/// increased every time it changes in a way older versions of Clue can't read
+pub const CACHE_FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"CLUE";
+const TOKENS: u8 = 0;
+const AST: u8 = 1;
+
+// FNV-1a, enough to notice a truncated or damaged cache
+fn checksum(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+		(hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+	})
+}
+
+fn write_cache(kind: u8, payload: Vec<u8>) -> Vec<u8> {
+	let version = env!("CARGO_PKG_VERSION").as_bytes();
+	let mut cache = Vec::with_capacity(payload.len() + version.len() + 18);
+	cache.extend_from_slice(MAGIC);
+	cache.push(kind);
+	cache.extend_from_slice(&CACHE_FORMAT_VERSION.to_le_bytes());
+	cache.push(version.len() as u8);
+	cache.extend_from_slice(version);
+	cache.extend_from_slice(&checksum(&payload).to_le_bytes());
+	cache.extend(payload);
+	cache
+}
+
+// checks the header of the cache and returns what comes after it
+fn read_cache(kind: u8, cache: &[u8]) -> Result<&[u8], String> {
+	let invalid = || String::from("The cache is not a valid Clue cache");
+	let header = cache.get(..10).ok_or_else(invalid)?;
+	if &header[..4] != MAGIC || header[4] != kind {
+		return Err(invalid());
+	}
+	let format = u32::from_le_bytes(header[5..9].try_into().unwrap());
+	let len = usize::from(header[9]);
+	let version = cache.get(10..10 + len).ok_or_else(invalid)?;
+	let version = String::from_utf8_lossy(version);
+	if format != CACHE_FORMAT_VERSION || version != env!("CARGO_PKG_VERSION") {
+		return Err(format!(
+			"The cache was made by Clue {version}, not Clue {}",
+			env!("CARGO_PKG_VERSION")
+		));
+	}
+	let sum = cache.get(10 + len..18 + len).ok_or_else(invalid)?;
+	let payload = &cache[18 + len..];
+	if u64::from_le_bytes(sum.try_into().unwrap()) != checksum(payload) {
+		return Err(String::from("The cache is corrupted"));
+	}
+	Ok(payload)
+}
+
+/// Stores the tokens returned by [`scan_code`](crate::scanner::scan_code) in a cache
+///
+/// # Errors
+/// Returns an [`Err`] if the tokens could not be serialized.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{cache::*, env::Options, scan_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let tokens = scan_source("local a = 1".to_owned(), &filename, &Options::default())?;
+///     let cache = tokens_to_cache(&tokens)?;
+///     assert_eq!(tokens_from_cache(&cache)?.len(), tokens.len());
+///
+///     let mut corrupted = cache.clone();
+///     *corrupted.last_mut().unwrap() ^= 1;
+///     assert!(tokens_from_cache(&corrupted).is_err());
+///
+///     Ok(())
+/// }
+/// ```
+pub fn tokens_to_cache(tokens: &[Token]) -> Result<Vec<u8>, String> {
+	Ok(write_cache(TOKENS, check!(bincode::serialize(tokens))))
+}
+
+/// Loads the tokens stored in a cache by [`tokens_to_cache`]
+///
+/// # Errors
+/// Returns an [`Err`] if the cache is invalid, corrupted or was made by another version of Clue.
+pub fn tokens_from_cache(cache: &[u8]) -> Result<Vec<Token>, String> {
+	Ok(check!(bincode::deserialize(read_cache(TOKENS, cache)?)))
+}
+
+/// Stores the output of [`parse_tokens`](crate::parser::parse_tokens) in a cache as an [`AstDocument`]
+///
+/// # Errors
+/// Returns an [`Err`] if the AST could not be serialized.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{cache::*, env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let parsed = parse_source("local a = 1".to_owned(), &filename, &Options::default())?;
+///     let cache = ast_to_cache(filename.clone(), parsed.clone())?;
+///     let document = ast_from_cache(&cache)?;
+///     assert_eq!(document.filename, filename);
+///     assert_eq!((document.ast, document.statics), parsed);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn ast_to_cache(
+	filename: impl Into<String>,
+	parsed: (Expression, Expression),
+) -> Result<Vec<u8>, String> {
+	let document = AstDocument::new(filename, parsed);
+	Ok(write_cache(AST, check!(bincode::serialize(&document))))
+}
+
+/// Loads the [`AstDocument`] stored in a cache by [`ast_to_cache`]
+///
+/// # Errors
+/// Returns an [`Err`] if the cache is invalid, corrupted or was made by another version of Clue.
+pub fn ast_from_cache(cache: &[u8]) -> Result<AstDocument, String> {
+	let document: AstDocument = check!(bincode::deserialize(read_cache(AST, cache)?));
+	if document.version != AST_FORMAT_VERSION {
+		return Err(format!(
+			"Unsupported AST format version {} (expected {})",
+			document.version, AST_FORMAT_VERSION
+		));
+	}
+	Ok(document)
+}
@@ -9,6 +9,7 @@ use std::{
 		VecDeque,
 	},
 	ffi::OsString,
+	fmt::{self, Write},
 	hash::Hash,
 };
 
@@ -119,13 +120,12 @@ impl IntoIterator for Code {
 	}
 }
 
-impl ToString for Code {
-	fn to_string(&self) -> String {
-		let mut result = String::with_capacity(self.len());
+impl fmt::Display for Code {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		for c in self.clone().chars() {
-			result.push(c);
+			f.write_char(c)?;
 		}
-		result
+		Ok(())
 	}
 }
 
@@ -281,7 +281,7 @@ impl Code {
 	}
 
 	/// Returns an iterator over the characters in the [`Code`] without consuming it.
-	pub fn iter(&self) -> Iter<CodeChar> {
+	pub fn iter(&self) -> Iter<'_, CodeChar> {
 		self.list.iter()
 	}
 
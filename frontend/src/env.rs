@@ -0,0 +1,593 @@
+//! The `env` module contains miscellaneous structs and enums related to the compiler options available
+//!
+//! It contains [`Options`] struct is the main struct used to store the compiler options,
+//! and is used by the parser and the backends to determine how to compile the code
+//! and also other helpful enums such as [`LuaVersion`], [`TargetEnv`], [`Language`], [`EnumMode`], [`WrapMode`], [`BitwiseMode`] and [`ContinueMode`]
+
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// The continue mode to use when compiling `continue` keywords
+pub enum ContinueMode {
+	#[default]
+	#[cfg_attr(feature = "clap", clap(name = "simple"))]
+	/// Simple: This mode uses the native continue keyword.
+	/// This can only be used in implementations which support it (like BLUA and Luau).
+	Simple,
+
+	/// DEPRECATED
+	///
+	/// LuaJIT: Same as `Goto`, only for compatibility reasons
+	LuaJIT,
+
+	#[cfg_attr(feature = "clap", clap(name = "goto"))]
+	/// Goto: Clue will use goto continue; and a ::continue:: label when compiling `continue` keywords
+	/// instead of assuming the version of Lua you're compiling to has a proper continue keyword.
+	/// This will work with most versions of Lua (Lua 5.2, LuaJIT).
+	Goto,
+
+	/// Moonscript: This approach is guaranteed to work with any version of Lua although
+	/// it has a performance impact because it uses an additional loop.
+	MoonScript,
+}
+
+/*
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum TypesMode {
+	NONE,
+	WARN,
+	STRICT,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum LuaSTD {
+	NONE,
+	LUAJIT,
+	LUA54,
+	//ADD MORE LATER
+}
+*/
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// The Lua version to target
+pub enum LuaVersion {
+	#[default]
+	/// LuaJIT
+	LuaJIT,
+
+	/// Lua 5.4
+	Lua54,
+
+	/// Lua 5.3
+	Lua53,
+
+	/// Lua 5.2
+	Lua52,
+
+	/// Lua 5.1
+	Lua51,
+
+	/// BLUA
+	BLUA,
+
+	/// Luau, the Lua 5.1 dialect used by Roblox
+	Luau,
+
+	/// Lua 5.1 without any library besides the standard one,
+	/// for embedded interpreters like GopherLua
+	Lua51Pure,
+}
+
+impl LuaVersion {
+	/// Returns the version of standard Lua the target is based on, as `(major, minor)`,
+	/// which is what `@iflua >= 5.3` compares
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_frontend::env::LuaVersion;
+	///
+	/// assert_eq!(LuaVersion::Lua53.base_version(), (5, 3));
+	/// assert!(LuaVersion::LuaJIT.base_version() < LuaVersion::Lua52.base_version());
+	/// ```
+	pub const fn base_version(self) -> (u8, u8) {
+		match self {
+			LuaVersion::Lua54 => (5, 4),
+			LuaVersion::Lua53 => (5, 3),
+			LuaVersion::Lua52 => (5, 2),
+			LuaVersion::LuaJIT
+			| LuaVersion::Lua51
+			| LuaVersion::BLUA
+			| LuaVersion::Luau
+			| LuaVersion::Lua51Pure => (5, 1),
+		}
+	}
+}
+
+// the globals of the standard library, which every environment defines
+#[rustfmt::skip]
+const STANDARD_GLOBALS: [&str; 31] = [
+	"_G", "_VERSION", "assert", "collectgarbage", "coroutine", "debug", "dofile", "error",
+	"getmetatable", "io", "ipairs", "load", "loadfile", "loadstring", "math", "next", "os",
+	"pairs", "pcall", "print", "rawequal", "rawget", "rawset", "require", "select",
+	"setmetatable", "string", "table", "tonumber", "tostring", "type",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// A program which runs Lua code, which chooses the options that work in it (see [`Options::preset`])
+pub enum TargetEnv {
+	#[cfg_attr(feature = "clap", clap(name = "roblox"))]
+	/// Roblox: Luau, where the output is a ModuleScript, which must return a value
+	Roblox,
+
+	#[cfg_attr(feature = "clap", clap(name = "craftos"))]
+	/// CraftOS: The operating system of the computers of ComputerCraft (CC: Tweaked),
+	/// which runs Lua 5.1 code with the bit32 library
+	CraftOS,
+}
+
+impl TargetEnv {
+	/// Returns the version of Lua the environment runs
+	pub const fn lua_version(self) -> LuaVersion {
+		match self {
+			TargetEnv::Roblox => LuaVersion::Luau,
+			TargetEnv::CraftOS => LuaVersion::Lua51,
+		}
+	}
+
+	/// Returns the globals the environment defines besides the ones of the standard library
+	#[rustfmt::skip]
+	pub const fn globals(self) -> &'static [&'static str] {
+		match self {
+			TargetEnv::Roblox => &[
+				"game", "workspace", "script", "plugin", "shared", "settings", "UserSettings",
+				"Instance", "Enum", "Vector2", "Vector3", "CFrame", "Color3", "BrickColor",
+				"UDim", "UDim2", "Rect", "Ray", "Region3", "TweenInfo", "NumberRange",
+				"NumberSequence", "ColorSequence", "PhysicalProperties", "Random", "DateTime",
+				"task", "wait", "delay", "spawn", "tick", "time", "warn", "typeof", "bit32",
+				"utf8",
+			],
+			TargetEnv::CraftOS => &[
+				"term", "fs", "shell", "http", "peripheral", "redstone", "rs", "colors", "colours",
+				"textutils", "parallel", "paintutils", "window", "vector", "keys", "gps", "rednet",
+				"disk", "settings", "multishell", "turtle", "pocket", "commands", "read", "sleep",
+				"write", "printError", "bit32", "_HOST",
+			],
+		}
+	}
+
+	/// Returns whether the environment defines the global, the standard library included
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_frontend::env::TargetEnv;
+	///
+	/// assert!(TargetEnv::Roblox.defines_global("workspace"));
+	/// assert!(TargetEnv::CraftOS.defines_global("print"));
+	/// assert!(!TargetEnv::CraftOS.defines_global("workspace"));
+	/// ```
+	pub fn defines_global(self, name: &str) -> bool {
+		STANDARD_GLOBALS.contains(&name) || self.globals().contains(&name)
+	}
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// The mode to use for bitwise operations
+pub enum BitwiseMode {
+	#[default]
+	/// Clue: This mode uses the bitwise operators from Clue as is with no change
+	/// Works in BLUA
+	Clue,
+
+	#[cfg_attr(feature = "clap", clap(name = "library"))]
+	/// Library: This mode uses the bit library to perform bitwise operations
+	/// Works in LuaJIT (bit), Lua 5.2 (bit32)
+	Library,
+
+	#[cfg_attr(feature = "clap", clap(name = "vanilla"))]
+	/// Vanilla: This mode uses the bitwise operators from standard Lua
+	/// Works in Lua 5.3+
+	Vanilla,
+
+	#[cfg_attr(feature = "clap", clap(name = "pure"))]
+	/// Pure: Like `Library`, but the bit library is written in Lua and added once at the start of the output
+	/// Works in any version of Lua, but it's much slower
+	Pure,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// How named arguments in function calls (like `spawn(x = 10, y = 20)`) are passed to the function
+pub enum NamedArgsMode {
+	#[default]
+	#[cfg_attr(feature = "clap", clap(name = "table"))]
+	/// Table: The named arguments are put in a table given as the last argument,
+	/// so `spawn(x = 10, y = 20)` becomes `spawn({x = 10, y = 20})`
+	Table,
+
+	#[cfg_attr(feature = "clap", clap(name = "positional"))]
+	/// Positional: The named arguments are given in the position of the parameter with their name,
+	/// which only works when calling functions declared before the call in the same file
+	Positional,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// How named enums (like `enum Color { RED, GREEN }`) are compiled,
+/// a single enum can use another mode with the `@table`, `@reverse` or `@inline` attribute
+pub enum EnumMode {
+	#[default]
+	#[cfg_attr(feature = "clap", clap(name = "table"))]
+	/// Table: The enum is a table with its members, and `Color.RED` is read from it
+	Table,
+
+	#[cfg_attr(feature = "clap", clap(name = "reverse"))]
+	/// Reverse: Like `Table`, but the table also maps the value of every member
+	/// to its name, so that `Color[1]` is `"RED"`
+	Reverse,
+
+	#[cfg_attr(feature = "clap", clap(name = "inline"))]
+	/// Inline: `Color.RED` is replaced by the value of the member, which is faster,
+	/// and the table is only kept for global enums, since other files could use it
+	Inline,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// The language the code is compiled to
+pub enum Language {
+	#[default]
+	#[cfg_attr(feature = "clap", clap(name = "lua"))]
+	/// Lua: Plain Lua code, for the version chosen with [`Options::env_target`]
+	Lua,
+
+	#[cfg_attr(feature = "clap", clap(name = "teal"))]
+	/// Teal: Lua with types (`.tl` files), where structs become records
+	/// and the functions of structs get the types of their parameters
+	Teal,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// The boilerplate the output is wrapped in, for the frameworks which load it in their own way
+pub enum WrapMode {
+	#[default]
+	#[cfg_attr(feature = "clap", clap(name = "none"))]
+	/// None: The output is left as it is
+	None,
+
+	#[cfg_attr(feature = "clap", clap(name = "love2d"))]
+	/// Love2D: The functions in the table returned by the code (like `load`, `update` and `draw`)
+	/// become the callbacks of [LÖVE](https://love2d.org), so the output can be its `main.lua`
+	Love2D,
+
+	#[cfg_attr(feature = "clap", clap(name = "amd"))]
+	/// AMD: The code is the factory of an [AMD](https://github.com/amdjs/amdjs-api) `define`,
+	/// which gets `require`, `exports` and `module` and returns the value of the module
+	AMD,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", clap(rename_all = "verbatim"))]
+/// A step of the compilation whose result can be written to a file, see [`Options::env_dump`]
+pub enum Stage {
+	#[cfg_attr(feature = "clap", clap(name = "preprocess"))]
+	/// Preprocess: The code after the preprocessor ran
+	Preprocess,
+
+	#[cfg_attr(feature = "clap", clap(name = "scan"))]
+	/// Scan: The tokens found by the scanner
+	Scan,
+
+	#[cfg_attr(feature = "clap", clap(name = "parse"))]
+	/// Parse: The AST built by the parser, with the static variables
+	Parse,
+
+	#[cfg_attr(feature = "clap", clap(name = "optimize"))]
+	/// Optimize: The AST after the passes that change it, like [`Options::env_fold_constants`]
+	Optimize,
+
+	#[cfg_attr(feature = "clap", clap(name = "mangle"))]
+	/// Mangle: The new names of the locals renamed by [`Options::env_mangle_names`], with the names they replace
+	Mangle,
+
+	#[cfg_attr(feature = "clap", clap(name = "compile"))]
+	/// Compile: The generated Lua code
+	Compile,
+}
+
+impl Stage {
+	/// Returns the name of the stage, used as the extension of the files it's dumped to
+	pub const fn name(self) -> &'static str {
+		match self {
+			Stage::Preprocess => "preprocess",
+			Stage::Scan => "scan",
+			Stage::Parse => "parse",
+			Stage::Optimize => "optimize",
+			Stage::Mangle => "mangle",
+			Stage::Compile => "compile",
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// The compiler options for Clue
+pub struct Options {
+	/// The path of the output file, if any
+	pub env_outputname: Option<PathBuf>,
+
+	/// Prints the tokens to stdout
+	pub env_tokens: bool,
+
+	/// Prints the AST to stdout
+	pub env_struct: bool,
+
+	/// DEPRECATED
+	///
+	/// The name of the varible the bits library is assigned to
+	pub env_jitbit: Option<String>,
+
+	/// The mode to use for bitwise operations
+	pub env_bitwise: BitwiseMode,
+
+	/// The continue mode to use when compiling `continue` keywords
+	pub env_continue: ContinueMode,
+
+	/// How named arguments in function calls are passed to the function
+	pub env_named_args: NamedArgsMode,
+
+	/// How named enums are compiled, see [`EnumMode`]
+	pub env_enums: EnumMode,
+
+	/// Whether to put EmmyLua annotations (`---@class`, `---@field`, `---@param`, `---@enum`, ...)
+	/// before the compiled structs and named enums, so that editors know them in the Lua code
+	pub env_annotations: bool,
+
+	/// The language the code is compiled to, see [`Language`]
+	pub env_language: Language,
+
+	/// Whether to use rawset(_G, ...) instead of simply x = ... for globals
+	pub env_rawsetglobals: bool,
+
+	/// The table the globals are read from and declared in, only for Lua 5.2 and later:
+	/// the output starts with `local _ENV = <table>` (unless it's `_ENV` itself)
+	/// and the globals are declared as `_ENV.name = ...`, so the code can be sandboxed
+	pub env_global_table: Option<String>,
+
+	/// Whether the globals declared at the top level of a file become locals declared at its start
+	/// and the file ends with `return { name = name, ... }`, so that it can be required as a module
+	pub env_exports: bool,
+
+	/// The boilerplate of a framework the whole output is wrapped in, see `wrap_entry` in the `clue_backend_lua` crate
+	pub env_wrap: WrapMode,
+
+	/// Lua code put after the static variables of every output (or once for a whole directory),
+	/// like `local unpack = unpack or table.unpack`, see `compile_preamble` in the `clue_backend_lua` crate
+	pub env_preamble: Option<String>,
+
+	/// Whether to print debug information
+	pub env_debug: bool,
+
+	/// Whether to check at runtime that the values being indexed and the results of the calls
+	/// used in arithmetic are not nil, failing with the Clue file and line when they are
+	pub env_strict: bool,
+
+	/// Whether to mark the compiled statements with the Clue line they came from,
+	/// see `add_traceback_helper` in the `clue_backend_lua` crate
+	pub env_traceback: bool,
+
+	/// Whether to mark the compiled statements with the Clue line they came from to build a source map,
+	/// see `extract_source_map` in the `clue_backend_lua` crate
+	pub env_source_map: bool,
+
+	/// Whether to mark the compiled statements with the Clue line they came from to build the debug information,
+	/// see `extract_debug_info` in the `clue_backend_lua` crate
+	pub env_debug_info: bool,
+
+	/// The function called with the Clue file and line before the statements and at the start
+	/// of the blocks to measure the code coverage, see `covered_lines` in the `clue_backend_lua` crate
+	pub env_coverage: Option<String>,
+
+	/// Whether to call `enter(name)` and `exit(name, ...)` on the profiler around the body of every function,
+	/// where the name is the Clue name of the function with its file and line
+	pub env_profile: bool,
+
+	/// The table with the `enter` and `exit` functions used by [`Options::env_profile`],
+	/// if there's none `clue_profile` is added to the output, which adds up the time spent in each function
+	pub env_profile_hook: Option<String>,
+
+	/// Whether to print the output to stdout
+	pub env_output: bool,
+
+	/// Whether to print the preprocessed file
+	pub env_expand: bool,
+
+	/// Whether to measure the size and the compile time of every file and print them in a table
+	/// after compiling (see `CompileStats` in the `clue_backend_lua` crate)
+	pub env_stats: bool,
+
+	/// The Lua version to target, also checked by `@iflua`
+	pub env_target: Option<LuaVersion>,
+
+	/// The environment the code runs in, which chooses [`Options::env_target`] (see [`TargetEnv`])
+	pub env_target_env: Option<TargetEnv>,
+
+	/// The operating system checked by `@ifos`
+	pub env_targetos: String,
+
+	/// The architecture checked by `@ifarch`
+	pub env_targetarch: String,
+
+	/// Whether to print the symbol table
+	pub env_symbols: bool,
+
+	/// Whether to output the AST as JSON instead of the compiled Lua code
+	pub env_ast: bool,
+
+	/// The stages whose result is written to `<file name>.<stage>.txt`, for finding which one causes a bug
+	pub env_dump: Vec<Stage>,
+
+	/// Whether to write every decision of the parser to `<file name>.trace.txt`,
+	/// see [`crate::parser::parse_tokens_traced`]
+	pub env_trace_parse: bool,
+
+	/// Whether to fold constant expressions and remove the branches that never run, see [`crate::optimize::fold_constants`]
+	pub env_fold_constants: bool,
+
+	/// Whether to replace the calls to small local functions with what they return,
+	/// see [`crate::optimize::inline_functions`]
+	pub env_inline_functions: bool,
+
+	/// Whether to run the `const { ... }` blocks while compiling, in a sandbox,
+	/// see `ConstBlocks` in `clue_core` (it needs its `mlua` feature).
+	/// Otherwise they run when the code is loaded
+	pub env_const_eval: bool,
+
+	/// The most instructions the `const { ... }` blocks of a file can run while compiling,
+	/// or [`None`] for the default limit of `ConstBlocks` in `clue_core`
+	pub env_const_max_instructions: Option<u64>,
+
+	/// The most bytes of memory the `const { ... }` blocks of a file can use while compiling,
+	/// or [`None`] for the default limit of `ConstBlocks` in `clue_core`
+	pub env_const_max_memory: Option<usize>,
+
+	/// The global functions and values the `const { ... }` blocks can use besides the libraries
+	/// while compiling, or [`None`] for `CONST_GLOBALS` in `clue_core`
+	pub env_const_globals: Option<Vec<String>>,
+
+	/// Whether to rename the local variables to short names, see [`crate::optimize::mangle_names`]
+	pub env_mangle_names: bool,
+
+	/// Whether code using syntax of future versions of Clue should fail with a single error
+	/// telling which version it needs, instead of the syntax errors it would cause
+	pub env_forward_compat: bool,
+
+	/// Whether chained comparisons like `a < b < c` mean `a < b and b < c` (evaluating `b` once)
+	/// instead of comparing the result of `a < b` with `c`
+	pub env_chained_comparisons: bool,
+
+	/// The fewest literals the cases of a `match` block must compare the value with for it to be
+	/// compiled to a table of functions indexed by them instead of an `if` chain,
+	/// or [`None`] to always use `if` chains
+	pub env_match_dispatch: Option<usize>,
+
+	/// Whether the static variables of the modules of a bundle are run when the module is first imported
+	/// instead of when the program starts, which makes them only visible in their own module
+	pub env_lazy_modules: bool,
+
+	/// Whether the output of a directory must only depend on its files and the options, and not
+	/// on where it is: the files are then named by their path relative to the directory, with `/`
+	/// between the folders, in the output (like in the debug information and the source maps).
+	/// The files are always put in the output in the order of their paths and the temporary
+	/// variables are numbered from 0 in every file, so compiling twice gives the same output
+	pub env_reproducible: bool,
+
+	/// The column after which the lines of the output are split where it's safe,
+	/// see `wrap_lines` in the `clue_backend_lua` crate
+	pub env_max_line_length: Option<usize>,
+
+	/// The number of spaces the output is indented with, or [`None`] to indent it with tabs
+	pub env_indent_spaces: Option<usize>,
+
+	/// Whether to remove the semicolons the output puts at the end of the statements
+	pub env_omit_semicolons: bool,
+
+	/// The most empty lines in a row the output can have, or [`None`] for no limit
+	pub env_max_blank_lines: Option<usize>,
+
+	//pub env_types: TypesMode,
+	//pub env_std: LuaSTD,
+}
+
+impl Options {
+	/// Applies the chosen preset to the options
+	/// This should be called after `env_jitbit`, `env_target` or `env_target_env` is set to reflect the chosen preset
+	pub fn preset(&mut self) {
+		use LuaVersion::*;
+		if let Some(environment) = self.env_target_env {
+			self.env_target = Some(environment.lua_version());
+			if environment == TargetEnv::CraftOS && self.env_jitbit.is_none() {
+				self.env_jitbit = Some(String::from("bit32"));
+			}
+		}
+		let Some(version) = self.env_target else {
+			return;
+		};
+		match version {
+			LuaJIT => {
+				if self.env_jitbit.is_none() {
+					self.env_jitbit = Some(String::from("bit"));
+				}
+				self.env_bitwise = BitwiseMode::Library;
+				self.env_continue = ContinueMode::Goto;
+			}
+			Lua54 | Lua53 => {
+				self.env_bitwise = BitwiseMode::Vanilla;
+				self.env_continue = ContinueMode::Goto;
+			}
+			Lua52 => {
+				if self.env_jitbit.is_none() {
+					self.env_jitbit = Some(String::from("bit32"));
+				}
+				self.env_bitwise = BitwiseMode::Library;
+				self.env_continue = ContinueMode::Goto;
+			}
+			Lua51 => {
+				if self.env_jitbit.is_none() {
+					self.env_jitbit = Some(String::from("bit"));
+				}
+				self.env_bitwise = BitwiseMode::Library;
+				self.env_continue = ContinueMode::MoonScript;
+			}
+			BLUA => {
+				self.env_bitwise = BitwiseMode::Clue;
+				self.env_continue = ContinueMode::Simple;
+				self.env_rawsetglobals = true;
+			}
+			Luau => {
+				if self.env_jitbit.is_none() {
+					self.env_jitbit = Some(String::from("bit32"));
+				}
+				self.env_bitwise = BitwiseMode::Library;
+				self.env_continue = ContinueMode::Simple;
+			}
+			Lua51Pure => {
+				if self.env_jitbit.is_none() {
+					self.env_jitbit = Some(String::from("bit"));
+				}
+				self.env_bitwise = BitwiseMode::Pure;
+				self.env_continue = ContinueMode::MoonScript;
+			}
+		}
+	}
+}
@@ -0,0 +1,224 @@
+//! The features module finds which language features a piece of Clue code uses,
+//! so that the code can be checked against the Lua version it will run on before compiling it.
+//!
+//! It exposes [`FeatureSet`] and [`analyze_tokens`], most users will want to use
+//! `Clue::analyze_features` from the `clue_core` crate instead.
+
+use std::{fmt, ops};
+
+use crate::{
+	env::LuaVersion,
+	scanner::{Token, TokenType::*},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A set of language features, see the associated constants for the available ones
+pub struct FeatureSet(u8);
+
+impl FeatureSet {
+	/// No features
+	pub const EMPTY: Self = Self(0);
+
+	/// Bitwise operators (`&`, `|`, `^^`, `~`, `<<` and `>>`),
+	/// they need Lua 5.3+, BLUA or a bit library (LuaJIT's `bit`, Lua 5.2's `bit32`)
+	pub const BITWISE: Self = Self(1);
+
+	/// The `continue` keyword, it needs `goto` (Lua 5.2+, LuaJIT) or BLUA
+	/// unless the `MoonScript` continue mode is used
+	pub const CONTINUE: Self = Self(1 << 1);
+
+	/// Numbers with the `LL` or `ULL` suffix, only LuaJIT supports them
+	pub const INTEGER_SUFFIX: Self = Self(1 << 2);
+
+	/// Safe navigation (`?.`, `?::`, `?[` and `?(`), it works with every Lua version
+	pub const SAFE_NAVIGATION: Self = Self(1 << 3);
+
+	/// `const` variables, they need Lua 5.4
+	pub const CONST: Self = Self(1 << 4);
+
+	/// The floor division operator (`//`), it works with every Lua version
+	pub const FLOOR_DIVISION: Self = Self(1 << 5);
+
+	/// `goto` statements and labels, they need Lua 5.2+ or LuaJIT
+	pub const GOTO: Self = Self(1 << 6);
+
+	/// Ordered tables (`ordered { ... }`), they need `__pairs` (Lua 5.2+)
+	pub const ORDERED: Self = Self(1 << 7);
+
+	/// All the features with their names and the Lua versions they work with
+	pub const ALL: [(Self, &'static str, &'static str); 8] = [
+		(
+			Self::BITWISE,
+			"bitwise operators",
+			"needs Lua 5.3+ or BLUA, or a bit library on LuaJIT and Lua 5.2 (--bitwise library)",
+		),
+		(
+			Self::CONTINUE,
+			"continue",
+			"needs goto on LuaJIT and Lua 5.2+ (--continue goto) or BLUA, or --continue MoonScript",
+		),
+		(Self::INTEGER_SUFFIX, "integer suffixes", "needs LuaJIT"),
+		(
+			Self::SAFE_NAVIGATION,
+			"safe navigation",
+			"works with any Lua version",
+		),
+		(Self::CONST, "const variables", "needs Lua 5.4"),
+		(
+			Self::FLOOR_DIVISION,
+			"floor division",
+			"works with any Lua version",
+		),
+		(Self::GOTO, "goto", "needs LuaJIT or Lua 5.2+"),
+		(Self::ORDERED, "ordered tables", "needs Lua 5.2+"),
+	];
+
+	/// The Lua versions that can be targeted, from the one with the least features
+	pub const TARGETS: [LuaVersion; 8] = [
+		LuaVersion::Lua51,
+		LuaVersion::Luau,
+		LuaVersion::Lua51Pure,
+		LuaVersion::LuaJIT,
+		LuaVersion::Lua52,
+		LuaVersion::Lua53,
+		LuaVersion::Lua54,
+		LuaVersion::BLUA,
+	];
+
+	/// Returns `true` if all the features of `other` are in this set
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// Returns `true` if the set has no features
+	pub const fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Adds the features of `other` to this set
+	pub fn insert(&mut self, other: Self) {
+		self.0 |= other.0;
+	}
+
+	/// Returns the names of the features in this set
+	pub fn names(self) -> Vec<&'static str> {
+		self.requirements()
+			.into_iter()
+			.map(|(name, _)| name)
+			.collect()
+	}
+
+	/// Returns the names of the features in this set with the Lua versions they work with
+	pub fn requirements(self) -> Vec<(&'static str, &'static str)> {
+		Self::ALL
+			.iter()
+			.filter(|(feature, ..)| self.contains(*feature))
+			.map(|(_, name, requirement)| (*name, *requirement))
+			.collect()
+	}
+
+	/// Returns the features that the given Lua version can run,
+	/// assuming the options set by [`Options::preset`](crate::env::Options::preset) for it are used
+	pub const fn supported_by(version: LuaVersion) -> Self {
+		use LuaVersion::*;
+		let common = Self::SAFE_NAVIGATION.0 | Self::FLOOR_DIVISION.0 | Self::CONTINUE.0;
+		Self(match version {
+			LuaJIT => common | Self::BITWISE.0 | Self::INTEGER_SUFFIX.0 | Self::GOTO.0,
+			Lua54 => common | Self::BITWISE.0 | Self::CONST.0 | Self::GOTO.0 | Self::ORDERED.0,
+			Lua53 | Lua52 => common | Self::BITWISE.0 | Self::GOTO.0 | Self::ORDERED.0,
+			BLUA => common | Self::BITWISE.0 | Self::GOTO.0,
+			Luau | Lua51Pure => common | Self::BITWISE.0,
+			Lua51 => common,
+		})
+	}
+
+	/// Returns the features of this set that the given Lua version cannot run
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_frontend::{env::LuaVersion, features::FeatureSet};
+	///
+	/// let features = FeatureSet::BITWISE | FeatureSet::SAFE_NAVIGATION;
+	/// assert!(features.unsupported_by(LuaVersion::Lua53).is_empty());
+	/// assert_eq!(features.unsupported_by(LuaVersion::Lua51), FeatureSet::BITWISE);
+	/// ```
+	pub const fn unsupported_by(self, version: LuaVersion) -> Self {
+		Self(self.0 & !Self::supported_by(version).0)
+	}
+
+	/// Returns the Lua versions that can run all the features of this set,
+	/// the first one being the minimum target
+	///
+	/// # Example
+	/// ```rust
+	/// use clue_frontend::{env::LuaVersion, features::FeatureSet};
+	///
+	/// let features = FeatureSet::BITWISE | FeatureSet::INTEGER_SUFFIX;
+	/// assert_eq!(features.compatible_targets(), [LuaVersion::LuaJIT]);
+	/// ```
+	pub fn compatible_targets(self) -> Vec<LuaVersion> {
+		Self::TARGETS
+			.into_iter()
+			.filter(|version| self.unsupported_by(*version).is_empty())
+			.collect()
+	}
+}
+
+impl ops::BitOr for FeatureSet {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self {
+		Self(self.0 | rhs.0)
+	}
+}
+
+impl ops::BitOrAssign for FeatureSet {
+	fn bitor_assign(&mut self, rhs: Self) {
+		self.insert(rhs);
+	}
+}
+
+impl fmt::Display for FeatureSet {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.names().join(", "))
+	}
+}
+
+// `ordered {` starts an ordered table unless it's a name followed by the block of a statement
+fn is_ordered_table(tokens: &[Token], i: usize) -> bool {
+	tokens[i].lexeme == "ordered"
+		&& tokens
+			.get(i + 1)
+			.is_some_and(|next| next.kind == CURLY_BRACKET_OPEN)
+		&& !i.checked_sub(1).is_some_and(|previous| {
+			matches!(
+				tokens[previous].kind,
+				IF | ELSEIF | WHILE | UNTIL | MATCH | IN | OF | WITH
+			)
+		})
+}
+
+/// Finds the features used by the given tokens
+pub fn analyze_tokens(tokens: &[Token]) -> FeatureSet {
+	let mut features = FeatureSet::EMPTY;
+	for (i, token) in tokens.iter().enumerate() {
+		features |= match token.kind {
+			BIT_AND | BIT_OR | BIT_XOR | BIT_NOT | LEFT_SHIFT | RIGHT_SHIFT => FeatureSet::BITWISE,
+			CONTINUE => FeatureSet::CONTINUE,
+			NUMBER if token.lexeme.ends_with("LL") => FeatureSet::INTEGER_SUFFIX,
+			SAFE_DOT | SAFE_DOUBLE_COLON | SAFE_SQUARE_BRACKET | SAFE_CALL => {
+				FeatureSet::SAFE_NAVIGATION
+			}
+			CONST => FeatureSet::CONST,
+			FLOOR_DIVISION => FeatureSet::FLOOR_DIVISION,
+			GOTO => FeatureSet::GOTO,
+			IDENTIFIER if is_ordered_table(tokens, i) => FeatureSet::ORDERED,
+			_ => continue,
+		};
+	}
+	features
+}
@@ -0,0 +1,117 @@
+//! The grammar module contains a machine readable catalog of the tokens and of the AST nodes of Clue,
+//! so that external tools (like syntax highlighters) can stay in sync with the compiler.
+//!
+//! The catalog is generated by the build script from the definitions of
+//! [`TokenType`](crate::scanner::TokenType) and [`ComplexToken`](crate::parser::ComplexToken),
+//! so it always matches the version of the crate.
+
+use crate::scanner::TokenType;
+
+/// The catalog as JSON, an object with these keys:
+/// - `version`: the [`AST_FORMAT_VERSION`](crate::parser::AST_FORMAT_VERSION) the catalog describes
+/// - `tokens`: every token type with its `name` and `category` (`symbols`, `literals`, `keywords` or `other`)
+/// - `symbols`: every symbol with its `lexeme` and the `token` it is scanned as
+/// - `string_delimiters`: the characters that start a string
+/// - `keywords`: every keyword with the `token` it is scanned as and whether it is also a `lua` keyword
+/// - `reserved`: the words that cannot be used as names
+/// - `aliases`: the type aliases used by the fields of the AST
+/// - `structs` and `nodes`: the structs used by the AST and the variants of
+///   [`ComplexToken`](crate::parser::ComplexToken), with their `name`, `doc` and `fields`
+///   (each with its `name`, Rust `type` and `doc`, tuple variants have a single field called `0`)
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::grammar::GRAMMAR;
+///
+/// assert!(GRAMMAR.contains("{\"lexeme\": \"|>\", \"token\": \"PIPE\"}"));
+/// assert!(GRAMMAR.contains("{\"keyword\": \"fn\", \"token\": \"FN\", \"lua\": false}"));
+/// ```
+pub const GRAMMAR: &str = include_str!(concat!(env!("OUT_DIR"), "/grammar.json"));
+
+/// The kind of source text a [`TokenSpec`] describes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenForm {
+	/// A word with a special meaning, its text is the word itself
+	Keyword,
+
+	/// A word that cannot be used as a name, its text is the word itself
+	Reserved,
+
+	/// An operator or punctuation, its text is the exact lexeme
+	Symbol,
+
+	/// A literal, name or comment, its text is a regular expression matching it
+	Literal,
+}
+
+/// An entry of [`TOKEN_SPEC`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TokenSpec {
+	/// The exact text of the token, or a regular expression if [`form`](TokenSpec::form) is
+	/// [`TokenForm::Literal`]
+	pub text: &'static str,
+
+	/// What [`text`](TokenSpec::text) describes
+	pub form: TokenForm,
+
+	/// The TextMate scope used to highlight the token, like `keyword.control` or `string`
+	pub category: &'static str,
+
+	/// The token the text is scanned as, [`None`] for reserved words and comments
+	pub token: Option<TokenType>,
+}
+
+/// Every keyword, reserved word, symbol and literal form of Clue with its highlighting category,
+/// generated like [`GRAMMAR`] so that syntax highlighters can be generated from it.
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{grammar::*, scanner::TokenType};
+///
+/// let pipe = TOKEN_SPEC.iter().find(|spec| spec.text == "|>").unwrap();
+/// assert_eq!(pipe.form, TokenForm::Symbol);
+/// assert_eq!(pipe.category, "keyword.operator");
+/// assert_eq!(pipe.token, Some(TokenType::PIPE));
+/// ```
+pub const TOKEN_SPEC: &[TokenSpec] = &include!(concat!(env!("OUT_DIR"), "/token_spec.rs"));
+
+/// Returns [`TOKEN_SPEC`] as a JSON array of objects with the keys
+/// `text`, `form` (`keyword`, `reserved`, `symbol` or `literal`), `category` and `token`
+/// (the name of the token or `null`)
+pub fn token_spec_json() -> String {
+	let escape = |text: &str| {
+		let mut escaped = String::with_capacity(text.len() + 2);
+		escaped.push('"');
+		for c in text.chars() {
+			match c {
+				'"' => escaped.push_str("\\\""),
+				'\\' => escaped.push_str("\\\\"),
+				'\n' => escaped.push_str("\\n"),
+				c => escaped.push(c),
+			}
+		}
+		escaped.push('"');
+		escaped
+	};
+	let entries: Vec<String> = TOKEN_SPEC
+		.iter()
+		.map(|spec| {
+			let form = match spec.form {
+				TokenForm::Keyword => "keyword",
+				TokenForm::Reserved => "reserved",
+				TokenForm::Symbol => "symbol",
+				TokenForm::Literal => "literal",
+			};
+			let token = spec.token.map_or_else(
+				|| String::from("null"),
+				|token| escape(&format!("{token:?}")),
+			);
+			format!(
+				"\t{{\"text\": {}, \"form\": \"{form}\", \"category\": {}, \"token\": {token}}}",
+				escape(spec.text),
+				escape(spec.category)
+			)
+		})
+		.collect();
+	format!("[\n{}\n]", entries.join(",\n"))
+}
@@ -0,0 +1,151 @@
+//! # The Clue compiler frontend
+//! This crate contains the preprocessor, the scanner and the parser of the Clue compiler,
+//! it turns Clue code into an AST without generating any code.
+//!
+//! It can be used by tools that only need to understand Clue code (like linters, formatters
+//! or language servers) and by backends that want to generate something else than Lua.
+//! The Lua code generator lives in the `clue_backend_lua` crate,
+//! while the `clue_core` crate combines both behind the `Clue` struct.
+//!
+//! # Stable API
+//! The following items are the stable API of the frontend:
+//! - [`parse_source`] and [`scan_source`], the simplest way to get the AST or the tokens of some code
+//! - the [`code`], [`env`], [`preprocessor`], [`scanner`] and [`parser`] modules,
+//!   which contain the single steps of the frontend and the AST itself ([`parser::ComplexToken`])
+//! - the [`parser::visitor`] module, which should be used to walk the AST
+//! - the [`parser::precedence`] module, which documents the precedence of the operators
+//! - the [`features`], [`grammar`], [`lint`], [`optimize`] and [`pattern`] modules
+//! - the `cache` module, available with the `serde` feature
+//!
+//! Items hidden from the documentation are not part of it even if they are public.
+//!
+//! # Semver policy
+//! The frontend, the Lua backend and `clue_core` are released together and always have the same version.
+//! - Patch releases only fix bugs, the AST of a piece of code that already compiled correctly does not change.
+//! - Minor releases can add new functions, options and [`TokenType`](scanner::TokenType)s,
+//!   and can make the parser produce a different (but equivalent) AST for the same code.
+//! - Any change to the shape of [`parser::ComplexToken`] or to the signature of a stable item
+//!   is a major release, which also increases [`parser::AST_FORMAT_VERSION`].
+//!
+//! Backends should only rely on the meaning of the AST, not on the exact tree the parser produces for some code.
+
+use code::Code;
+use env::Options;
+use parser::{parse_tokens, Expression};
+use preprocessor::{preprocess_code, preprocess_codes, Remapping};
+use scanner::{scan_code_with_options, Token};
+
+#[cfg(feature = "serde")]
+pub mod cache;
+pub mod code;
+pub mod env;
+pub mod features;
+pub mod grammar;
+pub mod lint;
+pub mod optimize;
+pub mod parser;
+pub mod pattern;
+pub mod preprocessor;
+pub mod scanner;
+
+#[macro_export]
+/// Check whether `tocheck` is `Ok` or `Err`
+/// If it's `Ok` it returns it
+/// If it's `Err` it converts the error to a `String` and propagates it to the caller function
+macro_rules! check {
+	($tocheck:expr) => {
+		match $tocheck {
+			Ok(t) => t,
+			Err(e) => return Err(e.to_string()),
+		}
+	};
+}
+
+#[macro_export]
+/// Format strings, used mainly inside the Clue code base
+/// This is used to format strings in a way that is more efficient than using `format!`
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::format_clue;
+///
+/// let a = "Hello";
+/// let b = "World";
+/// let c = format_clue!(a, ", ", b, "!");
+/// assert_eq!(c, "Hello, World!");
+/// ```
+macro_rules! format_clue {
+    ($($strings:expr),+) => {{
+        let vc = [
+          $($strings.to_string(),)+
+        ];
+
+        vc.join("")
+    }};
+}
+
+/// Preprocesses and scans the given code
+/// Takes a [`String`] containing the code, the name of the file it comes from and the [`Options`]
+///
+/// # Errors
+/// If an error occurs while preprocessing or scanning the code, an [`Err`] containing a [`String`] with the error message will be returned
+pub fn scan_source(
+	code: String,
+	filename: &String,
+	options: &Options,
+) -> Result<Vec<Token>, String> {
+	Ok(scan_remapped_source(code, filename, options)?.0)
+}
+
+// like scan_source, also returning the remapping the parser needs to report its errors
+fn scan_remapped_source(
+	code: String,
+	filename: &String,
+	options: &Options,
+) -> Result<(Vec<Token>, Remapping), String> {
+	let mut code = code;
+	let (codes, variables, .., mut remapping) = preprocess_code(
+		// SAFETY: This is safe because the preprocessor will never output anything other than UTF-8
+		unsafe { code.as_bytes_mut() },
+		1,
+		false,
+		filename,
+		options,
+	)?;
+	let code: Code = preprocess_codes(0, codes, &variables, &mut remapping, filename)?;
+	let tokens = scan_code_with_options(code, filename, &remapping, options)?;
+	Ok((tokens, remapping))
+}
+
+/// Preprocesses, scans and parses the given code
+/// Takes a [`String`] containing the code, the name of the file it comes from and the [`Options`]
+///
+/// Returns the AST of the code and the AST of its static variables, see [`parse_tokens`]
+///
+/// # Errors
+/// If an error occurs while preprocessing, scanning or parsing the code, an [`Err`] containing a [`String`] with the error message will be returned
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{env::Options, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let (ast, statics) = parse_source(
+///         "static a = 1\nprint(a)".to_owned(),
+///         &String::from("(library)"),
+///         &Options::default(),
+///     )?;
+///     assert_eq!(ast.len(), 1);
+///     assert_eq!(statics.len(), 1);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn parse_source(
+	code: String,
+	filename: &String,
+	options: &Options,
+) -> Result<(Expression, Expression), String> {
+	let (tokens, remapping) = scan_remapped_source(code, filename, options)?;
+	parse_tokens(tokens, filename, &remapping, options)
+}
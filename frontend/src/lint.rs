@@ -0,0 +1,393 @@
+//! The `lint` module contains the analysis of the scopes of the AST and the lints made with it
+//!
+//! [`find_locals`] finds every local the code declares and counts how many times it's read,
+//! following the same scoping rules as Lua, and [`unused_locals`] uses it to find the ones
+//! which are never read. The parser warns about those after parsing a file
+
+use crate::optimize::is_name;
+use crate::parser::{
+	visitor::{walk_code_block, walk_ctoken, Visitor},
+	CodeBlock,
+	ComplexToken::{self, *},
+	Expression, FunctionArgs,
+};
+use crate::scanner::TokenType::DEFINE;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// What declared a [`Local`]
+pub enum LocalKind {
+	/// A local variable, an iterator of a loop or the error of a `catch` block
+	Variable,
+
+	/// An argument of a function, including the implicit `self` of the methods
+	Argument,
+
+	/// A local function
+	Function,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A local declared by the code
+pub struct Local {
+	/// The name of the local
+	pub name: String,
+
+	/// What declared the local
+	pub kind: LocalKind,
+
+	/// The line where the local is declared,
+	/// which for the arguments and the functions is the line where the code of the function starts
+	pub line: usize,
+
+	/// How many times the local is read, assigning it a value is not a read
+	pub reads: usize,
+}
+
+/// Finds every local declared by `ast` in the order they are declared and counts their reads
+///
+/// The names `statics` declare are not included, since the static variables of the files of a directory
+/// share the same scope, but the reads in them are still counted.
+/// The resource of a `with` block is read once more when it's closed
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{env::Options, lint::{find_locals, LocalKind}, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local a = 1\nlocal fn f(x) { return a + x }\na = f(a)".to_owned();
+///     let (ast, statics) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     let locals = find_locals(&ast, &statics);
+///     let reads: Vec<(&str, LocalKind, usize)> = locals
+///         .iter()
+///         .map(|local| (local.name.as_str(), local.kind, local.reads))
+///         .collect();
+///     assert_eq!(
+///         reads,
+///         [
+///             ("a", LocalKind::Variable, 2),
+///             ("f", LocalKind::Function, 1),
+///             ("x", LocalKind::Argument, 1)
+///         ]
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+pub fn find_locals(ast: &Expression, statics: &Expression) -> Vec<Local> {
+	let mut scopes = Scopes {
+		locals: Vec::new(),
+		scopes: vec![Vec::new()],
+		statics: true,
+	};
+	scopes.visit_expression(statics);
+	scopes.statics = false;
+	scopes.visit_expression(ast);
+	scopes.locals
+}
+
+/// Returns the locals declared by `ast` which are never read (see [`find_locals`]),
+/// except the ones whose name starts with `_`, which are meant to be unused, and `self`
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{env::Options, lint::unused_locals, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local a = 1\nlocal _b = 2\nfor i = 1, 10 {}\nwith f = open() {}\nreturn fn(x, _y) => x";
+///     let code = code.to_owned();
+///     let (ast, statics) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     let unused: Vec<String> = unused_locals(&ast, &statics)
+///         .into_iter()
+///         .map(|local| local.name)
+///         .collect();
+///     assert_eq!(unused, ["a", "i"]);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn unused_locals(ast: &Expression, statics: &Expression) -> Vec<Local> {
+	find_locals(ast, statics)
+		.into_iter()
+		.filter(|local| local.reads == 0 && !local.name.starts_with('_') && local.name != "self")
+		.collect()
+}
+
+struct Scopes {
+	locals: Vec<Local>,
+	// the indexes of the locals that can be seen, for every scope from the outermost one
+	scopes: Vec<Vec<usize>>,
+	// whether the statics are being visited, which declare nothing
+	statics: bool,
+}
+
+impl Scopes {
+	fn declare(&mut self, name: &str, kind: LocalKind, line: usize) {
+		if self.statics || !is_name(name) {
+			return;
+		}
+		let scope = self
+			.scopes
+			.last_mut()
+			.expect("there should always be a scope");
+		scope.push(self.locals.len());
+		self.locals.push(Local {
+			name: name.to_owned(),
+			kind,
+			line,
+			reads: 0,
+		});
+	}
+
+	fn read(&mut self, name: &str) {
+		let local = self
+			.scopes
+			.iter()
+			.rev()
+			.flat_map(|scope| scope.iter().rev())
+			.find(|i| self.locals[**i].name == name);
+		if let Some(&i) = local {
+			self.locals[i].reads += 1;
+		}
+	}
+
+	fn in_scope(&mut self, f: impl FnOnce(&mut Self)) {
+		self.scopes.push(Vec::new());
+		f(self);
+		self.scopes.pop();
+	}
+
+	fn visit_function(&mut self, args: &FunctionArgs, code: &CodeBlock, method: bool) {
+		self.in_scope(|scopes| {
+			if method {
+				scopes.declare("self", LocalKind::Argument, code.start);
+			}
+			for (arg, _) in args {
+				scopes.declare(arg, LocalKind::Argument, code.start);
+			}
+			// the default values are set inside the function
+			for (_, default) in args {
+				if let Some((default, _)) = default {
+					scopes.visit_expression(default);
+				}
+			}
+			scopes.visit_code_block(code);
+		})
+	}
+}
+
+impl Visitor for Scopes {
+	fn visit_expression(&mut self, expr: &Expression) {
+		let mut field = false;
+		for ctoken in expr {
+			match ctoken {
+				// the name of a field after `.` or `:`
+				SYMBOL(_, _) if field => field = false,
+				SYMBOL(symbol, _) => {
+					field = symbol == "." || symbol == ":";
+					self.visit_symbol(symbol)
+				}
+				_ => self.visit_ctoken(ctoken),
+			}
+		}
+	}
+
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			VARIABLE {
+				local: true,
+				names,
+				values,
+				span,
+				..
+			} => {
+				for value in values {
+					self.visit_expression(value);
+				}
+				for name in names {
+					self.declare(name, LocalKind::Variable, span.start.line);
+				}
+			}
+			// assigning a value to a name doesn't read it
+			ALTER {
+				kind: DEFINE,
+				names,
+				values,
+				..
+			} => {
+				for name in names {
+					if !matches!((name.len(), name.front()), (1, Some(SYMBOL(_, _)))) {
+						self.visit_expression(name);
+					}
+				}
+				for value in values {
+					self.visit_expression(value);
+				}
+			}
+			FUNCTION {
+				local,
+				name,
+				args,
+				code,
+				attributes,
+				..
+			} => {
+				for (attribute, attribute_args) in attributes {
+					self.visit_symbol(attribute);
+					for arg in attribute_args {
+						self.visit_expression(arg);
+					}
+				}
+				match (*local, name.len(), name.front()) {
+					(true, 1, Some(SYMBOL(name, _))) => {
+						self.declare(name, LocalKind::Function, code.start)
+					}
+					(false, 1, Some(SYMBOL(_, _))) => {}
+					_ => self.visit_expression(name),
+				}
+				let method = name
+					.iter()
+					.any(|ctoken| matches!(ctoken, SYMBOL(symbol, _) if symbol == ":"));
+				self.visit_function(args, code, method)
+			}
+			LAMBDA { args, code, .. } => self.visit_function(args, code, false),
+			TABLE {
+				values,
+				metas,
+				metatable,
+				..
+			} => {
+				for (key, value, _) in values {
+					match key {
+						// a named key
+						Some(key)
+							if matches!(key.front(), Some(SYMBOL(_, _))) && key.len() == 1 => {}
+						Some(key) => self.visit_expression(key),
+						None => {}
+					}
+					self.visit_expression(value);
+				}
+				for (_, value, _) in metas {
+					self.visit_expression(value);
+				}
+				if let Some(metatable) = metatable {
+					self.visit_symbol(metatable);
+				}
+			}
+			MATCH_BLOCK {
+				name,
+				value,
+				branches,
+				span,
+			} => {
+				self.visit_expression(value);
+				self.declare(name, LocalKind::Variable, span.start.line);
+				for (conditions, internal_expr, extra_if, code) in branches {
+					self.in_scope(|scopes| {
+						for condition in conditions {
+							scopes.visit_expression(condition);
+						}
+						scopes.visit_expression(internal_expr);
+						if let Some(extra_if) = extra_if {
+							scopes.visit_expression(extra_if);
+						}
+						scopes.visit_code_block(code);
+					})
+				}
+			}
+			// the condition can see the locals of the loop
+			LOOP_UNTIL {
+				condition, code, ..
+			} => self.in_scope(|scopes| {
+				scopes.visit_expression(&code.code);
+				scopes.visit_expression(condition);
+			}),
+			FOR_LOOP {
+				iterator,
+				start,
+				end,
+				alter,
+				code,
+				span,
+				..
+			} => {
+				self.visit_expression(start);
+				self.visit_expression(end);
+				self.visit_expression(alter);
+				self.in_scope(|scopes| {
+					scopes.declare(iterator, LocalKind::Variable, span.start.line);
+					scopes.visit_code_block(code);
+				})
+			}
+			FOR_FUNC_LOOP {
+				iterators,
+				expr,
+				code,
+				span,
+				..
+			} => {
+				self.visit_expression(expr);
+				self.in_scope(|scopes| {
+					for iterator in iterators {
+						scopes.declare(iterator, LocalKind::Variable, span.start.line);
+					}
+					scopes.visit_code_block(code);
+				})
+			}
+			TRY_CATCH {
+				totry,
+				catch,
+				error,
+				finally,
+				..
+			} => {
+				self.visit_code_block(totry);
+				if let Some(catch) = catch {
+					self.in_scope(|scopes| {
+						if let Some(error) = error {
+							scopes.declare(error, LocalKind::Variable, catch.start);
+						}
+						scopes.visit_code_block(catch);
+					})
+				}
+				if let Some(finally) = finally {
+					self.visit_code_block(finally);
+				}
+			}
+			WITH_BLOCK {
+				name,
+				value,
+				code,
+				span,
+			} => {
+				self.visit_expression(value);
+				self.in_scope(|scopes| {
+					scopes.declare(name, LocalKind::Variable, span.start.line);
+					// closing the resource when the block ends reads it
+					scopes.read(name);
+					scopes.visit_code_block(code);
+				})
+			}
+			_ => walk_ctoken(self, ctoken),
+		}
+	}
+
+	fn visit_code_block(&mut self, block: &CodeBlock) {
+		self.in_scope(|scopes| walk_code_block(scopes, block))
+	}
+
+	fn visit_symbol(&mut self, symbol: &str) {
+		if is_name(symbol) {
+			return self.read(symbol);
+		}
+		// the names inside a bigger symbol (like `bit.band`), but not the words of strings
+		if symbol.starts_with(['"', '\'', '`', '[']) {
+			return;
+		}
+		let words = symbol
+			.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+			.filter(|word| is_name(word));
+		for word in words {
+			self.read(word)
+		}
+	}
+}
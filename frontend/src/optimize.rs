@@ -0,0 +1,1136 @@
+//! The `optimize` module contains the optimizations that can be done on the AST before compiling it
+//!
+//! The `PassManager` of `clue_core` runs them after parsing when they are enabled in the [`Options`](crate::env::Options)
+//! and [`mangle_names`] runs after all of them, but they can also be run on any AST
+
+use crate::parser::{
+	precedence::binary_precedence,
+	visitor::{
+		walk_code_block_mut, walk_ctoken, walk_ctoken_mut, walk_expression_mut, walk_function_args,
+		Transformer, Visitor,
+	},
+	CodeBlock,
+	ComplexToken::{self, *},
+	Expression, FunctionArgs, Span,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Folds the operations made only of literals (like `1 + 2 * 3`, `"a" .. "b"` or `!true`),
+/// replaces `if` statements whose condition is a literal with the branch that always runs
+/// and removes double negations (`!!x`) from conditions
+///
+/// Operations whose result could change between Lua versions (like integer overflows)
+/// or that would need string coercions are left as they are
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{
+///     env::Options,
+///     optimize::fold_constants,
+///     parse_source,
+///     parser::ComplexToken::*,
+/// };
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local a = 1 + 2 * 3 .. \"\" .. \"!\"\nif false { print(a) }".to_owned();
+///     let (mut ast, _) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     fold_constants(&mut ast);
+///     assert_eq!(ast.len(), 1);
+///     let VARIABLE { values, .. } = &ast[0] else {
+///         unreachable!()
+///     };
+///     assert_eq!(values[0].len(), 3);
+///     assert!(matches!(&values[0][0], EXPR(number, _) if matches!(&number[0], SYMBOL(n, _) if n == "7")));
+///     assert!(matches!(&values[0][2], SYMBOL(string, _) if string == "\"!\""));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn fold_constants(ast: &mut Expression) {
+	ConstantFolder.transform_expression(ast)
+}
+
+/// Folds `expr` and returns the literal it's made of, if it's made of just one,
+/// replacing first the names `known` returns the value of
+pub(crate) fn evaluate(
+	expr: &mut Expression,
+	known: &impl Fn(&str) -> Option<Value>,
+) -> Option<Value> {
+	replace_names(expr, known);
+	fold_constants(expr);
+	constant(expr)
+}
+
+fn replace_names(expr: &mut Expression, known: &impl Fn(&str) -> Option<Value>) {
+	for ctoken in expr {
+		match ctoken {
+			IDENT { expr, span } if expr.len() == 1 => {
+				if let Some(value) = match &expr[0] {
+					SYMBOL(name, _) => known(name),
+					_ => None,
+				} {
+					*ctoken = EXPR(
+						[SYMBOL(value.to_symbol(), span.clone())].into(),
+						span.clone(),
+					);
+				}
+			}
+			EXPR(expr, _) => replace_names(expr, known),
+			_ => {}
+		}
+	}
+}
+
+/// Replaces the calls to small local functions with the expression they return,
+/// saving the cost of the call (like in hot loops)
+///
+/// To make sure the code still does the same, a function is inlined only when:
+/// - it's a local function whose name is declared only once in the file and never assigned to
+/// - it has no attributes, its parameters have no default values and it doesn't take `...`
+/// - its body is a single `return` of one value, which doesn't call functions, create functions
+///   or tables, or use the names declared in the file other than its parameters
+///
+/// and a call is inlined only when it's not a statement, it's in the scope of the function,
+/// it gives exactly one argument for each parameter and every argument is a literal or a name
+///
+/// The declaration of the function is left as it is, since it can still be used as a value
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{
+///     env::Options,
+///     optimize::inline_functions,
+///     parse_source,
+///     parser::ComplexToken::*,
+/// };
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local fn dot(a, b) { return a.x * b.x + a.y * b.y }\nlocal d = dot(v, w)".to_owned();
+///     let (mut ast, _) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     inline_functions(&mut ast);
+///     let VARIABLE { values, .. } = &ast[1] else {
+///         unreachable!()
+///     };
+///     assert!(matches!(&values[0][0], EXPR(body, _) if body.len() == 7));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn inline_functions(ast: &mut Expression) {
+	let mut names = DeclaredNames::default();
+	names.visit_expression(ast);
+	Inliner {
+		names,
+		functions: Vec::new(),
+		statements: true,
+	}
+	.transform_expression(ast)
+}
+
+/// Renames the local variables (including the parameters, the iterators and the temporaries made by the parser)
+/// to short names like `a`, `b`, ..., `z`, `a1`, `b1`, ..., to make the output smaller,
+/// and returns the new names with the names they replace, in the order they were declared
+///
+/// Globals, fields and table keys keep their names, and so do the top level names of `statics`,
+/// since the static variables of the files of a directory share the same scope.
+/// Every local gets a different name, which is never one already used in the code,
+/// and the names the code also uses in ways that can't be followed (like inside the code generated
+/// for some operators) are kept as they are
+///
+/// It should run after every other change to the AST, since they could add new names
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{env::Options, optimize::mangle_names, parse_source};
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local count = 0\nfor index = 1, 10 { count += index }\nglobal total = count".to_owned();
+///     let (mut ast, mut statics) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     let renames = mangle_names(&mut ast, &mut statics);
+///     assert_eq!(
+///         renames,
+///         [
+///             (String::from("count"), String::from("a")),
+///             (String::from("index"), String::from("b"))
+///         ]
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+pub fn mangle_names(ast: &mut Expression, statics: &mut Expression) -> Vec<(String, String)> {
+	let mut words = Words::default();
+	words.visit_expression(statics);
+	words.visit_expression(ast);
+	words.kept.insert(String::from("self"));
+	for ctoken in statics.iter() {
+		match ctoken {
+			VARIABLE { names, .. } => words.kept.extend(names.iter().cloned()),
+			FUNCTION { name, .. } => {
+				if let (1, Some(SYMBOL(name, _))) = (name.len(), name.front()) {
+					words.kept.insert(name.clone());
+				}
+			}
+			_ => {}
+		}
+	}
+	let mut mangler = Mangler {
+		words,
+		scopes: vec![Vec::new()],
+		renames: Vec::new(),
+		next: 0,
+	};
+	mangler.transform_expression(statics);
+	mangler.transform_expression(ast);
+	mangler.renames
+}
+
+struct ConstantFolder;
+
+impl Transformer for ConstantFolder {
+	fn transform_expression(&mut self, expr: &mut Expression) {
+		walk_expression_mut(self, expr);
+		if expr
+			.iter()
+			.any(|ctoken| matches!(ctoken, IF_STATEMENT { .. }))
+		{
+			*expr = std::mem::take(expr)
+				.into_iter()
+				.filter_map(simplify_if)
+				.collect();
+		}
+		let folded = {
+			let tokens = expr.make_contiguous();
+			let mut folder = Folder {
+				tokens,
+				current: 0,
+				changed: false,
+			};
+			folder
+				.expression(0)
+				.filter(|_| folder.current == tokens.len())
+				.filter(|_| folder.changed)
+				.map(|node| {
+					let mut folded = Expression::with_capacity(tokens.len());
+					folder.emit(node, true, &mut folded);
+					folded
+				})
+		};
+		if let Some(folded) = folded {
+			*expr = folded;
+		}
+	}
+
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		walk_ctoken_mut(self, ctoken);
+		if let IF_STATEMENT { condition, .. }
+		| WHILE_LOOP { condition, .. }
+		| LOOP_UNTIL { condition, .. } = ctoken
+		{
+			// `!!x` is only the same as `x` when just its truthiness matters
+			if condition.len() == 3
+				&& condition
+					.iter()
+					.take(2)
+					.all(|ctoken| matches!(ctoken, SYMBOL(op, _) if op == "not "))
+			{
+				condition.drain(..2);
+			}
+		}
+	}
+}
+
+// returns the code that runs instead of the `if` statement, if any
+fn simplify_if(ctoken: ComplexToken) -> Option<ComplexToken> {
+	let IF_STATEMENT {
+		condition,
+		code,
+		next,
+		span,
+	} = ctoken
+	else {
+		return Some(ctoken);
+	};
+	match constant(&condition).map(|value| value.truthy()) {
+		Some(true) => Some(DO_BLOCK(code, span)),
+		Some(false) => next.and_then(|next| simplify_if(*next)),
+		None => Some(IF_STATEMENT {
+			condition,
+			code,
+			next: next.and_then(|next| simplify_if(*next)).map(Box::new),
+			span,
+		}),
+	}
+}
+
+// the value of an expression made of just a literal
+fn constant(expr: &Expression) -> Option<Value> {
+	match expr.front() {
+		Some(SYMBOL(lexeme, _)) if expr.len() == 1 => Value::parse(lexeme),
+		Some(EXPR(expr, _)) if expr.len() == 1 => constant(expr),
+		_ => None,
+	}
+}
+
+// integers bigger than this cannot be represented exactly by the numbers of Lua 5.1 and LuaJIT
+const MAX_SAFE_INTEGER: i64 = 1 << 53;
+
+/// A literal of Lua, as understood by the optimizations
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+	Integer(i64),
+	Float(f64),
+	String(char, String),
+	Boolean(bool),
+	Nil,
+}
+
+impl Value {
+	fn parse(lexeme: &str) -> Option<Self> {
+		match lexeme {
+			"true" => Some(Value::Boolean(true)),
+			"false" => Some(Value::Boolean(false)),
+			"nil" => Some(Value::Nil),
+			_ => {
+				let first = lexeme.chars().next()?;
+				if first == '"' || first == '\'' {
+					let inner = lexeme.get(1..lexeme.len() - 1)?;
+					(lexeme.len() > 1 && lexeme.ends_with(first) && !inner.contains('\n'))
+						.then(|| Value::String(first, inner.to_owned()))
+				} else if let Some(number) = lexeme.strip_prefix('-') {
+					match Value::parse(number)? {
+						Value::Integer(n) => Some(Value::Integer(-n)),
+						Value::Float(n) => Some(Value::Float(-n)),
+						_ => None,
+					}
+				} else if let Some(hex) = lexeme.strip_prefix("0x").or(lexeme.strip_prefix("0X")) {
+					i64::from_str_radix(hex, 16)
+						.ok()
+						.filter(|n| *n <= MAX_SAFE_INTEGER)
+						.map(Value::Integer)
+				} else if lexeme.bytes().all(|c| c.is_ascii_digit()) {
+					lexeme
+						.parse()
+						.ok()
+						.filter(|n| *n <= MAX_SAFE_INTEGER)
+						.map(Value::Integer)
+				} else if first.is_ascii_digit() && !lexeme.contains(['x', 'X']) {
+					lexeme.parse().ok().map(Value::Float)
+				} else {
+					None
+				}
+			}
+		}
+	}
+
+	pub(crate) fn to_symbol(&self) -> String {
+		match self {
+			Value::Integer(n) => n.to_string(),
+			Value::Float(n) => format!("{n:?}"),
+			Value::String(quote, string) => format!("{quote}{string}{quote}"),
+			Value::Boolean(boolean) => boolean.to_string(),
+			Value::Nil => String::from("nil"),
+		}
+	}
+
+	const fn truthy(&self) -> bool {
+		!matches!(self, Value::Nil | Value::Boolean(false))
+	}
+
+	const fn number(&self) -> Option<f64> {
+		match self {
+			Value::Integer(n) => Some(*n as f64),
+			Value::Float(n) => Some(*n),
+			_ => None,
+		}
+	}
+
+	fn float(n: f64) -> Option<Self> {
+		n.is_finite().then_some(Value::Float(n))
+	}
+
+	pub(crate) fn equals(&self, other: &Self) -> Option<bool> {
+		if let (Some(a), Some(b)) = (self.number(), other.number()) {
+			return Some(a == b);
+		}
+		match (self, other) {
+			// escape sequences can make different literals equal
+			(Value::String(_, a), Value::String(_, b)) if a.contains('\\') || b.contains('\\') => {
+				(self == other).then_some(true)
+			}
+			(Value::String(_, a), Value::String(_, b)) => Some(a == b),
+			_ => Some(self == other),
+		}
+	}
+
+	fn unary(op: &str, value: Self) -> Option<Self> {
+		match (op, value) {
+			("-" | " -", Value::Integer(n)) => Some(Value::Integer(-n)),
+			("-" | " -", Value::Float(n)) => Some(Value::Float(-n)),
+			("not ", value) => Some(Value::Boolean(!value.truthy())),
+			_ => None,
+		}
+	}
+
+	fn binary(left: Self, op: &str, right: Self) -> Option<Self> {
+		match op {
+			"+" | "-" | "*" => {
+				if let (Value::Integer(a), Value::Integer(b)) = (&left, &right) {
+					let result = match op {
+						"+" => a.checked_add(*b),
+						"-" => a.checked_sub(*b),
+						_ => a.checked_mul(*b),
+					}?;
+					return (result.abs() <= MAX_SAFE_INTEGER).then_some(Value::Integer(result));
+				}
+				let (a, b) = (left.number()?, right.number()?);
+				Value::float(match op {
+					"+" => a + b,
+					"-" => a - b,
+					_ => a * b,
+				})
+			}
+			"/" => Value::float(left.number()? / right.number()?),
+			"%" => match (left, right) {
+				(Value::Integer(a), Value::Integer(b)) if b > 0 => {
+					Some(Value::Integer(a.rem_euclid(b)))
+				}
+				_ => None,
+			},
+			"^" => Value::float(left.number()?.powf(right.number()?)),
+			".." => match (left, right) {
+				// `"\1" .. "2"` is not `"\12"` and `"\z" .. " a"` is not `"\z a"`
+				(Value::String(_, a), Value::String(_, b))
+					if a.contains('\\')
+						&& b.starts_with(|c: char| c.is_ascii_digit() || c.is_whitespace()) =>
+				{
+					None
+				}
+				(Value::String(quote, a), Value::String(other, b)) if quote == other => {
+					Some(Value::String(quote, a + &b))
+				}
+				_ => None,
+			},
+			"==" | "~=" => Some(Value::Boolean(left.equals(&right)? == (op == "=="))),
+			"<" | "<=" | ">" | ">=" => {
+				let (a, b) = (left.number()?, right.number()?);
+				Some(Value::Boolean(match op {
+					"<" => a < b,
+					"<=" => a <= b,
+					">" => a > b,
+					_ => a >= b,
+				}))
+			}
+			" and " => Some(if left.truthy() { right } else { left }),
+			" or " => Some(if left.truthy() { left } else { right }),
+			_ => None,
+		}
+	}
+}
+
+enum Node<'a> {
+	Operand(&'a ComplexToken),
+	Constant(Value, Span),
+	Unary(&'a str, &'a Span, Box<Node<'a>>),
+	Binary(Box<Node<'a>>, &'a str, &'a Span, Box<Node<'a>>),
+}
+
+impl Node<'_> {
+	// from the start of its first token to the end of its last one
+	fn span(&self) -> Span {
+		match self {
+			Node::Operand(token) => token.span(),
+			Node::Constant(_, span) => span.clone(),
+			Node::Unary(_, op, operand) => op.start..operand.span().end,
+			Node::Binary(left, .., right) => left.span().start..right.span().end,
+		}
+	}
+}
+
+// parses an expression using the precedence of Lua's operators and folds its constant parts,
+// any token it doesn't understand (like bitwise operators or indexing) makes it give up
+struct Folder<'a> {
+	tokens: &'a [ComplexToken],
+	current: usize,
+	changed: bool,
+}
+
+impl<'a> Folder<'a> {
+	fn expression(&mut self, min_precedence: usize) -> Option<Node<'a>> {
+		let mut left = self.unary()?;
+		while let Some(token) = self.tokens.get(self.current) {
+			let SYMBOL(op, op_span) = token else {
+				return None;
+			};
+			let (precedence, right_associative) = binary_precedence(op)?;
+			if precedence < min_precedence {
+				break;
+			}
+			self.current += 1;
+			let right = self.expression(if right_associative {
+				precedence
+			} else {
+				precedence + 1
+			})?;
+			left = self.fold(left, op, op_span, right);
+		}
+		Some(left)
+	}
+
+	fn unary(&mut self) -> Option<Node<'a>> {
+		let token = self.tokens.get(self.current)?;
+		self.current += 1;
+		if let SYMBOL(op, op_span) = token {
+			if matches!(op.as_str(), "-" | " -" | "not ") {
+				let operand = self.unary()?;
+				return Some(
+					match Self::value(&operand).and_then(|value| Value::unary(op, value)) {
+						Some(value) => {
+							self.changed = true;
+							Node::Constant(value, op_span.start..operand.span().end)
+						}
+						None => Node::Unary(op, op_span, Box::new(operand)),
+					},
+				);
+			} else if binary_precedence(op).is_some() {
+				return None;
+			}
+		}
+		if matches!(token, EXPR(inner, _) if inner.len() == 1 && constant(inner).is_some()) {
+			// the brackets around a literal are removed when emitting it
+			self.changed = true;
+		}
+		let operand = Node::Operand(token);
+		match self.tokens.get(self.current) {
+			Some(SYMBOL(op, op_span)) if op == "^" => {
+				self.current += 1;
+				let exponent = self.unary()?;
+				Some(self.fold(operand, op, op_span, exponent))
+			}
+			_ => Some(operand),
+		}
+	}
+
+	fn fold(
+		&mut self,
+		left: Node<'a>,
+		op: &'a str,
+		op_span: &'a Span,
+		right: Node<'a>,
+	) -> Node<'a> {
+		if let (Some(a), Some(b)) = (Self::value(&left), Self::value(&right)) {
+			if let Some(value) = Value::binary(a, op, b) {
+				self.changed = true;
+				return Node::Constant(value, left.span().start..right.span().end);
+			}
+		}
+		Node::Binary(Box::new(left), op, op_span, Box::new(right))
+	}
+
+	fn value(node: &Node) -> Option<Value> {
+		match node {
+			Node::Operand(token) => constant(&[(*token).clone()].into()),
+			Node::Constant(value, _) => Some(value.clone()),
+			_ => None,
+		}
+	}
+
+	// negative numbers are put in brackets unless they are the whole expression,
+	// so that `a - -1` doesn't become the comment `a--1`
+	fn emit(&mut self, node: Node, root: bool, expr: &mut Expression) {
+		match node {
+			Node::Operand(EXPR(inner, span)) if inner.len() == 1 && constant(inner).is_some() => {
+				match &inner[0] {
+					SYMBOL(lexeme, _) if !root && lexeme.starts_with('-') => {
+						expr.push_back(EXPR(inner.clone(), span.clone()))
+					}
+					literal => expr.push_back(literal.clone()),
+				}
+			}
+			Node::Operand(token) => expr.push_back(token.clone()),
+			Node::Constant(value, span) => {
+				let symbol = SYMBOL(value.to_symbol(), span.clone());
+				match &symbol {
+					SYMBOL(lexeme, _) if !root && lexeme.starts_with('-') => {
+						expr.push_back(EXPR([symbol].into(), span))
+					}
+					_ => expr.push_back(symbol),
+				}
+			}
+			Node::Unary(op, op_span, operand) => {
+				expr.push_back(SYMBOL(op.to_owned(), op_span.clone()));
+				self.emit(*operand, false, expr);
+			}
+			// `1..x` would be read as a malformed number
+			Node::Binary(left, "..", op_span, right)
+				if matches!(
+					*left,
+					Node::Constant(Value::Integer(_) | Value::Float(_), _)
+				) =>
+			{
+				let span = left.span();
+				let mut number = Expression::new();
+				self.emit(*left, true, &mut number);
+				expr.push_back(EXPR(number, span));
+				expr.push_back(SYMBOL(String::from(".."), op_span.clone()));
+				self.emit(*right, false, expr);
+			}
+			Node::Binary(left, op, op_span, right) => {
+				self.emit(*left, false, expr);
+				expr.push_back(SYMBOL(op.to_owned(), op_span.clone()));
+				self.emit(*right, false, expr);
+			}
+		}
+	}
+}
+
+#[derive(Default)]
+struct DeclaredNames {
+	// how many times every name is declared as a local (or a parameter, or an iterator...)
+	declared: HashMap<String, usize>,
+	// the names that are assigned to without declaring them
+	assigned: HashSet<String>,
+}
+
+impl DeclaredNames {
+	fn declare(&mut self, name: &str) {
+		*self.declared.entry(name.to_owned()).or_default() += 1;
+	}
+
+	fn count(&self, name: &str) -> usize {
+		self.declared.get(name).copied().unwrap_or_default()
+	}
+}
+
+impl Visitor for DeclaredNames {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			VARIABLE {
+				local: true, names, ..
+			} => names.iter().for_each(|name| self.declare(name)),
+			VARIABLE { names, .. } => self.assigned.extend(names.iter().cloned()),
+			FUNCTION { local, name, .. } => {
+				if let (1, Some(SYMBOL(name, _))) = (name.len(), name.front()) {
+					if *local {
+						self.declare(name)
+					} else {
+						self.assigned.insert(name.clone());
+					}
+				}
+			}
+			ALTER { names, .. } => {
+				for name in names {
+					if let (1, Some(SYMBOL(name, _))) = (name.len(), name.front()) {
+						self.assigned.insert(name.clone());
+					}
+				}
+			}
+			FOR_LOOP { iterator, .. } => self.declare(iterator),
+			FOR_FUNC_LOOP { iterators, .. } => iterators.iter().for_each(|name| self.declare(name)),
+			TRY_CATCH {
+				error: Some(error), ..
+			} => self.declare(error),
+			MATCH_BLOCK { name, .. } | WITH_BLOCK { name, .. } => self.declare(name),
+			_ => {}
+		}
+		walk_ctoken(self, ctoken)
+	}
+
+	fn visit_function_args(&mut self, args: &FunctionArgs) {
+		for (name, _) in args {
+			self.declare(name);
+		}
+		walk_function_args(self, args)
+	}
+}
+
+struct InlineFunction {
+	name: String,
+	params: Vec<String>,
+	body: Expression,
+}
+
+// checks that the body of a function can be copied where the function is called
+struct InlinableBody<'a> {
+	params: &'a [String],
+	names: &'a DeclaredNames,
+	inlinable: bool,
+}
+
+impl Visitor for InlinableBody<'_> {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			CALL(_, _) | FUNCTION { .. } | LAMBDA { .. } | CONST_BLOCK { .. } | TABLE { .. } => {
+				self.inlinable = false
+			}
+			SYMBOL(symbol, _) if symbol == "..." => self.inlinable = false,
+			IDENT { expr, .. } => match expr.front() {
+				Some(SYMBOL(name, _)) if self.params.contains(name) => {}
+				Some(SYMBOL(name, _)) if self.names.count(name) == 0 => {}
+				_ => self.inlinable = false,
+			},
+			_ => {}
+		}
+		walk_ctoken(self, ctoken)
+	}
+}
+
+// replaces the parameters in the body of an inlined function with the arguments of the call
+struct Substitution<'a> {
+	params: &'a [String],
+	args: &'a [ComplexToken],
+	substituted: bool,
+}
+
+impl Transformer for Substitution<'_> {
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		let IDENT { expr, .. } = ctoken else {
+			return walk_ctoken_mut(self, ctoken);
+		};
+		let Some(param) = (match expr.front() {
+			Some(SYMBOL(name, _)) => self.params.iter().position(|param| param == name),
+			_ => None,
+		}) else {
+			return walk_ctoken_mut(self, ctoken);
+		};
+		// the argument is not walked, it could be named like another parameter
+		match &self.args[param] {
+			arg if expr.len() == 1 => *ctoken = arg.clone(),
+			IDENT { expr: name, .. } => {
+				expr[0] = name[0].clone();
+				for ctoken in expr.iter_mut().skip(1) {
+					self.transform_ctoken(ctoken);
+				}
+			}
+			// a literal would need parentheses to be indexed or called
+			_ => self.substituted = false,
+		}
+	}
+}
+
+struct Inliner {
+	names: DeclaredNames,
+	// the functions that can be inlined in the current scope
+	functions: Vec<InlineFunction>,
+	// whether the next expression is a list of statements
+	statements: bool,
+}
+
+impl Inliner {
+	// the function declared by the statement, if it can be inlined
+	fn inlinable(&self, ctoken: &ComplexToken) -> Option<InlineFunction> {
+		let FUNCTION {
+			local: true,
+			name,
+			args,
+			code,
+			attributes,
+			..
+		} = ctoken
+		else {
+			return None;
+		};
+		let (1, Some(SYMBOL(name, _))) = (name.len(), name.front()) else {
+			return None;
+		};
+		if !attributes.is_empty()
+			|| self.names.count(name) != 1
+			|| self.names.assigned.contains(name)
+		{
+			return None;
+		}
+		let mut params = Vec::with_capacity(args.len());
+		for (param, default) in args {
+			if default.is_some() || param == "..." {
+				return None;
+			}
+			params.push(param.clone());
+		}
+		let (1, Some(RETURN_EXPR(Some(values), _))) = (code.code.len(), code.code.front()) else {
+			return None;
+		};
+		let [body] = values.as_slice() else {
+			return None;
+		};
+		let mut check = InlinableBody {
+			params: &params,
+			names: &self.names,
+			inlinable: true,
+		};
+		check.visit_expression(body);
+		check.inlinable.then(|| InlineFunction {
+			name: name.clone(),
+			params,
+			body: body.clone(),
+		})
+	}
+
+	// the expression replacing the call, if it's a call to a function that can be inlined
+	fn inline_call(&self, expr: &Expression) -> Option<Expression> {
+		let (2, Some(SYMBOL(name, _)), Some(CALL(args, _))) =
+			(expr.len(), expr.front(), expr.back())
+		else {
+			return None;
+		};
+		let function = self
+			.functions
+			.iter()
+			.rev()
+			.find(|function| &function.name == name)?;
+		if args.len() != function.params.len() {
+			return None;
+		}
+		let args = args
+			.iter()
+			.map(|arg| match (arg.len(), arg.front()) {
+				(1, Some(arg @ SYMBOL(literal, _))) if Value::parse(literal).is_some() => {
+					Some(arg.clone())
+				}
+				(1, Some(arg @ IDENT { expr, .. }))
+					if expr.len() == 1 && matches!(expr[0], SYMBOL(_, _)) =>
+				{
+					Some(arg.clone())
+				}
+				_ => None,
+			})
+			.collect::<Option<Vec<ComplexToken>>>()?;
+		let mut body = function.body.clone();
+		let mut substitution = Substitution {
+			params: &function.params,
+			args: &args,
+			substituted: true,
+		};
+		substitution.transform_expression(&mut body);
+		substitution.substituted.then_some(body)
+	}
+}
+
+impl Transformer for Inliner {
+	fn transform_expression(&mut self, expr: &mut Expression) {
+		let statements = std::mem::take(&mut self.statements);
+		let scope = self.functions.len();
+		for ctoken in expr {
+			self.transform_ctoken(ctoken);
+			if statements {
+				if let Some(function) = self.inlinable(ctoken) {
+					self.functions.push(function);
+				}
+			} else if let IDENT { expr, span } = ctoken {
+				if let Some(body) = self.inline_call(expr) {
+					*ctoken = EXPR(body, span.clone());
+				}
+			}
+		}
+		self.functions.truncate(scope);
+	}
+
+	fn transform_code_block(&mut self, block: &mut CodeBlock) {
+		self.statements = true;
+		walk_code_block_mut(self, block)
+	}
+}
+
+pub(crate) fn is_name(symbol: &str) -> bool {
+	symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+		&& symbol
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// finds every word written in the code, which the new names can't be,
+// and the words which are part of a bigger symbol (like `bit.band`), which can't be renamed
+#[derive(Default)]
+struct Words {
+	used: HashSet<String>,
+	kept: HashSet<String>,
+}
+
+impl Visitor for Words {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			TABLE {
+				metatable: Some(metatable),
+				..
+			} => self.visit_symbol(metatable),
+			FUNCTION { attributes, .. } => {
+				for (attribute, _) in attributes {
+					self.visit_symbol(attribute)
+				}
+			}
+			_ => {}
+		}
+		walk_ctoken(self, ctoken)
+	}
+
+	fn visit_symbol(&mut self, symbol: &str) {
+		if is_name(symbol) {
+			self.used.insert(symbol.to_owned());
+			return;
+		}
+		let words = symbol
+			.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+			.filter(|word| is_name(word));
+		for word in words {
+			self.used.insert(word.to_owned());
+			self.kept.insert(word.to_owned());
+		}
+	}
+}
+
+struct Mangler {
+	words: Words,
+	// the locals that can be seen, with their new names, for every scope from the outermost one
+	scopes: Vec<Vec<(String, String)>>,
+	renames: Vec<(String, String)>,
+	next: usize,
+}
+
+impl Mangler {
+	fn new_name(&mut self) -> String {
+		loop {
+			let letter = char::from(b'a' + (self.next % 26) as u8);
+			let name = match self.next / 26 {
+				0 => letter.to_string(),
+				round => format!("{letter}{round}"),
+			};
+			self.next += 1;
+			if !self.words.used.contains(&name) {
+				break name;
+			}
+		}
+	}
+
+	fn declare(&mut self, name: &mut String) {
+		if !is_name(name) {
+			return;
+		}
+		let new_name = if self.words.kept.contains(name) {
+			name.clone()
+		} else {
+			let new_name = self.new_name();
+			self.renames.push((name.clone(), new_name.clone()));
+			new_name
+		};
+		let scope = self
+			.scopes
+			.last_mut()
+			.expect("there should always be a scope");
+		scope.push((std::mem::replace(name, new_name.clone()), new_name));
+	}
+
+	fn in_scope(&mut self, f: impl FnOnce(&mut Self)) {
+		self.scopes.push(Vec::new());
+		f(self);
+		self.scopes.pop();
+	}
+
+	fn transform_function(&mut self, args: &mut FunctionArgs, code: &mut CodeBlock, method: bool) {
+		self.in_scope(|mangler| {
+			// the implicit parameter of methods, which is kept
+			if method {
+				mangler.declare(&mut String::from("self"));
+			}
+			for (arg, _) in args.iter_mut() {
+				mangler.declare(arg);
+			}
+			// the default values are set inside the function
+			for (_, default) in args.iter_mut() {
+				if let Some((default, _)) = default {
+					mangler.transform_expression(default);
+				}
+			}
+			mangler.transform_code_block(code);
+		})
+	}
+}
+
+impl Transformer for Mangler {
+	fn transform_expression(&mut self, expr: &mut Expression) {
+		let mut field = false;
+		for ctoken in expr {
+			match ctoken {
+				// the name of a field after `.` or `:`
+				SYMBOL(_, _) if field => field = false,
+				SYMBOL(symbol, _) => {
+					field = symbol == "." || symbol == ":";
+					self.transform_symbol(symbol)
+				}
+				_ => self.transform_ctoken(ctoken),
+			}
+		}
+	}
+
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		match ctoken {
+			VARIABLE {
+				local: true,
+				names,
+				values,
+				..
+			} => {
+				for value in values {
+					self.transform_expression(value);
+				}
+				for name in names {
+					self.declare(name);
+				}
+			}
+			VARIABLE { values, .. } => {
+				for value in values {
+					self.transform_expression(value);
+				}
+			}
+			FUNCTION {
+				local,
+				name,
+				args,
+				code,
+				attributes,
+				..
+			} => {
+				for (attribute, attribute_args) in attributes {
+					self.transform_symbol(attribute);
+					for arg in attribute_args {
+						self.transform_expression(arg);
+					}
+				}
+				match (*local, name.len(), name.front_mut()) {
+					(true, 1, Some(SYMBOL(name, _))) => self.declare(name),
+					_ => self.transform_expression(name),
+				}
+				let method = name
+					.iter()
+					.any(|ctoken| matches!(ctoken, SYMBOL(symbol, _) if symbol == ":"));
+				self.transform_function(args, code, method)
+			}
+			LAMBDA { args, code, .. } => self.transform_function(args, code, false),
+			TABLE {
+				values,
+				metas,
+				metatable,
+				..
+			} => {
+				for (key, value, _) in values {
+					match key {
+						// a named key
+						Some(key)
+							if matches!(key.front(), Some(SYMBOL(_, _))) && key.len() == 1 => {}
+						Some(key) => self.transform_expression(key),
+						None => {}
+					}
+					self.transform_expression(value);
+				}
+				for (_, value, _) in metas {
+					self.transform_expression(value);
+				}
+				if let Some(metatable) = metatable {
+					self.transform_symbol(metatable);
+				}
+			}
+			MATCH_BLOCK {
+				name,
+				value,
+				branches,
+				..
+			} => {
+				self.transform_expression(value);
+				self.declare(name);
+				for (conditions, internal_expr, extra_if, code) in branches {
+					self.in_scope(|mangler| {
+						for condition in conditions {
+							mangler.transform_expression(condition);
+						}
+						mangler.transform_expression(internal_expr);
+						if let Some(extra_if) = extra_if {
+							mangler.transform_expression(extra_if);
+						}
+						mangler.transform_code_block(code);
+					})
+				}
+			}
+			// the condition can see the locals of the loop
+			LOOP_UNTIL {
+				condition, code, ..
+			} => self.in_scope(|mangler| {
+				mangler.transform_expression(&mut code.code);
+				mangler.transform_expression(condition);
+			}),
+			FOR_LOOP {
+				iterator,
+				start,
+				end,
+				alter,
+				code,
+				..
+			} => {
+				self.transform_expression(start);
+				self.transform_expression(end);
+				self.transform_expression(alter);
+				self.in_scope(|mangler| {
+					mangler.declare(iterator);
+					mangler.transform_code_block(code);
+				})
+			}
+			FOR_FUNC_LOOP {
+				iterators,
+				expr,
+				code,
+				..
+			} => {
+				self.transform_expression(expr);
+				self.in_scope(|mangler| {
+					for iterator in iterators {
+						mangler.declare(iterator);
+					}
+					mangler.transform_code_block(code);
+				})
+			}
+			TRY_CATCH {
+				totry,
+				catch,
+				error,
+				finally,
+				..
+			} => {
+				self.transform_code_block(totry);
+				if let Some(catch) = catch {
+					self.in_scope(|mangler| {
+						if let Some(error) = error {
+							mangler.declare(error);
+						}
+						mangler.transform_code_block(catch);
+					})
+				}
+				if let Some(finally) = finally {
+					self.transform_code_block(finally);
+				}
+			}
+			WITH_BLOCK {
+				name, value, code, ..
+			} => {
+				self.transform_expression(value);
+				self.in_scope(|mangler| {
+					mangler.declare(name);
+					mangler.transform_code_block(code);
+				})
+			}
+			_ => walk_ctoken_mut(self, ctoken),
+		}
+	}
+
+	fn transform_code_block(&mut self, block: &mut CodeBlock) {
+		self.in_scope(|mangler| walk_code_block_mut(mangler, block))
+	}
+
+	fn transform_symbol(&mut self, symbol: &mut String) {
+		let local = self
+			.scopes
+			.iter()
+			.rev()
+			.flat_map(|scope| scope.iter().rev())
+			.find(|(name, _)| name == symbol);
+		if let Some((_, new_name)) = local {
+			*symbol = new_name.clone();
+		}
+	}
+}
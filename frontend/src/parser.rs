@@ -0,0 +1,6962 @@
+//! The parser is the third step of the compilation process. It takes the tokens from the scanner and
+//! converts them into an AST (Abstract Syntax Tree).
+//!
+//! The parser is a recursive descent parser, which means that it uses a recursive function to parse
+//! the tokens. This function is called [`parse_tokens`] and is the entry point for the parser.
+
+#![allow(non_camel_case_types)]
+
+use self::ComplexToken::*;
+#[cfg(feature = "json")]
+use crate::check;
+use crate::env::{
+	BitwiseMode, ContinueMode, EnumMode, Language, LuaVersion, NamedArgsMode, Options,
+};
+use crate::format_clue;
+use crate::lint::{unused_locals, LocalKind};
+use crate::optimize::{evaluate, Value};
+use crate::pattern::{check_pattern_literal, PATTERN_FUNCTIONS};
+use crate::preprocessor::Remapping;
+use crate::scanner::{future_keyword_version, BorrowedToken, TokenType::*};
+use crate::scanner::{Token, TokenPosition, TokenType};
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::iter::once;
+use std::ops::Range;
+use std::rc::Rc;
+use std::vec;
+use std::{cmp, collections::VecDeque};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod precedence;
+pub mod visitor;
+
+use precedence::{binary_precedence, is_ordering, Node, UNARY_PRECEDENCE};
+use visitor::{
+	walk_ctoken, walk_ctoken_mut, walk_expression, walk_expression_mut, Transformer, Visitor,
+};
+
+macro_rules! vec_deque {
+	($($elem:expr),*) => {
+		VecDeque::from([$(($elem)),*])
+	};
+}
+
+/// A list of [`ComplexToken`]s, which is the AST.
+pub type Expression = VecDeque<ComplexToken>;
+
+/// The range of the source code a [`ComplexToken`] was parsed from,
+/// from where its first token starts to where its last token ends.
+pub type Span = Range<TokenPosition>;
+
+/// Function arguments, a list of identifiers with optional default values.
+/// used in function signatures.
+pub type FunctionArgs = Vec<(String, Option<(Expression, usize)>)>;
+
+/// The attributes of a function declaration, like `@memoize` or `@deprecated("use bar")`,
+/// each with its name and its arguments, in the order they were written.
+pub type FunctionAttributes = Vec<(String, Vec<Expression>)>;
+
+//pub type LocalsList = Option<AHashMap<String, LuaType>>;
+//pub type ArgsAndTypes = (FunctionArgs, Option<Vec<(String, LuaType)>>);
+
+/// An optional end token, which is used to check if the end token is present.
+/// It is a tuple of the token type and the token lexeme.
+type OptionalEnd = Option<(TokenType, &'static str)>;
+
+/// A tuple representing a match case, containing the things you can match, it's internal code, an optional condition and a code block.
+/// In the example
+/// ```clue
+/// match x {
+///   1 if z == 0 => {foo()},
+/// }
+/// ```
+/// the first element of the tuple would be `1`, the second element would be `z == 0`
+/// and the third element would be `{foo()}`.
+type MatchCase = (Vec<Expression>, Expression, Option<Expression>, CodeBlock);
+
+/// The default values of a destructured table, with the index of the name they belong to,
+/// the default value and its internal code.
+type DestructuringDefaults = Vec<(usize, Expression, Expression)>;
+
+/// A destructured table, containing the names of the variables, the keys they are read from,
+/// the internal variables used to read nested tables and the default values.
+type DestructuredTable = (Vec<String>, Vec<String>, Vec<String>, DestructuringDefaults);
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An enum representing all the possible complex tokens that can be parsed
+/// such as functions, if statements, variables, tables etc.
+pub enum ComplexToken {
+	/// A variable declaration.
+	VARIABLE {
+		/// Whether the variable(s) is/are local or not.
+		local: bool,
+
+		/// Whether the variable(s) is/are const or not.
+		r#const: bool,
+
+		/// The names of the variable(s).
+		names: Vec<String>,
+
+		/// The values of the variable(s).
+		values: Vec<Expression>,
+
+		/// The span of the variable declaration in the source code.
+		span: Span,
+	},
+
+	/// An assignment to a variable or a list of variables.
+	ALTER {
+		/// The kind of assignment (e.g. `+=`, `-=`, `=`)
+		kind: TokenType,
+
+		/// The names of the variable(s).
+		names: VecDeque<Expression>,
+
+		/// The values of the variable(s).
+		values: Vec<Expression>,
+
+		/// The span of the assignment in the source code.
+		span: Span,
+	},
+
+	/// A table.
+	TABLE {
+		/// the table's keys and values values.
+		values: Vec<(Option<Expression>, Expression, Span)>,
+
+		/// the table's metamethods.
+		metas: Vec<(String, Expression, Span)>,
+
+		/// the table's metatable.
+		metatable: Option<String>,
+
+		/// The span of the table in the source code.
+		span: Span,
+	},
+
+	/// A function declaration.
+	FUNCTION {
+		/// Whether the function is local or not.
+		local: bool,
+
+		/// The name of the function.
+		name: Expression,
+
+		/// The arguments of the function.
+		args: FunctionArgs,
+
+		/// The code block of the function.
+		code: CodeBlock,
+
+		/// The attributes the function is wrapped with.
+		attributes: FunctionAttributes,
+
+		/// The span of the function declaration in the source code.
+		span: Span,
+	},
+
+	/// A lambda function.
+	LAMBDA {
+		/// The arguments of the function.
+		args: FunctionArgs,
+
+		/// The code block of the function.
+		code: CodeBlock,
+
+		/// The span of the function in the source code.
+		span: Span,
+	},
+
+	/// An if statement.
+	IF_STATEMENT {
+		/// The condition of the if statement.
+		condition: Expression,
+
+		/// The code block of the if statement.
+		code: CodeBlock,
+
+		/// The next elseif/else statement.
+		next: Option<Box<ComplexToken>>,
+
+		/// The span of the if statement in the source code, from its keyword to the end of its block,
+		/// without the following elseif/else statements.
+		span: Span,
+	},
+
+	/// A match statement.
+	MATCH_BLOCK {
+		/// The name of the internal variable used to store the matched expression.
+		name: String,
+
+		/// The expression to match on.
+		value: Expression,
+
+		/// The list of match cases.
+		branches: Vec<MatchCase>,
+
+		/// The span of the match statement in the source code.
+		span: Span,
+	},
+
+	/// A while loop.
+	WHILE_LOOP {
+		/// The condition of the while loop.
+		condition: Expression,
+
+		/// The code block of the while loop.
+		code: CodeBlock,
+
+		/// The label of the loop, used by `break` and `continue` to jump out of nested loops.
+		label: Option<String>,
+
+		/// The span of the while loop in the source code.
+		span: Span,
+	},
+
+	/// An until loop.
+	LOOP_UNTIL {
+		/// The condition of the loop.
+		condition: Expression,
+
+		/// The code block of the loop.
+		code: CodeBlock,
+
+		/// The label of the loop, used by `break` and `continue` to jump out of nested loops.
+		label: Option<String>,
+
+		/// The span of the loop in the source code.
+		span: Span,
+	},
+
+	/// A for loop over a range of number e.g. `for i = 0, 10, 1 {...}`.
+	FOR_LOOP {
+		/// The iterator variable of the for loop.
+		iterator: String,
+
+		/// The starting value of the for loop.
+		start: Expression,
+
+		/// The last value of the for loop.
+		end: Expression,
+
+		/// The amount to alter the iterator every iteration.
+		alter: Expression,
+
+		/// The code block of the for loop.
+		code: CodeBlock,
+
+		/// The label of the loop, used by `break` and `continue` to jump out of nested loops.
+		label: Option<String>,
+
+		/// The span of the for loop in the source code.
+		span: Span,
+	},
+
+	/// A for loop over a some iterator which can be either a for..in loop, a for..of loop or a for..with loop.
+	FOR_FUNC_LOOP {
+		/// The iterator of the for loop.
+		iterators: Vec<String>,
+
+		/// The expression of the for loop
+		/// for..in loops: for k,v in pairs(t) do end
+		/// for..of loops: for k,v in ipairs(t) do end
+		/// for..with loops: for k,v in custom_iter(t) do end
+		expr: Expression,
+
+		/// The code block of the for loop.
+		code: CodeBlock,
+
+		/// The label of the loop, used by `break` and `continue` to jump out of nested loops.
+		label: Option<String>,
+
+		/// The span of the for loop in the source code.
+		span: Span,
+	},
+
+	/// A try catch block.
+	TRY_CATCH {
+		/// The code block of the try block.
+		totry: CodeBlock,
+
+		/// An optional code block of the catch block.
+		catch: Option<CodeBlock>,
+
+		/// The name of the error variable in the catch block.
+		error: Option<String>,
+
+		/// An optional code block which always runs after the try and catch blocks,
+		/// even if they throw an error.
+		finally: Option<CodeBlock>,
+
+		/// The span of the try catch block in the source code.
+		span: Span,
+	},
+
+	/// A `with` block which closes its resource once the block ends.
+	/// Below Lua 5.4 its code runs inside `pcall`, so it can't `return`, `break`, `continue`, `goto` or use `...`.
+	WITH_BLOCK {
+		/// The name of the variable holding the resource.
+		name: String,
+
+		/// The expression opening the resource.
+		value: Expression,
+
+		/// The code block using the resource.
+		code: CodeBlock,
+
+		/// The span of the with block in the source code.
+		span: Span,
+	},
+
+	/// An identifier.
+	IDENT {
+		/// The expression of the identifier.
+		expr: Expression,
+
+		/// The span of the identifier in the source code.
+		span: Span,
+	},
+
+	/// Any symbol, with its span in the source code.
+	SYMBOL(String, Span),
+
+	/// A function call, with the span of its arguments in the source code.
+	CALL(Vec<Expression>, Span),
+
+	/// An expression, with its span in the source code.
+	EXPR(Expression, Span),
+
+	/// A do block, with its span in the source code.
+	DO_BLOCK(CodeBlock, Span),
+
+	/// A `const { ... }` block, whose code returns its value.
+	/// `ConstBlocks` in `clue_core` runs it while compiling when [`Options::env_const_eval`] is enabled,
+	/// otherwise it's compiled to a function called right away, which runs when the code is loaded.
+	CONST_BLOCK {
+		/// The code of the block, which ends by returning its value.
+		code: CodeBlock,
+
+		/// The span of the block in the source code.
+		span: Span,
+	},
+
+	/// A return statement, with its span in the source code.
+	RETURN_EXPR(Option<Vec<Expression>>, Span),
+
+	/// A continue keyword, with the label of the loop to continue if any and its span in the source code.
+	CONTINUE_LOOP(Option<String>, Span),
+
+	/// A break keyword, with the label of the loop to break if any and its span in the source code.
+	BREAK_LOOP(Option<String>, Span),
+
+	/// A goto statement, with the label it jumps to and its span in the source code.
+	GOTO_LABEL(String, Span),
+
+	/// A label (`::name::`) that goto statements can jump to, with its span in the source code.
+	LABEL(String, Span),
+
+	/// An opaque statement using the syntax of a future version of Clue,
+	/// only produced when [`Options::env_forward_compat`] is enabled.
+	UNSUPPORTED {
+		/// The keyword the statement starts with.
+		keyword: String,
+
+		/// The version of Clue the statement needs.
+		version: String,
+
+		/// The span of the statement in the source code.
+		span: Span,
+	},
+
+	/// A placeholder for a statement that could not be parsed,
+	/// only produced by [`parse_tokens_recovering`].
+	ERROR {
+		/// The error message.
+		message: String,
+
+		/// The line number where the error was found.
+		line: usize,
+
+		/// The column number where the error was found.
+		column: usize,
+	},
+}
+
+impl ComplexToken {
+	/// Returns the span of the [`ComplexToken`] in the source code.
+	/// Tokens made by the parser for code it generates have the span of the code they were made from,
+	/// while an [`ERROR`] has an empty span where the error was found.
+	pub fn span(&self) -> Span {
+		match self {
+			VARIABLE { span, .. }
+			| ALTER { span, .. }
+			| TABLE { span, .. }
+			| FUNCTION { span, .. }
+			| LAMBDA { span, .. }
+			| IF_STATEMENT { span, .. }
+			| MATCH_BLOCK { span, .. }
+			| WHILE_LOOP { span, .. }
+			| LOOP_UNTIL { span, .. }
+			| FOR_LOOP { span, .. }
+			| FOR_FUNC_LOOP { span, .. }
+			| TRY_CATCH { span, .. }
+			| WITH_BLOCK { span, .. }
+			| IDENT { span, .. }
+			| SYMBOL(_, span)
+			| CALL(_, span)
+			| EXPR(_, span)
+			| DO_BLOCK(_, span)
+			| CONST_BLOCK { span, .. }
+			| RETURN_EXPR(_, span)
+			| CONTINUE_LOOP(_, span)
+			| BREAK_LOOP(_, span)
+			| GOTO_LABEL(_, span)
+			| LABEL(_, span)
+			| UNSUPPORTED { span, .. } => span.clone(),
+			ERROR { line, column, .. } => {
+				let position = TokenPosition {
+					line: *line,
+					column: *column,
+				};
+				position..position
+			}
+		}
+	}
+}
+
+// whether the expression is just a string literal, like the `"%d"` of `("%d")::rep(2)`
+fn is_string_literal(expr: &Expression) -> bool {
+	matches!(
+		(expr.len(), expr.front()),
+		(1, Some(SYMBOL(lexeme, _))) if lexeme.starts_with(['"', '\'', '['])
+	)
+}
+
+// the span from the start of the first token of the expression to the end of its last one
+pub(crate) fn expression_span(expr: &Expression) -> Span {
+	match (expr.front(), expr.back()) {
+		(Some(first), Some(last)) => first.span().start..last.span().end,
+		_ => Span::default(),
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A code block.
+pub struct CodeBlock {
+	/// The start line of the code block.
+	pub start: usize,
+
+	/// The code of the code block.
+	pub code: Expression,
+
+	/// The end line of the code block.
+	pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An error found by [`parse_tokens_recovering`].
+pub struct ParseError {
+	/// The error message.
+	pub message: String,
+
+	/// The line number where the error was found.
+	pub line: usize,
+
+	/// The column number where the error was found.
+	pub column: usize,
+}
+
+/*
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LuaType {
+	ANY,
+	NIL,
+	NUMBER,
+}
+*/
+
+// the structs, the functions and the inlined enums declared before the code being parsed that it can see,
+// with the names of the methods of the structs, of the parameters of the functions
+// and of the members of the enums with their values
+#[derive(Clone, Default)]
+struct Declarations {
+	structs: Vec<(String, Vec<String>)>,
+	functions: Vec<(String, Vec<String>)>,
+	enums: Vec<(String, Vec<(String, String)>)>,
+}
+
+// the log written by `parse_tokens_traced`, one event per line:
+// `<depth> <kind> <line>:<column> <detail>`, where the kind is `>` when a rule starts, `<` when it
+// ends, `x` when it fails, `.` when a token is consumed and `!` when the parser goes back
+#[derive(Default)]
+struct ParseTrace {
+	log: String,
+	depth: usize,
+}
+
+struct ParserInfo<'a> {
+	options: &'a Options,
+	current: usize,
+	size: usize,
+	filename: &'a String,
+	remapping: &'a Remapping,
+	expr: Expression,
+	tokens: Vec<Token>,
+	internal_var_id: u8,
+	internal_stack: Vec<Cell<Expression>>,
+	statics: Expression,
+	recover: bool,
+	errors: Vec<ParseError>,
+	error_location: (usize, usize),
+	declarations: Declarations,
+	// the mode chosen by the attribute before the enum being parsed
+	enum_mode: Option<EnumMode>,
+	trace: Option<Rc<RefCell<ParseTrace>>>,
+	//locals: LocalsList,
+}
+
+impl<'a> ParserInfo<'a> {
+	fn new(
+		tokens: Vec<Token>, /* , locals: LocalsList */
+		filename: &'a String,
+		remapping: &'a Remapping,
+		options: &'a Options,
+	) -> ParserInfo<'a> {
+		ParserInfo {
+			current: 0,
+			size: tokens.len() - 1,
+			filename,
+			remapping,
+			expr: Expression::with_capacity(tokens.len()),
+			tokens,
+			internal_var_id: 0,
+			internal_stack: Vec::new(),
+			statics: Expression::new(),
+			options,
+			recover: false,
+			errors: Vec::new(),
+			error_location: (0, 0),
+			declarations: Declarations::default(),
+			enum_mode: None,
+			trace: None,
+			// locals,
+		}
+	}
+
+	fn warning(&self, msg: impl Into<String>, line: usize, column: usize) {
+		if !self.recover {
+			self.remapping
+				.print_location("Warning", self.filename, line, column);
+			eprintln!("Warning: \"{}\"", msg.into());
+		}
+	}
+
+	fn error(&mut self, msg: impl Into<String>, line: usize, column: usize) -> String {
+		self.error_location = (line, column);
+		if !self.recover {
+			self.remapping
+				.print_location("Error", self.filename, line, column);
+		}
+		msg.into()
+	}
+
+	fn expected(&mut self, expected: &str, got: &str, line: usize, column: usize) -> String {
+		self.error(
+			format_clue!("Expected '", expected, "', got '", got, "'"),
+			line,
+			column,
+		)
+	}
+
+	fn expected_before(
+		&mut self,
+		expected: &str,
+		before: &str,
+		line: usize,
+		column: usize,
+	) -> String {
+		self.error(
+			format_clue!("Expected '", expected, "' before '", before, "'"),
+			line,
+			column,
+		)
+	}
+
+	fn unexpected(&mut self, str: &str, line: usize, column: usize) -> String {
+		self.error(format_clue!("Unexpected token '", str, "'"), line, column)
+	}
+
+	fn trace(&self, kind: char, (line, column): (usize, usize), detail: impl FnOnce() -> String) {
+		if let Some(trace) = &self.trace {
+			let mut trace = trace.borrow_mut();
+			let depth = trace.depth;
+			writeln!(trace.log, "{depth} {kind} {line}:{column} {}", detail()).unwrap();
+		}
+	}
+
+	fn trace_token(&self, t: &BorrowedToken) {
+		self.trace('.', (t.line(), t.column()), || {
+			format!("{:?} {:?}", t.kind(), t.lexeme())
+		});
+	}
+
+	// runs `f`, recording in the trace when the rule starts (at the token `start`) and ends or fails
+	fn traced<T>(
+		&mut self,
+		rule: &str,
+		start: &BorrowedToken,
+		f: impl FnOnce(&mut Self) -> Result<T, String>,
+	) -> Result<T, String> {
+		let Some(trace) = self.trace.clone() else {
+			return f(self);
+		};
+		self.trace('>', (start.line(), start.column()), || rule.to_owned());
+		trace.borrow_mut().depth += 1;
+		let result = f(self);
+		trace.borrow_mut().depth -= 1;
+		let t = self.at(self.current.saturating_sub(1));
+		let position = (t.line(), t.column());
+		match &result {
+			Ok(_) => self.trace('<', position, || rule.to_owned()),
+			Err(e) => self.trace('x', position, || format_clue!(rule, ": ", e)),
+		}
+		result
+	}
+
+	const fn ended(&self) -> bool {
+		self.current >= self.size
+	}
+
+	fn at(&self, pos: usize) -> BorrowedToken {
+		BorrowedToken::new(&self.tokens[cmp::min(pos, self.size)])
+	}
+
+	fn advance(&mut self) -> BorrowedToken {
+		self.current += 1;
+		let t = self.look_back(0);
+		self.trace_token(&t);
+		t
+	}
+
+	fn peek(&self, pos: usize) -> BorrowedToken {
+		let pos: usize = self.current + pos;
+		self.at(pos)
+	}
+
+	fn look_back(&self, pos: usize) -> BorrowedToken {
+		let pos: usize = self.current - pos - 1;
+		self.at(pos)
+	}
+
+	fn compare(&self, expected: TokenType) -> bool {
+		if self.ended() {
+			return false;
+		}
+		if self.peek(0).kind() != expected {
+			return false;
+		}
+		true
+	}
+
+	fn advance_if(&mut self, expected: TokenType) -> bool {
+		if self.ended() {
+			return false;
+		}
+		if self.peek(0).kind() != expected {
+			return false;
+		}
+		self.current += 1;
+		self.trace_token(&self.look_back(0));
+		true
+	}
+
+	fn assert_advance(
+		&mut self,
+		expected: TokenType,
+		error: &str,
+	) -> Result<BorrowedToken, String> {
+		let t = self.advance();
+		if t.kind() != expected {
+			return Err(self.expected(error, &t.lexeme(), t.line(), t.column()));
+		}
+		Ok(t)
+	}
+
+	fn assert_compare(&mut self, expected: TokenType, error: &str) -> Result<(), String> {
+		if !self.compare(expected) {
+			let t = self.peek(0);
+			return Err(self.expected(error, &t.lexeme(), t.line(), t.column()));
+		}
+		Ok(())
+	}
+
+	fn assert_end<T>(
+		&mut self,
+		tocheck: &BorrowedToken,
+		end: OptionalEnd,
+		iftrue: T,
+	) -> Result<T, String> {
+		if let Some((kind, lexeme)) = end {
+			if tocheck.kind() != kind {
+				return Err(self.expected(
+					lexeme,
+					&tocheck.lexeme(),
+					tocheck.line(),
+					tocheck.column(),
+				));
+			}
+		}
+		Ok(iftrue)
+	}
+
+	fn assert(&mut self, expected: TokenType, error: &str) -> Result<(), String> {
+		if !self.advance_if(expected) {
+			let t = self.peek(0);
+			return Err(self.expected(error, &t.lexeme(), t.line(), t.column()));
+		}
+		Ok(())
+	}
+	/*
+		fn assert_variable(&mut self, mut variable: Iter<ComplexToken>) -> Result<LuaType, String> {
+			let mut scope: &LocalsList = &self.locals;
+			let mut luatype = LuaType::NIL;
+			while let Some(locals) = scope {
+				if let Some(t) = variable.next() {
+					// TODO
+				} else {
+					break;
+				}
+			}
+			Ok(luatype)
+		}
+	*/
+
+	// the span from `start` to the end of the last token that was read
+	fn span_from(&self, start: TokenPosition) -> Span {
+		start..self.look_back(0).end_position()
+	}
+
+	// the EmmyLua annotations put before a declaration with the annotations option, one per line,
+	// which are also how the compiler knows the types of the declarations when compiling to Teal
+	fn annotations(&self, lines: impl IntoIterator<Item = String>, span: &Span) -> Expression {
+		if !self.options.env_annotations && self.options.env_language != Language::Teal {
+			return Expression::new();
+		}
+		lines
+			.into_iter()
+			.map(|line| SYMBOL(format_clue!("---@", line), span.clone()))
+			.collect()
+	}
+
+	fn get_next_internal_var(&mut self) -> String {
+		let var = format_clue!("_internal", self.internal_var_id.to_string());
+		self.internal_var_id += 1;
+		var
+	}
+
+	// `callee` is the function being called, used to find its parameters for named arguments
+	fn build_call(&mut self, callee: &Expression) -> Result<Vec<Expression>, String> {
+		if self.advance_if(ROUND_BRACKET_CLOSED) {
+			return Ok(Vec::new());
+		}
+		let mut args = Vec::new();
+		let mut spreads = Vec::new();
+		let mut named: Vec<(BorrowedToken, Expression, Span)> = Vec::new();
+		loop {
+			let start = self.peek(0).position();
+			if self.peek(0).kind() == IDENTIFIER && self.peek(1).kind() == DEFINE {
+				let name = self.advance();
+				self.current += 1;
+				if named
+					.iter()
+					.any(|(other, ..)| other.lexeme() == name.lexeme())
+				{
+					return Err(self.error(
+						format!("The argument '{}' is given twice", name.lexeme()),
+						name.line(),
+						name.column(),
+					));
+				}
+				let arg = self.build_expression(None)?;
+				named.push((name, arg, start..self.look_back(1).end_position()));
+			} else {
+				if let Some((name, ..)) = named.last() {
+					return Err(self.error(
+						format!(
+							"Positional arguments must come before named arguments like '{}'",
+							name.lexeme()
+						),
+						name.line(),
+						name.column(),
+					));
+				}
+				if self.advance_spread() {
+					spreads.push(args.len());
+				}
+				let arg = self.build_expression(None)?;
+				args.push((None, arg, start..self.look_back(1).end_position()));
+			}
+			let t = self.look_back(0);
+			if t.kind() != COMMA {
+				self.assert_end(&t, Some((ROUND_BRACKET_CLOSED, ")")), ())?;
+				break;
+			}
+		}
+		if !named.is_empty() {
+			if !spreads.is_empty() {
+				let (name, ..) = &named[0];
+				return Err(self.error(
+					"Named arguments cannot be given together with spread arguments",
+					name.line(),
+					name.column(),
+				));
+			}
+			return self.add_named_args(callee, args, named);
+		}
+		let unpack = String::from(self.unpack_function());
+		Ok(match spreads[..] {
+			[] => args.into_iter().map(|(_, arg, _)| arg).collect(),
+			[spread] if spread == args.len() - 1 => {
+				let (_, spread, span) = args.pop().unwrap();
+				let mut args: Vec<Expression> = args.into_iter().map(|(_, arg, _)| arg).collect();
+				args.push(vec_deque![
+					SYMBOL(unpack, span.clone()),
+					CALL(vec![spread], span)
+				]);
+				args
+			}
+			_ => {
+				// the arguments from the first spread one are all in the table
+				let span = args[spreads[0]].2.start..args[args.len() - 1].2.end;
+				let (table, len) = self.build_spread_table(args, &spreads, Vec::new(), None, false);
+				let arg = vec_deque![
+					SYMBOL(unpack, span.clone()),
+					CALL(
+						vec![
+							vec_deque![SYMBOL(table, span.clone())],
+							vec_deque![SYMBOL(String::from("1"), span.clone())],
+							vec_deque![SYMBOL(len, span.clone())]
+						],
+						span
+					)
+				];
+				vec![arg]
+			}
+		})
+	}
+
+	// passes the named arguments to the function as chosen by `Options::env_named_args`
+	fn add_named_args(
+		&mut self,
+		callee: &Expression,
+		args: Vec<(Option<Expression>, Expression, Span)>,
+		named: Vec<(BorrowedToken, Expression, Span)>,
+	) -> Result<Vec<Expression>, String> {
+		if self.options.env_named_args == NamedArgsMode::Table {
+			let mut args: Vec<Expression> = args.into_iter().map(|(_, arg, _)| arg).collect();
+			let span = named[0].2.start..named[named.len() - 1].2.end;
+			args.push(vec_deque![TABLE {
+				values: named
+					.into_iter()
+					.map(|(name, arg, span)| {
+						(
+							Some(vec_deque![SYMBOL(name.lexeme(), name.span())]),
+							arg,
+							span,
+						)
+					})
+					.collect(),
+				metas: Vec::new(),
+				metatable: None,
+				span,
+			}]);
+
+			return Ok(args);
+		}
+		let function = function_name(callee);
+		let parameters = function.as_ref().and_then(|function| {
+			self.declarations
+				.functions
+				.iter()
+				.rev()
+				.find(|(name, _)| name == function)
+				.map(|(_, parameters)| parameters.clone())
+		});
+		let (Some(function), Some(parameters)) = (function, parameters) else {
+			let (name, ..) = &named[0];
+			return Err(self.error(
+				"Named arguments can only be given to functions declared before the call in the same file",
+				name.line(),
+				name.column(),
+			));
+		};
+		let positional = args.len();
+		let mut indices = Vec::with_capacity(named.len());
+		for (name, ..) in &named {
+			let Some(i) = parameters
+				.iter()
+				.position(|parameter| *parameter == name.lexeme())
+			else {
+				return Err(self.error(
+					format!("'{function}' has no parameter named '{}'", name.lexeme()),
+					name.line(),
+					name.column(),
+				));
+			};
+			if i < positional {
+				return Err(self.error(
+					format!(
+						"The argument '{}' was already given by position",
+						name.lexeme()
+					),
+					name.line(),
+					name.column(),
+				));
+			}
+			indices.push(i);
+		}
+		let mut args: Vec<(Expression, Span)> = args
+			.into_iter()
+			.chain(named.into_iter().map(|(_, arg, span)| (None, arg, span)))
+			.map(|(_, arg, span)| (arg, span))
+			.collect();
+		// moving the named arguments to their parameters would change the order they are evaluated in,
+		// so unless they are already in that order the arguments which are not pure are evaluated first
+		if indices.windows(2).any(|pair| pair[0] > pair[1]) {
+			for (arg, span) in &mut args {
+				if !Self::is_pure(arg) {
+					let name = self.get_next_internal_var();
+					let value =
+						std::mem::replace(arg, vec_deque![SYMBOL(name.clone(), span.clone())]);
+					self.get_prev_expr().push_back(VARIABLE {
+						local: true,
+						r#const: false,
+						names: vec![name],
+						values: vec![value],
+						span: span.clone(),
+					});
+				}
+			}
+		}
+		let named = args.split_off(positional);
+		let mut args: Vec<Expression> = args.into_iter().map(|(arg, _)| arg).collect();
+		for (i, (arg, span)) in indices.into_iter().zip(named) {
+			if i >= args.len() {
+				args.resize_with(i + 1, || {
+					vec_deque![SYMBOL(String::from("nil"), span.clone())]
+				});
+			}
+			args[i] = arg;
+		}
+		Ok(args)
+	}
+
+	// skips the `...` of a spread (`...t`), which unlike varargs is followed by a value
+	fn advance_spread(&mut self) -> bool {
+		if self.peek(0).kind() == THREEDOTS
+			&& matches!(
+				self.peek(1).kind(),
+				IDENTIFIER | ROUND_BRACKET_OPEN | CURLY_BRACKET_OPEN
+			) {
+			self.current += 1;
+			true
+		} else {
+			false
+		}
+	}
+
+	// `a < b < c` becomes `a < b and b < c`, where unless it's just a name or a literal `b` is
+	// evaluated only once by a function assigning it to a variable declared before the statement,
+	// so that it's still only evaluated when the comparisons before it are.
+	// `middle` is `b`, which is replaced by the function, the operand returned goes before `< c`
+	fn build_chained_comparison(&mut self, t: &BorrowedToken, middle: &mut Node) -> Node {
+		let bit = self.options.env_jitbit.as_deref();
+		let middle_node = std::mem::replace(middle, Node::Operand(Expression::new()));
+		let value = middle_node.into_expression(bit);
+		if Self::is_name(&value) {
+			*middle = Node::Operand(value.clone());
+			return Node::Operand(value);
+		}
+		let value_span = expression_span(&value);
+		let name = self.get_next_internal_var();
+		let span = self.span_from(t.position());
+		let line = t.line();
+		self.get_prev_expr().push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: Vec::new(),
+			span: span.clone(),
+		});
+		// the varargs of the function around the statement are passed to the one evaluating `b`
+		let mut varargs = BranchExits {
+			loops: Vec::new(),
+			found: false,
+			varargs: false,
+		};
+		varargs.visit_expression(&value);
+		let code = CodeBlock {
+			start: line,
+			code: vec_deque![
+				ALTER {
+					kind: DEFINE,
+					names: vec_deque![vec_deque![SYMBOL(name.clone(), value_span.clone())]],
+					values: vec![value],
+					span,
+				},
+				RETURN_EXPR(
+					Some(vec![vec_deque![SYMBOL(name.clone(), value_span.clone())]]),
+					value_span.clone()
+				)
+			],
+			end: line,
+		};
+		*middle = Node::Operand(Self::call_in_place(
+			code,
+			varargs.varargs,
+			value_span.clone(),
+		));
+		Node::Operand(vec_deque![SYMBOL(name, value_span)])
+	}
+
+	// whether the expression is just a name or a literal, which can be evaluated more than once
+	fn is_name(expr: &Expression) -> bool {
+		expr.len() == 1
+			&& match &expr[0] {
+				SYMBOL(_, _) => true,
+				IDENT { expr, .. } => expr.len() == 1 && matches!(expr[0], SYMBOL(_, _)),
+				_ => false,
+			}
+	}
+
+	fn unpack_function(&self) -> &'static str {
+		match self.options.env_target {
+			Some(LuaVersion::LuaJIT | LuaVersion::Lua51 | LuaVersion::Lua51Pure) => "unpack",
+			Some(_) => "table.unpack",
+			None => "(unpack or table.unpack)",
+		}
+	}
+
+	// builds the table of a table constructor or an argument list containing spreads
+	// before the current statement and returns the names of the table and of its length,
+	// only tables copy the keys of the spread tables, argument lists only use their array part
+	fn build_spread_table(
+		&mut self,
+		mut values: Vec<(Option<Expression>, Expression, Span)>,
+		spreads: &[usize],
+		metas: Vec<(String, Expression, Span)>,
+		metatable: Option<String>,
+		copy_keys: bool,
+	) -> (String, String) {
+		let first = spreads[0];
+		let rest = values.split_off(first);
+		let count = values.iter().filter(|(key, ..)| key.is_none()).count();
+		if let Some((None, last, span)) = values.last_mut() {
+			// only the last value can expand into multiple values, which would break the count
+			*last = vec_deque![EXPR(std::mem::take(last), span.clone())];
+		}
+		// the span of all the values, the ones before the first spread one are in the table
+		let span = values.first().unwrap_or(&rest[0]).2.start..rest[rest.len() - 1].2.end;
+		let table = self.get_next_internal_var();
+		let len = self.get_next_internal_var();
+		let mut code = vec_deque![VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![table.clone(), len.clone()],
+			values: vec![
+				vec_deque![TABLE {
+					values,
+					metas,
+					metatable,
+					span: span.clone(),
+				}],
+				vec_deque![SYMBOL(count.to_string(), span.clone())]
+			],
+			span: rest[0].2.clone(),
+		}];
+
+		let assign = |name: String, value: Expression, span: &Span| ALTER {
+			kind: DEFINE,
+			names: vec_deque![vec_deque![SYMBOL(name, span.clone())]],
+			values: vec![value],
+			span: span.clone(),
+		};
+		for (i, (key, value, span)) in rest.into_iter().enumerate() {
+			let line = span.start.line;
+			if spreads.contains(&(first + i)) {
+				let spread = self.get_next_internal_var();
+				let spread_len = self.get_next_internal_var();
+				code.push_back(VARIABLE {
+					local: true,
+					r#const: false,
+					names: vec![spread.clone()],
+					values: vec![value],
+					span: span.clone(),
+				});
+				code.push_back(VARIABLE {
+					local: true,
+					r#const: false,
+					names: vec![spread_len.clone()],
+					values: vec![vec_deque![SYMBOL(format!("#{spread}"), span.clone())]],
+					span: span.clone(),
+				});
+				let k = self.get_next_internal_var();
+				if copy_keys {
+					let v = self.get_next_internal_var();
+					let condition = format!(
+						"type({k}) == \"number\" and {k} >= 1 and {k} <= {spread_len} and {k} % 1 == 0"
+					);
+					let body = vec_deque![IF_STATEMENT {
+						condition: vec_deque![SYMBOL(condition, span.clone())],
+						code: CodeBlock {
+							start: line,
+							code: vec_deque![assign(
+								format!("{table}[{len} + {k}]"),
+								vec_deque![SYMBOL(v.clone(), span.clone())],
+								&span
+							)],
+							end: line,
+						},
+						next: Some(Box::new(DO_BLOCK(
+							CodeBlock {
+								start: line,
+								code: vec_deque![assign(
+									format!("{table}[{k}]"),
+									vec_deque![SYMBOL(v.clone(), span.clone())],
+									&span
+								)],
+								end: line,
+							},
+							span.clone()
+						))),
+						span: span.clone(),
+					}];
+					code.push_back(FOR_FUNC_LOOP {
+						label: None,
+						iterators: vec![k, v],
+						expr: vec_deque![
+							SYMBOL(String::from("pairs"), span.clone()),
+							CALL(vec![vec_deque![SYMBOL(spread, span.clone())]], span.clone())
+						],
+						code: CodeBlock {
+							start: line,
+							code: body,
+							end: line,
+						},
+						span: span.clone(),
+					});
+				} else {
+					let body = vec_deque![assign(
+						format!("{table}[{len} + {k}]"),
+						vec_deque![SYMBOL(format!("{spread}[{k}]"), span.clone())],
+						&span
+					)];
+					code.push_back(FOR_LOOP {
+						label: None,
+						iterator: k,
+						start: vec_deque![SYMBOL(String::from("1"), span.clone())],
+						end: vec_deque![SYMBOL(spread_len.clone(), span.clone())],
+						alter: vec_deque![SYMBOL(String::from("1"), span.clone())],
+						code: CodeBlock {
+							start: line,
+							code: body,
+							end: line,
+						},
+						span: span.clone(),
+					});
+				}
+				code.push_back(assign(
+					len.clone(),
+					vec_deque![SYMBOL(format!("{len} + {spread_len}"), span.clone())],
+					&span,
+				));
+			} else if let Some(mut key) = key {
+				let name = if matches!(key.front(), Some(SYMBOL(bracket, _)) if bracket == "[") {
+					vec_deque![SYMBOL(table.clone(), span.clone())]
+				} else {
+					vec_deque![
+						SYMBOL(table.clone(), span.clone()),
+						SYMBOL(String::from("."), span.clone())
+					]
+				};
+				let mut name: Expression = name;
+				name.append(&mut key);
+				code.push_back(ALTER {
+					kind: DEFINE,
+					names: vec_deque![name],
+					values: vec![value],
+					span: span.clone(),
+				});
+			} else {
+				code.push_back(assign(
+					len.clone(),
+					vec_deque![SYMBOL(format!("{len} + 1"), span.clone())],
+					&span,
+				));
+				code.push_back(assign(format!("{table}[{len}]"), value, &span));
+			}
+		}
+		self.get_prev_expr().append(&mut code);
+		(table, len)
+	}
+
+	fn find_expressions(&mut self, end: OptionalEnd) -> Result<Vec<Expression>, String> {
+		let mut exprs: Vec<Expression> = Vec::new();
+		loop {
+			let expr = self.build_expression(None)?;
+			let t = self.look_back(0);
+			exprs.push(expr);
+			if t.kind() != COMMA {
+				return self.assert_end(&t, end, exprs);
+			}
+		}
+	}
+
+	fn build_table(&mut self) -> Result<ComplexToken, String> {
+		let table_start = self.look_back(0).position();
+		let mut values: Vec<(Option<Expression>, Expression, Span)> = Vec::new();
+		let mut metas: Vec<(String, Expression, Span)> = Vec::new();
+		let mut metatable: Option<String> = None;
+		let mut spreads = Vec::new();
+		while !self.advance_if(CURLY_BRACKET_CLOSED) {
+			let start = self.current;
+			let mut qscope = 1u8;
+			let mut iskey = false;
+			while match self.peek(0).kind() {
+				CURLY_BRACKET_OPEN => {
+					qscope += 1;
+					true
+				}
+				CURLY_BRACKET_CLOSED => {
+					qscope -= 1;
+					qscope > 1
+				}
+				COMMA => qscope != 1,
+				DEFINE if qscope == 1 => {
+					iskey = true;
+					false
+				}
+				META if self.peek(1).kind() == WITH => {
+					iskey = true;
+					true
+				}
+				EOF => {
+					return Err(self.expected_before(
+						"}",
+						"<end>",
+						self.peek(0).line(),
+						self.peek(0).column(),
+					))
+				}
+				_ => true,
+			} {
+				self.current += 1;
+			}
+			self.current = start;
+			if !iskey {
+				if self.advance_spread() {
+					spreads.push(values.len());
+				}
+				let value = self.build_expression(None)?;
+				let span = self.at(start).position()..self.look_back(1).end_position();
+				values.push((None, value, span));
+				self.current -= 1;
+				self.advance_if(COMMA);
+				continue;
+			}
+			let name: Result<Expression, String>;
+			let pn = self.advance();
+			match pn.kind() {
+				IDENTIFIER => {
+					name = Ok(vec_deque![SYMBOL(pn.lexeme(), pn.span())]);
+				}
+				SQUARE_BRACKET_OPEN => {
+					let mut qscope = 1u8;
+					let start = self.current;
+					while match self.advance().kind() {
+						SQUARE_BRACKET_OPEN => {
+							qscope += 1;
+							true
+						}
+						SQUARE_BRACKET_CLOSED => {
+							qscope -= 1;
+							!matches!(qscope, 0)
+						}
+						EOF => {
+							return Err(self.expected_before(
+								"]",
+								"<end>",
+								self.peek(0).line(),
+								self.peek(0).column(),
+							))
+						}
+						_ => true,
+					} {}
+					self.current = start;
+					let key_start = self.peek(0).position();
+					let key = self.build_expression(Some((SQUARE_BRACKET_CLOSED, "]")))?;
+					let key_span = key_start..self.look_back(1).end_position();
+					name = Ok(vec_deque![
+						SYMBOL(String::from("["), pn.span()),
+						EXPR(key, key_span),
+						SYMBOL(String::from("]"), self.look_back(0).span())
+					]);
+				}
+				META => {
+					if self.advance_if(WITH) {
+						if !metas.is_empty() {
+							return Err(self.error(
+								"An external metatable cannot be used if the table already set its own metamethods",
+								pn.line(),
+								pn.column()
+							));
+						}
+						metatable = Some(self.assert_advance(IDENTIFIER, "<name>")?.lexeme());
+						self.advance_if(COMMA);
+						continue;
+					}
+
+					if metatable.is_some() {
+						return Err(self.error(
+								"Metamethods cannot be set if the table already uses an external metatable",
+								pn.line(),
+								pn.column()
+							));
+					}
+					let name_token = self.advance();
+					name = Err(String::from(match name_token.lexeme().as_ref() {
+						"index" => "__index",
+						"newindex" => "__newindex",
+						"usedindex" => {
+							if matches!(self.options.env_target, Some(LuaVersion::BLUA)) {
+								"__usedindex"
+							} else {
+								return Err(self.error(
+								"The 'usedindex' metamethod can only be used with --target=blua",
+								name_token.line(),
+								name_token.column()
+							));
+							}
+						}
+						"mode" => "__mode",
+						"call" => "__call",
+						"metatable" => "__metatable",
+						"tostring" => "__tostring",
+						"len" => "__len",
+						"pairs" => "__pairs",
+						"ipairs" => "__ipairs",
+						"gc" => "__gc",
+						"name" => "__name",
+						"close" => "__close",
+						"unm" | "unary" => "__unm",
+						"add" | "+" => "__add",
+						"sub" | "-" => "__sub",
+						"mul" | "*" => "__mul",
+						"div" | "/" => "__div",
+						"mod" | "%" => "__mod",
+						"pow" | "^" => "__pow",
+						"concat" | ".." => "__concat",
+						"eq" | "equal" | "==" => "__eq",
+						"lt" | "less_than" | "<" => "__lt",
+						"le" | "less_than_equal" | "<=" => "__le",
+						_ => {
+							return Err(self.expected(
+								"<meta name>",
+								&name_token.lexeme(),
+								name_token.line(),
+								name_token.column(),
+							));
+						}
+					}))
+				}
+				_ => return Err(self.expected("<name>", &pn.lexeme(), pn.line(), pn.column())),
+			}
+			if !self.advance_if(DEFINE) {
+				let t = self.peek(0);
+				return Err(self.expected("=", &t.lexeme(), t.line(), t.column()));
+			}
+			let start = self.current;
+			let mut cscope = 0u8;
+			while match self.peek(0).kind() {
+				COMMA | CURLY_BRACKET_CLOSED => cscope != 0,
+				ROUND_BRACKET_OPEN | SAFE_CALL => {
+					cscope += 1;
+					true
+				}
+				ROUND_BRACKET_CLOSED => {
+					if cscope == 0 {
+						let t = self.peek(0);
+						return Err(self.expected_before("(", ")", t.line(), t.column()));
+					}
+					cscope -= 1;
+					true
+				}
+				EOF => {
+					return Err(self.expected_before(
+						"}",
+						"<end>",
+						self.peek(0).line(),
+						self.peek(0).column(),
+					))
+				}
+				_ => true,
+			} {
+				self.current += 1;
+			}
+			self.current = start;
+			let value = self.build_expression(None)?;
+			let span = pn.position()..self.look_back(1).end_position();
+			match name {
+				Ok(n) => values.push((Some(n), value, span)),
+				Err(n) => metas.push((n, value, span)),
+			}
+			self.current -= 1;
+			self.advance_if(COMMA);
+		}
+		let span = self.span_from(table_start);
+		if !spreads.is_empty() {
+			let (table, _) = self.build_spread_table(values, &spreads, metas, metatable, true);
+			return Ok(SYMBOL(table, span));
+		}
+		Ok(TABLE {
+			values,
+			metas,
+			metatable,
+			span,
+		})
+	}
+
+	// `ordered { ... }` keeps the order of its keys in the `__order` field of its metatable,
+	// which its `__pairs` metamethod follows, and the keys in it in its `__keys` field
+	// so that its `__newindex` metamethod adds the new keys only once
+	fn build_ordered_table(&mut self) -> Result<ComplexToken, String> {
+		let t = self.look_back(1);
+		match self.options.env_target {
+			// `pairs` only uses `__pairs` since Lua 5.2
+			Some(target) if target.base_version() < (5, 2) => {
+				return Err(self.error(
+					"Ordered tables can only be used when targeting Lua 5.2 or later",
+					t.line(),
+					t.column(),
+				))
+			}
+			None => self.warning(
+				"Ordered tables are iterated in order by 'pairs' only on Lua 5.2 or later",
+				t.line(),
+				t.column(),
+			),
+			Some(_) => {}
+		}
+		let TABLE {
+			mut values,
+			mut metas,
+			metatable,
+			span: table_span,
+		} = self.build_table()?
+		else {
+			return Err(self.error(
+				"Ordered tables cannot contain spreads",
+				t.line(),
+				t.column(),
+			));
+		};
+		if metatable.is_some()
+			|| metas
+				.iter()
+				.any(|(name, ..)| name == "__pairs" || name == "__newindex")
+		{
+			return Err(self.error(
+				"Ordered tables cannot use an external metatable or their own 'pairs' and 'newindex' metamethods",
+				t.line(),
+				t.column(),
+			));
+		}
+		let mut order = Vec::with_capacity(values.len());
+		let mut keys = Vec::with_capacity(values.len());
+		let mut index = 0;
+		for (key, _, span) in &mut values {
+			let key = match key {
+				Some(key) if key.len() == 1 => {
+					let SYMBOL(name, _) = &key[0] else {
+						unreachable!()
+					};
+					vec_deque![SYMBOL(format_clue!("\"", name, "\""), span.clone())]
+				}
+				// keys like `[f()]` are computed only once
+				Some(key) => {
+					let EXPR(expr, _) = &mut key[1] else {
+						unreachable!()
+					};
+					if expr.len() == 1 && matches!(expr[0], SYMBOL(_, _)) {
+						expr.clone()
+					} else {
+						let name = self.get_next_internal_var();
+						self.get_prev_expr().push_back(VARIABLE {
+							local: true,
+							r#const: false,
+							names: vec![name.clone()],
+							values: vec![std::mem::replace(
+								expr,
+								vec_deque![SYMBOL(name.clone(), span.clone())],
+							)],
+							span: span.clone(),
+						});
+						vec_deque![SYMBOL(name, span.clone())]
+					}
+				}
+				None => {
+					index += 1;
+					vec_deque![SYMBOL(index.to_string(), span.clone())]
+				}
+			};
+			let mut keyed = key.clone();
+			keyed.push_front(SYMBOL(String::from("["), span.clone()));
+			keyed.push_back(SYMBOL(String::from("]"), span.clone()));
+			keys.push((
+				Some(keyed),
+				vec_deque![SYMBOL(String::from("true"), span.clone())],
+				span.clone(),
+			));
+			order.push((None, key, span.clone()));
+		}
+		let span = t.span();
+		for (name, values) in [("__order", order), ("__keys", keys)] {
+			metas.push((
+				String::from(name),
+				vec_deque![TABLE {
+					values,
+					metas: Vec::new(),
+					metatable: None,
+					span: span.clone(),
+				}],
+				span.clone(),
+			));
+		}
+		metas.push((
+			String::from("__newindex"),
+			vec_deque![SYMBOL(String::from(
+				"function(t, k, v) local meta = getmetatable(t) if v ~= nil and not meta.__keys[k] then meta.__keys[k] = true meta.__order[#meta.__order + 1] = k end rawset(t, k, v) end"
+			), span.clone())],
+			span.clone(),
+		));
+		// the keys whose value was removed are skipped
+		metas.push((
+			String::from("__pairs"),
+			vec_deque![SYMBOL(String::from(
+				"function(t) local order, i = getmetatable(t).__order, 0 return function() local k repeat i = i + 1 k = order[i] until k == nil or t[k] ~= nil if k ~= nil then return k, t[k] end end, t, nil end"
+			), span.clone())],
+			span,
+		));
+		Ok(TABLE {
+			values,
+			metas,
+			metatable,
+			span: t.position()..table_span.end,
+		})
+	}
+
+	// `set { a, b }` is the table `{[a] = true, [b] = true}`
+	fn build_set(&mut self) -> Result<ComplexToken, String> {
+		let t = self.look_back(1);
+		let TABLE {
+			values,
+			metas,
+			metatable,
+			span: table_span,
+		} = self.build_table()?
+		else {
+			return Err(self.error("Sets cannot contain spreads", t.line(), t.column()));
+		};
+		if values.iter().any(|(key, ..)| key.is_some()) {
+			return Err(self.error(
+				"Sets can only contain values, not keys",
+				t.line(),
+				t.column(),
+			));
+		}
+		let values = values
+			.into_iter()
+			.map(|(_, value, span)| {
+				let key = vec_deque![
+					SYMBOL(String::from("["), span.clone()),
+					EXPR(value, span.clone()),
+					SYMBOL(String::from("]"), span.clone())
+				];
+				let value = vec_deque![SYMBOL(String::from("true"), span.clone())];
+				(Some(key), value, span)
+			})
+			.collect();
+		Ok(TABLE {
+			values,
+			metas,
+			metatable,
+			span: t.position()..table_span.end,
+		})
+	}
+
+	// `defaulttable(factory)` is an empty table that sets the keys that are missing
+	// to the result of `factory(key)` when they are read
+	fn build_default_table(&mut self) -> Result<ComplexToken, String> {
+		let t = self.look_back(1);
+		let factory = self.build_expression(Some((ROUND_BRACKET_CLOSED, ")")))?;
+		if factory.is_empty() {
+			return Err(self.error(
+				"'defaulttable' needs the function that creates the default values",
+				t.line(),
+				t.column(),
+			));
+		}
+		let name = self.get_next_internal_var();
+		let span = self.span_from(t.position());
+		self.get_prev_expr().push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: vec![factory],
+			span: span.clone(),
+		});
+		Ok(TABLE {
+			values: Vec::new(),
+			metas: vec![(
+				String::from("__index"),
+				vec_deque![SYMBOL(
+					format_clue!(
+						"function(t, k) local v = ",
+						name,
+						"(k) t[k] = v return v end"
+					),
+					span.clone()
+				)],
+				span.clone(),
+			)],
+			metatable: None,
+			span,
+		})
+	}
+
+	// `left` is whether a binary operator has an operand on its left, [`None`] for unary ones
+	fn check_operator(&mut self, t: &BorrowedToken, left: Option<bool>) -> Result<(), String> {
+		if !matches!(
+			self.peek(0).kind(),
+			NUMBER
+				| IDENTIFIER | STRING
+				| TRUE | FALSE
+				| MINUS | BIT_NOT
+				| NIL | NOT | HASHTAG
+				| ROUND_BRACKET_OPEN
+				| THREEDOTS | MATCH
+				| IF | DO | CONST
+				| CURLY_BRACKET_OPEN
+		) {
+			return Err(self.error(
+				format!("Operator '{}' has invalid right hand token", t.lexeme()),
+				t.line(),
+				t.column(),
+			));
+		}
+		if let Some(left) = left {
+			if !left {
+				return Err(self.error(
+					format!("Operator '{}' lacks a left hand token", t.lexeme()),
+					t.line(),
+					t.column(),
+				));
+			} else if !matches!(
+				self.look_back(1).kind(),
+				NUMBER
+					| IDENTIFIER | STRING
+					| TRUE | FALSE | NIL
+					| ROUND_BRACKET_CLOSED
+					| SQUARE_BRACKET_CLOSED
+					| THREEDOTS | CURLY_BRACKET_CLOSED
+			) {
+				return Err(self.error(
+					format!("Operator '{}' has invalid left hand token", t.lexeme()),
+					t.line(),
+					t.column(),
+				));
+			}
+		}
+		Ok(())
+	}
+
+	// `x |> f(a)` becomes `f(x, a)`, the value is passed through a placeholder argument
+	// added to the tokens of the last call (or a new call) so that safe calls and
+	// method calls put it in the right place
+	fn build_pipe(&mut self, t: &BorrowedToken, expr: &mut Expression) -> Result<(), String> {
+		self.check_operator(t, Some(true))?;
+		if !self.compare(IDENTIFIER) {
+			let t = self.peek(0);
+			return Err(self.expected("<function>", &t.lexeme(), t.line(), t.column()));
+		}
+		let mut value = Expression::with_capacity(expr.len());
+		value.append(expr);
+		let (i, call) = self.skip_identifier(self.current);
+		let (line, column) = (t.line(), t.column());
+		let placeholder = Token::new(IDENTIFIER, "|>", line, column);
+		match call {
+			Some(i) => {
+				if self.at(i).kind() != ROUND_BRACKET_CLOSED {
+					self.tokens.insert(i, Token::new(COMMA, ",", line, column));
+					self.size += 1;
+				}
+				self.tokens.insert(i, placeholder);
+				self.size += 1;
+			}
+			None => {
+				self.tokens
+					.insert(i, Token::new(ROUND_BRACKET_CLOSED, ")", line, column));
+				self.tokens.insert(i, placeholder);
+				self.tokens
+					.insert(i, Token::new(ROUND_BRACKET_OPEN, "(", line, column));
+				self.size += 3;
+			}
+		}
+		self.current += 1;
+		let mut function = self.build_identifier()?;
+		self.current -= 1;
+		PipeValue(Some(value)).transform_ctoken(&mut function);
+		expr.push_back(function);
+		Ok(())
+	}
+
+	// returns the position after the identifier starting at `start`
+	// and the position after the '(' of its last call if it ends with one
+	fn skip_identifier(&self, start: usize) -> (usize, Option<usize>) {
+		let mut call = None;
+		let mut i = start + 1;
+		loop {
+			match self.at(i).kind() {
+				DOT | SAFE_DOT | DOUBLE_COLON | SAFE_DOUBLE_COLON
+					if self.at(i + 1).kind() == IDENTIFIER =>
+				{
+					call = None;
+					i += 2;
+				}
+				kind @ (SQUARE_BRACKET_OPEN | SAFE_SQUARE_BRACKET | ROUND_BRACKET_OPEN
+				| SAFE_CALL) => {
+					call = matches!(kind, ROUND_BRACKET_OPEN | SAFE_CALL).then_some(i + 1);
+					i = self.skip_brackets(i);
+				}
+				_ => return (i, call),
+			}
+		}
+	}
+
+	// returns the position after the bracket closing the one at `start`
+	fn skip_brackets(&self, start: usize) -> usize {
+		let mut depth = 0usize;
+		let mut i = start;
+		loop {
+			match self.at(i).kind() {
+				ROUND_BRACKET_OPEN | SQUARE_BRACKET_OPEN | CURLY_BRACKET_OPEN | SAFE_CALL
+				| SAFE_SQUARE_BRACKET => depth += 1,
+				ROUND_BRACKET_CLOSED | SQUARE_BRACKET_CLOSED | CURLY_BRACKET_CLOSED => {
+					depth -= 1;
+					if depth == 0 {
+						return i + 1;
+					}
+				}
+				EOF => return i,
+				_ => {}
+			}
+			i += 1;
+		}
+	}
+
+	fn check_index(
+		&mut self,
+		t: &BorrowedToken,
+		expr: &mut Expression,
+		lexeme: &str,
+	) -> Result<(), String> {
+		if !self.compare(IDENTIFIER)
+			|| matches!(self.look_back(0).kind(), IDENTIFIER | SQUARE_BRACKET_CLOSED)
+		{
+			return Err(self.error(
+				format!("'{}' should be used only when indexing", t.lexeme()),
+				self.peek(0).line(),
+				self.peek(0).column(),
+			));
+		}
+		expr.push_back(SYMBOL(lexeme.to_owned(), t.span()));
+		Ok(())
+	}
+
+	// whether the next token starts a value, which ends the expression before it
+	fn starts_value(&self) -> bool {
+		matches!(
+			self.peek(0).kind(),
+			NUMBER
+				| IDENTIFIER | STRING
+				| TRUE | BIT_NOT
+				| FALSE | NIL
+				| NOT | HASHTAG
+				| CURLY_BRACKET_OPEN
+				| THREEDOTS | MATCH
+				| IF | DO | CONST
+		)
+	}
+
+	fn check_val(&mut self) -> bool {
+		let value = self.starts_value();
+		if value {
+			self.current += 1;
+		}
+		value
+	}
+
+	fn get_prev_expr(&mut self) -> &mut Expression {
+		match self.internal_stack.last_mut() {
+			Some(last) => last.get_mut(),
+			None => &mut self.expr,
+		}
+	}
+
+	fn use_internal_stack<T>(
+		&mut self,
+		f: impl FnOnce(&mut Self) -> Result<T, String>,
+	) -> Result<(T, Expression), String> {
+		self.internal_stack.push(Cell::new(Expression::new()));
+		let result = f(self)?;
+		let code = self.internal_stack.pop().unwrap().into_inner();
+		Ok((result, code))
+	}
+
+	fn build_expression(&mut self, end: OptionalEnd) -> Result<Expression, String> {
+		self.traced("expression", &self.peek(0), |i| {
+			i.build_expression_internal(end)
+		})
+	}
+
+	fn build_expression_internal(&mut self, end: OptionalEnd) -> Result<Expression, String> {
+		let start = self.current;
+		let bit = self.options.env_jitbit.as_deref();
+		let mut expr = match self.build_unary(end, false, true)? {
+			Some(first) => self
+				.build_operation(first, 0, end, false)?
+				.into_expression(bit),
+			None => Expression::new(),
+		};
+		// the operators looser than every other one, which take the whole expression on their left
+		while !expr.is_empty() {
+			match self.peek(0).kind() {
+				PIPE => {
+					let t = self.advance();
+					self.build_pipe(&t, &mut expr)?;
+					// what the function returns is the left operand of the operators after it
+					let value = Node::Operand(std::mem::take(&mut expr));
+					expr = self
+						.build_operation(value, 0, end, false)?
+						.into_expression(bit);
+				}
+				COALESCE => {
+					let t = self.advance();
+					let mut leftexpr = Expression::with_capacity(expr.len());
+					leftexpr.append(&mut expr);
+					let (rightexpr, mut code) =
+						self.use_internal_stack(|i| i.build_expression(end))?;
+					self.current -= 1;
+					let name = self.get_next_internal_var();
+					let span = self.span_from(self.at(start).position());
+					let end = self.at(self.current).line();
+					let prev_expr = self.get_prev_expr();
+					prev_expr.push_back(VARIABLE {
+						span: span.clone(),
+						local: true,
+						r#const: false,
+						names: vec![name.clone()],
+						values: vec![leftexpr],
+					});
+					let name = SYMBOL(name, span.clone());
+					code.push_back(ALTER {
+						kind: DEFINE,
+						span: t.position()..span.end,
+						names: vec_deque![vec_deque![name.clone()]],
+						values: vec![rightexpr],
+					});
+					prev_expr.push_back(IF_STATEMENT {
+						condition: vec_deque![
+							name.clone(),
+							SYMBOL(String::from(" == nil"), t.span())
+						],
+						code: CodeBlock {
+							start: t.line(),
+							code,
+							end,
+						},
+						next: None,
+						span,
+					});
+					expr.push_back(name);
+				}
+				QUESTION_MARK => {
+					self.advance();
+					let mut condition = Expression::with_capacity(expr.len());
+					condition.append(&mut expr);
+					let iftrue =
+						self.use_internal_stack(|i| i.build_expression(Some((COLON, ":"))))?;
+					let t2 = self.look_back(0);
+					let iffalse = self.use_internal_stack(|i| i.build_expression(end))?;
+					self.current -= 1;
+					let positions = (
+						self.at(start).position(),
+						t2.position(),
+						self.look_back(0).end_position(),
+					);
+					expr.push_back(self.build_conditional(condition, iftrue, iffalse, positions));
+				}
+				_ => break,
+			}
+		}
+		let last = self.advance();
+		if last.kind() == SEMICOLON {
+			self.current += 1;
+		}
+		if expr.is_empty() {
+			return Err(self.expected("<expr>", &last.lexeme(), last.line(), last.column()));
+		}
+		self.assert_end(&self.look_back(0), end, expr)
+	}
+
+	// the Lua operator of a token if it's a binary operator
+	fn binary_operator(&self, t: &BorrowedToken) -> Option<String> {
+		Some(match t.kind() {
+			PLUS | MINUS | STAR | SLASH | PERCENTUAL | CARET | TWODOTS | EQUAL | BIGGER
+			| BIGGER_EQUAL | SMALLER | SMALLER_EQUAL | BIT_AND | BIT_OR | LEFT_SHIFT
+			| RIGHT_SHIFT => t.lexeme(),
+			FLOOR_DIVISION => String::from("//"),
+			// the bit library is used once the operands are known, like with the other operators
+			BIT_XOR
+				if self.options.env_bitwise == BitwiseMode::Vanilla
+					|| self.options.env_jitbit.is_some() =>
+			{
+				String::from("~")
+			}
+			BIT_XOR => t.lexeme(),
+			NOT_EQUAL => String::from("~="),
+			AND => String::from(" and "),
+			OR => String::from(" or "),
+			_ => return None,
+		})
+	}
+
+	// reads the binary operators after `left` that bind at least as tight as `min_precedence`
+	// and their operands, see the `precedence` module.
+	// `lazy` is whether the code is only run when an `and` or an `or` before it lets it
+	fn build_operation(
+		&mut self,
+		mut left: Node,
+		min_precedence: usize,
+		end: OptionalEnd,
+		lazy: bool,
+	) -> Result<Node, String> {
+		while let Some(op) = self.binary_operator(&self.peek(0)) {
+			let (precedence, right_associative) =
+				binary_precedence(&op).expect("every binary operator has a precedence");
+			if precedence < min_precedence {
+				break;
+			}
+			let t = self.advance();
+			self.check_operator(&t, Some(true))?;
+			if self.options.env_chained_comparisons && is_ordering(&op) {
+				if let Some(middle) = left.chained_operand() {
+					let operand = self.build_chained_comparison(&t, middle);
+					let right = self.build_lazy_operand(precedence + 1, end)?;
+					let comparison = Node::Binary(Box::new(operand), op, t.span(), Box::new(right));
+					let and = String::from(" and ");
+					left = Node::Binary(Box::new(left), and, t.span(), Box::new(comparison));
+					continue;
+				}
+			}
+			let min_precedence = if right_associative {
+				precedence
+			} else {
+				precedence + 1
+			};
+			let right = if matches!(op.as_str(), " and " | " or ") {
+				self.build_lazy_operand(min_precedence, end)?
+			} else {
+				self.build_right_operand(min_precedence, end, lazy)?
+			};
+			left = Node::Binary(Box::new(left), op, t.span(), Box::new(right));
+		}
+		Ok(left)
+	}
+
+	// reads the operand on the right of an operator with the operators after it
+	// that bind at least as tight as `min_precedence`
+	fn build_right_operand(
+		&mut self,
+		min_precedence: usize,
+		end: OptionalEnd,
+		lazy: bool,
+	) -> Result<Node, String> {
+		let Some(operand) = self.build_unary(end, lazy, false)? else {
+			let t = self.peek(0);
+			return Err(self.expected("<expr>", &t.lexeme(), t.line(), t.column()));
+		};
+		self.build_operation(operand, min_precedence, end, lazy)
+	}
+
+	// reads an operand which is only evaluated when the operator before it needs it,
+	// like the right one of `and`, so the statements it needs can't be moved before the expression:
+	// they are run by a function called in place of the operand
+	fn build_lazy_operand(
+		&mut self,
+		min_precedence: usize,
+		end: OptionalEnd,
+	) -> Result<Node, String> {
+		let t = self.peek(0);
+		let (operand, mut code) =
+			self.use_internal_stack(|i| i.build_right_operand(min_precedence, end, true))?;
+		// declaring a local without a value does nothing, so those can still be moved
+		if code
+			.iter()
+			.all(|ctoken| matches!(ctoken, VARIABLE { values, .. } if values.is_empty()))
+		{
+			self.get_prev_expr().append(&mut code);
+			return Ok(operand);
+		}
+		let mut exits = BranchExits {
+			loops: Vec::new(),
+			found: false,
+			varargs: false,
+		};
+		exits.visit_expression(&code);
+		if exits.found {
+			return Err(self.error(
+				"'return', 'break', 'continue' and 'goto' cannot be used in an expression after '&&', '||' or a chained comparison",
+				t.line(),
+				t.column(),
+			));
+		}
+		let value = operand.into_expression(self.options.env_jitbit.as_deref());
+		let span = expression_span(&value);
+		code.push_back(RETURN_EXPR(Some(vec![value]), span.clone()));
+		let line = t.line();
+		let code = CodeBlock {
+			start: line,
+			code,
+			end: self.look_back(1).line(),
+		};
+		Ok(Node::Operand(Self::call_in_place(
+			code,
+			exits.varargs,
+			span,
+		)))
+	}
+
+	// a call to a function running `code`, which is given the varargs of the function around it
+	// `span` is the span of the code the function replaces
+	fn call_in_place(code: CodeBlock, varargs: bool, span: Span) -> Expression {
+		let (args, call_args) = if varargs {
+			let varargs = vec_deque![SYMBOL(String::from("..."), span.clone())];
+			(vec![(String::from("..."), None)], vec![varargs])
+		} else {
+			(FunctionArgs::new(), Vec::new())
+		};
+		let function = LAMBDA {
+			args,
+			code,
+			span: span.clone(),
+		};
+		vec_deque![
+			EXPR(vec_deque![function], span.clone()),
+			CALL(call_args, span)
+		]
+	}
+
+	// reads a unary operator and its operand, or just an operand.
+	// `first` is whether it starts the expression, where a `{` ends it when it's expected after it
+	fn build_unary(
+		&mut self,
+		end: OptionalEnd,
+		lazy: bool,
+		first: bool,
+	) -> Result<Option<Node>, String> {
+		let t = self.peek(0);
+		let op = match t.kind() {
+			MINUS | HASHTAG if matches!(self.peek(1).kind(), QUESTION_MARK | SAFE_CALL) => None,
+			// `- -a` can't become `--a`, which would be a comment
+			MINUS if self.current > 0 && self.look_back(0).kind() == MINUS => {
+				Some(String::from(" -"))
+			}
+			MINUS | BIT_NOT => Some(t.lexeme()),
+			NOT => Some(String::from("not ")),
+			HASHTAG => Some(String::from("#")),
+			_ => None,
+		};
+		let Some(op) = op else {
+			return self.build_operand(end, lazy, first);
+		};
+		let t = self.advance();
+		if t.kind() == HASHTAG {
+			if !matches!(
+				self.peek(0).kind(),
+				IDENTIFIER | CURLY_BRACKET_OPEN | ROUND_BRACKET_OPEN
+			) {
+				let t = self.peek(0);
+				return Err(self.expected("<table>", &t.lexeme(), t.line(), t.column()));
+			}
+		} else {
+			self.check_operator(&t, None)?;
+		}
+		let operand = self.build_right_operand(UNARY_PRECEDENCE, end, lazy)?;
+		Ok(Some(Node::Unary(op, t.span(), Box::new(operand))))
+	}
+
+	// reads a single value, like a name followed by calls and indexing or a table,
+	// [`None`] means that the expression ends before it
+	fn build_operand(
+		&mut self,
+		end: OptionalEnd,
+		lazy: bool,
+		first: bool,
+	) -> Result<Option<Node>, String> {
+		let t = self.peek(0);
+		// `set {` and `ordered {` are names when they could be followed by a block
+		let before_block = matches!(end, Some((CURLY_BRACKET_OPEN, _)));
+		let mut expr = Expression::with_capacity(4);
+		match t.kind() {
+			IDENTIFIER
+				if t.lexeme() == "ordered"
+					&& self.peek(1).kind() == CURLY_BRACKET_OPEN
+					&& !before_block =>
+			{
+				self.advance();
+				self.current += 1;
+				expr.push_back(self.build_ordered_table()?);
+			}
+			IDENTIFIER
+				if t.lexeme() == "set"
+					&& self.peek(1).kind() == CURLY_BRACKET_OPEN
+					&& !before_block =>
+			{
+				self.advance();
+				self.current += 1;
+				expr.push_back(self.build_set()?);
+			}
+			IDENTIFIER
+				if t.lexeme() == "defaulttable" && self.peek(1).kind() == ROUND_BRACKET_OPEN =>
+			{
+				self.advance();
+				self.current += 1;
+				expr.push_back(self.build_default_table()?);
+			}
+			IDENTIFIER => {
+				self.advance();
+				let fname = self.build_identifier()?;
+				self.current -= 1;
+				expr.push_back(fname);
+			}
+			CURLY_BRACKET_OPEN if first && before_block => return Ok(None),
+			CURLY_BRACKET_OPEN => {
+				self.advance();
+				expr.push_back(self.build_table()?);
+			}
+			MINUS | HASHTAG => {
+				let op = if self.advance().kind() == MINUS {
+					"-"
+				} else {
+					"#"
+				};
+				return self.build_safe_unary(op, end, lazy).map(Some);
+			}
+			/*SAFE_EXPRESSION => {
+				self.assert(ROUND_BRACKET_OPEN, "(")?;
+				self.current += 1;
+				expr.push_back(PGET(self.build_identifier(true)?));
+			}*/
+			MATCH => {
+				self.advance();
+				expr.extend(self.build_match_expression(lazy)?);
+			}
+			DO => {
+				self.advance();
+				expr.push_back(self.build_do_expression()?);
+			}
+			CONST if self.peek(1).kind() == CURLY_BRACKET_OPEN => {
+				self.advance();
+				expr.push_back(self.build_const_block()?);
+			}
+			IF => {
+				self.advance();
+				expr.push_back(self.build_if_expression()?);
+			}
+			THREEDOTS | NUMBER | TRUE | FALSE | NIL | STRING => {
+				let t = self.advance();
+				expr.push_back(SYMBOL(t.lexeme(), t.span()));
+			}
+			ROUND_BRACKET_OPEN => {
+				self.advance();
+				let inner = self.build_expression(Some((ROUND_BRACKET_CLOSED, ")")))?;
+				let bracketed_expr = EXPR(inner, self.span_from(t.position()));
+				if self.starts_value() {
+					expr.push_back(bracketed_expr);
+				} else {
+					self.build_round_brackets(vec_deque![bracketed_expr], &mut expr, false)?;
+				}
+			}
+			FN => {
+				self.advance();
+				let (args, rest /*, types*/) = if self.advance_if(ROUND_BRACKET_OPEN)
+					&& !self.advance_if(ROUND_BRACKET_CLOSED)
+				{
+					self.build_function_args()?
+				} else {
+					(FunctionArgs::new(), None /*, None*/)
+				};
+				let code = if self.advance_if(ARROW) {
+					self.build_short_lambda(rest)?
+				} else {
+					self.build_function_block(rest /*, types*/)?
+				};
+				expr.push_back(LAMBDA {
+					args,
+					code,
+					span: self.span_from(t.position()),
+				});
+			}
+			PIPE => {
+				let t = self.advance();
+				return Err(self
+					.check_operator(&t, Some(false))
+					.expect_err("an operator can't start an expression"));
+			}
+			_ if self.binary_operator(&t).is_some() => {
+				let t = self.advance();
+				return Err(self
+					.check_operator(&t, Some(false))
+					.expect_err("an operator can't start an expression"));
+			}
+			_ => return Ok(None),
+		}
+		Ok(Some(Node::Operand(expr)))
+	}
+
+	fn build_if_expression(&mut self) -> Result<ComplexToken, String> {
+		let start = self.look_back(0).position();
+		let condition = self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?;
+		let iftrue =
+			self.use_internal_stack(|i| i.build_expression(Some((CURLY_BRACKET_CLOSED, "}"))))?;
+		let t = self.advance();
+		let iffalse = match self.else_kind(&t) {
+			ELSE => {
+				self.assert(CURLY_BRACKET_OPEN, "{")?;
+				self.use_internal_stack(|i| i.build_expression(Some((CURLY_BRACKET_CLOSED, "}"))))?
+			}
+			ELSEIF => self.use_internal_stack(|i| Ok(vec_deque![i.build_if_expression()?]))?,
+			_ => return Err(self.expected("else", &t.lexeme(), t.line(), t.column())),
+		};
+		let positions = (start, t.position(), self.look_back(0).end_position());
+		Ok(self.build_conditional(condition, iftrue, iffalse, positions))
+	}
+
+	fn is_truthy(expr: &Expression) -> bool {
+		if expr.len() != 1 {
+			return false;
+		}
+		match &expr[0] {
+			SYMBOL(lexeme, _) => {
+				lexeme == "true"
+					|| lexeme.starts_with(|c: char| c.is_ascii_digit() || "\"'[".contains(c))
+			}
+			TABLE { .. } | LAMBDA { .. } => true,
+			_ => false,
+		}
+	}
+
+	// compiles to `cond and a or b` when `a` can never be false or nil,
+	// otherwise to an if statement which assigns the result to an internal variable
+	fn build_conditional(
+		&mut self,
+		condition: Expression,
+		(exprtrue, mut codetrue): (Expression, Expression),
+		(exprfalse, mut codefalse): (Expression, Expression),
+		(start, middle, end): (TokenPosition, TokenPosition, TokenPosition),
+	) -> ComplexToken {
+		if codetrue.is_empty() && codefalse.is_empty() && Self::is_truthy(&exprtrue) {
+			let condition_span = expression_span(&condition);
+			let false_span = expression_span(&exprfalse);
+			let mut expr = vec_deque![
+				EXPR(condition, condition_span),
+				SYMBOL(String::from(" and "), start..middle)
+			];
+			expr.extend(exprtrue);
+			expr.push_back(SYMBOL(String::from(" or "), middle..end));
+			expr.push_back(EXPR(exprfalse, false_span));
+			return EXPR(expr, start..end);
+		}
+		let name = self.get_next_internal_var();
+		let prev_expr = self.get_prev_expr();
+		prev_expr.push_back(VARIABLE {
+			span: start..end,
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: Vec::new(),
+		});
+		let name = SYMBOL(name, start..end);
+		codetrue.push_back(ALTER {
+			kind: DEFINE,
+			span: start..middle,
+			names: vec_deque![vec_deque![name.clone()]],
+			values: vec![exprtrue],
+		});
+		codefalse.push_back(ALTER {
+			kind: DEFINE,
+			span: middle..end,
+			names: vec_deque![vec_deque![name.clone()]],
+			values: vec![exprfalse],
+		});
+		prev_expr.push_back(IF_STATEMENT {
+			condition,
+			code: CodeBlock {
+				start: start.line,
+				code: codetrue,
+				end: middle.line,
+			},
+			next: Some(Box::new(DO_BLOCK(
+				CodeBlock {
+					start: middle.line,
+					code: codefalse,
+					end: end.line,
+				},
+				middle..end,
+			))),
+			span: start..middle,
+		});
+		name
+	}
+
+	fn build_identifier(&mut self) -> Result<ComplexToken, String> {
+		let start = self.look_back(0).position();
+		let mut expr = Expression::with_capacity(8);
+		let safe_indexing = self.build_identifier_internal(&mut expr)?;
+		// the token after the identifier was already read
+		let span = start..self.look_back(1).end_position();
+		if safe_indexing {
+			expr.push_front(SYMBOL(String::from("("), span.clone()));
+			expr.push_back(SYMBOL(String::from(")"), span.clone()));
+		} else if let Some(value) = self.inlined_enum_member(&expr, start)? {
+			return Ok(SYMBOL(value, span));
+		}
+		Ok(IDENT { expr, span })
+	}
+
+	// the value of `Name.MEMBER` when `Name` is an enum declared with `EnumMode::Inline`
+	fn inlined_enum_member(
+		&mut self,
+		expr: &Expression,
+		start: TokenPosition,
+	) -> Result<Option<String>, String> {
+		let symbols: Vec<&ComplexToken> = expr.iter().collect();
+		let [SYMBOL(name, _), SYMBOL(dot, _), SYMBOL(member, _)] = symbols[..] else {
+			return Ok(None);
+		};
+		let Some((_, members)) = self
+			.declarations
+			.enums
+			.iter()
+			.rev()
+			.find(|(r#enum, _)| r#enum == name && dot == ".")
+		else {
+			return Ok(None);
+		};
+		match members.iter().find(|(other, _)| other == member) {
+			Some((_, value)) => Ok(Some(value.clone())),
+			None => Err(self.error(
+				format!("'{member}' is not a member of the enum '{name}'"),
+				start.line,
+				start.column,
+			)),
+		}
+	}
+
+	// `#?x` and `-?x` are nil instead of an error when `x` is nil
+	// `-?a ^ b` is `-(a ^ b)` like in Lua, so the operand is read with the `^` after it
+	fn build_safe_unary(&mut self, op: &str, end: OptionalEnd, lazy: bool) -> Result<Node, String> {
+		let op_span = self.look_back(0).span();
+		let start = op_span.start;
+		let t = self.advance();
+		let operand = if t.kind() == SAFE_CALL {
+			let inner = self.build_expression(Some((ROUND_BRACKET_CLOSED, ")")))?;
+			EXPR(inner, self.span_from(t.position()))
+		} else {
+			self.assert_advance(IDENTIFIER, "<name>")?;
+			let operand = self.build_identifier()?;
+			self.current -= 1;
+			operand
+		};
+		let name = self.get_next_internal_var();
+		let span = self.span_from(start);
+		self.get_prev_expr().push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: vec![vec_deque![operand]],
+			span: span.clone(),
+		});
+		let value = Node::Operand(vec_deque![SYMBOL(name.clone(), span.clone())]);
+		let value = self.build_operation(value, UNARY_PRECEDENCE, end, lazy)?;
+		Ok(Node::Binary(
+			Box::new(Node::Operand(vec_deque![SYMBOL(name, span)])),
+			String::from(" and "),
+			op_span.clone(),
+			Box::new(Node::Unary(op.to_owned(), op_span, Box::new(value))),
+		))
+	}
+
+	fn build_safe_index(
+		&mut self,
+		normal_kind: TokenType,
+		kind: TokenType,
+		expr: &mut Expression,
+	) -> bool {
+		if (kind as u8).wrapping_sub(6) != normal_kind as u8 {
+			return false;
+		}
+		let mut safe_expr = Expression::with_capacity(expr.len());
+		safe_expr.append(expr);
+		let name = self.get_next_internal_var();
+		let span = self.look_back(0).span();
+		self.get_prev_expr().push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: vec![safe_expr],
+			span: span.clone(),
+		});
+		expr.push_back(SYMBOL(name.clone(), span.clone()));
+		expr.push_back(SYMBOL(String::from(" and "), span.clone()));
+		expr.push_back(SYMBOL(name, span));
+		true
+	}
+
+	fn build_identifier_internal(&mut self, expr: &mut Expression) -> Result<bool, String> {
+		let mut safe_indexing = false;
+		self.current -= 1;
+		loop {
+			let t = self.advance();
+			match t.kind() {
+				IDENTIFIER => {
+					expr.push_back(SYMBOL(t.lexeme(), t.span()));
+					if self.check_val() {
+						break;
+					}
+				}
+				DOT | SAFE_DOT => {
+					safe_indexing |= self.build_safe_index(DOT, t.kind(), expr);
+					self.check_index(&t, expr, ".")?;
+				}
+				// `::name::` after an expression is a label, not a method
+				DOUBLE_COLON if self.peek(1).kind() == DOUBLE_COLON => break,
+				DOUBLE_COLON | SAFE_DOUBLE_COLON => {
+					safe_indexing |= self.build_safe_index(DOUBLE_COLON, t.kind(), expr);
+					self.check_index(&t, expr, ":")?;
+					match self.peek(1).kind() {
+						ROUND_BRACKET_OPEN => {}
+						SAFE_CALL => {
+							expr.pop_back();
+							let span = self.peek(0).span();
+							let line = span.start.line;
+							let name = if t.kind() == DOUBLE_COLON {
+								let mut start = {
+									let mut start = Expression::with_capacity(2);
+									if let Some(SYMBOL(lexeme, _)) = expr.get(1) {
+										if lexeme == " and " {
+											start.push_back(expr[0].clone());
+											start.push_back(expr[1].clone());
+										}
+									}
+									start
+								};
+								let mut expr_self = Expression::with_capacity(expr.len());
+								expr_self.append(expr);
+								let name = self.get_next_internal_var();
+								self.expr.push_back(VARIABLE {
+									local: true,
+									r#const: false,
+									names: vec![name.clone()],
+									values: vec![expr_self],
+									span: span.clone(),
+								});
+								expr.append(&mut start);
+								expr.push_back(SYMBOL(name.clone(), span.clone()));
+								name
+							} else {
+								let SYMBOL(ref name, _) = expr[0] else {
+									unreachable!();
+								};
+								name.to_owned()
+							};
+							expr.push_back(SYMBOL(String::from("."), span));
+							self.tokens
+								.insert(self.current + 2, Token::new(IDENTIFIER, name, line, 0));
+							if self.peek(3).kind() != ROUND_BRACKET_CLOSED {
+								self.tokens.insert(
+									self.current + 3,
+									Token::new(COMMA, String::from(","), line, 0),
+								);
+								self.size += 2;
+							} else {
+								self.size += 1;
+							}
+						}
+						_ => {
+							let t = self.peek(1);
+							return Err(self.expected("(", &t.lexeme(), t.line(), t.column()));
+						}
+					}
+				}
+				SQUARE_BRACKET_OPEN | SAFE_SQUARE_BRACKET => {
+					safe_indexing |= self.build_safe_index(SQUARE_BRACKET_OPEN, t.kind(), expr);
+					let start = self.peek(0).position();
+					let qexpr = self.build_expression(Some((SQUARE_BRACKET_CLOSED, "]")))?;
+					let qspan = start..self.look_back(1).end_position();
+					expr.push_back(SYMBOL(String::from("[("), t.span()));
+					expr.push_back(EXPR(qexpr, qspan));
+					expr.push_back(SYMBOL(String::from(")]"), self.look_back(0).span()));
+					if self.check_val() {
+						break;
+					}
+				}
+				ROUND_BRACKET_OPEN | SAFE_CALL => {
+					safe_indexing |= self.build_safe_index(ROUND_BRACKET_OPEN, t.kind(), expr);
+					let start = self.current;
+					let args = self.build_call(expr)?;
+					expr.push_back(CALL(args, self.span_from(t.position())));
+					self.check_pattern_argument(expr, start);
+
+					if self.check_val() {
+						break;
+					}
+				}
+				_ => break,
+			}
+		}
+		Ok(safe_indexing)
+	}
+
+	// warns about an invalid pattern literal passed to a pattern function of the string library,
+	// only when it's called as `string.name(...)` or as a method of a string literal,
+	// since any other function or method with the same name could take something else
+	fn check_pattern_argument(&self, expr: &Expression, start: usize) {
+		let len = expr.len();
+		let Some(SYMBOL(name, _)) = len.checked_sub(2).and_then(|i| expr.get(i)) else {
+			return;
+		};
+		if !PATTERN_FUNCTIONS.contains(&name.as_str()) {
+			return;
+		}
+		let arg = match (len.checked_sub(4).map(|i| &expr[i]), &expr[len - 3]) {
+			(Some(EXPR(receiver, _)), SYMBOL(index, _))
+				if index == ":" && is_string_literal(receiver) =>
+			{
+				0
+			}
+			(Some(SYMBOL(lib, _)), SYMBOL(index, _))
+				if len == 4 && lib == "string" && index == "." =>
+			{
+				1
+			}
+			_ => return,
+		};
+		let mut depth = 0u8;
+		let mut current = 0;
+		let mut found = Vec::new();
+		// `find` does a plain search, without patterns, when its 4th argument is true
+		let mut plain = Vec::new();
+		for token in &self.tokens[start..self.current - 1] {
+			match token.kind {
+				ROUND_BRACKET_OPEN | SAFE_CALL | SQUARE_BRACKET_OPEN | SAFE_SQUARE_BRACKET
+				| CURLY_BRACKET_OPEN => depth += 1,
+				ROUND_BRACKET_CLOSED | SQUARE_BRACKET_CLOSED | CURLY_BRACKET_CLOSED => {
+					depth = depth.saturating_sub(1)
+				}
+				COMMA if depth == 0 => current += 1,
+				_ if depth == 0 && current == arg => found.push(token),
+				_ if depth == 0 && current == arg + 2 => plain.push(token),
+				_ => {}
+			}
+		}
+		if name == "find" && matches!(plain[..], [token] if token.kind == TRUE) {
+			return;
+		}
+		if let [string] = found[..] {
+			if string.kind == STRING {
+				if let Err((msg, offset)) = check_pattern_literal(&string.lexeme) {
+					self.warning(
+						format_clue!("Invalid pattern: ", msg),
+						string.line,
+						string.column + offset,
+					);
+				}
+			}
+		}
+	}
+
+	fn get_code_block_start(&mut self) -> Result<usize, String> {
+		let t = self.advance();
+		if t.kind() != CURLY_BRACKET_OPEN {
+			self.current -= 2;
+			let t2 = self.advance();
+			if t2.kind() == CURLY_BRACKET_OPEN {
+				Ok(t2.line())
+			} else {
+				Err(self.expected("{", &t.lexeme(), t.line(), t.column()))
+			}
+		} else {
+			Ok(t.line())
+		}
+	}
+
+	fn parse_code_block(
+		&mut self,
+		mut tokens: Vec<Token>,
+		//locals: LocalsList,
+	) -> Result<Expression, String> {
+		if tokens.is_empty() {
+			Ok(Expression::new())
+		} else {
+			tokens.push(self.tokens.last().unwrap().clone());
+			let (ctokens, statics, errors) = parse_tokens_internal(
+				tokens,
+				self.filename,
+				self.remapping,
+				self.options,
+				self.recover,
+				self.declarations.clone(),
+				self.trace.clone(),
+			)?;
+			self.statics.extend(statics);
+			self.errors.extend(errors);
+			Ok(ctokens)
+		}
+	}
+
+	fn build_code_block(&mut self /* , locals: LocalsList */) -> Result<CodeBlock, String> {
+		self.traced("block", &self.peek(0), |i| {
+			let (start, tokens, end) = i.read_code_block()?;
+			let code = i.parse_code_block(tokens /* , locals */)?;
+			Ok(CodeBlock { start, code, end })
+		})
+	}
+
+	// returns the tokens inside the next code block and the lines where it starts and ends
+	fn read_code_block(&mut self) -> Result<(usize, Vec<Token>, usize), String> {
+		let start = self.get_code_block_start()?;
+		let mut tokens: Vec<Token> = Vec::new();
+		let mut cscope = 1u8;
+		let end: usize;
+		loop {
+			// the tokens are traced when the code block is parsed
+			self.current += 1;
+			let t = self.look_back(0);
+			match t.kind() {
+				CURLY_BRACKET_OPEN => cscope += 1,
+				CURLY_BRACKET_CLOSED => {
+					cscope -= 1;
+					if cscope == 0 {
+						end = t.line();
+						break;
+					}
+				}
+				EOF => return Err(self.expected_before("}", "<end>", t.line(), t.column())),
+				_ => {}
+			}
+			tokens.push(t.into_owned());
+		}
+		Ok((start, tokens, end))
+	}
+
+	// parses the code block of a `do` or `const` expression, whose value is the one of its last expression,
+	// returning the statements before the value, the value, where it starts and the static variables of the block
+	fn build_block_value(
+		&mut self,
+		keyword: &str,
+	) -> Result<(CodeBlock, Expression, TokenPosition, Expression), String> {
+		let (start, mut tokens, end) = self.read_code_block()?;
+		// the block ends at its `}`, so that's where the errors about its end are reported
+		let close = self.look_back(0);
+		tokens.push(Token::new(EOF, "<end>", close.line(), close.column()));
+		let mut i = ParserInfo::new(tokens, self.filename, self.remapping, self.options);
+		i.recover = self.recover;
+		i.trace = self.trace.clone();
+		i.internal_var_id = self.internal_var_id;
+		i.declarations = self.declarations.clone();
+		let (value, value_start) = loop {
+			let value_start = i.peek(0).position();
+			if let Some(value) = i.try_last_value() {
+				break (value, value_start);
+			}
+			let t = i.advance();
+			if !i.parse_statement(&t)? {
+				return Err(self.error(
+					format!("The last statement of a '{keyword}' block must be a value"),
+					t.line(),
+					t.column(),
+				));
+			}
+		};
+		self.internal_var_id = i.internal_var_id;
+		self.errors.extend(i.errors);
+		let code = CodeBlock {
+			start,
+			code: i.expr,
+			end,
+		};
+		Ok((code, value, value_start, i.statics))
+	}
+
+	// `do { ... }` runs the code in the block before the expression it's used in,
+	// and its value is the one of the last expression of the block
+	fn build_do_expression(&mut self) -> Result<ComplexToken, String> {
+		let first = self.look_back(0).position();
+		let (CodeBlock { start, code, end }, value, value_start, statics) =
+			self.build_block_value("do")?;
+		let span = self.span_from(first);
+		self.statics.extend(statics);
+		if code.is_empty() {
+			return Ok(EXPR(value, span));
+		}
+		let name = self.get_next_internal_var();
+		let mut code = code;
+		let value_span = value_start..self.look_back(1).end_position();
+		code.push_back(ALTER {
+			kind: DEFINE,
+			names: vec_deque![vec_deque![SYMBOL(name.clone(), value_span.clone())]],
+			values: vec![value],
+			span: value_span,
+		});
+		let prev_expr = self.get_prev_expr();
+		prev_expr.push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: Vec::new(),
+			span: span.clone(),
+		});
+		prev_expr.push_back(DO_BLOCK(CodeBlock { start, code, end }, span.clone()));
+		Ok(SYMBOL(name, span))
+	}
+
+	// `const { ... }` is a block like `do { ... }` meant to run while compiling, see CONST_BLOCK
+	fn build_const_block(&mut self) -> Result<ComplexToken, String> {
+		let first = self.look_back(0).position();
+		let (mut code, value, _, statics) = self.build_block_value("const")?;
+		let span = self.span_from(first);
+		for (i, r#static) in statics.into_iter().enumerate() {
+			code.code.insert(i, r#static);
+		}
+		let value_span = expression_span(&value);
+		code.code
+			.push_back(RETURN_EXPR(Some(vec![value]), value_span));
+		Ok(CONST_BLOCK { code, span })
+	}
+
+	// parses the rest of the code as an expression if it's only an expression,
+	// otherwise it leaves everything as it was
+	fn try_last_value(&mut self) -> Option<Expression> {
+		let (current, internal_var_id, recover) =
+			(self.current, self.internal_var_id, self.recover);
+		let stack = self.internal_stack.len();
+		self.recover = true;
+		let result = self.use_internal_stack(|i| i.build_expression(None));
+		self.recover = recover;
+		match result {
+			Ok((value, code)) if self.look_back(0).kind() == EOF => {
+				self.expr.extend(code);
+				Some(value)
+			}
+			_ => {
+				self.current = current;
+				self.internal_var_id = internal_var_id;
+				self.internal_stack.truncate(stack);
+				let t = self.peek(0);
+				self.trace('!', (t.line(), t.column()), || {
+					String::from("not a value, going back")
+				});
+				None
+			}
+		}
+	}
+
+	// `rest` is the name and the span of the rest parameter (`...rest`), if the function has one
+	fn build_function_block(
+		&mut self,
+		rest: Option<(String, Span)>,
+		//args: Option<Vec<(String, LuaType)>>,
+	) -> Result<CodeBlock, String> {
+		/*
+		if let Some(args) = args {
+			self.build_code_block({
+				let mut locals = self.locals.clone().unwrap();
+				for (name, luatype) in args {
+					locals.insert(name, luatype);
+				}
+				Some(locals)
+			})
+		} else {*/
+		let mut block = self.build_code_block(/*self.locals.clone()*/)?;
+		//}
+		if let Some((rest, span)) = rest {
+			self.add_rest_parameter(rest, span, &mut block);
+		}
+		Ok(block)
+	}
+
+	// builds the code block of `fn(...) => value`, which just returns the value
+	fn build_short_lambda(&mut self, rest: Option<(String, Span)>) -> Result<CodeBlock, String> {
+		let start = self.look_back(0).line();
+		let (value, mut code) = self.use_internal_stack(|i| i.build_expression(None))?;
+		self.current -= 1;
+		let span = expression_span(&value);
+		code.push_back(RETURN_EXPR(Some(vec![value]), span));
+
+		let mut block = CodeBlock {
+			start,
+			code,
+			end: self.look_back(0).line(),
+		};
+		if let Some((rest, span)) = rest {
+			self.add_rest_parameter(rest, span, &mut block);
+		}
+		Ok(block)
+	}
+
+	// the rest parameter is just `...` if it's only spread as the last argument of calls,
+	// otherwise it becomes a table of the extra arguments with their count in `n`
+	fn add_rest_parameter(&self, rest: String, span: Span, block: &mut CodeBlock) {
+		let unpack = self.unpack_function();
+		let mut uses = RestUses {
+			rest: &rest,
+			unpack,
+			spreads: 0,
+			total: 0,
+		};
+		uses.visit_code_block(block);
+		if uses.spreads == uses.total {
+			RestUses::spread_varargs(&rest, unpack, &mut block.code);
+			return;
+		}
+		let count = vec_deque![
+			SYMBOL(String::from("select"), span.clone()),
+			CALL(
+				vec![
+					vec_deque![SYMBOL(String::from("\"#\""), span.clone())],
+					vec_deque![SYMBOL(String::from("..."), span.clone())]
+				],
+				span.clone()
+			)
+		];
+		block.code.push_front(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![rest],
+			values: vec![vec_deque![TABLE {
+				values: vec![
+					(
+						Some(vec_deque![SYMBOL(String::from("n"), span.clone())]),
+						count,
+						span.clone()
+					),
+					(
+						None,
+						vec_deque![SYMBOL(String::from("..."), span.clone())],
+						span.clone()
+					),
+				],
+				metas: Vec::new(),
+				metatable: None,
+				span: span.clone(),
+			}]],
+			span,
+		});
+	}
+
+	fn build_loop_block(&mut self) -> Result<CodeBlock, String> {
+		let mut hascontinue: Option<String> = None;
+		let mut is_in_other_loop = false;
+		let start = self.get_code_block_start()?;
+		let open = self.look_back(0).position();
+		let mut tokens: Vec<Token> = Vec::new();
+		let mut cscope = 1u8;
+		let end: usize;
+		loop {
+			let t = self.advance();
+			match t.kind() {
+				CURLY_BRACKET_OPEN => cscope += 1,
+				CURLY_BRACKET_CLOSED => {
+					cscope -= 1;
+					is_in_other_loop = false;
+					if cscope == 0 {
+						end = t.line();
+						break;
+					}
+				}
+				FOR | WHILE | LOOP => is_in_other_loop = true,
+				// labeled continues are handled once the labeled loop is parsed
+				CONTINUE
+					if !is_in_other_loop
+						&& (self.peek(0).kind() != IDENTIFIER
+							|| self.peek(0).line() != t.line()) =>
+				{
+					if self.options.env_continue == ContinueMode::MoonScript {
+						let line = t.line();
+						tokens.push(Token::new(
+							IDENTIFIER,
+							{
+								if let Some(name) = hascontinue.as_ref() {
+									name.clone()
+								} else {
+									let name = self.get_next_internal_var();
+									hascontinue = Some(name.clone());
+									name
+								}
+							},
+							line,
+							0,
+						));
+						tokens.push(Token::new(DEFINE, "=", line, 0));
+						tokens.push(Token::new(TRUE, "true", line, 0));
+						tokens.push(Token::new(BREAK, "break", line, 0));
+						continue;
+					} else if hascontinue.is_none() {
+						hascontinue = Some(String::new())
+					}
+				}
+				EOF => return Err(self.expected_before("}", "<end>", t.line(), t.column())),
+				_ => {}
+			}
+			tokens.push(t.into_owned());
+		}
+		let span = self.span_from(open);
+		let mut code = self.parse_code_block(tokens /* , self.locals.clone() */)?;
+		if let Some(name) = hascontinue {
+			use ContinueMode::*;
+			match self.options.env_continue {
+				Simple => {}
+				Goto | LuaJIT => code.push_back(SYMBOL(String::from("::continue::"), span.clone())),
+				MoonScript => {
+					code.push_back(ALTER {
+						kind: DEFINE,
+						names: vec_deque![vec_deque![SYMBOL(name.clone(), span.clone())]],
+						values: vec![vec_deque![SYMBOL(String::from("true"), span.clone())]],
+						span: span.clone(),
+					});
+					code = vec_deque![
+						VARIABLE {
+							local: true,
+							r#const: false,
+							names: vec![name.clone()],
+							values: vec![vec_deque![SYMBOL(String::from("false"), span.clone())]],
+							span: span.clone()
+						},
+						LOOP_UNTIL {
+							condition: vec_deque![SYMBOL(String::from("true"), span.clone())],
+							code: CodeBlock { start, code, end },
+							label: None,
+							span: span.clone()
+						},
+						IF_STATEMENT {
+							condition: vec_deque![
+								SYMBOL(String::from("not "), span.clone()),
+								SYMBOL(name, span.clone())
+							],
+							code: CodeBlock {
+								start: end,
+								code: vec_deque![BREAK_LOOP(None, span.clone())],
+								end
+							},
+							next: None,
+							span
+						}
+					]
+				}
+			}
+		}
+		Ok(CodeBlock { start, code, end })
+	}
+
+	fn build_identifier_list(&mut self) -> Result<Vec<String>, String> {
+		let mut idents: Vec<String> = Vec::new();
+		loop {
+			let t = self.assert_advance(IDENTIFIER, "<name>")?;
+			idents.push(t.lexeme());
+			if !self.advance_if(COMMA) {
+				break Ok(idents);
+			}
+		}
+	}
+
+	// also returns the name and the span of the rest parameter (`...rest`), if there is one
+	fn build_function_args(
+		&mut self,
+	) -> Result</* ArgsAndTypes */ (FunctionArgs, Option<(String, Span)>), String> {
+		let mut args = FunctionArgs::new();
+		let mut rest = None;
+		/*let mut types: Option<Vec<(String, LuaType)>> = if self.locals.is_some() {
+			Some(Vec::new())
+		} else {
+			None
+		};*/
+		while {
+			let name = {
+				let t = self.advance();
+				match t.kind() {
+					IDENTIFIER => t,
+					THREEDOTS => {
+						if self.peek(0).kind() == IDENTIFIER {
+							let name = self.advance();
+							rest = Some((name.lexeme(), t.position()..name.end_position()));
+						}
+						self.assert_compare(ROUND_BRACKET_CLOSED, ")")?;
+						t
+					}
+					_ => return Err(self.expected("<name>", &t.lexeme(), t.line(), t.column())),
+				}
+			};
+			/*if let Some(types) = &mut types {
+				types.push((
+					name.lexeme(),
+					if name.kind() == THREEDOTS {
+						LuaType::ANY
+					} else {
+						self.build_type()?
+					},
+				))
+			}*/
+			let t = self.advance();
+			match t.kind() {
+				COMMA => {
+					args.push((name.lexeme(), None));
+					true
+				}
+				DEFINE => {
+					let default = self.build_expression(None)?;
+					args.push((name.lexeme(), Some((default, name.line()))));
+					let notended = self.peek(0).kind() != CURLY_BRACKET_OPEN;
+					if notended {
+						match self.look_back(0).kind() {
+							COMMA => {}
+							ROUND_BRACKET_CLOSED => self.current -= 1,
+							_ => {
+								let t = self.peek(0);
+								return Err(self.expected(")", &t.lexeme(), t.line(), t.column()));
+							}
+						}
+					}
+					notended
+				}
+				ROUND_BRACKET_CLOSED => {
+					args.push((name.lexeme(), None));
+					false
+				}
+				_ => return Err(self.expected(")", &t.lexeme(), t.line(), t.column())),
+			}
+		} {}
+		Ok(/* (args, types) */ (args, rest))
+	}
+
+	fn build_elseif_chain(
+		&mut self,
+		condition: Option<Expression>,
+	) -> Result<ComplexToken, String> {
+		let keyword = self.look_back(0).position();
+		let condition = match condition {
+			Some(condition) => condition,
+			None => {
+				if self.advance_if(LOCAL) {
+					let start = self.look_back(0).position();
+					let destructure = self.advance_if(CURLY_BRACKET_OPEN);
+					let (vars, mut code) = self.use_internal_stack(|i| {
+						i.build_variables(true, false, start, destructure)
+					})?;
+					let (condition, end) = {
+						let VARIABLE { names, span, .. } = &vars else {
+							unreachable!()
+						};
+						let mut condition = Expression::with_capacity(names.len());
+						let mut names = names.iter();
+						let first = names.next().unwrap();
+						condition.push_back(SYMBOL(format_clue!(first, " ~= nil"), span.clone()));
+						for name in names {
+							let check = format_clue!(" and ", name, " ~= nil");
+							condition.push_back(SYMBOL(check, span.clone()))
+						}
+						(condition, span.end.line)
+					};
+					code.push_back(vars);
+					code.push_back(self.build_elseif_chain(Some(condition))?);
+					return Ok(DO_BLOCK(
+						CodeBlock {
+							start: start.line,
+							code,
+							end,
+						},
+						self.span_from(keyword),
+					));
+				}
+				self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?
+			}
+		};
+		let code = self.build_code_block(/*self.locals.clone()*/)?;
+		let span = self.span_from(keyword);
+		Ok(IF_STATEMENT {
+			condition,
+			code,
+			span,
+			next: {
+				let t = self.advance();
+				match self.else_kind(&t) {
+					ELSEIF => {
+						// the code the condition needs only runs if the previous conditions were false
+						let (next, mut code) =
+							self.use_internal_stack(|i| i.build_elseif_chain(None))?;
+						Some(Box::new(if code.is_empty() {
+							next
+						} else {
+							code.push_back(next);
+							DO_BLOCK(
+								CodeBlock {
+									start: t.line(),
+									code,
+									end: self.look_back(0).line(),
+								},
+								self.span_from(t.position()),
+							)
+						}))
+					}
+					ELSE => Some(Box::new(DO_BLOCK(
+						self.build_code_block(/*self.locals.clone()*/)?,
+						self.span_from(t.position()),
+					))),
+
+					_ => {
+						self.current -= 1;
+						None
+					}
+				}
+			},
+		})
+	}
+
+	// `else if` is read like `elseif`
+	fn else_kind(&mut self, t: &BorrowedToken) -> TokenType {
+		match t.kind() {
+			ELSE if self.advance_if(IF) => ELSEIF,
+			kind => kind,
+		}
+	}
+
+	fn build_enums(&mut self, local: bool) -> Result<Expression, String> {
+		self.current += 1;
+		let name = match self.peek(0).kind() {
+			IDENTIFIER => Some(self.advance()),
+			_ => None,
+		};
+		self.assert(CURLY_BRACKET_OPEN, "{")?;
+		let mut members: Vec<(String, Value, Span)> = Vec::new();
+		loop {
+			if self.advance_if(CURLY_BRACKET_CLOSED) {
+				break;
+			}
+			let member = self.assert_advance(IDENTIFIER, "<name>")?;
+			let t = self.advance();
+			let mut end = member.end_position();
+			let value = match t.kind() {
+				CURLY_BRACKET_CLOSED | COMMA => {
+					if t.kind() == CURLY_BRACKET_CLOSED {
+						self.current -= 1;
+					}
+					match members.last() {
+						None => Value::Integer(1),
+						Some((_, Value::Integer(n), _)) => Value::Integer(n + 1),
+						Some((previous, ..)) => {
+							return Err(self.error(
+								format!(
+									"'{}' needs a value, since the value of '{previous}' is not a number",
+									member.lexeme()
+								),
+								member.line(),
+								member.column(),
+							))
+						}
+					}
+				}
+				DEFINE => {
+					let mut expr = self.build_expression(None)?;
+					self.current -= 1;
+					end = self.look_back(0).end_position();
+					self.advance_if(COMMA);
+					let known = |name: &str| {
+						members
+							.iter()
+							.find(|(member, ..)| member == name)
+							.map(|(_, value, _)| value.clone())
+					};
+					match evaluate(&mut expr, &known) {
+						Some(value @ (Value::Integer(_) | Value::String(..))) => value,
+						_ => {
+							return Err(self.error(
+								"Enums values should be integers or strings that can be computed at compile time",
+								t.line(),
+								t.column(),
+							))
+						}
+					}
+				}
+				_ => return Err(self.expected("}", &t.lexeme(), t.line(), t.column())),
+			};
+			if let Some((other, ..)) = members
+				.iter()
+				.find(|(_, other, _)| other.equals(&value) == Some(true))
+			{
+				self.warning(
+					format!("'{}' has the same value as '{other}'", member.lexeme()),
+					member.line(),
+					member.column(),
+				);
+			}
+			members.push((member.lexeme(), value, member.position()..end));
+		}
+		/*if let Some(locals) = &mut self.locals {
+			for r#enum in &enums {
+				if let VARIABLE { names, .. } = r#enum {
+					locals.insert(names[0].clone(), LuaType::NUMBER);
+				}
+			}
+		}*/
+		let mode = self.enum_mode.take().unwrap_or(self.options.env_enums);
+		if let (Some(name), EnumMode::Inline) = (&name, mode) {
+			let values = members
+				.iter()
+				.map(|(member, value, _)| (member.clone(), value.to_symbol()))
+				.collect();
+			self.declarations.enums.push((name.lexeme(), values));
+			// other files could read the table of a global enum, so only that one is kept
+			if local {
+				return Ok(Expression::new());
+			}
+		}
+		let reverse = if mode == EnumMode::Reverse {
+			reverse_enum_members(&members)
+		} else {
+			Vec::new()
+		};
+		let members = members.into_iter().map(|(name, value, span)| {
+			let value = vec_deque![SYMBOL(value.to_symbol(), span.clone())];
+			(name, value, span)
+		});
+		Ok(match name {
+			// `enum Name { ... }` puts its members in the table `Name`
+			Some(name) => {
+				let span = self.span_from(name.position());
+				let annotation = format_clue!("enum ", name.lexeme());
+				let mut r#enum = self.annotations([annotation], &span);
+				r#enum.push_back(VARIABLE {
+					span: span.clone(),
+					local,
+					r#const: false,
+					names: vec![name.lexeme()],
+					values: vec![vec_deque![TABLE {
+						values: members
+							.map(|(member, value, span)| {
+								(Some(vec_deque![SYMBOL(member, span.clone())]), value, span)
+							})
+							.chain(reverse)
+							.collect(),
+						metas: Vec::new(),
+						metatable: None,
+						span,
+					}]],
+				});
+
+				r#enum
+			}
+			None => members
+				.map(|(member, value, span)| VARIABLE {
+					span,
+					local,
+					r#const: false,
+					names: vec![member],
+					values: vec![value],
+				})
+				.collect(),
+		})
+	}
+
+	// `struct Name { ... }` becomes a table used as the metatable of its instances,
+	// with a `new` function which creates an instance with the default values of the fields,
+	// runs the constructor on it and returns it.
+	// `struct Name with Mixin1, Mixin2 { ... }` also copies the methods of the given structs into it,
+	// while the fields of the mixins which are tables are copied at runtime after everything else,
+	// so they never replace a method of the struct, of its struct mixins or a field of the tables before them
+	fn build_struct(&mut self, local: bool) -> Result<Expression, String> {
+		self.current += 1;
+		let t = self.assert_advance(IDENTIFIER, "<name>")?;
+		let name = t.lexeme();
+		let mut parents = Vec::new();
+		let mut mixins = Vec::new();
+		let mut tables = Vec::new();
+		if self.advance_if(WITH) {
+			loop {
+				let mixin = self.assert_advance(IDENTIFIER, "<name>")?;
+				match self
+					.declarations
+					.structs
+					.iter()
+					.find(|(name, _)| *name == mixin.lexeme())
+				{
+					Some(r#struct) => mixins.push(r#struct.clone()),
+					// Teal records can't be indexed with the keys of another table
+					None if self.options.env_language == Language::Teal => {
+						return Err(self.error(
+							format!(
+								"'{}' is not a struct declared before, Teal structs can only mix in structs",
+								mixin.lexeme()
+							),
+							mixin.line(),
+							mixin.column(),
+						));
+					}
+					None => tables.push(mixin.lexeme()),
+				}
+				parents.push(mixin.lexeme());
+				if !self.advance_if(COMMA) {
+					break;
+				}
+			}
+		}
+		self.assert(CURLY_BRACKET_OPEN, "{")?;
+		let mut fields: Vec<String> = Vec::new();
+		let mut field_types = Vec::new();
+		let mut methods: Vec<String> = Vec::new();
+		let mut defaults = Vec::new();
+		let mut code = Expression::new();
+		let mut constructor = None;
+		let mut functions = Expression::new();
+		loop {
+			let member = self.advance();
+			match member.kind() {
+				CURLY_BRACKET_CLOSED => break,
+				IDENTIFIER => {
+					let field = member.lexeme();
+					if fields.contains(&field) {
+						return Err(self.error(
+							format!("'{field}' is declared twice in struct '{name}'"),
+							member.line(),
+							member.column(),
+						));
+					}
+					let mut field_type = "any";
+					if self.advance_if(DEFINE) {
+						let (value, mut value_code) =
+							self.use_internal_stack(|i| i.build_expression(None))?;
+						self.current -= 1;
+						code.append(&mut value_code);
+						field_type = annotation_type(&value);
+						defaults.push((
+							Some(vec_deque![SYMBOL(field.clone(), member.span())]),
+							value,
+							self.span_from(member.position()),
+						));
+					}
+					self.advance_if(COMMA);
+					field_types.push(format!("field {field} {field_type}"));
+					fields.push(field);
+				}
+				CONSTRUCTOR => {
+					if constructor.is_some() {
+						return Err(self.error(
+							format!("Struct '{name}' can only have one constructor"),
+							member.line(),
+							member.column(),
+						));
+					}
+					constructor = Some(self.build_struct_function(format_clue!(name, ".new"))?);
+				}
+				FN | METHOD => {
+					let function_token = self.assert_advance(IDENTIFIER, "<name>")?;
+					let function = function_token.lexeme();
+					let index = if member.kind() == METHOD {
+						methods.push(function.clone());
+						":"
+					} else {
+						"."
+					};
+					let (args, code) =
+						self.build_struct_function(format_clue!(name, index, function))?;
+					let span = self.span_from(member.position());
+					functions.extend(self.annotations(param_annotations(&args), &span));
+					functions.push_back(FUNCTION {
+						local: false,
+						name: vec_deque![
+							SYMBOL(name.clone(), t.span()),
+							SYMBOL(String::from(index), function_token.span()),
+							SYMBOL(function, function_token.span())
+						],
+						args,
+						code,
+						attributes: FunctionAttributes::new(),
+						span,
+					});
+				}
+				_ => {
+					return Err(self.expected(
+						"}",
+						&member.lexeme(),
+						member.line(),
+						member.column(),
+					))
+				}
+			}
+		}
+		let span = self.span_from(t.position());
+		// the methods defined by the struct itself replace the ones of its mixins,
+		// so two mixins can only have a method with the same name if the struct defines it too
+		let mut inherited: Vec<(String, String)> = Vec::new();
+		for (mixin, mixin_methods) in mixins {
+			for method in mixin_methods {
+				if methods.contains(&method) {
+					continue;
+				}
+				match inherited.iter().find(|(other, _)| *other == method) {
+					Some((_, other_mixin)) if *other_mixin != mixin => {
+						return Err(self.error(
+							format!(
+								"'{method}' is defined by both '{other_mixin}' and '{mixin}', struct '{name}' must define it itself"
+							),
+							t.line(),
+							t.column(),
+						))
+					}
+					Some(_) => {}
+					None => inherited.push((method, mixin.clone())),
+				}
+			}
+		}
+		methods.extend(inherited.iter().map(|(method, _)| method.clone()));
+		self.declarations.structs.push((name.clone(), methods));
+		// Teal only lets the functions declared in the same scope be added to a record,
+		// so the copies of the methods of the mixins are declared as fields and cast to their type,
+		// and the record is cast to the metatable of its instances
+		let teal = self.options.env_language == Language::Teal;
+		let method_type = format_clue!("function(", name, ", ...: any): any...");
+		if teal {
+			field_types.extend(
+				inherited
+					.iter()
+					.map(|(method, _)| format_clue!("field ", method, " ", method_type)),
+			);
+		}
+		let cast = |mut value: Expression, luatype: &str| {
+			if teal {
+				value.push_back(SYMBOL(format_clue!(" as ", luatype), span.clone()));
+			}
+			value
+		};
+		let index = |table: &String, method: String| {
+			vec_deque![
+				SYMBOL(table.clone(), span.clone()),
+				SYMBOL(String::from("."), span.clone()),
+				SYMBOL(method, span.clone())
+			]
+		};
+		let mut copies: Expression = inherited
+			.into_iter()
+			.map(|(method, mixin)| ALTER {
+				kind: DEFINE,
+				names: vec_deque![index(&name, method.clone())],
+				values: vec![cast(index(&mixin, method), &method_type)],
+				span: span.clone(),
+			})
+			.collect();
+		if constructor.is_none() {
+			self.declare_function(format_clue!(name, ".new"), &FunctionArgs::new());
+		}
+		let (args, mut new) = constructor.unwrap_or_else(|| {
+			(
+				FunctionArgs::new(),
+				CodeBlock {
+					start: span.start.line,
+					code: Expression::new(),
+					end: span.end.line,
+				},
+			)
+		});
+		let instance = IDENT {
+			expr: vec_deque![
+				SYMBOL(String::from("setmetatable"), span.clone()),
+				CALL(
+					vec![
+						vec_deque![TABLE {
+							values: defaults,
+							metas: Vec::new(),
+							metatable: None,
+							span: span.clone(),
+						}],
+						cast(
+							vec_deque![SYMBOL(name.clone(), span.clone())],
+							&format_clue!("metatable<", name, ">"),
+						),
+					],
+					span.clone()
+				)
+			],
+			span: span.clone(),
+		};
+		code.extend(self.annotations([format_clue!("type ", name)], &span));
+		code.push_back(VARIABLE {
+			span: span.clone(),
+			local: true,
+			r#const: false,
+			names: vec![String::from("self")],
+			values: vec![vec_deque![instance]],
+		});
+		code.append(&mut new.code);
+		code.push_back(RETURN_EXPR(
+			Some(vec![vec_deque![SYMBOL(String::from("self"), span.clone())]]),
+			span.clone(),
+		));
+		new.code = code;
+		let class = match parents.is_empty() {
+			true => format_clue!("class ", name),
+			false => format_clue!("class ", name, " : ", parents.join(", ")),
+		};
+		let mut r#struct = self.annotations(once(class).chain(field_types), &span);
+		let new_annotations = param_annotations(&args)
+			.into_iter()
+			.chain(once(format_clue!("return ", name)));
+		let new_annotations = self.annotations(new_annotations, &span);
+		r#struct.extend([
+			VARIABLE {
+				span: span.clone(),
+				local,
+				r#const: false,
+				names: vec![name.clone()],
+				values: vec![vec_deque![TABLE {
+					values: Vec::new(),
+					metas: Vec::new(),
+					metatable: None,
+					span: span.clone(),
+				}]],
+			},
+			ALTER {
+				kind: DEFINE,
+				names: vec_deque![index(&name, String::from("__index"))],
+				values: vec![vec_deque![SYMBOL(name.clone(), span.clone())]],
+				span: span.clone(),
+			},
+		]);
+		r#struct.extend(new_annotations);
+		r#struct.push_back(FUNCTION {
+			local: false,
+			name: index(&name, String::from("new")),
+			args,
+			code: new,
+			attributes: FunctionAttributes::new(),
+			span: span.clone(),
+		});
+		r#struct.append(&mut copies);
+		r#struct.append(&mut functions);
+		for table in tables {
+			let (k, v) = (self.get_next_internal_var(), self.get_next_internal_var());
+			let line = span.end.line;
+			r#struct.push_back(FOR_FUNC_LOOP {
+				label: None,
+				iterators: vec![k.clone(), v.clone()],
+				expr: vec_deque![
+					SYMBOL(String::from("pairs"), span.clone()),
+					CALL(vec![vec_deque![SYMBOL(table, span.clone())]], span.clone())
+				],
+				code: CodeBlock {
+					start: line,
+					code: vec_deque![IF_STATEMENT {
+						condition: vec_deque![SYMBOL(format!("{name}[{k}] == nil"), span.clone())],
+						code: CodeBlock {
+							start: line,
+							code: vec_deque![ALTER {
+								kind: DEFINE,
+								names: vec_deque![vec_deque![SYMBOL(
+									format!("{name}[{k}]"),
+									span.clone()
+								)]],
+								values: vec![vec_deque![SYMBOL(v, span.clone())]],
+								span: span.clone(),
+							}],
+							end: line,
+						},
+						next: None,
+						span: span.clone(),
+					}],
+					end: line,
+				},
+				span: span.clone(),
+			});
+		}
+		Ok(r#struct)
+	}
+
+	// builds the arguments and the code of the constructor or of a function of a struct,
+	// `name` is how the function is called from outside the struct
+	fn build_struct_function(&mut self, name: String) -> Result<(FunctionArgs, CodeBlock), String> {
+		self.assert(ROUND_BRACKET_OPEN, "(")?;
+		let (args, rest) = if !self.advance_if(ROUND_BRACKET_CLOSED) {
+			self.build_function_args()?
+		} else {
+			(FunctionArgs::new(), None)
+		};
+		self.declare_function(name, &args);
+		Ok((args, self.build_function_block(rest)?))
+	}
+
+	fn declare_function(&mut self, name: String, args: &FunctionArgs) {
+		let parameters = args.iter().map(|(arg, _)| arg.clone()).collect();
+		self.declarations.functions.push((name, parameters));
+	}
+
+	// `start` is where the declaration starts, at `local` or `global` if it has one
+	fn build_function(
+		&mut self,
+		local: bool,
+		start: TokenPosition,
+	) -> Result<ComplexToken, String> {
+		self.current += 1;
+		let t = self.assert_advance(IDENTIFIER, "<name>")?;
+		let name = vec_deque![SYMBOL(t.lexeme(), t.span())];
+		self.assert(ROUND_BRACKET_OPEN, "(")?;
+		let (args, rest /*, types*/) = if !self.advance_if(ROUND_BRACKET_CLOSED) {
+			self.build_function_args()?
+		} else {
+			(FunctionArgs::new(), None /*, None*/)
+		};
+		self.declare_function(t.lexeme(), &args);
+		let code = self.build_function_block(rest /*, types*/)?;
+		/*if self.locals.is_some() {
+			self.add_variable(t.lexeme(), LuaType::NIL);
+		}*/
+		Ok(FUNCTION {
+			local,
+			name,
+			args,
+			code,
+			attributes: FunctionAttributes::new(),
+			span: self.span_from(start),
+		})
+	}
+	/*
+		fn add_variable(&mut self, name: String, luatype: LuaType) {
+			if let Some(locals) = &mut self.locals {
+				locals.insert(name, luatype);
+			}
+		}
+
+		fn build_type(&mut self) -> Result<LuaType, String> {
+			if self.advance_if(COLON) {
+				Ok(LuaType::ANY) //PLACEHOLDER
+			} else {
+				Ok(LuaType::NIL)
+			}
+		}
+	*/
+	/*fn build_variable(&mut self) -> Result</*(*/ String /*, LuaType)*/, String> {
+		let name = self.assert_advance(IDENTIFIER, "<name>")?.lexeme();
+		/*if self.locals.is_some() {
+			let luatype = self.build_type()?;
+			self.add_variable(name.to_string(), luatype.clone());
+			Ok((name, luatype))
+		} else {*/
+		Ok(/*(*/ name /*, LuaType::ANY)*/)
+		//}
+	}*/
+
+	#[allow(clippy::type_complexity)]
+	fn build_destructure_table(&mut self) -> Result<DestructuredTable, String> {
+		let mut names = Vec::new();
+		let mut key_names = Vec::new();
+		let mut defaults = Vec::new();
+		let name = self.get_next_internal_var();
+		let mut internal_names = vec![name.clone()];
+		self.build_destructure_table_internal(
+			&mut names,
+			&mut key_names,
+			&mut internal_names,
+			&mut defaults,
+			name + ".",
+		)?;
+		Ok((names, key_names, internal_names, defaults))
+	}
+
+	// `key = name` and `key => name` read `key` into `name`, `key => { ... }` destructures it,
+	// then `= value` sets a default value, which must be in brackets when it's just a name
+	// since `key = value` would rename `key`
+	fn build_destructure_table_internal(
+		&mut self,
+		names: &mut Vec<String>,
+		key_names: &mut Vec<String>,
+		internal_names: &mut Vec<String>,
+		defaults: &mut DestructuringDefaults,
+		key_start: String,
+	) -> Result<(), String> {
+		loop {
+			let t = self.assert_advance(IDENTIFIER, "<name>")?;
+			let name = if self.peek(0).kind() == DEFINE
+				&& self.peek(1).kind() == IDENTIFIER
+				&& matches!(self.peek(2).kind(), COMMA | CURLY_BRACKET_CLOSED | DEFINE)
+			{
+				self.current += 1;
+				self.advance().lexeme()
+			} else if self.advance_if(ARROW) {
+				if self.advance_if(CURLY_BRACKET_OPEN) {
+					let name = self.get_next_internal_var();
+					internal_names.push(format_clue!(key_start, t.lexeme()));
+					internal_names.push(name.clone());
+					self.build_destructure_table_internal(
+						names,
+						key_names,
+						internal_names,
+						defaults,
+						name + ".",
+					)?;
+					if self.advance_if(COMMA) {
+						continue;
+					} else {
+						self.assert_advance(CURLY_BRACKET_CLOSED, "}")?;
+						break Ok(());
+					}
+				} else {
+					self.assert_advance(IDENTIFIER, "<name>")?.lexeme()
+				}
+			} else {
+				t.lexeme()
+			};
+			names.push(name);
+			if self.advance_if(DEFINE) {
+				let (default, code) = self.use_internal_stack(|i| i.build_expression(None))?;
+				self.current -= 1;
+				defaults.push((key_names.len(), default, code));
+			}
+			key_names.push(format_clue!(key_start, t.lexeme()));
+			if !self.advance_if(COMMA) {
+				self.assert_advance(CURLY_BRACKET_CLOSED, "}")?;
+				break Ok(());
+			}
+		}
+	}
+
+	fn build_table_destructuring(
+		&mut self,
+		(key_names, internal_names, defaults): (Vec<String>, Vec<String>, DestructuringDefaults),
+		values: Vec<Expression>,
+		span: Span,
+	) -> Vec<Expression> {
+		let line = span.start.line;
+		let prev_expr = self.get_prev_expr();
+		let mut names = internal_names.into_iter();
+		prev_expr.push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![names.next().unwrap()],
+			values,
+			span: span.clone(),
+		});
+		while let (Some(prev_name), Some(name)) = (names.next(), names.next()) {
+			prev_expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: vec![name.clone()],
+				values: vec![vec_deque![SYMBOL(prev_name, span.clone())]],
+				span: span.clone(),
+			});
+		}
+		let mut values: Vec<Expression> = key_names
+			.into_iter()
+			.map(|key_name| vec_deque![SYMBOL(key_name, span.clone())])
+			.collect();
+		for (i, default, mut code) in defaults {
+			let name = self.get_next_internal_var();
+			let value = std::mem::replace(
+				&mut values[i],
+				vec_deque![SYMBOL(name.clone(), span.clone())],
+			);
+			let prev_expr = self.get_prev_expr();
+			prev_expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: vec![name.clone()],
+				values: vec![value],
+				span: span.clone(),
+			});
+			let name = SYMBOL(name, span.clone());
+			code.push_back(ALTER {
+				kind: DEFINE,
+				names: vec_deque![vec_deque![name.clone()]],
+				values: vec![default],
+				span: span.clone(),
+			});
+			prev_expr.push_back(IF_STATEMENT {
+				condition: vec_deque![name, SYMBOL(String::from(" == nil"), span.clone())],
+				code: CodeBlock {
+					start: line,
+					code,
+					end: line,
+				},
+				next: None,
+				span: span.clone(),
+			});
+		}
+		values
+	}
+
+	fn build_variables(
+		&mut self,
+		local: bool,
+		r#const: bool,
+		start: TokenPosition,
+		destructure: bool,
+	) -> Result<ComplexToken, String> {
+		let (names, destructure) = if destructure {
+			let (names, key_names, internal_names, defaults) = self.build_destructure_table()?;
+			(names, Some((key_names, internal_names, defaults)))
+		} else {
+			(self.build_identifier_list()?, None)
+		};
+		let checkt = self.advance();
+		let check = checkt.kind();
+		let mut values: Vec<Expression> = if check != DEFINE {
+			if check == SEMICOLON {
+				self.current += 1;
+			}
+
+			if local {
+				Vec::new()
+			} else {
+				/*if let Some(_locals) = &self.locals {
+					//println!("{:?}", locals);
+				} else {
+					self.warning("Defining external globals will not do anything if you don't have type checking enabled!", line)
+				}*/
+				self.current -= 1;
+				return Ok(SYMBOL(String::new(), self.span_from(start)));
+			}
+		} else {
+			let values = self.find_expressions(None)?;
+			if destructure.is_none() {
+				self.check_assignment(&checkt, names.len(), &values)?;
+			}
+			values
+		};
+		self.current -= 1;
+		let span = self.span_from(start);
+		if let Some(destructure) = destructure {
+			values = self.build_table_destructuring(destructure, values, span.clone());
+		}
+		Ok(VARIABLE {
+			local,
+			r#const,
+			names,
+			values,
+			span,
+		})
+	}
+
+	fn build_match_block(
+		&mut self,
+		name: String,
+		func: &impl Fn(&mut ParserInfo<'a> /* , LocalsList */) -> Result<CodeBlock, String>,
+	) -> Result<ComplexToken, String> {
+		let start = self.look_back(0).position();
+		let value = self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?;
+		let mut branches: Vec<MatchCase> = Vec::new();
+		while {
+			if self.advance_if(DEFAULT) {
+				let t = self.advance();
+				match t.kind() {
+					ARROW => {
+						if branches.is_empty() {
+							return Err(self.error(
+								"The default case (with no extra if) of a match block must be the last case, not the first",
+								t.line(),
+								t.column()
+							));
+						}
+						branches.push((
+							Vec::new(),
+							Expression::new(),
+							None,
+							func(self /* , self.locals.clone() */)?,
+						));
+						self.assert(CURLY_BRACKET_CLOSED, "}")?;
+						false
+					}
+					IF => {
+						let (extra_if, internal_expr) =
+							self.use_internal_stack(|i| i.build_expression(Some((ARROW, "=>"))))?;
+						branches.push((
+							Vec::new(),
+							internal_expr,
+							Some(extra_if),
+							func(self /* , self.locals.clone() */)?,
+						));
+						!self.advance_if(CURLY_BRACKET_CLOSED)
+					}
+					_ => return Err(self.expected("=>", &t.lexeme(), t.line(), t.column())),
+				}
+			} else if self.advance_if(CURLY_BRACKET_OPEN) {
+				let (extra_if, internal_expr) =
+					self.use_internal_stack(|i| i.build_destructuring_case(&name))?;
+				branches.push((
+					Vec::new(),
+					internal_expr,
+					Some(extra_if),
+					func(self /* , self.locals.clone() */)?,
+				));
+				!self.advance_if(CURLY_BRACKET_CLOSED)
+			} else if self.peek(0).kind() == IDENTIFIER && self.peek(1).kind() == IF {
+				// `x if x > 10 => ...` binds the value to `x` before checking the guard
+				let t = self.advance();
+				self.current += 1;
+				let (extra_if, internal_expr) = self.use_internal_stack(|i| {
+					i.get_prev_expr().push_back(VARIABLE {
+						local: true,
+						r#const: false,
+						names: vec![t.lexeme()],
+						values: vec![vec_deque![SYMBOL(name.clone(), t.span())]],
+						span: t.span(),
+					});
+					i.build_expression(Some((ARROW, "=>")))
+				})?;
+				branches.push((
+					Vec::new(),
+					internal_expr,
+					Some(extra_if),
+					func(self /* , self.locals.clone() */)?,
+				));
+				!self.advance_if(CURLY_BRACKET_CLOSED)
+			} else {
+				let ((expr, extra_if), internal_expr) = self.use_internal_stack(|i| {
+					let expr = i.build_expression(None)?;
+					let t = i.look_back(0);
+					let extra_if = match t.kind() {
+						ARROW => None,
+						IF => Some(i.build_expression(Some((ARROW, "=>")))?),
+						_ => return Err(i.expected("=>", &t.lexeme(), t.line(), t.column())),
+					};
+					Ok((expr, extra_if))
+				})?;
+				let mut conditions: Vec<Expression> = Vec::new();
+				let mut current = Expression::with_capacity(3);
+				for ctoken in expr {
+					match ctoken {
+						SYMBOL(lexeme, _) if lexeme == " or " => {
+							conditions.push(current.clone());
+							current.clear();
+						}
+						_ => current.push_back(ctoken),
+					}
+				}
+				if !current.is_empty() {
+					conditions.push(current);
+				}
+				branches.push((
+					conditions,
+					internal_expr,
+					extra_if,
+					func(self /* , self.locals.clone() */)?,
+				));
+				!self.advance_if(CURLY_BRACKET_CLOSED)
+			}
+		} {}
+		Ok(MATCH_BLOCK {
+			name,
+			value,
+			branches,
+			span: self.span_from(start),
+		})
+	}
+
+	// `match` in an expression is an `and`/`or` chain when the value is just a name and every case
+	// compares it with constants to give a value which is never false or nil, otherwise the match
+	// block assigns the result to an internal variable before the expression,
+	// or inside a function called by the expression if the match may not be evaluated
+	fn build_match_expression(&mut self, lazy: bool) -> Result<Expression, String> {
+		let start = self.look_back(0).line();
+		let name = self.get_next_internal_var();
+		let ident = name.clone();
+		let (mut ctoken, mut code) = self.use_internal_stack(|i| {
+			i.build_match_block(name, &|i /* , _ */| {
+				let first = i.peek(0).position();
+				let (expr, mut code) = i.use_internal_stack(|i| i.build_expression(None))?;
+				let last = i.look_back(1);
+				if matches!(i.look_back(0).kind(), CURLY_BRACKET_CLOSED | DEFAULT) {
+					i.current -= 1
+				}
+				let span = first..last.end_position();
+				code.push_back(ALTER {
+					kind: DEFINE,
+					names: vec_deque![vec_deque![SYMBOL(ident.clone(), span.clone())]],
+					values: vec![expr],
+					span,
+				});
+				Ok(CodeBlock {
+					start: first.line,
+					code,
+					end: last.line(),
+				})
+			})
+		})?;
+		let MATCH_BLOCK {
+			value,
+			branches,
+			span,
+			..
+		} = &mut ctoken
+		else {
+			unreachable!()
+		};
+		let span = span.clone();
+		let ident = SYMBOL(ident, span.clone());
+		let last_branch = branches.last().unwrap();
+		if !(last_branch.0.is_empty() && last_branch.2.is_none()) {
+			let line = span.start.line;
+			branches.push((
+				Vec::new(),
+				Expression::new(),
+				None,
+				CodeBlock {
+					start: line,
+					code: vec_deque![ALTER {
+						kind: DEFINE,
+						names: vec_deque![vec_deque![ident.clone()]],
+						values: vec![vec_deque![SYMBOL(String::from("nil"), span.clone())]],
+						span: span.clone()
+					}],
+					end: line,
+				},
+			))
+		}
+		if code.is_empty() && Self::is_name(value) {
+			if let Some(chain) = Self::build_match_chain(value, branches) {
+				return Ok(vec_deque![EXPR(chain, span)]);
+			}
+		}
+		code.push_back(ctoken);
+		if lazy {
+			code.push_back(RETURN_EXPR(Some(vec![vec_deque![ident]]), span.clone()));
+			let function = LAMBDA {
+				args: FunctionArgs::new(),
+				code: CodeBlock {
+					start,
+					code,
+					end: self.look_back(1).line(),
+				},
+				span: span.clone(),
+			};
+			Ok(vec_deque![
+				EXPR(vec_deque![function], span.clone()),
+				CALL(Vec::new(), span)
+			])
+		} else {
+			self.get_prev_expr().append(&mut code);
+			Ok(vec_deque![ident])
+		}
+	}
+
+	// builds `value == a and x or value == b and y or z` from the branches of a match expression,
+	// if they can be written like that
+	fn build_match_chain(value: &Expression, branches: &[MatchCase]) -> Option<Expression> {
+		let result = |(_, internal_expr, extra_if, code): &MatchCase| match code.code.front() {
+			Some(ALTER { values, .. })
+				if internal_expr.is_empty() && extra_if.is_none() && code.code.len() == 1 =>
+			{
+				Some(values[0].clone())
+			}
+			_ => None,
+		};
+		let group = |expr: Expression| match expr.len() {
+			1 => expr,
+			_ => {
+				let span = expression_span(&expr);
+				vec_deque![EXPR(expr, span)]
+			}
+		};
+		let (default, branches) = branches.split_last().unwrap();
+		let mut chain = Expression::new();
+		for branch in branches {
+			let result = result(branch).filter(Self::is_truthy)?;
+			// the operators have the span of the case or of the result they come with
+			let result_span = expression_span(&result);
+			let mut condition = Expression::new();
+			for expr in &branch.0 {
+				let span = expression_span(expr);
+				if !condition.is_empty() {
+					condition.push_back(SYMBOL(String::from(" or "), span.clone()));
+				}
+				condition.extend(value.iter().cloned());
+				condition.push_back(SYMBOL(String::from(" == "), span));
+				condition.extend(group(expr.clone()));
+			}
+			chain.extend(group(condition));
+			chain.push_back(SYMBOL(String::from(" and "), result_span.clone()));
+			chain.extend(result);
+			chain.push_back(SYMBOL(String::from(" or "), result_span));
+		}
+
+		chain.extend(group(result(default)?));
+		Some(chain)
+	}
+
+	fn build_destructuring_case(&mut self, name: &str) -> Result<Expression, String> {
+		let start = self.look_back(0).position();
+		let mut condition = Expression::new();
+		let mut bindings = Vec::new();
+		self.build_table_pattern(name.to_owned(), &mut condition, &mut bindings)?;
+		let span = self.span_from(start);
+		let line = start.line;
+		let condition = if bindings.is_empty() {
+			condition
+		} else {
+			let checked = self.get_next_internal_var();
+			let (names, fields): (Vec<String>, Vec<String>) = bindings.into_iter().unzip();
+			let prev_expr = self.get_prev_expr();
+			prev_expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: vec![checked.clone()],
+				values: vec![condition],
+				span: span.clone(),
+			});
+			prev_expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: names.clone(),
+				values: Vec::new(),
+				span: span.clone(),
+			});
+			prev_expr.push_back(IF_STATEMENT {
+				condition: vec_deque![SYMBOL(checked.clone(), span.clone())],
+				code: CodeBlock {
+					start: line,
+					code: vec_deque![ALTER {
+						kind: DEFINE,
+						names: names
+							.into_iter()
+							.map(|name| vec_deque![SYMBOL(name, span.clone())])
+							.collect(),
+						values: fields
+							.into_iter()
+							.map(|field| vec_deque![SYMBOL(field, span.clone())])
+							.collect(),
+						span: span.clone(),
+					}],
+					end: line,
+				},
+				next: None,
+				span: span.clone(),
+			});
+			vec_deque![SYMBOL(checked, span.clone())]
+		};
+		let t = self.advance();
+		match t.kind() {
+			ARROW => Ok(condition),
+			IF => {
+				let guard = self.build_expression(Some((ARROW, "=>")))?;
+				let guard_span = expression_span(&guard);
+				Ok(vec_deque![
+					EXPR(condition, span),
+					SYMBOL(String::from(" and "), t.span()),
+					EXPR(guard, guard_span)
+				])
+			}
+			_ => Err(self.expected("=>", &t.lexeme(), t.line(), t.column())),
+		}
+	}
+
+	fn build_table_pattern(
+		&mut self,
+		path: String,
+		condition: &mut Expression,
+		bindings: &mut Vec<(String, String)>,
+	) -> Result<(), String> {
+		let open = self.look_back(0).span();
+		if !condition.is_empty() {
+			condition.push_back(SYMBOL(String::from(" and "), open.clone()));
+		}
+		condition.push_back(SYMBOL(format_clue!("type(", path, ") == \"table\""), open));
+		if self.advance_if(CURLY_BRACKET_CLOSED) {
+			return Ok(());
+		}
+		loop {
+			let t = self.advance();
+			if t.kind() != IDENTIFIER {
+				return Err(self.expected("<name>", &t.lexeme(), t.line(), t.column()));
+			}
+			let key = t.lexeme();
+			let field = format_clue!(path, ".", key);
+			// `key` and `key = name` bind the field, `key == value` and `key = value` (unless
+			// the value is just a name) compare it and `key = { ... }` matches it with a table pattern
+			let compare = self.advance_if(EQUAL);
+			if !compare && !self.advance_if(DEFINE) {
+				bindings.push((key, field));
+			} else if !compare
+				&& self.peek(0).kind() == IDENTIFIER
+				&& matches!(self.peek(1).kind(), COMMA | CURLY_BRACKET_CLOSED)
+			{
+				bindings.push((self.advance().lexeme(), field));
+			} else if !compare && self.advance_if(CURLY_BRACKET_OPEN) {
+				self.build_table_pattern(field, condition, bindings)?;
+			} else {
+				let value = self.build_expression(None)?;
+				self.current -= 1;
+				let value_span = expression_span(&value);
+				condition.push_back(SYMBOL(format_clue!(" and ", field, " == "), t.span()));
+				condition.push_back(EXPR(value, value_span));
+			}
+			let t = self.advance();
+			match t.kind() {
+				COMMA if self.advance_if(CURLY_BRACKET_CLOSED) => return Ok(()),
+				COMMA => {}
+				CURLY_BRACKET_CLOSED => return Ok(()),
+				_ => return Err(self.expected("}", &t.lexeme(), t.line(), t.column())),
+			}
+		}
+	}
+
+	fn build_loop(
+		&mut self,
+		code: Option<CodeBlock>,
+		end: OptionalEnd,
+	) -> Result<(Expression, CodeBlock), String> {
+		let start = self.peek(0).line();
+		let (condition, mut internal_code) =
+			self.use_internal_stack(|i| i.build_expression(end))?;
+		let end = self.look_back(1).line();
+		let code = match code {
+			Some(code) => code,
+			None => self.build_loop_block()?,
+		};
+		if !internal_code.is_empty() {
+			// the function computing the condition has the span of the condition
+			let span = expression_span(&condition);
+			internal_code.push_back(RETURN_EXPR(Some(vec![condition]), span.clone()));
+			let function_name = self.get_next_internal_var();
+			self.expr.push_back(FUNCTION {
+				local: true,
+				name: vec_deque![SYMBOL(function_name.clone(), span.clone())],
+				args: vec![],
+				code: CodeBlock {
+					start,
+					code: internal_code,
+					end,
+				},
+				attributes: FunctionAttributes::new(),
+				span: span.clone(),
+			});
+			Ok((
+				vec_deque![SYMBOL(format_clue!(function_name, "()"), span)],
+				code,
+			))
+		} else {
+			Ok((condition, code))
+		}
+	}
+
+	fn build_round_brackets(
+		&mut self,
+		mut expr: Expression,
+		target_expr: &mut Expression,
+		strict: bool,
+	) -> Result<(), String> {
+		let start = self.peek(0).position();
+		self.current += 1;
+		let safe_indexing = self.build_identifier_internal(&mut expr)?;
+		let span = start..self.look_back(1).end_position();
+		if strict && !matches!(expr.back(), Some(CALL(..))) {
+			let t = self.look_back(0);
+			return Err(self.expected_before("<function call>", &t.lexeme(), t.line(), t.column()));
+		}
+		if safe_indexing {
+			let call = if strict { expr.pop_back() } else { None };
+			expr.push_front(SYMBOL(String::from("("), span.clone()));
+			expr.push_back(SYMBOL(String::from(")"), span.clone()));
+
+			if let Some(call) = call {
+				expr.push_back(call);
+			}
+			target_expr.push_back(IDENT { expr, span });
+		} else {
+			target_expr.push_back(expr.pop_front().unwrap());
+			if !expr.is_empty() {
+				target_expr.push_back(IDENT { expr, span })
+			}
+		}
+		self.current -= 1;
+		Ok(())
+	}
+
+	fn parse_token_at(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let mut attributes = FunctionAttributes::new();
+		let mut overrides = false;
+		self.current -= 1;
+		while self.advance_if(AT) {
+			let mut name = self.assert_advance(IDENTIFIER, "<name>")?.lexeme();
+			// `@override` only tells the bundler that this global replaces another file's
+			if name == "override" && !matches!(self.peek(0).kind(), DOT | ROUND_BRACKET_OPEN) {
+				overrides = true;
+				continue;
+			}
+			while self.advance_if(DOT) {
+				let field = self.assert_advance(IDENTIFIER, "<name>")?.lexeme();
+				name = format_clue!(name, ".", field);
+			}
+			let args = if self.advance_if(ROUND_BRACKET_OPEN) {
+				self.build_call(&Expression::new())?
+			} else {
+				Vec::new()
+			};
+			attributes.push((name, args));
+		}
+		if overrides && self.peek(0).kind() != GLOBAL {
+			return Err(self.error(
+				"'@override' can only be used before a global declaration",
+				t.line(),
+				t.column(),
+			));
+		}
+		if attributes.is_empty() {
+			let t = self.advance();
+			return self.parse_token_local_global(&t);
+		}
+		// `@table`, `@reverse` and `@inline` choose how the enum after them is compiled
+		let enum_mode = match &attributes[..] {
+			[(name, args)] if args.is_empty() => match name.as_str() {
+				"table" => Some(EnumMode::Table),
+				"reverse" => Some(EnumMode::Reverse),
+				"inline" => Some(EnumMode::Inline),
+				_ => None,
+			},
+			_ => None,
+		};
+		if enum_mode.is_some()
+			&& matches!(self.peek(0).kind(), LOCAL | GLOBAL | STATIC)
+			&& self.peek(1).kind() == ENUM
+		{
+			self.enum_mode = enum_mode;
+			let t = self.advance();
+			return self.parse_statement(&t).map(|_| ());
+		}
+		let is_static = match self.peek(0).kind() {
+			LOCAL | GLOBAL if self.peek(1).kind() == FN => false,
+			STATIC if self.peek(1).kind() == FN => true,
+			METHOD => false,
+			_ => {
+				return Err(self.error(
+					format!(
+						"'@{}' is neither a directive nor the attribute of a function declaration",
+						attributes[0].0
+					),
+					t.line(),
+					t.column(),
+				))
+			}
+		};
+		let t = self.advance();
+		self.parse_statement(&t)?;
+		let function = if is_static {
+			self.statics.back_mut()
+		} else {
+			self.expr.back_mut()
+		};
+		if let Some(FUNCTION {
+			attributes: function_attributes,
+			..
+		}) = function
+		{
+			*function_attributes = attributes;
+		}
+		Ok(())
+	}
+
+	fn parse_token_local_global(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let local = t.kind() == LOCAL;
+		let r#const = t.kind() == CONST || self.advance_if(CONST);
+		if r#const
+			&& (self.options.env_target.is_none()
+				|| self
+					.options
+					.env_target
+					.is_some_and(|lua| lua != LuaVersion::Lua54))
+		{
+			println!(
+				"Warning: Using `const` without targeting Lua 5.4 {}:{}",
+				t.line(),
+				t.column()
+			);
+		}
+		match self.peek(0).kind() {
+			FN => {
+				let function = self.build_function(local, t.position())?;
+				self.expr.push_back(function);
+			}
+			ENUM => {
+				let enums = &mut self.build_enums(local)?;
+				self.expr.append(enums);
+			}
+			STRUCT => {
+				let r#struct = &mut self.build_struct(local)?;
+				self.expr.append(r#struct);
+			}
+			_ => {
+				let destructure = self.advance_if(CURLY_BRACKET_OPEN);
+				let vars = self.build_variables(local, r#const, t.position(), destructure)?;
+				self.expr.push_back(vars);
+			}
+		}
+		Ok(())
+	}
+
+	fn parse_token_static(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let r#const = t.kind() == CONST || self.advance_if(CONST);
+		if r#const
+			&& (self.options.env_target.is_none()
+				|| self
+					.options
+					.env_target
+					.is_some_and(|lua| lua != LuaVersion::Lua54))
+		{
+			println!(
+				"Warning: Using `const` without targeting Lua 5.4 {}:{}",
+				t.line(),
+				t.column()
+			);
+		}
+		match self.peek(0).kind() {
+			FN => {
+				let function = self.build_function(true, t.position())?;
+
+				self.statics.push_back(function);
+			}
+			ENUM => {
+				let enums = self.build_enums(true)?;
+				self.statics.extend(enums);
+			}
+			STRUCT => {
+				let r#struct = self.build_struct(true)?;
+				self.statics.extend(r#struct);
+			}
+			_ => {
+				let vars = self.build_variables(true, r#const, t.position(), false)?;
+				self.statics.push_back(vars);
+			}
+		}
+		Ok(())
+	}
+
+	fn parse_token_method(&mut self, start: TokenPosition) -> Result<(), String> {
+		let name = {
+			let mut expr = Expression::with_capacity(4);
+			loop {
+				let t = self.advance();
+				match t.kind() {
+					IDENTIFIER => {
+						let nt = self.peek(0);
+						if nt.kind() == IDENTIFIER {
+							return Err(self.unexpected(&nt.lexeme(), nt.line(), nt.column()));
+						}
+						expr.push_back(SYMBOL(t.lexeme(), t.span()))
+					}
+					DOT => self.check_index(&t, &mut expr, ".")?,
+					DOUBLE_COLON => {
+						self.check_index(&t, &mut expr, ":")?;
+						let t = self.peek(1);
+						if t.kind() != ROUND_BRACKET_OPEN {
+							return Err(self.expected("(", &t.lexeme(), t.line(), t.column()));
+						}
+					}
+					ROUND_BRACKET_OPEN => break,
+					_ => return Err(self.expected("(", &t.lexeme(), t.line(), t.column())),
+				}
+			}
+			expr
+		};
+		let (args, rest /*, types*/) = if !self.advance_if(ROUND_BRACKET_CLOSED) {
+			self.build_function_args()?
+		} else {
+			(FunctionArgs::new(), None /*, None*/)
+		};
+		if let Some(function) = function_name(&name) {
+			self.declare_function(function, &args);
+		}
+		let code = self.build_function_block(rest /*, types*/)?;
+		//ADD FUNCTION FOR ADDING VALUES INSIDE TABLES MAYBE?
+		self.expr.push_back(FUNCTION {
+			local: false,
+			name,
+			args,
+			code,
+			attributes: FunctionAttributes::new(),
+			span: self.span_from(start),
+		});
+		Ok(())
+	}
+
+	fn push_call(&mut self, mut call: Expression, safe_indexing: bool, span: Span) {
+		if safe_indexing {
+			let name = call.pop_front().unwrap();
+			call.pop_front();
+			call.pop_front();
+			call.push_front(name.clone());
+			let line = span.start.line;
+			self.expr.push_back(IF_STATEMENT {
+				condition: vec_deque![name],
+				code: CodeBlock {
+					start: line,
+					code: vec_deque![IDENT {
+						expr: call,
+						span: span.clone(),
+					}],
+					end: line,
+				},
+				next: None,
+				span,
+			});
+		} else {
+			self.expr.push_back(IDENT { expr: call, span });
+		}
+	}
+
+	fn parse_pipe_statement(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		self.current -= 1;
+		let mut expr = self.build_expression(None)?;
+		self.current -= 1;
+		if let (1, Some(IDENT { expr: call, span })) = (expr.len(), expr.back_mut()) {
+			let safe_indexing = matches!(call.front(), Some(SYMBOL(lexeme, _)) if lexeme == "(");
+			if safe_indexing {
+				call.pop_front();
+				call.pop_back();
+			}
+			if let Some(CALL(_, _)) = call.back() {
+				let call = std::mem::take(call);
+				let span = t.position()..span.end;
+				self.push_call(call, safe_indexing, span);
+				return Ok(());
+			}
+		}
+		Err(self.error(
+			"Pipelines used as statements must end with a function call",
+			t.line(),
+			t.column(),
+		))
+	}
+
+	fn parse_token_identifier(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		if self.peek(0).kind() == COLON && matches!(self.peek(1).kind(), WHILE | UNTIL | LOOP | FOR)
+		{
+			return self.parse_labeled_loop(t.lexeme());
+		}
+		let start = self.current - 1;
+		if self.at(self.skip_identifier(start).0).kind() == PIPE {
+			return self.parse_pipe_statement(t);
+		}
+		let mut first_expr = Expression::with_capacity(8);
+		let safe_indexing = self.build_identifier_internal(&mut first_expr)?;
+		if let CALL(_, _) = first_expr.back().unwrap() {
+			let span = self.at(start).position()..self.look_back(1).end_position();
+			self.push_call(first_expr, safe_indexing, span);
+			self.current -= 1;
+			if !safe_indexing {
+				self.advance_if(SEMICOLON);
+			}
+			return Ok(());
+		}
+		// `a?.b.c = v` only alters `a.b.c` (and computes `v`) if `a?.b` is not nil
+		let guard = if safe_indexing {
+			let name = first_expr.pop_front().unwrap();
+			first_expr.pop_front();
+			Some((name, self.expr.len()))
+		} else {
+			None
+		};
+		let mut names = vec_deque![first_expr];
+		while {
+			self.current += 1;
+			self.look_back(1).kind() == COMMA
+		} {
+			let start = self.look_back(0).position();
+			let mut name = Expression::with_capacity(8);
+			if self.build_identifier_internal(&mut name)? || guard.is_some() {
+				return Err(self.error(
+					"Safe indexing can only be used when altering a single variable",
+					t.line(),
+					t.column(),
+				));
+			}
+			let span = start..self.look_back(1).end_position();
+			names.push_back(vec_deque![IDENT { expr: name, span }]);
+		}
+		self.current -= 1;
+		let checkt = self.look_back(0);
+		let check = checkt.kind();
+		if check < DEFINE || check > MODULATE {
+			return Err(self.expected("=", &checkt.lexeme(), checkt.line(), checkt.column()));
+		}
+		if check != DEFINE {
+			names = names
+				.into_iter()
+				.map(|name| self.cache_alter_target(name, t.span()))
+				.collect();
+		}
+		let values = self.find_expressions(None)?;
+		self.check_assignment(&checkt, names.len(), &values)?;
+		let span = t.position()..self.look_back(1).end_position();
+		if check == DEFINE_COALESCE {
+			for (name, value) in names.into_iter().zip(values) {
+				let mut condition = name.clone();
+				condition.push_back(SYMBOL(String::from(" == nil"), checkt.span()));
+				self.expr.push_back(IF_STATEMENT {
+					condition,
+					code: CodeBlock {
+						start: t.line(),
+						code: vec_deque![ALTER {
+							kind: DEFINE,
+							names: vec_deque![name],
+							values: vec![value],
+							span: span.clone()
+						}],
+						end: t.line(),
+					},
+					next: None,
+					span: span.clone(),
+				});
+			}
+		} else {
+			self.expr.push_back(ALTER {
+				kind: check,
+				span: span.clone(),
+				names,
+				values,
+			});
+		}
+		if let Some((name, start)) = guard {
+			let code = self.expr.split_off(start);
+			self.expr.push_back(IF_STATEMENT {
+				condition: vec_deque![name],
+				code: CodeBlock {
+					start: t.line(),
+					code,
+					end: t.line(),
+				},
+				next: None,
+				span,
+			});
+		}
+
+		self.current -= 1;
+		Ok(())
+	}
+
+	// a call or `...` at the end of a list of values gives all its results instead of only the first
+	fn is_multi_valued(expr: &Expression) -> bool {
+		match expr.back() {
+			Some(SYMBOL(symbol, _)) => expr.len() == 1 && symbol == "...",
+			Some(IDENT { expr: ident, .. }) => {
+				expr.len() == 1 && matches!(ident.back(), Some(CALL(_, _)))
+			}
+			_ => false,
+		}
+	}
+
+	// `a, b = x, y` assigns every value at the same time, so a different number of values
+	// only warns (the extra values are discarded, the missing ones are nil) unless the last one
+	// can give many, but compound assignments like `a, b += x, y` need one value per variable
+	fn check_assignment(
+		&mut self,
+		operator: &BorrowedToken,
+		names: usize,
+		values: &[Expression],
+	) -> Result<(), String> {
+		let plural = |n: usize, what: &str| match n {
+			1 => format!("1 {what}"),
+			n => format!("{n} {what}s"),
+		};
+		if names == values.len() {
+			return Ok(());
+		}
+		let found = format!(
+			"{} for {}",
+			plural(values.len(), "value"),
+			plural(names, "variable")
+		);
+		let consequence = if operator.kind() != DEFINE {
+			return Err(self.error(
+				format!(
+					"'{}' needs one value for each variable, found {found}",
+					operator.lexeme()
+				),
+				operator.line(),
+				operator.column(),
+			));
+		} else if values.len() > names {
+			"the extra values are discarded"
+		} else if !values.last().is_some_and(Self::is_multi_valued) {
+			"the variables without a value are set to nil"
+		} else {
+			return Ok(());
+		};
+		self.warning(
+			format!("Found {found}, {consequence}"),
+			operator.line(),
+			operator.column(),
+		);
+		Ok(())
+	}
+
+	// an expression without calls or tables can be evaluated twice without side effects
+	fn is_pure(expr: &Expression) -> bool {
+		expr.iter().all(|ctoken| match ctoken {
+			SYMBOL(_, _) => true,
+			IDENT { expr, .. } | EXPR(expr, _) => Self::is_pure(expr),
+			_ => false,
+		})
+	}
+
+	// compound assignments use their target twice, so when the receiver or the key
+	// of an indexed target are not pure they are evaluated once into internal variables
+	fn cache_alter_target(&mut self, name: Expression, span: Span) -> Expression {
+		let (mut expr, ident_span) = match name.front() {
+			Some(IDENT { expr, span }) if name.len() == 1 => (expr.clone(), Some(span.clone())),
+			_ => (name.clone(), None),
+		};
+		let span = ident_span.clone().unwrap_or(span);
+		let len = expr.len();
+		let key_start = match expr.back() {
+			Some(SYMBOL(bracket, _)) if bracket == ")]" && len > 3 => len - 3,
+			Some(SYMBOL(_, _))
+				if len > 2 && matches!(&expr[len - 2], SYMBOL(dot, _) if dot == ".") =>
+			{
+				len - 2
+			}
+			_ => return name,
+		};
+		let mut key = expr.split_off(key_start);
+		if Self::is_pure(&expr) && Self::is_pure(&key) {
+			return name;
+		}
+		let cache = |i: &mut Self, value: Expression| {
+			let var = i.get_next_internal_var();
+			i.expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: vec![var.clone()],
+				values: vec![value],
+				span: span.clone(),
+			});
+			var
+		};
+		if !Self::is_pure(&expr) {
+			let receiver_span = expression_span(&expr);
+			expr = vec_deque![SYMBOL(cache(self, expr), receiver_span)];
+		}
+		if let EXPR(index, index_span) = &mut key[1] {
+			if !Self::is_pure(index) {
+				let var = cache(self, std::mem::take(index));
+				*index = vec_deque![SYMBOL(var, index_span.clone())];
+			}
+		}
+
+		expr.append(&mut key);
+		match ident_span {
+			Some(span) => vec_deque![IDENT { expr, span }],
+			None => expr,
+		}
+	}
+
+	fn parse_labeled_loop(&mut self, name: String) -> Result<(), String> {
+		let label_start = self.look_back(0).position();
+		self.current += 1;
+		let t = self.advance();
+		match t.kind() {
+			WHILE => self.parse_token_while(t.position())?,
+			UNTIL => self.parse_token_until(t.position())?,
+			LOOP => self.parse_token_loop(t.position())?,
+			_ => self.parse_token_for(t.position())?,
+		}
+		let mut ctoken = self.expr.pop_back().unwrap();
+		let (WHILE_LOOP {
+			code, label, span, ..
+		}
+		| LOOP_UNTIL {
+			code, label, span, ..
+		}
+		| FOR_LOOP {
+			code, label, span, ..
+		}
+		| FOR_FUNC_LOOP {
+			code, label, span, ..
+		}) = &mut ctoken
+		else {
+			unreachable!()
+		};
+		*label = Some(name.clone());
+		span.start = label_start;
+		let span = span.clone();
+		let mut finder = LabelFinder {
+			label: &name,
+			found: false,
+			shadowed: false,
+		};
+		finder.visit_code_block(code);
+		if finder.shadowed {
+			return Err(self.error(
+				format!("Loop label '{name}' is already used by an enclosing loop"),
+				t.line(),
+				t.column(),
+			));
+		}
+		if !finder.found {
+			self.expr.push_back(ctoken);
+			return Ok(());
+		}
+		let (start, end) = (code.start, code.end);
+		if matches!(
+			self.options.env_continue,
+			ContinueMode::Goto | ContinueMode::LuaJIT
+		) {
+			let (breaks, continues) = self.build_labeled_jumps(&mut code.code, &name, None, 0)?;
+			if continues {
+				code.code
+					.push_back(SYMBOL(format!("::{name}_continue::"), span.clone()));
+			}
+			if breaks {
+				// the do block keeps the labels of sibling loops with the same name apart
+				let code = vec_deque![ctoken, SYMBOL(format!("::{name}_break::"), span.clone())];
+				self.expr
+					.push_back(DO_BLOCK(CodeBlock { start, code, end }, span));
+				return Ok(());
+			}
+		} else {
+			// without goto the body is wrapped in a `repeat ... until true` loop, so that both
+			// `break` and `continue` can leave it, and a flag tells which one it was
+			let flag = self.get_next_internal_var();
+			self.build_labeled_jumps(&mut code.code, &name, Some(&flag), 0)?;
+			let body = std::mem::take(&mut code.code);
+			code.code = vec_deque![
+				VARIABLE {
+					local: true,
+					r#const: false,
+					names: vec![flag.clone()],
+					values: Vec::new(),
+					span: span.clone(),
+				},
+				LOOP_UNTIL {
+					condition: vec_deque![SYMBOL(String::from("true"), span.clone())],
+					code: CodeBlock {
+						start,
+						code: body,
+						end,
+					},
+					label: None,
+					span: span.clone(),
+				},
+				IF_STATEMENT {
+					condition: vec_deque![SYMBOL(format!("{flag} == \"break\""), span.clone())],
+					code: CodeBlock {
+						start: end,
+						code: vec_deque![BREAK_LOOP(None, span.clone())],
+						end,
+					},
+					next: None,
+					span,
+				}
+			];
+		}
+		self.expr.push_back(ctoken);
+		Ok(())
+	}
+
+	// replaces the `break` and `continue` statements jumping to the given label with gotos,
+	// or when there is a flag with an assignment to it followed by a `break`, which is then
+	// repeated after every nested loop, returns whether any `break` and `continue` were found
+	fn build_labeled_jumps(
+		&mut self,
+		code: &mut Expression,
+		label: &str,
+		flag: Option<&str>,
+		depth: usize,
+	) -> Result<(bool, bool), String> {
+		let (mut breaks, mut continues) = (false, false);
+		// `at` is the span of the jump being replaced
+		let jump = |kind: &str, at: &Span| match flag {
+			None => vec![SYMBOL(format!("goto {label}_{kind};"), at.clone())],
+			Some(flag) => vec![
+				ALTER {
+					kind: DEFINE,
+					names: vec_deque![vec_deque![SYMBOL(flag.to_owned(), at.clone())]],
+					values: vec![vec_deque![SYMBOL(format!("\"{kind}\""), at.clone())]],
+					span: at.clone(),
+				},
+				BREAK_LOOP(None, at.clone()),
+			],
+		};
+		let mut i = 0;
+		while i < code.len() {
+			let mut found = (false, false);
+			let replacement = match &mut code[i] {
+				BREAK_LOOP(Some(name), at) if name == label => {
+					found.0 = true;
+					Some(jump("break", at))
+				}
+				CONTINUE_LOOP(Some(name), at) if name == label => {
+					found.1 = true;
+					Some(jump("continue", at))
+				}
+				// the body is in a `repeat` loop, so plain breaks have to leave the labeled loop too
+				BREAK_LOOP(None, at) if depth == 0 && flag.is_some() => Some(jump("break", at)),
+				WHILE_LOOP { code, .. }
+				| LOOP_UNTIL { code, .. }
+				| FOR_LOOP { code, .. }
+				| FOR_FUNC_LOOP { code, .. } => {
+					found = self.build_labeled_jumps(&mut code.code, label, flag, depth + 1)?;
+					None
+				}
+				IF_STATEMENT { .. } => {
+					let mut next = Some(&mut code[i]);
+					while let Some(ctoken) = next {
+						next = match ctoken {
+							IF_STATEMENT { code, next, .. } => {
+								let jumps =
+									self.build_labeled_jumps(&mut code.code, label, flag, depth)?;
+								found = (found.0 || jumps.0, found.1 || jumps.1);
+								next.as_deref_mut()
+							}
+							DO_BLOCK(code, _) => {
+								let jumps =
+									self.build_labeled_jumps(&mut code.code, label, flag, depth)?;
+								found = (found.0 || jumps.0, found.1 || jumps.1);
+								None
+							}
+							_ => None,
+						}
+					}
+					None
+				}
+				DO_BLOCK(code, _) => {
+					found = self.build_labeled_jumps(&mut code.code, label, flag, depth)?;
+					None
+				}
+				MATCH_BLOCK { branches, .. } => {
+					for (.., code) in branches {
+						let jumps = self.build_labeled_jumps(&mut code.code, label, flag, depth)?;
+						found = (found.0 || jumps.0, found.1 || jumps.1);
+					}
+					None
+				}
+				_ => None,
+			};
+			breaks |= found.0;
+			continues |= found.1;
+			let nested_loop = matches!(
+				code[i],
+				WHILE_LOOP { .. } | LOOP_UNTIL { .. } | FOR_LOOP { .. } | FOR_FUNC_LOOP { .. }
+			);
+			if let Some(replacement) = replacement {
+				code.remove(i);
+				for ctoken in replacement.into_iter().rev() {
+					code.insert(i, ctoken);
+				}
+				i += 1;
+			} else if let (Some(flag), true, true) = (flag, nested_loop, found.0 || found.1) {
+				// the check after a nested loop has the span of that loop
+				let span = code[i].span();
+				let line = span.end.line;
+				code.insert(
+					i + 1,
+					IF_STATEMENT {
+						condition: vec_deque![SYMBOL(flag.to_owned(), span.clone())],
+						code: CodeBlock {
+							start: line,
+							code: vec_deque![BREAK_LOOP(None, span.clone())],
+							end: line,
+						},
+						next: None,
+						span,
+					},
+				);
+				i += 1;
+			}
+			i += 1;
+		}
+		Ok((breaks, continues))
+	}
+
+	fn parse_token_round_bracket_open(&mut self, start: TokenPosition) -> Result<(), String> {
+		let inner = self.build_expression(Some((ROUND_BRACKET_CLOSED, ")")))?;
+		let expr = vec_deque![EXPR(inner, self.span_from(start))];
+		let mut target_expr = Expression::with_capacity(2);
+		self.build_round_brackets(expr, &mut target_expr, true)?;
+		self.expr.append(&mut target_expr);
+		self.advance_if(SEMICOLON);
+		Ok(())
+	}
+
+	fn is_destructuring_assignment(&self) -> bool {
+		if self.peek(0).kind() != IDENTIFIER
+			|| !matches!(
+				self.peek(1).kind(),
+				COMMA | ARROW | DEFINE | CURLY_BRACKET_CLOSED
+			) {
+			return false;
+		}
+		let mut cscope = 1u8;
+		for (i, t) in self.tokens.iter().enumerate().skip(self.current) {
+			match t.kind {
+				CURLY_BRACKET_OPEN => cscope += 1,
+				CURLY_BRACKET_CLOSED => {
+					cscope -= 1;
+					if cscope == 0 {
+						return self.tokens.get(i + 1).is_some_and(|t| t.kind == DEFINE);
+					}
+				}
+				EOF => break,
+				_ => {}
+			}
+		}
+		false
+	}
+
+	fn parse_token_curly_bracket_open(&mut self) -> Result<(), String> {
+		let start = self.look_back(0).position();
+		if self.is_destructuring_assignment() {
+			let (names, key_names, internal_names, defaults) = self.build_destructure_table()?;
+			self.assert(DEFINE, "=")?;
+			let values = self.find_expressions(None)?;
+			self.current -= 1;
+			let span = self.span_from(start);
+			let values = self.build_table_destructuring(
+				(key_names, internal_names, defaults),
+				values,
+				span.clone(),
+			);
+			self.expr.push_back(ALTER {
+				kind: DEFINE,
+				names: names
+					.into_iter()
+					.map(|name| vec_deque![SYMBOL(name, span.clone())])
+					.collect(),
+				values,
+				span,
+			});
+			return Ok(());
+		}
+		self.current -= 1;
+		let block = self.build_code_block(/*self.locals.clone()*/)?;
+		self.expr.push_back(DO_BLOCK(block, self.span_from(start)));
+		Ok(())
+	}
+
+	fn parse_token_if(&mut self) -> Result<(), String> {
+		let ctoken = self.build_elseif_chain(None)?;
+		self.expr.push_back(ctoken);
+		Ok(())
+	}
+
+	fn parse_token_match(&mut self) -> Result<(), String> {
+		let name = self.get_next_internal_var();
+		let ctoken = self.build_match_block(name, &ParserInfo::build_code_block)?;
+		let ctoken = match self.options.env_match_dispatch {
+			Some(threshold) => self.build_match_dispatch(ctoken, threshold),
+			None => ctoken,
+		};
+		self.expr.push_back(ctoken);
+		Ok(())
+	}
+
+	// turns a match block into a table of functions indexed by the literals its cases compare
+	// the value with, called with `(handlers[value] or default)()`, if it has at least `threshold`
+	// of them and its branches can run in a function (see `dispatch_keys`)
+	fn build_match_dispatch(&mut self, ctoken: ComplexToken, threshold: usize) -> ComplexToken {
+		let MATCH_BLOCK { branches, .. } = &ctoken else {
+			unreachable!()
+		};
+		let Some(keys) = dispatch_keys(branches)
+			.filter(|keys| keys.iter().map(Vec::len).sum::<usize>() >= threshold)
+		else {
+			return ctoken;
+		};
+		let MATCH_BLOCK {
+			name,
+			value,
+			mut branches,
+			span,
+		} = ctoken
+		else {
+			unreachable!()
+		};
+		let default = match branches.last() {
+			Some((conditions, ..)) if conditions.is_empty() => branches.pop(),
+			_ => None,
+		};
+		let mut code = vec_deque![VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![name.clone()],
+			values: vec![value],
+			span: span.clone(),
+		}];
+		let mut handlers = Vec::new();
+		for ((_, _, _, block), keys) in branches.into_iter().zip(keys) {
+			let mut function = vec_deque![LAMBDA {
+				args: FunctionArgs::new(),
+				code: block,
+				span: span.clone(),
+			}];
+
+			// the branches with more than one case share their function
+			if keys.len() > 1 {
+				let shared = self.get_next_internal_var();
+				code.push_back(VARIABLE {
+					local: true,
+					r#const: false,
+					names: vec![shared.clone()],
+					values: vec![function],
+					span: span.clone(),
+				});
+				function = vec_deque![SYMBOL(shared, span.clone())];
+			}
+			for key in keys {
+				let key = vec_deque![
+					SYMBOL(String::from("["), span.clone()),
+					SYMBOL(key, span.clone()),
+					SYMBOL(String::from("]"), span.clone())
+				];
+				handlers.push((Some(key), function.clone(), span.clone()));
+			}
+		}
+		let table = self.get_next_internal_var();
+		code.push_back(VARIABLE {
+			local: true,
+			r#const: false,
+			names: vec![table.clone()],
+			values: vec![vec_deque![TABLE {
+				values: handlers,
+				metas: Vec::new(),
+				metatable: None,
+				span: span.clone(),
+			}]],
+			span: span.clone(),
+		});
+		let mut handler = vec_deque![
+			SYMBOL(table, span.clone()),
+			SYMBOL(String::from("["), span.clone()),
+			SYMBOL(name, span.clone()),
+			SYMBOL(String::from("]"), span.clone())
+		];
+		code.push_back(match default {
+			Some((_, _, _, default)) => {
+				handler.push_back(SYMBOL(String::from(" or "), span.clone()));
+				handler.push_back(LAMBDA {
+					args: FunctionArgs::new(),
+					code: default,
+					span: span.clone(),
+				});
+				IDENT {
+					expr: vec_deque![
+						SYMBOL(String::from("("), span.clone()),
+						EXPR(handler, span.clone()),
+						SYMBOL(String::from(")"), span.clone()),
+						CALL(Vec::new(), span.clone())
+					],
+					span: span.clone(),
+				}
+			}
+			None => IF_STATEMENT {
+				condition: handler.clone(),
+				code: CodeBlock {
+					start: span.start.line,
+					code: vec_deque![IDENT {
+						expr: handler
+							.into_iter()
+							.chain(once(CALL(Vec::new(), span.clone())))
+							.collect(),
+						span: span.clone(),
+					}],
+					end: span.end.line,
+				},
+				next: None,
+				span: span.clone(),
+			},
+		});
+		DO_BLOCK(
+			CodeBlock {
+				start: span.start.line,
+				code,
+				end: span.end.line,
+			},
+			span.clone(),
+		)
+	}
+
+	fn parse_token_while(&mut self, start: TokenPosition) -> Result<(), String> {
+		let (condition, code) = self.build_loop(None, Some((CURLY_BRACKET_OPEN, "{")))?;
+		self.expr.push_back(WHILE_LOOP {
+			condition,
+			code,
+			label: None,
+			span: self.span_from(start),
+		});
+		Ok(())
+	}
+
+	fn parse_token_until(&mut self, start: TokenPosition) -> Result<(), String> {
+		let (mut condition, code) = self.build_loop(None, Some((CURLY_BRACKET_OPEN, "{")))?;
+		let span = expression_span(&condition);
+		condition.push_front(SYMBOL(String::from("not ("), span.clone()));
+		condition.push_back(SYMBOL(String::from(")"), span));
+		self.expr.push_back(WHILE_LOOP {
+			condition,
+			code,
+			label: None,
+			span: self.span_from(start),
+		});
+		Ok(())
+	}
+
+	fn parse_token_loop(&mut self, start: TokenPosition) -> Result<(), String> {
+		let code = self.build_loop_block()?;
+		let t = self.advance();
+		// every branch reads the token after the loop too
+		match t.kind() {
+			UNTIL => {
+				let (condition, code) = self.build_loop(Some(code), None)?;
+				self.expr.push_back(LOOP_UNTIL {
+					condition,
+					code,
+					label: None,
+					span: start..self.look_back(1).end_position(),
+				})
+			}
+			WHILE => {
+				let (mut condition, code) = self.build_loop(Some(code), None)?;
+				let span = expression_span(&condition);
+				condition.push_front(SYMBOL(String::from("not ("), span.clone()));
+				condition.push_back(SYMBOL(String::from(")"), span));
+				self.expr.push_back(LOOP_UNTIL {
+					condition,
+					code,
+					label: None,
+					span: start..self.look_back(1).end_position(),
+				})
+			}
+			_ => {
+				let span = start..self.look_back(1).end_position();
+				self.expr.push_back(WHILE_LOOP {
+					condition: vec_deque![SYMBOL(String::from("true"), span.clone())],
+					code,
+					label: None,
+					span,
+				})
+			}
+		}
+		self.current -= 1;
+		Ok(())
+	}
+
+	fn parse_token_for(&mut self, first: TokenPosition) -> Result<(), String> {
+		if self.peek(1).kind() == DEFINE {
+			let iterator = self.assert_advance(IDENTIFIER, "<name>")?.lexeme();
+			self.current += 1;
+			let start = self.build_expression(Some((COMMA, ",")))?;
+			let end = self.build_expression(None)?;
+			self.current -= 1;
+			let t = self.advance();
+			let alter = match t.kind() {
+				CURLY_BRACKET_OPEN => {
+					self.current -= 1;
+					// the step is 1 when the end value isn't followed by one
+					vec_deque![SYMBOL(String::from("1"), expression_span(&end))]
+				}
+				COMMA => self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?,
+				_ => return Err(self.expected(",", &t.lexeme(), t.line(), t.column())),
+			};
+			let code = self.build_loop_block()?;
+			self.expr.push_back(FOR_LOOP {
+				iterator,
+				start,
+				end,
+				alter,
+				code,
+				label: None,
+				span: self.span_from(first),
+			})
+		} else {
+			let iterators = self.build_identifier_list()?;
+			if let ([iterator], IN | OF) = (&iterators[..], self.peek(0).kind()) {
+				if let Some(range) = self.find_range(self.current + 1) {
+					self.current += 1;
+					return self.parse_range_for(iterator.clone(), range, first);
+				}
+			}
+			let t = self.advance();
+			let expr = match t.kind() {
+				OF => {
+					let mut expr = vec_deque![SYMBOL(String::from("pairs("), t.span())];
+					expr.append(&mut self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?);
+					expr.push_back(SYMBOL(String::from(")"), t.span()));
+					expr
+				}
+				IN => {
+					let mut expr = vec_deque![SYMBOL(String::from("ipairs("), t.span())];
+					expr.append(&mut self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?);
+					expr.push_back(SYMBOL(String::from(")"), t.span()));
+					expr
+				}
+
+				WITH => self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?,
+				_ => {
+					let t = self.peek(0);
+					return Err(self.expected(
+						"of', 'in' or 'with",
+						&t.lexeme(),
+						t.line(),
+						t.column(),
+					));
+				}
+			};
+			let code = self.build_loop_block()?;
+			self.expr.push_back(FOR_FUNC_LOOP {
+				iterators,
+				expr,
+				code,
+				label: None,
+				span: self.span_from(first),
+			});
+		}
+		Ok(())
+	}
+
+	// finds the `..` or `..=` of `for i in start..end`, if the loop iterates over a range
+	fn find_range(&self, mut i: usize) -> Option<usize> {
+		let mut depth = 0usize;
+		loop {
+			match self.at(i).kind() {
+				ROUND_BRACKET_OPEN | SQUARE_BRACKET_OPEN | SAFE_CALL | SAFE_SQUARE_BRACKET => {
+					depth += 1
+				}
+				ROUND_BRACKET_CLOSED | SQUARE_BRACKET_CLOSED => depth = depth.saturating_sub(1),
+				TWODOTS | CONCATENATE if depth == 0 => return Some(i),
+				CURLY_BRACKET_OPEN if depth == 0 => return None,
+				EOF => return None,
+				_ => {}
+			}
+			i += 1;
+		}
+	}
+
+	// `for i in start..end by step {...}` is compiled like `for i = start, end - 1, step {...}`
+	// when the bounds are integers, otherwise the loop is left with `if i >= end then break end`
+	// so that it stops before the end even if the steps never land on it. `..=` includes the end.
+	// Without a step the loop counts down when the start is bigger than the end,
+	// which is checked at runtime when the bounds are not constant
+	fn parse_range_for(
+		&mut self,
+		iterator: String,
+		range: usize,
+		keyword: TokenPosition,
+	) -> Result<(), String> {
+		let t = self.at(range);
+		let inclusive = t.kind() == CONCATENATE;
+		self.tokens[range].kind = COMMA;
+		let mut start = self.build_expression(Some((COMMA, "..")))?;
+		let mut end = self.build_expression(None)?;
+		self.current -= 1;
+		let next = self.advance();
+		let step = match next.kind() {
+			CURLY_BRACKET_OPEN => {
+				self.current -= 1;
+				None
+			}
+			IDENTIFIER if next.lexeme() == "by" => {
+				Some(self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?)
+			}
+			_ => return Err(self.expected("{", &next.lexeme(), next.line(), next.column())),
+		};
+		// the code added to the loop has the span of its header
+		let header = self.span_from(keyword);
+		let (first, last) = (constant_number(&start), constant_number(&end));
+
+		let runtime_step = step.is_none() && (first.is_none() || last.is_none());
+		let step_value = match &step {
+			Some(step) => constant_number(step),
+			None if runtime_step => None,
+			None if first > last => Some(-1.0),
+			None => Some(1.0),
+		};
+		let integers = [first, last, step_value]
+			.iter()
+			.all(|value| value.is_some_and(|value| value.fract() == 0.0));
+		// whether the loop needs to be left when it reaches the end, which is only set for `..`
+		let mut strict = None;
+		match step_value {
+			Some(0.0) => {
+				return Err(self.error("The step of a range cannot be 0", t.line(), t.column()))
+			}
+			Some(step) if !inclusive && integers => {
+				if end.len() > 1 {
+					end = vec_deque![EXPR(end, header.clone())];
+				}
+				end.push_back(SYMBOL(
+					String::from(if step > 0.0 { "-" } else { "+" }),
+					header.clone(),
+				));
+				end.push_back(SYMBOL(String::from("1"), header.clone()));
+			}
+			Some(step) if !inclusive => strict = Some(step > 0.0),
+			None if !inclusive && !runtime_step => {
+				return Err(self.error(
+					"Ranges made with '..' need a constant step, use '..=' instead",
+					t.line(),
+					t.column(),
+				))
+			}
+			_ => {}
+		}
+		if let (Some(first), Some(last), Some(step)) = (first, last, step_value) {
+			let empty = if inclusive {
+				(step > 0.0 && first > last) || (step < 0.0 && first < last)
+			} else {
+				(step > 0.0 && first >= last) || (step < 0.0 && first <= last)
+			};
+			if empty {
+				self.warning(
+					"This range is empty, the loop will never run",
+					t.line(),
+					t.column(),
+				);
+			}
+		}
+		if (strict.is_some() && last.is_none()) || runtime_step {
+			// the end is compared in every iteration and the step compares both bounds,
+			// so they're saved in locals first (in the same order Lua evaluates them)
+			let mut names = Vec::new();
+			let mut values = Vec::new();
+			for bound in [&mut start, &mut end] {
+				if constant_number(bound).is_none() {
+					let name = self.get_next_internal_var();
+					values.push(std::mem::replace(
+						bound,
+						vec_deque![SYMBOL(name.clone(), header.clone())],
+					));
+					names.push(name);
+				}
+			}
+			self.expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names,
+				values,
+				span: self.span_from(keyword),
+			});
+		}
+		let alter = match (step, step_value) {
+			(Some(step), _) => step,
+			(None, Some(step)) => vec_deque![SYMBOL(step.to_string(), header.clone())],
+			(None, None) => {
+				let mut alter = start.clone();
+				alter.push_back(SYMBOL(String::from(" > "), header.clone()));
+				alter.extend(end.iter().cloned());
+				alter.push_back(SYMBOL(String::from(" and -1 or 1"), header.clone()));
+				alter
+			}
+		};
+		// `..` also needs the step to know which side of the end leaves the loop
+		let alter = if runtime_step && !inclusive {
+			let name = self.get_next_internal_var();
+			self.expr.push_back(VARIABLE {
+				local: true,
+				r#const: false,
+				names: vec![name.clone()],
+				values: vec![alter],
+				span: self.span_from(keyword),
+			});
+			vec_deque![SYMBOL(name, header.clone())]
+		} else {
+			alter
+		};
+		let mut code = self.build_loop_block()?;
+		if runtime_step && !inclusive {
+			// `(i - end) * step >= 0` once `i` reaches the end from either side
+			let mut condition = vec_deque![SYMBOL(format!("({iterator}-"), header.clone())];
+			condition.extend(end.iter().cloned());
+			condition.push_back(SYMBOL(String::from(")*"), header.clone()));
+			condition.extend(alter.iter().cloned());
+			condition.push_back(SYMBOL(String::from(">=0"), header.clone()));
+			code.code.push_front(IF_STATEMENT {
+				condition,
+				code: CodeBlock {
+					start: code.start,
+					code: vec_deque![BREAK_LOOP(None, header.clone())],
+					end: code.start,
+				},
+				next: None,
+				span: header.clone(),
+			});
+		}
+		if let Some(ascending) = strict {
+			let mut condition = vec_deque![
+				SYMBOL(iterator.clone(), header.clone()),
+				SYMBOL(
+					String::from(if ascending { ">=" } else { "<=" }),
+					header.clone()
+				)
+			];
+			condition.extend(end.iter().cloned());
+			code.code.push_front(IF_STATEMENT {
+				condition,
+				code: CodeBlock {
+					start: code.start,
+					code: vec_deque![BREAK_LOOP(None, header.clone())],
+					end: code.start,
+				},
+				next: None,
+				span: header,
+			});
+		}
+		self.expr.push_back(FOR_LOOP {
+			iterator,
+			start,
+			end,
+			alter,
+			code,
+			label: None,
+			span: self.span_from(keyword),
+		});
+		Ok(())
+	}
+
+	// the label after `break` or `continue`, which has to be on the same line
+	fn build_jump_label(&mut self, line: usize) -> Option<String> {
+		let t = self.peek(0);
+		if t.kind() == IDENTIFIER && t.line() == line {
+			self.current += 1;
+			Some(t.lexeme())
+		} else {
+			None
+		}
+	}
+
+	fn parse_token_continue(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let label = self.build_jump_label(t.line());
+		self.expr
+			.push_back(CONTINUE_LOOP(label, self.span_from(t.position())));
+		self.advance_if(SEMICOLON);
+		Ok(())
+	}
+
+	fn parse_token_break(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let label = self.build_jump_label(t.line());
+		self.expr
+			.push_back(BREAK_LOOP(label, self.span_from(t.position())));
+		self.advance_if(SEMICOLON);
+		Ok(())
+	}
+
+	// `goto label` and `::label::` are passed to Lua as they are, Lua 5.1 and Luau are the only targets without them
+	fn check_goto(&mut self, t: &BorrowedToken, what: &str) -> Result<(), String> {
+		let target = match self.options.env_target {
+			Some(LuaVersion::Lua51 | LuaVersion::Lua51Pure) => "Lua 5.1",
+			Some(LuaVersion::Luau) => "Luau",
+			_ => return Ok(()),
+		};
+		Err(self.error(
+			format_clue!(
+				what,
+				" can only be used when targeting LuaJIT or Lua 5.2+, not ",
+				target
+			),
+			t.line(),
+			t.column(),
+		))
+	}
+
+	fn parse_token_goto(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		self.check_goto(t, "'goto'")?;
+		let label = self.assert_advance(IDENTIFIER, "<label>")?.lexeme();
+		self.expr
+			.push_back(GOTO_LABEL(label, self.span_from(t.position())));
+		self.advance_if(SEMICOLON);
+		Ok(())
+	}
+
+	fn parse_token_label(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		self.check_goto(t, "Labels")?;
+		let label = self.assert_advance(IDENTIFIER, "<label>")?.lexeme();
+		self.assert(DOUBLE_COLON, "::")?;
+		self.expr
+			.push_back(LABEL(label, self.span_from(t.position())));
+		Ok(())
+	}
+
+	fn parse_token_return(&mut self, start: TokenPosition) -> Result<(), String> {
+		let exprs = if self.ended() || self.advance_if(SEMICOLON) {
+			None
+		} else {
+			Some(self.find_expressions(None)?)
+		};
+		// the token after the values was already read
+		let end = match exprs {
+			Some(_) => self.look_back(1).end_position(),
+			None => self.look_back(0).end_position(),
+		};
+		self.expr.push_back(RETURN_EXPR(exprs, start..end));
+		if !self.ended() {
+			let t = self.look_back(0);
+			return Err(self.expected("<end>", &t.lexeme(), t.line(), t.column()));
+		}
+		Ok(())
+	}
+
+	fn parse_token_try(&mut self, start: TokenPosition) -> Result<(), String> {
+		let totry = self.build_code_block(/*self.locals.clone()*/)?;
+		let error: Option<String>;
+		let catch = if self.advance_if(CATCH) {
+			let t = self.advance();
+			if t.kind() == IDENTIFIER {
+				error = Some(t.lexeme());
+			} else {
+				error = None;
+				self.current -= 1;
+			}
+			Some(self.build_code_block(/*self.locals.clone()*/)?)
+		} else {
+			error = None;
+			None
+		};
+		let finally = if self.advance_if(FINALLY) {
+			Some(self.build_code_block(/*self.locals.clone()*/)?)
+		} else {
+			None
+		};
+		self.expr.push_back(TRY_CATCH {
+			totry,
+			error,
+			catch,
+			finally,
+			span: self.span_from(start),
+		});
+		Ok(())
+	}
+
+	// `throw x` is turned into `error(x, 0)` so that the error value is thrown as is
+	fn parse_token_throw(&mut self, start: TokenPosition) -> Result<(), String> {
+		let value = self.build_expression(None)?;
+		self.current -= 1;
+		let span = self.span_from(start);
+		self.expr.push_back(IDENT {
+			expr: vec_deque![
+				SYMBOL(String::from("error"), span.clone()),
+				CALL(
+					vec![value, vec_deque![SYMBOL(String::from("0"), span.clone())]],
+					span.clone()
+				)
+			],
+			span,
+		});
+		self.advance_if(SEMICOLON);
+		Ok(())
+	}
+
+	fn parse_token_with(&mut self, start: TokenPosition) -> Result<(), String> {
+		let name = self.assert_advance(IDENTIFIER, "<name>")?.lexeme();
+		self.assert(DEFINE, "=")?;
+		let value = self.build_expression(Some((CURLY_BRACKET_OPEN, "{")))?;
+		let code = self.build_code_block(/*self.locals.clone()*/)?;
+		// below Lua 5.4 the block is run by `pcall`, so it can't leave the function or loops around it
+		if self.options.env_target != Some(LuaVersion::Lua54) {
+			let mut exits = BranchExits {
+				loops: Vec::new(),
+				found: false,
+				varargs: false,
+			};
+			exits.visit_code_block(&code);
+			if exits.found || exits.varargs {
+				return Err(self.error(
+					"'return', 'break', 'continue', 'goto' and '...' cannot be used inside 'with' unless targeting Lua 5.4",
+					start.line,
+					start.column,
+				));
+			}
+		}
+		self.expr.push_back(WITH_BLOCK {
+			name,
+			value,
+			code,
+			span: self.span_from(start),
+		});
+		Ok(())
+	}
+
+	// skips the statement, which uses syntax from a future version of Clue, until its line or its block ends
+	fn parse_token_future(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		let keyword = t.lexeme();
+		let version = future_keyword_version(&keyword).unwrap_or_default();
+		let mut depth = 0usize;
+		while !self.ended() {
+			let next = self.peek(0);
+			if depth == 0 && next.line() > self.look_back(0).line() {
+				break;
+			}
+			match next.kind() {
+				ROUND_BRACKET_OPEN | SQUARE_BRACKET_OPEN | CURLY_BRACKET_OPEN | SAFE_CALL
+				| SAFE_SQUARE_BRACKET => depth += 1,
+				ROUND_BRACKET_CLOSED | SQUARE_BRACKET_CLOSED | CURLY_BRACKET_CLOSED => {
+					if depth == 0 {
+						break;
+					}
+					depth -= 1;
+					if depth == 0 && next.kind() == CURLY_BRACKET_CLOSED {
+						self.current += 1;
+						break;
+					}
+				}
+				SEMICOLON if depth == 0 => {
+					self.current += 1;
+					break;
+				}
+				EOF => break,
+				_ => {}
+			}
+			self.current += 1;
+		}
+		self.expr.push_back(UNSUPPORTED {
+			keyword,
+			version: version.to_owned(),
+			span: self.span_from(t.position()),
+		});
+		Ok(())
+	}
+
+	fn parse_token_fn_enum(&mut self, t: &BorrowedToken) -> Result<(), String> {
+		Err(self.error(
+			format!(
+				"'{}' must have 'local', 'global' or 'static' beforehand",
+				t.lexeme()
+			),
+			t.line(),
+			t.column(),
+		))
+	}
+
+	fn parse_statement(&mut self, t: &BorrowedToken) -> Result<bool, String> {
+		self.traced("statement", t, |i| i.parse_statement_internal(t))
+	}
+
+	fn parse_statement_internal(&mut self, t: &BorrowedToken) -> Result<bool, String> {
+		match t.kind() {
+			LOCAL | GLOBAL | CONST => self.parse_token_local_global(t)?,
+			STATIC => self.parse_token_static(t)?,
+			METHOD => self.parse_token_method(t.position())?,
+			AT => self.parse_token_at(t)?,
+			IDENTIFIER => self.parse_token_identifier(t)?,
+			ROUND_BRACKET_OPEN => self.parse_token_round_bracket_open(t.position())?,
+
+			CURLY_BRACKET_OPEN => self.parse_token_curly_bracket_open()?,
+			IF => self.parse_token_if()?,
+			MATCH => self.parse_token_match()?,
+			WHILE => self.parse_token_while(t.position())?,
+			UNTIL => self.parse_token_until(t.position())?,
+			LOOP => self.parse_token_loop(t.position())?,
+			FOR => self.parse_token_for(t.position())?,
+			CONTINUE => self.parse_token_continue(t)?,
+			BREAK => self.parse_token_break(t)?,
+			GOTO => self.parse_token_goto(t)?,
+			DOUBLE_COLON => self.parse_token_label(t)?,
+			RETURN => self.parse_token_return(t.position())?,
+			TRY => self.parse_token_try(t.position())?,
+
+			THROW => self.parse_token_throw(t.position())?,
+			WITH => self.parse_token_with(t.position())?,
+			FN | ENUM | STRUCT => self.parse_token_fn_enum(t)?,
+			EXTERN => self.parse_token_future(t)?,
+			SEMICOLON => {}
+			DO => {
+				return Err(self.error(
+					"'do { ... }' can only be used as an expression, code blocks in Clue are made like this: '{ ... }'",
+					t.line(),
+					t.column(),
+				))
+			}
+			EOF => return Ok(false),
+			_ => return Err(self.expected("<end>", &t.lexeme(), t.line(), t.column())),
+		}
+		Ok(true)
+	}
+
+	fn synchronize(&mut self, start: usize) {
+		self.internal_stack.clear();
+		self.current = cmp::max(self.current, start + 1);
+		while !self.ended() {
+			match self.peek(0).kind() {
+				SEMICOLON | CURLY_BRACKET_CLOSED => {
+					self.current += 1;
+					break;
+				}
+				LOCAL | GLOBAL | CONST | STATIC | METHOD | AT | IF | MATCH | WHILE | UNTIL
+				| LOOP | FOR | CONTINUE | BREAK | GOTO | RETURN | TRY | THROW | WITH | EOF => break,
+				_ => self.current += 1,
+			}
+		}
+	}
+}
+
+// the name of a function like `a.b` or `a:b`, if the expression is made only of names and indexes
+fn function_name(expr: &Expression) -> Option<String> {
+	expr.iter()
+		.map(|ctoken| match ctoken {
+			SYMBOL(lexeme, _) => Some(lexeme.as_str()),
+			_ => None,
+		})
+		.collect()
+}
+
+// the keys of the dispatch table of a match block for each branch but the default one, if every
+// case compares the value with a literal no other case uses and no branch leaves the match
+// (with `return`, `break`, ...) or uses the varargs, so that it can run in its own function
+fn dispatch_keys(branches: &[MatchCase]) -> Option<Vec<Vec<String>>> {
+	let (last, rest) = branches.split_last()?;
+	let default = last.0.is_empty() && last.2.is_none();
+	let cases = if default { rest } else { branches };
+	let mut literals = Vec::new();
+	let mut keys = Vec::new();
+	for (conditions, internal_expr, extra_if, _) in cases {
+		if conditions.is_empty() || !internal_expr.is_empty() || extra_if.is_some() {
+			return None;
+		}
+		let mut branch_keys = Vec::new();
+		for condition in conditions {
+			let (key, literal) = literal_key(condition)?;
+			if literals.contains(&literal) {
+				return None;
+			}
+			literals.push(literal);
+			branch_keys.push(key);
+		}
+		keys.push(branch_keys);
+	}
+	let mut exits = BranchExits {
+		loops: Vec::new(),
+		found: false,
+		varargs: false,
+	};
+	for (.., code) in branches {
+		exits.visit_code_block(code);
+	}
+	(!exits.found && !exits.varargs).then_some(keys)
+}
+
+// the code of a literal used as a key of a dispatch table and its value,
+// so that the same value written differently (like `1` and `1.0`) is found
+fn literal_key(expr: &Expression) -> Option<(String, String)> {
+	if let Some(number) = constant_number(expr) {
+		let mut key = String::new();
+		for ctoken in expr {
+			if let SYMBOL(lexeme, _) = ctoken {
+				key += lexeme
+			}
+		}
+		return Some((key, number.to_string()));
+	}
+	let [SYMBOL(lexeme, _)] = expr.iter().collect::<Vec<_>>()[..] else {
+		return None;
+	};
+	match lexeme.as_str() {
+		"true" | "false" => Some((lexeme.clone(), lexeme.clone())),
+		// the escapes could write the same string differently
+		string if string.starts_with(['"', '\'']) && !string.contains('\\') => {
+			let contents = &string[1..string.len() - 1];
+			Some((lexeme.clone(), format!("\"{contents}")))
+		}
+		_ => None,
+	}
+}
+
+// the type of a value for the EmmyLua annotations, when it can be known from the value alone
+fn annotation_type(value: &Expression) -> &'static str {
+	match value.iter().collect::<Vec<_>>().as_slice() {
+		[TABLE { .. }] => "table",
+		[LAMBDA { .. }] => "function",
+		[SYMBOL(lexeme, _)] if lexeme == "true" || lexeme == "false" => "boolean",
+		[SYMBOL(lexeme, _)] if lexeme.starts_with(['"', '\'', '[']) => "string",
+		_ if constant_number(value).is_some() => "number",
+		_ => "any",
+	}
+}
+
+// the `@param` annotations of the arguments of a function, the ones with a default value are optional
+fn param_annotations(args: &FunctionArgs) -> Vec<String> {
+	args.iter()
+		.map(|(arg, default)| match default {
+			Some((default, _)) => format!("param {arg}? {}", annotation_type(default)),
+			None => format!("param {arg} any"),
+		})
+		.collect()
+}
+
+// the value of an expression made of just a number, like `10` or `-1`
+fn constant_number(expr: &Expression) -> Option<f64> {
+	let mut number = String::new();
+	for ctoken in expr {
+		let SYMBOL(lexeme, _) = ctoken else {
+			return None;
+		};
+		number += lexeme.trim();
+	}
+	number.parse().ok()
+}
+
+// finds the `break` and `continue` statements jumping to a label, without entering functions,
+// and the nested loops using the same label
+struct LabelFinder<'a> {
+	label: &'a str,
+	found: bool,
+	shadowed: bool,
+}
+
+impl Visitor for LabelFinder<'_> {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			BREAK_LOOP(Some(label), _) | CONTINUE_LOOP(Some(label), _) if label == self.label => {
+				self.found = true
+			}
+			WHILE_LOOP {
+				label: Some(label), ..
+			}
+			| LOOP_UNTIL {
+				label: Some(label), ..
+			}
+			| FOR_LOOP {
+				label: Some(label), ..
+			}
+			| FOR_FUNC_LOOP {
+				label: Some(label), ..
+			} if label == self.label => self.shadowed = true,
+			FUNCTION { .. } | LAMBDA { .. } | TRY_CATCH { .. } | WITH_BLOCK { .. } => {}
+			_ => walk_ctoken(self, ctoken),
+		}
+	}
+}
+
+// finds the statements leaving a block of code other than by reaching its end (`return`, `goto`,
+// `break` and `continue` to loops around it) and the uses of the varargs, without entering functions
+struct BranchExits {
+	// the labels of the loops inside the block around the current statement
+	loops: Vec<Option<String>>,
+	found: bool,
+	varargs: bool,
+}
+
+impl Visitor for BranchExits {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			RETURN_EXPR(_, _) | GOTO_LABEL(_, _) => self.found = true,
+			BREAK_LOOP(None, _) | CONTINUE_LOOP(None, _) if self.loops.is_empty() => {
+				self.found = true
+			}
+			BREAK_LOOP(Some(label), _) | CONTINUE_LOOP(Some(label), _)
+				if !self.loops.contains(&Some(label.clone())) =>
+			{
+				self.found = true
+			}
+			WHILE_LOOP { label, .. }
+			| LOOP_UNTIL { label, .. }
+			| FOR_LOOP { label, .. }
+			| FOR_FUNC_LOOP { label, .. } => {
+				self.loops.push(label.clone());
+				walk_ctoken(self, ctoken);
+				self.loops.pop();
+			}
+			FUNCTION { .. } | LAMBDA { .. } | CONST_BLOCK { .. } => {}
+			_ => walk_ctoken(self, ctoken),
+		}
+	}
+
+	fn visit_symbol(&mut self, symbol: &str) {
+		if symbol == "..." {
+			self.varargs = true;
+		}
+	}
+}
+
+// finds the statements using syntax from future versions of Clue, with their keyword, version and start
+struct UnsupportedFinder(Vec<(String, String, TokenPosition)>);
+
+impl Visitor for UnsupportedFinder {
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			UNSUPPORTED {
+				keyword,
+				version,
+				span,
+			} => self.0.push((keyword.clone(), version.clone(), span.start)),
+			_ => walk_ctoken(self, ctoken),
+		}
+	}
+}
+
+// the `[value] = "MEMBER"` keys of an enum compiled with `EnumMode::Reverse`,
+// except for the string values which are the name of a member, since they would replace it
+fn reverse_enum_members(
+	members: &[(String, Value, Span)],
+) -> Vec<(Option<Expression>, Expression, Span)> {
+	members
+		.iter()
+		.filter(|(_, value, _)| {
+			!matches!(value, Value::String(_, string) if members.iter().any(|(member, ..)| member == string))
+		})
+		.map(|(member, value, span)| {
+			let key = vec_deque![SYMBOL(format_clue!("[", value.to_symbol(), "]"), span.clone())];
+			let name = vec_deque![SYMBOL(format_clue!("\"", member, "\""), span.clone())];
+			(Some(key), name, span.clone())
+		})
+		.collect()
+}
+
+// turns the globals declared at the top level of the file into locals declared at its start,
+// which it returns in a table at its end, so that it can be required as a module.
+// The locals are declared first so that the functions can use the ones declared after them
+fn export_globals(expr: &mut Expression) -> Result<(), String> {
+	let mut exports = Vec::new();
+	for ctoken in expr.iter_mut() {
+		match ctoken {
+			VARIABLE {
+				local: false,
+				names,
+				values,
+				span,
+				..
+			} => {
+				exports.extend(names.iter().cloned());
+				let names = names
+					.drain(..)
+					.map(|name| vec_deque![SYMBOL(name, span.clone())]);
+				*ctoken = ALTER {
+					kind: DEFINE,
+					names: names.collect(),
+					values: std::mem::take(values),
+					span: span.clone(),
+				};
+			}
+			FUNCTION {
+				local: false, name, ..
+			} if matches!((name.len(), name.front()), (1, Some(SYMBOL(_, _)))) => {
+				let Some(SYMBOL(global, span)) = name.pop_front() else {
+					unreachable!()
+				};
+				exports.push(global.clone());
+				// the compiler only declares a global when the name is a single SYMBOL
+				name.push_back(IDENT {
+					expr: vec_deque![SYMBOL(global, span.clone())],
+					span,
+				});
+			}
+			_ => {}
+		}
+	}
+	if exports.is_empty() {
+		return Ok(());
+	}
+	if let Some(RETURN_EXPR(_, _)) = expr.back() {
+		return Err(String::from(
+			"A file which declares globals can't return a value when they are exported",
+		));
+	}
+	// the code added has no position in the file, so it's put at its start
+	let start = TokenPosition { line: 1, column: 1 };
+	expr.push_front(VARIABLE {
+		local: true,
+		r#const: false,
+		names: exports.clone(),
+		values: Vec::new(),
+		span: start..start,
+	});
+	let values = exports.into_iter().map(|name| {
+		let key = vec_deque![SYMBOL(name.clone(), start..start)];
+		(
+			Some(key),
+			vec_deque![SYMBOL(name, start..start)],
+			start..start,
+		)
+	});
+	expr.push_back(RETURN_EXPR(
+		Some(vec![vec_deque![TABLE {
+			values: values.collect(),
+			metas: Vec::new(),
+			metatable: None,
+			span: start..start,
+		}]]),
+		start..start,
+	));
+	Ok(())
+}
+
+// the single error reported when the code uses syntax from future versions of Clue,
+// which tells the version needed instead of the syntax errors that syntax would cause
+fn unsupported_syntax(expr: &Expression, statics: &Expression) -> Option<ParseError> {
+	let mut finder = UnsupportedFinder(Vec::new());
+	finder.visit_expression(statics);
+	finder.visit_expression(expr);
+	let mut found = finder.0;
+	found.sort_by_key(|(_, _, start)| *start);
+	let needed = found
+		.iter()
+		.map(|(_, needed, _)| needed)
+		.max_by_key(|needed| {
+			needed
+				.split('.')
+				.filter_map(|n| n.parse().ok())
+				.collect::<Vec<u32>>()
+		})?;
+	let uses: Vec<String> = found
+		.iter()
+		.map(|(keyword, _, start)| format!("'{keyword}' at {}:{}", start.line, start.column))
+		.collect();
+	let first = found[0].2;
+	Some(ParseError {
+		message: format!(
+			"This code needs Clue {needed} or later because it uses {}, but this is Clue {}",
+			uses.join(", "),
+			env!("CARGO_PKG_VERSION")
+		),
+		line: first.line,
+		column: first.column,
+	})
+}
+
+// warns about the locals which are never read, the ones meant to be unused can start with `_`
+fn warn_unused_locals(expr: &Expression, statics: &Expression, filename: &str, options: &Options) {
+	// the library of the bitwise operations is loaded by the preprocessor and used by the compiler
+	let bit = options.env_jitbit.as_deref();
+	let unused = unused_locals(expr, statics).into_iter();
+	for local in unused.filter(|local| Some(local.name.as_str()) != bit) {
+		let kind = match local.kind {
+			LocalKind::Variable => "local variable",
+			LocalKind::Argument => "argument",
+			LocalKind::Function => "local function",
+		};
+		eprintln!(
+			"Warning in {filename}:{}!\nWarning: \"The {kind} '{}' is never read, name it '_{}' if it's meant to be unused\"",
+			local.line, local.name, local.name
+		);
+	}
+}
+
+// counts the uses of a rest parameter and those which are spreads that can use `...` directly,
+// which are the ones outside of nested functions since those have their own varargs
+struct RestUses<'a> {
+	rest: &'a str,
+	unpack: &'a str,
+	spreads: usize,
+	total: usize,
+}
+
+impl RestUses<'_> {
+	// whether the expression is `unpack(rest)`, which is what `...rest` becomes as the last argument
+	fn is_spread(rest: &str, unpack: &str, expr: &Expression) -> bool {
+		let (Some(SYMBOL(function, _)), Some(CALL(args, _)), 2) =
+			(expr.front(), expr.get(1), expr.len())
+		else {
+			return false;
+		};
+		let [arg] = &args[..] else {
+			return false;
+		};
+		let (Some(IDENT { expr: ident, .. }), 1) = (arg.front(), arg.len()) else {
+			return false;
+		};
+		function == unpack
+			&& ident.len() == 1
+			&& matches!(ident.front(), Some(SYMBOL(name, _)) if name == rest)
+	}
+
+	// replaces every spread of the rest parameter outside of nested functions with `...`
+	fn spread_varargs(rest: &str, unpack: &str, code: &mut Expression) {
+		struct Spreads<'a>(&'a str, &'a str);
+
+		impl Transformer for Spreads<'_> {
+			fn transform_expression(&mut self, expr: &mut Expression) {
+				if RestUses::is_spread(self.0, self.1, expr) {
+					let span = expression_span(expr);
+					*expr = vec_deque![SYMBOL(String::from("..."), span)];
+				} else {
+					walk_expression_mut(self, expr)
+				}
+			}
+
+			fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+				if !matches!(ctoken, FUNCTION { .. } | LAMBDA { .. }) {
+					walk_ctoken_mut(self, ctoken)
+				}
+			}
+		}
+
+		Spreads(rest, unpack).transform_expression(code)
+	}
+}
+
+impl Visitor for RestUses<'_> {
+	fn visit_expression(&mut self, expr: &Expression) {
+		if Self::is_spread(self.rest, self.unpack, expr) {
+			self.spreads += 1;
+			self.total += 1;
+		} else {
+			walk_expression(self, expr)
+		}
+	}
+
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		match ctoken {
+			// uses inside nested functions are never spreads of these varargs
+			FUNCTION { .. } | LAMBDA { .. } => {
+				let spreads = self.spreads;
+				walk_ctoken(self, ctoken);
+				self.spreads = spreads;
+			}
+			_ => walk_ctoken(self, ctoken),
+		}
+	}
+
+	fn visit_symbol(&mut self, symbol: &str) {
+		if symbol == self.rest {
+			self.total += 1;
+		}
+	}
+}
+
+// replaces the placeholder argument added by `build_pipe` with the piped value
+struct PipeValue(Option<Expression>);
+
+impl Transformer for PipeValue {
+	fn transform_expression(&mut self, expr: &mut Expression) {
+		if let [IDENT { expr: ident, .. }] = expr.make_contiguous() {
+			if let [SYMBOL(name, _)] = ident.make_contiguous() {
+				if name == "|>" {
+					if let Some(value) = self.0.take() {
+						*expr = value;
+					}
+					return;
+				}
+			}
+		}
+		walk_expression_mut(self, expr)
+	}
+}
+
+/// The version of the AST format produced by [`ast_to_json`].
+///
+/// It is increased every time a change to [`ComplexToken`] alters the shape of the serialized tree,
+/// so that external tools can refuse documents they do not understand.
+pub const AST_FORMAT_VERSION: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A whole parsed file, as returned by [`parse_tokens`], ready to be serialized.
+///
+/// Every [`ComplexToken`] is serialized as an object with a single key (the variant name)
+/// holding its fields.
+pub struct AstDocument {
+	/// The version of the format, see [`AST_FORMAT_VERSION`].
+	pub version: u32,
+
+	/// The name of the file the AST was parsed from.
+	pub filename: String,
+
+	/// The static variables of the file.
+	pub statics: Expression,
+
+	/// The AST of the file.
+	pub ast: Expression,
+}
+
+impl AstDocument {
+	/// Creates a new [`AstDocument`] from the output of [`parse_tokens`].
+	pub fn new(filename: impl Into<String>, (ast, statics): (Expression, Expression)) -> Self {
+		Self {
+			version: AST_FORMAT_VERSION,
+			filename: filename.into(),
+			statics,
+			ast,
+		}
+	}
+}
+
+#[cfg(feature = "json")]
+/// Serializes the output of [`parse_tokens`] as a JSON [`AstDocument`].
+/// If `pretty` is `true` the JSON will be indented.
+///
+/// # Errors
+/// Returns an [`Err`] if the AST could not be serialized.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, parse_source, parser::ast_to_json};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let parsed = parse_source("local a = 1".to_owned(), &filename, &Options::default())?;
+///     let json = ast_to_json(filename, parsed, false)?;
+///     assert!(json.starts_with("{\"version\":10"));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn ast_to_json(
+	filename: impl Into<String>,
+	parsed: (Expression, Expression),
+	pretty: bool,
+) -> Result<String, String> {
+	let document = AstDocument::new(filename, parsed);
+	let result = if pretty {
+		serde_json::to_string_pretty(&document)
+	} else {
+		serde_json::to_string(&document)
+	};
+	result.map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "json")]
+/// Deserializes a JSON [`AstDocument`] produced by [`ast_to_json`].
+///
+/// # Errors
+/// Returns an [`Err`] if the JSON is invalid or if it was made with a different [`AST_FORMAT_VERSION`].
+pub fn ast_from_json(json: &str) -> Result<AstDocument, String> {
+	let document: AstDocument = check!(serde_json::from_str(json));
+	if document.version != AST_FORMAT_VERSION {
+		return Err(format!(
+			"Unsupported AST format version {} (expected {})",
+			document.version, AST_FORMAT_VERSION
+		));
+	}
+	Ok(document)
+}
+
+/// Parses a list of tokens into an expression
+/// Takes a list of [`Token`]s, a filename, the [`Remapping`] of the preprocessed code and [`Options`]
+/// Returns an expression and the statements defining the static variables,
+/// which must be put before the rest of the code of every file once compiled
+///
+/// # Errors
+/// Returns an [`Err`] containing the error message if an unexpected [`Token`] is found.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, parser::*, preprocessor::*, scanner::*};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let filename = String::from("fizzbuzz.clue");
+///     let mut code = include_str!("../../examples/fizzbuzz.clue").to_owned();
+///
+///     let (codes, variables, .., mut remapping) = preprocess_code(
+///         unsafe { code.as_bytes_mut() },
+///         1,
+///         false,
+///         &filename,
+///         &options,
+///     )?;
+///     let codes = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+///     let tokens = scan_code_with_options(codes, &filename, &remapping, &options)?;
+///     let (expr, statics) = parse_tokens(tokens, &filename, &remapping, &options)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn parse_tokens(
+	tokens: Vec<Token>,
+	//locals: Option<AHashMap<String, LuaType>>,
+	filename: &String,
+	remapping: &Remapping,
+	options: &Options,
+) -> Result<(Expression, Expression), String> {
+	parse_tokens_with_trace(tokens, filename, remapping, options, None)
+}
+
+/// Parses a list of tokens like [`parse_tokens`], also returning a log of every decision
+/// the parser made, even if parsing failed, for finding out why some code is parsed wrongly
+///
+/// Every line of the log is an event: `<depth> <kind> <line>:<column> <detail>`,
+/// where the kind is `>` when a rule (like `statement` or `expression`) starts,
+/// `<` when it ends, `x` when it fails, `.` when a token is consumed
+/// and `!` when the parser goes back to try parsing the code differently
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, parser::*, preprocessor::Remapping, scan_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let tokens = scan_source("local a = 1".to_owned(), &filename, &options)?;
+///     let (result, trace) = parse_tokens_traced(tokens, &filename, &Remapping::default(), &options);
+///     assert!(result.is_ok());
+///     assert!(trace.starts_with("0 . 1:1 LOCAL \"local\"\n0 > 1:1 statement\n"));
+///     assert!(trace.contains("2 . 1:11 NUMBER \"1\""));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn parse_tokens_traced(
+	tokens: Vec<Token>,
+	filename: &String,
+	remapping: &Remapping,
+	options: &Options,
+) -> (Result<(Expression, Expression), String>, String) {
+	let trace = Rc::new(RefCell::new(ParseTrace::default()));
+	let result = parse_tokens_with_trace(tokens, filename, remapping, options, Some(trace.clone()));
+	(result, trace.take().log)
+}
+
+fn parse_tokens_with_trace(
+	tokens: Vec<Token>,
+	filename: &String,
+	remapping: &Remapping,
+	options: &Options,
+	trace: Option<Rc<RefCell<ParseTrace>>>,
+) -> Result<(Expression, Expression), String> {
+	let (mut expr, statics, _) = parse_tokens_internal(
+		tokens,
+		filename,
+		remapping,
+		options,
+		false,
+		Declarations::default(),
+		trace,
+	)?;
+	if let Some(error) = options
+		.env_forward_compat
+		.then(|| unsupported_syntax(&expr, &statics))
+		.flatten()
+	{
+		remapping.print_location("Error", filename, error.line, error.column);
+		return Err(error.message);
+	}
+	warn_unused_locals(&expr, &statics, filename, options);
+	if options.env_exports {
+		export_globals(&mut expr)?;
+	}
+	Ok((expr, statics))
+}
+
+/// Parses a list of tokens into an expression without stopping at the first error
+/// Takes a list of [`Token`]s, a filename, and [`Options`]
+/// Returns the (partial) expression, the statics and every [`ParseError`] found
+///
+/// Every statement that could not be parsed is replaced by an [`ERROR`] node
+/// and parsing resumes from the next `;`, `}` or statement keyword.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, parser::*, scan_source};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let tokens = scan_source("local a = 1 +; print(a)".to_owned(), &filename, &options)?;
+///     let (expr, _, errors) = parse_tokens_recovering(tokens, &filename, &options);
+///     assert_eq!(errors.len(), 1);
+///     assert_eq!(expr.len(), 2);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn parse_tokens_recovering(
+	tokens: Vec<Token>,
+	filename: &String,
+	options: &Options,
+) -> (Expression, Expression, Vec<ParseError>) {
+	let (expr, statics, mut errors) = parse_tokens_internal(
+		tokens,
+		filename,
+		&Remapping::default(),
+		options,
+		true,
+		Declarations::default(),
+		None,
+	)
+	.expect("the parser should never fail while recovering from errors");
+	if options.env_forward_compat {
+		errors.extend(unsupported_syntax(&expr, &statics));
+	}
+	(expr, statics, errors)
+}
+
+fn parse_tokens_internal(
+	tokens: Vec<Token>,
+	filename: &String,
+	remapping: &Remapping,
+	options: &Options,
+	recover: bool,
+	declarations: Declarations,
+	trace: Option<Rc<RefCell<ParseTrace>>>,
+) -> Result<(Expression, Expression, Vec<ParseError>), String> {
+	let mut i = ParserInfo::new(tokens /* , locals */, filename, remapping, options);
+	i.recover = recover;
+	i.declarations = declarations;
+	i.trace = trace;
+	while !i.ended() {
+		let start = i.current;
+		let t = i.advance();
+		match i.parse_statement(&t) {
+			Ok(true) => {}
+			Ok(false) => break,
+			Err(message) if recover => {
+				let (line, column) = i.error_location;
+				i.errors.push(ParseError {
+					message: message.clone(),
+					line,
+					column,
+				});
+				i.expr.push_back(ERROR {
+					message,
+					line,
+					column,
+				});
+				i.synchronize(start);
+			}
+			Err(e) => return Err(e),
+		}
+	}
+
+	//println!("LOCALS = {:#?}", i.locals);
+
+	Ok((i.expr, i.statics, i.errors))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{parse_source, scan_source};
+
+	#[test]
+	fn recovers_from_errors() {
+		let filename = String::from("(library)");
+		let options = Options::default();
+		let tokens = scan_source(
+			"local a = 1 +;\nif a { b c }\nprint(a)".to_owned(),
+			&filename,
+			&options,
+		)
+		.unwrap();
+		let (expr, _, errors) = parse_tokens_recovering(tokens, &filename, &options);
+		assert_eq!(errors.len(), 2);
+		assert_eq!(errors[0].line, 1);
+		assert_eq!(errors[1].line, 2);
+		assert!(matches!(expr[0], ERROR { .. }));
+		assert!(matches!(expr[1], IF_STATEMENT { .. }));
+		assert!(matches!(expr.back(), Some(IDENT { .. })));
+	}
+
+	#[test]
+	fn spans() {
+		let filename = String::from("(library)");
+		let code =
+			"local fn f(a) {\n\treturn a + 1\n}\nif f(1) {\n\tprint(a)\n} else {}\ntry {} catch {}";
+		let (expr, _) = parse_source(code.to_owned(), &filename, &Options::default()).unwrap();
+		let span = |(line, column), (end_line, end_column)| {
+			TokenPosition { line, column }..TokenPosition {
+				line: end_line,
+				column: end_column,
+			}
+		};
+		let FUNCTION {
+			code,
+			span: function,
+			..
+		} = &expr[0]
+		else {
+			panic!("expected a function, got {:?}", expr[0])
+		};
+		assert_eq!(*function, span((1, 1), (3, 2)));
+		assert_eq!(code.code[0].span(), span((2, 2), (2, 14)));
+		let IF_STATEMENT {
+			condition,
+			span: statement,
+			..
+		} = &expr[1]
+		else {
+			panic!("expected an if statement, got {:?}", expr[1])
+		};
+		assert_eq!(*statement, span((4, 1), (6, 2)));
+		let Some(IDENT { expr: call, .. }) = condition.front() else {
+			panic!("expected a call, got {condition:?}")
+		};
+		assert_eq!(call[0].span(), span((4, 4), (4, 5)));
+		assert_eq!(call[1].span(), span((4, 5), (4, 8)));
+		assert_eq!(expr[2].span(), span((7, 1), (7, 16)));
+	}
+
+	#[test]
+	fn goto_and_labels() {
+		let filename = String::from("(library)");
+		let code = "f()\n::top::\nt.goto = 1\ngoto top";
+		let (expr, _) = parse_source(code.to_owned(), &filename, &Options::default()).unwrap();
+		assert!(matches!(expr[0], IDENT { .. }));
+		assert!(matches!(&expr[1], LABEL(label, _) if label == "top"));
+		assert!(matches!(&expr[3], GOTO_LABEL(label, _) if label == "top"));
+		for target in [LuaVersion::Lua51, LuaVersion::Luau] {
+			let options = Options {
+				env_target: Some(target),
+				..Options::default()
+			};
+			assert!(parse_source(code.to_owned(), &filename, &options).is_err());
+		}
+	}
+
+	#[test]
+	fn parallel_assignment() {
+		let filename = String::from("(library)");
+		let parse = |code: &str| parse_source(code.to_owned(), &filename, &Options::default());
+		let (expr, _) = parse("x, y = y, x\na, b += 1, 2\na, b ??= f(), g()").unwrap();
+		assert!(
+			matches!(&expr[0], ALTER { names, values, .. } if names.len() == 2 && values.len() == 2)
+		);
+		assert!(matches!(&expr[1], ALTER { kind: INCREASE, names, .. } if names.len() == 2));
+		assert!(matches!(expr[2], IF_STATEMENT { .. }) && matches!(expr[3], IF_STATEMENT { .. }));
+		parse("a, b = f()\nlocal c, d = 1\nc, d = 1, 2, 3").unwrap();
+		assert!(parse("a, b += 1").is_err());
+		assert!(parse("a, b += f()").is_err());
+		assert!(parse("a ..= 1, 2").is_err());
+	}
+
+	#[test]
+	fn positional_named_args() {
+		let options = Options {
+			env_named_args: NamedArgsMode::Positional,
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let (expr, _) = parse_source(
+			"local fn f(a, b, c) {}\nf(1, c = 3)".to_owned(),
+			&filename,
+			&options,
+		)
+		.unwrap();
+		let Some(IDENT { expr: call, .. }) = expr.back() else {
+			panic!("expected a call, got {expr:?}")
+		};
+		let Some(CALL(args, _)) = call.back() else {
+			panic!("expected a call, got {call:?}")
+		};
+		let symbols: Vec<&str> = args
+			.iter()
+			.map(|arg| match (arg.len(), arg.front()) {
+				(1, Some(SYMBOL(symbol, _))) => symbol.as_str(),
+				_ => panic!("expected a symbol, got {arg:?}"),
+			})
+			.collect();
+		assert_eq!(symbols, ["1", "nil", "3"]);
+		assert!(parse_source("f(c = 3)".to_owned(), &filename, &options).is_err());
+	}
+
+	#[test]
+	fn struct_annotations() {
+		let options = Options {
+			env_annotations: true,
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let code =
+			"local struct Point { x = 0, name\nconstructor(x, y = 1) {}\nmethod move(dx) {} }";
+		let (expr, _) = parse_source(code.to_owned(), &filename, &options).unwrap();
+		let annotations: Vec<&str> = expr
+			.iter()
+			.filter_map(|ctoken| match ctoken {
+				SYMBOL(symbol, _) => Some(symbol.as_str()),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(
+			annotations,
+			[
+				"---@class Point",
+				"---@field x number",
+				"---@field name any",
+				"---@param x any",
+				"---@param y? number",
+				"---@return Point",
+				"---@param dx any",
+			]
+		);
+		assert!(matches!(expr.back(), Some(FUNCTION { .. })));
+	}
+
+	#[test]
+	fn match_dispatch() {
+		let options = Options {
+			env_match_dispatch: Some(3),
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let parse = |code: &str| {
+			parse_source(code.to_owned(), &filename, &options)
+				.unwrap()
+				.0
+		};
+		let expr = parse("match x { 1 || 2 => { f() } \"a\" => { g() } default => { h() } }");
+		let Some(DO_BLOCK(block, _)) = expr.back() else {
+			panic!("expected a dispatch table, got {expr:?}")
+		};
+		let Some(VARIABLE { values, .. }) = block.code.get(2) else {
+			panic!("expected the table of the handlers, got {block:?}")
+		};
+		let Some(TABLE { values, .. }) = values[0].front() else {
+			panic!("expected the table of the handlers, got {values:?}")
+		};
+		assert_eq!(values.len(), 3);
+		// too few literals, duplicated literals and branches that return
+		for code in [
+			"match x { 1 => { f() } 2 => { g() } }",
+			"match x { 1 => { f() } 2 => { g() } 1.0 => { h() } }",
+			"local fn a() { match x { 1 => { return 1 } 2 => { g() } 3 => { h() } } }",
+			"while true { match x { 1 => { break } 2 => { g() } 3 => { h() } } }",
+		] {
+			assert!(!format!("{:?}", parse(code)).contains("DO_BLOCK"), "{code}");
+		}
+		let expr = parse("match x { 1 => { while y { break } } 2 => { g() } 3 => { h() } }");
+		assert!(matches!(expr.back(), Some(DO_BLOCK(_, _))));
+	}
+
+	#[test]
+	fn exports() {
+		let options = Options {
+			env_exports: true,
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let code = "local a = 1\nglobal fn f() { return g() }\nglobal g = fn() => a\nif a { global c = 2 }";
+		let (expr, _) = parse_source(code.to_owned(), &filename, &options).unwrap();
+		let Some(VARIABLE { names, .. }) = expr.front() else {
+			panic!("expected the declaration of the exports, got {expr:?}")
+		};
+		// the globals declared in a block stay globals
+		assert_eq!(names, &["f", "g"]);
+		assert!(matches!(expr.back(), Some(RETURN_EXPR(_, _))));
+		assert!(parse_source(format!("{code}\nreturn a"), &filename, &options).is_err());
+		let (expr, _) = parse_source(String::from("return 1"), &filename, &options).unwrap();
+		assert_eq!(expr.len(), 1);
+	}
+
+	#[test]
+	fn enum_modes() {
+		let filename = String::from("(library)");
+		let parse = |code: &str, mode| {
+			let options = Options {
+				env_enums: mode,
+				..Options::default()
+			};
+			parse_source(code.to_owned(), &filename, &options)
+		};
+		let table_keys = |expr: &Expression| {
+			let Some(VARIABLE { values, .. }) = expr.front() else {
+				panic!("expected the table of the enum, got {expr:?}")
+			};
+			let Some(TABLE { values, .. }) = values[0].front() else {
+				panic!("expected the table of the enum, got {values:?}")
+			};
+			values.len()
+		};
+		let code = "local enum Side { LEFT, RIGHT = \"LEFT\" }\nprint(Side.RIGHT)";
+		let (expr, _) = parse(code, EnumMode::Table).unwrap();
+		assert_eq!(table_keys(&expr), 2);
+		// `[1] = "LEFT"`, but not `["LEFT"] = "RIGHT"`
+		let (expr, _) = parse(code, EnumMode::Reverse).unwrap();
+		assert_eq!(table_keys(&expr), 3);
+		let (expr, _) = parse(code, EnumMode::Inline).unwrap();
+		assert_eq!(expr.len(), 1);
+		assert!(format!("{expr:?}").contains(r#"SYMBOL("\"LEFT\"", "#));
+		// the attribute wins over the option, and the table of a global enum is kept
+		let (expr, _) = parse(&format!("@reverse\n{code}"), EnumMode::Inline).unwrap();
+		assert_eq!(table_keys(&expr), 3);
+		let (expr, _) = parse("@inline\nglobal enum A { X }\nprint(A.X)", EnumMode::Table).unwrap();
+		assert_eq!(table_keys(&expr), 1);
+		assert!(format!("{:?}", expr[1]).contains(r#"SYMBOL("1", "#));
+		assert!(parse("@inline\nlocal enum A { X }\nprint(A.Y)", EnumMode::Table).is_err());
+	}
+
+	#[test]
+	fn chained_comparisons() {
+		let options = Options {
+			env_chained_comparisons: true,
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let (expr, _) = parse_source(
+			"print(0 <= x < 10, 0 < f() <= 1)".to_owned(),
+			&filename,
+			&options,
+		)
+		.unwrap();
+		assert!(matches!(&expr[0], VARIABLE { names, .. } if names == &["_internal0"]));
+		let Some(IDENT { expr: call, .. }) = expr.back() else {
+			panic!("expected a call, got {expr:?}")
+		};
+		let Some(CALL(args, _)) = call.back() else {
+			panic!("expected a call, got {call:?}")
+		};
+		let symbols = |arg: &Expression| -> Vec<String> {
+			arg.iter()
+				.map(|ctoken| match ctoken {
+					SYMBOL(symbol, _) => symbol.clone(),
+					IDENT { expr, .. } => match &expr[0] {
+						SYMBOL(name, _) => name.clone(),
+						ctoken => panic!("expected a name, got {ctoken:?}"),
+					},
+					EXPR(expr, _) if matches!(expr[0], LAMBDA { .. }) => String::from("function"),
+					CALL(args, _) if args.is_empty() => String::from("()"),
+					ctoken => panic!("expected a symbol, got {ctoken:?}"),
+				})
+				.collect()
+		};
+		assert_eq!(symbols(&args[0]), ["0", "<=", "x", " and ", "x", "<", "10"]);
+		assert_eq!(
+			symbols(&args[1]),
+			["0", "<", "function", "()", " and ", "_internal0", "<=", "1"]
+		);
+	}
+
+	#[test]
+	fn match_expressions() {
+		let filename = String::from("(library)");
+		let (expr, _) = parse_source(
+			"local a = match x { 1 => \"one\", default => \"many\" }\n\
+			local b = x && match f() { 1 => \"one\" }\n\
+			local c = match x { 1 => f(), default => nil }"
+				.to_owned(),
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let value = |ctoken: &ComplexToken| match ctoken {
+			VARIABLE { values, .. } => values[0].clone(),
+			ctoken => panic!("expected a variable, got {ctoken:?}"),
+		};
+		assert!(matches!(&value(&expr[0])[0], EXPR(chain, _) if chain.len() == 5));
+		assert!(
+			matches!(value(&expr[1])[2], EXPR(ref function, _) if matches!(function[0], LAMBDA { .. }))
+		);
+		assert!(matches!(expr[2], MATCH_BLOCK { .. }));
+		assert!(matches!(&value(&expr[3])[0], SYMBOL(name, _) if name == "_internal2"));
+	}
+
+	#[test]
+	fn block_starting_with_minus() {
+		let filename = String::from("(library)");
+		let (expr, _) = parse_source(
+			"local a = do { -3 }".to_owned(),
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		assert!(matches!(&expr[0], VARIABLE { values, .. } if values[0].len() == 1));
+	}
+
+	#[test]
+	fn const_blocks() {
+		let filename = String::from("(library)");
+		let (expr, _) = parse_source(
+			"local a = const { local t = {}; t[1] = 2; t }\nlocal b = 2 - const { -3 }".to_owned(),
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let value = |ctoken: &ComplexToken| match ctoken {
+			VARIABLE { values, .. } => values[0].clone(),
+			ctoken => panic!("expected a variable, got {ctoken:?}"),
+		};
+		let block = value(&expr[0]);
+		assert!(matches!(&block[0], CONST_BLOCK { code, .. }
+			if code.code.len() == 3 && matches!(code.code[2], RETURN_EXPR(_, _))));
+		let block = value(&expr[1]);
+		assert!(matches!(block[2], CONST_BLOCK { .. }));
+		assert_eq!(expr.len(), 2);
+	}
+
+	#[test]
+	fn block_value_errors_at_the_block() {
+		let filename = String::from("(library)");
+		let options = Options::default();
+		for keyword in ["do", "const"] {
+			let tokens = scan_source(
+				format!("local a = {keyword} {{\n\tlocal b = 1\n}}\nprint(a)\n\nprint(a)"),
+				&filename,
+				&options,
+			)
+			.unwrap();
+			let (_, _, errors) = parse_tokens_recovering(tokens, &filename, &options);
+			assert_eq!(errors.len(), 1, "{keyword}: {errors:?}");
+			assert_eq!(
+				errors[0].message,
+				format!("The last statement of a '{keyword}' block must be a value")
+			);
+			assert_eq!((errors[0].line, errors[0].column), (3, 1), "{keyword}");
+		}
+	}
+
+	#[test]
+	fn skips_future_syntax() {
+		let options = Options {
+			env_forward_compat: true,
+			..Options::default()
+		};
+		let filename = String::from("(library)");
+		let tokens = scan_source(
+			"extern \"C\" {\n\tint puts(const char *s);\n}\nif a {\n\textern fn f(x)\n}\nprint(a)"
+				.to_owned(),
+			&filename,
+			&options,
+		)
+		.unwrap();
+		let (expr, _, errors) = parse_tokens_recovering(tokens, &filename, &options);
+		assert_eq!(errors.len(), 1);
+		assert_eq!((errors[0].line, errors[0].column), (1, 1));
+		assert!(errors[0].message.contains("Clue 4.0"));
+		assert!(errors[0].message.contains("5:2"));
+		assert!(matches!(expr[0], UNSUPPORTED { .. }));
+		assert!(matches!(expr[1], IF_STATEMENT { .. }));
+		assert!(matches!(expr[2], IDENT { .. }));
+	}
+
+	#[test]
+	#[cfg(feature = "json")]
+	fn ast_json_round_trip() {
+		let parsed = parse_source(
+			include_str!("../../examples/fizzbuzz.clue").to_owned(),
+			&String::from("fizzbuzz.clue"),
+			&Options::default(),
+		)
+		.unwrap();
+		let json = ast_to_json("fizzbuzz.clue", parsed.clone(), true).unwrap();
+		let document = ast_from_json(&json).unwrap();
+		assert_eq!(document.filename, "fizzbuzz.clue");
+		assert_eq!((document.ast, document.statics), parsed);
+	}
+
+	#[test]
+	#[cfg(feature = "json")]
+	fn ast_json_rejects_other_versions() {
+		let json = ast_to_json("(library)", (Expression::new(), Expression::new()), false)
+			.unwrap()
+			.replace(
+				&format!("\"version\":{AST_FORMAT_VERSION}"),
+				"\"version\":0",
+			);
+		assert!(ast_from_json(&json).is_err());
+	}
+}
@@ -0,0 +1,204 @@
+//! The precedence of the operators, which the parser uses to read expressions.
+//!
+//! Expressions are read by a Pratt parser: every binary operator has a precedence in [`PRECEDENCE`],
+//! which follows [the Lua manual](https://www.lua.org/manual/5.4/manual.html#3.4.8),
+//! and the operand on its right is read with the operators that bind tighter than it.
+//! The result is a tree that is turned back into the tokens of the output, where the operators
+//! that Lua doesn't have (`/_`, and the bitwise operators when a bit library is used)
+//! become function calls taking the exact operands they got.
+//!
+//! `|>`, `??` and `? :` bind looser than every operator in the table:
+//! they take the whole expression on their left.
+
+use super::{expression_span, ComplexToken::*, Expression, Span};
+
+/// The binary operators of the generated Lua code, from the loosest to the tightest.
+///
+/// The unary operators (`not`, `#`, `-` and `~`) bind tighter than all of them except `^`,
+/// and only `..` and `^` are right associative.
+/// `//` is the floor division that `/_` is turned into,
+/// `^^` is the exclusive or of the bitwise operators of Clue (when they are kept as they are).
+pub const PRECEDENCE: &[&[&str]] = &[
+	&[" or "],
+	&[" and "],
+	&["<", ">", "<=", ">=", "~=", "=="],
+	&["|"],
+	&["~", "^^"],
+	&["&"],
+	&["<<", ">>"],
+	&[".."],
+	&["+", "-"],
+	&["*", "/", "//", "%"],
+	&["^"],
+];
+
+/// The unary operators of the generated Lua code, `" -"` being a minus that follows another one
+pub const UNARY_OPERATORS: &[&str] = &["not ", "#", "-", " -", "~"];
+
+/// The precedence of the operand of a unary operator, which can only contain `^`
+pub const UNARY_PRECEDENCE: usize = PRECEDENCE.len() - 1;
+
+/// Returns the precedence of a binary operator (its index in [`PRECEDENCE`])
+/// and whether it's right associative
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::parser::precedence::binary_precedence;
+///
+/// let (or, _) = binary_precedence(" or ").unwrap();
+/// let (and, _) = binary_precedence(" and ").unwrap();
+/// let (bit_or, _) = binary_precedence("|").unwrap();
+/// assert!(or < and && and < bit_or);
+/// assert_eq!(binary_precedence(".."), Some((7, true)));
+/// assert_eq!(binary_precedence("not "), None);
+/// ```
+pub fn binary_precedence(op: &str) -> Option<(usize, bool)> {
+	let precedence = PRECEDENCE.iter().position(|level| level.contains(&op))?;
+	Some((precedence, matches!(op, ".." | "^")))
+}
+
+// whether the operator is one of the comparisons that can be chained
+pub(crate) fn is_ordering(op: &str) -> bool {
+	matches!(op, "<" | ">" | "<=" | ">=")
+}
+
+// an expression read by the parser, the operators have the span of their token
+pub(crate) enum Node {
+	// tokens that make up a single value, like a name followed by a call
+	Operand(Expression),
+	Unary(String, Span, Box<Node>),
+	Binary(Box<Node>, String, Span, Box<Node>),
+}
+
+impl Node {
+	// the span from the start of the first token of the node to the end of its last one
+	pub(crate) fn span(&self) -> Span {
+		match self {
+			Node::Operand(expr) => expression_span(expr),
+			Node::Unary(_, span, operand) => span.start..operand.span().end,
+			Node::Binary(left, _, _, right) => left.span().start..right.span().end,
+		}
+	}
+
+	// the operand on the right of the last comparison of `a < b` or of a chain of them
+	pub(crate) fn chained_operand(&mut self) -> Option<&mut Node> {
+		let Node::Binary(_, op, _, right) = self else {
+			return None;
+		};
+		if op == " and " {
+			// the parser only puts a comparison on the left of another one after chaining them
+			right.chained_operand()
+		} else {
+			is_ordering(op).then_some(right)
+		}
+	}
+
+	// turns the tree back into tokens, the operators Lua doesn't have become calls
+	// to `math.floor` or to the functions of the bit library named `bit`
+	pub(crate) fn into_expression(self, bit: Option<&str>) -> Expression {
+		let mut expr = Expression::new();
+		Emitter { bit }.emit(self, &mut expr);
+		expr
+	}
+}
+
+struct Emitter<'a> {
+	bit: Option<&'a str>,
+}
+
+impl Emitter<'_> {
+	// the function the operator is turned into, if it's not left as is
+	fn function(&self, op: &str, unary: bool) -> Option<String> {
+		let name = match (op, unary) {
+			("//", false) => return Some(String::from("math.floor")),
+			("&", false) => "band",
+			("|", false) => "bor",
+			("~", false) => "bxor",
+			("<<", false) => "lshift",
+			(">>", false) => "rshift",
+			("~", true) => "bnot",
+			_ => return None,
+		};
+		self.bit.map(|bit| format!("{bit}.{name}"))
+	}
+
+	// whether the node has to be put in brackets to stay the operand of an operator with
+	// the given precedence, `tighter` meaning that an operator with the same one also needs them.
+	// The trees read from the code never need them, only the ones changed by the parser
+	fn needs_brackets(&self, node: &Node, precedence: usize, tighter: bool) -> bool {
+		match node {
+			Node::Operand(_) => false,
+			// only `(-a)^b`, since `^` is the only operator tighter than the unary ones
+			Node::Unary(op, ..) => {
+				self.function(op, true).is_none() && precedence == UNARY_PRECEDENCE && tighter
+			}
+			Node::Binary(_, op, _, _) if self.function(op, false).is_some() => false,
+			Node::Binary(_, op, _, _) => {
+				let (own, _) = binary_precedence(op).expect("the parser only uses known operators");
+				// `a and (b and c)` is the same as `a and b and c`, and so is `a or (b or c)`
+				own < precedence
+					|| (own == precedence && tighter && !matches!(op.as_str(), " and " | " or "))
+			}
+		}
+	}
+
+	fn emit_operand(&self, node: Node, precedence: usize, tighter: bool, expr: &mut Expression) {
+		if self.needs_brackets(&node, precedence, tighter) {
+			let span = node.span();
+			let mut inner = Expression::new();
+			self.emit(node, &mut inner);
+			expr.push_back(EXPR(inner, span));
+		} else {
+			self.emit(node, expr);
+		}
+	}
+
+	fn emit(&self, node: Node, expr: &mut Expression) {
+		match node {
+			Node::Operand(tokens) => expr.extend(tokens),
+			Node::Unary(op, span, operand) => match self.function(&op, true) {
+				Some(function) => {
+					let call_span = operand.span();
+					expr.push_back(SYMBOL(function, span));
+					expr.push_back(CALL(vec![self.argument(*operand)], call_span));
+				}
+				None => {
+					expr.push_back(SYMBOL(op, span));
+					self.emit_operand(*operand, UNARY_PRECEDENCE, false, expr);
+				}
+			},
+			Node::Binary(left, op, span, right) => {
+				let (precedence, right_associative) =
+					binary_precedence(&op).expect("the parser only uses known operators");
+				// the call of the function an operator is turned into spans both operands
+				let call_span = left.span().start..right.span().end;
+				match self.function(&op, false) {
+					Some(function) if op == "//" => {
+						let mut division = Expression::new();
+						self.emit_operand(*left, precedence, false, &mut division);
+						division.push_back(SYMBOL(String::from("/"), span.clone()));
+						self.emit_operand(*right, precedence, true, &mut division);
+						expr.push_back(SYMBOL(function, span));
+						expr.push_back(CALL(vec![division], call_span));
+					}
+					Some(function) => {
+						let args = vec![self.argument(*left), self.argument(*right)];
+						expr.push_back(SYMBOL(function, span));
+						expr.push_back(CALL(args, call_span));
+					}
+					None => {
+						self.emit_operand(*left, precedence, right_associative, expr);
+						expr.push_back(SYMBOL(op, span));
+						self.emit_operand(*right, precedence, !right_associative, expr);
+					}
+				}
+			}
+		}
+	}
+
+	fn argument(&self, node: Node) -> Expression {
+		let mut argument = Expression::new();
+		self.emit(node, &mut argument);
+		argument
+	}
+}
@@ -0,0 +1,454 @@
+//! Traits to walk the AST produced by the parser without matching every [`ComplexToken`] by hand.
+//!
+//! [`Visitor`] walks the AST by reference and is meant for analysis passes,
+//! while [`Transformer`] walks it mutably and is meant for passes that rewrite it.
+//!
+//! Every method has a default implementation that calls the matching `walk_*` function,
+//! which visits all the children of the node, so an implementation only has to override
+//! the methods for the nodes it cares about (and call the `walk_*` function itself if it
+//! still wants to go deeper).
+
+use super::{CodeBlock, ComplexToken, ComplexToken::*, Expression, FunctionArgs};
+
+/// A read only pass over the AST.
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{env::Options, parse_source, parser::{visitor::Visitor, ComplexToken}};
+///
+/// struct CountFunctions(usize);
+///
+/// impl Visitor for CountFunctions {
+///     fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+///         if let ComplexToken::FUNCTION { .. } | ComplexToken::LAMBDA { .. } = ctoken {
+///             self.0 += 1;
+///         }
+///         clue_frontend::parser::visitor::walk_ctoken(self, ctoken);
+///     }
+/// }
+///
+/// fn main() -> Result<(), String> {
+///     let code = "local fn a() { return fn() {} }".to_owned();
+///     let (ast, _) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     let mut counter = CountFunctions(0);
+///     counter.visit_expression(&ast);
+///     assert_eq!(counter.0, 2);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait Visitor {
+	/// Visits a list of [`ComplexToken`]s, either a statement list or an expression.
+	fn visit_expression(&mut self, expr: &Expression) {
+		walk_expression(self, expr)
+	}
+
+	/// Visits a single [`ComplexToken`].
+	fn visit_ctoken(&mut self, ctoken: &ComplexToken) {
+		walk_ctoken(self, ctoken)
+	}
+
+	/// Visits a [`CodeBlock`].
+	fn visit_code_block(&mut self, block: &CodeBlock) {
+		walk_code_block(self, block)
+	}
+
+	/// Visits the arguments of a function or lambda.
+	fn visit_function_args(&mut self, args: &FunctionArgs) {
+		walk_function_args(self, args)
+	}
+
+	/// Visits the lexeme of a [`SYMBOL`].
+	fn visit_symbol(&mut self, _symbol: &str) {}
+}
+
+/// Visits every [`ComplexToken`] of the [`Expression`].
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+	for ctoken in expr {
+		visitor.visit_ctoken(ctoken);
+	}
+}
+
+/// Visits the code of the [`CodeBlock`].
+pub fn walk_code_block<V: Visitor + ?Sized>(visitor: &mut V, block: &CodeBlock) {
+	visitor.visit_expression(&block.code);
+}
+
+/// Visits the default values of the [`FunctionArgs`].
+pub fn walk_function_args<V: Visitor + ?Sized>(visitor: &mut V, args: &FunctionArgs) {
+	for (_, default) in args {
+		if let Some((default, _)) = default {
+			visitor.visit_expression(default);
+		}
+	}
+}
+
+/// Visits every child of the [`ComplexToken`].
+pub fn walk_ctoken<V: Visitor + ?Sized>(visitor: &mut V, ctoken: &ComplexToken) {
+	match ctoken {
+		VARIABLE { values, .. } => {
+			for value in values {
+				visitor.visit_expression(value);
+			}
+		}
+		ALTER { names, values, .. } => {
+			for name in names {
+				visitor.visit_expression(name);
+			}
+			for value in values {
+				visitor.visit_expression(value);
+			}
+		}
+		TABLE { values, metas, .. } => {
+			for (key, value, _) in values {
+				if let Some(key) = key {
+					visitor.visit_expression(key);
+				}
+				visitor.visit_expression(value);
+			}
+			for (_, value, _) in metas {
+				visitor.visit_expression(value);
+			}
+		}
+		FUNCTION {
+			name,
+			args,
+			code,
+			attributes,
+			..
+		} => {
+			for (_, attribute_args) in attributes {
+				for arg in attribute_args {
+					visitor.visit_expression(arg);
+				}
+			}
+			visitor.visit_expression(name);
+			visitor.visit_function_args(args);
+			visitor.visit_code_block(code);
+		}
+		LAMBDA { args, code, .. } => {
+			visitor.visit_function_args(args);
+			visitor.visit_code_block(code);
+		}
+		IF_STATEMENT {
+			condition,
+			code,
+			next,
+			..
+		} => {
+			visitor.visit_expression(condition);
+			visitor.visit_code_block(code);
+			if let Some(next) = next {
+				visitor.visit_ctoken(next);
+			}
+		}
+		MATCH_BLOCK {
+			value, branches, ..
+		} => {
+			visitor.visit_expression(value);
+			for (conditions, internal_expr, extra_if, code) in branches {
+				for condition in conditions {
+					visitor.visit_expression(condition);
+				}
+				visitor.visit_expression(internal_expr);
+				if let Some(extra_if) = extra_if {
+					visitor.visit_expression(extra_if);
+				}
+				visitor.visit_code_block(code);
+			}
+		}
+		WHILE_LOOP {
+			condition, code, ..
+		}
+		| LOOP_UNTIL {
+			condition, code, ..
+		} => {
+			visitor.visit_expression(condition);
+			visitor.visit_code_block(code);
+		}
+		FOR_LOOP {
+			start,
+			end,
+			alter,
+			code,
+			..
+		} => {
+			visitor.visit_expression(start);
+			visitor.visit_expression(end);
+			visitor.visit_expression(alter);
+			visitor.visit_code_block(code);
+		}
+		FOR_FUNC_LOOP { expr, code, .. } => {
+			visitor.visit_expression(expr);
+			visitor.visit_code_block(code);
+		}
+		TRY_CATCH {
+			totry,
+			catch,
+			finally,
+			..
+		} => {
+			visitor.visit_code_block(totry);
+			if let Some(catch) = catch {
+				visitor.visit_code_block(catch);
+			}
+			if let Some(finally) = finally {
+				visitor.visit_code_block(finally);
+			}
+		}
+		WITH_BLOCK { value, code, .. } => {
+			visitor.visit_expression(value);
+			visitor.visit_code_block(code);
+		}
+		IDENT { expr, .. } | EXPR(expr, _) => visitor.visit_expression(expr),
+		SYMBOL(symbol, _) => visitor.visit_symbol(symbol),
+		CALL(args, _) => {
+			for arg in args {
+				visitor.visit_expression(arg);
+			}
+		}
+		DO_BLOCK(code, _) => visitor.visit_code_block(code),
+		CONST_BLOCK { code, .. } => visitor.visit_code_block(code),
+		RETURN_EXPR(exprs, _) => {
+			if let Some(exprs) = exprs {
+				for expr in exprs {
+					visitor.visit_expression(expr);
+				}
+			}
+		}
+		CONTINUE_LOOP(..)
+		| BREAK_LOOP(..)
+		| GOTO_LABEL(..)
+		| LABEL(..)
+		| UNSUPPORTED { .. }
+		| ERROR { .. } => {}
+	}
+}
+
+/// A pass over the AST that can modify it in place.
+///
+/// # Example
+/// ```rust
+/// use clue_frontend::{
+///     env::Options,
+///     parse_source,
+///     parser::{visitor::Transformer, ComplexToken},
+///     scanner::TokenPosition,
+/// };
+///
+/// struct Rename;
+///
+/// impl Transformer for Rename {
+///     fn transform_symbol(&mut self, symbol: &mut String) {
+///         if symbol == "a" {
+///             *symbol = String::from("b");
+///         }
+///     }
+/// }
+///
+/// fn main() -> Result<(), String> {
+///     let code = "print(a)".to_owned();
+///     let (mut ast, _) = parse_source(code, &String::from("(library)"), &Options::default())?;
+///     Rename.transform_expression(&mut ast);
+///     let ComplexToken::IDENT { expr, .. } = &ast[0] else {
+///         unreachable!()
+///     };
+///     let span = TokenPosition { line: 1, column: 7 }..TokenPosition { line: 1, column: 8 };
+///     let b = [ComplexToken::SYMBOL(String::from("b"), span.clone())].into();
+///     let args = vec![[ComplexToken::IDENT { expr: b, span: span.clone() }].into()];
+///     let call_span = TokenPosition { line: 1, column: 6 }..TokenPosition { line: 1, column: 9 };
+///     assert_eq!(expr[1], ComplexToken::CALL(args, call_span));
+///
+///     Ok(())
+/// }
+/// ```
+pub trait Transformer {
+	/// Transforms a list of [`ComplexToken`]s, either a statement list or an expression.
+	fn transform_expression(&mut self, expr: &mut Expression) {
+		walk_expression_mut(self, expr)
+	}
+
+	/// Transforms a single [`ComplexToken`].
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		walk_ctoken_mut(self, ctoken)
+	}
+
+	/// Transforms a [`CodeBlock`].
+	fn transform_code_block(&mut self, block: &mut CodeBlock) {
+		walk_code_block_mut(self, block)
+	}
+
+	/// Transforms the arguments of a function or lambda.
+	fn transform_function_args(&mut self, args: &mut FunctionArgs) {
+		walk_function_args_mut(self, args)
+	}
+
+	/// Transforms the lexeme of a [`SYMBOL`].
+	fn transform_symbol(&mut self, _symbol: &mut String) {}
+}
+
+/// Transforms every [`ComplexToken`] of the [`Expression`].
+pub fn walk_expression_mut<T: Transformer + ?Sized>(transformer: &mut T, expr: &mut Expression) {
+	for ctoken in expr {
+		transformer.transform_ctoken(ctoken);
+	}
+}
+
+/// Transforms the code of the [`CodeBlock`].
+pub fn walk_code_block_mut<T: Transformer + ?Sized>(transformer: &mut T, block: &mut CodeBlock) {
+	transformer.transform_expression(&mut block.code);
+}
+
+/// Transforms the default values of the [`FunctionArgs`].
+pub fn walk_function_args_mut<T: Transformer + ?Sized>(
+	transformer: &mut T,
+	args: &mut FunctionArgs,
+) {
+	for (_, default) in args {
+		if let Some((default, _)) = default {
+			transformer.transform_expression(default);
+		}
+	}
+}
+
+/// Transforms every child of the [`ComplexToken`].
+pub fn walk_ctoken_mut<T: Transformer + ?Sized>(transformer: &mut T, ctoken: &mut ComplexToken) {
+	match ctoken {
+		VARIABLE { values, .. } => {
+			for value in values {
+				transformer.transform_expression(value);
+			}
+		}
+		ALTER { names, values, .. } => {
+			for name in names {
+				transformer.transform_expression(name);
+			}
+			for value in values {
+				transformer.transform_expression(value);
+			}
+		}
+		TABLE { values, metas, .. } => {
+			for (key, value, _) in values {
+				if let Some(key) = key {
+					transformer.transform_expression(key);
+				}
+				transformer.transform_expression(value);
+			}
+			for (_, value, _) in metas {
+				transformer.transform_expression(value);
+			}
+		}
+		FUNCTION {
+			name,
+			args,
+			code,
+			attributes,
+			..
+		} => {
+			for (_, attribute_args) in attributes {
+				for arg in attribute_args {
+					transformer.transform_expression(arg);
+				}
+			}
+			transformer.transform_expression(name);
+			transformer.transform_function_args(args);
+			transformer.transform_code_block(code);
+		}
+		LAMBDA { args, code, .. } => {
+			transformer.transform_function_args(args);
+			transformer.transform_code_block(code);
+		}
+		IF_STATEMENT {
+			condition,
+			code,
+			next,
+			..
+		} => {
+			transformer.transform_expression(condition);
+			transformer.transform_code_block(code);
+			if let Some(next) = next {
+				transformer.transform_ctoken(next);
+			}
+		}
+		MATCH_BLOCK {
+			value, branches, ..
+		} => {
+			transformer.transform_expression(value);
+			for (conditions, internal_expr, extra_if, code) in branches {
+				for condition in conditions {
+					transformer.transform_expression(condition);
+				}
+				transformer.transform_expression(internal_expr);
+				if let Some(extra_if) = extra_if {
+					transformer.transform_expression(extra_if);
+				}
+				transformer.transform_code_block(code);
+			}
+		}
+		WHILE_LOOP {
+			condition, code, ..
+		}
+		| LOOP_UNTIL {
+			condition, code, ..
+		} => {
+			transformer.transform_expression(condition);
+			transformer.transform_code_block(code);
+		}
+		FOR_LOOP {
+			start,
+			end,
+			alter,
+			code,
+			..
+		} => {
+			transformer.transform_expression(start);
+			transformer.transform_expression(end);
+			transformer.transform_expression(alter);
+			transformer.transform_code_block(code);
+		}
+		FOR_FUNC_LOOP { expr, code, .. } => {
+			transformer.transform_expression(expr);
+			transformer.transform_code_block(code);
+		}
+		TRY_CATCH {
+			totry,
+			catch,
+			finally,
+			..
+		} => {
+			transformer.transform_code_block(totry);
+			if let Some(catch) = catch {
+				transformer.transform_code_block(catch);
+			}
+			if let Some(finally) = finally {
+				transformer.transform_code_block(finally);
+			}
+		}
+		WITH_BLOCK { value, code, .. } => {
+			transformer.transform_expression(value);
+			transformer.transform_code_block(code);
+		}
+		IDENT { expr, .. } | EXPR(expr, _) => transformer.transform_expression(expr),
+		SYMBOL(symbol, _) => transformer.transform_symbol(symbol),
+		CALL(args, _) => {
+			for arg in args {
+				transformer.transform_expression(arg);
+			}
+		}
+		DO_BLOCK(code, _) => transformer.transform_code_block(code),
+		CONST_BLOCK { code, .. } => transformer.transform_code_block(code),
+		RETURN_EXPR(exprs, _) => {
+			if let Some(exprs) = exprs {
+				for expr in exprs {
+					transformer.transform_expression(expr);
+				}
+			}
+		}
+		CONTINUE_LOOP(..)
+		| BREAK_LOOP(..)
+		| GOTO_LABEL(..)
+		| LABEL(..)
+		| UNSUPPORTED { .. }
+		| ERROR { .. } => {}
+	}
+}
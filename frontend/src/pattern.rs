@@ -0,0 +1,255 @@
+//! The pattern module validates Lua patterns, the ones used by `string.match`, `string.gmatch`,
+//! `string.gsub` and `string.find`, at compile time.
+//!
+//! It exposes [`check_pattern`], which checks an already unescaped pattern,
+//! and [`check_pattern_literal`], which checks a string literal as it was written in the source code.
+
+/// The functions of the `string` library which take a pattern as their second argument.
+pub const PATTERN_FUNCTIONS: [&str; 4] = ["match", "gmatch", "gsub", "find"];
+
+/// Checks that the given pattern is a valid Lua pattern.
+///
+/// # Errors
+/// Returns an [`Err`] with the error message and the index of the offending character in the pattern.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::pattern::check_pattern;
+///
+/// assert!(check_pattern("^(%w+)%s*=%s*(.-)$").is_ok());
+/// assert_eq!(check_pattern("%d+%").unwrap_err().1, 3);
+/// ```
+pub fn check_pattern(pattern: &str) -> Result<(), (String, usize)> {
+	let chars: Vec<char> = pattern.chars().collect();
+	let mut i = 0;
+	let mut captures = Vec::new();
+	let mut can_repeat = false;
+	if chars.first() == Some(&'^') {
+		i += 1;
+	}
+	while i < chars.len() {
+		let c = chars[i];
+		match c {
+			'%' => {
+				let class = chars.get(i + 1).copied();
+				i = check_escape(&chars, i)?;
+				// balances, frontiers and back references cannot be repeated
+				can_repeat = !matches!(class, Some('b' | 'f' | '0'..='9'));
+				continue;
+			}
+			'[' => {
+				i = check_set(&chars, i)?;
+				can_repeat = true;
+				continue;
+			}
+			'(' => {
+				captures.push(i);
+				can_repeat = false;
+			}
+			')' => {
+				if captures.pop().is_none() {
+					return Err((String::from("invalid pattern capture, no '(' to close"), i));
+				}
+				can_repeat = false;
+			}
+			'*' | '+' | '-' | '?' if !can_repeat => {
+				return Err((
+					format!("'{c}' has nothing to repeat (it will match a literal '{c}', use '%{c}' instead)"),
+					i,
+				));
+			}
+			'*' | '+' | '-' | '?' => can_repeat = false,
+			'$' if i + 1 == chars.len() => {}
+			_ => can_repeat = true,
+		}
+		i += 1;
+	}
+	match captures.pop() {
+		Some(start) => Err((String::from("unfinished capture, missing ')'"), start)),
+		None => Ok(()),
+	}
+}
+
+fn check_class(chars: &[char], i: usize) -> Result<(), (String, usize)> {
+	let class = chars[i];
+	if class.is_ascii_alphabetic()
+		&& !matches!(
+			class.to_ascii_lowercase(),
+			'a' | 'c' | 'd' | 'g' | 'l' | 'p' | 's' | 'u' | 'w' | 'x'
+		) {
+		return Err((format!("unknown character class '%{class}'"), i - 1));
+	}
+	Ok(())
+}
+
+fn check_escape(chars: &[char], i: usize) -> Result<usize, (String, usize)> {
+	let Some(&class) = chars.get(i + 1) else {
+		return Err((String::from("malformed pattern (ends with '%')"), i));
+	};
+	match class {
+		'b' => {
+			if chars.len() < i + 4 {
+				return Err((String::from("missing arguments to '%b'"), i));
+			}
+			Ok(i + 4)
+		}
+		'f' => {
+			if chars.get(i + 2) != Some(&'[') {
+				return Err((String::from("missing '[' after '%f' in pattern"), i));
+			}
+			check_set(chars, i + 2)
+		}
+		_ => {
+			check_class(chars, i + 1)?;
+			Ok(i + 2)
+		}
+	}
+}
+
+fn check_set(chars: &[char], start: usize) -> Result<usize, (String, usize)> {
+	let mut i = start + 1;
+	if chars.get(i) == Some(&'^') {
+		i += 1;
+	}
+	// a ']' right after the '[' (or '[^') is part of the set
+	if chars.get(i) == Some(&']') {
+		i += 1;
+	}
+	loop {
+		match chars.get(i) {
+			None => return Err((String::from("malformed pattern (missing ']')"), start)),
+			Some(']') => return Ok(i + 1),
+			Some('%') => {
+				if i + 1 >= chars.len() {
+					return Err((String::from("malformed pattern (missing ']')"), start));
+				}
+				check_class(chars, i + 1)?;
+				i += 2;
+			}
+			_ => i += 1,
+		}
+	}
+}
+
+/// Checks a string literal (with its quotes) containing a Lua pattern.
+/// The literal can be either a quoted string or a long bracket string.
+///
+/// # Errors
+/// Returns an [`Err`] with the error message and the offset of the offending character
+/// from the start of the literal, including the opening quote.
+/// If the literal contains escape sequences that cannot be decoded it is not checked.
+pub fn check_pattern_literal(literal: &str) -> Result<(), (String, usize)> {
+	let Some((pattern, offsets)) = unescape_literal(literal) else {
+		return Ok(());
+	};
+	check_pattern(&pattern).map_err(|(msg, i)| (msg, offsets[i]))
+}
+
+fn unescape_literal(literal: &str) -> Option<(String, Vec<usize>)> {
+	let chars: Vec<char> = literal.chars().collect();
+	let mut pattern = String::with_capacity(chars.len());
+	let mut offsets = Vec::with_capacity(chars.len());
+	match chars.first()? {
+		'"' | '\'' => {
+			let mut i = 1;
+			while i + 1 < chars.len() {
+				let offset = i;
+				let c = if chars[i] == '\\' {
+					i += 1;
+					match chars[i] {
+						'n' => '\n',
+						't' => '\t',
+						'r' => '\r',
+						'a' => '\x07',
+						'b' => '\x08',
+						'f' => '\x0C',
+						'v' => '\x0B',
+						c @ ('\\' | '"' | '\'') => c,
+						c if c.is_ascii_digit() => {
+							let mut code = 0u32;
+							let end = i + 3;
+							while i < end && chars[i].is_ascii_digit() {
+								code = code * 10 + chars[i].to_digit(10)?;
+								i += 1;
+							}
+							i -= 1;
+							char::from_u32(code)?
+						}
+						_ => return None,
+					}
+				} else {
+					chars[i]
+				};
+				pattern.push(c);
+				offsets.push(offset);
+				i += 1;
+			}
+		}
+		'[' => {
+			let level = chars[1..].iter().take_while(|c| **c == '=').count();
+			let start = level + 2;
+			for (i, c) in chars[start..chars.len() - start].iter().enumerate() {
+				pattern.push(*c);
+				// raw strings are written with a single '`' in the source code
+				offsets.push(i + 1);
+			}
+		}
+		_ => return None,
+	}
+	Some((pattern, offsets))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn valid_patterns() {
+		for pattern in [
+			"",
+			"^hello$",
+			"%d+%.?%d*",
+			"[%w_]+",
+			"[]]",
+			"[^%s]-",
+			"%b()",
+			"%f[%w]%w+",
+			"(a)(b)%1",
+			"()",
+			"a$b",
+			"%%",
+		] {
+			assert_eq!(check_pattern(pattern), Ok(()), "{pattern}");
+		}
+	}
+
+	#[test]
+	fn invalid_patterns() {
+		for (pattern, position) in [
+			("abc%", 3),
+			("[abc", 0),
+			("%q", 0),
+			("-abc", 0),
+			("(-)", 1),
+			("a**", 2),
+			("(abc", 0),
+			("abc)", 3),
+			("%b(", 0),
+			("%fabc", 0),
+			("[%y]", 1),
+		] {
+			assert_eq!(
+				check_pattern(pattern).map_err(|(_, i)| i),
+				Err(position),
+				"{pattern}"
+			);
+		}
+	}
+
+	#[test]
+	fn literal_positions() {
+		assert_eq!(check_pattern_literal("\"\\n%\"").unwrap_err().1, 3);
+		assert_eq!(check_pattern_literal("[[ab%]]").unwrap_err().1, 3);
+		assert_eq!(check_pattern_literal("'%w+'"), Ok(()));
+	}
+}
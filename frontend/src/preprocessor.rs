@@ -0,0 +1,3171 @@
+//! The preprocessor is the first step in the compilation process.
+//! It is responsible for removing comments and expanding macros and directives.
+//!
+//! It exposes three functions: [`preprocess_code`], [`preprocess_codes`] and [`preprocess_variables`]
+//!
+//! # Loops
+//! `@for name = start, end, step { ... }` repeats its block once for every integer from `start`
+//! to `end` (both included), counting by `step` (1 when it's left out, it can be negative),
+//! while `@foreach name in a, b, c { ... }` repeats it once for every item of the comma separated list.
+//! Inside the block, `$name` is the current number or item:
+//! ```clue
+//! @for i = 1, 3 { print($i) }
+//! @foreach key in "a", "b" { t[$key] = true }
+//! ```
+//! The bounds of `@for` can use other variables (`@for i = 1, $N`), but they must be integers
+//! and a loop can repeat its block at most [`MAX_LOOP_ITERATIONS`] times.
+
+use crate::{
+	check,
+	code::{Code, CodeChar},
+	env::{BitwiseMode, LuaVersion, Options},
+	format_clue,
+};
+#[cfg(feature = "ahash")]
+use ahash::AHashMap as HashMap;
+#[cfg(not(feature = "ahash"))]
+use std::collections::HashMap;
+use std::{
+	cell::RefCell,
+	cmp,
+	collections::VecDeque,
+	env, fs,
+	iter::{Peekable, Rev},
+	path::{Path, PathBuf},
+	str::{self, Split},
+	time::{SystemTime, UNIX_EPOCH},
+};
+use utf8_decode::decode;
+
+#[cfg(feature = "lsp")]
+use serde_json::json;
+
+macro_rules! pp_if {
+	($code:ident, $ifname:ident, $prev:ident) => {{
+		let check = $code.$ifname(b'{')?;
+		$code.keep_block($prev && check)?;
+	}};
+}
+
+/// The most times a single `@for` can repeat its block, since every iteration is expanded up front
+pub const MAX_LOOP_ITERATIONS: usize = 65536;
+
+thread_local! {
+	// the files being preprocessed by this thread, the last one is the one `@include` is relative to
+	static INCLUDES: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug, Clone)]
+// the code a file included with `@include`, which is moved to the line of the directive,
+// each character to its own column after the end of the line
+struct Inclusion {
+	// the file with the directive
+	file: String,
+	line: usize,
+	column: usize,
+	// the path of the included file, as written in the directive
+	path: String,
+	// the column of the first character
+	first_column: usize,
+	// the line and column of every character in the included file
+	positions: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where the code included by `@include` was written, see [`Remapping::origin_at`].
+pub struct Origin {
+	/// The path of the file the code was written in, as written in the `@include` directive.
+	pub file: String,
+
+	/// The line where the code was written.
+	pub line: usize,
+
+	/// The column where the code was written.
+	pub column: usize,
+
+	/// The line of the `@include` directive, which is where the code is moved.
+	pub include_line: usize,
+
+	/// The column of the `@include` directive.
+	pub include_column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A variable or macro expanded by [`preprocess_codes`].
+///
+/// The expanded code is moved to where the macro was used, so that the errors found in it
+/// point at the original code, see [`Remapping::expansion_at`] for where it was defined.
+pub struct Expansion {
+	/// The name of the variable or macro.
+	pub name: String,
+
+	/// The line where the macro was used.
+	pub line: usize,
+
+	/// The column where the macro was used.
+	pub column: usize,
+
+	/// The line where the macro was defined.
+	pub defined_line: usize,
+
+	/// The column where the macro was defined.
+	pub defined_column: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Where the preprocessor moved the code of a file from: the code included by `@include`,
+/// which is returned by [`preprocess_code`], and the variables and macros expanded by [`preprocess_codes`].
+///
+/// The scanner and the parser take it to report their errors where the code was written
+pub struct Remapping {
+	inclusions: Vec<Inclusion>,
+	expansions: Vec<Expansion>,
+	// the number of `@for` and `@foreach` iterations so far in the file and the files it includes,
+	// which gives every loop variable a unique name
+	loops: usize,
+}
+
+impl Remapping {
+	/// Returns where the character at `line` and `column` of `filename` was written,
+	/// if it was included by an `@include` directive (following the files included by the included files).
+	///
+	/// The included code is moved to the line of the directive, so that the compiled code
+	/// points at it, but each character keeps its own column after the end of the line.
+	///
+	/// # Examples
+	/// ```
+	/// use clue_frontend::{env::Options, preprocessor::*};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let dir = std::env::temp_dir().join("clue_origin_at");
+	///     std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+	///     std::fs::write(dir.join("lib.clue"), "local a = 1\nlocal b = 2").map_err(|e| e.to_string())?;
+	///     std::fs::write(dir.join("main.clue"), "@include \"lib.clue\"").map_err(|e| e.to_string())?;
+	///     let filename = String::from("main.clue");
+	///     let (codes, variables, mut remapping) =
+	///         read_file(dir.join("main.clue"), &filename, &Options::default())?;
+	///     let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+	///     let b = code.iter().find(|(c, ..)| *c == b'b').unwrap();
+	///     assert_eq!(b.1, 1);
+	///     let origin = remapping.origin_at(&filename, b.1, b.2).unwrap();
+	///     assert_eq!((origin.file.as_str(), origin.line, origin.column), ("lib.clue", 2, 7));
+	///     assert_eq!((origin.include_line, origin.include_column), (1, 1));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn origin_at(&self, filename: &str, line: usize, column: usize) -> Option<Origin> {
+		let find = |file: &str, line: usize, column: usize| {
+			self.inclusions.iter().find_map(|inclusion| {
+				let i = column.checked_sub(inclusion.first_column)?;
+				let position = inclusion.positions.get(i)?;
+				(inclusion.file == file && inclusion.line == line).then_some((inclusion, *position))
+			})
+		};
+		let (inclusion, (mut line, mut column)) = find(filename, line, column)?;
+		let mut file = &inclusion.path;
+		while let Some((nested, position)) = find(file, line, column) {
+			file = &nested.path;
+			(line, column) = position;
+		}
+		Some(Origin {
+			file: file.clone(),
+			line,
+			column,
+			include_line: inclusion.line,
+			include_column: inclusion.column,
+		})
+	}
+
+	/// Returns the variable or macro whose expanded code is at `line` and `column`,
+	/// if it was expanded by [`preprocess_codes`].
+	///
+	/// # Examples
+	/// ```
+	/// use clue_frontend::{env::Options, preprocessor::*};
+	///
+	/// fn main() -> Result<(), String> {
+	///     let filename = String::from("test.clue");
+	///     let mut code = String::from("@define TWO 2\nprint($TWO)");
+	///     let (codes, variables, .., mut remapping) = preprocess_code(
+	///         unsafe { code.as_bytes_mut() },
+	///         1,
+	///         false,
+	///         &filename,
+	///         &Options::default(),
+	///     )?;
+	///     preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+	///     let expansion = remapping.expansion_at(2, 7).unwrap();
+	///     assert_eq!(expansion.name, "TWO");
+	///     assert_eq!((expansion.defined_line, expansion.defined_column), (1, 13));
+	///
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn expansion_at(&self, line: usize, column: usize) -> Option<&Expansion> {
+		self.expansions
+			.iter()
+			.find(|expansion| expansion.line == line && expansion.column == column)
+	}
+
+	// prints where the error or warning is, which for the included code is the file it was written in
+	pub(crate) fn print_location(&self, kind: &str, filename: &str, line: usize, column: usize) {
+		match self.origin_at(filename, line, column) {
+			Some(origin) => eprintln!(
+				"{kind} in {}:{}:{}!\nNote: included at {filename}:{}:{}",
+				origin.file, origin.line, origin.column, origin.include_line, origin.include_column
+			),
+			None => eprintln!("{kind} in {filename}:{line}:{column}!"),
+		}
+		self.note_expansion(filename, line, column);
+	}
+
+	// prints where the macro whose expanded code has an error was defined
+	fn note_expansion(&self, filename: &str, line: usize, column: usize) {
+		if let Some(expansion) = self.expansion_at(line, column) {
+			let (line, column) = (expansion.defined_line, expansion.defined_column);
+			let (file, line, column) = match self.origin_at(filename, line, column) {
+				Some(origin) => (origin.file, origin.line, origin.column),
+				None => (filename.to_owned(), line, column),
+			};
+			eprintln!(
+				"Note: expanded from macro '{}' defined at {file}:{line}:{column}",
+				expansion.name
+			);
+		}
+	}
+
+	// prints where the error is and gives back its message
+	fn error(&self, msg: impl Into<String>, line: usize, column: usize, filename: &str) -> String {
+		self.print_location("Error", filename, line, column);
+		msg.into()
+	}
+}
+
+/// A HashMap of preprocessor variables.
+pub type PPVars = HashMap<Code, PPVar>;
+/// A list of code segments and its size.
+pub type PPCode = (VecDeque<(Code, bool)>, usize);
+
+#[derive(Debug, Clone)]
+/// A preprocessor variable or macro.
+pub enum PPVar {
+	/// A simple variable.
+	Simple(Code),
+
+	/// A variable that has to be processed before expansion.
+	ToProcess(Code),
+
+	/// A macro.
+	Macro {
+		/// The code of the macro.
+		code: PPCode,
+
+		/// The arguments of the macro.
+		args: Vec<Code>,
+
+		/// The preprocessor variables of the macro.
+		ppvars: PPVars,
+
+		/// Whether the macro is variadic.
+		vararg: bool,
+	},
+
+	/// Variadic arguments variable in a macro.
+	VarArgs(PPCode),
+}
+
+// prints which use of a macro the error found while expanding its code comes from
+fn note_use(error: String, stacklevel: u8, name: &Code, c: &CodeChar, filename: &String) -> String {
+	if stacklevel == 0 {
+		eprintln!(
+			"Note: in the expansion of macro '{}' at {filename}:{}:{}",
+			written_name(&name.to_string()),
+			c.1,
+			c.2
+		);
+	}
+	error
+}
+
+// the name of the variable as the user wrote it,
+// `@for` and `@foreach` rename their variable to `_<name>_for<id>`
+fn written_name(name: &str) -> &str {
+	name.strip_prefix('_')
+		.and_then(|name| name.rsplit_once("_for"))
+		.filter(|(_, id)| !id.is_empty() && id.bytes().all(|c| c.is_ascii_digit()))
+		.map_or(name, |(name, _)| name)
+}
+
+// a value in the condition of `@if`
+#[derive(Debug, Clone, PartialEq)]
+enum PPValue {
+	Bool(bool),
+	Number(f64),
+	String(String),
+}
+
+impl PPValue {
+	// reads the value of a variable, which is a number or a string (with or without quotes)
+	fn parse(value: &str) -> Self {
+		if let Ok(number) = value.parse() {
+			return Self::Number(number);
+		}
+		let unquoted = [b'"', b'\'', b'`'].iter().find_map(|&quote| {
+			let quote = quote as char;
+			value.strip_prefix(quote)?.strip_suffix(quote)
+		});
+		Self::String(unquoted.unwrap_or(value).to_owned())
+	}
+
+	fn is_true(&self) -> bool {
+		match self {
+			Self::Bool(b) => *b,
+			Self::Number(n) => *n != 0.0,
+			Self::String(s) => !s.is_empty(),
+		}
+	}
+
+	fn kind(&self) -> &'static str {
+		match self {
+			Self::Bool(_) => "a boolean",
+			Self::Number(_) => "a number",
+			Self::String(_) => "a string",
+		}
+	}
+}
+
+fn error(msg: impl Into<String>, line: usize, column: usize, filename: &str) -> String {
+	eprintln!("Error in {filename}:{line}:{column}!");
+	msg.into()
+}
+
+fn expected(expected: &str, got: &str, line: usize, column: usize, filename: &str) -> String {
+	error(
+		format_clue!("Expected '", expected, "', got '", got, "'"),
+		line,
+		column,
+		filename,
+	)
+}
+
+fn expected_before(
+	expected: &str,
+	before: &str,
+	line: usize,
+	column: usize,
+	filename: &str,
+) -> String {
+	error(
+		format_clue!("Expected '", expected, "' before '", before, "'"),
+		line,
+		column,
+		filename,
+	)
+}
+
+// whether `text` matches `pattern`, where `*` matches any sequence of characters
+// and `?` matches any single character
+fn glob_matches(pattern: &str, text: &str) -> bool {
+	let pattern: Vec<char> = pattern.chars().collect();
+	let text: Vec<char> = text.chars().collect();
+	let (mut p, mut t) = (0, 0);
+	// where the last `*` was found and the text it started matching from
+	let mut star: Option<(usize, usize)> = None;
+	while t < text.len() {
+		match pattern.get(p) {
+			Some('*') => {
+				star = Some((p, t));
+				p += 1;
+			}
+			Some(&c) if c == '?' || c == text[t] => {
+				p += 1;
+				t += 1;
+			}
+			_ => match star {
+				// let the last `*` match one more character and try again
+				Some((star_p, star_t)) => {
+					star = Some((star_p, star_t + 1));
+					p = star_p + 1;
+					t = star_t + 1;
+				}
+				None => return false,
+			},
+		}
+	}
+	pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum CommentState {
+	String,
+	None,
+	Single,
+	Multi,
+	Ended,
+}
+
+struct CodeFile<'a> {
+	options: &'a Options,
+	code: &'a mut [u8],
+	comment: CommentState,
+	checked: usize,
+	read: usize,
+	peeked: Option<CodeChar>,
+	line: usize,
+	column: usize,
+	filename: &'a String,
+	last_if: bool,
+	cscope: u8,
+	ends: Vec<u8>,
+}
+
+impl<'a> CodeFile<'a> {
+	fn new(
+		code: &'a mut [u8],
+		line: usize,
+		filename: &'a String,
+		cscope: u8,
+		options: &'a Options,
+	) -> Self {
+		Self {
+			options,
+			code,
+			comment: CommentState::None,
+			checked: 0,
+			read: 0,
+			peeked: None,
+			line,
+			column: 1,
+			filename,
+			last_if: true,
+			cscope,
+			ends: Vec::new(),
+		}
+	}
+
+	fn is_ascii(&mut self, c: Option<CodeChar>) -> Result<Option<CodeChar>, String> {
+		match c {
+			None => Ok(None),
+			Some(c) if c.0.is_ascii() => Ok(Some(c)),
+			Some((_, line, column)) => {
+				let c = check!(decode(
+					&mut self.code[self.read - 1..cmp::min(self.read + 3, self.code.len())]
+						.iter()
+						.copied()
+				)
+				.unwrap());
+				Err(error(
+					format!("Invalid character '{c}'"),
+					line,
+					column,
+					self.filename,
+				))
+			}
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while let Some((c, ..)) = self.peek_char_unchecked() {
+			if c.is_ascii_whitespace() {
+				self.read_char_unchecked();
+			} else {
+				break;
+			}
+		}
+	}
+
+	fn read_char_unchecked(&mut self) -> Option<CodeChar> {
+		if self.peeked.is_some() {
+			let peeked = self.peeked;
+			self.peeked = None;
+			peeked
+		} else {
+			let next = self.code.get(self.read + 1).copied();
+			let current = self.code.get_mut(self.read);
+			if let Some(current) = current {
+				let c = *current;
+				self.read += 1;
+				let line = self.line;
+				let column = self.column;
+				self.column += 1;
+				if self.comment > CommentState::None && *current != b'\n' {
+					*current = b' ';
+				}
+				match c {
+					b'\n' => {
+						self.line += 1;
+						self.column = 1;
+						if self.comment == CommentState::Single {
+							self.comment = CommentState::None;
+						}
+					}
+					b'/' if self.comment == CommentState::None => {
+						if let Some(next) = next {
+							self.comment = match next {
+								b'/' => {
+									*current = b' ';
+									CommentState::Single
+								}
+								b'*' => {
+									*current = b' ';
+									CommentState::Multi
+								}
+								_ => CommentState::None,
+							}
+						}
+					}
+					b'*' if self.comment == CommentState::Multi => {
+						if let Some(next) = next {
+							if next == b'/' {
+								self.comment = CommentState::Ended;
+							}
+						}
+					}
+					_ if self.comment == CommentState::Ended => {
+						self.comment = CommentState::None;
+					}
+					_ => {}
+				}
+				Some((*current, line, column))
+			} else {
+				None
+			}
+		}
+	}
+
+	fn read_char(&mut self) -> Result<Option<CodeChar>, String> {
+		let c = self.read_char_unchecked();
+		self.is_ascii(c)
+	}
+
+	fn peek_char_unchecked(&mut self) -> Option<CodeChar> {
+		if self.peeked.is_none() {
+			self.peeked = self.read_char_unchecked();
+		}
+		self.peeked
+	}
+
+	fn peek_char(&mut self) -> Result<Option<CodeChar>, String> {
+		let c = self.peek_char_unchecked();
+		self.is_ascii(c)
+	}
+
+	fn assert_char(&mut self, wanted_c: u8) -> Result<(), String> {
+		match self.read_char()? {
+			None => Err(expected_before(
+				&String::from_utf8_lossy(&[wanted_c]),
+				"<end>",
+				self.line,
+				self.column,
+				self.filename,
+			)),
+			Some((c, line, column)) if c != wanted_c => Err(expected(
+				&String::from_utf8_lossy(&[wanted_c]),
+				&String::from_utf8_lossy(&[c]),
+				line,
+				column,
+				self.filename,
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	fn assert_reach(&mut self, wanted_c: u8) -> Result<(), String> {
+		self.skip_whitespace();
+		self.assert_char(wanted_c)
+	}
+
+	fn read(
+		&mut self,
+		mut get: impl FnMut(&mut Self) -> Result<Option<CodeChar>, String>,
+		mut check: impl FnMut(&mut Self, CodeChar) -> bool,
+	) -> Result<Code, String> {
+		let mut code = Code::new();
+		while let Some(c) = get(self)? {
+			if check(self, c) {
+				break;
+			}
+			code.push(c)
+		}
+		Ok(code)
+	}
+
+	fn read_line(&mut self) -> String {
+		self.read(
+			|code| Ok(code.read_char_unchecked()),
+			|_, (c, ..)| c == b'\n',
+		)
+		.unwrap()
+		.to_string()
+	}
+
+	fn read_identifier(&mut self) -> Result<Code, String> {
+		self.read(Self::peek_char, |code, (c, ..)| {
+			if c.is_ascii_alphanumeric() || c == b'_' {
+				code.read_char_unchecked().unwrap();
+				false
+			} else {
+				true
+			}
+		})
+	}
+
+	fn read_string(&mut self, c: CodeChar) -> Result<Code, String> {
+		self.comment = CommentState::String;
+		let mut skip_next = false;
+		self.read(
+			|code| {
+				let stringc = code.read_char_unchecked();
+				if stringc.is_none() {
+					Err(error("Unterminated string", c.1, c.2, self.filename))
+				} else {
+					Ok(stringc)
+				}
+			},
+			|code, (stringc, ..)| {
+				if stringc == b'\n' {
+					false
+				} else if skip_next {
+					skip_next = false;
+					false
+				} else if stringc == c.0 {
+					code.comment = CommentState::None;
+					true
+				} else {
+					if stringc == b'\\' {
+						skip_next = true;
+					}
+					false
+				}
+			},
+		)
+	}
+
+	fn read_until_with(
+		&mut self,
+		end: u8,
+		f: impl FnMut(&mut Self) -> Result<Option<CodeChar>, String>,
+	) -> Result<Option<Code>, String> {
+		let mut reached = false;
+		let result = self.read(f, |_, (c, ..)| {
+			if c == end {
+				reached = true;
+				true
+			} else {
+				false
+			}
+		})?;
+		Ok(reached.then_some(result))
+	}
+
+	fn read_until(&mut self, end: u8) -> Result<Code, String> {
+		self.read_until_with(end, Self::read_char)?.ok_or_else(|| {
+			expected_before(
+				&(end as char).to_string(),
+				"<end>",
+				self.line,
+				self.column,
+				self.filename,
+			)
+		})
+	}
+
+	fn read_macro_args(&mut self) -> Result<Code, String> {
+		let mut args = Code::new();
+		args.push(self.read_char_unchecked().unwrap());
+		while let Some(c) = self.peek_char()? {
+			match c.0 {
+				b'(' => args.append(self.read_macro_args()?),
+				b')' => {
+					args.push(self.read_char_unchecked().unwrap());
+					return Ok(args);
+				}
+				b'\'' | b'"' | b'`' => {
+					args.push(self.read_char_unchecked().unwrap());
+					args.append(self.read_string(c)?);
+					args.push(c);
+				}
+				_ => args.push(self.read_char_unchecked().unwrap()),
+			}
+		}
+		Err(expected_before(
+			")",
+			"<end>",
+			self.line,
+			self.column,
+			self.filename,
+		))
+	}
+
+	// reads the names of the arguments of a macro after the `(`, and whether it's variadic
+	fn read_macro_params(&mut self) -> Result<(bool, Vec<Code>), String> {
+		let mut args = Vec::new();
+		loop {
+			self.skip_whitespace();
+			if let Some((b'.', line, column)) = self.peek_char_unchecked() {
+				if self.read(Self::peek_char, |code, (c, ..)| {
+					if c == b'.' {
+						code.read_char_unchecked();
+						false
+					} else {
+						true
+					}
+				})? == "..."
+				{
+					self.skip_whitespace();
+					self.assert_char(b')')?;
+					return Ok((true, args));
+				} else {
+					return Err(expected(",", ".", line, column, self.filename));
+				}
+			}
+			let arg = self.read_identifier()?;
+			self.skip_whitespace();
+			if arg.is_empty() {
+				if args.is_empty() {
+					self.assert_char(b')')?;
+					return Ok((false, args));
+				}
+				let (got, line, column) = match self.read_char_unchecked() {
+					Some((c, line, column)) => ((c as char).to_string(), line, column),
+					None => (String::from("<end>"), self.line, self.column),
+				};
+				return Err(expected("<name>", &got, line, column, self.filename));
+			}
+			args.push(arg);
+			if let Some((b')', ..)) = self.peek_char_unchecked() {
+				self.read_char_unchecked();
+				return Ok((false, args));
+			}
+			self.assert_char(b',')?;
+		}
+	}
+
+	fn read_macro_block(&mut self, remapping: &mut Remapping) -> Result<(PPCode, PPVars), String> {
+		let line = self.line;
+		let len = self.code.len();
+		let block = &mut self.code[self.read..len];
+		let options = Options::default();
+		let (block, ppvars, line, read, _) = preprocess_text(
+			block,
+			line,
+			true,
+			self.filename,
+			&options,
+			PPVars::new(),
+			remapping,
+		)?;
+		self.line = line;
+		self.read += read;
+		Ok((block, ppvars))
+	}
+
+	fn skip_block(&mut self) -> Result<(), String> {
+		while let Some(c) = self.read_char()? {
+			match c.0 {
+				b'{' => self.skip_block()?,
+				b'}' => return Ok(()),
+				b'\'' | b'"' | b'`' => {
+					self.read_string(c)?;
+				}
+				_ => {}
+			}
+		}
+		Err(expected_before(
+			"}",
+			"<end>",
+			self.line,
+			self.column,
+			self.filename,
+		))
+	}
+
+	fn keep_block(&mut self, to_keep: bool) -> Result<(), String> {
+		self.last_if = to_keep;
+		if to_keep {
+			self.ends.push(self.cscope);
+			self.cscope += 1;
+			Ok(())
+		} else {
+			self.skip_block()
+		}
+	}
+
+	fn ifos(&mut self, end: u8) -> Result<bool, String> {
+		let checked_os = self.read_until(end)?.trim();
+		Ok(glob_matches(
+			&checked_os.to_string(),
+			&self.options.env_targetos,
+		))
+	}
+
+	fn ifarch(&mut self, end: u8) -> Result<bool, String> {
+		let checked_arch = self.read_until(end)?.trim();
+		Ok(glob_matches(
+			&checked_arch.to_string(),
+			&self.options.env_targetarch,
+		))
+	}
+
+	fn iflua(&mut self, end: u8) -> Result<bool, String> {
+		use crate::env::LuaVersion::*;
+		let checked_lua_version = self.read_until(end)?.trim();
+		let checked = checked_lua_version.to_string().to_lowercase();
+		// `>= 5.3` compares the version of standard Lua the target is based on
+		let comparison = ["<=", ">=", "==", "!=", "<", ">"]
+			.into_iter()
+			.find(|comparison| checked.starts_with(comparison));
+		if let Some(comparison) = comparison {
+			let version = checked[comparison.len()..].trim();
+			let Some(version) = parse_lua_version(version) else {
+				let (line, column) = checked_lua_version
+					.last()
+					.map_or((self.line, self.column), |c| (c.1, c.2));
+				return Err(expected("<version>", version, line, column, self.filename));
+			};
+			let Some(target) = self.options.env_target else {
+				return Ok(false);
+			};
+			let target = target.base_version();
+			return Ok(match comparison {
+				"<=" => target <= version,
+				">=" => target >= version,
+				"==" => target == version,
+				"!=" => target != version,
+				"<" => target < version,
+				_ => target > version,
+			});
+		}
+		let Some(target) = self.options.env_target else {
+			return Ok(false);
+		};
+		Ok(match checked.as_str() {
+			"luajit" | "jit" => target == LuaJIT,
+			"lua54" | "lua5.4" | "lua 54" | "lua 5.4" | "54" | "5.4" => target == Lua54,
+			"lua53" | "lua5.3" | "lua 53" | "lua 5.3" | "53" | "5.3" => target == Lua53,
+			"lua52" | "lua5.2" | "lua 52" | "lua 5.2" | "52" | "5.2" => target == Lua52,
+			"lua51" | "lua5.1" | "lua 51" | "lua 5.1" | "51" | "5.1" => target == Lua51,
+			"blua" => target == BLUA,
+			"luau" => target == Luau,
+			"lua51pure" | "lua5.1pure" | "pure" => target == Lua51Pure,
+			_ => false,
+		})
+	}
+
+	fn ifdef(&mut self, end: u8) -> Result<bool, String> {
+		let to_check = self.read_until(end)?.trim();
+		Ok(env::var_os(to_check.to_string()).is_some())
+	}
+
+	fn ifndef(&mut self, end: u8) -> Result<bool, String> {
+		self.ifdef(end).map(|ok| !ok)
+	}
+
+	fn ifcmp(&mut self, end: u8) -> Result<bool, String> {
+		let Some(to_compare1) = env::var_os(self.read_identifier()?.to_string()) else {
+			self.read_until(end)?;
+			return Ok(false);
+		};
+		self.skip_whitespace();
+		let comparison = [
+			self.read_char_unchecked()
+				.ok_or_else(|| {
+					expected("==' or '!=", "<end>", self.line, self.column, self.filename)
+				})?
+				.0,
+			self.read_char_unchecked()
+				.ok_or_else(|| {
+					expected("==' or '!=", "<end>", self.line, self.column, self.filename)
+				})?
+				.0,
+		];
+		let to_compare2 = self.read_until(end)?.trim();
+		Ok(match &comparison {
+			b"==" => to_compare2 == to_compare1,
+			b"!=" => to_compare2 != to_compare1,
+			_ => {
+				return Err(expected(
+					"==' or '!=",
+					&String::from_utf8_lossy(&comparison),
+					self.line,
+					self.column,
+					self.filename,
+				))
+			}
+		})
+	}
+
+	fn bool_op(&mut self, b: bool, variables: &PPVars) -> Result<bool, String> {
+		let mut result = !b;
+		loop {
+			if self.r#if(variables)? == b {
+				result = b;
+			}
+			self.skip_whitespace();
+			if let Some((b')', ..)) = self.peek_char_unchecked() {
+				self.read_char_unchecked();
+				break Ok(result);
+			}
+			self.assert_char(b',')?;
+			self.skip_whitespace();
+		}
+	}
+
+	fn r#if(&mut self, variables: &PPVars) -> Result<bool, String> {
+		let check = self.condition_or(variables)?.is_true();
+		self.skip_whitespace();
+		Ok(check)
+	}
+
+	// the operators of `@if` from the lowest precedence to the highest, like in C:
+	// `||`, `&&`, comparisons, `+` and `-`, `*`, `/` and `%`, then the unary `!` and `-`
+	fn condition_or(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let mut value = self.condition_and(variables)?;
+		while let Some((b'|', ..)) = self.peek_char_unchecked() {
+			self.read_char_unchecked();
+			self.assert_char(b'|')?;
+			self.skip_whitespace();
+			let other = self.condition_and(variables)?;
+			value = PPValue::Bool(value.is_true() || other.is_true());
+		}
+		Ok(value)
+	}
+
+	fn condition_and(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let mut value = self.condition_comparison(variables)?;
+		while let Some((b'&', ..)) = self.peek_char_unchecked() {
+			self.read_char_unchecked();
+			self.assert_char(b'&')?;
+			self.skip_whitespace();
+			let other = self.condition_comparison(variables)?;
+			value = PPValue::Bool(value.is_true() && other.is_true());
+		}
+		Ok(value)
+	}
+
+	fn condition_comparison(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let value = self.condition_sum(variables)?;
+		let Some((c @ (b'=' | b'!' | b'<' | b'>'), line, column)) = self.peek_char_unchecked()
+		else {
+			return Ok(value);
+		};
+		self.read_char_unchecked();
+		let equal = matches!(self.peek_char_unchecked(), Some((b'=', ..)));
+		if equal {
+			self.read_char_unchecked();
+		} else if matches!(c, b'=' | b'!') {
+			let operator = if c == b'=' { "==" } else { "!=" };
+			let got = (c as char).to_string();
+			return Err(expected(operator, &got, line, column, self.filename));
+		}
+		self.skip_whitespace();
+		let other = self.condition_sum(variables)?;
+		let ordering = match (&value, &other) {
+			(PPValue::Number(a), PPValue::Number(b)) => a.partial_cmp(b),
+			(PPValue::String(a), PPValue::String(b)) => Some(a.cmp(b)),
+			(PPValue::Bool(a), PPValue::Bool(b)) if matches!(c, b'=' | b'!') => Some(a.cmp(b)),
+			_ if matches!(c, b'=' | b'!') => None,
+			_ => {
+				return Err(error(
+					format_clue!("Cannot compare ", value.kind(), " with ", other.kind()),
+					line,
+					column,
+					self.filename,
+				))
+			}
+		};
+		use cmp::Ordering::*;
+		Ok(PPValue::Bool(match (c, equal) {
+			(b'=', _) => ordering == Some(Equal),
+			(b'!', _) => ordering != Some(Equal),
+			(b'<', false) => ordering == Some(Less),
+			(b'<', true) => matches!(ordering, Some(Less | Equal)),
+			(_, false) => ordering == Some(Greater),
+			(_, true) => matches!(ordering, Some(Greater | Equal)),
+		}))
+	}
+
+	fn condition_sum(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let mut value = self.condition_product(variables)?;
+		while let Some((op @ (b'+' | b'-'), line, column)) = self.peek_char_unchecked() {
+			self.read_char_unchecked();
+			self.skip_whitespace();
+			let other = self.condition_product(variables)?;
+			let (a, b) = self.operands(value, other, op, line, column)?;
+			value = PPValue::Number(if op == b'+' { a + b } else { a - b });
+		}
+		Ok(value)
+	}
+
+	fn condition_product(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let mut value = self.condition_value(variables)?;
+		while let Some((op @ (b'*' | b'/' | b'%'), line, column)) = self.peek_char_unchecked() {
+			self.read_char_unchecked();
+			self.skip_whitespace();
+			let other = self.condition_value(variables)?;
+			let (a, b) = self.operands(value, other, op, line, column)?;
+			value = PPValue::Number(match op {
+				b'*' => a * b,
+				b'/' => a / b,
+				_ => a.rem_euclid(b),
+			});
+		}
+		Ok(value)
+	}
+
+	fn operands(
+		&self,
+		a: PPValue,
+		b: PPValue,
+		op: u8,
+		line: usize,
+		column: usize,
+	) -> Result<(f64, f64), String> {
+		match (a, b) {
+			(PPValue::Number(a), PPValue::Number(b)) => Ok((a, b)),
+			(a, b) => Err(error(
+				format_clue!(
+					"Cannot use '",
+					(op as char).to_string(),
+					"' with ",
+					a.kind(),
+					" and ",
+					b.kind()
+				),
+				line,
+				column,
+				self.filename,
+			)),
+		}
+	}
+
+	fn condition_value(&mut self, variables: &PPVars) -> Result<PPValue, String> {
+		let Some(c) = self.peek_char_unchecked() else {
+			return Err(expected_before(
+				"<value>",
+				"<end>",
+				self.line,
+				self.column,
+				self.filename,
+			));
+		};
+		let value = match c.0 {
+			b'(' => {
+				self.read_char_unchecked();
+				self.skip_whitespace();
+				let value = self.condition_or(variables)?;
+				self.assert_char(b')')?;
+				value
+			}
+			b'!' => {
+				self.read_char_unchecked();
+				PPValue::Bool(!self.condition_value(variables)?.is_true())
+			}
+			b'-' => {
+				self.read_char_unchecked();
+				match self.condition_value(variables)? {
+					PPValue::Number(n) => PPValue::Number(-n),
+					value => {
+						return Err(error(
+							format_clue!("Cannot use '-' with ", value.kind()),
+							c.1,
+							c.2,
+							self.filename,
+						))
+					}
+				}
+			}
+			b'\'' | b'"' | b'`' => {
+				self.read_char_unchecked();
+				PPValue::String(self.read_string(c)?.to_string())
+			}
+			b'0'..=b'9' => {
+				let number = self.read(Self::peek_char, |code, (c, ..)| {
+					if c.is_ascii_alphanumeric() || c == b'.' {
+						code.read_char_unchecked();
+						false
+					} else {
+						true
+					}
+				})?;
+				match number.to_string().parse() {
+					Ok(number) => PPValue::Number(number),
+					Err(_) => {
+						let number = number.to_string();
+						return Err(expected("<number>", &number, c.1, c.2, self.filename));
+					}
+				}
+			}
+			b'$' => {
+				self.read_char_unchecked();
+				let name = self.read_identifier()?;
+				let predefined = predefined_value(&name, c.1, self.filename);
+				let value = match predefined.map_or_else(|| env::var(name.to_string()), Ok) {
+					Ok(value) => value,
+					Err(_) => match variables.get(&name) {
+						Some(PPVar::Simple(value)) => value.to_string(),
+						Some(PPVar::ToProcess(value)) => {
+							let remapping = &mut Remapping::default();
+							preprocess_variables(
+								0,
+								value,
+								value.len(),
+								variables,
+								remapping,
+								self.filename,
+							)?
+							.to_string()
+						}
+						Some(_) => {
+							return Err(error(
+								format_clue!("'", name.to_string(), "' is a macro, not a value"),
+								c.1,
+								c.2,
+								self.filename,
+							))
+						}
+						None => {
+							return Err(error(
+								format_clue!("Value '", name.to_string(), "' not found"),
+								c.1,
+								c.2,
+								self.filename,
+							))
+						}
+					},
+				};
+				PPValue::parse(value.trim())
+			}
+			_ => {
+				let function = self.read_identifier()?.to_string();
+				if function.is_empty() {
+					let got = (c.0 as char).to_string();
+					return Err(expected("<value>", &got, c.1, c.2, self.filename));
+				}
+				match function.as_str() {
+					"true" => PPValue::Bool(true),
+					"false" => PPValue::Bool(false),
+					"env" => {
+						self.assert_char(b'(')?;
+						let name = self.read_env_name()?;
+						let default = match self.peek_char_unchecked() {
+							Some((b',', ..)) => {
+								self.read_char_unchecked();
+								self.skip_whitespace();
+								Some(self.condition_or(variables)?)
+							}
+							_ => None,
+						};
+						self.assert_char(b')')?;
+						match (env::var(&name), default) {
+							(Ok(value), _) => PPValue::parse(&value),
+							(Err(_), Some(default)) => default,
+							(Err(_), None) => {
+								return Err(unset_variable(&name, c.1, c.2, self.filename))
+							}
+						}
+					}
+					_ => {
+						self.assert_char(b'(')?;
+						self.skip_whitespace();
+						PPValue::Bool(self.condition_function(&function, variables, c)?)
+					}
+				}
+			}
+		};
+		self.skip_whitespace();
+		Ok(value)
+	}
+
+	// reads the name of the environment variable of `env("NAME", default)`
+	fn read_env_name(&mut self) -> Result<String, String> {
+		self.skip_whitespace();
+		let name = match self.read_char_unchecked() {
+			Some(c @ (b'\'' | b'"' | b'`', ..)) => self.read_string(c)?.to_string(),
+			Some((c, line, column)) => {
+				let got = (c as char).to_string();
+				return Err(expected("<string>", &got, line, column, self.filename));
+			}
+			None => {
+				return Err(expected_before(
+					"<string>",
+					"<end>",
+					self.line,
+					self.column,
+					self.filename,
+				))
+			}
+		};
+		self.skip_whitespace();
+		Ok(name)
+	}
+
+	fn condition_function(
+		&mut self,
+		function: &str,
+		variables: &PPVars,
+		start: CodeChar,
+	) -> Result<bool, String> {
+		Ok(match function {
+			"all" => self.bool_op(false, variables)?,
+			"any" => self.bool_op(true, variables)?,
+			"os" => self.ifos(b')')?,
+			"arch" => self.ifarch(b')')?,
+			"lua" => self.iflua(b')')?,
+			"def" => self.ifdef(b')')?,
+			"ndef" => self.ifndef(b')')?,
+			"cmp" => self.ifcmp(b')')?,
+			"defined" => {
+				let name = self.read_until(b')')?.trim();
+				env::var_os(name.to_string()).is_some() || variables.contains_key(&name)
+			}
+			"not" => {
+				let result = self.r#if(variables)?;
+				self.assert_char(b')')?;
+				!result
+			}
+			_ => {
+				return Err(error(
+					format!("Unknown function '{function}'"),
+					start.1,
+					start.2,
+					self.filename,
+				))
+			}
+		})
+	}
+
+	fn get_version_number(&self, version: &mut Split<char>, default: &str) -> Result<u8, String> {
+		let num = match version.next() {
+			None => {
+				return Err(error(
+					"Incomplete version (must be 'X.Y.Z')",
+					self.line,
+					self.column,
+					self.filename,
+				))
+			}
+			Some("*") => default,
+			Some(num) => num,
+		};
+		match num.parse::<u8>() {
+			Ok(num) => Ok(num),
+			Err(_) => Err(error(
+				"Invalid version (must be 'X.Y.Z')",
+				self.line,
+				self.column,
+				self.filename,
+			)),
+		}
+	}
+}
+/// Reads a file and gives back the a list of preprocessed code blocks, the variables and the [`Remapping`] of its included code
+/// Reads a file and gives back the a list of preprocessed code blocks and the variables
+///
+/// # Errors
+/// If the file cannot be read or the code cannot be preprocessed it will return an [`Err`] with the error message
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, preprocessor::read_file};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let (code, vars, remapping) = read_file(
+///         "../examples/macro.clue",
+///         &String::from("macro.clue"),
+///         &options,
+///     )?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn read_file(
+	path: impl Into<PathBuf>,
+	filename: &String,
+	options: &Options,
+) -> Result<(PPCode, PPVars, Remapping), String> {
+	let path = path.into();
+	let mut code = check!(fs::read(&path));
+	let _file = WithinFile::new(&path);
+	let result = preprocess_code(&mut code, 1, false, filename, options)?;
+	Ok((result.0, result.1, result.4))
+}
+
+/// Preprocesses code and gives back the a list of preprocessed code blocks and the variable
+/// The variables always include the built-in logging macros (see [`LOG_LEVELS`])
+/// and the predefined variables (see [`PREDEFINED_VARIABLES`])
+///
+/// # Errors
+/// If the code cannot be preprocessed it will return an [`Err`] with the error message
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{preprocessor::preprocess_code, env::Options};
+///
+/// fn main() -> Result<(), String> {
+///   let options = Options::default();
+///   let mut code = include_str!("../../examples/macro.clue").to_owned();
+///
+///   let (code, vars, ..) = preprocess_code(&mut code.into_bytes(), 1, false, &String::from("macro.clue"), &options)?;
+///
+///   Ok(())
+/// }
+pub fn preprocess_code(
+	code: &mut [u8],
+	line: usize,
+	is_block: bool,
+	filename: &String,
+	options: &Options,
+) -> Result<(PPCode, PPVars, usize, usize, Remapping), String> {
+	let variables = if is_block {
+		PPVars::new()
+	} else {
+		let mut variables = log_macros(filename, options)?;
+		variables.extend(predefined_variables(options));
+		variables
+	};
+	preprocess_code_with(code, line, is_block, filename, options, variables)
+}
+
+/// The variables every file can use without defining them:
+/// - `$__FILE__`: the name of the file, as a string
+/// - `$__LINE__`: the line where it's used, as a number
+/// - `$__VERSION__`: the version of the compiler, as a string
+/// - `$__TARGET__`: the Lua version chosen with [`Options::env_target`] as a string (like `"Lua54"`),
+///   or `nil` without one
+/// - `$__TIMESTAMP__`: when the code was compiled, in seconds since the Unix epoch,
+///   or the value of `SOURCE_DATE_EPOCH` for reproducible builds
+pub const PREDEFINED_VARIABLES: [&str; 5] = [
+	"__FILE__",
+	"__LINE__",
+	"__VERSION__",
+	"__TARGET__",
+	"__TIMESTAMP__",
+];
+
+// parses the version `@iflua` compares with, like `5.3` or `lua 5.3`
+fn parse_lua_version(version: &str) -> Option<(u8, u8)> {
+	let version = version.strip_prefix("lua").unwrap_or(version).trim();
+	let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+	Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+// the predefined variables which don't change in the file, `__FILE__` and `__LINE__` are expanded
+// by predefined_value where they are used
+fn predefined_variables(options: &Options) -> PPVars {
+	let target = match options.env_target {
+		Some(target) => format!("\"{target:?}\""),
+		None => String::from("nil"),
+	};
+	let timestamp = env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH);
+		now.map_or(0, |now| now.as_secs()).to_string()
+	});
+	let version = format_clue!("\"", env!("CARGO_PKG_VERSION"), "\"");
+	[
+		("__VERSION__", version),
+		("__TARGET__", target),
+		("__TIMESTAMP__", timestamp),
+	]
+	.into_iter()
+	.map(|(name, value)| {
+		(
+			Code::from((name, 1, 1)),
+			PPVar::Simple(Code::from((value, 1, 1))),
+		)
+	})
+	.collect()
+}
+
+// the value of `$__FILE__` and `$__LINE__`, which depend on where they are used
+fn predefined_value(name: &Code, line: usize, filename: &str) -> Option<String> {
+	if *name == "__FILE__" {
+		Some(quote_string(filename))
+	} else if *name == "__LINE__" {
+		Some(line.to_string())
+	} else {
+		None
+	}
+}
+
+/// The log levels accepted by the `LOG_LEVEL` variable, from the most verbose to the least verbose.
+pub const LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+/// Defines the built-in `$log_debug!`, `$log_info!`, `$log_warn!` and `$log_error!` macros.
+///
+/// The macros of the levels enabled by the `LOG_LEVEL` variable expand to a call to `print`
+/// (or to the function named by the `LOG_FUNCTION` variable, which also receives the level),
+/// all the others expand to nothing, arguments included.
+/// Logging is disabled entirely if `LOG_LEVEL` is not defined or is `none`.
+fn log_macros(filename: &String, options: &Options) -> Result<PPVars, String> {
+	let level = match env::var("LOG_LEVEL") {
+		Ok(level) => match LOG_LEVELS
+			.iter()
+			.position(|name| name.eq_ignore_ascii_case(&level))
+		{
+			Some(level) => level,
+			None if level.eq_ignore_ascii_case("none") => LOG_LEVELS.len(),
+			None => {
+				return Err(format_clue!(
+					"Unknown log level '",
+					level,
+					"' (expected one of ",
+					LOG_LEVELS.join(", "),
+					" or none)"
+				))
+			}
+		},
+		Err(_) => LOG_LEVELS.len(),
+	};
+	let function = env::var("LOG_FUNCTION").ok();
+	let mut macros = String::new();
+	for (i, name) in LOG_LEVELS.iter().enumerate() {
+		let call = if i < level {
+			String::new()
+		} else if let Some(function) = &function {
+			format_clue!(function, "(\"", name, "\"${, $vararg})")
+		} else {
+			format_clue!("print(\"[", name.to_uppercase(), "]\"${, $vararg})")
+		};
+		macros += &format_clue!("@macro log_", name, "(...) {", call, "}\n");
+	}
+	let (_, variables, ..) = preprocess_code_with(
+		unsafe { macros.as_bytes_mut() },
+		1,
+		false,
+		filename,
+		options,
+		PPVars::new(),
+	)?;
+	Ok(variables)
+}
+
+fn preprocess_code_with(
+	code: &mut [u8],
+	line: usize,
+	is_block: bool,
+	filename: &String,
+	options: &Options,
+	variables: PPVars,
+) -> Result<(PPCode, PPVars, usize, usize, Remapping), String> {
+	let mut remapping = Remapping::default();
+	let ((mut finalcode, size), variables, line, read, bitwise) = preprocess_text(
+		code,
+		line,
+		is_block,
+		filename,
+		options,
+		variables,
+		&mut remapping,
+	)?;
+	// bit32 is a global in Luau and in every TargetEnv and can't be required, while the library of
+	// BitwiseMode::Pure is defined by the compiler once when it's needed
+	let without_loader = options.env_bitwise == BitwiseMode::Pure
+		|| (options.env_target == Some(LuaVersion::Luau) || options.env_target_env.is_some())
+			&& options.env_jitbit.as_deref() == Some("bit32");
+	if let (true, false, Some(bit)) = (bitwise, without_loader, options.env_jitbit.as_ref()) {
+		let loader = format_clue!("local ", bit, " = require(\"", bit, "\");");
+		let mut loader = Code::from((loader, 1, 1));
+		let first = finalcode.pop_front().unwrap();
+		loader.append(first.0);
+		finalcode.push_front((loader, first.1));
+	}
+	#[cfg(feature = "lsp")]
+	if options.env_symbols {
+		use std::collections::HashMap;
+		use PPVar::*;
+		let mut str_variables = HashMap::new();
+		for (name, variable) in &variables {
+			let (name, value) = match variable {
+				Simple(value) | ToProcess(value) => (format_clue!('$', name), value.to_string()),
+				_ => continue,
+			};
+			str_variables.insert(name, value);
+		}
+		println!(
+			"{}",
+			json!({
+				"type": "PPVars",
+				"value": str_variables
+			})
+		);
+	}
+	Ok(((finalcode, size), variables, line, read, remapping))
+}
+
+// keeps `path` as the file being preprocessed until it's dropped, even if preprocessing it fails
+struct WithinFile;
+
+impl WithinFile {
+	fn new(path: &Path) -> Self {
+		let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+		INCLUDES.with(|includes| includes.borrow_mut().push(path));
+		Self
+	}
+}
+
+impl Drop for WithinFile {
+	fn drop(&mut self) {
+		INCLUDES.with(|includes| includes.borrow_mut().pop());
+	}
+}
+
+// preprocesses the file included by `@include`, with the variables defined so far,
+// the path is relative to the file with the directive (or to the current directory if it's not a file)
+fn include_file(
+	path: &str,
+	line: usize,
+	column: usize,
+	filename: &str,
+	options: &Options,
+	variables: PPVars,
+	remapping: &mut Remapping,
+) -> Result<(PPCode, PPVars, bool), String> {
+	let dir = INCLUDES.with(|includes| {
+		let includes = includes.borrow();
+		includes
+			.last()
+			.and_then(|file| file.parent())
+			.map(Path::to_path_buf)
+	});
+	let full_path = match dir {
+		Some(dir) => dir.join(path),
+		None => PathBuf::from(path),
+	};
+	let cannot_include = |e: std::io::Error| {
+		error(
+			format_clue!("Cannot include '", path, "': ", e.to_string()),
+			line,
+			column,
+			filename,
+		)
+	};
+	let full_path = fs::canonicalize(full_path).map_err(cannot_include)?;
+	if INCLUDES.with(|includes| includes.borrow().contains(&full_path)) {
+		return Err(error(
+			format_clue!("'", path, "' is included by itself"),
+			line,
+			column,
+			filename,
+		));
+	}
+	let mut code = fs::read(&full_path).map_err(cannot_include)?;
+	let included = String::from(path);
+	let _file = WithinFile::new(&full_path);
+	let (code, variables, _, _, bitwise) = preprocess_text(
+		&mut code, 1, false, &included, options, variables, remapping,
+	)?;
+	Ok((code, variables, bitwise))
+}
+
+// moves the code to the given position, which is where the macro was used
+fn relocate(code: Code, line: usize, column: usize) -> Code {
+	let mut relocated = Code::with_capacity(code.len());
+	for (c, ..) in code {
+		relocated.push((c, line, column));
+	}
+	relocated
+}
+
+// moves the code included from `path` to the line of the `@include` directive in `file`,
+// from the first column after `line_end` not used by other included code (see Remapping::origin_at)
+fn relocate_included(
+	codes: PPCode,
+	file: &str,
+	(line, column): (usize, usize),
+	line_end: usize,
+	path: String,
+	remapping: &mut Remapping,
+) -> VecDeque<(Code, bool)> {
+	let first_column = remapping
+		.inclusions
+		.iter()
+		.filter(|inclusion| inclusion.file == file && inclusion.line == line)
+		.map(|inclusion| inclusion.first_column + inclusion.positions.len())
+		.fold(line_end + 1, cmp::max);
+	let mut positions = Vec::with_capacity(codes.1);
+	let mut relocated = VecDeque::with_capacity(codes.0.len());
+	for (code, expand) in codes.0 {
+		let mut moved = Code::with_capacity(code.len());
+		for (c, own_line, own_column) in code {
+			moved.push((c, line, first_column + positions.len()));
+			positions.push((own_line, own_column));
+		}
+		relocated.push_back((moved, expand));
+	}
+	remapping.inclusions.push(Inclusion {
+		file: file.to_owned(),
+		line,
+		column,
+		path,
+		first_column,
+		positions,
+	});
+	relocated
+}
+
+// like preprocess_code_with, also returning whether the code uses bitwise operators,
+// the code it includes is added to `remapping`
+#[allow(clippy::blocks_in_conditions)]
+fn preprocess_text(
+	code: &mut [u8],
+	line: usize,
+	is_block: bool,
+	filename: &String,
+	options: &Options,
+	mut variables: PPVars,
+	remapping: &mut Remapping,
+) -> Result<(PPCode, PPVars, usize, usize, bool), String> {
+	let mut output_dir: Option<PathBuf> = None;
+	let mut finalcode = VecDeque::new();
+	let mut currentcode = Code::with_capacity(code.len());
+	let mut size = 0;
+	let mut code = CodeFile::new(code, line, filename, is_block as u8, options);
+	let mut pseudos: Option<VecDeque<Code>> = None;
+	let mut bitwise = false;
+	while let Some(c) = code.read_char()? {
+		if match c.0 {
+			b'@' => {
+				let directive_code = code.read_identifier()?;
+				let directive_name = directive_code.to_string();
+				code.skip_whitespace();
+				let else_if = directive_name.starts_with("else_if");
+				let skip = else_if && code.last_if;
+				let (directive, prev) = if else_if {
+					(
+						directive_name
+							.strip_prefix("else_")
+							.expect("else_if should start with else_"),
+						!code.last_if,
+					)
+				} else {
+					(directive_name.as_str(), true)
+				};
+				match directive {
+					"ifos" => pp_if!(code, ifos, prev),
+					"ifarch" => pp_if!(code, ifarch, prev),
+					"iflua" => pp_if!(code, iflua, prev),
+					"ifdef" => pp_if!(code, ifdef, prev),
+					"ifndef" => pp_if!(code, ifndef, prev),
+					"ifcmp" => pp_if!(code, ifcmp, prev),
+					"if" => {
+						let check = code.r#if(&variables)?;
+						code.assert_char(b'{')?;
+						code.keep_block(prev && check)?;
+					}
+					"else" => {
+						code.assert_reach(b'{')?;
+						code.keep_block(!code.last_if)?;
+					}
+					"import" => {
+						if output_dir.is_none() {
+							output_dir = Some(match options.env_outputname.as_ref() {
+								Some(output_dir) => output_dir.parent().map_or_else(
+									|| output_dir.to_path_buf(),
+									|output_dir| output_dir.to_path_buf(),
+								),
+								None => check!(env::current_dir()),
+							})
+						}
+						let output_dir = output_dir.as_ref().unwrap();
+						let str_start = code.read_char_unchecked();
+						let module = match str_start {
+							Some((b'\'' | b'"' | b'`', ..)) => {
+								code.read_string(str_start.expect("character should not be None"))?
+							}
+							_ => {
+								return Err(expected_before("<path>", "<end>", c.1, c.2, filename))
+							}
+						}
+						.to_string();
+						let name = code.read_line();
+						let name = name.trim();
+						let mut dirs = module.split('.');
+						let mut module_path = output_dir.join(dirs.next().unwrap());
+						for dir in dirs {
+							module_path.push(dir);
+						}
+						module_path.set_extension("lua");
+						let function = if module_path.exists() {
+							"require"
+						} else {
+							"import"
+						};
+						let (name, start) = match name.strip_prefix("=>") {
+							Some(name) => {
+								let mut trimmed_name = name.trim_start().to_owned();
+								if trimmed_name.is_empty() {
+									return Err(expected(
+										"<name>",
+										"<empty>",
+										code.line,
+										code.column,
+										filename,
+									));
+								}
+								if trimmed_name.contains(['$', '@']) {
+									let (codes, new_variables, ..) = preprocess_code(
+										unsafe { trimmed_name.as_bytes_mut() },
+										code.line,
+										false,
+										filename,
+										options,
+									)?;
+									for (key, value) in new_variables {
+										variables.insert(key, value);
+									}
+									trimmed_name = preprocess_codes(
+										0,
+										codes,
+										&variables,
+										&mut Remapping::default(),
+										filename,
+									)?
+									.to_string();
+								}
+								let start = if trimmed_name.contains(['.', '[']) {
+									""
+								} else {
+									"local "
+								};
+								(trimmed_name, start)
+							}
+							None => (
+								match module.rsplit_once('.') {
+									Some((_, name)) => name,
+									None => &module,
+								}
+								.trim()
+								.to_string(),
+								"local ",
+							),
+						};
+						if name.is_empty() {
+							return Err(expected("<file name>", "<empty>", c.1, c.2, filename));
+						}
+						currentcode.append(Code::from((
+							format_clue!(start, name, " = ", function, "(\"", module, "\")"),
+							c.1,
+							c.2,
+						)));
+					}
+					"for" | "foreach" => {
+						let name = code.read_identifier()?;
+						if name.is_empty() {
+							let (got, line, column) = match code.peek_char_unchecked() {
+								Some((c, line, column)) => ((c as char).to_string(), line, column),
+								None => (String::from("<end>"), code.line, code.column),
+							};
+							return Err(expected("<name>", &got, line, column, filename));
+						}
+						code.skip_whitespace();
+						if directive == "for" {
+							code.assert_char(b'=')?;
+						} else {
+							let (line, column) = code
+								.peek_char_unchecked()
+								.map_or((code.line, code.column), |c| (c.1, c.2));
+							let keyword = code.read_identifier()?.to_string();
+							if keyword != "in" {
+								return Err(expected("in", &keyword, line, column, filename));
+							}
+						}
+						let mut header = code.read_until(b'{')?;
+						if header.iter().any(|&(c, ..)| c == b'$') {
+							let len = header.len();
+							let remapping = &mut Remapping::default();
+							header = preprocess_variables(
+								0, &header, len, &variables, remapping, filename,
+							)?;
+						}
+						let items = if directive == "for" {
+							range_items(header, c.1, c.2, filename)?
+						} else {
+							split_items(header)
+						};
+						let ((block, _), ppvars) = code.read_macro_block(remapping)?;
+						variables.extend(ppvars);
+						size += currentcode.len();
+						finalcode.push_back((currentcode, false));
+						for item in items {
+							let id = remapping.loops.to_string();
+							remapping.loops += 1;
+							let unique = format_clue!("_", name.to_string(), "_for", id);
+							let unique = Code::from((unique, c.1, c.2));
+							for (segment, expand) in &block {
+								let segment = if *expand {
+									rename_variable(segment, &name, &unique)
+								} else {
+									segment.clone()
+								};
+								size += segment.len();
+								finalcode.push_back((segment, *expand));
+							}
+							variables.insert(unique, PPVar::Simple(item));
+						}
+						currentcode = Code::with_capacity(code.code.len() - code.read);
+					}
+					"include" => {
+						let str_start = code.read_char_unchecked();
+						let path = match str_start {
+							Some((b'\'' | b'"' | b'`', ..)) => {
+								code.read_string(str_start.expect("character should not be None"))?
+							}
+							_ => {
+								return Err(expected_before("<path>", "<end>", c.1, c.2, filename))
+							}
+						}
+						.to_string();
+						let variables_so_far = std::mem::take(&mut variables);
+						let (codes, new_variables, included_bitwise) = include_file(
+							&path,
+							c.1,
+							c.2,
+							filename,
+							options,
+							variables_so_far,
+							remapping,
+						)?;
+						variables = new_variables;
+						bitwise |= included_bitwise;
+						size += currentcode.len() + codes.1;
+						finalcode.push_back((currentcode, false));
+						let rest = &code.code[code.read..];
+						let line_end =
+							code.column + rest.iter().take_while(|c| **c != b'\n').count();
+						finalcode.extend(relocate_included(
+							codes,
+							filename,
+							(c.1, c.2),
+							line_end,
+							path,
+							remapping,
+						));
+						currentcode = Code::with_capacity(code.code.len() - code.read);
+					}
+					"version" => {
+						let full_wanted_version = code.read_line();
+						let full_wanted_version = full_wanted_version.trim();
+						#[allow(clippy::type_complexity)]
+						let (mut wanted_version, check): (&str, &dyn Fn(&u8, &u8) -> bool) =
+							match full_wanted_version.strip_prefix('=') {
+								Some(wanted_version) => (wanted_version, &u8::ne),
+								None => (full_wanted_version, &u8::lt),
+							};
+						if let Some(v) = full_wanted_version.strip_prefix(">=") {
+							wanted_version = v;
+							println!(
+								"Note: \"@version directives should no longer start with '>='\""
+							);
+						}
+						let wanted_version_iter = &mut wanted_version.split('.');
+						const CURRENT_MAJOR: &str = env!("CARGO_PKG_VERSION_MAJOR");
+						const CURRENT_MINOR: &str = env!("CARGO_PKG_VERSION_MINOR");
+						const CURRENT_PATCH: &str = env!("CARGO_PKG_VERSION_PATCH");
+						let wanted_major =
+							code.get_version_number(wanted_version_iter, CURRENT_MAJOR)?;
+						let wanted_minor =
+							code.get_version_number(wanted_version_iter, CURRENT_MINOR)?;
+						let wanted_patch =
+							code.get_version_number(wanted_version_iter, CURRENT_PATCH)?;
+						let current_major: u8 = CURRENT_MAJOR.parse().unwrap();
+						let current_minor: u8 = CURRENT_MINOR.parse().unwrap();
+						let current_patch: u8 = CURRENT_PATCH.parse().unwrap();
+						if check(&current_major, &wanted_major)
+							|| check(&current_minor, &wanted_minor)
+							|| check(&current_patch, &wanted_patch)
+						{
+							return Err(error(
+								if full_wanted_version.starts_with('=') {
+									format_clue!(
+										"This code is only compatible with version '",
+										wanted_version,
+										"'"
+									)
+								} else {
+									format_clue!(
+										"This code is only compatible with versions not older than '",
+										wanted_version,
+										"'"
+									)
+								},
+								c.1,
+								c.2,
+								filename,
+							));
+						}
+					}
+					"define" => {
+						let name = code.read_identifier()?;
+						// like in C, `@define NAME(a, b) ...` defines a macro only without a space before `(`
+						let params = match code.peek_char_unchecked() {
+							Some((b'(', ..)) => {
+								code.read_char_unchecked();
+								Some(code.read_macro_params()?)
+							}
+							_ => None,
+						};
+						let mut has_values = false;
+						let mut string_char = 0u8; //basically recreating read_string...
+						let mut skip_next = false; //refactor in 4.0?
+						let value = code.read(
+							|code| Ok(code.read_char_unchecked()),
+							|code, (c, ..)| match c {
+								b'$' => {
+									has_values = true;
+									false
+								}
+								_ if skip_next && code.comment == CommentState::String => {
+									skip_next = false;
+									false
+								}
+								b'\n' => code.comment != CommentState::String,
+								_ if c == string_char && code.comment == CommentState::String => {
+									code.comment = CommentState::None;
+									false
+								}
+								b'\'' | b'"' | b'`' if code.comment != CommentState::String => {
+									code.comment = CommentState::String;
+									string_char = c;
+									false
+								}
+								b'\\' if code.comment == CommentState::String => {
+									skip_next = true;
+									false
+								}
+								_ => false,
+							},
+						)?;
+						if code.comment == CommentState::String {
+							return Err(error(
+								"Unterminated string",
+								code.line,
+								code.column,
+								code.filename,
+							));
+						}
+						let value = value.trim();
+						if let Some((vararg, args)) = params {
+							let mut body = substitute_params(value, &args).to_string() + "}";
+							let (code, ppvars, ..) = preprocess_code(
+								unsafe { body.as_bytes_mut() },
+								c.1,
+								true,
+								filename,
+								&Options::default(),
+							)?;
+							variables.insert(
+								name,
+								PPVar::Macro {
+									code,
+									args,
+									ppvars,
+									vararg,
+								},
+							);
+						} else {
+							variables.insert(
+								name,
+								if has_values {
+									PPVar::ToProcess(value)
+								} else {
+									PPVar::Simple(value)
+								},
+							);
+						}
+					}
+					"macro" => {
+						let name = code.read_identifier()?;
+						code.assert_reach(b'(')?;
+						let (vararg, args) = code.read_macro_params()?;
+						code.assert_reach(b'{')?;
+						let (code, ppvars) = code.read_macro_block(remapping)?;
+						variables.insert(
+							name,
+							PPVar::Macro {
+								code,
+								args,
+								ppvars,
+								vararg,
+							},
+						);
+					}
+					"env" => {
+						let Some((b'(', ..)) = code.peek_char_unchecked() else {
+							return Err(expected_before("(", "<end>", c.1, c.2, filename));
+						};
+						let mut args = code.read_macro_args()?;
+						args.pop_start();
+						args.pop();
+						let mut args = split_items(args).into_iter();
+						let name = args.next().unwrap_or_default().to_string();
+						let name = match PPValue::parse(&name) {
+							PPValue::String(unquoted) if unquoted.len() + 2 == name.len() => {
+								unquoted
+							}
+							_ => return Err(expected("<string>", &name, c.1, c.2, filename)),
+						};
+						let default = args.next();
+						if args.next().is_some() {
+							let message = "Too many arguments passed to '@env'";
+							return Err(error(message, c.1, c.2, filename));
+						}
+						match (env::var(&name), default) {
+							(Ok(value), _) => {
+								currentcode.append(Code::from((quote_string(&value), c.1, c.2)))
+							}
+							(Err(_), Some(default)) if default.iter().any(|&(c, ..)| c == b'$') => {
+								size += currentcode.len() + default.len();
+								finalcode.push_back((currentcode, false));
+								finalcode.push_back((default, true));
+								currentcode = Code::with_capacity(code.code.len() - code.read);
+							}
+							(Err(_), Some(default)) => currentcode.append(default),
+							(Err(_), None) => {
+								return Err(unset_variable(&name, c.1, c.2, filename))
+							}
+						}
+					}
+					"error" => return Err(error(code.read_line(), c.1, c.2, filename)),
+					"print" => println!("{}", code.read_line()),
+					_ => {
+						// not a directive, the parser will read it as an attribute
+						currentcode.push(c);
+						currentcode.append(directive_code);
+						currentcode.push((b' ', c.1, c.2));
+					}
+				}
+				if skip {
+					code.last_if = true;
+				}
+				false
+			}
+			b'$' if is_block && matches!(code.peek_char_unchecked(), Some((b'{', ..))) => {
+				size += currentcode.len() + 8;
+				finalcode.push_back((currentcode, false));
+				let name = format_clue!("_vararg", variables.len().to_string());
+				finalcode.push_back((Code::from((format_clue!("$", name), c.1, c.2)), true));
+				code.read_char_unchecked();
+				let (vararg_code, ppvars) = code.read_macro_block(remapping)?;
+				variables.extend(ppvars);
+				variables.insert(Code::from((name, c.1, c.2)), PPVar::VarArgs(vararg_code));
+				currentcode = Code::with_capacity(code.code.len() - code.read);
+				false
+			}
+			b'$' => {
+				let mut name = code.read_identifier()?;
+				if name.len() <= 1 && matches!(name.last(), Some((b'1'..=b'9', ..)) | None) {
+					let n = match name.pop() {
+						Some((c, ..)) => (c - b'0') as usize,
+						None => 1,
+					};
+					if pseudos.is_none() {
+						let tocheck = code.code[code.checked..code.read].iter().rev().peekable();
+						pseudos = Some(read_pseudos(tocheck, c.1, c.2));
+						code.checked = code.read;
+					}
+					match pseudos.as_ref().unwrap().get(n - 1) {
+						Some(name) => currentcode.append(name.clone()),
+						None => currentcode.append(name.clone()),
+					}
+				} else {
+					size += currentcode.len();
+					finalcode.push_back((currentcode, false));
+					name.push_start(c);
+					if {
+						if matches!(code.peek_char_unchecked(), Some((b'!', ..))) {
+							name.push(code.read_char_unchecked().unwrap());
+							matches!(code.peek_char_unchecked(), Some((b'(', ..)))
+						} else {
+							false
+						}
+					} {
+						name.append(code.read_macro_args()?)
+					}
+					size += name.len();
+					finalcode.push_back((name, true));
+					currentcode = Code::with_capacity(code.code.len() - code.read);
+				}
+				false
+			}
+			b'\'' | b'"' | b'`' => {
+				currentcode.push(c);
+				currentcode.append(code.read_string(c)?);
+				true
+			}
+			b'&' | b'|' => {
+				if code.peek_char_unchecked().unwrap_or((b'\0', 0, 0)).0 == c.0 {
+					currentcode.push(code.read_char_unchecked().unwrap());
+				} else {
+					bitwise = true;
+				}
+				true
+			}
+			b'^' => {
+				let nextc = code.peek_char_unchecked();
+				if nextc.is_some() && nextc.unwrap().0 == b'^' {
+					bitwise = true;
+					currentcode.push(code.read_char_unchecked().unwrap());
+				}
+				true
+			}
+			b'~' => {
+				bitwise = true;
+				true
+			}
+			b'>' | b'<' => {
+				currentcode.push(c);
+				if let Some((nc, ..)) = code.peek_char_unchecked() {
+					match nc {
+						b'=' => {
+							currentcode.push(code.read_char_unchecked().unwrap());
+						}
+						nc if nc == c.0 => {
+							currentcode.push(code.read_char_unchecked().unwrap());
+							bitwise = true;
+						}
+						_ => {}
+					}
+				}
+				false
+			}
+			b'=' => {
+				currentcode.push(c);
+				if let Some((nc, ..)) = code.peek_char_unchecked() {
+					if matches!(nc, b'=' | b'>') {
+						currentcode.push(code.read_char_unchecked().unwrap());
+					} else {
+						pseudos = None;
+					}
+				}
+				false
+			}
+			b'!' => {
+				currentcode.push(c);
+				if let Some((nc, ..)) = code.peek_char_unchecked() {
+					if nc == b'=' {
+						currentcode.push(code.read_char_unchecked().unwrap());
+					}
+				}
+				false
+			}
+			b'{' if code.cscope > 0 || is_block => {
+				code.cscope += 1;
+				true
+			}
+			b'}' if code.cscope > 0 => {
+				code.cscope -= 1;
+				if is_block && code.cscope == 0 {
+					break;
+				}
+				if let Some(end) = code.ends.last() {
+					if code.cscope != *end {
+						true
+					} else {
+						code.ends.pop().unwrap();
+						false
+					}
+				} else {
+					true
+				}
+			}
+			_ => true,
+		} {
+			currentcode.push(c)
+		}
+	}
+	if code.cscope > 0 {
+		return Err(expected_before(
+			"}",
+			"<end>",
+			code.line,
+			code.column,
+			filename,
+		));
+	}
+	if !currentcode.is_empty() {
+		size += currentcode.len();
+		finalcode.push_back((currentcode, false))
+	}
+	Ok(((finalcode, size), variables, code.line, code.read, bitwise))
+}
+
+// puts a `$` before the names of the arguments in the body of a macro made with `@define`,
+// except in strings, after `$` and in fields (`t.name` and `t:name()`)
+fn substitute_params(body: Code, params: &[Code]) -> Code {
+	let mut result = Code::with_capacity(body.len());
+	let mut chars = body.into_iter().peekable();
+	let mut prev = [0u8; 2];
+	while let Some(c) = chars.next() {
+		match c.0 {
+			b'\'' | b'"' | b'`' => {
+				result.push(c);
+				while let Some(stringc) = chars.next() {
+					result.push(stringc);
+					match stringc.0 {
+						b'\\' => {
+							if let Some(escaped) = chars.next() {
+								result.push(escaped)
+							}
+						}
+						stringc if stringc == c.0 => break,
+						_ => {}
+					}
+				}
+			}
+			first if first.is_ascii_alphabetic() || first == b'_' => {
+				let mut name = Code::new();
+				name.push(c);
+				while let Some(&c) = chars.peek() {
+					if !(c.0.is_ascii_alphanumeric() || c.0 == b'_') {
+						break;
+					}
+					name.push(c);
+					chars.next();
+				}
+				// `..` is the concatenation operator
+				let field = matches!(prev[1], b'.' | b':') && prev[0] != prev[1];
+				if prev[1] != b'$' && !field && params.contains(&name) {
+					result.push((b'$', c.1, c.2));
+				}
+				result.append(name);
+			}
+			_ => result.push(c),
+		}
+		prev = [prev[1], result.last().map_or(0, |c| c.0)];
+	}
+	result
+}
+
+// the numbers from `start` to `end` (both included) of `@for name = start, end, step`
+fn range_items(
+	header: Code,
+	line: usize,
+	column: usize,
+	filename: &str,
+) -> Result<Vec<Code>, String> {
+	let mut bounds = Vec::with_capacity(3);
+	for bound in split_items(header) {
+		let text = bound.to_string();
+		match text.parse::<i64>() {
+			Ok(n) => bounds.push(n),
+			Err(_) => {
+				let (line, column) = bound.iter().next().map_or((line, column), |c| (c.1, c.2));
+				return Err(expected("<integer>", &text, line, column, filename));
+			}
+		}
+	}
+	let (start, end, step) = match bounds[..] {
+		[start, end] => (start, end, 1),
+		[start, end, step] if step != 0 => (start, end, step),
+		[_, _, _] => {
+			return Err(error(
+				"The step of '@for' can't be 0",
+				line,
+				column,
+				filename,
+			))
+		}
+		_ => {
+			return Err(error(
+				"'@for' needs a start, an end and optionally a step",
+				line,
+				column,
+				filename,
+			))
+		}
+	};
+	let iterations = match (i128::from(end) - i128::from(start)) / i128::from(step) {
+		..=-1 => 0,
+		distance => distance + 1,
+	};
+	if iterations > MAX_LOOP_ITERATIONS as i128 {
+		return Err(error(
+			format_clue!(
+				"'@for' can't repeat its block more than ",
+				MAX_LOOP_ITERATIONS.to_string(),
+				" times"
+			),
+			line,
+			column,
+			filename,
+		));
+	}
+	let mut items = Vec::with_capacity(iterations as usize);
+	let mut n = Some(start);
+	while let Some(current) = n.filter(|&n| (step > 0 && n <= end) || (step < 0 && n >= end)) {
+		items.push(Code::from((current.to_string(), line, column)));
+		n = current.checked_add(step);
+	}
+	Ok(items)
+}
+
+// splits the items of `@foreach` at the commas outside of strings and brackets
+fn split_items(header: Code) -> Vec<Code> {
+	let mut items = Vec::new();
+	let mut item = Code::new();
+	let mut depth = 0usize;
+	let mut string = None;
+	let mut escaped = false;
+	for c in header {
+		match (c.0, string) {
+			(_, Some(_)) if escaped => escaped = false,
+			(b'\\', Some(_)) => escaped = true,
+			(quote, Some(end)) if quote == end => string = None,
+			(_, Some(_)) => {}
+			(b'\'' | b'"' | b'`', None) => string = Some(c.0),
+			(b'(' | b'[' | b'{', None) => depth += 1,
+			(b')' | b']' | b'}', None) => depth = depth.saturating_sub(1),
+			(b',', None) if depth == 0 => {
+				items.push(std::mem::take(&mut item).trim());
+				continue;
+			}
+			_ => {}
+		}
+		item.push(c);
+	}
+	let item = item.trim();
+	if !item.is_empty() || !items.is_empty() {
+		items.push(item);
+	}
+	items
+}
+
+// replaces `$name` with `$unique` in code that is expanded later
+fn rename_variable(code: &Code, name: &Code, unique: &Code) -> Code {
+	let mut result = Code::with_capacity(code.len());
+	let mut chars = code.iter().peekable();
+	while let Some(&c) = chars.next() {
+		result.push(c);
+		if c.0 != b'$' {
+			continue;
+		}
+		let mut identifier = Code::new();
+		while let Some(&&next) = chars.peek() {
+			if !(next.0.is_ascii_alphanumeric() || next.0 == b'_') {
+				break;
+			}
+			identifier.push(next);
+			chars.next();
+		}
+		if identifier == *name {
+			for &(unique_c, ..) in unique {
+				result.push((unique_c, c.1, c.2));
+			}
+		} else {
+			result.append(identifier);
+		}
+	}
+	result
+}
+
+fn unset_variable(name: &str, line: usize, column: usize, filename: &str) -> String {
+	error(
+		format!("Environment variable '{name}' is not set and has no default"),
+		line,
+		column,
+		filename,
+	)
+}
+
+// the Clue string literal of the value of an environment variable
+fn quote_string(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+	quoted.push('"');
+	for c in value.chars() {
+		match c {
+			'"' | '\\' => {
+				quoted.push('\\');
+				quoted.push(c);
+			}
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+fn skip_whitespace_backwards(code: &mut Peekable<Rev<std::slice::Iter<u8>>>) {
+	while let Some(c) = code.peek() {
+		if c.is_ascii_whitespace() {
+			code.next();
+		} else {
+			break;
+		}
+	}
+}
+
+fn read_pseudos(
+	mut code: Peekable<Rev<std::slice::Iter<u8>>>,
+	line: usize,
+	column: usize,
+) -> VecDeque<Code> {
+	let mut newpseudos = VecDeque::new();
+	while {
+		let Some(c) = code.next() else {
+			return newpseudos;
+		};
+		match c {
+			b'=' => {
+				let Some(c) = code.next() else {
+					return newpseudos;
+				};
+				match c {
+					b'!' | b'=' | b'>' | b'<' => true,
+					b'.' | b'&' | b'|' | b'?' => code.next().unwrap_or(&b'\0') != c,
+					_ => false,
+				}
+			}
+			b'>' if matches!(code.peek(), Some(b'=')) => {
+				code.next().unwrap();
+				true
+			}
+			b'\'' | b'"' | b'`' => {
+				while {
+					let Some(nextc) = code.next() else {
+						return newpseudos;
+					};
+					if nextc == c {
+						matches!(code.peek(), Some(b'\\'))
+					} else {
+						true
+					}
+				} {}
+				true
+			}
+			_ => true,
+		}
+	} {}
+	skip_whitespace_backwards(&mut code);
+	while {
+		let mut name = Code::new();
+		let mut qscope = 0u8;
+		let mut in_string = false;
+		while {
+			if let Some(c) = code.peek() {
+				match c {
+					b'\'' | b'"' | b'`' => {
+						name.push_start((*code.next().unwrap(), line, column));
+						if !matches!(code.peek(), Some(b'\\')) {
+							in_string = !in_string;
+						}
+						true
+					}
+					_ if in_string => true,
+					b'[' => {
+						qscope = qscope.saturating_sub(1);
+						true
+					}
+					_ if qscope > 0 => true,
+					b']' => {
+						qscope += 1;
+						true
+					}
+					b'_' | b'.' | b':' => true,
+					_ => c.is_ascii_alphanumeric(),
+				}
+			} else {
+				false
+			}
+		} {
+			name.push_start((*code.next().unwrap(), line, column))
+		}
+		newpseudos.push_front(name);
+		skip_whitespace_backwards(&mut code);
+		if let Some(c) = code.next() {
+			*c == b','
+		} else {
+			false
+		}
+	} {}
+	newpseudos
+}
+
+/// Preprocesses the list code segments, expands the variables and returns the final code.
+/// Take a stacklevel, a list of code segments, the variables, and a filename.
+///
+/// With a stacklevel of 0 the expanded code is moved to where each variable or macro was used,
+/// and the expansions are added to the [`Remapping`] returned by [`preprocess_code`] (see [`Remapping::expansion_at`]).
+///
+/// # Errors
+/// Returns an error if a variable is not found.
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, preprocessor::*};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let filename = String::from("macro.clue");
+///     let mut code = include_str!("../../examples/macro.clue").to_owned();
+///
+///     let (codes, variables, .., mut remapping) = preprocess_code(
+///         unsafe { code.as_bytes_mut() },
+///         1,
+///         false,
+///         &filename,
+///         &options,
+///     )?;
+///     let codes = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn preprocess_codes(
+	stacklevel: u8,
+	codes: PPCode,
+	variables: &PPVars,
+	remapping: &mut Remapping,
+	filename: &String,
+) -> Result<Code, String> {
+	let (mut codes, size) = codes;
+	if codes.len() == 1 {
+		Ok(codes.pop_back().unwrap().0)
+	} else {
+		let mut code = Code::with_capacity(size);
+		for (codepart, uses_vars) in codes {
+			code.append(if uses_vars {
+				let len = codepart.len();
+				preprocess_variables(stacklevel, &codepart, len, variables, remapping, filename)?
+			} else {
+				codepart
+			})
+		}
+		Ok(code)
+	}
+}
+
+/// Expand the variables in a [`Code`]
+/// Takes a stacklevel, a code segment, the variables, and a filename.
+///
+/// # Errors
+/// Returns an error if a variable is not found, if macro expansion is too deep,
+/// if a macro is not found, or if a macro receives too many or too little arguments.
+///
+/// # Examples
+/// See [`preprocess_codes`]
+///
+/// ```
+/// use clue_frontend::{code::Code, env::Options, preprocessor::*};
+///
+/// fn main() -> Result<(), String> {
+///     let options = Options::default();
+///     let filename = String::from("macro.clue");
+///     let mut code = include_str!("../../examples/macro.clue").to_owned();
+///
+///     let (codes, variables, ..) = preprocess_code(
+///         unsafe { code.as_bytes_mut() },
+///         1,
+///         false,
+///         &filename,
+///         &options,
+///     )?;
+///     let mut remapping = Remapping::default();
+///     let codes: Code = codes
+///         .0
+///         .iter()
+///         .flat_map(|code| {
+///             preprocess_variables(0, &code.0, codes.1, &variables, &mut remapping, &filename)
+///         })
+///         .fold(Code::new(), |mut acc, code| {
+///             acc.append(code);
+///             acc
+///         });
+///
+///     Ok(())
+/// }
+/// ```
+pub fn preprocess_variables(
+	stacklevel: u8,
+	code: &Code,
+	size: usize,
+	//mut chars: Peekable<Iter<CodeChar>>,
+	variables: &PPVars,
+	remapping: &mut Remapping,
+	filename: &String,
+) -> Result<Code, String> {
+	let mut result = Code::with_capacity(size);
+	let mut chars = code.iter().peekable();
+	while let Some(c) = chars.next() {
+		match c.0 {
+			b'$' => {
+				let name = {
+					let mut name = Code::with_capacity(cmp::min(size - 1, 8));
+					while let Some((c, ..)) = chars.peek() {
+						if !(c.is_ascii_alphanumeric() || *c == b'_') {
+							break;
+						}
+						name.push(*chars.next().unwrap())
+					}
+					name
+				};
+				if let Some(value) = predefined_value(&name, c.1, filename) {
+					result.append(Code::from((value, c.1, c.2)));
+				} else if let Ok(value) = env::var(name.to_string()) {
+					result.push((b'"', c.1, c.2));
+					for strc in value.as_bytes() {
+						result.push((*strc, c.1, c.2));
+					}
+					result.push((b'"', c.1, c.2));
+				} else if let Some(value) = variables.get(&name) {
+					if stacklevel == u8::MAX {
+						return Err(remapping.error(
+							"Too many variables called (likely recursive)",
+							c.1,
+							c.2,
+							filename,
+						));
+					}
+					let first =
+						|code: &Code| code.iter().find(|c| !c.0.is_ascii_whitespace()).copied();
+					let defined = match value {
+						PPVar::Simple(value) | PPVar::ToProcess(value) => first(value),
+						PPVar::Macro { code, .. } | PPVar::VarArgs(code) => {
+							code.0.iter().find_map(|(code, _)| first(code))
+						}
+					};
+					let expanded = match value {
+						PPVar::Simple(value) => value.clone(),
+						PPVar::ToProcess(value) => preprocess_variables(
+							stacklevel + 1,
+							value,
+							value.len(),
+							variables,
+							remapping,
+							filename,
+						)
+						.map_err(|e| note_use(e, stacklevel, &name, c, filename))?,
+						PPVar::Macro {
+							code,
+							args,
+							ppvars,
+							vararg,
+						} => {
+							// TODO: See issue #87
+							let macro_variables = {
+								let mut macro_variables = variables.clone();
+								macro_variables.extend(ppvars.clone());
+								let is_called = matches!(chars.next(), Some((b'!', ..)));
+								if !is_called || !matches!(chars.next(), Some((b'(', ..))) {
+									let name = name.to_string();
+									return Err(remapping.error(
+										format!(
+											"Macro not called (replace '${name}{}' with '${name}!()')",
+											if is_called {
+												"!"
+											} else {
+												""
+											}
+										),
+										c.1,
+										c.2,
+										filename,
+									));
+								}
+								let mut args = args.iter();
+								let mut varargs = 0;
+								let len = macro_variables.len();
+								loop {
+									let mut value = Code::new();
+									let mut cscope = 1u8;
+									let end = loop {
+										let Some(c) = chars.next() else {
+											return Err(remapping.error(
+												"Expected ')' before '<end>'",
+												c.1,
+												c.2,
+												filename,
+											));
+										};
+										match c.0 {
+											b'\'' | b'"' | b'`' => {
+												value.push(*c);
+												while let Some(stringc) = chars.next() {
+													value.push(*stringc);
+													match stringc.0 {
+														b'\\' => value.push(*chars.next().unwrap()),
+														stringc if stringc == c.0 => break,
+														_ => {}
+													}
+												}
+												continue;
+											}
+											b'(' | b'{' => cscope += 1,
+											b',' if cscope == 1 => break b',',
+											b'}' if cscope > 1 => cscope -= 1,
+											b')' => {
+												cscope -= 1;
+												if cscope == 0 {
+													break b')';
+												}
+											}
+											_ => {}
+										}
+										value.push(*c)
+									};
+									let value = value.trim();
+									if value.is_empty() {
+										if len == macro_variables.len() && end == b')' {
+											break;
+										} else {
+											let end = (end as char).to_string();
+											return Err(remapping.error(
+												format_clue!(
+													"Expected '<name>' before '",
+													end,
+													"'"
+												),
+												c.1,
+												c.2,
+												filename,
+											));
+										}
+									}
+									let value = PPVar::Simple(preprocess_variables(
+										stacklevel + 1,
+										&value,
+										value.len(),
+										variables,
+										remapping,
+										filename,
+									)?);
+									if let Some(arg_name) = args.next() {
+										macro_variables.insert(arg_name.clone(), value);
+									} else if *vararg {
+										varargs += 1;
+										let mut arg_name = Code::with_capacity(varargs + 1);
+										arg_name.push((b'_', c.1, c.2));
+										for _ in 0..varargs {
+											arg_name.push((b'v', c.1, c.2));
+										}
+										macro_variables.insert(arg_name, value);
+									} else {
+										return Err(remapping.error(
+											"Too many arguments passed to macro",
+											c.1,
+											c.2,
+											filename,
+										));
+									}
+									if end == b')' {
+										break;
+									}
+								}
+								if let Some(missed) = args.next() {
+									return Err(remapping.error(
+										format!("Missing argument '{missed}' for macro"),
+										c.1,
+										c.2,
+										filename,
+									));
+								}
+								macro_variables
+							};
+							preprocess_codes(
+								stacklevel + 1,
+								code.clone(),
+								&macro_variables,
+								remapping,
+								filename,
+							)
+							.map_err(|e| note_use(e, stacklevel, &name, c, filename))?
+						}
+						PPVar::VarArgs((codes, size)) => {
+							let mut result = Code::with_capacity(size * 3);
+							let mut variables = variables.clone();
+							let mut name = Code::from((b"_v", c.1, c.2));
+							while let Some(vararg) = variables.remove(&name) {
+								variables.insert(Code::from((b"vararg", c.1, c.2)), vararg);
+								result.append(preprocess_codes(
+									stacklevel + 1,
+									(codes.clone(), *size),
+									&variables,
+									remapping,
+									filename,
+								)?);
+								name.push(*name.last().unwrap());
+							}
+							result
+						}
+					};
+					if stacklevel == 0 {
+						// the expanded code is moved to where it's used, like C compilers do
+						let name = written_name(&name.to_string()).to_owned();
+						if let Some(defined) =
+							defined.filter(|_| !PREDEFINED_VARIABLES.contains(&name.as_str()))
+						{
+							remapping.expansions.push(Expansion {
+								name,
+								line: c.1,
+								column: c.2,
+								defined_line: defined.1,
+								defined_column: defined.2,
+							});
+						}
+						result.append(relocate(expanded, c.1, c.2));
+					} else {
+						result.append(expanded);
+					}
+				} else {
+					return Err(remapping.error(
+						format_clue!("Value '", name.to_string(), "' not found"),
+						c.1,
+						c.2,
+						filename,
+					));
+				};
+			}
+			b'\'' | b'"' | b'`' => {
+				result.push(*c);
+				while let Some(stringc) = chars.next() {
+					result.push(*stringc);
+					let stringc = stringc.0;
+					if stringc == b'\\' {
+						if let Some(nextc) = chars.next() {
+							result.push(*nextc)
+						}
+					} else if stringc == c.0 {
+						break;
+					}
+				}
+			}
+			_ => result.push(*c),
+		}
+	}
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn glob_patterns() {
+		for (pattern, text, matches) in [
+			("linux", "linux", true),
+			("linux", "linuxx", false),
+			("win*", "windows", true),
+			("*bsd", "freebsd", true),
+			("*bsd", "bsdx", false),
+			("x86*", "x86", true),
+			("x86_??", "x86_64", true),
+			("x86_??", "x86_6", false),
+			("*a*a*", "banana", true),
+			("*", "", true),
+			("?", "", false),
+		] {
+			assert_eq!(glob_matches(pattern, text), matches, "{pattern} {text}");
+		}
+	}
+
+	#[test]
+	fn define_macros() {
+		let filename = String::from("(library)");
+		let mut code = String::from(
+			"@define MAX(a, b) ((a) > (b) ? (a) : (b))\n\
+			@define FIELD(t, a) t.a .. a .. \"a\"\n\
+			@define GROUPED (x)\n\
+			print($MAX!(1, $MAX!(2, 3)), $FIELD!(obj, key), $GROUPED)",
+		);
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		assert_eq!(
+			code.to_string().trim(),
+			"print(((1) > (((2) > (3) ? (2) : (3))) ? (1) : (((2) > (3) ? (2) : (3)))), \
+			obj.a .. key .. \"a\", (x))"
+		);
+	}
+
+	#[test]
+	fn if_expressions() {
+		let filename = String::from("(library)");
+		let preprocess = |condition: &str| {
+			let mut code = format!(
+				"@define VERSION 3\n@define NAME \"clue\"\n@if {condition} {{ yes }} @else {{ no }}"
+			);
+			let (codes, variables, .., mut remapping) = preprocess_code(
+				unsafe { code.as_bytes_mut() },
+				1,
+				false,
+				&filename,
+				&Options::default(),
+			)?;
+			let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+			Ok::<_, String>(code.to_string().trim() == "yes")
+		};
+		assert!(preprocess("$VERSION >= 2 && defined(NAME) && !defined(MISSING)").unwrap());
+		assert!(preprocess("($VERSION + 1) * 2 == 8 || 0").unwrap());
+		assert!(preprocess("$NAME == \"clue\" && -$VERSION % 2 == 1").unwrap());
+		assert!(preprocess("all(not(def(CLUE_UNDEFINED)), 1 < 2) && !false").unwrap());
+		assert!(!preprocess("!$VERSION == 3 || $NAME != \"clue\"").unwrap());
+		let error = preprocess("$VERSION > $NAME").unwrap_err();
+		assert_eq!(error, "Cannot compare a number with a string");
+		assert!(preprocess("$VERSION = 3").is_err());
+	}
+
+	#[test]
+	fn lua_versions() {
+		let filename = String::from("(library)");
+		let preprocess = |target: Option<LuaVersion>| {
+			let mut code = String::from(
+				"@iflua >= 5.3 { a } @else_iflua == lua 5.2 { b } @else { c }\n\
+				@iflua jit { d } @if lua(< 5.2) && !lua(5.1) { e }",
+			);
+			let options = Options {
+				env_target: target,
+				..Options::default()
+			};
+			let (codes, variables, .., mut remapping) = preprocess_code(
+				unsafe { code.as_bytes_mut() },
+				1,
+				false,
+				&filename,
+				&options,
+			)?;
+			let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
+			Ok::<_, String>(code.to_string().split_whitespace().collect::<String>())
+		};
+		assert_eq!(preprocess(Some(LuaVersion::Lua54)).unwrap(), "a");
+		assert_eq!(preprocess(Some(LuaVersion::Lua53)).unwrap(), "a");
+		assert_eq!(preprocess(Some(LuaVersion::Lua52)).unwrap(), "b");
+		assert_eq!(preprocess(Some(LuaVersion::Lua51)).unwrap(), "c");
+		assert_eq!(preprocess(Some(LuaVersion::LuaJIT)).unwrap(), "cde");
+		assert_eq!(preprocess(None).unwrap(), "c");
+		let mut code = String::from("@iflua >= five { a }");
+		let code = unsafe { code.as_bytes_mut() };
+		assert!(preprocess_code(code, 1, false, &filename, &Options::default()).is_err());
+	}
+
+	#[test]
+	fn loops() {
+		let filename = String::from("(library)");
+		let mut code = String::from(
+			"@define N 2\n\
+			@macro pair(a, b) { $a = $b }\n\
+			@for i = 1, $N { @foreach key in \"a,b\", c { $pair!(t[$i][$key], $i) } }\n\
+			@for i = 3, 1, -2 { f($i) }",
+		);
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		let code = code.to_string();
+		assert_eq!(
+			code.split_whitespace().collect::<Vec<_>>().join(" "),
+			"t[1][\"a,b\"] = 1 t[1][c] = 1 t[2][\"a,b\"] = 2 t[2][c] = 2 f(3) f(1)"
+		);
+	}
+
+	#[test]
+	fn loop_bounds() {
+		let filename = String::from("(library)");
+		let preprocess = |code: &str| {
+			let mut code = code.to_owned();
+			let (codes, variables, .., mut remapping) = preprocess_code(
+				unsafe { code.as_bytes_mut() },
+				1,
+				false,
+				&filename,
+				&Options::default(),
+			)?;
+			preprocess_codes(0, codes, &variables, &mut remapping, &filename).map(|code| {
+				code.to_string()
+					.split_whitespace()
+					.collect::<Vec<_>>()
+					.join(" ")
+			})
+		};
+		assert_eq!(
+			preprocess("@for i = 9223372036854775806, 9223372036854775807 { f($i) }").unwrap(),
+			"f(9223372036854775806) f(9223372036854775807)"
+		);
+		assert_eq!(
+			preprocess("@for i = -9223372036854775807, -9223372036854775808, -1 { f($i) }")
+				.unwrap(),
+			"f(-9223372036854775807) f(-9223372036854775808)"
+		);
+		assert_eq!(preprocess("@for i = 2, 1 { f($i) }").unwrap(), "");
+		let error = preprocess("@for i = 0, 9223372036854775807 { f($i) }").unwrap_err();
+		assert!(error.contains("more than 65536 times"), "{error}");
+		let error = preprocess("@for i = 1, 65537 { }").unwrap_err();
+		assert!(error.contains("more than 65536 times"), "{error}");
+		preprocess("@for i = 1, 65536 { }").unwrap();
+	}
+
+	#[test]
+	fn env_values() {
+		env::set_var("CLUE_TEST_ENV", "1.0 \"beta\"");
+		let filename = String::from("(library)");
+		let mut code = String::from(
+			"a = @env(\"CLUE_TEST_ENV\", 1)\n\
+			b = @env('CLUE_TEST_UNSET', f(1, 2))\n\
+			@if env(\"CLUE_TEST_UNSET\", 2) > 1 && env(\"CLUE_TEST_ENV\") != \"\" { c = 3 }",
+		);
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		assert_eq!(
+			code.to_string()
+				.split_whitespace()
+				.collect::<Vec<_>>()
+				.join(" "),
+			"a = \"1.0 \\\"beta\\\"\" b = f(1, 2) c = 3"
+		);
+		let mut code = String::from("a = @env(\"CLUE_TEST_UNSET\")");
+		let code = unsafe { code.as_bytes_mut() };
+		assert!(preprocess_code(code, 1, false, &filename, &Options::default()).is_err());
+	}
+
+	#[test]
+	fn predefined_variables() {
+		let filename = String::from("file.clue");
+		let mut code = String::from(
+			"a = $__FILE__\n\
+			b = $__LINE__\n\
+			@if $__LINE__ == 3 && $__TARGET__ == \"LuaJIT\" { c = $__TARGET__ }\n\
+			d = $__VERSION__\n\
+			e = $__TIMESTAMP__",
+		);
+		let options = Options {
+			env_target: Some(LuaVersion::LuaJIT),
+			..Options::default()
+		};
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&options,
+		)
+		.unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		let words = code.to_string();
+		let words = words.split_whitespace().collect::<Vec<_>>();
+		assert_eq!(
+			words[..12].join(" "),
+			format!(
+				"a = \"file.clue\" b = 2 c = \"LuaJIT\" d = \"{}\"",
+				env!("CARGO_PKG_VERSION")
+			)
+		);
+		assert!(words[14].parse::<u64>().unwrap() > 0);
+		let version = code.iter().filter(|c| c.0 == b'"').nth(4).unwrap();
+		assert_eq!(version.1, 4);
+	}
+
+	#[test]
+	fn expansions() {
+		let filename = String::from("expansions.clue");
+		let mut code = String::from(
+			"@define TWO 2\n\
+			@macro double(x) {\n\
+				$x * $TWO\n\
+			}\n\
+			print($double!(1))\n\
+			@for i = 7, 7 { f($i) }",
+		);
+		let (codes, variables, .., mut remapping) = preprocess_code(
+			unsafe { code.as_bytes_mut() },
+			1,
+			false,
+			&filename,
+			&Options::default(),
+		)
+		.unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		let doubled = code.iter().find(|c| c.0 == b'*').unwrap();
+		assert_eq!((doubled.1, doubled.2), (5, 7));
+		let double = remapping.expansion_at(5, 7).unwrap();
+		assert_eq!(double.name, "double");
+		assert_eq!((double.defined_line, double.defined_column), (3, 1));
+		let item = code.iter().find(|c| c.0 == b'7').unwrap();
+		assert_eq!(remapping.expansion_at(item.1, item.2).unwrap().name, "i");
+		assert_eq!(remapping.expansion_at(5, 8), None);
+	}
+
+	#[test]
+	fn includes() {
+		let path = env::temp_dir().join(format!("clue_include_{}", std::process::id()));
+		fs::create_dir_all(path.join("lib")).unwrap();
+		let consts = "@define LIMIT 10\n@include \"name.clue\"";
+		fs::write(path.join("lib/consts.clue"), consts).unwrap();
+		fs::write(path.join("lib/name.clue"), "@define NAME \"clue\"").unwrap();
+		let main = "@include \"lib/consts.clue\"\nprint($LIMIT, $NAME)";
+		fs::write(path.join("main.clue"), main).unwrap();
+		fs::write(path.join("loop.clue"), "@include \"loop.clue\"").unwrap();
+		let filename = String::from("main.clue");
+		let options = Options::default();
+		let (codes, variables, mut remapping) =
+			read_file(path.join("main.clue"), &filename, &options).unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		assert_eq!(code.to_string().trim(), "print(10, \"clue\")");
+		assert!(code.iter().all(|&(_, line, _)| line <= 2));
+		let error = read_file(path.join("loop.clue"), &filename, &options).unwrap_err();
+		assert!(error.contains("included by itself"));
+		assert!(INCLUDES.with(|includes| includes.borrow().is_empty()));
+		fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn included_positions() {
+		let path = env::temp_dir().join(format!("clue_included_{}", std::process::id()));
+		fs::create_dir_all(&path).unwrap();
+		fs::write(path.join("inner.clue"), "\n  inner()").unwrap();
+		fs::write(path.join("outer.clue"), "outer()\n@include \"inner.clue\"").unwrap();
+		let main = "first() @include \"outer.clue\"\n@include \"inner.clue\"\nlast()";
+		fs::write(path.join("main.clue"), main).unwrap();
+		let filename = String::from("main.clue");
+		let (codes, variables, mut remapping) =
+			read_file(path.join("main.clue"), &filename, &Options::default()).unwrap();
+		let code = preprocess_codes(0, codes, &variables, &mut remapping, &filename).unwrap();
+		let origin = |name: &str| {
+			let text = code.to_string();
+			let i = text.find(name).unwrap();
+			let &(_, line, column) = code.iter().nth(i).unwrap();
+			let origin = remapping
+				.origin_at(&filename, line, column)
+				.map(|origin| (origin.file, origin.line, origin.column, origin.include_line));
+			(line, origin)
+		};
+		assert_eq!(origin("first"), (1, None));
+		assert_eq!(
+			origin("outer"),
+			(1, Some((String::from("outer.clue"), 1, 1, 1)))
+		);
+		assert_eq!(
+			origin("inner"),
+			(1, Some((String::from("inner.clue"), 2, 3, 1)))
+		);
+		let second = code.to_string().rfind("inner").unwrap();
+		let &(_, line, column) = code.iter().nth(second).unwrap();
+		let included = remapping.origin_at(&filename, line, column).unwrap();
+		assert_eq!(
+			(line, included.line, included.column, included.include_line),
+			(2, 2, 3, 2)
+		);
+		assert_eq!(origin("last"), (3, None));
+		fs::remove_dir_all(path).unwrap();
+	}
+}
@@ -2,18 +2,20 @@
 //! into a list of tokens
 //!
 //! It exposes a single function, [`scan_code`], which takes a [`Code`] and returns a [`Vec`] of [`Token`]
+//! ([`scan_code_with_options`] does the same but also takes the [`Remapping`] of the code and the [`Options`])
 
 #![allow(non_camel_case_types)]
 #![allow(clippy::upper_case_acronyms)]
 
 use crate::{
 	code::{Code, CodeChars},
+	env::Options,
 	format_clue,
+	preprocessor::Remapping,
 };
 
 use self::TokenType::*;
-use phf::phf_map;
-use std::fmt;
+use std::{fmt, ops::Range};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -45,7 +47,7 @@ pub enum TokenType {
 	SAFE_DOUBLE_COLON, NOT, BIT_NOT, STAR, SLASH, PERCENTUAL,
 	PLUS, MINUS, LEFT_SHIFT, RIGHT_SHIFT, SMALLER, SMALLER_EQUAL,
 	BIGGER, BIGGER_EQUAL, EQUAL, NOT_EQUAL, BIT_AND, BIT_XOR, BIT_OR,
-	AND, OR, FLOOR_DIVISION, CARET, HASHTAG, COALESCE,
+	AND, OR, FLOOR_DIVISION, CARET, HASHTAG, COALESCE, PIPE, AT,
 	DEFINE, DEFINE_AND, DEFINE_OR, INCREASE, DECREASE, MULTIPLY, DIVIDE,
 	DEFINE_COALESCE, EXPONENTIATE, CONCATENATE, MODULATE,
 
@@ -55,11 +57,23 @@ pub enum TokenType {
 	//keywords
 	IF, ELSEIF, ELSE, FOR, OF, IN, WITH, WHILE, META, GLOBAL, UNTIL,
 	LOCAL, CONST, FN, METHOD, RETURN, TRUE, FALSE, NIL, LOOP, STATIC, ENUM,
-	CONTINUE, BREAK, TRY, CATCH, MATCH, DEFAULT, STRUCT, EXTERN, CONSTRUCTOR,
+	CONTINUE, BREAK, TRY, CATCH, FINALLY, THROW, MATCH, DEFAULT, DO, STRUCT, EXTERN,
+	CONSTRUCTOR, GOTO,
 
 	EOF,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A position in the source code, lines and columns start from 1.
+pub struct TokenPosition {
+	/// The line of the position.
+	pub line: usize,
+
+	/// The column of the position.
+	pub column: usize,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Represents a token with its type, its literal string and the location in the file.
@@ -93,6 +107,33 @@ impl Token {
 	pub fn is_op(&self) -> bool {
 		self.kind >= NOT && self.kind <= OR
 	}
+
+	/// Returns the position where the token starts.
+	pub const fn position(&self) -> TokenPosition {
+		TokenPosition {
+			line: self.line,
+			column: self.column,
+		}
+	}
+
+	/// Returns the position right after the end of the token.
+	pub fn end_position(&self) -> TokenPosition {
+		match self.lexeme.rsplit_once('\n') {
+			Some((_, last)) => TokenPosition {
+				line: self.line,
+				column: last.chars().count() + 1,
+			},
+			None => TokenPosition {
+				line: self.line,
+				column: self.column + self.lexeme.chars().count(),
+			},
+		}
+	}
+
+	/// Returns the range of the source code the token covers.
+	pub fn span(&self) -> Range<TokenPosition> {
+		self.position()..self.end_position()
+	}
 }
 
 /// A token that has a raw pointer to a [`Token`].
@@ -132,6 +173,21 @@ impl BorrowedToken {
 		self.token().column
 	}
 
+	/// Returns the position where the token starts.
+	pub const fn position(&self) -> TokenPosition {
+		self.token().position()
+	}
+
+	/// Returns the position right after the end of the token.
+	pub fn end_position(&self) -> TokenPosition {
+		self.token().end_position()
+	}
+
+	/// Returns the range of the source code the token covers.
+	pub fn span(&self) -> Range<TokenPosition> {
+		self.token().span()
+	}
+
 	/// Clones the inner [`Token`] and returns it.
 	pub fn into_owned(&self) -> Token {
 		self.token().clone()
@@ -147,13 +203,20 @@ struct CodeInfo<'a> {
 	code: CodeChars,
 	read: Vec<(char, usize, usize)>,
 	filename: &'a String,
+	remapping: &'a Remapping,
 	tokens: Vec<Token>,
 	last: TokenType,
 	errored: bool,
+	forward_compat: bool,
 }
 
 impl<'a> CodeInfo<'a> {
-	fn new(code: Code, filename: &'a String) -> Self {
+	fn new(
+		code: Code,
+		filename: &'a String,
+		remapping: &'a Remapping,
+		forward_compat: bool,
+	) -> Self {
 		let size = code.len() + 2;
 		let mut code = code.chars();
 		let mut read = Vec::with_capacity(size);
@@ -168,9 +231,11 @@ impl<'a> CodeInfo<'a> {
 			code,
 			read,
 			filename,
+			remapping,
 			tokens: Vec::new(),
 			last: EOF,
 			errored: false,
+			forward_compat,
 		}
 	}
 
@@ -246,13 +311,9 @@ impl<'a> CodeInfo<'a> {
 	}
 
 	fn warning(&mut self, message: impl Into<String>) {
-		eprintln!(
-			"Error in {}:{}:{}!\nError: \"{}\"\n",
-			self.filename,
-			self.line,
-			self.column,
-			message.into()
-		);
+		self.remapping
+			.print_location("Error", self.filename, self.line, self.column);
+		eprintln!("Error: \"{}\"\n", message.into());
 		self.errored = true;
 	}
 
@@ -396,6 +457,7 @@ impl<'a> CodeInfo<'a> {
 }
 
 #[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 enum SymbolType {
 	Just(TokenType),
 	Function(fn(&mut CodeInfo)),
@@ -419,7 +481,8 @@ impl fmt::Debug for SymbolType {
 enum KeywordType {
 	Just(TokenType),
 	Lua(TokenType),
-	Error(&'static str),
+	// a keyword used by a future version of Clue, which is only scanned with `--forward-compat`
+	Future(TokenType, &'static str),
 	Reserved(&'static str),
 }
 
@@ -471,6 +534,7 @@ const SYMBOLS: SymbolsMap = generate_map(&[
 		),
 	),
 	('#', SymbolType::Just(HASHTAG)),
+	('@', SymbolType::Just(AT)),
 	(
 		'/',
 		SymbolType::Symbols(
@@ -571,10 +635,13 @@ const SYMBOLS: SymbolsMap = generate_map(&[
 	(
 		'|',
 		SymbolType::Symbols(
-			generate_map(&[(
-				'|',
-				SymbolType::Symbols(generate_map(&[('=', SymbolType::Just(DEFINE_OR))]), OR),
-			)]),
+			generate_map(&[
+				(
+					'|',
+					SymbolType::Symbols(generate_map(&[('=', SymbolType::Just(DEFINE_OR))]), OR),
+				),
+				('>', SymbolType::Just(PIPE)),
+			]),
 			BIT_OR,
 		),
 	),
@@ -583,11 +650,32 @@ const SYMBOLS: SymbolsMap = generate_map(&[
 	('`', SymbolType::Function(|i| i.read_raw_string())),
 ]);
 
-static KEYWORDS: phf::Map<&'static [u8], KeywordType> = phf_map! {
+// looks the keywords up with a perfect hash map if the `phf` feature is enabled, or with a match otherwise
+macro_rules! keywords {
+	($($keyword:literal => $kind:expr),* $(,)?) => {
+		#[cfg(feature = "phf")]
+		fn get_keyword(ident: &[u8]) -> Option<&'static KeywordType> {
+			static KEYWORDS: phf::Map<&'static [u8], KeywordType> = phf::phf_map! {
+				$($keyword => $kind,)*
+			};
+			KEYWORDS.get(ident)
+		}
+
+		#[cfg(not(feature = "phf"))]
+		fn get_keyword(ident: &[u8]) -> Option<&'static KeywordType> {
+			match ident {
+				$($keyword => Some(&$kind),)*
+				_ => None,
+			}
+		}
+	};
+}
+
+keywords! {
 	b"and" => KeywordType::Reserved("'and' operators in Clue are made with '&&'"),
 	b"not" => KeywordType::Reserved("'not' operators in Clue are made with '!'"),
 	b"or" => KeywordType::Reserved("'or' operators in Clue are made with '||'"),
-	b"do" => KeywordType::Reserved("'do ... end' blocks in Clue are made like this: '{ ... }'"),
+	b"do" => KeywordType::Lua(DO),
 	b"end" => KeywordType::Reserved("code blocks in Clue are closed with '}'"),
 	b"function" => KeywordType::Reserved("functions in Clue are defined with the 'fn' keyword"),
 	b"repeat" => KeywordType::Reserved(
@@ -608,6 +696,7 @@ static KEYWORDS: phf::Map<&'static [u8], KeywordType> = phf_map! {
 	b"false" => KeywordType::Lua(FALSE),
 	b"nil" => KeywordType::Lua(NIL),
 	b"break" => KeywordType::Lua(BREAK),
+	b"goto" => KeywordType::Just(GOTO),
 	b"of" => KeywordType::Just(OF),
 	b"with" => KeywordType::Just(WITH),
 	b"meta" => KeywordType::Just(META),
@@ -620,12 +709,14 @@ static KEYWORDS: phf::Map<&'static [u8], KeywordType> = phf_map! {
 	b"continue" => KeywordType::Just(CONTINUE),
 	b"try" => KeywordType::Just(TRY),
 	b"catch" => KeywordType::Just(CATCH),
+	b"finally" => KeywordType::Just(FINALLY),
+	b"throw" => KeywordType::Just(THROW),
 	b"match" => KeywordType::Just(MATCH),
 	b"default" => KeywordType::Just(DEFAULT),
-	b"constructor" => KeywordType::Error("'constructor' is reserved for Clue 4.0 and cannnot be used."),
-	b"struct" => KeywordType::Error("'struct' is reserved for Clue 4.0 and cannot be used"),
-	b"extern" =>KeywordType::Error("'extern' is reserved for Clue 4.0 and cannot be used"),
-};
+	b"constructor" => KeywordType::Just(CONSTRUCTOR),
+	b"struct" => KeywordType::Just(STRUCT),
+	b"extern" => KeywordType::Future(EXTERN, "4.0"),
+}
 
 /// Scans the code and returns a [`Vec`] of [`Token`]s
 /// It takes a preprocessed code and a filename as arguments
@@ -635,28 +726,45 @@ static KEYWORDS: phf::Map<&'static [u8], KeywordType> = phf_map! {
 ///
 /// # Examples
 /// ```
-/// use clue_core::{env::Options, preprocessor::*, scanner::*};
+/// use clue_frontend::{env::Options, preprocessor::*, scanner::*};
 ///
 /// fn main() -> Result<(), String> {
 ///     let options = Options::default();
 ///     let filename = String::from("fizzbuzz.clue");
 ///     let mut code = include_str!("../../examples/fizzbuzz.clue").to_owned();
 ///
-///     let (codes, variables, ..) = preprocess_code(
+///     let (codes, variables, .., mut remapping) = preprocess_code(
 ///         unsafe { code.as_bytes_mut() },
 ///         1,
 ///         false,
 ///         &filename,
 ///         &options,
 ///     )?;
-///     let codes = preprocess_codes(0, codes, &variables, &filename)?;
+///     let codes = preprocess_codes(0, codes, &variables, &mut remapping, &filename)?;
 ///     let tokens = scan_code(codes, &filename)?;
 ///
 ///     Ok(())
 /// }
 /// ```
 pub fn scan_code(code: Code, filename: &String) -> Result<Vec<Token>, String> {
-	let mut i: CodeInfo = CodeInfo::new(code, filename);
+	scan_code_with_options(code, filename, &Remapping::default(), &Options::default())
+}
+
+/// Like [`scan_code`], but takes the [`Remapping`] of the code and the [`Options`] too
+/// The errors in the code that the preprocessor included or expanded are reported where it was written.
+/// When [`Options::env_forward_compat`] is enabled, the keywords reserved for future versions of Clue
+/// (like `extern`) are scanned as their own [`TokenType`] instead of being an error,
+/// so that the parser can report which version of Clue the code needs
+///
+/// # Errors
+/// If the code is invalid, it will return an [`Err`] with the error message
+pub fn scan_code_with_options(
+	code: Code,
+	filename: &String,
+	remapping: &Remapping,
+	options: &Options,
+) -> Result<Vec<Token>, String> {
+	let mut i: CodeInfo = CodeInfo::new(code, filename, remapping, options.env_forward_compat);
 	while !i.ended() && i.peek(0) != '\0' {
 		i.start = i.current;
 		i.update_column();
@@ -688,7 +796,7 @@ pub fn scan_code(code: Code, filename: &String) -> Result<Vec<Token>, String> {
 				}
 			} else if c.is_ascii_alphabetic() || c == '_' {
 				let ident = i.read_identifier();
-				let kind = if let Some(keyword) = KEYWORDS.get(ident.as_bytes()) {
+				let kind = if let Some(keyword) = get_keyword(ident.as_bytes()) {
 					match keyword {
 						KeywordType::Lua(kind) => *kind,
 						KeywordType::Reserved(e) => i.reserved(&ident, e),
@@ -700,8 +808,15 @@ pub fn scan_code(code: Code, filename: &String) -> Result<Vec<Token>, String> {
 							IDENTIFIER
 						}
 						KeywordType::Just(kind) => *kind,
-						KeywordType::Error(e) => {
-							i.warning(*e);
+						KeywordType::Future(kind, _) if i.forward_compat => *kind,
+						KeywordType::Future(_, version) => {
+							i.warning(format_clue!(
+								"'",
+								ident,
+								"' is reserved for Clue ",
+								*version,
+								" and cannot be used"
+							));
 							IDENTIFIER
 						}
 					}
@@ -723,6 +838,40 @@ pub fn scan_code(code: Code, filename: &String) -> Result<Vec<Token>, String> {
 	Ok(i.tokens)
 }
 
+// returns the version of Clue which uses the given keyword, if it's reserved for a future version
+pub(crate) fn future_keyword_version(keyword: &str) -> Option<&'static str> {
+	match get_keyword(keyword.as_bytes()) {
+		Some(KeywordType::Future(_, version)) => Some(version),
+		_ => None,
+	}
+}
+
+/// Checks whether two lists of [`Token`]s are the same ignoring where each token is located,
+/// meaning the code they come from only differs in comments and whitespace
+/// Code compiled with debug information should still be recompiled, since it contains the lines of the tokens
+///
+/// # Examples
+/// ```
+/// use clue_frontend::{env::Options, scan_source, scanner::same_tokens};
+///
+/// fn main() -> Result<(), String> {
+///     let filename = String::from("(library)");
+///     let options = Options::default();
+///     let old = scan_source("local a = 1".to_owned(), &filename, &options)?;
+///     let new = scan_source("// the answer\nlocal a =\n\t1".to_owned(), &filename, &options)?;
+///     assert!(same_tokens(&old, &new));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn same_tokens(old: &[Token], new: &[Token]) -> bool {
+	old.len() == new.len()
+		&& old
+			.iter()
+			.zip(new)
+			.all(|(old, new)| old.kind == new.kind && old.lexeme == new.lexeme)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::TokenType::*;
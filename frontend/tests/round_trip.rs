@@ -0,0 +1,409 @@
+//! Property based tests that generate random valid Clue programs and check that
+//! the way they are laid out does not change how they are scanned and parsed.
+//!
+//! Clue has no formatter yet, so the programs are printed by the generator itself,
+//! once on a single line and once spread across many lines with indentation.
+//! New syntax should be added to the generator, so that it is checked too.
+
+use clue_frontend::{
+	env::Options,
+	parse_source,
+	parser::{
+		visitor::{walk_code_block_mut, walk_ctoken_mut, walk_function_args_mut, Transformer},
+		CodeBlock, ComplexToken,
+		ComplexToken::*,
+		Expression, FunctionArgs, Span,
+	},
+	scan_source,
+	scanner::TokenType,
+};
+use proptest::prelude::*;
+
+#[derive(Clone, Debug)]
+enum Expr {
+	Number(u16),
+	Str(String),
+	Name(&'static str),
+	Unary(&'static str, Box<Expr>),
+	Binary(Box<Expr>, &'static str, Box<Expr>),
+	Call(&'static str, Vec<Expr>),
+	Table(Vec<(Option<&'static str>, Expr)>),
+	Lambda(Vec<&'static str>, Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+enum Stmt {
+	Local(&'static str, Expr),
+	Assign(&'static str, &'static str, Expr),
+	Call(&'static str, Vec<Expr>),
+	If(Expr, Vec<Stmt>, Vec<Stmt>),
+	While(Expr, Vec<Stmt>),
+	For(&'static str, Expr, Expr, Vec<Stmt>),
+	Function(&'static str, Vec<&'static str>, Vec<Stmt>),
+}
+
+const NAMES: &[&str] = &["a", "b", "value", "count", "list"];
+const FUNCTIONS: &[&str] = &["print", "f", "tostring"];
+const UNARY: &[&str] = &["-", "!", "#"];
+const BINARY: &[&str] = &[
+	"+", "-", "*", "/", "%", "^", "..", "==", "!=", "<", "<=", ">", ">=", "&&", "||",
+];
+const ASSIGNMENTS: &[&str] = &["=", "+=", "-=", "*=", "..="];
+
+fn expr() -> impl Strategy<Value = Expr> {
+	let leaf = prop_oneof![
+		any::<u16>().prop_map(Expr::Number),
+		"[a-z ]{0,8}".prop_map(Expr::Str),
+		prop::sample::select(NAMES).prop_map(Expr::Name),
+	];
+	leaf.prop_recursive(4, 32, 4, |inner| {
+		prop_oneof![
+			(prop::sample::select(UNARY), inner.clone())
+				.prop_map(|(op, value)| Expr::Unary(op, Box::new(value))),
+			(inner.clone(), prop::sample::select(BINARY), inner.clone())
+				.prop_map(|(left, op, right)| Expr::Binary(Box::new(left), op, Box::new(right))),
+			(
+				prop::sample::select(FUNCTIONS),
+				prop::collection::vec(inner.clone(), 0..3)
+			)
+				.prop_map(|(name, args)| Expr::Call(name, args)),
+			prop::collection::vec(
+				(prop::option::of(prop::sample::select(NAMES)), inner.clone()),
+				0..3
+			)
+			.prop_map(Expr::Table),
+			(prop::sample::subsequence(NAMES, 0..3), inner)
+				.prop_map(|(args, value)| Expr::Lambda(args, Box::new(value))),
+		]
+	})
+}
+
+fn block(inner: impl Strategy<Value = Stmt>) -> impl Strategy<Value = Vec<Stmt>> {
+	prop::collection::vec(inner, 0..3)
+}
+
+fn stmt() -> impl Strategy<Value = Stmt> {
+	let name = || prop::sample::select(NAMES);
+	let leaf = prop_oneof![
+		(name(), expr()).prop_map(|(name, value)| Stmt::Local(name, value)),
+		(name(), prop::sample::select(ASSIGNMENTS), expr())
+			.prop_map(|(name, op, value)| Stmt::Assign(name, op, value)),
+		(
+			prop::sample::select(FUNCTIONS),
+			prop::collection::vec(expr(), 0..3)
+		)
+			.prop_map(|(name, args)| Stmt::Call(name, args)),
+	];
+	leaf.prop_recursive(3, 24, 3, move |inner| {
+		prop_oneof![
+			(expr(), block(inner.clone()), block(inner.clone()))
+				.prop_map(|(condition, code, otherwise)| Stmt::If(condition, code, otherwise)),
+			(expr(), block(inner.clone()))
+				.prop_map(|(condition, code)| Stmt::While(condition, code)),
+			(name(), expr(), expr(), block(inner.clone()))
+				.prop_map(|(name, start, end, code)| Stmt::For(name, start, end, code)),
+			(
+				prop::sample::select(FUNCTIONS),
+				prop::sample::subsequence(NAMES, 0..3),
+				block(inner)
+			)
+				.prop_map(|(name, args, code)| Stmt::Function(name, args, code)),
+		]
+	})
+}
+
+/// Prints the generated code, on a single line if `spread` is false
+/// or with every statement, argument and table entry on its own line if it's true.
+/// Lambdas are printed as `fn(...) => value` if `short_lambdas` is true.
+struct Printer {
+	spread: bool,
+	short_lambdas: bool,
+	indent: usize,
+	output: String,
+}
+
+impl Printer {
+	fn print(stmts: &[Stmt], spread: bool, short_lambdas: bool) -> String {
+		let mut printer = Printer {
+			spread,
+			short_lambdas,
+			indent: 0,
+			output: String::new(),
+		};
+		printer.stmts(stmts);
+		printer.output
+	}
+
+	fn newline(&mut self) {
+		if self.spread {
+			self.output.push('\n');
+			self.output += &"\t".repeat(self.indent);
+		} else {
+			self.output.push(' ');
+		}
+	}
+
+	fn list<T>(
+		&mut self,
+		items: &[T],
+		open: &str,
+		close: &str,
+		mut item: impl FnMut(&mut Self, &T),
+	) {
+		self.output += open;
+		self.indent += 1;
+		for (i, value) in items.iter().enumerate() {
+			if i > 0 {
+				self.output.push(',');
+			}
+			self.newline();
+			item(self, value);
+		}
+		self.indent -= 1;
+		if !items.is_empty() {
+			self.newline();
+		}
+		self.output += close;
+	}
+
+	fn block(&mut self, stmts: &[Stmt]) {
+		self.output += "{";
+		self.indent += 1;
+		for stmt in stmts {
+			self.newline();
+			self.stmt(stmt);
+		}
+		self.indent -= 1;
+		self.newline();
+		self.output += "}";
+	}
+
+	fn stmts(&mut self, stmts: &[Stmt]) {
+		for (i, stmt) in stmts.iter().enumerate() {
+			if i > 0 {
+				self.newline();
+			}
+			self.stmt(stmt);
+		}
+	}
+
+	fn stmt(&mut self, stmt: &Stmt) {
+		match stmt {
+			Stmt::Local(name, value) => {
+				self.output += &format!("local {name} = ");
+				self.expr(value);
+			}
+			Stmt::Assign(name, op, value) => {
+				self.output += &format!("{name} {op} ");
+				self.expr(value);
+			}
+			Stmt::Call(name, args) => self.call(name, args),
+			Stmt::If(condition, code, otherwise) => {
+				self.output += "if ";
+				self.condition(condition);
+				self.block(code);
+				if !otherwise.is_empty() {
+					self.output += " else ";
+					self.block(otherwise);
+				}
+			}
+			Stmt::While(condition, code) => {
+				self.output += "while ";
+				self.condition(condition);
+				self.block(code);
+			}
+			Stmt::For(name, start, end, code) => {
+				self.output += &format!("for {name} = ");
+				self.expr(start);
+				self.output += ", ";
+				self.condition(end);
+				self.block(code);
+			}
+			Stmt::Function(name, args, code) => {
+				self.output += &format!("local fn {name}");
+				self.list(args, "(", ")", |printer, arg| printer.output += arg);
+				self.output += " ";
+				self.block(code);
+			}
+		}
+	}
+
+	// a table right before a block would be read as the block itself
+	fn condition(&mut self, expr: &Expr) {
+		if let Expr::Table(_) | Expr::Lambda(..) = expr {
+			self.output += "(";
+			self.expr(expr);
+			self.output += ")";
+		} else {
+			self.expr(expr);
+		}
+		self.output += " ";
+	}
+
+	// lambdas can only be used as operands inside parentheses
+	fn operand(&mut self, expr: &Expr) {
+		if let Expr::Lambda(..) = expr {
+			self.output += "(";
+			self.expr(expr);
+			self.output += ")";
+		} else {
+			self.expr(expr);
+		}
+	}
+
+	fn call(&mut self, name: &str, args: &[Expr]) {
+		self.output += name;
+		self.list(args, "(", ")", Self::expr);
+	}
+
+	fn expr(&mut self, expr: &Expr) {
+		match expr {
+			Expr::Number(n) => self.output += &n.to_string(),
+			Expr::Str(s) => self.output += &format!("\"{s}\""),
+			Expr::Name(name) => self.output += name,
+			// `#` only accepts names, tables and parenthesized expressions
+			Expr::Unary("#", value) => {
+				self.output += "(#(";
+				self.expr(value);
+				self.output += "))";
+			}
+			Expr::Unary(op, value) => {
+				self.output += &format!("({op}");
+				self.operand(value);
+				self.output += ")";
+			}
+			Expr::Binary(left, op, right) => {
+				self.output += "(";
+				self.operand(left);
+				self.output += &format!(" {op} ");
+				self.operand(right);
+				self.output += ")";
+			}
+			Expr::Call(name, args) => self.call(name, args),
+			Expr::Table(entries) => self.list(entries, "{", "}", |printer, (key, value)| {
+				if let Some(key) = key {
+					printer.output += &format!("{key} = ");
+				}
+				printer.expr(value);
+			}),
+			Expr::Lambda(args, value) => {
+				self.output += "fn";
+				self.list(args, "(", ")", |printer, arg| printer.output += arg);
+				if self.short_lambdas {
+					self.output += " => ";
+					self.expr(value);
+				} else {
+					self.output += " { return ";
+					self.expr(value);
+					self.output += " }";
+				}
+			}
+		}
+	}
+}
+
+/// Resets every line number and span of the AST, so that ASTs of code laid out differently can be compared.
+struct ForgetLines;
+
+impl Transformer for ForgetLines {
+	fn transform_ctoken(&mut self, ctoken: &mut ComplexToken) {
+		match ctoken {
+			VARIABLE { span, .. }
+			| ALTER { span, .. }
+			| FUNCTION { span, .. }
+			| LAMBDA { span, .. }
+			| IF_STATEMENT { span, .. }
+			| MATCH_BLOCK { span, .. }
+			| WHILE_LOOP { span, .. }
+			| LOOP_UNTIL { span, .. }
+			| FOR_LOOP { span, .. }
+			| FOR_FUNC_LOOP { span, .. }
+			| TRY_CATCH { span, .. }
+			| WITH_BLOCK { span, .. }
+			| IDENT { span, .. }
+			| SYMBOL(_, span)
+			| CALL(_, span)
+			| EXPR(_, span)
+			| DO_BLOCK(_, span)
+			| CONST_BLOCK { span, .. }
+			| RETURN_EXPR(_, span)
+			| CONTINUE_LOOP(_, span)
+			| BREAK_LOOP(_, span)
+			| GOTO_LABEL(_, span)
+			| LABEL(_, span)
+			| UNSUPPORTED { span, .. } => *span = Span::default(),
+			TABLE {
+				values,
+				metas,
+				span,
+				..
+			} => {
+				*span = Span::default();
+				values
+					.iter_mut()
+					.for_each(|(.., span)| *span = Span::default());
+				metas
+					.iter_mut()
+					.for_each(|(.., span)| *span = Span::default());
+			}
+			_ => {}
+		}
+		walk_ctoken_mut(self, ctoken)
+	}
+
+	fn transform_code_block(&mut self, block: &mut CodeBlock) {
+		block.start = 0;
+		block.end = 0;
+		walk_code_block_mut(self, block)
+	}
+
+	fn transform_function_args(&mut self, args: &mut FunctionArgs) {
+		for (_, default) in args.iter_mut() {
+			if let Some((_, line)) = default {
+				*line = 0;
+			}
+		}
+		walk_function_args_mut(self, args)
+	}
+}
+
+fn parse(code: String) -> (Expression, Expression) {
+	let (mut ast, mut statics) = parse_source(code, &String::from("(test)"), &Options::default())
+		.unwrap_or_else(|e| panic!("generated code failed to parse: {e}"));
+	ForgetLines.transform_expression(&mut ast);
+	ForgetLines.transform_expression(&mut statics);
+	(ast, statics)
+}
+
+fn scan(code: String) -> Vec<(TokenType, String)> {
+	scan_source(code, &String::from("(test)"), &Options::default())
+		.unwrap_or_else(|e| panic!("generated code failed to scan: {e}"))
+		.into_iter()
+		.map(|token| (token.kind, token.lexeme))
+		.collect()
+}
+
+proptest! {
+	#[test]
+	fn layout_does_not_change_the_tokens(program in prop::collection::vec(stmt(), 1..6)) {
+		let compact = Printer::print(&program, false, false);
+		let spread = Printer::print(&program, true, false);
+		prop_assert_eq!(scan(compact.clone()), scan(compact.clone()));
+		prop_assert_eq!(scan(compact), scan(spread));
+	}
+
+	#[test]
+	fn layout_does_not_change_the_ast(program in prop::collection::vec(stmt(), 1..6)) {
+		let compact = Printer::print(&program, false, false);
+		let spread = Printer::print(&program, true, false);
+		prop_assert_eq!(parse(compact.clone()), parse(compact.clone()));
+		prop_assert_eq!(parse(compact), parse(spread));
+	}
+
+	#[test]
+	fn short_lambdas_match_full_ones(program in prop::collection::vec(stmt(), 1..6)) {
+		let full = Printer::print(&program, false, false);
+		let short = Printer::print(&program, true, true);
+		prop_assert_eq!(parse(full), parse(short));
+	}
+}
@@ -4,7 +4,10 @@
 
 use clue_core::{
 	code::Code,
-	env::{BitwiseMode, ContinueMode, LuaVersion},
+	env::{
+		BitwiseMode, ContinueMode, EnumMode, Language, LuaVersion, NamedArgsMode, TargetEnv,
+		WrapMode,
+	},
 	parser::Expression,
 	scanner::Token,
 	Clue as ClueCore,
@@ -51,7 +54,7 @@ impl Clue {
 
 	/// Sets the `bitwise_mode` option
 	/// The `bitwise_mode` option is used to set the bitwise mode
-	/// The `bitwise_mode` option can be set to `clue`, `library`, or `vanilla`
+	/// The `bitwise_mode` option can be set to `clue`, `library`, `vanilla`, or `pure`
 	///
 	/// # Errors
 	/// If the `bitwise_mode` option is set to an invalid value then an error will be thrown
@@ -61,6 +64,7 @@ impl Clue {
 			"clue" => BitwiseMode::Clue,
 			"library" => BitwiseMode::Library,
 			"vanilla" => BitwiseMode::Vanilla,
+			"pure" => BitwiseMode::Pure,
 			_ => return Err(format!("Invalid bitwise mode {}", mode)),
 		});
 		Ok(())
@@ -84,6 +88,61 @@ impl Clue {
 		Ok(())
 	}
 
+	/// Sets the `named_args_mode` option
+	/// The `named_args_mode` option is used to set how named arguments are passed to functions
+	/// The `named_args_mode` option can be set to `table` or `positional`
+	///
+	/// # Errors
+	/// If the `named_args_mode` option is set to an invalid value then an error will be thrown
+	#[wasm_bindgen(js_name = "namedArgsMode")]
+	pub fn named_args_mode(&mut self, mode: String) -> Result<(), String> {
+		self.inner.named_args_mode(match mode.as_str() {
+			"table" => NamedArgsMode::Table,
+			"positional" => NamedArgsMode::Positional,
+			_ => return Err(format!("Invalid named arguments mode {}", mode)),
+		});
+		Ok(())
+	}
+
+	/// Sets the `enums` option
+	/// The `enums` option is used to set how named enums are compiled
+	/// The `enums` option can be set to `table`, `reverse` or `inline`
+	///
+	/// # Errors
+	/// If the `enums` option is set to an invalid value then an error will be thrown
+	pub fn enums(&mut self, mode: String) -> Result<(), String> {
+		self.inner.enums(match mode.as_str() {
+			"table" => EnumMode::Table,
+			"reverse" => EnumMode::Reverse,
+			"inline" => EnumMode::Inline,
+			_ => return Err(format!("Invalid enum mode {}", mode)),
+		});
+		Ok(())
+	}
+
+	/// Sets the `annotations` option
+	/// If `annotations` is `true` then then the `annotations` option will be enabled
+	/// If `annotations` is `false` then then the `annotations` option will be disabled
+	/// The `annotations` option puts EmmyLua annotations before the compiled structs and enums
+	pub fn annotations(&mut self, env_annotations: bool) {
+		self.inner.annotations(env_annotations);
+	}
+
+	/// Sets the `language` option
+	/// The `language` option is used to set the language the code is compiled to
+	/// The `language` option can be set to `lua` or `teal`
+	///
+	/// # Errors
+	/// If the `language` option is set to an invalid value then an error will be thrown
+	pub fn language(&mut self, language: String) -> Result<(), String> {
+		self.inner.language(match language.as_str() {
+			"lua" => Language::Lua,
+			"teal" => Language::Teal,
+			_ => return Err(format!("Invalid language {}", language)),
+		});
+		Ok(())
+	}
+
 	/// Sets the `rawsetglobals` option
 	/// If `rawsetglobals` is `true` then then the `rawsetglobals` option will be enabled
 	/// If `rawsetglobals` is `false` then then the `rawsetglobals` option will be disabled
@@ -91,6 +150,41 @@ impl Clue {
 		self.inner.rawsetglobals(env_rawsetglobal);
 	}
 
+	/// Sets the `global_table` option
+	/// The `global_table` option is the table the globals are read from and declared in (Lua 5.2 and later)
+	pub fn global_table(&mut self, env_global_table: Option<String>) {
+		self.inner.global_table(env_global_table);
+	}
+
+	/// Sets the `exports` option
+	/// If `exports` is `true` then the globals declared at the top level of the code are returned in a table
+	/// If `exports` is `false` then the globals are declared in the global table
+	pub fn exports(&mut self, env_exports: bool) {
+		self.inner.exports(env_exports);
+	}
+
+	/// Sets the `wrap` option
+	/// The `wrap` option is used to wrap the output in the boilerplate a framework expects
+	/// The `wrap` option can be set to `none`, `love2d` or `amd`
+	///
+	/// # Errors
+	/// If the `wrap` option is set to an invalid value then an error will be thrown
+	pub fn wrap(&mut self, mode: String) -> Result<(), String> {
+		self.inner.wrap(match mode.as_str() {
+			"none" => WrapMode::None,
+			"love2d" => WrapMode::Love2D,
+			"amd" => WrapMode::AMD,
+			_ => return Err(format!("Invalid wrap mode {}", mode)),
+		});
+		Ok(())
+	}
+
+	/// Sets the `preamble` option
+	/// The `preamble` option is the Lua code put after the static variables
+	pub fn preamble(&mut self, env_preamble: Option<String>) {
+		self.inner.preamble(env_preamble);
+	}
+
 	/// Sets the `debug` option
 	/// If `debug` is `true` then then the `debug` option will be enabled
 	/// If `debug` is `false` then then the `debug` option will be disabled
@@ -99,6 +193,34 @@ impl Clue {
 		self.inner.debug(env_debug);
 	}
 
+	/// Sets the `strict` option
+	/// If `strict` is `true` then then the `strict` option will be enabled
+	/// If `strict` is `false` then then the `strict` option will be disabled
+	/// The `strict` option checks at runtime that the values being indexed are not nil
+	pub fn strict(&mut self, env_strict: bool) {
+		self.inner.strict(env_strict);
+	}
+
+	/// Sets the `coverage` option
+	/// The `coverage` option is the function called with the file and the line before the statements
+	pub fn coverage(&mut self, env_coverage: Option<String>) {
+		self.inner.coverage(env_coverage);
+	}
+
+	/// Sets the `profile` option
+	/// If `profile` is `true` then then the `profile` option will be enabled
+	/// If `profile` is `false` then then the `profile` option will be disabled
+	/// The `profile` option calls the profiler when entering and exiting every function
+	pub fn profile(&mut self, env_profile: bool) {
+		self.inner.profile(env_profile);
+	}
+
+	/// Sets the `profile_hook` option
+	/// The `profile_hook` option is the table used by the `profile` option instead of `clue_profile`
+	pub fn profile_hook(&mut self, env_profile_hook: Option<String>) {
+		self.inner.profile_hook(env_profile_hook);
+	}
+
 	/// Sets the `output` option
 	/// If `output` is `true` then then the `output` option will be enabled
 	/// If `output` is `false` then then the `output` option will be disabled
@@ -107,9 +229,72 @@ impl Clue {
 		self.inner.output(env_output);
 	}
 
+	/// Sets the `forward_compat` option
+	/// If `forward_compat` is `true` then then the `forward_compat` option will be enabled
+	/// If `forward_compat` is `false` then then the `forward_compat` option will be disabled
+	/// When the `forward_compat` option is enabled, code using syntax from a newer version of Clue
+	/// fails with a single error telling which version it needs
+	#[wasm_bindgen(js_name = "forwardCompat")]
+	pub fn forward_compat(&mut self, env_forward_compat: bool) {
+		self.inner.forward_compat(env_forward_compat);
+	}
+
+	/// Sets the `chained_comparisons` option
+	/// If `chained_comparisons` is `true` then then the `chained_comparisons` option will be enabled
+	/// If `chained_comparisons` is `false` then then the `chained_comparisons` option will be disabled
+	/// When the `chained_comparisons` option is enabled, `a < b < c` means `a < b and b < c`
+	/// and `b` is evaluated only once
+	#[wasm_bindgen(js_name = "chainedComparisons")]
+	pub fn chained_comparisons(&mut self, env_chained_comparisons: bool) {
+		self.inner.chained_comparisons(env_chained_comparisons);
+	}
+
+	/// Sets the `match_dispatch` option
+	/// If `match_dispatch` is a number then the match blocks comparing the value with at least
+	/// that many different literals will be compiled to a table of functions indexed by them
+	/// If `match_dispatch` is `undefined` then match blocks will always be compiled to if chains
+	#[wasm_bindgen(js_name = "matchDispatch")]
+	pub fn match_dispatch(&mut self, env_match_dispatch: Option<usize>) {
+		self.inner.match_dispatch(env_match_dispatch);
+	}
+
+	/// Sets the `max_line_length` option
+	/// If `max_line_length` is a number then the lines of the output longer than it will be split
+	/// where it doesn't change the meaning of the code
+	/// If `max_line_length` is `undefined` then the lines will be left as they are
+	#[wasm_bindgen(js_name = "maxLineLength")]
+	pub fn max_line_length(&mut self, env_max_line_length: Option<usize>) {
+		self.inner.max_line_length(env_max_line_length);
+	}
+
+	/// Sets the `indent_spaces` option
+	/// If `indent_spaces` is a number then the output will be indented with that many spaces
+	/// If `indent_spaces` is `undefined` then the output will be indented with tabs
+	#[wasm_bindgen(js_name = "indentSpaces")]
+	pub fn indent_spaces(&mut self, env_indent_spaces: Option<usize>) {
+		self.inner.indent_spaces(env_indent_spaces);
+	}
+
+	/// Sets the `omit_semicolons` option
+	/// If `omit_semicolons` is `true` then the semicolons at the end of the statements will be removed
+	/// unless they are needed
+	/// If `omit_semicolons` is `false` then every statement will end with a semicolon
+	#[wasm_bindgen(js_name = "omitSemicolons")]
+	pub fn omit_semicolons(&mut self, env_omit_semicolons: bool) {
+		self.inner.omit_semicolons(env_omit_semicolons);
+	}
+
+	/// Sets the `max_blank_lines` option
+	/// If `max_blank_lines` is a number then the output will never have more empty lines in a row
+	/// If `max_blank_lines` is `undefined` then the empty lines will be left as they are
+	#[wasm_bindgen(js_name = "maxBlankLines")]
+	pub fn max_blank_lines(&mut self, env_max_blank_lines: Option<usize>) {
+		self.inner.max_blank_lines(env_max_blank_lines);
+	}
+
 	/// Sets the `target` option
 	/// The `target` option is used to set the target Lua version
-	/// The `target` option can be set to `5.1`, `5.2`, `5.3`, `5.4`, `blua`, or `luajit`
+	/// The `target` option can be set to `5.1`, `5.2`, `5.3`, `5.4`, `5.1pure`, `blua`, `luau`, or `luajit`
 	///
 	/// # Errors
 	/// If the `target` option is set to an invalid value then an error will be thrown
@@ -120,6 +305,8 @@ impl Clue {
 			Some("5.3") => Some(LuaVersion::Lua53),
 			Some("5.4") => Some(LuaVersion::Lua54),
 			Some("blua") => Some(LuaVersion::BLUA),
+			Some("luau") => Some(LuaVersion::Luau),
+			Some("5.1pure") => Some(LuaVersion::Lua51Pure),
 			Some("luajit") => Some(LuaVersion::LuaJIT),
 			None => None,
 			Some(version) => return Err(format!("Invalid Lua version {}", version)),
@@ -127,6 +314,23 @@ impl Clue {
 		Ok(())
 	}
 
+	/// Sets the `target_env` option
+	/// The `target_env` option is used to set the program the code runs in, which also sets the target Lua version
+	/// The `target_env` option can be set to `roblox` or `craftos`
+	///
+	/// # Errors
+	/// If the `target_env` option is set to an invalid value then an error will be thrown
+	#[wasm_bindgen(js_name = "targetEnv")]
+	pub fn target_env(&mut self, environment: Option<String>) -> Result<(), String> {
+		self.inner.target_env(match environment.as_deref() {
+			Some("roblox") => Some(TargetEnv::Roblox),
+			Some("craftos") => Some(TargetEnv::CraftOS),
+			None => None,
+			Some(environment) => return Err(format!("Invalid environment {}", environment)),
+		});
+		Ok(())
+	}
+
 	/// Sets the `target_os` option
 	/// The `target_os` option is used to set the target operating system
 	/// The `target_os` option can be set to `windows`, `linux`, `macos`, `ios`, `android`, `freebsd`, `openbsd`, `netbsd`, `dragonfly`, `emscripten`, `wasi`, or `unknown`
@@ -134,6 +338,14 @@ impl Clue {
 	pub fn target_os(&mut self, os: String) {
 		self.inner.target_os(os);
 	}
+
+	/// Sets the `target_arch` option
+	/// The `target_arch` option is used to set the target architecture
+	/// The `target_arch` option can be set to `x86`, `x86_64`, `arm`, `aarch64`, `wasm32`, `riscv64` or any other architecture name used by Rust
+	#[wasm_bindgen(js_name = "targetArch")]
+	pub fn target_arch(&mut self, arch: String) {
+		self.inner.target_arch(arch);
+	}
 }
 
 #[wasm_bindgen]
@@ -208,6 +420,17 @@ impl Clue {
 		serde_wasm_bindgen::to_value(&parsed).map_err(|err| err.to_string())
 	}
 
+	/// Parses the given code without stopping at the first syntax error
+	/// Takes a string of code and returns the partial Expression object, the statics and the list of errors
+	///
+	/// # Errors
+	/// If the code cannot be preprocessed or scanned then an error will be thrown
+	#[wasm_bindgen(js_name = "parseCodeRecovering")]
+	pub fn parse_code_recovering(&self, code: String) -> Result<JsValue, String> {
+		let parsed = self.inner.parse_code_recovering(code)?;
+		serde_wasm_bindgen::to_value(&parsed).map_err(|err| err.to_string())
+	}
+
 	/// Compiles the given preprocessed code
 	/// Takes a Code object and returns a string of Lua code
 	///
@@ -240,7 +463,7 @@ impl Clue {
 	/// If the code is invalid then an error will be thrown
 	#[wasm_bindgen(js_name = "compileAst")]
 	pub fn compile_ast(&self, parse_result: JsValue) -> Result<String, String> {
-		let parse_result: (Expression, String) =
+		let parse_result: (Expression, Expression) =
 			serde_wasm_bindgen::from_value(parse_result).map_err(|err| err.to_string())?;
 
 		self.inner.compile_ast(parse_result)